@@ -3,7 +3,7 @@ use rayon::prelude::*;
 use solana_measure::measure::Measure;
 use solana_runtime::{
     accounts::{create_test_accounts, update_accounts, Accounts},
-    accounts_index::Ancestors,
+    accounts_index::{AccountsIndexConfig, Ancestors},
 };
 use solana_sdk::{genesis_config::ClusterType, pubkey::Pubkey};
 use std::env;
@@ -43,6 +43,19 @@ fn main() {
                 .takes_value(false)
                 .help("Run clean"),
         )
+        .arg(
+            Arg::with_name("disk_index")
+                .long("disk_index")
+                .takes_value(false)
+                .help("Page the accounts index out to disk"),
+        )
+        .arg(
+            Arg::with_name("index_limit_mb")
+                .long("index_limit_mb")
+                .takes_value(true)
+                .value_name("MEGABYTES")
+                .help("Memory limit of the disk-backed accounts index"),
+        )
         .get_matches();
 
     let num_slots = value_t!(matches, "num_slots", usize).unwrap_or(4);
@@ -50,13 +63,24 @@ fn main() {
     let iterations = value_t!(matches, "iterations", usize).unwrap_or(20);
     let clean = matches.is_present("clean");
     println!("clean: {:?}", clean);
+    let disk_index = matches.is_present("disk_index");
+    let index_limit_mb = value_t!(matches, "index_limit_mb", usize).ok();
 
     let path = PathBuf::from(env::var("FARF_DIR").unwrap_or_else(|_| "farf".to_owned()))
         .join("accounts-bench");
     if fs::remove_dir_all(path.clone()).is_err() {
         println!("Warning: Couldn't remove {:?}", path);
     }
-    let accounts = Accounts::new(vec![path], &ClusterType::Testnet);
+    let accounts_index_config = if disk_index {
+        Some(AccountsIndexConfig {
+            drives: Some(vec![path.join("index")]),
+            index_limit_mb,
+        })
+    } else {
+        None
+    };
+    let accounts =
+        Accounts::new_with_config(vec![path], &ClusterType::Testnet, accounts_index_config);
     println!("Creating {} accounts", num_accounts);
     let mut create_time = Measure::start("create accounts");
     let pubkeys: Vec<_> = (0..num_slots)
@@ -0,0 +1,17 @@
+//! Builds the reference `FilePlugin` as a dynamic library loadable by the validator:
+//!
+//! ```sh
+//! $ cargo build --release --example solana_accountsdb_file_plugin
+//! ```
+use solana_accountsdb_plugin::{
+    accountsdb_plugin_interface::AccountsDbPlugin, file_plugin::FilePlugin,
+};
+
+/// # Safety
+///
+/// Called by the validator's `AccountsDbPluginManager`, which takes ownership of the plugin
+#[no_mangle]
+pub unsafe fn _create_plugin() -> *mut dyn AccountsDbPlugin {
+    let plugin: Box<dyn AccountsDbPlugin> = Box::new(FilePlugin::default());
+    Box::into_raw(plugin)
+}
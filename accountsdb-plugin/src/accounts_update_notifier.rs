@@ -0,0 +1,93 @@
+//! The `accounts_update_notifier` module forwards account writes from `AccountsDB` to the
+//! loaded AccountsDb plugins
+
+use {
+    crate::{
+        accountsdb_plugin_interface::ReplicaAccountInfo,
+        accountsdb_plugin_manager::AccountsDbPluginManager,
+    },
+    log::*,
+    solana_runtime::accounts_update_notifier_interface::AccountsUpdateNotifierInterface,
+    solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey},
+    std::sync::{Arc, RwLock},
+};
+
+#[derive(Debug)]
+pub struct AccountsUpdateNotifierImpl {
+    plugin_manager: Arc<RwLock<AccountsDbPluginManager>>,
+}
+
+impl AccountsUpdateNotifierInterface for AccountsUpdateNotifierImpl {
+    fn notify_account_update(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &Account,
+        write_version: u64,
+    ) {
+        self.notify_plugins_of_account_update(slot, pubkey, account, write_version, false);
+    }
+
+    fn notify_account_restore_from_snapshot(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &Account,
+        write_version: u64,
+    ) {
+        self.notify_plugins_of_account_update(slot, pubkey, account, write_version, true);
+    }
+
+    fn notify_end_of_restore_from_snapshot(&self) {
+        let plugin_manager = self.plugin_manager.read().unwrap();
+        for plugin in plugin_manager.plugins.iter() {
+            if let Err(err) = plugin.notify_end_of_startup() {
+                error!(
+                    "Failed to notify the end of restore from snapshot, error: {} to plugin {}",
+                    err,
+                    plugin.name()
+                );
+            }
+        }
+    }
+}
+
+impl AccountsUpdateNotifierImpl {
+    pub fn new(plugin_manager: Arc<RwLock<AccountsDbPluginManager>>) -> Self {
+        Self { plugin_manager }
+    }
+
+    fn notify_plugins_of_account_update(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &Account,
+        write_version: u64,
+        is_startup: bool,
+    ) {
+        let plugin_manager = self.plugin_manager.read().unwrap();
+        if plugin_manager.plugins.is_empty() {
+            return;
+        }
+        let account_info = ReplicaAccountInfo {
+            pubkey,
+            lamports: account.lamports,
+            owner: &account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: &account.data,
+            write_version,
+        };
+        for plugin in plugin_manager.plugins.iter() {
+            if let Err(err) = plugin.update_account(account_info.clone(), slot, is_startup) {
+                error!(
+                    "Failed to update account {} at slot {}, error: {} to plugin {}",
+                    pubkey,
+                    slot,
+                    err,
+                    plugin.name()
+                );
+            }
+        }
+    }
+}
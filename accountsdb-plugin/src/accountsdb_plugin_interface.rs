@@ -0,0 +1,107 @@
+//! The `accountsdb_plugin_interface` module defines the interface an AccountsDb plugin
+//! must implement. A plugin is a dynamic library exporting a `_create_plugin` function:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub unsafe fn _create_plugin() -> *mut dyn AccountsDbPlugin {
+//!     let plugin: Box<dyn AccountsDbPlugin> = Box::new(MyPlugin::default());
+//!     Box::into_raw(plugin)
+//! }
+//! ```
+//!
+//! The plugin must be built with the same compiler and `solana-accountsdb-plugin` version as
+//! the validator loading it.
+
+use {
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{any::Any, error, io},
+    thiserror::Error,
+};
+
+/// The account state handed to a plugin on every update
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicaAccountInfo<'a> {
+    pub pubkey: &'a Pubkey,
+    pub lamports: u64,
+    pub owner: &'a Pubkey,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub data: &'a [u8],
+    pub write_version: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum AccountsDbPluginError {
+    #[error("Error opening config file")]
+    ConfigFileOpenError(#[from] io::Error),
+
+    #[error("Error reading config file: {msg}")]
+    ConfigFileReadError { msg: String },
+
+    #[error("Error updating account: {msg}")]
+    AccountsUpdateError { msg: String },
+
+    #[error("Error updating slot status: {msg}")]
+    SlotStatusUpdateError { msg: String },
+
+    #[error("Plugin-defined custom error: {0}")]
+    Custom(Box<dyn error::Error + Send + Sync>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SlotStatus {
+    /// The bank for the slot has been replayed and frozen
+    Processed,
+
+    /// The slot has been optimistically confirmed by the cluster
+    Confirmed,
+
+    /// The slot has been rooted
+    Rooted,
+}
+
+impl SlotStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlotStatus::Processed => "processed",
+            SlotStatus::Confirmed => "confirmed",
+            SlotStatus::Rooted => "rooted",
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AccountsDbPluginError>;
+
+pub trait AccountsDbPlugin: Any + Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    /// Called once right after the plugin is loaded, with the path of the config file the
+    /// plugin was loaded from. Plugin-specific settings live in the same file.
+    fn on_load(&mut self, _config_file: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called right before the plugin is unloaded
+    fn on_unload(&mut self) {}
+
+    /// Called for every account write. `is_startup` is set while the accounts of a snapshot
+    /// are replayed at startup, in which case only the latest version of each account is sent.
+    fn update_account(
+        &self,
+        account: ReplicaAccountInfo,
+        slot: Slot,
+        is_startup: bool,
+    ) -> Result<()>;
+
+    /// Called once every account of the startup snapshot has been sent
+    fn notify_end_of_startup(&self) -> Result<()>;
+
+    /// Called when a slot transitions to a new status
+    fn update_slot_status(
+        &self,
+        slot: Slot,
+        parent: Option<Slot>,
+        status: SlotStatus,
+    ) -> Result<()>;
+}
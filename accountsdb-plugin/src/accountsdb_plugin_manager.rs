@@ -0,0 +1,84 @@
+//! The `accountsdb_plugin_manager` module owns the loaded AccountsDb plugins and the dynamic
+//! libraries backing them
+
+use {
+    crate::accountsdb_plugin_interface::AccountsDbPlugin,
+    libloading::{Library, Symbol},
+    log::*,
+    std::path::Path,
+};
+
+/// Name of the constructor every plugin library must export
+pub const PLUGIN_CONSTRUCTOR_SYMBOL: &[u8] = b"_create_plugin";
+
+type PluginConstructor = unsafe fn() -> *mut dyn AccountsDbPlugin;
+
+#[derive(Default, Debug)]
+pub struct AccountsDbPluginManager {
+    pub plugins: Vec<Box<dyn AccountsDbPlugin>>,
+    libs: Vec<Library>,
+}
+
+impl AccountsDbPluginManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the plugin from the dynamic library at `libpath` and initialize it with
+    /// `config_file`.
+    ///
+    /// # Safety
+    ///
+    /// The library must export a `_create_plugin` constructor returning a valid
+    /// `AccountsDbPlugin`, built with the same compiler and interface version as this crate.
+    pub unsafe fn load_plugin(&mut self, libpath: &Path, config_file: &str) -> Result<(), String> {
+        let lib = Library::new(libpath)
+            .map_err(|err| format!("Failed to load plugin {:?}: {}", libpath, err))?;
+        let constructor: Symbol<PluginConstructor> =
+            lib.get(PLUGIN_CONSTRUCTOR_SYMBOL).map_err(|err| {
+                format!(
+                    "Plugin {:?} does not export `_create_plugin`: {}",
+                    libpath, err
+                )
+            })?;
+        let mut plugin = Box::from_raw(constructor());
+        // The library has to outlive the plugin it created
+        self.libs.push(lib);
+
+        plugin
+            .on_load(config_file)
+            .map_err(|err| format!("Plugin {} failed to load: {}", plugin.name(), err))?;
+        info!(
+            "Loaded AccountsDb plugin {} from {:?}",
+            plugin.name(),
+            libpath
+        );
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Add an already constructed plugin, e.g. one linked statically into the validator
+    pub fn add_plugin(
+        &mut self,
+        mut plugin: Box<dyn AccountsDbPlugin>,
+        config_file: &str,
+    ) -> Result<(), String> {
+        plugin
+            .on_load(config_file)
+            .map_err(|err| format!("Plugin {} failed to load: {}", plugin.name(), err))?;
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Unload all plugins and their libraries
+    pub fn unload(&mut self) {
+        for mut plugin in self.plugins.drain(..) {
+            info!("Unloading AccountsDb plugin {}", plugin.name());
+            plugin.on_unload();
+        }
+        // Libraries must be dropped after the plugins they created
+        for lib in self.libs.drain(..) {
+            drop(lib);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! The `accountsdb_plugin_service` module loads the AccountsDb plugins listed in the config
+//! files given to the validator and hands out the notifiers that feed them
+
+use {
+    crate::{
+        accounts_update_notifier::AccountsUpdateNotifierImpl,
+        accountsdb_plugin_manager::AccountsDbPluginManager,
+        slot_status_notifier::{SlotStatusNotifier, SlotStatusNotifierImpl},
+    },
+    log::*,
+    solana_runtime::accounts_update_notifier_interface::AccountsUpdateNotifier,
+    std::{
+        fs::File,
+        io::Read,
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock},
+        thread,
+    },
+    thiserror::Error,
+};
+
+#[derive(Error, Debug)]
+pub enum AccountsdbPluginServiceError {
+    #[error("Cannot open the plugin config file")]
+    CannotOpenConfigFile(String),
+
+    #[error("Cannot read the plugin config file")]
+    CannotReadConfigFile(String),
+
+    #[error("The config file is not in a valid Json format")]
+    InvalidConfigFileFormat(String),
+
+    #[error("Plugin library path is not specified in the config file")]
+    LibPathNotSet,
+
+    #[error("Failed to load the plugin: {0}")]
+    PluginLoadError(String),
+}
+
+/// Settings common to all plugins. Plugin-specific settings may be added to the same file and
+/// are read by the plugin itself in `AccountsDbPlugin::on_load`.
+#[derive(Deserialize, Debug)]
+struct AccountsDbPluginConfig {
+    libpath: Option<PathBuf>,
+}
+
+pub struct AccountsDbPluginService {
+    plugin_manager: Arc<RwLock<AccountsDbPluginManager>>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    slot_status_notifier: Option<SlotStatusNotifier>,
+}
+
+impl AccountsDbPluginService {
+    /// Load every plugin listed in `accountsdb_plugin_config_files`. Each config file is a Json
+    /// document whose `libpath` field is the path of the plugin's dynamic library, for example:
+    ///
+    /// ```json
+    /// {
+    ///    "libpath": "/path/to/libsolana_accountsdb_file_plugin.so",
+    ///    "output_file": "/tmp/accounts.jsonl"
+    /// }
+    /// ```
+    pub fn new(
+        accountsdb_plugin_config_files: &[PathBuf],
+    ) -> Result<Self, AccountsdbPluginServiceError> {
+        info!(
+            "Starting AccountsDbPluginService from config files: {:?}",
+            accountsdb_plugin_config_files
+        );
+        let mut plugin_manager = AccountsDbPluginManager::new();
+        for accountsdb_plugin_config_file in accountsdb_plugin_config_files {
+            Self::load_plugin(&mut plugin_manager, accountsdb_plugin_config_file)?;
+        }
+        Ok(Self::new_with_plugin_manager(plugin_manager))
+    }
+
+    /// Wrap an already populated plugin manager
+    pub fn new_with_plugin_manager(plugin_manager: AccountsDbPluginManager) -> Self {
+        let plugin_manager = Arc::new(RwLock::new(plugin_manager));
+        let accounts_update_notifier: AccountsUpdateNotifier =
+            Arc::new(AccountsUpdateNotifierImpl::new(plugin_manager.clone()));
+        let slot_status_notifier: SlotStatusNotifier =
+            Arc::new(SlotStatusNotifierImpl::new(plugin_manager.clone()));
+        Self {
+            plugin_manager,
+            accounts_update_notifier: Some(accounts_update_notifier),
+            slot_status_notifier: Some(slot_status_notifier),
+        }
+    }
+
+    fn load_plugin(
+        plugin_manager: &mut AccountsDbPluginManager,
+        accountsdb_plugin_config_file: &Path,
+    ) -> Result<(), AccountsdbPluginServiceError> {
+        let config = Self::read_config(accountsdb_plugin_config_file)?;
+        let libpath = config
+            .libpath
+            .ok_or(AccountsdbPluginServiceError::LibPathNotSet)?;
+        // A relative library path is resolved against the directory of the config file
+        let libpath = match accountsdb_plugin_config_file.parent() {
+            Some(config_dir) if libpath.is_relative() => config_dir.join(libpath),
+            _ => libpath,
+        };
+        let config_file = accountsdb_plugin_config_file.to_str().ok_or_else(|| {
+            AccountsdbPluginServiceError::CannotOpenConfigFile(format!(
+                "Invalid config file path {:?}",
+                accountsdb_plugin_config_file
+            ))
+        })?;
+
+        unsafe { plugin_manager.load_plugin(&libpath, config_file) }
+            .map_err(AccountsdbPluginServiceError::PluginLoadError)
+    }
+
+    fn read_config(
+        accountsdb_plugin_config_file: &Path,
+    ) -> Result<AccountsDbPluginConfig, AccountsdbPluginServiceError> {
+        let mut file = File::open(accountsdb_plugin_config_file).map_err(|err| {
+            AccountsdbPluginServiceError::CannotOpenConfigFile(format!(
+                "Failed to open the plugin config file {:?}, error: {:?}",
+                accountsdb_plugin_config_file, err
+            ))
+        })?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).map_err(|err| {
+            AccountsdbPluginServiceError::CannotReadConfigFile(format!(
+                "Failed to read the plugin config file {:?}, error: {:?}",
+                accountsdb_plugin_config_file, err
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|err| {
+            AccountsdbPluginServiceError::InvalidConfigFileFormat(format!(
+                "The config file {:?} is not in a valid Json format, error: {:?}",
+                accountsdb_plugin_config_file, err
+            ))
+        })
+    }
+
+    pub fn get_accounts_update_notifier(&self) -> Option<AccountsUpdateNotifier> {
+        self.accounts_update_notifier.clone()
+    }
+
+    pub fn get_slot_status_notifier(&self) -> Option<SlotStatusNotifier> {
+        self.slot_status_notifier.clone()
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.plugin_manager.write().unwrap().unload();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_config(contents: &str) -> tempfile::NamedTempFile {
+        let mut config_file = tempfile::NamedTempFile::new().unwrap();
+        config_file.write_all(contents.as_bytes()).unwrap();
+        config_file
+    }
+
+    #[test]
+    fn test_load_plugin_config_errors() {
+        let config_file = write_config("{\"output_file\": \"/tmp/accounts\"}");
+        assert!(matches!(
+            AccountsDbPluginService::new(&[config_file.path().to_path_buf()]),
+            Err(AccountsdbPluginServiceError::LibPathNotSet)
+        ));
+
+        let config_file = write_config("not json");
+        assert!(matches!(
+            AccountsDbPluginService::new(&[config_file.path().to_path_buf()]),
+            Err(AccountsdbPluginServiceError::InvalidConfigFileFormat(_))
+        ));
+
+        assert!(matches!(
+            AccountsDbPluginService::new(&[PathBuf::from("/nonexistent/plugin.json")]),
+            Err(AccountsdbPluginServiceError::CannotOpenConfigFile(_))
+        ));
+
+        let config_file = write_config("{\"libpath\": \"/nonexistent/libplugin.so\"}");
+        assert!(matches!(
+            AccountsDbPluginService::new(&[config_file.path().to_path_buf()]),
+            Err(AccountsdbPluginServiceError::PluginLoadError(_))
+        ));
+    }
+
+    #[test]
+    fn test_no_plugins() {
+        let service = AccountsDbPluginService::new(&[]).unwrap();
+        assert!(service.get_accounts_update_notifier().is_some());
+        assert!(service.get_slot_status_notifier().is_some());
+        service.join().unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! The `file_plugin` module is a reference AccountsDb plugin that writes every account update
+//! and slot status change as a line of Json to a local file or Unix domain socket.
+//!
+//! It is configured by the following fields of its config file, next to `libpath`:
+//!
+//! * `output_file` - the file to append to, or
+//! * `output_socket` - the path of a listening Unix domain socket to connect to
+//! * `owners` - optional list of base58 owner program ids; when set, only accounts owned by one
+//!   of them are written
+
+use {
+    crate::accountsdb_plugin_interface::{
+        AccountsDbPlugin, AccountsDbPluginError, ReplicaAccountInfo, Result, SlotStatus,
+    },
+    log::*,
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{
+        collections::HashSet,
+        fs::{File, OpenOptions},
+        io::{BufWriter, Read, Write},
+        str::FromStr,
+        sync::Mutex,
+    },
+};
+
+#[derive(Deserialize, Debug)]
+struct FilePluginConfig {
+    output_file: Option<String>,
+    output_socket: Option<String>,
+    owners: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum FilePluginRecord {
+    #[serde(rename_all = "camelCase")]
+    Account {
+        slot: Slot,
+        pubkey: String,
+        owner: String,
+        lamports: u64,
+        executable: bool,
+        rent_epoch: u64,
+        /// base64 encoded account data
+        data: String,
+        write_version: u64,
+        is_startup: bool,
+    },
+    EndOfStartup,
+    #[serde(rename_all = "camelCase")]
+    Slot {
+        slot: Slot,
+        parent: Option<Slot>,
+        status: SlotStatus,
+    },
+}
+
+#[derive(Default)]
+pub struct FilePlugin {
+    writer: Option<Mutex<Box<dyn Write + Send>>>,
+    owners: Option<HashSet<Pubkey>>,
+}
+
+impl std::fmt::Debug for FilePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("FilePlugin")
+            .field("owners", &self.owners)
+            .finish()
+    }
+}
+
+impl FilePlugin {
+    fn write_record(&self, record: &FilePluginRecord) -> Result<()> {
+        let writer = self
+            .writer
+            .as_ref()
+            .ok_or_else(|| AccountsDbPluginError::Custom("plugin not loaded".into()))?;
+        let mut line = serde_json::to_vec(record)
+            .map_err(|err| AccountsDbPluginError::Custom(Box::new(err)))?;
+        line.push(b'\n');
+        let mut writer = writer.lock().unwrap();
+        writer.write_all(&line)?;
+        if let FilePluginRecord::Account { .. } = record {
+            Ok(())
+        } else {
+            // Flush at slot boundaries so consumers see complete slots promptly
+            writer.flush()?;
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    fn connect_socket(path: &str) -> Result<Box<dyn Write + Send>> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Ok(Box::new(BufWriter::new(stream)))
+    }
+
+    #[cfg(not(unix))]
+    fn connect_socket(_path: &str) -> Result<Box<dyn Write + Send>> {
+        Err(AccountsDbPluginError::ConfigFileReadError {
+            msg: "output_socket is only supported on unix".to_string(),
+        })
+    }
+}
+
+impl AccountsDbPlugin for FilePlugin {
+    fn name(&self) -> &'static str {
+        "FilePlugin"
+    }
+
+    fn on_load(&mut self, config_file: &str) -> Result<()> {
+        let mut contents = String::new();
+        File::open(config_file)?.read_to_string(&mut contents)?;
+        let config: FilePluginConfig = serde_json::from_str(&contents).map_err(|err| {
+            AccountsDbPluginError::ConfigFileReadError {
+                msg: format!("{:?}", err),
+            }
+        })?;
+
+        let writer: Box<dyn Write + Send> = match (config.output_file, config.output_socket) {
+            (Some(output_file), None) => Box::new(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_file)?,
+            )),
+            (None, Some(output_socket)) => Self::connect_socket(&output_socket)?,
+            _ => {
+                return Err(AccountsDbPluginError::ConfigFileReadError {
+                    msg: "exactly one of output_file or output_socket must be set".to_string(),
+                })
+            }
+        };
+        self.writer = Some(Mutex::new(writer));
+
+        if let Some(owners) = config.owners {
+            let owners = owners
+                .iter()
+                .map(|owner| {
+                    Pubkey::from_str(owner).map_err(|_| {
+                        AccountsDbPluginError::ConfigFileReadError {
+                            msg: format!("invalid owner: {}", owner),
+                        }
+                    })
+                })
+                .collect::<Result<HashSet<_>>>()?;
+            self.owners = Some(owners);
+        }
+        Ok(())
+    }
+
+    fn on_unload(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            if let Err(err) = writer.lock().unwrap().flush() {
+                warn!("FilePlugin failed to flush on unload: {}", err);
+            }
+        }
+    }
+
+    fn update_account(
+        &self,
+        account: ReplicaAccountInfo,
+        slot: Slot,
+        is_startup: bool,
+    ) -> Result<()> {
+        if let Some(owners) = &self.owners {
+            if !owners.contains(account.owner) {
+                return Ok(());
+            }
+        }
+        self.write_record(&FilePluginRecord::Account {
+            slot,
+            pubkey: account.pubkey.to_string(),
+            owner: account.owner.to_string(),
+            lamports: account.lamports,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: base64::encode(account.data),
+            write_version: account.write_version,
+            is_startup,
+        })
+    }
+
+    fn notify_end_of_startup(&self) -> Result<()> {
+        self.write_record(&FilePluginRecord::EndOfStartup)
+    }
+
+    fn update_slot_status(
+        &self,
+        slot: Slot,
+        parent: Option<Slot>,
+        status: SlotStatus,
+    ) -> Result<()> {
+        self.write_record(&FilePluginRecord::Slot {
+            slot,
+            parent,
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_file_plugin() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let output_file = output_dir.path().join("accounts.jsonl");
+        let config_file = output_dir.path().join("config.json");
+        let owner = Pubkey::new_unique();
+        std::fs::write(
+            &config_file,
+            json!({
+                "libpath": "libsolana_accountsdb_file_plugin.so",
+                "output_file": output_file,
+                "owners": [owner.to_string()],
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut plugin = FilePlugin::default();
+        plugin.on_load(config_file.to_str().unwrap()).unwrap();
+
+        let pubkey = Pubkey::new_unique();
+        let other_owner = Pubkey::new_unique();
+        let mut account = ReplicaAccountInfo {
+            pubkey: &pubkey,
+            lamports: 42,
+            owner: &owner,
+            executable: false,
+            rent_epoch: 1,
+            data: &[1, 2, 3],
+            write_version: 7,
+        };
+        plugin.update_account(account.clone(), 5, true).unwrap();
+        account.owner = &other_owner;
+        plugin.update_account(account, 5, true).unwrap();
+        plugin.notify_end_of_startup().unwrap();
+        plugin
+            .update_slot_status(6, Some(5), SlotStatus::Rooted)
+            .unwrap();
+        plugin.on_unload();
+
+        let records: Vec<Value> = std::fs::read_to_string(&output_file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                json!({
+                    "type": "account",
+                    "slot": 5,
+                    "pubkey": pubkey.to_string(),
+                    "owner": owner.to_string(),
+                    "lamports": 42,
+                    "executable": false,
+                    "rentEpoch": 1,
+                    "data": "AQID",
+                    "writeVersion": 7,
+                    "isStartup": true,
+                }),
+                json!({"type": "endOfStartup"}),
+                json!({"type": "slot", "slot": 6, "parent": 5, "status": "rooted"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_plugin_bad_config() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let config_file = output_dir.path().join("config.json");
+        std::fs::write(&config_file, json!({"libpath": "x"}).to_string()).unwrap();
+        let mut plugin = FilePlugin::default();
+        assert!(plugin.on_load(config_file.to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+pub mod accounts_update_notifier;
+pub mod accountsdb_plugin_interface;
+pub mod accountsdb_plugin_manager;
+pub mod accountsdb_plugin_service;
+pub mod file_plugin;
+pub mod slot_status_notifier;
+
+#[macro_use]
+extern crate serde_derive;
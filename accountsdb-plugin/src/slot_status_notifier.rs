@@ -0,0 +1,62 @@
+//! The `slot_status_notifier` module forwards slot status transitions to the loaded
+//! AccountsDb plugins
+
+use {
+    crate::{
+        accountsdb_plugin_interface::SlotStatus, accountsdb_plugin_manager::AccountsDbPluginManager,
+    },
+    log::*,
+    solana_sdk::clock::Slot,
+    std::sync::{Arc, RwLock},
+};
+
+pub trait SlotStatusNotifierInterface {
+    /// Notified when a slot's bank has been replayed and frozen
+    fn notify_slot_processed(&self, slot: Slot, parent: Option<Slot>);
+
+    /// Notified when a slot is optimistically confirmed
+    fn notify_slot_confirmed(&self, slot: Slot, parent: Option<Slot>);
+
+    /// Notified when a slot is rooted
+    fn notify_slot_rooted(&self, slot: Slot, parent: Option<Slot>);
+}
+
+pub type SlotStatusNotifier = Arc<dyn SlotStatusNotifierInterface + Sync + Send>;
+
+pub struct SlotStatusNotifierImpl {
+    plugin_manager: Arc<RwLock<AccountsDbPluginManager>>,
+}
+
+impl SlotStatusNotifierInterface for SlotStatusNotifierImpl {
+    fn notify_slot_processed(&self, slot: Slot, parent: Option<Slot>) {
+        self.notify_slot_status(slot, parent, SlotStatus::Processed);
+    }
+
+    fn notify_slot_confirmed(&self, slot: Slot, parent: Option<Slot>) {
+        self.notify_slot_status(slot, parent, SlotStatus::Confirmed);
+    }
+
+    fn notify_slot_rooted(&self, slot: Slot, parent: Option<Slot>) {
+        self.notify_slot_status(slot, parent, SlotStatus::Rooted);
+    }
+}
+
+impl SlotStatusNotifierImpl {
+    pub fn new(plugin_manager: Arc<RwLock<AccountsDbPluginManager>>) -> Self {
+        Self { plugin_manager }
+    }
+
+    fn notify_slot_status(&self, slot: Slot, parent: Option<Slot>, slot_status: SlotStatus) {
+        let plugin_manager = self.plugin_manager.read().unwrap();
+        for plugin in plugin_manager.plugins.iter() {
+            if let Err(err) = plugin.update_slot_status(slot, parent, slot_status) {
+                error!(
+                    "Failed to update slot status at slot {}, error: {} to plugin {}",
+                    slot,
+                    err,
+                    plugin.name()
+                );
+            }
+        }
+    }
+}
@@ -20,9 +20,12 @@ use solana_perf::packet::to_packets_chunked;
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::{
     hash::Hash,
+    instruction::AccountMeta,
+    message::Message,
+    pubkey::Pubkey,
     signature::Keypair,
-    signature::Signature,
-    system_transaction,
+    signature::{Signature, Signer},
+    system_instruction, system_transaction,
     timing::{duration_as_us, timestamp},
     transaction::Transaction,
 };
@@ -63,24 +66,55 @@ fn check_txs(
     no_bank
 }
 
+// A transfer that also requires `extra_signers` readonly signatures, and so pays a higher fee
+fn transfer_with_signers(
+    payer_key: &Keypair,
+    to_pubkey: &Pubkey,
+    extra_signers: usize,
+    hash: Hash,
+) -> Transaction {
+    let mut instruction = system_instruction::transfer(&payer_key.pubkey(), to_pubkey, 1);
+    instruction.accounts.extend(
+        (0..extra_signers).map(|_| AccountMeta::new_readonly(solana_sdk::pubkey::new_rand(), true)),
+    );
+    let mut message = Message::new(&[instruction], Some(&payer_key.pubkey()));
+    message.recent_blockhash = hash;
+    Transaction::new_unsigned(message)
+}
+
 fn make_accounts_txs(
     total_num_transactions: usize,
     hash: Hash,
     same_payer: bool,
+    fee_mix: bool,
 ) -> Vec<Transaction> {
     let to_pubkey = solana_sdk::pubkey::new_rand();
     let payer_key = Keypair::new();
-    let dummy = system_transaction::transfer(&payer_key, &to_pubkey, 1, hash);
+    // With a fee mix, transactions cycle through paying for 1 to 4 signatures
+    let dummies: Vec<_> = if fee_mix {
+        (0..4)
+            .map(|extra_signers| transfer_with_signers(&payer_key, &to_pubkey, extra_signers, hash))
+            .collect()
+    } else {
+        vec![system_transaction::transfer(
+            &payer_key, &to_pubkey, 1, hash,
+        )]
+    };
     (0..total_num_transactions)
         .into_par_iter()
-        .map(|_| {
-            let mut new = dummy.clone();
-            let sig: Vec<u8> = (0..64).map(|_| thread_rng().gen()).collect();
+        .map(|i| {
+            let mut new = dummies[i % dummies.len()].clone();
+            let num_signatures = new.message.header.num_required_signatures as usize;
             if !same_payer {
                 new.message.account_keys[0] = solana_sdk::pubkey::new_rand();
             }
-            new.message.account_keys[1] = solana_sdk::pubkey::new_rand();
-            new.signatures = vec![Signature::new(&sig[0..64])];
+            new.message.account_keys[num_signatures] = solana_sdk::pubkey::new_rand();
+            new.signatures = (0..num_signatures)
+                .map(|_| {
+                    let sig: Vec<u8> = (0..64).map(|_| thread_rng().gen()).collect();
+                    Signature::new(&sig[0..64])
+                })
+                .collect();
             new
         })
         .collect()
@@ -147,6 +181,12 @@ fn main() {
                 .takes_value(true)
                 .help("Number of iterations"),
         )
+        .arg(
+            Arg::with_name("priority_scheduler")
+                .long("priority-scheduler")
+                .takes_value(false)
+                .help("Order buffered packets by fee with the priority scheduler"),
+        )
         .get_matches();
 
     let num_threads =
@@ -174,8 +214,13 @@ fn main() {
     info!("threads: {} txs: {}", num_threads, total_num_transactions);
 
     let same_payer = matches.is_present("same_payer");
-    let mut transactions =
-        make_accounts_txs(total_num_transactions, genesis_config.hash(), same_payer);
+    let enable_priority_scheduler = matches.is_present("priority_scheduler");
+    let mut transactions = make_accounts_txs(
+        total_num_transactions,
+        genesis_config.hash(),
+        same_payer,
+        enable_priority_scheduler,
+    );
 
     // fund all the accounts
     transactions.iter().for_each(|tx| {
@@ -225,6 +270,7 @@ fn main() {
             vote_receiver,
             None,
             replay_vote_sender,
+            enable_priority_scheduler,
         );
         poh_recorder.lock().unwrap().set_bank(&bank);
 
@@ -0,0 +1,280 @@
+//! The `quic_client` module sends wire transactions to the QUIC listener of a TPU, falling back
+//! to UDP for TPUs that do not complete the QUIC handshake.
+
+use log::*;
+use solana_sdk::{packet::PACKET_DATA_SIZE, signature::Keypair};
+use solana_streamer::quic::{new_client_config, ClientConfig, Connection, QUIC_PORT_OFFSET};
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How long transactions for a TPU go over UDP after a QUIC handshake with it failed
+pub const QUIC_FALLBACK_DURATION: Duration = Duration::from_secs(60);
+
+const RECV_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Sends transactions over QUIC from a background thread, which keeps one connection per TPU
+pub struct QuicClient {
+    sender: Mutex<Sender<(SocketAddr, Vec<u8>)>>,
+    exit: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl QuicClient {
+    /// Creates a client that authenticates with `identity`
+    pub fn new(identity: &Keypair) -> io::Result<Self> {
+        let config = new_client_config(identity)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+        let (sender, receiver) = channel();
+        let exit = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let exit = exit.clone();
+            Builder::new()
+                .name("solana-quic-client".to_string())
+                .spawn(move || {
+                    QuicClientDriver {
+                        socket,
+                        config,
+                        connections: HashMap::new(),
+                        udp_fallbacks: HashMap::new(),
+                    }
+                    .run(&receiver, &exit)
+                })?
+        };
+        Ok(Self {
+            sender: Mutex::new(sender),
+            exit,
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues `wire_transaction` to be sent to the TPU at `tpu_addr`. The QUIC listener of the
+    /// TPU is at `tpu_addr` plus `QUIC_PORT_OFFSET`
+    pub fn send_wire_transaction(
+        &self,
+        tpu_addr: &SocketAddr,
+        wire_transaction: &[u8],
+    ) -> io::Result<()> {
+        if wire_transaction.len() > PACKET_DATA_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "transaction too large",
+            ));
+        }
+        self.sender
+            .lock()
+            .unwrap()
+            .send((*tpu_addr, wire_transaction.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "quic client exited"))
+    }
+}
+
+impl Drop for QuicClient {
+    fn drop(&mut self) {
+        self.exit.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct QuicClientDriver {
+    socket: UdpSocket,
+    config: Arc<ClientConfig>,
+    // Connections by the TPU address they send transactions to
+    connections: HashMap<SocketAddr, Connection>,
+    // TPU addresses transactions are sent to over UDP, and until when
+    udp_fallbacks: HashMap<SocketAddr, Instant>,
+}
+
+impl QuicClientDriver {
+    fn run(&mut self, receiver: &Receiver<(SocketAddr, Vec<u8>)>, exit: &AtomicBool) {
+        let mut buf = [0u8; 2048];
+        while !exit.load(Ordering::Relaxed) {
+            while let Ok((tpu_addr, wire_transaction)) = receiver.try_recv() {
+                self.send(tpu_addr, wire_transaction);
+            }
+            if let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+                let now = Instant::now();
+                if let Some(connection) = self
+                    .connections
+                    .values_mut()
+                    .find(|connection| connection.remote_addr() == from)
+                {
+                    connection.handle_datagram(&mut buf[..len], now);
+                }
+            }
+            self.drive_connections(Instant::now());
+        }
+        let now = Instant::now();
+        for connection in self.connections.values_mut() {
+            connection.close();
+            connection.transmit(&self.socket, now);
+        }
+    }
+
+    fn send(&mut self, tpu_addr: SocketAddr, wire_transaction: Vec<u8>) {
+        let now = Instant::now();
+        self.udp_fallbacks.retain(|_, until| *until > now);
+        let quic_addr = tpu_addr
+            .port()
+            .checked_add(QUIC_PORT_OFFSET)
+            .map(|port| SocketAddr::new(tpu_addr.ip(), port));
+        let quic_addr = match quic_addr {
+            Some(quic_addr) if !self.udp_fallbacks.contains_key(&tpu_addr) => quic_addr,
+            _ => return self.send_udp(&tpu_addr, &wire_transaction),
+        };
+        let config = &self.config;
+        let connection = self
+            .connections
+            .entry(tpu_addr)
+            .or_insert_with(|| Connection::connect(config, quic_addr, now));
+        if let Err(err) = connection.send_stream(wire_transaction.clone()) {
+            debug!("failed to queue transaction for {}: {}", quic_addr, err);
+            self.send_udp(&tpu_addr, &wire_transaction);
+        }
+    }
+
+    fn send_udp(&self, tpu_addr: &SocketAddr, wire_transaction: &[u8]) {
+        if let Err(err) = self.socket.send_to(wire_transaction, tpu_addr) {
+            warn!("failed to send transaction to {}: {:?}", tpu_addr, err);
+        }
+    }
+
+    fn drive_connections(&mut self, now: Instant) {
+        for connection in self.connections.values_mut() {
+            if connection
+                .poll_timeout()
+                .map_or(false, |timeout| now >= timeout)
+            {
+                connection.handle_timeout(now);
+            }
+            connection.transmit(&self.socket, now);
+        }
+        let closed: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.is_closed())
+            .map(|(tpu_addr, _)| *tpu_addr)
+            .collect();
+        for tpu_addr in closed {
+            let mut connection = self.connections.remove(&tpu_addr).unwrap();
+            // The peer identity is only known once the handshake completed
+            if connection.peer_pubkey().is_none() {
+                info!(
+                    "quic handshake with {} failed, sending transactions over udp",
+                    connection.remote_addr()
+                );
+                self.udp_fallbacks
+                    .insert(tpu_addr, now + QUIC_FALLBACK_DURATION);
+            }
+            for wire_transaction in connection.take_queued_streams() {
+                self.send_udp(&tpu_addr, &wire_transaction);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signer;
+    use solana_streamer::quic::{spawn_server, StakedNodes};
+    use std::sync::RwLock;
+
+    // Binds a TPU socket and a QUIC socket at the TPU port plus QUIC_PORT_OFFSET
+    fn bind_tpu_sockets() -> (UdpSocket, UdpSocket) {
+        loop {
+            let quic_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let quic_port = quic_socket.local_addr().unwrap().port();
+            if let Some(tpu_port) = quic_port.checked_sub(QUIC_PORT_OFFSET) {
+                if let Ok(tpu_socket) = UdpSocket::bind(("127.0.0.1", tpu_port)) {
+                    return (tpu_socket, quic_socket);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quic_client() {
+        solana_logger::setup();
+        let (tpu_socket, quic_socket) = bind_tpu_sockets();
+        let tpu_addr = tpu_socket.local_addr().unwrap();
+        let (packet_sender, packet_receiver) = channel();
+        let exit = Arc::new(AtomicBool::new(false));
+        let server = spawn_server(
+            quic_socket,
+            &Keypair::new(),
+            packet_sender,
+            exit.clone(),
+            Arc::new(RwLock::new(StakedNodes::default())),
+        )
+        .unwrap();
+
+        let client = QuicClient::new(&Keypair::new()).unwrap();
+        for i in 0..10u8 {
+            client.send_wire_transaction(&tpu_addr, &[i; 100]).unwrap();
+        }
+        assert!(client
+            .send_wire_transaction(&tpu_addr, &[0; PACKET_DATA_SIZE + 1])
+            .is_err());
+        let mut received = vec![];
+        while received.len() < 10 {
+            let packets = packet_receiver
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap();
+            received.extend(packets.packets.iter().map(|packet| packet.data[0]));
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+
+        // Nothing went over UDP
+        tpu_socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert!(tpu_socket.recv_from(&mut [0; 2048]).is_err());
+
+        drop(client);
+        exit.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_quic_client_udp_fallback() {
+        solana_logger::setup();
+        // Nothing listens for QUIC, so the handshake times out
+        let (tpu_socket, quic_socket) = bind_tpu_sockets();
+        drop(quic_socket);
+        let tpu_addr = tpu_socket.local_addr().unwrap();
+        let keypair = Keypair::new();
+        let client = QuicClient::new(&keypair).unwrap();
+        client
+            .send_wire_transaction(&tpu_addr, keypair.pubkey().as_ref())
+            .unwrap();
+
+        tpu_socket
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut buf = [0; 2048];
+        let (len, _) = tpu_socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], keypair.pubkey().as_ref());
+
+        // Later transactions go over UDP right away
+        let start = Instant::now();
+        client.send_wire_transaction(&tpu_addr, &[1, 2, 3]).unwrap();
+        let (len, _) = tpu_socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[1, 2, 3]);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -346,6 +346,24 @@ impl RpcClient {
         self.send(RpcRequest::GetConfirmedBlock, json!([slot, encoding]))
     }
 
+    pub fn get_block_by_hash(
+        &self,
+        blockhash: &Hash,
+    ) -> ClientResult<Option<EncodedConfirmedBlock>> {
+        self.get_block_by_hash_with_encoding(blockhash, UiTransactionEncoding::Json)
+    }
+
+    pub fn get_block_by_hash_with_encoding(
+        &self,
+        blockhash: &Hash,
+        encoding: UiTransactionEncoding,
+    ) -> ClientResult<Option<EncodedConfirmedBlock>> {
+        self.send(
+            RpcRequest::GetBlockByHash,
+            json!([blockhash.to_string(), encoding]),
+        )
+    }
+
     pub fn get_confirmed_blocks(
         &self,
         start_slot: Slot,
@@ -571,6 +589,8 @@ impl RpcClient {
             encoding: Some(UiAccountEncoding::Base64),
             commitment: Some(commitment_config),
             data_slice: None,
+            slot: None,
+            min_context_slot: None,
         };
         let response = self.sender.send(
             RpcRequest::GetAccountInfo,
@@ -618,6 +638,8 @@ impl RpcClient {
             encoding: Some(UiAccountEncoding::Base64),
             commitment: Some(commitment_config),
             data_slice: None,
+            slot: None,
+            min_context_slot: None,
         };
         let pubkeys: Vec<_> = pubkeys.iter().map(|pubkey| pubkey.to_string()).collect();
         let response = self.send(RpcRequest::GetMultipleAccounts, json!([pubkeys, config]))?;
@@ -793,6 +815,23 @@ impl RpcClient {
         })
     }
 
+    pub fn is_blockhash_valid(&self, blockhash: &Hash) -> ClientResult<bool> {
+        Ok(self
+            .is_blockhash_valid_with_commitment(blockhash, CommitmentConfig::default())?
+            .value)
+    }
+
+    pub fn is_blockhash_valid_with_commitment(
+        &self,
+        blockhash: &Hash,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<bool> {
+        self.send(
+            RpcRequest::IsBlockhashValid,
+            json!([blockhash.to_string(), commitment_config]),
+        )
+    }
+
     pub fn get_fee_rate_governor(&self) -> RpcResult<FeeRateGovernor> {
         let Response {
             context,
@@ -862,6 +901,8 @@ impl RpcClient {
             encoding: Some(UiAccountEncoding::JsonParsed),
             commitment: Some(commitment_config),
             data_slice: None,
+            slot: None,
+            min_context_slot: None,
         };
         let response = self.sender.send(
             RpcRequest::GetAccountInfo,
@@ -956,6 +997,8 @@ impl RpcClient {
             encoding: Some(UiAccountEncoding::JsonParsed),
             commitment: Some(commitment_config),
             data_slice: None,
+            slot: None,
+            min_context_slot: None,
         };
 
         self.send(
@@ -995,6 +1038,8 @@ impl RpcClient {
             encoding: Some(UiAccountEncoding::JsonParsed),
             commitment: Some(commitment_config),
             data_slice: None,
+            slot: None,
+            min_context_slot: None,
         };
 
         self.send(
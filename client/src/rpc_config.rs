@@ -1,7 +1,7 @@
 use crate::rpc_filter::RpcFilterType;
 use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_sdk::{
-    clock::Epoch,
+    clock::{Epoch, Slot},
     commitment_config::{CommitmentConfig, CommitmentLevel},
 };
 use solana_transaction_status::UiTransactionEncoding;
@@ -59,6 +59,12 @@ pub struct RpcStakeConfig {
 pub struct RpcAccountInfoConfig {
     pub encoding: Option<UiAccountEncoding>,
     pub data_slice: Option<UiDataSliceConfig>,
+    /// Return the account state as of this slot rather than at the requested commitment. Only
+    /// honored by getAccountInfo and getMultipleAccounts
+    pub slot: Option<Slot>,
+    /// Fail the request unless it is evaluated at this slot or a later one. Only honored by
+    /// getAccountInfo and getMultipleAccounts
+    pub min_context_slot: Option<Slot>,
     #[serde(flatten)]
     pub commitment: Option<CommitmentConfig>,
 }
@@ -10,6 +10,8 @@ pub const JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE: i64
 pub const JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE: i64 = -32004;
 pub const JSON_RPC_SERVER_ERROR_NODE_UNHEALTHLY: i64 = -32005;
 pub const JSON_RPC_SERVER_ERROR_TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE: i64 = -32006;
+pub const JSON_RPC_SERVER_ERROR_ACCOUNT_STATE_NOT_AVAILABLE: i64 = -32007;
+pub const JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32008;
 
 pub enum RpcCustomError {
     BlockCleanedUp {
@@ -26,6 +28,12 @@ pub enum RpcCustomError {
     },
     RpcNodeUnhealthy,
     TransactionPrecompileVerificationFailure(solana_sdk::transaction::TransactionError),
+    AccountStateNotAvailable {
+        slot: Slot,
+    },
+    MinContextSlotNotReached {
+        context_slot: Slot,
+    },
 }
 
 impl From<RpcCustomError> for Error {
@@ -73,6 +81,16 @@ impl From<RpcCustomError> for Error {
                 message: format!("Transaction precompile verification failure {:?}", e),
                 data: None,
             },
+            RpcCustomError::AccountStateNotAvailable { slot } => Self {
+                code: ErrorCode::ServerError(JSON_RPC_SERVER_ERROR_ACCOUNT_STATE_NOT_AVAILABLE),
+                message: format!("Account state not available for slot {}", slot),
+                data: None,
+            },
+            RpcCustomError::MinContextSlotNotReached { context_slot } => Self {
+                code: ErrorCode::ServerError(JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED),
+                message: "Minimum context slot has not been reached".to_string(),
+                data: Some(serde_json::json!({ "contextSlot": context_slot })),
+            },
         }
     }
 }
@@ -3,7 +3,7 @@
 //! messages to the network directly. The binary encoding of its messages are
 //! unstable and may change in future releases.
 
-use crate::{rpc_client::RpcClient, rpc_response::Response};
+use crate::{quic_client::QuicClient, rpc_client::RpcClient, rpc_response::Response};
 use bincode::{serialize_into, serialized_size};
 use log::*;
 use solana_sdk::{
@@ -120,6 +120,7 @@ pub struct ThinClient {
     tpu_addrs: Vec<SocketAddr>,
     rpc_clients: Vec<RpcClient>,
     optimizer: ClientOptimizer,
+    quic_client: Option<QuicClient>,
 }
 
 impl ThinClient {
@@ -143,6 +144,20 @@ impl ThinClient {
         Self::new_from_client(tpu_addr, transactions_socket, rpc_client)
     }
 
+    /// Create a new ThinClient that sends transactions to the Tpu at `tpu_addr` with
+    /// `quic_client`, which falls back to UDP if the QUIC handshake fails.
+    pub fn new_with_quic_client(
+        rpc_addr: SocketAddr,
+        tpu_addr: SocketAddr,
+        transactions_socket: UdpSocket,
+        quic_client: QuicClient,
+    ) -> Self {
+        Self {
+            quic_client: Some(quic_client),
+            ..Self::new(rpc_addr, tpu_addr, transactions_socket)
+        }
+    }
+
     fn new_from_client(
         tpu_addr: SocketAddr,
         transactions_socket: UdpSocket,
@@ -153,6 +168,7 @@ impl ThinClient {
             tpu_addrs: vec![tpu_addr],
             rpc_clients: vec![rpc_client],
             optimizer: ClientOptimizer::new(0),
+            quic_client: None,
         }
     }
 
@@ -171,6 +187,7 @@ impl ThinClient {
             transactions_socket,
             rpc_clients,
             optimizer,
+            quic_client: None,
         }
     }
 
@@ -182,6 +199,18 @@ impl ThinClient {
         &self.rpc_clients[self.optimizer.best()]
     }
 
+    fn send_wire_transaction(&self, wire_transaction: &[u8]) -> io::Result<()> {
+        match &self.quic_client {
+            Some(quic_client) => {
+                quic_client.send_wire_transaction(self.tpu_addr(), wire_transaction)
+            }
+            None => self
+                .transactions_socket
+                .send_to(wire_transaction, self.tpu_addr())
+                .map(|_| ()),
+        }
+    }
+
     /// Retry a sending a signed Transaction to the server for processing.
     pub fn retry_transfer_until_confirmed(
         &self,
@@ -223,8 +252,7 @@ impl ThinClient {
             while now.elapsed().as_secs() < wait_time as u64 {
                 if num_confirmed == 0 {
                     // Send the transaction if there has been no confirmation (e.g. the first time)
-                    self.transactions_socket
-                        .send_to(&buf[..], &self.tpu_addr())?;
+                    self.send_wire_transaction(&buf)?;
                 }
 
                 if let Ok(confirmed_blocks) = self.poll_for_signature_confirmation(
@@ -555,8 +583,7 @@ impl AsyncClient for ThinClient {
         serialize_into(&mut wr, &transaction)
             .expect("serialize Transaction in pub fn transfer_signed");
         assert!(buf.len() < PACKET_DATA_SIZE);
-        self.transactions_socket
-            .send_to(&buf[..], &self.tpu_addr())?;
+        self.send_wire_transaction(&buf)?;
         Ok(transaction.signatures[0])
     }
     fn async_send_message<T: Signers>(
@@ -17,6 +17,7 @@ use solana_ledger::{blockstore::Blockstore, get_tmp_ledger_path};
 use solana_perf::packet::to_packets_chunked;
 use solana_perf::test_tx::test_tx;
 use solana_runtime::bank::Bank;
+use solana_runtime::cost_tracker::CostTracker;
 use solana_sdk::genesis_config::GenesisConfig;
 use solana_sdk::hash::Hash;
 use solana_sdk::message::Message;
@@ -30,7 +31,7 @@ use solana_sdk::timing::{duration_as_us, timestamp};
 use solana_sdk::transaction::Transaction;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::Receiver;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use test::Bencher;
 
@@ -74,6 +75,7 @@ fn bench_consume_buffered(bencher: &mut Bencher) {
             packets.push((batch, vec![0usize; batch_len]));
         }
         let (s, _r) = unbounded();
+        let cost_tracker = Arc::new(Mutex::new(CostTracker::default()));
         // This tests the performance of buffering packets.
         // If the packet buffers are copied, performance will be poor.
         bencher.iter(move || {
@@ -84,6 +86,7 @@ fn bench_consume_buffered(bencher: &mut Bencher) {
                 10_000,
                 None,
                 &s,
+                &cost_tracker,
             );
         });
 
@@ -201,6 +204,7 @@ fn bench_banking(bencher: &mut Bencher, tx_type: TransactionType) {
             vote_receiver,
             None,
             s,
+            false,
         );
         poh_recorder.lock().unwrap().set_bank(&bank);
 
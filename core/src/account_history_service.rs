@@ -0,0 +1,113 @@
+//! The `account_history_service` archives the accounts modified in each rooted slot to the
+//! blockstore, so that RPC can serve account states at slots no longer held in `BankForks`.
+//!
+//! ReplayStage only hands banks over; the modified accounts of a bank are collected here as soon
+//! as it is frozen, while its storage is still in place, and written once the slot is rooted.
+//! Frozen banks that never become roots are dropped once the root passes them.
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use solana_ledger::blockstore::Blockstore;
+use solana_measure::measure::Measure;
+use solana_runtime::bank::Bank;
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub enum AccountHistoryNotification {
+    /// A bank that finished replay
+    Frozen(Arc<Bank>),
+    /// The banks newly rooted, in slot order
+    Root(Vec<Arc<Bank>>),
+}
+
+pub type AccountHistoryReceiver = Receiver<AccountHistoryNotification>;
+pub type AccountHistorySender = Sender<AccountHistoryNotification>;
+
+pub struct AccountHistoryService {
+    thread_hdl: JoinHandle<()>,
+}
+
+const ACCOUNT_HISTORY_WARNING_MS: u64 = 150;
+
+impl AccountHistoryService {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        account_history_receiver: AccountHistoryReceiver,
+        blockstore: Arc<Blockstore>,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        let exit = exit.clone();
+        let thread_hdl = Builder::new()
+            .name("solana-account-history".to_string())
+            .spawn(move || {
+                // Modified accounts of the frozen banks not rooted yet
+                let mut pending_accounts: HashMap<Slot, Vec<(Pubkey, Account)>> = HashMap::new();
+                loop {
+                    if exit.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let recv_result = account_history_receiver.recv_timeout(Duration::from_secs(1));
+                    match recv_result {
+                        Err(RecvTimeoutError::Disconnected) => {
+                            break;
+                        }
+                        Ok(AccountHistoryNotification::Frozen(bank)) => {
+                            pending_accounts
+                                .insert(bank.slot(), bank.get_all_accounts_modified_since_parent());
+                        }
+                        Ok(AccountHistoryNotification::Root(rooted_banks)) => {
+                            Self::write_rooted_banks(
+                                &blockstore,
+                                &rooted_banks,
+                                &mut pending_accounts,
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+            })
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    fn write_rooted_banks(
+        blockstore: &Blockstore,
+        rooted_banks: &[Arc<Bank>],
+        pending_accounts: &mut HashMap<Slot, Vec<(Pubkey, Account)>>,
+    ) {
+        for bank in rooted_banks {
+            let slot = bank.slot();
+            // Banks frozen before this service started, e.g. while the ledger was processed at
+            // startup, are only seen here
+            let accounts = pending_accounts
+                .remove(&slot)
+                .unwrap_or_else(|| bank.get_all_accounts_modified_since_parent());
+            let mut account_history_timer = Measure::start("account_history_timer");
+            if let Err(e) = blockstore.write_account_history(slot, &accounts) {
+                error!("write_account_history failed: slot {:?} {:?}", slot, e);
+            }
+            account_history_timer.stop();
+            if account_history_timer.as_ms() > ACCOUNT_HISTORY_WARNING_MS {
+                warn!(
+                    "write_account_history of {} accounts took: {}ms",
+                    accounts.len(),
+                    account_history_timer.as_ms()
+                );
+            }
+        }
+        if let Some(root) = rooted_banks.last().map(|bank| bank.slot()) {
+            pending_accounts.retain(|slot, _| *slot > root);
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
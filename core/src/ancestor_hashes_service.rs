@@ -0,0 +1,452 @@
+//! The `ancestor_hashes_service` module resolves which version of a duplicate slot the cluster
+//! is on. For every slot with a duplicate proof that this node has replayed, it asks a
+//! stake-weighted sample of peers for the bank hashes of the slot and its ancestors. Once a
+//! supermajority of the sampled stake agrees, the first slot whose local version differs from
+//! theirs is marked dead and handed to `RepairService` to be dumped and repaired.
+use crate::{
+    cluster_info::ClusterInfo,
+    repair_service::REPAIR_MS,
+    serve_repair::{AncestorHashesResponse, ServeRepair},
+    weighted_shuffle::weighted_shuffle,
+};
+use crossbeam_channel::{Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
+use rand::Rng;
+use solana_ledger::{blockstore::Blockstore, shred::Nonce, staking_utils};
+use solana_perf::packet::limited_deserialize;
+use solana_runtime::{bank_forks::BankForks, commitment::VOTE_THRESHOLD_SIZE};
+use solana_sdk::{
+    clock::Slot, hash::Hash, packet::PACKET_DATA_SIZE, pubkey::Pubkey, timing::timestamp,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub type AncestorDuplicateSlotsSender = CrossbeamSender<Slot>;
+pub type AncestorDuplicateSlotsReceiver = CrossbeamReceiver<Slot>;
+
+/// Number of peers asked for the ancestor hashes of a slot
+pub const ANCESTOR_HASHES_SAMPLE_SIZE: usize = 21;
+/// Time after which a request that has not reached agreement is sent to a new sample
+pub const ANCESTOR_HASHES_REQUEST_TIMEOUT_MS: u64 = 10_000;
+/// Maximum number of responses read from the socket per iteration
+const MAX_RESPONSES_PER_ITERATION: usize = 1024;
+
+struct AncestorRequestStatus {
+    nonce: Nonce,
+    start: u64,
+    // Sampled peers by the address the request was sent to, with their stake weight
+    sampled_peers: HashMap<SocketAddr, (Pubkey, u64)>,
+    responses: HashMap<Pubkey, Vec<(Slot, Hash)>>,
+}
+
+impl AncestorRequestStatus {
+    fn new(nonce: Nonce, sampled_peers: HashMap<SocketAddr, (Pubkey, u64)>) -> Self {
+        Self {
+            nonce,
+            start: timestamp(),
+            sampled_peers,
+            responses: HashMap::new(),
+        }
+    }
+
+    /// Record the response received from `from_addr`, and return the ancestor hashes a
+    /// supermajority of the sampled stake agrees on, once it does
+    fn add_response(
+        &mut self,
+        from_addr: &SocketAddr,
+        response: AncestorHashesResponse,
+    ) -> Option<Vec<(Slot, Hash)>> {
+        let (pubkey, _) = self.sampled_peers.get(from_addr)?;
+        if response.nonce != self.nonce
+            || response.ancestor_hashes.first().map(|(slot, _)| *slot) != Some(response.slot)
+        {
+            return None;
+        }
+        self.responses.insert(*pubkey, response.ancestor_hashes);
+
+        let stakes: HashMap<_, _> = self.sampled_peers.values().cloned().collect();
+        let total_stake: u64 = stakes.values().sum();
+        let mut stake_by_hash: HashMap<Hash, u64> = HashMap::new();
+        for (pubkey, ancestor_hashes) in &self.responses {
+            *stake_by_hash.entry(ancestor_hashes[0].1).or_default() += stakes[pubkey];
+        }
+        let (agreed_hash, _) = stake_by_hash
+            .into_iter()
+            .find(|(_, stake)| *stake as f64 / total_stake as f64 > VOTE_THRESHOLD_SIZE)?;
+        // A bank hash commits to its parent's, so all the peers which agree on the hash of the
+        // slot agree on its ancestors, up to how far back each of them could answer
+        self.responses
+            .values()
+            .filter(|ancestor_hashes| ancestor_hashes[0].1 == agreed_hash)
+            .max_by_key(|ancestor_hashes| ancestor_hashes.len())
+            .cloned()
+    }
+}
+
+pub struct AncestorHashesService {
+    t_ancestor_hashes: JoinHandle<()>,
+}
+
+impl AncestorHashesService {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        cluster_info: Arc<ClusterInfo>,
+        socket: UdpSocket,
+        repair_validators: Option<HashSet<Pubkey>>,
+        ancestor_duplicate_slots_sender: AncestorDuplicateSlotsSender,
+    ) -> Self {
+        let t_ancestor_hashes = Builder::new()
+            .name("solana-ancestor-hashes-service".to_string())
+            .spawn(move || {
+                Self::run(
+                    &exit,
+                    &blockstore,
+                    &bank_forks,
+                    cluster_info,
+                    &socket,
+                    &repair_validators,
+                    &ancestor_duplicate_slots_sender,
+                )
+            })
+            .unwrap();
+        Self { t_ancestor_hashes }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_ancestor_hashes.join()
+    }
+
+    fn run(
+        exit: &AtomicBool,
+        blockstore: &Blockstore,
+        bank_forks: &RwLock<BankForks>,
+        cluster_info: Arc<ClusterInfo>,
+        socket: &UdpSocket,
+        repair_validators: &Option<HashSet<Pubkey>>,
+        ancestor_duplicate_slots_sender: &AncestorDuplicateSlotsSender,
+    ) {
+        let serve_repair = ServeRepair::new(cluster_info);
+        let mut pending_requests: BTreeMap<Slot, AncestorRequestStatus> = BTreeMap::new();
+        // Slots whose version has been settled, until they are rooted
+        let mut resolved_slots: BTreeSet<Slot> = BTreeSet::new();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(REPAIR_MS)))
+            .expect("Failed to set ancestor hashes socket read timeout");
+        let mut buf = [0u8; PACKET_DATA_SIZE];
+
+        while !exit.load(Ordering::Relaxed) {
+            for _ in 0..MAX_RESPONSES_PER_ITERATION {
+                let (size, from_addr) = match socket.recv_from(&mut buf) {
+                    Ok(received) => received,
+                    Err(_) => break,
+                };
+                let response: AncestorHashesResponse = match limited_deserialize(&buf[..size]) {
+                    Ok(response) => response,
+                    Err(_) => continue,
+                };
+                let slot = response.slot;
+                let agreed_ancestor_hashes = match pending_requests.get_mut(&slot) {
+                    Some(status) => status.add_response(&from_addr, response),
+                    None => continue,
+                };
+                if let Some(agreed_ancestor_hashes) = agreed_ancestor_hashes {
+                    pending_requests.remove(&slot);
+                    resolved_slots.insert(slot);
+                    Self::handle_agreed_ancestor_hashes(
+                        slot,
+                        &agreed_ancestor_hashes,
+                        blockstore,
+                        bank_forks,
+                        ancestor_duplicate_slots_sender,
+                    );
+                }
+            }
+
+            let root = bank_forks.read().unwrap().root();
+            pending_requests = pending_requests.split_off(&(root + 1));
+            resolved_slots = resolved_slots.split_off(&(root + 1));
+            let now = timestamp();
+            for slot in Self::find_candidate_slots(blockstore, bank_forks, root) {
+                let is_pending = pending_requests.get(&slot).map_or(false, |status| {
+                    now.saturating_sub(status.start) < ANCESTOR_HASHES_REQUEST_TIMEOUT_MS
+                });
+                if resolved_slots.contains(&slot) || is_pending {
+                    continue;
+                }
+                if let Some(status) = Self::send_ancestor_hashes_request(
+                    slot,
+                    &serve_repair,
+                    bank_forks,
+                    socket,
+                    repair_validators,
+                ) {
+                    pending_requests.insert(slot, status);
+                }
+            }
+        }
+    }
+
+    /// Slots past the root with a duplicate proof, whose local version this node has either
+    /// frozen or marked dead
+    fn find_candidate_slots(
+        blockstore: &Blockstore,
+        bank_forks: &RwLock<BankForks>,
+        root: Slot,
+    ) -> Vec<Slot> {
+        let duplicate_slots = match blockstore.duplicate_slots_iterator(root + 1) {
+            Ok(duplicate_slots) => duplicate_slots,
+            Err(err) => {
+                error!("failed to iterate duplicate slots: {:?}", err);
+                return vec![];
+            }
+        };
+        let bank_forks = bank_forks.read().unwrap();
+        duplicate_slots
+            .filter(|slot| {
+                bank_forks
+                    .get(*slot)
+                    .map(|bank| bank.is_frozen())
+                    .unwrap_or(false)
+                    || blockstore.is_dead(*slot)
+            })
+            .collect()
+    }
+
+    /// Send an `AncestorHashes` request for `slot` to a stake-weighted sample of peers
+    fn send_ancestor_hashes_request(
+        slot: Slot,
+        serve_repair: &ServeRepair,
+        bank_forks: &RwLock<BankForks>,
+        socket: &UdpSocket,
+        repair_validators: &Option<HashSet<Pubkey>>,
+    ) -> Option<AncestorRequestStatus> {
+        let peers = serve_repair.repair_peers(repair_validators, slot);
+        if peers.is_empty() {
+            return None;
+        }
+        let root_bank = bank_forks.read().unwrap().root_bank().clone();
+        let epoch = root_bank.epoch_schedule().get_epoch(slot);
+        let stakes = staking_utils::staked_nodes_at_epoch(&root_bank, epoch).unwrap_or_default();
+        // Unstaked peers are sampled too, so that clusters without stake can make progress
+        let weights: Vec<_> = peers
+            .iter()
+            .map(|peer| stakes.get(&peer.id).cloned().unwrap_or_default().max(1))
+            .collect();
+        let sampled_peers: HashMap<_, _> =
+            weighted_shuffle(weights.clone(), solana_sdk::pubkey::new_rand().to_bytes())
+                .into_iter()
+                .take(ANCESTOR_HASHES_SAMPLE_SIZE)
+                .map(|i| (peers[i].serve_repair, (peers[i].id, weights[i])))
+                .collect();
+
+        let nonce = rand::thread_rng().gen();
+        let request = serve_repair
+            .ancestor_hashes_request_bytes(slot, nonce)
+            .ok()?;
+        for addr in sampled_peers.keys() {
+            if let Err(err) = socket.send_to(&request, addr) {
+                info!("ancestor hashes request send_to({}) error {:?}", addr, err);
+            }
+        }
+        Some(AncestorRequestStatus::new(nonce, sampled_peers))
+    }
+
+    fn handle_agreed_ancestor_hashes(
+        slot: Slot,
+        agreed_ancestor_hashes: &[(Slot, Hash)],
+        blockstore: &Blockstore,
+        bank_forks: &RwLock<BankForks>,
+        ancestor_duplicate_slots_sender: &AncestorDuplicateSlotsSender,
+    ) {
+        let wrong_slot = {
+            let bank_forks = bank_forks.read().unwrap();
+            Self::find_wrong_version(
+                agreed_ancestor_hashes,
+                |slot| {
+                    bank_forks
+                        .get(slot)
+                        .filter(|bank| bank.is_frozen())
+                        .map(|bank| bank.hash())
+                },
+                |slot| blockstore.is_dead(slot),
+                bank_forks.root(),
+            )
+        };
+        match wrong_slot {
+            Some(wrong_slot) => {
+                warn!(
+                    "cluster agreed on a different version of slot {}, marking slot {} dead \
+                     to repair it",
+                    slot, wrong_slot
+                );
+                datapoint_info!(
+                    "ancestor_hashes_service-wrong_version",
+                    ("slot", slot as i64, i64),
+                    ("wrong_slot", wrong_slot as i64, i64),
+                );
+                if let Err(err) = blockstore.set_dead_slot(wrong_slot) {
+                    error!("failed to mark slot {} dead: {:?}", wrong_slot, err);
+                }
+                let _ = ancestor_duplicate_slots_sender.send(wrong_slot);
+            }
+            None => info!("cluster agreed on this node's version of slot {}", slot),
+        }
+    }
+
+    /// Return the earliest slot past `root` whose local version differs from the one in
+    /// `agreed_ancestor_hashes`, ordered most recent first as in `AncestorHashesResponse`.
+    /// Slots which are missing locally are skipped, as the mismatch then shows up in their
+    /// descendants.
+    fn find_wrong_version(
+        agreed_ancestor_hashes: &[(Slot, Hash)],
+        local_bank_hash: impl Fn(Slot) -> Option<Hash>,
+        is_dead: impl Fn(Slot) -> bool,
+        root: Slot,
+    ) -> Option<Slot> {
+        agreed_ancestor_hashes
+            .iter()
+            .rev()
+            .filter(|(slot, _)| *slot > root)
+            .find(|(slot, agreed_hash)| match local_bank_hash(*slot) {
+                Some(local_hash) => local_hash != *agreed_hash,
+                None => is_dead(*slot),
+            })
+            .map(|(slot, _)| *slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_response(slot: Slot, nonce: Nonce, hashes: &[Hash]) -> AncestorHashesResponse {
+        AncestorHashesResponse {
+            slot,
+            nonce,
+            ancestor_hashes: hashes
+                .iter()
+                .enumerate()
+                .map(|(i, hash)| (slot - i as u64, *hash))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_ancestor_request_status_add_response() {
+        let addrs: Vec<SocketAddr> = (0..4)
+            .map(|i| SocketAddr::from(([127, 0, 0, 1], 8000 + i)))
+            .collect();
+        let sampled_peers: HashMap<_, _> = addrs
+            .iter()
+            .zip(&[40, 30, 20, 10])
+            .map(|(addr, stake)| (*addr, (Pubkey::new_unique(), *stake)))
+            .collect();
+        let mut status = AncestorRequestStatus::new(7, sampled_peers);
+        let (hash1, hash2, parent) = (Hash::new_unique(), Hash::new_unique(), Hash::new_unique());
+
+        // Responses from unknown peers, with the wrong nonce or for another slot are ignored
+        let unknown = SocketAddr::from(([127, 0, 0, 1], 9000));
+        assert_eq!(
+            status.add_response(&unknown, new_response(5, 7, &[hash1])),
+            None
+        );
+        assert_eq!(
+            status.add_response(&addrs[0], new_response(5, 8, &[hash1])),
+            None
+        );
+        assert_eq!(
+            status.add_response(
+                &addrs[0],
+                AncestorHashesResponse {
+                    slot: 5,
+                    nonce: 7,
+                    ancestor_hashes: vec![(4, hash1)],
+                }
+            ),
+            None
+        );
+        assert!(status.responses.is_empty());
+
+        // 40% agreeing is not enough
+        assert_eq!(
+            status.add_response(&addrs[0], new_response(5, 7, &[hash1])),
+            None
+        );
+        assert_eq!(
+            status.add_response(&addrs[1], new_response(5, 7, &[hash2])),
+            None
+        );
+        assert_eq!(
+            status.add_response(&addrs[2], new_response(5, 7, &[hash1, parent])),
+            None
+        );
+        // 70% is, and the longest agreeing ancestry is returned
+        assert_eq!(
+            status.add_response(&addrs[3], new_response(5, 7, &[hash1])),
+            Some(vec![(5, hash1), (4, parent)])
+        );
+    }
+
+    #[test]
+    fn test_find_wrong_version() {
+        let hashes: HashMap<Slot, Hash> = (0..10).map(|slot| (slot, Hash::new_unique())).collect();
+        let agreed: Vec<_> = (3..=8).rev().map(|slot| (slot, hashes[&slot])).collect();
+        let hashes = &hashes;
+        let local = |mismatched: Vec<Slot>, missing: Vec<Slot>| {
+            move |slot| {
+                if missing.contains(&slot) {
+                    None
+                } else if mismatched.contains(&slot) {
+                    Some(Hash::new_unique())
+                } else {
+                    Some(hashes[&slot])
+                }
+            }
+        };
+
+        // Same version
+        assert_eq!(
+            AncestorHashesService::find_wrong_version(&agreed, local(vec![], vec![]), |_| false, 0),
+            None
+        );
+        // The earliest mismatching ancestor is the wrong version
+        assert_eq!(
+            AncestorHashesService::find_wrong_version(
+                &agreed,
+                local(vec![5, 8], vec![]),
+                |_| false,
+                0
+            ),
+            Some(5)
+        );
+        // unless it is already rooted
+        assert_eq!(
+            AncestorHashesService::find_wrong_version(
+                &agreed,
+                local(vec![5, 8], vec![]),
+                |_| false,
+                5
+            ),
+            Some(8)
+        );
+        // Dead slots are wrong, missing ones are skipped
+        assert_eq!(
+            AncestorHashesService::find_wrong_version(
+                &agreed,
+                local(vec![], vec![4, 7]),
+                |slot| slot == 7,
+                0
+            ),
+            Some(7)
+        );
+    }
+}
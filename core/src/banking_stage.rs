@@ -5,6 +5,7 @@ use crate::{
     cluster_info::ClusterInfo,
     poh_recorder::{PohRecorder, PohRecorderError, WorkingBankEntry},
     poh_service::PohService,
+    priority_scheduler::{prioritize_buffered_packets, PriorityCache},
 };
 use crossbeam_channel::{Receiver as CrossbeamReceiver, RecvTimeoutError};
 use itertools::Itertools;
@@ -25,6 +26,9 @@ use solana_runtime::{
     accounts_db::ErrorCounters,
     bank::{Bank, TransactionBalancesSet, TransactionProcessResult},
     bank_utils,
+    cost_model::TransactionCost,
+    cost_tracker::CostTracker,
+    message_processor::ExecuteDetailsTimings,
     transaction_batch::TransactionBatch,
     vote_sender_types::ReplayVoteSender,
 };
@@ -84,6 +88,7 @@ impl BankingStage {
         verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
+        enable_priority_scheduler: bool,
     ) -> Self {
         Self::new_num_threads(
             cluster_info,
@@ -93,9 +98,11 @@ impl BankingStage {
             Self::num_threads(),
             transaction_status_sender,
             gossip_vote_sender,
+            enable_priority_scheduler,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new_num_threads(
         cluster_info: &Arc<ClusterInfo>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
@@ -104,27 +111,33 @@ impl BankingStage {
         num_threads: u32,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
+        enable_priority_scheduler: bool,
     ) -> Self {
         let batch_limit = TOTAL_BUFFERED_PACKETS / ((num_threads - 1) as usize * PACKETS_PER_BATCH);
         // Single thread to generate entries from many banks.
         // This thread talks to poh_service and broadcasts the entries once they have been recorded.
         // Once an entry has been recorded, its blockhash is registered with the bank.
         let my_pubkey = cluster_info.id();
+        // All the banking threads pack the same block, so they share one cost tracker
+        let cost_tracker = Arc::new(Mutex::new(CostTracker::default()));
         // Many banks that process transactions in parallel.
         let bank_thread_hdls: Vec<JoinHandle<()>> = (0..num_threads)
             .map(|i| {
-                let (verified_receiver, enable_forwarding) = if i < num_threads - 1 {
-                    (verified_receiver.clone(), true)
-                } else {
-                    // Disable forwarding of vote transactions, as votes are gossiped
-                    (verified_vote_receiver.clone(), false)
-                };
+                let (verified_receiver, enable_forwarding, enable_priority_scheduler) =
+                    if i < num_threads - 1 {
+                        (verified_receiver.clone(), true, enable_priority_scheduler)
+                    } else {
+                        // Disable forwarding of vote transactions, as votes are gossiped.
+                        // Votes don't pay for priority either.
+                        (verified_vote_receiver.clone(), false, false)
+                    };
 
                 let poh_recorder = poh_recorder.clone();
                 let cluster_info = cluster_info.clone();
                 let mut recv_start = Instant::now();
                 let transaction_status_sender = transaction_status_sender.clone();
                 let gossip_vote_sender = gossip_vote_sender.clone();
+                let cost_tracker = cost_tracker.clone();
                 Builder::new()
                     .name("solana-banking-stage-tx".to_string())
                     .spawn(move || {
@@ -140,6 +153,8 @@ impl BankingStage {
                             batch_limit,
                             transaction_status_sender,
                             gossip_vote_sender,
+                            enable_priority_scheduler,
+                            &cost_tracker,
                         );
                     })
                     .unwrap()
@@ -176,6 +191,7 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
     ) -> UnprocessedPackets {
         let mut unprocessed_packets = vec![];
         let mut rebuffered_packets = 0;
@@ -208,6 +224,7 @@ impl BankingStage {
                     unprocessed_indexes.to_owned(),
                     transaction_status_sender.clone(),
                     gossip_vote_sender,
+                    cost_tracker,
                 );
 
             new_tx_count += processed;
@@ -303,6 +320,9 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        enable_priority_scheduler: bool,
+        priority_cache: &mut PriorityCache,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
     ) -> BufferedPacketsDecision {
         let (leader_at_slot_offset, poh_has_bank, would_be_leader) = {
             let poh = poh_recorder.lock().unwrap();
@@ -324,6 +344,16 @@ impl BankingStage {
 
         match decision {
             BufferedPacketsDecision::Consume => {
+                if enable_priority_scheduler {
+                    let bank = poh_recorder.lock().unwrap().bank();
+                    prioritize_buffered_packets(
+                        buffered_packets,
+                        bank.as_deref(),
+                        priority_cache,
+                        MAX_NUM_TRANSACTIONS_PER_BATCH,
+                        batch_limit,
+                    );
+                }
                 let mut unprocessed = Self::consume_buffered_packets(
                     my_pubkey,
                     poh_recorder,
@@ -331,11 +361,22 @@ impl BankingStage {
                     batch_limit,
                     transaction_status_sender,
                     gossip_vote_sender,
+                    cost_tracker,
                 );
                 buffered_packets.append(&mut unprocessed);
             }
             BufferedPacketsDecision::Forward => {
                 if enable_forwarding {
+                    if enable_priority_scheduler {
+                        // Send the highest priority transactions to the next leader first
+                        prioritize_buffered_packets(
+                            buffered_packets,
+                            None,
+                            priority_cache,
+                            MAX_NUM_TRANSACTIONS_PER_BATCH,
+                            batch_limit,
+                        );
+                    }
                     let next_leader = poh_recorder
                         .lock()
                         .unwrap()
@@ -376,9 +417,12 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
+        enable_priority_scheduler: bool,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
     ) {
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let mut buffered_packets = vec![];
+        let mut priority_cache = PriorityCache::default();
         loop {
             while !buffered_packets.is_empty() {
                 let decision = Self::process_buffered_packets(
@@ -391,6 +435,9 @@ impl BankingStage {
                     batch_limit,
                     transaction_status_sender.clone(),
                     &gossip_vote_sender,
+                    enable_priority_scheduler,
+                    &mut priority_cache,
+                    cost_tracker,
                 );
                 if decision == BufferedPacketsDecision::Hold {
                     // If we are waiting on a new bank,
@@ -419,6 +466,7 @@ impl BankingStage {
                 batch_limit,
                 transaction_status_sender.clone(),
                 &gossip_vote_sender,
+                cost_tracker,
             ) {
                 Err(RecvTimeoutError::Timeout) => (),
                 Err(RecvTimeoutError::Disconnected) => break,
@@ -518,6 +566,7 @@ impl BankingStage {
         batch: &TransactionBatch,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        execute_timings: &mut ExecuteDetailsTimings,
     ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
         let mut load_execute_time = Measure::start("load_execute_time");
         // Use a shorter maximum age when adding transactions into the pipeline.  This will reduce
@@ -543,6 +592,7 @@ impl BankingStage {
             MAX_PROCESSING_AGE,
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
+            execute_timings,
         );
         load_execute_time.stop();
 
@@ -609,6 +659,7 @@ impl BankingStage {
         chunk_offset: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        execute_timings: &mut ExecuteDetailsTimings,
     ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
         let mut lock_time = Measure::start("lock_time");
         // Once accounts are locked, other threads cannot encode transactions that will modify the
@@ -622,6 +673,7 @@ impl BankingStage {
             &batch,
             transaction_status_sender,
             gossip_vote_sender,
+            execute_timings,
         );
         retryable_txs.iter_mut().for_each(|x| *x += chunk_offset);
 
@@ -651,6 +703,7 @@ impl BankingStage {
         poh: &Arc<Mutex<PohRecorder>>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        execute_timings: &mut ExecuteDetailsTimings,
     ) -> (usize, Vec<usize>) {
         let mut chunk_start = 0;
         let mut unprocessed_txs = vec![];
@@ -667,6 +720,7 @@ impl BankingStage {
                 chunk_start,
                 transaction_status_sender.clone(),
                 gossip_vote_sender,
+                execute_timings,
             );
             trace!("process_transactions result: {:?}", result);
 
@@ -739,6 +793,7 @@ impl BankingStage {
         msgs: &Packets,
         transaction_indexes: &[usize],
         secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
     ) -> (Vec<Transaction>, Vec<usize>) {
         let packets = Packets::new(
             transaction_indexes
@@ -748,26 +803,33 @@ impl BankingStage {
         );
 
         let transactions = Self::deserialize_transactions(&packets);
-        let maybe_secp_verified_transactions: Vec<_> = if secp256k1_program_enabled {
-            transactions
-                .into_iter()
-                .map(|tx| {
-                    if let Some(tx) = tx {
-                        if tx.verify_precompiles().is_ok() {
-                            Some(tx)
+        let maybe_verified_transactions: Vec<_> =
+            if secp256k1_program_enabled || ed25519_program_enabled {
+                transactions
+                    .into_iter()
+                    .map(|tx| {
+                        if let Some(tx) = tx {
+                            if tx
+                                .verify_precompiles_with_features(
+                                    secp256k1_program_enabled,
+                                    ed25519_program_enabled,
+                                )
+                                .is_ok()
+                            {
+                                Some(tx)
+                            } else {
+                                None
+                            }
                         } else {
                             None
                         }
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        } else {
-            transactions
-        };
+                    })
+                    .collect()
+            } else {
+                transactions
+            };
 
-        Self::filter_transaction_indexes(maybe_secp_verified_transactions, &transaction_indexes)
+        Self::filter_transaction_indexes(maybe_verified_transactions, &transaction_indexes)
     }
 
     /// This function filters pending packets that are still valid
@@ -815,11 +877,13 @@ impl BankingStage {
         packet_indexes: Vec<usize>,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
     ) -> (usize, usize, Vec<usize>) {
         let (transactions, transaction_to_packet_indexes) = Self::transactions_from_packets(
             msgs,
             &packet_indexes,
             bank.secp256k1_program_enabled(),
+            bank.ed25519_program_enabled(),
         );
         debug!(
             "bank: {} filtered transactions {}",
@@ -827,14 +891,35 @@ impl BankingStage {
             transactions.len()
         );
 
+        let (
+            transactions,
+            transaction_to_packet_indexes,
+            transaction_costs,
+            cost_limited_packet_indexes,
+        ) = Self::filter_transactions_by_cost(
+            bank,
+            transactions,
+            transaction_to_packet_indexes,
+            cost_tracker,
+        );
+
         let tx_len = transactions.len();
 
+        let mut execute_timings = ExecuteDetailsTimings::default();
         let (processed, unprocessed_tx_indexes) = Self::process_transactions(
             bank,
             &transactions,
             poh,
             transaction_status_sender,
             gossip_vote_sender,
+            &mut execute_timings,
+        );
+
+        Self::update_cost_tracker(
+            cost_tracker,
+            &transaction_costs,
+            &unprocessed_tx_indexes,
+            &execute_timings,
         );
 
         let unprocessed_tx_count = unprocessed_tx_indexes.len();
@@ -850,7 +935,84 @@ impl BankingStage {
             unprocessed_tx_count.saturating_sub(filtered_unprocessed_packet_indexes.len())
         );
 
-        (processed, tx_len, filtered_unprocessed_packet_indexes)
+        // Transactions that didn't fit in this block are retried in the next one
+        let mut unprocessed_packet_indexes = filtered_unprocessed_packet_indexes;
+        unprocessed_packet_indexes.extend(cost_limited_packet_indexes);
+        unprocessed_packet_indexes.sort_unstable();
+
+        (processed, tx_len, unprocessed_packet_indexes)
+    }
+
+    /// Add the estimated cost of `transactions` to the block being packed in `bank`
+    ///
+    /// Returns the transactions that fit in the block with their packet indexes and costs, and
+    /// the packet indexes of the transactions that would exceed the block's cost limits
+    fn filter_transactions_by_cost(
+        bank: &Arc<Bank>,
+        transactions: Vec<Transaction>,
+        transaction_to_packet_indexes: Vec<usize>,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
+    ) -> (
+        Vec<Transaction>,
+        Vec<usize>,
+        Vec<TransactionCost>,
+        Vec<usize>,
+    ) {
+        let mut cost_tracker = cost_tracker.lock().unwrap();
+        cost_tracker.reset_if_new_bank(bank.slot());
+
+        let mut fitting_transactions = Vec::with_capacity(transactions.len());
+        let mut fitting_packet_indexes = Vec::with_capacity(transactions.len());
+        let mut transaction_costs = Vec::with_capacity(transactions.len());
+        let mut cost_limited_packet_indexes = vec![];
+        for (transaction, packet_index) in
+            transactions.into_iter().zip(transaction_to_packet_indexes)
+        {
+            match cost_tracker.try_add(&transaction) {
+                Ok(transaction_cost) => {
+                    fitting_transactions.push(transaction);
+                    fitting_packet_indexes.push(packet_index);
+                    transaction_costs.push(transaction_cost);
+                }
+                Err(err) => {
+                    debug!(
+                        "bank: {} transaction {} over cost limit: {:?}",
+                        bank.slot(),
+                        transaction.signatures[0],
+                        err
+                    );
+                    cost_limited_packet_indexes.push(packet_index);
+                }
+            }
+        }
+        inc_new_counter_info!(
+            "banking_stage-cost_limited_transactions",
+            cost_limited_packet_indexes.len()
+        );
+
+        (
+            fitting_transactions,
+            fitting_packet_indexes,
+            transaction_costs,
+            cost_limited_packet_indexes,
+        )
+    }
+
+    /// Release the estimated cost of the transactions that were not committed, and record the
+    /// measured execution cost of the instructions of each program executed
+    fn update_cost_tracker(
+        cost_tracker: &Arc<Mutex<CostTracker>>,
+        transaction_costs: &[TransactionCost],
+        unprocessed_tx_indexes: &[usize],
+        execute_timings: &ExecuteDetailsTimings,
+    ) {
+        let mut cost_tracker = cost_tracker.lock().unwrap();
+        for index in unprocessed_tx_indexes {
+            cost_tracker.remove(&transaction_costs[*index]);
+        }
+        cost_tracker
+            .cost_model_mut()
+            .update_execution_costs(execute_timings);
     }
 
     fn filter_unprocessed_packets(
@@ -873,6 +1035,7 @@ impl BankingStage {
             msgs,
             &transaction_indexes,
             bank.secp256k1_program_enabled(),
+            bank.ed25519_program_enabled(),
         );
 
         let tx_count = transaction_to_packet_indexes.len();
@@ -920,6 +1083,7 @@ impl BankingStage {
         batch_limit: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
+        cost_tracker: &Arc<Mutex<CostTracker>>,
     ) -> Result<UnprocessedPackets, RecvTimeoutError> {
         let mut recv_time = Measure::start("process_packets_recv");
         let mms = verified_receiver.recv_timeout(recv_timeout)?;
@@ -963,6 +1127,7 @@ impl BankingStage {
                 packet_indexes,
                 transaction_status_sender.clone(),
                 gossip_vote_sender,
+                cost_tracker,
             );
 
             new_tx_count += processed;
@@ -1093,7 +1258,7 @@ mod tests {
         get_tmp_ledger_path,
     };
     use solana_perf::packet::to_packets;
-    use solana_runtime::bank::HashAgeKind;
+    use solana_runtime::{bank::HashAgeKind, cost_model::COMPUTE_UNIT_TO_US_RATIO};
     use solana_sdk::{
         instruction::InstructionError,
         signature::{Keypair, Signer},
@@ -1128,6 +1293,7 @@ mod tests {
                 vote_receiver,
                 None,
                 gossip_vote_sender,
+                false,
             );
             drop(verified_sender);
             drop(vote_sender);
@@ -1171,6 +1337,7 @@ mod tests {
                 vote_receiver,
                 None,
                 gossip_vote_sender,
+                false,
             );
             trace!("sending bank");
             drop(verified_sender);
@@ -1237,6 +1404,7 @@ mod tests {
                 vote_receiver,
                 None,
                 gossip_vote_sender,
+                false,
             );
 
             // fund another account so we can send 2 good transactions in a single batch.
@@ -1382,6 +1550,7 @@ mod tests {
                     2,
                     None,
                     gossip_vote_sender,
+                    false,
                 );
 
                 // wait for banking_stage to eat the packets
@@ -1678,6 +1847,90 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bank_filter_transactions_by_cost() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let transfer = |from: &Keypair| {
+            system_transaction::transfer(
+                from,
+                &solana_sdk::pubkey::new_rand(),
+                1,
+                genesis_config.hash(),
+            )
+        };
+        let other = Keypair::new();
+        let transactions = vec![
+            transfer(&mint_keypair),
+            transfer(&mint_keypair),
+            transfer(&other),
+            transfer(&mint_keypair),
+        ];
+        let cost = CostTracker::default()
+            .cost_model()
+            .calculate_cost(&transactions[0])
+            .sum();
+
+        // Only two transactions may write the mint in one block
+        let cost_tracker = Arc::new(Mutex::new(CostTracker::new(cost * 2, cost * 10)));
+        let (fitting, packet_indexes, costs, cost_limited_packet_indexes) =
+            BankingStage::filter_transactions_by_cost(
+                &bank,
+                transactions.clone(),
+                vec![1, 3, 5, 7],
+                &cost_tracker,
+            );
+        assert_eq!(fitting, transactions[0..3].to_vec());
+        assert_eq!(packet_indexes, vec![1, 3, 5]);
+        assert_eq!(costs.len(), 3);
+        assert_eq!(cost_limited_packet_indexes, vec![7]);
+
+        // Releasing an uncommitted transaction makes room for the deferred one
+        BankingStage::update_cost_tracker(
+            &cost_tracker,
+            &costs,
+            &[1],
+            &ExecuteDetailsTimings::default(),
+        );
+        let (fitting, _, _, cost_limited_packet_indexes) =
+            BankingStage::filter_transactions_by_cost(
+                &bank,
+                vec![transactions[3].clone()],
+                vec![7],
+                &cost_tracker,
+            );
+        assert_eq!(fitting, vec![transactions[3].clone()]);
+        assert!(cost_limited_packet_indexes.is_empty());
+
+        // A new bank starts an empty block
+        let bank = Arc::new(Bank::new_from_parent(&bank, &Pubkey::default(), 1));
+        let (fitting, _, _, _) = BankingStage::filter_transactions_by_cost(
+            &bank,
+            transactions.clone(),
+            vec![1, 3, 5, 7],
+            &cost_tracker,
+        );
+        assert_eq!(fitting, transactions[0..3].to_vec());
+
+        // The measured execution time of each program updates its estimated cost
+        let program_id = Pubkey::new_unique();
+        let mut execute_timings = ExecuteDetailsTimings::default();
+        execute_timings.accumulate_program(&program_id, 10);
+        BankingStage::update_cost_tracker(&cost_tracker, &[], &[], &execute_timings);
+        assert_eq!(
+            cost_tracker
+                .lock()
+                .unwrap()
+                .cost_model()
+                .find_instruction_cost(&program_id),
+            10 * COMPUTE_UNIT_TO_US_RATIO
+        );
+    }
+
     #[test]
     fn test_should_process_or_forward_packets() {
         let my_pubkey = solana_sdk::pubkey::new_rand();
@@ -1768,6 +2021,7 @@ mod tests {
                 0,
                 None,
                 &gossip_vote_sender,
+                &mut ExecuteDetailsTimings::default(),
             )
             .0
             .unwrap();
@@ -1805,6 +2059,7 @@ mod tests {
                     0,
                     None,
                     &gossip_vote_sender,
+                    &mut ExecuteDetailsTimings::default(),
                 )
                 .0,
                 Err(PohRecorderError::MaxHeightReached)
@@ -1865,6 +2120,7 @@ mod tests {
                 0,
                 None,
                 &gossip_vote_sender,
+                &mut ExecuteDetailsTimings::default(),
             );
 
             assert!(result.is_ok());
@@ -1957,6 +2213,7 @@ mod tests {
                     &poh_recorder,
                     None,
                     &gossip_vote_sender,
+                    &mut ExecuteDetailsTimings::default(),
                 );
 
             assert_eq!(processed_transactions_count, 0,);
@@ -2043,6 +2300,7 @@ mod tests {
                 0,
                 Some(transaction_status_sender),
                 &gossip_vote_sender,
+                &mut ExecuteDetailsTimings::default(),
             );
 
             transaction_status_service.join().unwrap();
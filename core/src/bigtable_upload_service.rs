@@ -22,7 +22,7 @@ pub struct BigTableUploadService {
 impl BigTableUploadService {
     pub fn new(
         runtime_handle: runtime::Handle,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        bigtable_ledger_storage: Arc<dyn solana_storage_bigtable::LedgerStorage>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
@@ -46,7 +46,7 @@ impl BigTableUploadService {
 
     fn run(
         runtime: runtime::Handle,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        bigtable_ledger_storage: Arc<dyn solana_storage_bigtable::LedgerStorage>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
@@ -7,6 +7,7 @@ use solana_ledger::{
     entry::Entry,
     shred::{Shred, Shredder, RECOMMENDED_FEC_RATE, SHRED_TICK_REFERENCE_MASK},
 };
+use solana_runtime::feature_set;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair, timing::duration_as_us};
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -20,7 +21,6 @@ pub struct StandardBroadcastRun {
     unfinished_slot: Option<UnfinishedSlotInfo>,
     current_slot_and_parent: Option<(u64, u64)>,
     slot_broadcast_start: Option<Instant>,
-    keypair: Arc<Keypair>,
     shred_version: u16,
     last_datapoint_submit: Arc<AtomicU64>,
     num_batches: usize,
@@ -35,7 +35,7 @@ struct BroadcastPeerCache {
 }
 
 impl StandardBroadcastRun {
-    pub(super) fn new(keypair: Arc<Keypair>, shred_version: u16) -> Self {
+    pub(super) fn new(shred_version: u16) -> Self {
         Self {
             process_shreds_stats: ProcessShredsStats::default(),
             transmit_shreds_stats: Arc::new(Mutex::new(SlotBroadcastStats::default())),
@@ -43,7 +43,6 @@ impl StandardBroadcastRun {
             unfinished_slot: None,
             current_slot_and_parent: None,
             slot_broadcast_start: None,
-            keypair,
             shred_version,
             last_datapoint_submit: Arc::new(AtomicU64::new(0)),
             num_batches: 0,
@@ -52,7 +51,11 @@ impl StandardBroadcastRun {
         }
     }
 
-    fn check_for_interrupted_slot(&mut self, max_ticks_in_slot: u8) -> Option<Shred> {
+    fn check_for_interrupted_slot(
+        &mut self,
+        keypair: &Keypair,
+        max_ticks_in_slot: u8,
+    ) -> Option<Shred> {
         let (slot, _) = self.current_slot_and_parent.unwrap();
         let mut last_unfinished_slot_shred = self
             .unfinished_slot
@@ -78,13 +81,18 @@ impl StandardBroadcastRun {
 
         // This shred should only be Some if the previous slot was interrupted
         if let Some(ref mut shred) = last_unfinished_slot_shred {
-            Shredder::sign_shred(&self.keypair, shred);
+            Shredder::sign_shred(keypair, shred);
             self.unfinished_slot = None;
         }
 
         last_unfinished_slot_shred
     }
-    fn init_shredder(&self, blockstore: &Blockstore, reference_tick: u8) -> (Shredder, u32) {
+    fn init_shredder(
+        &self,
+        keypair: &Arc<Keypair>,
+        blockstore: &Blockstore,
+        reference_tick: u8,
+    ) -> (Shredder, u32) {
         let (slot, parent_slot) = self.current_slot_and_parent.unwrap();
         let next_shred_index = self
             .unfinished_slot
@@ -101,7 +109,7 @@ impl StandardBroadcastRun {
                 slot,
                 parent_slot,
                 RECOMMENDED_FEC_RATE,
-                self.keypair.clone(),
+                keypair.clone(),
                 reference_tick,
                 self.shred_version,
             )
@@ -127,10 +135,29 @@ impl StandardBroadcastRun {
 
         data_shreds
     }
+    fn entries_to_merkle_shreds(
+        &mut self,
+        shredder: &Shredder,
+        next_shred_index: u32,
+        entries: &[Entry],
+        is_slot_end: bool,
+    ) -> (Vec<Shred>, Vec<Shred>) {
+        let (data_shreds, coding_shreds, new_next_shred_index) =
+            shredder.entries_to_merkle_shreds(entries, is_slot_end, next_shred_index);
+
+        self.unfinished_slot = Some(UnfinishedSlotInfo {
+            next_shred_index: new_next_shred_index,
+            slot: shredder.slot,
+            parent: shredder.parent_slot,
+        });
+
+        (data_shreds, coding_shreds)
+    }
 
     #[cfg(test)]
     fn test_process_receive_results(
         &mut self,
+        keypair: &Arc<Keypair>,
         cluster_info: &ClusterInfo,
         sock: &UdpSocket,
         blockstore: &Arc<Blockstore>,
@@ -138,7 +165,7 @@ impl StandardBroadcastRun {
     ) -> Result<()> {
         let (bsend, brecv) = channel();
         let (ssend, srecv) = channel();
-        self.process_receive_results(&blockstore, &ssend, &bsend, receive_results)?;
+        self.process_receive_results(keypair, &blockstore, &ssend, &bsend, receive_results)?;
         let srecv = Arc::new(Mutex::new(srecv));
         let brecv = Arc::new(Mutex::new(brecv));
         //data
@@ -152,6 +179,7 @@ impl StandardBroadcastRun {
 
     fn process_receive_results(
         &mut self,
+        keypair: &Arc<Keypair>,
         blockstore: &Arc<Blockstore>,
         socket_sender: &Sender<(TransmitShreds, Option<BroadcastShredBatchInfo>)>,
         blockstore_sender: &Sender<(Arc<Vec<Shred>>, Option<BroadcastShredBatchInfo>)>,
@@ -180,20 +208,37 @@ impl StandardBroadcastRun {
 
         // 1) Check if slot was interrupted
         let last_unfinished_slot_shred =
-            self.check_for_interrupted_slot(bank.ticks_per_slot() as u8);
+            self.check_for_interrupted_slot(keypair, bank.ticks_per_slot() as u8);
 
         // 2) Convert entries to shreds and coding shreds
         let (shredder, next_shred_index) = self.init_shredder(
+            keypair,
             blockstore,
             (bank.tick_height() % bank.ticks_per_slot()) as u8,
         );
         let is_last_in_slot = last_tick_height == bank.max_tick_height();
-        let data_shreds = self.entries_to_data_shreds(
-            &shredder,
-            next_shred_index,
-            &receive_results.entries,
-            is_last_in_slot,
-        );
+        // Merkle shreds are signed once per erasure batch, so their coding shreds have to be
+        // generated up front along with the data shreds
+        let (data_shreds, merkle_coding_shreds) = if bank
+            .feature_set
+            .is_active(&feature_set::merkle_shreds::id())
+        {
+            let (data_shreds, coding_shreds) = self.entries_to_merkle_shreds(
+                &shredder,
+                next_shred_index,
+                &receive_results.entries,
+                is_last_in_slot,
+            );
+            (data_shreds, Some(coding_shreds))
+        } else {
+            let data_shreds = self.entries_to_data_shreds(
+                &shredder,
+                next_shred_index,
+                &receive_results.entries,
+                is_last_in_slot,
+            );
+            (data_shreds, None)
+        };
         // Insert the first shred so blockstore stores that the leader started this block
         // This must be done before the blocks are sent out over the wire.
         if !data_shreds.is_empty() && data_shreds[0].index() == 0 {
@@ -245,7 +290,9 @@ impl StandardBroadcastRun {
         let data_shreds = Arc::new(data_shreds);
         socket_sender.send(((stakes.clone(), data_shreds.clone()), batch_info.clone()))?;
         blockstore_sender.send((data_shreds.clone(), batch_info.clone()))?;
-        let coding_shreds = shredder.data_shreds_to_coding_shreds(&data_shreds[0..last_data_shred]);
+        let coding_shreds = merkle_coding_shreds.unwrap_or_else(|| {
+            shredder.data_shreds_to_coding_shreds(&data_shreds[0..last_data_shred])
+        });
         let coding_shreds = Arc::new(coding_shreds);
         socket_sender.send(((stakes, coding_shreds.clone()), batch_info.clone()))?;
         blockstore_sender.send((coding_shreds, batch_info))?;
@@ -382,6 +429,7 @@ impl StandardBroadcastRun {
 impl BroadcastRun for StandardBroadcastRun {
     fn run(
         &mut self,
+        keypair: &Arc<Keypair>,
         blockstore: &Arc<Blockstore>,
         receiver: &Receiver<WorkingBankEntry>,
         socket_sender: &Sender<(TransmitShreds, Option<BroadcastShredBatchInfo>)>,
@@ -389,6 +437,7 @@ impl BroadcastRun for StandardBroadcastRun {
     ) -> Result<()> {
         let receive_results = broadcast_utils::recv_slot_entries(receiver)?;
         self.process_receive_results(
+            keypair,
             blockstore,
             socket_sender,
             blockstore_sender,
@@ -420,10 +469,12 @@ mod test {
     use crate::cluster_info::{ClusterInfo, Node};
     use solana_ledger::genesis_utils::create_genesis_config;
     use solana_ledger::{
-        blockstore::Blockstore, entry::create_ticks, get_tmp_ledger_path,
-        shred::max_ticks_per_n_shreds,
+        blockstore::Blockstore,
+        entry::create_ticks,
+        get_tmp_ledger_path,
+        shred::{max_ticks_per_n_shreds, SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD},
     };
-    use solana_runtime::bank::Bank;
+    use solana_runtime::{bank::Bank, feature_set::FeatureSet};
     use solana_sdk::{
         genesis_config::GenesisConfig,
         signature::{Keypair, Signer},
@@ -452,7 +503,10 @@ mod test {
         let cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(leader_info.info));
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let mut genesis_config = create_genesis_config(10_000).genesis_config;
-        genesis_config.ticks_per_slot = max_ticks_per_n_shreds(num_shreds_per_slot, None) + 1;
+        // Development genesis activates all features, merkle shreds included
+        genesis_config.ticks_per_slot =
+            max_ticks_per_n_shreds(num_shreds_per_slot, Some(SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD))
+                + 1;
         let bank0 = Arc::new(Bank::new(&genesis_config));
         (
             blockstore,
@@ -467,7 +521,7 @@ mod test {
     #[test]
     fn test_interrupted_slot_last_shred() {
         let keypair = Arc::new(Keypair::new());
-        let mut run = StandardBroadcastRun::new(keypair.clone(), 0);
+        let mut run = StandardBroadcastRun::new(0);
 
         // Set up the slot to be interrupted
         let next_shred_index = 10;
@@ -485,7 +539,7 @@ mod test {
 
         // Slot 2 interrupted slot 1
         let shred = run
-            .check_for_interrupted_slot(0)
+            .check_for_interrupted_slot(&keypair, 0)
             .expect("Expected a shred that signals an interrupt");
 
         // Validate the shred
@@ -513,9 +567,15 @@ mod test {
         };
 
         // Step 1: Make an incomplete transmission for slot 0
-        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair.clone(), 0);
+        let mut standard_broadcast_run = StandardBroadcastRun::new(0);
         standard_broadcast_run
-            .test_process_receive_results(&cluster_info, &socket, &blockstore, receive_results)
+            .test_process_receive_results(
+                &leader_keypair,
+                &cluster_info,
+                &socket,
+                &blockstore,
+                receive_results,
+            )
             .unwrap();
         let unfinished_slot = standard_broadcast_run.unfinished_slot.as_ref().unwrap();
         assert_eq!(unfinished_slot.next_shred_index as u64, num_shreds_per_slot);
@@ -562,7 +622,7 @@ mod test {
         let num_shreds = 1;
         assert!(num_shreds < num_shreds_per_slot);
         let ticks1 = create_ticks(
-            max_ticks_per_n_shreds(num_shreds, None),
+            max_ticks_per_n_shreds(num_shreds, Some(SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD)),
             0,
             genesis_config.hash(),
         );
@@ -573,7 +633,13 @@ mod test {
             last_tick_height: (ticks1.len() - 1) as u64,
         };
         standard_broadcast_run
-            .test_process_receive_results(&cluster_info, &socket, &blockstore, receive_results)
+            .test_process_receive_results(
+                &leader_keypair,
+                &cluster_info,
+                &socket,
+                &blockstore,
+                receive_results,
+            )
             .unwrap();
         let unfinished_slot = standard_broadcast_run.unfinished_slot.as_ref().unwrap();
 
@@ -627,10 +693,60 @@ mod test {
             last_tick_height: ticks.len() as u64,
         };
 
-        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair, 0);
+        let mut standard_broadcast_run = StandardBroadcastRun::new(0);
         standard_broadcast_run
-            .test_process_receive_results(&cluster_info, &socket, &blockstore, receive_results)
+            .test_process_receive_results(
+                &leader_keypair,
+                &cluster_info,
+                &socket,
+                &blockstore,
+                receive_results,
+            )
             .unwrap();
         assert!(standard_broadcast_run.unfinished_slot.is_none())
     }
+
+    #[test]
+    fn test_slot_finish_shred_variants() {
+        for merkle_shreds in &[false, true] {
+            // Setup
+            let num_shreds_per_slot = 2;
+            let (blockstore, genesis_config, cluster_info, bank0, leader_keypair, socket) =
+                setup(num_shreds_per_slot);
+            let mut bank0 = Arc::try_unwrap(bank0).unwrap();
+            bank0.feature_set = Arc::new(if *merkle_shreds {
+                FeatureSet::all_enabled()
+            } else {
+                FeatureSet::default()
+            });
+
+            // Insert complete slot of ticks needed to finish the slot
+            let ticks = create_ticks(genesis_config.ticks_per_slot, 0, genesis_config.hash());
+            let receive_results = ReceiveResults {
+                entries: ticks.clone(),
+                time_elapsed: Duration::new(3, 0),
+                bank: Arc::new(bank0),
+                last_tick_height: ticks.len() as u64,
+            };
+
+            let mut standard_broadcast_run = StandardBroadcastRun::new(0);
+            standard_broadcast_run
+                .test_process_receive_results(
+                    &leader_keypair,
+                    &cluster_info,
+                    &socket,
+                    &blockstore,
+                    receive_results,
+                )
+                .unwrap();
+            assert!(standard_broadcast_run.unfinished_slot.is_none());
+            assert!(blockstore.is_full(0));
+            let shreds = blockstore.get_data_shreds_for_slot(0, 0).unwrap();
+            assert!(!shreds.is_empty());
+            assert!(shreds.iter().all(|shred| {
+                shred.is_merkle() == *merkle_shreds && shred.verify(&leader_keypair.pubkey())
+            }));
+            assert_eq!(blockstore.get_slot_entries(0, 0).unwrap(), ticks);
+        }
+    }
 }
@@ -43,6 +43,7 @@ impl CacheBlockTimeService {
                     }
                     Ok(bank) => {
                         let mut cache_block_time_timer = Measure::start("cache_block_time_timer");
+                        Self::cache_blockhash(&bank, &blockstore);
                         Self::cache_block_time(bank, &blockstore);
                         cache_block_time_timer.stop();
                         if cache_block_time_timer.as_ms() > CACHE_BLOCK_TIME_WARNING_MS {
@@ -59,6 +60,13 @@ impl CacheBlockTimeService {
         Self { thread_hdl }
     }
 
+    // The last blockhash of a frozen bank is the blockhash of its block
+    fn cache_blockhash(bank: &Bank, blockstore: &Blockstore) {
+        if let Err(e) = blockstore.cache_blockhash(bank.slot(), &bank.last_blockhash()) {
+            error!("cache_blockhash failed: slot {:?} {:?}", bank.slot(), e);
+        }
+    }
+
     fn cache_block_time(bank: Arc<Bank>, blockstore: &Arc<Blockstore>) {
         if bank
             .feature_set
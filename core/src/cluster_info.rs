@@ -18,17 +18,18 @@ use crate::{
     crds_gossip_error::CrdsGossipError,
     crds_gossip_pull::{CrdsFilter, ProcessPullStats, CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS},
     crds_value::{
-        self, CrdsData, CrdsValue, CrdsValueLabel, EpochSlotsIndex, LowestSlot, SnapshotHash,
-        Version, Vote, MAX_WALLCLOCK,
+        self, CrdsData, CrdsValue, CrdsValueLabel, DuplicateShredIndex, EpochSlotsIndex,
+        LowestSlot, NodeInstance, SnapshotHash, Version, Vote, MAX_DUPLICATE_SHREDS, MAX_WALLCLOCK,
     },
     data_budget::DataBudget,
+    duplicate_shred::{self, DuplicateShred},
     epoch_slots::EpochSlots,
     result::{Error, Result},
     weighted_shuffle::weighted_shuffle,
 };
 
 use rand::distributions::{Distribution, WeightedIndex};
-use rand::SeedableRng;
+use rand::{thread_rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use solana_sdk::sanitize::{Sanitize, SanitizeError};
 
@@ -38,12 +39,12 @@ use itertools::Itertools;
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
 use rayon::{ThreadPool, ThreadPoolBuilder};
-use solana_ledger::staking_utils;
+use solana_ledger::{shred::Shred, staking_utils};
 use solana_measure::measure::Measure;
 use solana_measure::thread_mem_usage;
 use solana_metrics::{datapoint_debug, inc_new_counter_debug, inc_new_counter_error};
 use solana_net_utils::{
-    bind_common, bind_common_in_range, bind_in_range, find_available_port_in_range,
+    bind_common, bind_common_in_range, bind_in_range, bind_to, find_available_port_in_range,
     multi_bind_in_range, PortRange,
 };
 use solana_perf::packet::{
@@ -61,6 +62,7 @@ use solana_sdk::{
     timing::timestamp,
     transaction::Transaction,
 };
+use solana_streamer::quic::QUIC_PORT_OFFSET;
 use solana_streamer::sendmmsg::multicast;
 use solana_streamer::streamer::{PacketReceiver, PacketSender};
 use std::{
@@ -77,7 +79,7 @@ use std::{
 };
 
 pub const VALIDATOR_PORT_RANGE: PortRange = (8000, 10_000);
-pub const MINIMUM_VALIDATOR_PORT_RANGE_WIDTH: u16 = 10; // VALIDATOR_PORT_RANGE must be at least this wide
+pub const MINIMUM_VALIDATOR_PORT_RANGE_WIDTH: u16 = 11; // VALIDATOR_PORT_RANGE must be at least this wide
 
 /// The Data plane fanout size, also used as the neighborhood size
 pub const DATA_PLANE_FANOUT: usize = 200;
@@ -97,6 +99,9 @@ const MAX_GOSSIP_TRAFFIC: usize = 128_000_000 / PACKET_DATA_SIZE;
 
 /// Keep the number of snapshot hashes a node publishes under MAX_PROTOCOL_PAYLOAD_SIZE
 pub const MAX_SNAPSHOT_HASHES: usize = 16;
+/// Maximum serialized size of a DuplicateShred, leaving room for the CrdsValue holding it
+/// within MAX_PROTOCOL_PAYLOAD_SIZE
+const DUPLICATE_SHRED_MAX_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - 512;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ClusterInfoError {
@@ -104,6 +109,7 @@ pub enum ClusterInfoError {
     NoLeader,
     BadContactInfo,
     BadGossipAddress,
+    DuplicateNodeInstance,
 }
 
 struct GossipWriteLock<'a> {
@@ -246,15 +252,16 @@ pub struct ClusterInfo {
     /// The network
     pub gossip: RwLock<CrdsGossip>,
     /// set the keypair that will be used to sign crds values generated. It is unset only in tests.
-    pub(crate) keypair: Arc<Keypair>,
+    keypair: RwLock<Arc<Keypair>>,
     /// The network entrypoint
     entrypoint: RwLock<Option<ContactInfo>>,
     outbound_budget: DataBudget,
     my_contact_info: RwLock<ContactInfo>,
-    id: Pubkey,
     stats: GossipStats,
     socket: UdpSocket,
     local_message_pending_push_queue: RwLock<Vec<(CrdsValue, u64)>>,
+    /// Random token identifying this running instance of the node
+    instance: RwLock<NodeInstance>,
 }
 
 impl Default for ClusterInfo {
@@ -455,14 +462,14 @@ impl ClusterInfo {
         let id = contact_info.id;
         let me = Self {
             gossip: RwLock::new(CrdsGossip::default()),
-            keypair,
+            keypair: RwLock::new(keypair),
             entrypoint: RwLock::new(None),
             outbound_budget: DataBudget::default(),
             my_contact_info: RwLock::new(contact_info),
-            id,
             stats: GossipStats::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             local_message_pending_push_queue: RwLock::new(vec![]),
+            instance: RwLock::new(NodeInstance::new(&mut thread_rng(), id, timestamp())),
         };
         {
             let mut gossip = me.gossip.write().unwrap();
@@ -482,11 +489,10 @@ impl ClusterInfo {
         my_contact_info.id = *new_id;
         ClusterInfo {
             gossip: RwLock::new(gossip),
-            keypair: self.keypair.clone(),
+            keypair: RwLock::new(self.keypair().clone()),
             entrypoint: RwLock::new(self.entrypoint.read().unwrap().clone()),
             outbound_budget: self.outbound_budget.clone_non_atomic(),
             my_contact_info: RwLock::new(my_contact_info),
-            id: *new_id,
             stats: GossipStats::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             local_message_pending_push_queue: RwLock::new(
@@ -495,9 +501,28 @@ impl ClusterInfo {
                     .unwrap()
                     .clone(),
             ),
+            instance: RwLock::new(NodeInstance::new(&mut thread_rng(), *new_id, timestamp())),
         }
     }
 
+    /// Switches the identity of this node to `new_keypair`, re-advertising
+    /// the contact info and a fresh node instance under the new pubkey.
+    pub fn set_keypair(&self, new_keypair: Arc<Keypair>) {
+        let id = new_keypair.pubkey();
+        *self.instance.write().unwrap() = NodeInstance::new(&mut thread_rng(), id, timestamp());
+        *self.keypair.write().unwrap() = new_keypair;
+        self.my_contact_info.write().unwrap().id = id;
+        self.gossip.write().unwrap().set_self(&id);
+        self.insert_self();
+        let message = CrdsData::Version(Version::new(id));
+        self.push_message(CrdsValue::new_signed(message, &self.keypair()));
+        self.push_self(&HashMap::new(), None);
+    }
+
+    pub fn keypair(&self) -> RwLockReadGuard<Arc<Keypair>> {
+        self.keypair.read().unwrap()
+    }
+
     pub fn update_contact_info<F>(&self, modify: F)
     where
         F: FnOnce(&mut ContactInfo),
@@ -515,8 +540,13 @@ impl ClusterInfo {
     ) {
         let now = timestamp();
         self.my_contact_info.write().unwrap().wallclock = now;
-        let entry =
-            CrdsValue::new_signed(CrdsData::ContactInfo(self.my_contact_info()), &self.keypair);
+        let entries: Vec<_> = vec![
+            CrdsData::ContactInfo(self.my_contact_info()),
+            CrdsData::NodeInstance(self.instance.read().unwrap().with_wallclock(now)),
+        ]
+        .into_iter()
+        .map(|data| (CrdsValue::new_signed(data, &self.keypair()), now))
+        .collect();
         self.gossip
             .write()
             .unwrap()
@@ -524,12 +554,12 @@ impl ClusterInfo {
         self.local_message_pending_push_queue
             .write()
             .unwrap()
-            .push((entry, now));
+            .extend(entries);
     }
 
     // TODO kill insert_info, only used by tests
     pub fn insert_info(&self, contact_info: ContactInfo) {
-        let value = CrdsValue::new_signed(CrdsData::ContactInfo(contact_info), &self.keypair);
+        let value = CrdsValue::new_signed(CrdsData::ContactInfo(contact_info), &self.keypair());
         let _ = self.gossip.write().unwrap().crds.insert(value, timestamp());
     }
 
@@ -538,7 +568,7 @@ impl ClusterInfo {
     }
 
     pub fn id(&self) -> Pubkey {
-        self.id
+        self.my_contact_info.read().unwrap().id
     }
 
     pub fn lookup_contact_info<F, Y>(&self, id: &Pubkey, map: F) -> Option<Y>
@@ -751,7 +781,7 @@ impl ClusterInfo {
         if min > last {
             let entry = CrdsValue::new_signed(
                 CrdsData::LowestSlot(0, LowestSlot::new(id, min, now)),
-                &self.keypair,
+                &self.keypair(),
             );
             self.local_message_pending_push_queue
                 .write()
@@ -808,7 +838,7 @@ impl ClusterInfo {
             };
             let n = slots.fill(&update[num..], now);
             if n > 0 {
-                let entry = CrdsValue::new_signed(CrdsData::EpochSlots(ix, slots), &self.keypair);
+                let entry = CrdsValue::new_signed(CrdsData::EpochSlots(ix, slots), &self.keypair());
                 self.local_message_pending_push_queue
                     .write()
                     .unwrap()
@@ -856,7 +886,7 @@ impl ClusterInfo {
         }
 
         let message = CrdsData::AccountsHashes(SnapshotHash::new(self.id(), accounts_hashes));
-        self.push_message(CrdsValue::new_signed(message, &self.keypair));
+        self.push_message(CrdsValue::new_signed(message, &self.keypair()));
     }
 
     pub fn push_snapshot_hashes(&self, snapshot_hashes: Vec<(Slot, Hash)>) {
@@ -869,7 +899,55 @@ impl ClusterInfo {
         }
 
         let message = CrdsData::SnapshotHashes(SnapshotHash::new(self.id(), snapshot_hashes));
-        self.push_message(CrdsValue::new_signed(message, &self.keypair));
+        self.push_message(CrdsValue::new_signed(message, &self.keypair()));
+    }
+
+    /// Push the proof that the leader of `shred`'s slot also signed `other_payload` for the
+    /// same slot and index, split into as many DuplicateShred values as needed. `slot_leader`
+    /// looks up the leader of the slot.
+    pub fn push_duplicate_shred(
+        &self,
+        shred: &Shred,
+        other_payload: Vec<u8>,
+        slot_leader: impl FnOnce(Slot) -> Option<Pubkey>,
+    ) -> std::result::Result<(), duplicate_shred::Error> {
+        let now = timestamp();
+        let chunks = duplicate_shred::from_shred(
+            shred,
+            other_payload,
+            self.id(),
+            slot_leader,
+            now,
+            DUPLICATE_SHRED_MAX_PAYLOAD_SIZE,
+        )?;
+        let mut pending_push_queue = self.local_message_pending_push_queue.write().unwrap();
+        // Use the free indices first, then overwrite our oldest duplicate shreds
+        let mut used: Vec<(u64, DuplicateShredIndex)> = {
+            let gossip = self.gossip.read().unwrap();
+            (0..MAX_DUPLICATE_SHREDS)
+                .filter_map(|ix| {
+                    let value = gossip
+                        .crds
+                        .lookup(&CrdsValueLabel::DuplicateShred(ix, self.id()))?;
+                    Some((value.wallclock(), ix))
+                })
+                .collect()
+        };
+        used.extend(
+            pending_push_queue.iter().filter_map(|(value, wallclock)| {
+                Some((*wallclock, value.duplicate_shred_index()?))
+            }),
+        );
+        used.sort_unstable();
+        let used_indices: HashSet<_> = used.iter().map(|(_, ix)| *ix).collect();
+        let indices = (0..MAX_DUPLICATE_SHREDS)
+            .filter(|ix| !used_indices.contains(ix))
+            .chain(used.into_iter().map(|(_, ix)| ix).unique());
+        for (ix, chunk) in indices.zip(chunks) {
+            let entry = CrdsValue::new_signed(CrdsData::DuplicateShred(ix, chunk), &self.keypair());
+            pending_push_queue.push((entry, now));
+        }
+        Ok(())
     }
 
     pub fn push_vote(&self, tower_index: usize, vote: Transaction) {
@@ -883,7 +961,7 @@ impl ClusterInfo {
                 .collect();
             CrdsValue::compute_vote_index(tower_index, current_votes)
         };
-        let entry = CrdsValue::new_signed(CrdsData::Vote(vote_ix, vote), &self.keypair);
+        let entry = CrdsValue::new_signed(CrdsData::Vote(vote_ix, vote), &self.keypair());
         self.local_message_pending_push_queue
             .write()
             .unwrap()
@@ -1007,6 +1085,31 @@ impl ClusterInfo {
         (vec, max)
     }
 
+    /// Get the duplicate shred proof chunks inserted in the crds after `since`, and the latest
+    /// insert timestamp among them
+    pub fn get_duplicate_shreds_since(
+        &self,
+        since: Option<u64>,
+    ) -> (Vec<DuplicateShred>, Option<u64>) {
+        let vals: Vec<_> = self
+            .gossip
+            .read()
+            .unwrap()
+            .crds
+            .table
+            .values()
+            .filter(|x| {
+                since
+                    .map(|since| x.insert_timestamp > since)
+                    .unwrap_or(true)
+            })
+            .filter_map(|x| Some((x.value.duplicate_shred()?.clone(), x.insert_timestamp)))
+            .collect();
+        let max = vals.iter().map(|x| x.1).max().or(since);
+        let vec = vals.into_iter().map(|x| x.0).collect();
+        (vec, max)
+    }
+
     pub fn get_node_version(&self, pubkey: &Pubkey) -> Option<solana_version::Version> {
         let version = self
             .gossip
@@ -1402,8 +1505,10 @@ impl ClusterInfo {
     }
 
     fn insert_self(&self) {
-        let value =
-            CrdsValue::new_signed(CrdsData::ContactInfo(self.my_contact_info()), &self.keypair);
+        let value = CrdsValue::new_signed(
+            CrdsData::ContactInfo(self.my_contact_info()),
+            &self.keypair(),
+        );
         let _ = self.gossip.write().unwrap().crds.insert(value, timestamp());
     }
 
@@ -1717,7 +1822,7 @@ impl ClusterInfo {
                 let recycler = PacketsRecycler::default();
 
                 let message = CrdsData::Version(Version::new(self.id()));
-                self.push_message(CrdsValue::new_signed(message, &self.keypair));
+                self.push_message(CrdsValue::new_signed(message, &self.keypair()));
                 let mut generate_pull_requests = true;
                 loop {
                     let start = timestamp();
@@ -1781,7 +1886,7 @@ impl ClusterInfo {
         response_sender: &PacketSender,
         feature_set: Option<&FeatureSet>,
         epoch_time_ms: u64,
-    ) {
+    ) -> Result<()> {
         // iter over the packets, collect pulls separately and process everything else
         let allocated = thread_mem_usage::Allocatedp::default();
         let mut gossip_pull_data: Vec<PullData> = vec![];
@@ -1818,6 +1923,7 @@ impl ClusterInfo {
                     );
                 }
                 Protocol::PullResponse(from, data) => {
+                    self.check_duplicate_instance(&data)?;
                     let start = allocated.get();
                     let pull_entry = pull_responses.entry(from).or_insert_with(Vec::new);
                     pull_entry.extend(data);
@@ -1827,6 +1933,7 @@ impl ClusterInfo {
                     );
                 }
                 Protocol::PushMessage(from, data) => {
+                    self.check_duplicate_instance(&data)?;
                     let start = allocated.get();
                     let rsp = self.handle_push_message(recycler, &from, data, stakes);
                     if let Some(rsp) = rsp {
@@ -1882,6 +1989,17 @@ impl ClusterInfo {
                 let _ignore_disconnect = response_sender.send(rsp);
             }
         }
+        Ok(())
+    }
+
+    // Returns an error if the values include another running instance of
+    // this node which was started earlier, in which case this one should exit.
+    fn check_duplicate_instance(&self, values: &[CrdsValue]) -> Result<()> {
+        let instance = self.instance.read().unwrap();
+        if values.iter().any(|value| instance.check_duplicate(value)) {
+            return Err(Error::from(ClusterInfoError::DuplicateNodeInstance));
+        }
+        Ok(())
     }
 
     fn update_data_budget(&self, num_staked: usize) {
@@ -2032,7 +2150,7 @@ impl ClusterInfo {
         timeouts: &HashMap<Pubkey, u64>,
     ) -> (usize, usize, usize) {
         let len = crds_values.len();
-        trace!("PullResponse me: {} from: {} len={}", self.id, from, len);
+        trace!("PullResponse me: {} from: {} len={}", self.id(), from, len);
         let shred_version = self
             .lookup_contact_info(from, |ci| ci.shred_version)
             .unwrap_or(0);
@@ -2157,7 +2275,7 @@ impl ClusterInfo {
                         destination: from,
                         wallclock: timestamp(),
                     };
-                    prune_msg.sign(&self.keypair);
+                    prune_msg.sign(&self.keypair());
                     let rsp = Protocol::PruneMessage(self_id, prune_msg);
                     (ci.gossip, rsp)
                 })
@@ -2218,7 +2336,7 @@ impl ClusterInfo {
         stakes: HashMap<Pubkey, u64>,
         feature_set: Option<&FeatureSet>,
         epoch_time_ms: u64,
-    ) {
+    ) -> Result<()> {
         let mut timer = Measure::start("process_gossip_packets_time");
         let packets: Vec<_> = thread_pool.install(|| {
             requests
@@ -2233,7 +2351,7 @@ impl ClusterInfo {
                 })
                 .collect()
         });
-        self.handle_packets(
+        let result = self.handle_packets(
             recycler,
             &stakes,
             packets,
@@ -2244,6 +2362,7 @@ impl ClusterInfo {
         self.stats
             .process_gossip_packets_time
             .add_measure(&mut timer);
+        result
     }
 
     /// Process messages from the network
@@ -2295,7 +2414,7 @@ impl ClusterInfo {
             stakes,
             feature_set.as_deref(),
             epoch_time_ms,
-        );
+        )?;
 
         self.print_reset_stats(last_print);
 
@@ -2529,6 +2648,15 @@ impl ClusterInfo {
                         &thread_pool,
                         &mut last_print,
                     );
+                    if let Err(Error::ClusterInfoError(ClusterInfoError::DuplicateNodeInstance)) = e
+                    {
+                        error!(
+                            "duplicate running instances of the same validator node: {}, exiting",
+                            self.id()
+                        );
+                        exit.store(true, Ordering::Relaxed);
+                        return;
+                    }
                     if exit.load(Ordering::Relaxed) {
                         return;
                     }
@@ -2628,11 +2756,14 @@ pub struct Sockets {
     pub tvu: Vec<UdpSocket>,
     pub tvu_forwards: Vec<UdpSocket>,
     pub tpu: Vec<UdpSocket>,
+    /// Bound at the TPU port plus `QUIC_PORT_OFFSET`, if that port was free
+    pub tpu_quic: Option<UdpSocket>,
     pub tpu_forwards: Vec<UdpSocket>,
     pub broadcast: Vec<UdpSocket>,
     pub repair: UdpSocket,
     pub retransmit_sockets: Vec<UdpSocket>,
     pub serve_repair: UdpSocket,
+    pub ancestor_hashes_requests: UdpSocket,
 }
 
 #[derive(Debug)]
@@ -2649,6 +2780,10 @@ impl Node {
     pub fn new_localhost_with_pubkey(pubkey: &Pubkey) -> Self {
         let bind_ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let tpu = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tpu_quic = Self::bind_tpu_quic(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            tpu.local_addr().unwrap().port(),
+        );
         let (gossip_port, (gossip, ip_echo)) =
             bind_common_in_range(bind_ip_addr, (1024, 65535)).unwrap();
         let gossip_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), gossip_port);
@@ -2668,6 +2803,7 @@ impl Node {
         let broadcast = vec![UdpSocket::bind("0.0.0.0:0").unwrap()];
         let retransmit_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let serve_repair = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ancestor_hashes_requests = UdpSocket::bind("0.0.0.0:0").unwrap();
         let info = ContactInfo {
             id: *pubkey,
             gossip: gossip_addr,
@@ -2691,11 +2827,13 @@ impl Node {
                 tvu: vec![tvu],
                 tvu_forwards: vec![tvu_forwards],
                 tpu: vec![tpu],
+                tpu_quic,
                 tpu_forwards: vec![tpu_forwards],
                 broadcast,
                 repair,
                 retransmit_sockets: vec![retransmit_socket],
                 serve_repair,
+                ancestor_hashes_requests,
             },
         }
     }
@@ -2718,6 +2856,12 @@ impl Node {
     fn bind(bind_ip_addr: IpAddr, port_range: PortRange) -> (u16, UdpSocket) {
         bind_in_range(bind_ip_addr, port_range).expect("Failed to bind")
     }
+    fn bind_tpu_quic(bind_ip_addr: IpAddr, tpu_port: u16) -> Option<UdpSocket> {
+        let port = tpu_port.checked_add(QUIC_PORT_OFFSET)?;
+        bind_to(bind_ip_addr, port, false)
+            .map_err(|err| warn!("tpu_quic bind_to port {}: {}", port, err))
+            .ok()
+    }
 
     pub fn new_with_external_ip(
         pubkey: &Pubkey,
@@ -2736,6 +2880,7 @@ impl Node {
 
         let (tpu_port, tpu_sockets) =
             multi_bind_in_range(bind_ip_addr, port_range, 32).expect("tpu multi_bind");
+        let tpu_quic = Self::bind_tpu_quic(bind_ip_addr, tpu_port);
 
         let (tpu_forwards_port, tpu_forwards_sockets) =
             multi_bind_in_range(bind_ip_addr, port_range, 8).expect("tpu_forwards multi_bind");
@@ -2745,6 +2890,7 @@ impl Node {
 
         let (repair_port, repair) = Self::bind(bind_ip_addr, port_range);
         let (serve_repair_port, serve_repair) = Self::bind(bind_ip_addr, port_range);
+        let (_, ancestor_hashes_requests) = Self::bind(bind_ip_addr, port_range);
 
         let (_, broadcast) =
             multi_bind_in_range(bind_ip_addr, port_range, 4).expect("broadcast multi_bind");
@@ -2773,11 +2919,13 @@ impl Node {
                 tvu: tvu_sockets,
                 tvu_forwards: tvu_forwards_sockets,
                 tpu: tpu_sockets,
+                tpu_quic,
                 tpu_forwards: tpu_forwards_sockets,
                 broadcast,
                 repair,
                 retransmit_sockets,
                 serve_repair,
+                ancestor_hashes_requests,
                 ip_echo: Some(ip_echo),
             },
         }
@@ -2795,7 +2943,10 @@ pub fn stake_weight_peers<S: std::hash::BuildHasher>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crds_value::{CrdsValue, CrdsValueLabel, Vote as CrdsVote};
+    use crate::{
+        crds_value::{CrdsValue, CrdsValueLabel, Vote as CrdsVote},
+        duplicate_shred::tests::new_rand_shred,
+    };
     use rayon::prelude::*;
     use solana_perf::test_tx::test_tx;
     use solana_sdk::signature::{Keypair, Signer};
@@ -2932,6 +3083,73 @@ mod tests {
         assert_eq!(d.id, cluster_info.id());
     }
 
+    #[test]
+    fn test_check_duplicate_instance() {
+        let keypair = Arc::new(Keypair::new());
+        let start = timestamp();
+        let contact_info = ContactInfo::new_localhost(&keypair.pubkey(), start);
+        let cluster_info = ClusterInfo::new(contact_info, keypair.clone());
+        let now = timestamp();
+        let mut rng = thread_rng();
+        let new_instance = |pubkey, now| {
+            CrdsValue::new_signed(
+                CrdsData::NodeInstance(NodeInstance::new(&mut thread_rng(), pubkey, now)),
+                &keypair,
+            )
+        };
+        // Our own instance, other nodes and newer instances of this node are fine.
+        let values = vec![
+            CrdsValue::new_signed(
+                CrdsData::NodeInstance(
+                    cluster_info
+                        .instance
+                        .read()
+                        .unwrap()
+                        .with_wallclock(now + 1),
+                ),
+                &keypair,
+            ),
+            new_instance(solana_sdk::pubkey::new_rand(), start - 1),
+            new_instance(keypair.pubkey(), now + 1),
+            CrdsValue::new_rand(&mut rng),
+        ];
+        assert!(cluster_info.check_duplicate_instance(&values).is_ok());
+        // An instance of this node started earlier means this one should exit.
+        let values = vec![new_instance(keypair.pubkey(), start - 1)];
+        assert!(matches!(
+            cluster_info.check_duplicate_instance(&values),
+            Err(Error::ClusterInfoError(
+                ClusterInfoError::DuplicateNodeInstance
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_set_keypair() {
+        let keypair = Arc::new(Keypair::new());
+        let contact_info = ContactInfo::new_localhost(&keypair.pubkey(), timestamp());
+        let cluster_info = ClusterInfo::new(contact_info, keypair.clone());
+        let new_keypair = Arc::new(Keypair::new());
+        let new_id = new_keypair.pubkey();
+        cluster_info.set_keypair(new_keypair);
+        assert_eq!(cluster_info.id(), new_id);
+        assert_eq!(cluster_info.keypair().pubkey(), new_id);
+        assert_eq!(cluster_info.my_contact_info().id, new_id);
+        assert_eq!(cluster_info.gossip.read().unwrap().id, new_id);
+        assert!(cluster_info
+            .lookup_contact_info(&new_id, |ci| ci.clone())
+            .is_some());
+        // The instance is re-issued under the new identity, so the previous
+        // identity's instance is not reported as a duplicate.
+        let old_instance = CrdsValue::new_signed(
+            CrdsData::NodeInstance(NodeInstance::new(&mut thread_rng(), keypair.pubkey(), 0)),
+            &keypair,
+        );
+        assert!(cluster_info
+            .check_duplicate_instance(&[old_instance])
+            .is_ok());
+    }
+
     #[test]
     fn insert_info_test() {
         let d = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
@@ -3005,9 +3223,16 @@ mod tests {
     fn check_node_sockets(node: &Node, ip: IpAddr, range: (u16, u16)) {
         check_socket(&node.sockets.gossip, ip, range);
         check_socket(&node.sockets.repair, ip, range);
+        check_socket(&node.sockets.ancestor_hashes_requests, ip, range);
 
         check_sockets(&node.sockets.tvu, ip, range);
         check_sockets(&node.sockets.tpu, ip, range);
+        if let Some(tpu_quic) = &node.sockets.tpu_quic {
+            assert_eq!(
+                tpu_quic.local_addr().unwrap().port(),
+                node.info.tpu.port() + QUIC_PORT_OFFSET
+            );
+        }
     }
 
     #[test]
@@ -3027,7 +3252,10 @@ mod tests {
     fn new_with_external_ip_test_gossip() {
         // Can't use VALIDATOR_PORT_RANGE because if this test runs in parallel with others, the
         // port returned by `bind_in_range()` might be snatched up before `Node::new_with_external_ip()` runs
-        let port_range = (VALIDATOR_PORT_RANGE.1 + 10, VALIDATOR_PORT_RANGE.1 + 20);
+        let port_range = (
+            VALIDATOR_PORT_RANGE.1 + MINIMUM_VALIDATOR_PORT_RANGE_WIDTH,
+            VALIDATOR_PORT_RANGE.1 + 2 * MINIMUM_VALIDATOR_PORT_RANGE_WIDTH,
+        );
 
         let ip = IpAddr::V4(Ipv4Addr::from(0));
         let port = bind_in_range(ip, port_range).expect("Failed to bind").0;
@@ -3041,6 +3269,7 @@ mod tests {
         check_node_sockets(&node, ip, port_range);
 
         assert_eq!(node.sockets.gossip.local_addr().unwrap().port(), port);
+        assert!(node.sockets.tpu_quic.is_some());
     }
 
     //test that all cluster_info objects only generate signed messages
@@ -3293,6 +3522,61 @@ mod tests {
         assert_eq!(since2, since);
     }
 
+    #[test]
+    fn test_push_duplicate_shred() {
+        let keypair = Arc::new(Keypair::new());
+        let cluster_info = ClusterInfo::new(
+            ContactInfo::new_localhost(&keypair.pubkey(), 0),
+            keypair.clone(),
+        );
+        let leader = Arc::new(Keypair::new());
+        let (slot, index) = (37, 11);
+        let shred1 = new_rand_shred(slot, index, &leader);
+        let shred2 = new_rand_shred(slot, index, &leader);
+        cluster_info
+            .push_duplicate_shred(&shred1, shred2.payload.clone(), |_| Some(leader.pubkey()))
+            .unwrap();
+        assert!(cluster_info
+            .push_duplicate_shred(&shred1, shred2.payload.clone(), |_| Some(keypair.pubkey()))
+            .is_err());
+
+        // Every chunk fits in a gossip message
+        for (value, _) in cluster_info
+            .local_message_pending_push_queue
+            .read()
+            .unwrap()
+            .iter()
+        {
+            assert!(value.size() <= MAX_PROTOCOL_PAYLOAD_SIZE);
+        }
+        cluster_info.flush_push_queue();
+
+        let (chunks, since) = cluster_info.get_duplicate_shreds_since(None);
+        assert!(since.is_some());
+        assert!(chunks.iter().all(|chunk| chunk.from == keypair.pubkey()));
+        let (shred3, shred4) =
+            duplicate_shred::into_shreds(chunks, |_| Some(leader.pubkey())).unwrap();
+        assert_eq!(shred1, shred3);
+        assert_eq!(shred2, shred4);
+
+        // A second proof uses the indices still free
+        sleep(Duration::from_millis(2));
+        let shred5 = new_rand_shred(slot + 1, index, &leader);
+        let shred6 = new_rand_shred(slot + 1, index, &leader);
+        cluster_info
+            .push_duplicate_shred(&shred5, shred6.payload, |_| Some(leader.pubkey()))
+            .unwrap();
+        cluster_info.flush_push_queue();
+        let (chunks, since2) = cluster_info.get_duplicate_shreds_since(since);
+        assert!(chunks.iter().all(|chunk| chunk.slot == slot + 1));
+        let (chunks, _) = cluster_info.get_duplicate_shreds_since(None);
+        assert_eq!(
+            chunks.iter().filter(|chunk| chunk.slot == slot).count(),
+            chunks.iter().filter(|chunk| chunk.slot == slot + 1).count()
+        );
+        assert!(since2 > since);
+    }
+
     #[test]
     fn test_append_entrypoint_to_pulls() {
         let thread_pool = ThreadPoolBuilder::new().build().unwrap();
@@ -1,6 +1,7 @@
 use crate::{
     progress_map::{LockoutIntervals, ProgressMap},
     pubkey_references::PubkeyReferences,
+    tower_storage::TowerStorage,
 };
 use chrono::prelude::*;
 use solana_ledger::{ancestor_iterator::AncestorIterator, blockstore::Blockstore, blockstore_db};
@@ -22,10 +23,7 @@ use solana_vote_program::{
 use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
-    fs::{self, File},
-    io::BufReader,
     ops::Bound::{Included, Unbounded},
-    path::{Path, PathBuf},
     sync::Arc,
 };
 use thiserror::Error;
@@ -99,10 +97,6 @@ pub struct Tower {
     last_vote: Vote,
     last_timestamp: BlockTimestamp,
     #[serde(skip)]
-    path: PathBuf,
-    #[serde(skip)]
-    tmp_path: PathBuf, // used before atomic fs::rename()
-    #[serde(skip)]
     // Restored last voted slot which cannot be found in SlotHistory at replayed root
     // (This is a special field for slashing-free validator restart with edge cases).
     // This could be emptied after some time; but left intact indefinitely for easier
@@ -123,8 +117,6 @@ impl Default for Tower {
             lockouts: VoteState::default(),
             last_vote: Vote::default(),
             last_timestamp: BlockTimestamp::default(),
-            path: PathBuf::default(),
-            tmp_path: PathBuf::default(),
             stray_restored_slot: Option::default(),
             last_switch_threshold_check: Option::default(),
         };
@@ -140,14 +132,9 @@ impl Tower {
         vote_account_pubkey: &Pubkey,
         root: Slot,
         bank: &Bank,
-        path: &Path,
     ) -> Self {
-        let path = Self::get_filename(&path, node_pubkey);
-        let tmp_path = Self::get_tmp_filename(&path);
         let mut tower = Self {
             node_pubkey: *node_pubkey,
-            path,
-            tmp_path,
             ..Tower::default()
         };
         tower.initialize_lockouts_from_bank(vote_account_pubkey, root, bank);
@@ -174,7 +161,6 @@ impl Tower {
 
     pub fn new_from_bankforks(
         bank_forks: &BankForks,
-        ledger_path: &Path,
         my_pubkey: &Pubkey,
         vote_account: &Pubkey,
     ) -> Self {
@@ -197,13 +183,7 @@ impl Tower {
             Tower::find_heaviest_bank(&bank_forks, &my_pubkey).unwrap_or_else(|| root_bank.clone())
         };
 
-        Self::new(
-            &my_pubkey,
-            &vote_account,
-            root,
-            &heaviest_bank,
-            &ledger_path,
-        )
+        Self::new(&my_pubkey, &vote_account, root, &heaviest_bank)
     }
 
     pub(crate) fn collect_vote_lockouts<F>(
@@ -1028,16 +1008,11 @@ impl Tower {
         self.lockouts.root_slot = Some(root);
     }
 
-    pub fn get_filename(path: &Path, node_pubkey: &Pubkey) -> PathBuf {
-        path.join(format!("tower-{}", node_pubkey))
-            .with_extension("bin")
-    }
-
-    pub fn get_tmp_filename(path: &Path) -> PathBuf {
-        path.with_extension("bin.new")
-    }
-
-    pub fn save(&self, node_keypair: &Arc<Keypair>) -> Result<()> {
+    pub fn save(
+        &self,
+        tower_storage: &dyn TowerStorage,
+        node_keypair: &Arc<Keypair>,
+    ) -> Result<()> {
         let mut measure = Measure::start("tower_save-ms");
 
         if self.node_pubkey != node_keypair.pubkey() {
@@ -1048,18 +1023,9 @@ impl Tower {
             )));
         }
 
-        let filename = &self.path;
-        let new_filename = &self.tmp_path;
-        {
-            // overwrite anything if exists
-            let mut file = File::create(&new_filename)?;
-            let saved_tower = SavedTower::new(self, node_keypair)?;
-            bincode::serialize_into(&mut file, &saved_tower)?;
-            // file.sync_all() hurts performance; pipeline sync-ing and submitting votes to the cluster!
-        }
+        let saved_tower = SavedTower::new(self, node_keypair)?;
+        tower_storage.store(&self.node_pubkey, &saved_tower)?;
         trace!("persisted votes: {:?}", self.voted_slots());
-        fs::rename(&new_filename, &filename)?;
-        // self.path.parent().sync_all() hurts performance same as the above sync
 
         measure.stop();
         inc_new_counter_info!("tower_save-ms", measure.as_ms() as usize);
@@ -1067,22 +1033,12 @@ impl Tower {
         Ok(())
     }
 
-    pub fn restore(path: &Path, node_pubkey: &Pubkey) -> Result<Self> {
-        let filename = Self::get_filename(path, node_pubkey);
-
-        // Ensure to create parent dir here, because restore() precedes save() always
-        fs::create_dir_all(&filename.parent().unwrap())?;
-
-        let file = File::open(&filename)?;
-        let mut stream = BufReader::new(file);
-
-        let saved_tower: SavedTower = bincode::deserialize_from(&mut stream)?;
+    pub fn restore(tower_storage: &dyn TowerStorage, node_pubkey: &Pubkey) -> Result<Self> {
+        let saved_tower = tower_storage.load(node_pubkey)?;
         if !saved_tower.verify(node_pubkey) {
             return Err(TowerError::InvalidSignature);
         }
-        let mut tower = saved_tower.deserialize()?;
-        tower.path = filename;
-        tower.tmp_path = Self::get_tmp_filename(&tower.path);
+        let tower = saved_tower.deserialize()?;
 
         // check that the tower actually belongs to this node
         if &tower.node_pubkey != node_pubkey {
@@ -1109,6 +1065,9 @@ pub enum TowerError {
     #[error("The tower does not match this validator: {0}")]
     WrongTower(String),
 
+    #[error("Tower storage error: {0}")]
+    StorageError(String),
+
     #[error(
         "The tower is too old: \
         newest slot in tower ({0}) << oldest slot in available history ({1})"
@@ -1194,6 +1153,7 @@ pub mod test {
         heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice,
         progress_map::ForkProgress,
         replay_stage::{HeaviestForkFailures, ReplayStage},
+        tower_storage::FileTowerStorage,
     };
     use solana_ledger::{blockstore::make_slot_entries, get_tmp_ledger_path};
     use solana_runtime::{
@@ -1214,6 +1174,7 @@ pub mod test {
         collections::HashMap,
         fs::{remove_file, OpenOptions},
         io::{Read, Seek, SeekFrom, Write},
+        path::PathBuf,
         rc::Rc,
         sync::RwLock,
     };
@@ -2372,17 +2333,16 @@ pub mod test {
     {
         let dir = TempDir::new().unwrap();
         let identity_keypair = Arc::new(Keypair::new());
+        let tower_storage = FileTowerStorage::new(dir.path().to_path_buf());
 
         // Use values that will not match the default derived from BankForks
         let mut tower = Tower::new_for_tests(10, 0.9);
-        tower.path = Tower::get_filename(&dir.path().to_path_buf(), &identity_keypair.pubkey());
-        tower.tmp_path = Tower::get_tmp_filename(&tower.path);
 
         modify_original(&mut tower, &identity_keypair.pubkey());
 
-        tower.save(&identity_keypair).unwrap();
-        modify_serialized(&tower.path);
-        let loaded = Tower::restore(&dir.path(), &identity_keypair.pubkey());
+        tower.save(&tower_storage, &identity_keypair).unwrap();
+        modify_serialized(&tower_storage.filename(&identity_keypair.pubkey()));
+        let loaded = Tower::restore(&tower_storage, &identity_keypair.pubkey());
 
         (tower, loaded)
     }
@@ -2591,7 +2551,10 @@ pub mod test {
         let identity_keypair = Arc::new(Keypair::new());
         let tower = Tower::new_with_key(&Pubkey::default());
         assert_matches!(
-            tower.save(&identity_keypair),
+            tower.save(
+                &FileTowerStorage::new(PathBuf::default()),
+                &identity_keypair
+            ),
             Err(TowerError::WrongTower(_))
         )
     }
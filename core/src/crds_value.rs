@@ -1,7 +1,9 @@
 use crate::contact_info::ContactInfo;
 use crate::deprecated;
+use crate::duplicate_shred::DuplicateShred;
 use crate::epoch_slots::EpochSlots;
 use bincode::{serialize, serialized_size};
+use rand::{CryptoRng, Rng};
 use solana_sdk::sanitize::{Sanitize, SanitizeError};
 use solana_sdk::timing::timestamp;
 use solana_sdk::{
@@ -13,6 +15,7 @@ use solana_sdk::{
 };
 use std::{
     borrow::{Borrow, Cow},
+    cmp,
     collections::{BTreeSet, HashSet},
     fmt,
 };
@@ -26,6 +29,9 @@ pub const MAX_VOTES: VoteIndex = 32;
 pub type EpochSlotsIndex = u8;
 pub const MAX_EPOCH_SLOTS: EpochSlotsIndex = 255;
 
+pub type DuplicateShredIndex = u16;
+pub const MAX_DUPLICATE_SHREDS: DuplicateShredIndex = 512;
+
 /// CrdsValue that is replicated across the cluster
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
 pub struct CrdsValue {
@@ -77,6 +83,8 @@ pub enum CrdsData {
     EpochSlots(EpochSlotsIndex, EpochSlots),
     LegacyVersion(LegacyVersion),
     Version(Version),
+    DuplicateShred(DuplicateShredIndex, DuplicateShred),
+    NodeInstance(NodeInstance),
 }
 
 impl Sanitize for CrdsData {
@@ -105,6 +113,14 @@ impl Sanitize for CrdsData {
             }
             CrdsData::LegacyVersion(version) => version.sanitize(),
             CrdsData::Version(version) => version.sanitize(),
+            CrdsData::DuplicateShred(ix, shred) => {
+                if *ix >= MAX_DUPLICATE_SHREDS {
+                    Err(SanitizeError::ValueOutOfBounds)
+                } else {
+                    shred.sanitize()
+                }
+            }
+            CrdsData::NodeInstance(node) => node.sanitize(),
         }
     }
 }
@@ -254,6 +270,59 @@ impl Version {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
+pub struct NodeInstance {
+    from: Pubkey,
+    wallclock: u64,
+    timestamp: u64, // Timestamp when the instance was created.
+    token: u64,     // Randomly generated value at node instantiation.
+}
+
+impl NodeInstance {
+    pub fn new<R>(rng: &mut R, from: Pubkey, now: u64) -> Self
+    where
+        R: Rng + CryptoRng,
+    {
+        Self {
+            from,
+            wallclock: now,
+            timestamp: now,
+            token: rng.gen(),
+        }
+    }
+
+    /// Clones the value with an updated wallclock.
+    pub fn with_wallclock(&self, wallclock: u64) -> Self {
+        Self { wallclock, ..*self }
+    }
+
+    /// Returns true if the crds value is another running instance of this
+    /// node which was started before this one, in which case this instance
+    /// should shut down. Ties on the startup timestamp are broken by token.
+    pub fn check_duplicate(&self, other: &CrdsValue) -> bool {
+        match &other.data {
+            CrdsData::NodeInstance(other) => {
+                let started_before = match other.timestamp.cmp(&self.timestamp) {
+                    cmp::Ordering::Less => true,
+                    cmp::Ordering::Greater => false,
+                    cmp::Ordering::Equal => other.token < self.token,
+                };
+                self.from == other.from && self.token != other.token && started_before
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Sanitize for NodeInstance {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        if self.wallclock >= MAX_WALLCLOCK {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        self.from.sanitize()
+    }
+}
+
 /// Type of the replicated value
 /// These are labels for values in a record that is associated with `Pubkey`
 #[derive(PartialEq, Hash, Eq, Clone, Debug)]
@@ -266,6 +335,8 @@ pub enum CrdsValueLabel {
     AccountsHashes(Pubkey),
     LegacyVersion(Pubkey),
     Version(Pubkey),
+    DuplicateShred(DuplicateShredIndex, Pubkey),
+    NodeInstance(Pubkey),
 }
 
 impl fmt::Display for CrdsValueLabel {
@@ -279,6 +350,8 @@ impl fmt::Display for CrdsValueLabel {
             CrdsValueLabel::AccountsHashes(_) => write!(f, "AccountsHashes({})", self.pubkey()),
             CrdsValueLabel::LegacyVersion(_) => write!(f, "LegacyVersion({})", self.pubkey()),
             CrdsValueLabel::Version(_) => write!(f, "Version({})", self.pubkey()),
+            CrdsValueLabel::DuplicateShred(ix, pk) => write!(f, "DuplicateShred({}, {})", ix, pk),
+            CrdsValueLabel::NodeInstance(_) => write!(f, "NodeInstance({})", self.pubkey()),
         }
     }
 }
@@ -294,6 +367,8 @@ impl CrdsValueLabel {
             CrdsValueLabel::AccountsHashes(p) => *p,
             CrdsValueLabel::LegacyVersion(p) => *p,
             CrdsValueLabel::Version(p) => *p,
+            CrdsValueLabel::DuplicateShred(_, p) => *p,
+            CrdsValueLabel::NodeInstance(p) => *p,
         }
     }
 }
@@ -335,6 +410,8 @@ impl CrdsValue {
             CrdsData::EpochSlots(_, p) => p.wallclock,
             CrdsData::LegacyVersion(version) => version.wallclock,
             CrdsData::Version(version) => version.wallclock,
+            CrdsData::DuplicateShred(_, shred) => shred.wallclock,
+            CrdsData::NodeInstance(node) => node.wallclock,
         }
     }
     pub fn pubkey(&self) -> Pubkey {
@@ -347,6 +424,8 @@ impl CrdsValue {
             CrdsData::EpochSlots(_, p) => p.from,
             CrdsData::LegacyVersion(version) => version.from,
             CrdsData::Version(version) => version.from,
+            CrdsData::DuplicateShred(_, shred) => shred.from,
+            CrdsData::NodeInstance(node) => node.from,
         }
     }
     pub fn label(&self) -> CrdsValueLabel {
@@ -359,6 +438,8 @@ impl CrdsValue {
             CrdsData::EpochSlots(ix, _) => CrdsValueLabel::EpochSlots(*ix, self.pubkey()),
             CrdsData::LegacyVersion(_) => CrdsValueLabel::LegacyVersion(self.pubkey()),
             CrdsData::Version(_) => CrdsValueLabel::Version(self.pubkey()),
+            CrdsData::DuplicateShred(ix, _) => CrdsValueLabel::DuplicateShred(*ix, self.pubkey()),
+            CrdsData::NodeInstance(_) => CrdsValueLabel::NodeInstance(self.pubkey()),
         }
     }
     pub fn contact_info(&self) -> Option<&ContactInfo> {
@@ -423,6 +504,20 @@ impl CrdsValue {
         }
     }
 
+    pub fn duplicate_shred(&self) -> Option<&DuplicateShred> {
+        match &self.data {
+            CrdsData::DuplicateShred(_, shred) => Some(shred),
+            _ => None,
+        }
+    }
+
+    pub fn duplicate_shred_index(&self) -> Option<DuplicateShredIndex> {
+        match &self.data {
+            CrdsData::DuplicateShred(ix, _) => Some(*ix),
+            _ => None,
+        }
+    }
+
     /// Return all the possible labels for a record identified by Pubkey.
     pub fn record_labels(key: &Pubkey) -> Vec<CrdsValueLabel> {
         let mut labels = vec![
@@ -432,9 +527,11 @@ impl CrdsValue {
             CrdsValueLabel::AccountsHashes(*key),
             CrdsValueLabel::LegacyVersion(*key),
             CrdsValueLabel::Version(*key),
+            CrdsValueLabel::NodeInstance(*key),
         ];
         labels.extend((0..MAX_VOTES).map(|ix| CrdsValueLabel::Vote(ix, *key)));
         labels.extend((0..MAX_EPOCH_SLOTS).map(|ix| CrdsValueLabel::EpochSlots(ix, *key)));
+        labels.extend((0..MAX_DUPLICATE_SHREDS).map(|ix| CrdsValueLabel::DuplicateShred(ix, *key)));
         labels
     }
 
@@ -482,7 +579,8 @@ mod test {
 
     #[test]
     fn test_labels() {
-        let mut hits = [false; 6 + MAX_VOTES as usize + MAX_EPOCH_SLOTS as usize];
+        let mut hits = [false;
+            7 + MAX_VOTES as usize + MAX_EPOCH_SLOTS as usize + MAX_DUPLICATE_SHREDS as usize];
         // this method should cover all the possible labels
         for v in &CrdsValue::record_labels(&Pubkey::default()) {
             match v {
@@ -492,9 +590,13 @@ mod test {
                 CrdsValueLabel::AccountsHashes(_) => hits[3] = true,
                 CrdsValueLabel::LegacyVersion(_) => hits[4] = true,
                 CrdsValueLabel::Version(_) => hits[5] = true,
-                CrdsValueLabel::Vote(ix, _) => hits[*ix as usize + 6] = true,
+                CrdsValueLabel::NodeInstance(_) => hits[6] = true,
+                CrdsValueLabel::Vote(ix, _) => hits[*ix as usize + 7] = true,
                 CrdsValueLabel::EpochSlots(ix, _) => {
-                    hits[*ix as usize + MAX_VOTES as usize + 6] = true
+                    hits[*ix as usize + MAX_VOTES as usize + 7] = true
+                }
+                CrdsValueLabel::DuplicateShred(ix, _) => {
+                    hits[*ix as usize + MAX_VOTES as usize + MAX_EPOCH_SLOTS as usize + 7] = true
                 }
             }
         }
@@ -596,6 +698,47 @@ mod test {
         );
         assert_eq!(item.sanitize(), Err(SanitizeError::ValueOutOfBounds));
     }
+    #[test]
+    fn test_node_instance_check_duplicate() {
+        let mut rng = rand::thread_rng();
+        let pubkey = Pubkey::new_unique();
+        let now = timestamp();
+        let node = NodeInstance::new(&mut rng, pubkey, now);
+        // Same token is not a duplicate, even with an updated wallclock.
+        let other = node.with_wallclock(now + 1);
+        assert!(!node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(other))));
+        // Instances of a different node are not duplicates.
+        let other = NodeInstance::new(&mut rng, Pubkey::new_unique(), now - 1);
+        assert!(!node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(other))));
+        // Only the instance started later is the duplicate.
+        let older = NodeInstance::new(&mut rng, pubkey, now - 1);
+        let newer = NodeInstance::new(&mut rng, pubkey, now + 1);
+        assert!(
+            node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(
+                older.clone()
+            )))
+        );
+        assert!(
+            !node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(
+                newer.clone()
+            )))
+        );
+        assert!(newer.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(node))));
+        assert!(!older.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(newer))));
+        // With the same timestamp exactly one of the two instances backs off.
+        let node = NodeInstance::new(&mut rng, pubkey, now);
+        let other = NodeInstance::new(&mut rng, pubkey, now);
+        assert_ne!(
+            node.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(
+                other.clone()
+            ))),
+            other.check_duplicate(&CrdsValue::new_unsigned(CrdsData::NodeInstance(node)))
+        );
+        // Other crds values are never duplicates.
+        let other = CrdsValue::new_unsigned(CrdsData::Version(Version::new(pubkey)));
+        assert!(!older.check_duplicate(&other));
+    }
+
     #[test]
     fn test_compute_vote_index_empty() {
         for i in 0..MAX_VOTES {
@@ -0,0 +1,296 @@
+//! The `duplicate_shred` module splits the proof that a leader signed two different shreds
+//! for the same slot and index into chunks that fit in gossip messages, and reassembles and
+//! verifies the proof from the chunks received from gossip.
+use crate::crds_value::MAX_WALLCLOCK;
+use bincode::serialized_size;
+use solana_ledger::{
+    blockstore_meta::DuplicateSlotProof,
+    shred::{Shred, ShredError},
+};
+use solana_sdk::{
+    clock::Slot,
+    pubkey::Pubkey,
+    sanitize::{Sanitize, SanitizeError},
+};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, AbiExample)]
+pub struct DuplicateShred {
+    pub from: Pubkey,
+    pub wallclock: u64,
+    pub slot: Slot,
+    shred_index: u32,
+    num_chunks: u8,
+    chunk_index: u8,
+    chunk: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("data chunk mismatch")]
+    DataChunkMismatch,
+    #[error("invalid chunk index")]
+    InvalidChunkIndex,
+    #[error("invalid duplicate shreds")]
+    InvalidDuplicateShreds,
+    #[error("invalid shred: {0}")]
+    InvalidShred(#[from] ShredError),
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("missing data chunk")]
+    MissingDataChunk,
+    #[error("serialization error: {0}")]
+    SerializationError(#[from] bincode::Error),
+    #[error("shred index mismatch")]
+    ShredIndexMismatch,
+    #[error("slot mismatch")]
+    SlotMismatch,
+    #[error("too many chunks")]
+    TooManyChunks,
+    #[error("unknown slot leader")]
+    UnknownSlotLeader,
+}
+
+impl Sanitize for DuplicateShred {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        if self.wallclock >= MAX_WALLCLOCK {
+            return Err(SanitizeError::ValueOutOfBounds);
+        }
+        if self.num_chunks == 0 || self.chunk_index >= self.num_chunks {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+        self.from.sanitize()
+    }
+}
+
+impl DuplicateShred {
+    pub fn num_chunks(&self) -> u8 {
+        self.num_chunks
+    }
+
+    pub fn chunk_index(&self) -> u8 {
+        self.chunk_index
+    }
+
+    pub fn shred_index(&self) -> u32 {
+        self.shred_index
+    }
+}
+
+/// Check that `shred1` and `shred2` prove that the leader of their slot signed two different
+/// shreds for the same slot and index
+fn check_shreds(slot_leader: Option<Pubkey>, shred1: &Shred, shred2: &Shred) -> Result<(), Error> {
+    if shred1.slot() != shred2.slot() {
+        return Err(Error::SlotMismatch);
+    }
+    if shred1.index() != shred2.index() {
+        return Err(Error::ShredIndexMismatch);
+    }
+    if shred1.is_data() != shred2.is_data() || shred1.payload == shred2.payload {
+        return Err(Error::InvalidDuplicateShreds);
+    }
+    let slot_leader = slot_leader.ok_or(Error::UnknownSlotLeader)?;
+    if !shred1.verify(&slot_leader) || !shred2.verify(&slot_leader) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Split the proof that `shred` conflicts with the previously received `other_payload` into
+/// chunks, each of which serializes to at most `max_size` bytes. `slot_leader` looks up the
+/// leader of the shred's slot, whose signature must be on both shreds.
+pub fn from_shred(
+    shred: &Shred,
+    other_payload: Vec<u8>,
+    self_pubkey: Pubkey,
+    slot_leader: impl FnOnce(Slot) -> Option<Pubkey>,
+    wallclock: u64,
+    max_size: usize,
+) -> Result<impl Iterator<Item = DuplicateShred>, Error> {
+    let other_shred = Shred::new_from_serialized_shred(other_payload)?;
+    check_shreds(slot_leader(shred.slot()), shred, &other_shred)?;
+    let (slot, shred_index) = (shred.slot(), shred.index());
+    let proof = DuplicateSlotProof {
+        shred1: shred.payload.clone(),
+        shred2: other_shred.payload,
+    };
+    let data = bincode::serialize(&proof)?;
+    let header_size = serialized_size(&DuplicateShred {
+        from: self_pubkey,
+        wallclock,
+        slot,
+        shred_index,
+        num_chunks: u8::MAX,
+        chunk_index: u8::MAX,
+        chunk: Vec::default(),
+    })? as usize;
+    let chunk_size = max_size.saturating_sub(header_size);
+    if chunk_size == 0 {
+        return Err(Error::TooManyChunks);
+    }
+    let chunks: Vec<_> = data.chunks(chunk_size).map(<[u8]>::to_vec).collect();
+    if chunks.len() > usize::from(u8::MAX) {
+        return Err(Error::TooManyChunks);
+    }
+    let num_chunks = chunks.len() as u8;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(move |(chunk_index, chunk)| DuplicateShred {
+            from: self_pubkey,
+            wallclock,
+            slot,
+            shred_index,
+            num_chunks,
+            chunk_index: chunk_index as u8,
+            chunk,
+        }))
+}
+
+/// Reassemble the two conflicting shreds from the chunks of one proof, and verify that they
+/// are signed by the leader of their slot
+pub fn into_shreds(
+    chunks: impl IntoIterator<Item = DuplicateShred>,
+    slot_leader: impl FnOnce(Slot) -> Option<Pubkey>,
+) -> Result<(Shred, Shred), Error> {
+    let mut chunks = chunks.into_iter();
+    let DuplicateShred {
+        from,
+        slot,
+        shred_index,
+        num_chunks,
+        chunk_index,
+        chunk,
+        ..
+    } = chunks.next().ok_or(Error::InvalidDuplicateShreds)?;
+    if num_chunks == 0 || chunk_index >= num_chunks {
+        return Err(Error::InvalidChunkIndex);
+    }
+    let mut data = BTreeMap::new();
+    data.insert(chunk_index, chunk);
+    for chunk in chunks {
+        if chunk.from != from
+            || chunk.slot != slot
+            || chunk.shred_index != shred_index
+            || chunk.num_chunks != num_chunks
+        {
+            return Err(Error::DataChunkMismatch);
+        }
+        if chunk.chunk_index >= num_chunks {
+            return Err(Error::InvalidChunkIndex);
+        }
+        data.insert(chunk.chunk_index, chunk.chunk);
+    }
+    if data.len() != usize::from(num_chunks) {
+        return Err(Error::MissingDataChunk);
+    }
+    let data: Vec<u8> = data.into_iter().flat_map(|(_, chunk)| chunk).collect();
+    let proof: DuplicateSlotProof = bincode::deserialize(&data)?;
+    let shred1 = Shred::new_from_serialized_shred(proof.shred1)?;
+    let shred2 = Shred::new_from_serialized_shred(proof.shred2)?;
+    if shred1.slot() != slot || shred2.slot() != slot {
+        return Err(Error::SlotMismatch);
+    }
+    if shred1.index() != shred_index || shred2.index() != shred_index {
+        return Err(Error::ShredIndexMismatch);
+    }
+    check_shreds(slot_leader(slot), &shred1, &shred2)?;
+    Ok((shred1, shred2))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use solana_ledger::{entry::create_ticks, shred::Shredder};
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        timing::timestamp,
+    };
+    use std::sync::Arc;
+
+    pub(crate) fn new_rand_shred(slot: Slot, index: u32, leader: &Arc<Keypair>) -> Shred {
+        let shredder = Shredder::new(slot, slot - 1, 0.0, leader.clone(), 0, 0).unwrap();
+        let entries = create_ticks(10, 1, Hash::new_unique());
+        let (data_shreds, _, _) = shredder.entries_to_shreds(&entries, true, index);
+        data_shreds.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_duplicate_shred_round_trip() {
+        let leader = Arc::new(Keypair::new());
+        let (slot, index) = (53, 21);
+        let shred1 = new_rand_shred(slot, index, &leader);
+        let shred2 = new_rand_shred(slot, index, &leader);
+        let chunks: Vec<_> = from_shred(
+            &shred1,
+            shred2.payload.clone(),
+            Pubkey::new_unique(),
+            |s| {
+                assert_eq!(s, slot);
+                Some(leader.pubkey())
+            },
+            timestamp(),
+            512,
+        )
+        .unwrap()
+        .collect();
+        assert!(chunks.len() > 4);
+        assert!(chunks
+            .iter()
+            .all(|chunk| serialized_size(chunk).unwrap() <= 512 && chunk.sanitize().is_ok()));
+
+        // Chunks may arrive in any order
+        let (shred3, shred4) =
+            into_shreds(chunks.iter().rev().cloned(), |_| Some(leader.pubkey())).unwrap();
+        assert_eq!(shred1, shred3);
+        assert_eq!(shred2, shred4);
+
+        assert!(matches!(
+            into_shreds(chunks.iter().skip(1).cloned(), |_| Some(leader.pubkey())),
+            Err(Error::MissingDataChunk)
+        ));
+        assert!(matches!(
+            into_shreds(chunks.iter().cloned(), |_| Some(Pubkey::new_unique())),
+            Err(Error::InvalidSignature)
+        ));
+        assert!(matches!(
+            into_shreds(chunks.into_iter(), |_| None),
+            Err(Error::UnknownSlotLeader)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_shred_invalid_proof() {
+        let leader = Arc::new(Keypair::new());
+        let shred = new_rand_shred(53, 21, &leader);
+        let from_shred = |other: &Shred| {
+            from_shred(
+                &shred,
+                other.payload.clone(),
+                Pubkey::new_unique(),
+                |_| Some(leader.pubkey()),
+                timestamp(),
+                512,
+            )
+            .map(|chunks| chunks.count())
+        };
+        assert!(matches!(
+            from_shred(&shred),
+            Err(Error::InvalidDuplicateShreds)
+        ));
+        assert!(matches!(
+            from_shred(&new_rand_shred(54, 21, &leader)),
+            Err(Error::SlotMismatch)
+        ));
+        assert!(matches!(
+            from_shred(&new_rand_shred(53, 22, &leader)),
+            Err(Error::ShredIndexMismatch)
+        ));
+        assert!(matches!(
+            from_shred(&new_rand_shred(53, 21, &Arc::new(Keypair::new()))),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+//! The `duplicate_shred_listener` module reassembles the duplicate shred proofs other nodes
+//! push to gossip, verifies them, records the duplicate slots in the blockstore and notifies
+//! replay about them.
+use crate::{
+    cluster_info::{ClusterInfo, GOSSIP_SLEEP_MILLIS},
+    duplicate_shred::{self, DuplicateShred},
+};
+use crossbeam_channel::{Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
+use solana_ledger::{blockstore::Blockstore, leader_schedule_cache::LeaderScheduleCache};
+use solana_metrics::inc_new_counter_error;
+use solana_runtime::bank_forks::BankForks;
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub type DuplicateSlotSender = CrossbeamSender<Slot>;
+pub type DuplicateSlotReceiver = CrossbeamReceiver<Slot>;
+
+/// Maximum number of partially received proofs buffered at any time
+const MAX_PENDING_PROOFS: usize = 1024;
+
+pub struct DuplicateShredListener {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl DuplicateShredListener {
+    pub fn new(
+        exit: &Arc<AtomicBool>,
+        cluster_info: Arc<ClusterInfo>,
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        duplicate_slot_sender: DuplicateSlotSender,
+    ) -> Self {
+        let exit = exit.clone();
+        let thread_hdl = Builder::new()
+            .name("solana-duplicate-shred-listener".to_string())
+            .spawn(move || {
+                let mut handler = DuplicateShredHandler::new(
+                    blockstore,
+                    bank_forks,
+                    leader_schedule_cache,
+                    duplicate_slot_sender,
+                );
+                let mut since = None;
+                while !exit.load(atomic::Ordering::Relaxed) {
+                    let (chunks, new_since) = cluster_info.get_duplicate_shreds_since(since);
+                    since = new_since;
+                    for chunk in chunks {
+                        handler.handle(chunk);
+                    }
+                    sleep(Duration::from_millis(GOSSIP_SLEEP_MILLIS));
+                }
+            })
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
+
+struct DuplicateShredHandler {
+    blockstore: Arc<Blockstore>,
+    bank_forks: Arc<RwLock<BankForks>>,
+    leader_schedule_cache: Arc<LeaderScheduleCache>,
+    duplicate_slot_sender: DuplicateSlotSender,
+    // Chunks received so far of the latest proof from each node for each slot
+    pending_proofs: HashMap<(Pubkey, Slot), HashMap<u8, DuplicateShred>>,
+    // Slots already sent to replay
+    sent_slots: BTreeSet<Slot>,
+}
+
+impl DuplicateShredHandler {
+    fn new(
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        duplicate_slot_sender: DuplicateSlotSender,
+    ) -> Self {
+        Self {
+            blockstore,
+            bank_forks,
+            leader_schedule_cache,
+            duplicate_slot_sender,
+            pending_proofs: HashMap::new(),
+            sent_slots: BTreeSet::new(),
+        }
+    }
+
+    fn handle(&mut self, chunk: DuplicateShred) {
+        let root = self.bank_forks.read().unwrap().root();
+        if chunk.slot <= root || self.sent_slots.contains(&chunk.slot) {
+            return;
+        }
+        let key = (chunk.from, chunk.slot);
+        if !self.pending_proofs.contains_key(&key)
+            && self.pending_proofs.len() >= MAX_PENDING_PROOFS
+        {
+            self.prune(root);
+            if self.pending_proofs.len() >= MAX_PENDING_PROOFS {
+                return;
+            }
+        }
+        let chunks = self.pending_proofs.entry(key).or_default();
+        // A newer proof from the same node replaces the chunks of the older one
+        if let Some(other) = chunks.values().next() {
+            match other.wallclock.cmp(&chunk.wallclock) {
+                Ordering::Less => chunks.clear(),
+                Ordering::Greater => return,
+                Ordering::Equal => (),
+            }
+        }
+        let num_chunks = usize::from(chunk.num_chunks());
+        chunks.insert(chunk.chunk_index(), chunk);
+        if chunks.len() < num_chunks {
+            return;
+        }
+        let chunks = self.pending_proofs.remove(&key).unwrap();
+        let slot = key.1;
+        let root_bank = self.bank_forks.read().unwrap().root_bank().clone();
+        match duplicate_shred::into_shreds(chunks.into_iter().map(|(_, chunk)| chunk), |slot| {
+            self.leader_schedule_cache
+                .slot_leader_at(slot, Some(&root_bank))
+        }) {
+            Ok((shred1, shred2)) => {
+                if !self.blockstore.has_duplicate_shreds_in_slot(slot) {
+                    if let Err(err) =
+                        self.blockstore
+                            .store_duplicate_slot(slot, shred1.payload, shred2.payload)
+                    {
+                        error!("failed to store duplicate slot {}: {:?}", slot, err);
+                    }
+                }
+                if self.sent_slots.insert(slot) {
+                    let _ = self.duplicate_slot_sender.send(slot);
+                }
+                datapoint_info!(
+                    "duplicate_shred_listener-proof",
+                    ("slot", slot as i64, i64),
+                    ("from", key.0.to_string(), String),
+                );
+            }
+            Err(err) => {
+                inc_new_counter_error!("duplicate_shred_listener-invalid_proof", 1);
+                warn!(
+                    "invalid duplicate shred proof from {} for slot {}: {}",
+                    key.0, slot, err
+                );
+            }
+        }
+    }
+
+    fn prune(&mut self, root: Slot) {
+        self.pending_proofs.retain(|(_, slot), _| *slot > root);
+        self.sent_slots = self.sent_slots.split_off(&(root + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplicate_shred::tests::new_rand_shred;
+    use crossbeam_channel::unbounded;
+    use solana_ledger::{
+        genesis_utils::{create_genesis_config_with_leader, GenesisConfigInfo},
+        get_tmp_ledger_path,
+    };
+    use solana_runtime::bank::Bank;
+    use solana_sdk::{
+        signature::{Keypair, Signer},
+        timing::timestamp,
+    };
+
+    #[test]
+    fn test_duplicate_shred_handler() {
+        let ledger_path = get_tmp_ledger_path!();
+        let blockstore = Arc::new(Blockstore::open(&ledger_path).unwrap());
+        let leader = Arc::new(Keypair::new());
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(100, &leader.pubkey(), 10);
+        let bank = Bank::new(&genesis_config);
+        let leader_schedule_cache = Arc::new(LeaderScheduleCache::new_from_bank(&bank));
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let (sender, receiver) = unbounded();
+        let mut handler = DuplicateShredHandler::new(
+            blockstore.clone(),
+            bank_forks,
+            leader_schedule_cache,
+            sender,
+        );
+
+        let new_proof = |slot, signer: &Arc<Keypair>| -> Vec<DuplicateShred> {
+            let shred1 = new_rand_shred(slot, 3, signer);
+            let shred2 = new_rand_shred(slot, 3, signer);
+            duplicate_shred::from_shred(
+                &shred1,
+                shred2.payload,
+                Pubkey::new_unique(),
+                |_| Some(signer.pubkey()),
+                timestamp(),
+                512,
+            )
+            .unwrap()
+            .collect()
+        };
+
+        // A proof is only acted upon once all of its chunks arrive
+        let mut chunks = new_proof(5, &leader);
+        let last = chunks.pop().unwrap();
+        for chunk in chunks {
+            handler.handle(chunk);
+        }
+        assert!(!blockstore.has_duplicate_shreds_in_slot(5));
+        assert!(receiver.try_recv().is_err());
+        handler.handle(last);
+        assert!(blockstore.has_duplicate_shreds_in_slot(5));
+        assert_eq!(receiver.try_recv(), Ok(5));
+
+        // Another proof for the same slot is not sent to replay again
+        for chunk in new_proof(5, &leader) {
+            handler.handle(chunk);
+        }
+        assert!(receiver.try_recv().is_err());
+
+        // Proofs not signed by the slot leader are rejected
+        for chunk in new_proof(6, &Arc::new(Keypair::new())) {
+            handler.handle(chunk);
+        }
+        assert!(!blockstore.has_duplicate_shreds_in_slot(6));
+        assert!(receiver.try_recv().is_err());
+
+        drop(handler);
+        drop(blockstore);
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+}
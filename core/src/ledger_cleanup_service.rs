@@ -1,7 +1,7 @@
 //! The `ledger_cleanup_service` drops older ledger data to limit disk space usage
 
 use solana_ledger::blockstore::{Blockstore, PurgeType};
-use solana_ledger::blockstore_db::Result as BlockstoreResult;
+use solana_ledger::blockstore_db::{LedgerRetention, Result as BlockstoreResult};
 use solana_measure::measure::Measure;
 use solana_sdk::clock::{Slot, DEFAULT_TICKS_PER_SLOT, TICKS_PER_DAY};
 use std::string::ToString;
@@ -42,11 +42,12 @@ impl LedgerCleanupService {
         new_root_receiver: Receiver<Slot>,
         blockstore: Arc<Blockstore>,
         max_ledger_shreds: u64,
+        retention: LedgerRetention,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         info!(
-            "LedgerCleanupService active. Max Ledger Slots {}",
-            max_ledger_shreds
+            "LedgerCleanupService active. Max Ledger Slots {}, retention {:?}",
+            max_ledger_shreds, retention
         );
         let exit = exit.clone();
         let mut last_purge_slot = 0;
@@ -62,6 +63,7 @@ impl LedgerCleanupService {
                     &new_root_receiver,
                     &blockstore,
                     max_ledger_shreds,
+                    retention,
                     &mut last_purge_slot,
                     DEFAULT_PURGE_SLOT_INTERVAL,
                     &mut last_compaction_slot,
@@ -132,10 +134,12 @@ impl LedgerCleanupService {
         Ok(root)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn cleanup_ledger(
         new_root_receiver: &Receiver<Slot>,
         blockstore: &Arc<Blockstore>,
         max_ledger_shreds: u64,
+        retention: LedgerRetention,
         last_purge_slot: &mut u64,
         purge_interval: u64,
         last_compaction_slot: &mut u64,
@@ -180,11 +184,16 @@ impl LedgerCleanupService {
                     );
 
                     let mut purge_time = Measure::start("purge_slots");
-                    blockstore.purge_slots(
-                        purge_first_slot,
-                        lowest_cleanup_slot,
-                        PurgeType::PrimaryIndex,
-                    );
+                    match retention {
+                        LedgerRetention::Purge => blockstore.purge_slots(
+                            purge_first_slot,
+                            lowest_cleanup_slot,
+                            PurgeType::PrimaryIndex,
+                        ),
+                        LedgerRetention::CompactionFilter => {
+                            blockstore.set_max_expired_slot(lowest_cleanup_slot)
+                        }
+                    }
                     purge_time.stop();
                     info!("{}", purge_time);
 
@@ -193,9 +202,14 @@ impl LedgerCleanupService {
                             "compacting data from slots {} to {}",
                             compact_first_slot, lowest_cleanup_slot
                         );
-                        if let Err(err) =
-                            blockstore.compact_storage(compact_first_slot, lowest_cleanup_slot)
-                        {
+                        let compaction = match retention {
+                            LedgerRetention::Purge => {
+                                blockstore.compact_storage(compact_first_slot, lowest_cleanup_slot)
+                            }
+                            LedgerRetention::CompactionFilter => blockstore
+                                .compact_expired_slots(compact_first_slot, lowest_cleanup_slot),
+                        };
+                        if let Err(err) = compaction {
                             // This error is not fatal and indicates an internal error?
                             error!(
                                 "Error: {:?}; Couldn't compact storage from {:?} to {:?}",
@@ -250,8 +264,7 @@ mod tests {
     use solana_ledger::get_tmp_ledger_path;
     use std::sync::mpsc::channel;
 
-    #[test]
-    fn test_cleanup() {
+    fn run_test_cleanup(retention: LedgerRetention) {
         solana_logger::setup();
         let blockstore_path = get_tmp_ledger_path!();
         let blockstore = Blockstore::open(&blockstore_path).unwrap();
@@ -268,6 +281,7 @@ mod tests {
             &receiver,
             &blockstore,
             5,
+            retention,
             &mut last_purge_slot,
             10,
             &mut last_compaction_slot,
@@ -280,11 +294,22 @@ mod tests {
             .slot_meta_iterator(0)
             .unwrap()
             .for_each(|(slot, _)| assert!(slot > 40));
+        assert!(blockstore.lowest_slot() > 40);
 
         drop(blockstore);
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_cleanup() {
+        run_test_cleanup(LedgerRetention::Purge);
+    }
+
+    #[test]
+    fn test_cleanup_with_compaction_filter() {
+        run_test_cleanup(LedgerRetention::CompactionFilter);
+    }
+
     #[test]
     fn test_cleanup_speed() {
         solana_logger::setup();
@@ -325,6 +350,7 @@ mod tests {
                 &receiver,
                 &blockstore,
                 initial_slots,
+                LedgerRetention::Purge,
                 &mut last_purge_slot,
                 10,
                 &mut last_compaction_slot,
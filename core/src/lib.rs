@@ -6,7 +6,9 @@
 //! command-line tools to spin up validators and a Rust library
 //!
 
+pub mod account_history_service;
 pub mod accounts_hash_verifier;
+pub mod ancestor_hashes_service;
 pub mod banking_stage;
 pub mod bigtable_upload_service;
 pub mod broadcast_stage;
@@ -32,6 +34,8 @@ pub mod crds_gossip_push;
 pub mod crds_shards;
 pub mod crds_value;
 pub mod data_budget;
+pub mod duplicate_shred;
+pub mod duplicate_shred_listener;
 pub mod epoch_slots;
 pub mod fetch_stage;
 pub mod fork_choice;
@@ -45,6 +49,7 @@ pub mod optimistic_confirmation_verifier;
 pub mod optimistically_confirmed_bank_tracker;
 pub mod poh_recorder;
 pub mod poh_service;
+pub mod priority_scheduler;
 pub mod progress_map;
 pub mod pubkey_references;
 pub mod repair_response;
@@ -68,7 +73,9 @@ pub mod sigverify;
 pub mod sigverify_shreds;
 pub mod sigverify_stage;
 pub mod snapshot_packager_service;
+pub mod staked_nodes_updater_service;
 pub mod test_validator;
+pub mod tower_storage;
 pub mod tpu;
 pub mod transaction_status_service;
 pub mod tree_diff;
@@ -1,9 +1,10 @@
 //! The `optimistically_confirmed_bank_tracker` module implements a threaded service to track the
 //! most recent optimistically confirmed bank for use in rpc services, and triggers gossip
-//! subscription notifications
+//! subscription notifications and AccountsDb plugin slot status notifications
 
 use crate::rpc_subscriptions::RpcSubscriptions;
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use solana_accountsdb_plugin::slot_status_notifier::SlotStatusNotifier;
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::clock::Slot;
 use std::{
@@ -32,6 +33,9 @@ pub enum BankNotification {
     OptimisticallyConfirmed(Slot),
     Frozen(Arc<Bank>),
     Root(Arc<Bank>),
+    /// The slot and parent slot of each bank rooted by a new root, in slot order. Sent with
+    /// `Root`, since the parents of the root bank are gone once it is squashed
+    NewRootedChain(Vec<(Slot, Slot)>),
 }
 
 impl std::fmt::Debug for BankNotification {
@@ -42,6 +46,7 @@ impl std::fmt::Debug for BankNotification {
             }
             BankNotification::Frozen(bank) => write!(f, "Frozen({})", bank.slot()),
             BankNotification::Root(bank) => write!(f, "Root({})", bank.slot()),
+            BankNotification::NewRootedChain(chain) => write!(f, "NewRootedChain({:?})", chain),
         }
     }
 }
@@ -60,9 +65,11 @@ impl OptimisticallyConfirmedBankTracker {
         bank_forks: Arc<RwLock<BankForks>>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
         subscriptions: Arc<RpcSubscriptions>,
+        slot_status_notifier: Option<SlotStatusNotifier>,
     ) -> Self {
         let exit_ = exit.clone();
         let mut pending_optimistically_confirmed_banks = HashSet::new();
+        let mut last_notified_root = bank_forks.read().unwrap().root();
         let thread_hdl = Builder::new()
             .name("solana-optimistic-bank-tracker".to_string())
             .spawn(move || loop {
@@ -76,6 +83,8 @@ impl OptimisticallyConfirmedBankTracker {
                     &optimistically_confirmed_bank,
                     &subscriptions,
                     &mut pending_optimistically_confirmed_banks,
+                    &slot_status_notifier,
+                    &mut last_notified_root,
                 ) {
                     break;
                 }
@@ -90,8 +99,18 @@ impl OptimisticallyConfirmedBankTracker {
         optimistically_confirmed_bank: &Arc<RwLock<OptimisticallyConfirmedBank>>,
         subscriptions: &Arc<RpcSubscriptions>,
         mut pending_optimistically_confirmed_banks: &mut HashSet<Slot>,
+        slot_status_notifier: &Option<SlotStatusNotifier>,
+        last_notified_root: &mut Slot,
     ) -> Result<(), RecvTimeoutError> {
         let notification = receiver.recv_timeout(Duration::from_secs(1))?;
+        if let Some(slot_status_notifier) = slot_status_notifier {
+            Self::notify_slot_status(
+                &notification,
+                bank_forks,
+                slot_status_notifier,
+                last_notified_root,
+            );
+        }
         Self::process_notification(
             notification,
             bank_forks,
@@ -102,6 +121,39 @@ impl OptimisticallyConfirmedBankTracker {
         Ok(())
     }
 
+    /// Forward a bank notification to the AccountsDb plugins. A new root implicitly roots all
+    /// of its ancestors, so every slot between the last notified root and the new one is
+    /// reported as rooted.
+    pub(crate) fn notify_slot_status(
+        notification: &BankNotification,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        slot_status_notifier: &SlotStatusNotifier,
+        last_notified_root: &mut Slot,
+    ) {
+        match notification {
+            BankNotification::OptimisticallyConfirmed(slot) => {
+                let parent = bank_forks
+                    .read()
+                    .unwrap()
+                    .get(*slot)
+                    .map(|bank| bank.parent_slot());
+                slot_status_notifier.notify_slot_confirmed(*slot, parent);
+            }
+            BankNotification::Frozen(bank) => {
+                slot_status_notifier.notify_slot_processed(bank.slot(), Some(bank.parent_slot()));
+            }
+            BankNotification::Root(_) => {}
+            BankNotification::NewRootedChain(chain) => {
+                for (slot, parent) in chain {
+                    if *slot > *last_notified_root {
+                        slot_status_notifier.notify_slot_rooted(*slot, Some(*parent));
+                        *last_notified_root = *slot;
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn process_notification(
         notification: BankNotification,
         bank_forks: &Arc<RwLock<BankForks>>,
@@ -151,6 +203,7 @@ impl OptimisticallyConfirmedBankTracker {
                 drop(w_optimistically_confirmed_bank);
                 pending_optimistically_confirmed_banks.retain(|&s| s > root_slot);
             }
+            BankNotification::NewRootedChain(_) => {}
         }
     }
 
@@ -166,9 +219,11 @@ impl OptimisticallyConfirmedBankTracker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_accountsdb_plugin::slot_status_notifier::SlotStatusNotifierInterface;
     use solana_ledger::genesis_utils::{create_genesis_config, GenesisConfigInfo};
     use solana_runtime::commitment::BlockCommitmentCache;
     use solana_sdk::pubkey::Pubkey;
+    use std::sync::Mutex;
 
     #[test]
     fn test_process_notification() {
@@ -291,4 +346,81 @@ mod tests {
         assert_eq!(pending_optimistically_confirmed_banks.len(), 0);
         assert_eq!(pending_optimistically_confirmed_banks.contains(&6), false);
     }
+
+    #[derive(Default)]
+    struct SlotStatusRecorder {
+        statuses: Mutex<Vec<(&'static str, Slot, Option<Slot>)>>,
+    }
+
+    impl SlotStatusNotifierInterface for SlotStatusRecorder {
+        fn notify_slot_processed(&self, slot: Slot, parent: Option<Slot>) {
+            self.statuses
+                .lock()
+                .unwrap()
+                .push(("processed", slot, parent));
+        }
+
+        fn notify_slot_confirmed(&self, slot: Slot, parent: Option<Slot>) {
+            self.statuses
+                .lock()
+                .unwrap()
+                .push(("confirmed", slot, parent));
+        }
+
+        fn notify_slot_rooted(&self, slot: Slot, parent: Option<Slot>) {
+            self.statuses.lock().unwrap().push(("rooted", slot, parent));
+        }
+    }
+
+    #[test]
+    fn test_notify_slot_status() {
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(100);
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let bank0 = bank_forks.read().unwrap().get(0).unwrap().clone();
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        bank_forks.write().unwrap().insert(bank1);
+        let bank1 = bank_forks.read().unwrap().get(1).unwrap().clone();
+        let bank2 = Bank::new_from_parent(&bank1, &Pubkey::default(), 2);
+        bank_forks.write().unwrap().insert(bank2);
+        let bank2 = bank_forks.read().unwrap().get(2).unwrap().clone();
+        bank_forks
+            .write()
+            .unwrap()
+            .insert(Bank::new_from_parent(&bank2, &Pubkey::default(), 3));
+
+        // Replay stage collects the rooted chain before setting the root, which squashes the
+        // root bank and drops its parents
+        let rooted_chain = vec![(1, 0), (2, 1)];
+        bank_forks.write().unwrap().set_root(2, &None, None);
+        assert!(bank2.parents().is_empty());
+
+        let recorder = Arc::new(SlotStatusRecorder::default());
+        let slot_status_notifier: SlotStatusNotifier = recorder.clone();
+        let mut last_notified_root = 0;
+        for notification in vec![
+            BankNotification::Frozen(bank1),
+            BankNotification::OptimisticallyConfirmed(3),
+            BankNotification::Root(bank2),
+            BankNotification::NewRootedChain(rooted_chain),
+            BankNotification::NewRootedChain(vec![(1, 0)]),
+        ] {
+            OptimisticallyConfirmedBankTracker::notify_slot_status(
+                &notification,
+                &bank_forks,
+                &slot_status_notifier,
+                &mut last_notified_root,
+            );
+        }
+        assert_eq!(last_notified_root, 2);
+        assert_eq!(
+            *recorder.statuses.lock().unwrap(),
+            vec![
+                ("processed", 1, Some(0)),
+                ("confirmed", 3, Some(2)),
+                ("rooted", 1, Some(0)),
+                ("rooted", 2, Some(1)),
+            ]
+        );
+    }
 }
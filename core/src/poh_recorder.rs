@@ -234,6 +234,11 @@ impl PohRecorder {
             ))
     }
 
+    // the identity used to look up this node's leader slots
+    pub fn set_id(&mut self, id: &Pubkey) {
+        self.id = *id;
+    }
+
     // synchronize PoH with a bank
     pub fn reset(
         &mut self,
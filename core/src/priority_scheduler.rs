@@ -0,0 +1,419 @@
+//! The `priority_scheduler` module reorders the packets buffered by the banking stage so
+//! that transactions paying the highest fee per requested compute unit are processed and
+//! forwarded first.
+
+use crate::banking_stage::UnprocessedPackets;
+use solana_perf::packet::{limited_deserialize, Packet, Packets};
+use solana_runtime::{bank::Bank, process_instruction::ComputeBudget};
+use solana_sdk::{
+    fee_calculator::{FeeCalculator, DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE},
+    hash::Hash,
+    pubkey::Pubkey,
+    short_vec,
+    signature::Signature,
+    transaction::Transaction,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    mem::size_of,
+    sync::Arc,
+};
+
+/// Number of partially filled batches searched for a slot without account conflicts
+const MAX_OPEN_BATCHES: usize = 16;
+
+/// Scaling applied to the fee per compute unit so that small fees remain distinguishable
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TransactionPriority {
+    /// Fee paid per requested compute unit, in micro-lamports
+    pub fee_per_compute_unit: u64,
+    /// Fee paid per required signature, in lamports
+    pub fee_per_signature: u64,
+}
+
+impl TransactionPriority {
+    /// Compute the priority of `transaction`. The fee is calculated with the fee calculator of
+    /// the transaction's recent blockhash in `bank`, or at the default target lamports per
+    /// signature if no bank is available. Each instruction requests the full per-instruction compute budget.
+    pub fn new(transaction: &Transaction, bank: Option<&Bank>) -> Self {
+        ParsedTransaction::new(transaction).priority(bank)
+    }
+}
+
+/// What the scheduler needs of a buffered transaction, parsed once per packet
+struct ParsedTransaction {
+    recent_blockhash: Hash,
+    /// Fee of the transaction at one lamport per signature
+    fee_signatures: u64,
+    num_required_signatures: u64,
+    num_instructions: u64,
+    writable_keys: Vec<Pubkey>,
+    readonly_keys: Vec<Pubkey>,
+}
+
+impl ParsedTransaction {
+    fn new(transaction: &Transaction) -> Self {
+        let message = transaction.message();
+        let (writable_keys, readonly_keys) = message.get_account_keys_by_lock_type();
+        Self {
+            recent_blockhash: message.recent_blockhash,
+            fee_signatures: FeeCalculator::new(1).calculate_fee(message),
+            num_required_signatures: u64::from(message.header.num_required_signatures),
+            num_instructions: message.instructions.len() as u64,
+            writable_keys: writable_keys.into_iter().cloned().collect(),
+            readonly_keys: readonly_keys.into_iter().cloned().collect(),
+        }
+    }
+
+    fn priority(&self, bank: Option<&Bank>) -> TransactionPriority {
+        let lamports_per_signature = match bank {
+            Some(bank) => bank
+                .get_fee_calculator(&self.recent_blockhash)
+                .map(|fee_calculator| fee_calculator.lamports_per_signature)
+                .unwrap_or(0),
+            None => DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE,
+        };
+        let fee = lamports_per_signature * self.fee_signatures;
+        let requested_compute_units =
+            u128::from(self.num_instructions.max(1)) * ComputeBudget::default().max_units as u128;
+        let fee_per_compute_unit =
+            fee as u128 * MICRO_LAMPORTS_PER_LAMPORT / requested_compute_units.max(1);
+
+        TransactionPriority {
+            fee_per_compute_unit: fee_per_compute_unit.min(u64::MAX as u128) as u64,
+            fee_per_signature: fee / self.num_required_signatures.max(1),
+        }
+    }
+}
+
+/// The parsed transactions of the buffered packets, keyed by their first signature, so that
+/// packets still buffered from an earlier pass are not deserialized again
+#[derive(Default)]
+pub struct PriorityCache {
+    transactions: HashMap<Signature, Arc<ParsedTransaction>>,
+}
+
+impl PriorityCache {
+    // The first signature of a serialized transaction, read without deserializing the rest
+    fn first_signature(packet: &Packet) -> Option<Signature> {
+        let data = &packet.data[..packet.meta.size];
+        let (num_signatures, offset) = short_vec::decode_len(data).ok()?;
+        let end = offset + size_of::<Signature>();
+        if num_signatures == 0 || data.len() < end {
+            return None;
+        }
+        Some(Signature::new(&data[offset..end]))
+    }
+
+    fn get_or_parse(&self, packet: &Packet) -> Option<(Signature, Arc<ParsedTransaction>)> {
+        let signature = Self::first_signature(packet)?;
+        if let Some(parsed) = self.transactions.get(&signature) {
+            return Some((signature, parsed.clone()));
+        }
+        let transaction: Transaction =
+            limited_deserialize(&packet.data[0..packet.meta.size]).ok()?;
+        Some((signature, Arc::new(ParsedTransaction::new(&transaction))))
+    }
+}
+
+#[derive(Default)]
+struct ScheduledBatch {
+    packets: Vec<Packet>,
+    /// The parsed transactions of `packets`, kept in the cache while they stay buffered
+    parsed_transactions: Vec<(Signature, Arc<ParsedTransaction>)>,
+    write_locks: HashSet<Pubkey>,
+    read_locks: HashSet<Pubkey>,
+}
+
+impl ScheduledBatch {
+    fn conflicts(&self, writable_keys: &[Pubkey], readonly_keys: &[Pubkey]) -> bool {
+        writable_keys
+            .iter()
+            .any(|key| self.write_locks.contains(key) || self.read_locks.contains(key))
+            || readonly_keys
+                .iter()
+                .any(|key| self.write_locks.contains(key))
+    }
+
+    fn push(&mut self, packet: Packet, signature: Signature, parsed: Arc<ParsedTransaction>) {
+        self.packets.push(packet);
+        self.write_locks
+            .extend(parsed.writable_keys.iter().cloned());
+        self.read_locks.extend(parsed.readonly_keys.iter().cloned());
+        self.parsed_transactions.push((signature, parsed));
+    }
+}
+
+/// Rebuild `buffered_packets` into batches of at most `batch_size` transactions, ordered by
+/// descending `TransactionPriority`. A transaction is placed in the earliest open batch whose
+/// account locks it does not conflict with, so the highest priority non-conflicting
+/// transactions are processed together first. Packets that fail to deserialize are dropped,
+/// as are the lowest priority batches beyond `batch_limit`. `cache` is left holding the
+/// parsed transactions of the packets that remain buffered.
+pub fn prioritize_buffered_packets(
+    buffered_packets: &mut UnprocessedPackets,
+    bank: Option<&Bank>,
+    cache: &mut PriorityCache,
+    batch_size: usize,
+    batch_limit: usize,
+) {
+    let mut prioritized: Vec<_> = buffered_packets
+        .drain(..)
+        .flat_map(|(packets, packet_indexes)| {
+            packet_indexes
+                .into_iter()
+                .filter_map(|index| {
+                    let packet = packets.packets[index].clone();
+                    let (signature, parsed) = cache.get_or_parse(&packet)?;
+                    Some((parsed.priority(bank), signature, parsed, packet))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    // Stable sort keeps arrival order among transactions of equal priority
+    prioritized.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut batches: Vec<ScheduledBatch> = vec![];
+    let mut open_batches: Vec<usize> = vec![];
+    for (_, signature, parsed, packet) in prioritized {
+        let batch_index = match open_batches
+            .iter()
+            .take(MAX_OPEN_BATCHES)
+            .find(|i| !batches[**i].conflicts(&parsed.writable_keys, &parsed.readonly_keys))
+        {
+            Some(batch_index) => *batch_index,
+            None => {
+                batches.push(ScheduledBatch::default());
+                open_batches.push(batches.len() - 1);
+                batches.len() - 1
+            }
+        };
+        batches[batch_index].push(packet, signature, parsed);
+        if batches[batch_index].packets.len() >= batch_size {
+            open_batches.retain(|i| *i != batch_index);
+        }
+    }
+
+    let dropped_packets: usize = batches
+        .iter()
+        .skip(batch_limit)
+        .map(|batch| batch.packets.len())
+        .sum();
+    inc_new_counter_info!("banking_stage-priority_dropped_packets", dropped_packets);
+
+    cache.transactions.clear();
+    buffered_packets.extend(batches.into_iter().take(batch_limit).map(|batch| {
+        cache.transactions.extend(batch.parsed_transactions);
+        let packet_indexes = (0..batch.packets.len()).collect();
+        (Packets::new(batch.packets), packet_indexes)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::genesis_utils::{create_genesis_config, GenesisConfigInfo};
+    use solana_perf::packet::to_packets_chunked;
+    use solana_sdk::{
+        fee_calculator::FeeRateGovernor,
+        hash::Hash,
+        instruction::AccountMeta,
+        message::Message,
+        signature::{Keypair, Signer},
+        system_instruction, system_transaction,
+    };
+
+    fn buffered(transactions: &[Transaction]) -> UnprocessedPackets {
+        to_packets_chunked(transactions, 128)
+            .into_iter()
+            .map(|packets| {
+                let packet_indexes = (0..packets.packets.len()).collect();
+                (packets, packet_indexes)
+            })
+            .collect()
+    }
+
+    fn scheduled(buffered_packets: &[(Packets, Vec<usize>)]) -> Vec<Vec<Transaction>> {
+        buffered_packets
+            .iter()
+            .map(|(packets, packet_indexes)| {
+                packet_indexes
+                    .iter()
+                    .map(|index| {
+                        let packet = &packets.packets[*index];
+                        limited_deserialize(&packet.data[0..packet.meta.size]).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn two_signer_transfer(blockhash: Hash) -> Transaction {
+        let from = Keypair::new();
+        let cosigner = Keypair::new();
+        let mut instruction =
+            system_instruction::transfer(&from.pubkey(), &Pubkey::new_unique(), 1);
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(cosigner.pubkey(), true));
+        let message = Message::new(&[instruction], Some(&from.pubkey()));
+        Transaction::new(&[&from, &cosigner], message, blockhash)
+    }
+
+    #[test]
+    fn test_transaction_priority() {
+        let GenesisConfigInfo {
+            mut genesis_config, ..
+        } = create_genesis_config(10_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(5_000, 0);
+        let bank = Bank::new(&genesis_config);
+        let blockhash = genesis_config.hash();
+        let lamports_per_signature = bank
+            .get_fee_calculator(&blockhash)
+            .unwrap()
+            .lamports_per_signature;
+        let max_units = ComputeBudget::default().max_units;
+
+        let one_signer =
+            system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, blockhash);
+        let priority = TransactionPriority::new(&one_signer, Some(&bank));
+        assert_eq!(
+            priority,
+            TransactionPriority {
+                fee_per_compute_unit: lamports_per_signature * 1_000_000 / max_units,
+                fee_per_signature: lamports_per_signature,
+            }
+        );
+
+        let two_signers = two_signer_transfer(blockhash);
+        assert!(TransactionPriority::new(&two_signers, Some(&bank)) > priority);
+
+        // Two instructions request twice the compute for the same fee
+        let from = Keypair::new();
+        let instructions = vec![
+            system_instruction::transfer(&from.pubkey(), &Pubkey::new_unique(), 1),
+            system_instruction::transfer(&from.pubkey(), &Pubkey::new_unique(), 1),
+        ];
+        let message = Message::new(&instructions, Some(&from.pubkey()));
+        let two_instructions = Transaction::new(&[&from], message, blockhash);
+        assert!(TransactionPriority::new(&two_instructions, Some(&bank)) < priority);
+
+        // Unknown blockhashes pay nothing
+        let unknown_blockhash = system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            1,
+            Hash::default(),
+        );
+        assert_eq!(
+            TransactionPriority::new(&unknown_blockhash, Some(&bank)),
+            TransactionPriority::default()
+        );
+        assert_eq!(
+            TransactionPriority::new(&unknown_blockhash, None).fee_per_signature,
+            DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE
+        );
+    }
+
+    #[test]
+    fn test_prioritize_buffered_packets_order() {
+        let blockhash = Hash::new_unique();
+        let low =
+            system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, blockhash);
+        let high = two_signer_transfer(blockhash);
+
+        let mut buffered_packets = buffered(&[low.clone(), high.clone()]);
+        prioritize_buffered_packets(
+            &mut buffered_packets,
+            None,
+            &mut PriorityCache::default(),
+            128,
+            10,
+        );
+        assert_eq!(scheduled(&buffered_packets), vec![vec![high, low]]);
+    }
+
+    #[test]
+    fn test_prioritize_buffered_packets_conflicts() {
+        let blockhash = Hash::new_unique();
+        let payer = Keypair::new();
+        let first = system_transaction::transfer(&payer, &Pubkey::new_unique(), 1, blockhash);
+        let second = system_transaction::transfer(&payer, &Pubkey::new_unique(), 2, blockhash);
+        let other =
+            system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, blockhash);
+
+        // `second` write locks the same payer as `first`, so it starts a new batch that
+        // `other` would also fit in, but `other` is placed in the earliest batch it fits
+        let mut buffered_packets = buffered(&[first.clone(), second.clone(), other.clone()]);
+        prioritize_buffered_packets(
+            &mut buffered_packets,
+            None,
+            &mut PriorityCache::default(),
+            128,
+            10,
+        );
+        assert_eq!(
+            scheduled(&buffered_packets),
+            vec![vec![first.clone(), other], vec![second.clone()]]
+        );
+
+        // Batches beyond the limit are dropped, along with their cached transactions
+        let mut cache = PriorityCache::default();
+        let mut buffered_packets = buffered(&[first.clone(), second]);
+        prioritize_buffered_packets(&mut buffered_packets, None, &mut cache, 128, 1);
+        assert_eq!(scheduled(&buffered_packets), vec![vec![first.clone()]]);
+        assert_eq!(
+            cache.transactions.keys().collect::<Vec<_>>(),
+            vec![&first.signatures[0]]
+        );
+    }
+
+    #[test]
+    fn test_prioritize_buffered_packets_cache() {
+        let blockhash = Hash::new_unique();
+        let low =
+            system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, blockhash);
+        let high = two_signer_transfer(blockhash);
+        let mut cache = PriorityCache::default();
+        let mut buffered_packets = buffered(&[low.clone(), high.clone()]);
+        prioritize_buffered_packets(&mut buffered_packets, None, &mut cache, 128, 10);
+        assert_eq!(cache.transactions.len(), 2);
+
+        // Buffered packets are scheduled from their cached transactions, so a cached entry
+        // that pays more is preferred over what the packet would parse to
+        let high_fee = cache.transactions[&high.signatures[0]].clone();
+        cache.transactions.insert(low.signatures[0], high_fee);
+        let high_fee = cache.transactions.get_mut(&high.signatures[0]).unwrap();
+        *high_fee = Arc::new(ParsedTransaction::new(&low));
+        prioritize_buffered_packets(&mut buffered_packets, None, &mut cache, 128, 10);
+        assert_eq!(scheduled(&buffered_packets), vec![vec![low, high]]);
+    }
+
+    #[test]
+    fn test_prioritize_buffered_packets_batch_size() {
+        let blockhash = Hash::new_unique();
+        let transactions: Vec<_> = (0..5)
+            .map(|_| {
+                system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, blockhash)
+            })
+            .collect();
+        let mut buffered_packets = buffered(&transactions);
+        prioritize_buffered_packets(
+            &mut buffered_packets,
+            None,
+            &mut PriorityCache::default(),
+            2,
+            10,
+        );
+        assert_eq!(
+            scheduled(&buffered_packets),
+            vec![
+                transactions[0..2].to_vec(),
+                transactions[2..4].to_vec(),
+                transactions[4..5].to_vec()
+            ]
+        );
+    }
+}
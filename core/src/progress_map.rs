@@ -94,6 +94,9 @@ impl ValidatorStakeInfo {
 
 pub(crate) struct ForkProgress {
     pub(crate) is_dead: bool,
+    // Set when a proof that the leader produced two versions of this slot
+    // was received
+    pub(crate) is_duplicate: bool,
     pub(crate) fork_stats: ForkStats,
     pub(crate) propagated_stats: PropagatedStats,
     pub(crate) replay_stats: ReplaySlotStats,
@@ -142,6 +145,7 @@ impl ForkProgress {
             .unwrap_or((false, 0, HashSet::new(), false, 0));
         Self {
             is_dead: false,
+            is_duplicate: false,
             fork_stats: ForkStats::default(),
             replay_stats: ReplaySlotStats::default(),
             replay_progress: ConfirmationProgress::new(last_entry),
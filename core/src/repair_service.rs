@@ -1,6 +1,7 @@
 //! The `repair_service` module implements the tools necessary to generate a thread which
 //! regularly finds missing shreds in the ledger and sends repair requests for those shreds
 use crate::{
+    ancestor_hashes_service::AncestorDuplicateSlotsReceiver,
     cluster_info::ClusterInfo,
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
@@ -108,6 +109,8 @@ pub struct RepairInfo {
     pub bank_forks: Arc<RwLock<BankForks>>,
     pub epoch_schedule: EpochSchedule,
     pub duplicate_slots_reset_sender: DuplicateSlotsResetSender,
+    // Slots `AncestorHashesService` found this node has the wrong version of
+    pub ancestor_duplicate_slots_receiver: AncestorDuplicateSlotsReceiver,
     pub repair_validators: Option<HashSet<Pubkey>>,
 }
 
@@ -178,7 +181,7 @@ impl RepairService {
         let mut repair_stats = RepairStats::default();
         let mut repair_timing = RepairTiming::default();
         let mut last_stats = Instant::now();
-        let duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
+        let mut duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
             HashMap::new();
 
         loop {
@@ -226,7 +229,14 @@ impl RepairService {
                     blockstore,
                     cluster_slots,
                     &root_bank,
-                );
+                );*/
+                // Dump and repair the slots the cluster has a different version of
+                duplicate_slot_repair_statuses.retain(|slot, _| *slot > new_root);
+                let new_duplicate_slots: Vec<Slot> = repair_info
+                    .ancestor_duplicate_slots_receiver
+                    .try_iter()
+                    .filter(|slot| *slot > new_root)
+                    .collect();
                 Self::process_new_duplicate_slots(
                     &new_duplicate_slots,
                     &mut duplicate_slot_repair_statuses,
@@ -245,7 +255,7 @@ impl RepairService {
                     &mut repair_stats,
                     &repair_socket,
                     &repair_info.repair_validators,
-                );*/
+                );
 
                 repair_weight.get_best_weighted_repairs(
                     blockstore,
@@ -415,7 +425,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn generate_duplicate_repairs_for_slot(
         blockstore: &Blockstore,
         slot: Slot,
@@ -440,7 +449,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn generate_and_send_duplicate_repairs(
         duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
         cluster_slots: &ClusterSlots,
@@ -488,7 +496,6 @@ impl RepairService {
         })
     }
 
-    #[allow(dead_code)]
     fn serialize_and_send_request(
         repair_type: &RepairType,
         repair_socket: &UdpSocket,
@@ -504,7 +511,6 @@ impl RepairService {
         Ok(())
     }
 
-    #[allow(dead_code)]
     fn update_duplicate_slot_repair_addr(
         slot: Slot,
         status: &mut DuplicateSlotRepairStatus,
@@ -526,7 +532,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn process_new_duplicate_slots(
         new_duplicate_slots: &[Slot],
         duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
@@ -1,6 +1,7 @@
 //! The `replay_stage` replays transactions broadcast by the leader.
 
 use crate::{
+    account_history_service::{AccountHistoryNotification, AccountHistorySender},
     bank_weight_fork_choice::BankWeightForkChoice,
     broadcast_stage::RetransmitSlotsSender,
     cache_block_time_service::CacheBlockTimeSender,
@@ -9,6 +10,7 @@ use crate::{
     cluster_slots::ClusterSlots,
     commitment_service::{AggregateCommitmentService, CommitmentAggregationData},
     consensus::{ComputedBankState, Stake, SwitchForkDecision, Tower, VotedStakes},
+    duplicate_shred_listener::DuplicateSlotReceiver,
     fork_choice::{ForkChoice, SelectVoteAndResetForkResult},
     heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice,
     optimistically_confirmed_bank_tracker::{BankNotification, BankNotificationSender},
@@ -19,6 +21,7 @@ use crate::{
     result::Result,
     rewards_recorder_service::RewardsRecorderSender,
     rpc_subscriptions::RpcSubscriptions,
+    tower_storage::TowerStorage,
 };
 use solana_ledger::{
     block_error::BlockError,
@@ -47,7 +50,7 @@ use solana_vote_program::{
     vote_state::{Vote, VoteState},
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     ops::Deref,
     result,
     sync::{
@@ -99,7 +102,8 @@ struct SkippedSlotsInfo {
 pub struct ReplayStageConfig {
     pub my_pubkey: Pubkey,
     pub vote_account: Pubkey,
-    pub authorized_voter_keypairs: Vec<Arc<Keypair>>,
+    pub authorized_voter_keypairs: Arc<RwLock<Vec<Arc<Keypair>>>>,
+    pub tower_storage: Arc<dyn TowerStorage>,
     pub exit: Arc<AtomicBool>,
     pub subscriptions: Arc<RpcSubscriptions>,
     pub leader_schedule_cache: Arc<LeaderScheduleCache>,
@@ -109,6 +113,7 @@ pub struct ReplayStageConfig {
     pub transaction_status_sender: Option<TransactionStatusSender>,
     pub rewards_recorder_sender: Option<RewardsRecorderSender>,
     pub cache_block_time_sender: Option<CacheBlockTimeSender>,
+    pub account_history_sender: Option<AccountHistorySender>,
     pub bank_notification_sender: Option<BankNotificationSender>,
 }
 
@@ -227,11 +232,13 @@ impl ReplayStage {
         retransmit_slots_sender: RetransmitSlotsSender,
         duplicate_slots_reset_receiver: DuplicateSlotsResetReceiver,
         replay_vote_sender: ReplayVoteSender,
+        duplicate_slots_receiver: DuplicateSlotReceiver,
     ) -> Self {
         let ReplayStageConfig {
-            my_pubkey,
+            mut my_pubkey,
             vote_account,
             authorized_voter_keypairs,
+            tower_storage,
             exit,
             subscriptions,
             leader_schedule_cache,
@@ -241,6 +248,7 @@ impl ReplayStage {
             transaction_status_sender,
             rewards_recorder_sender,
             cache_block_time_sender,
+            account_history_sender,
             bank_notification_sender,
         } = config;
 
@@ -274,6 +282,7 @@ impl ReplayStage {
                 let mut partition_exists = false;
                 let mut skipped_slots_info = SkippedSlotsInfo::default();
                 let mut replay_timing = ReplayTiming::default();
+                let mut duplicate_slots = BTreeSet::new();
                 loop {
                     let allocated = thread_mem_usage::Allocatedp::default();
 
@@ -283,6 +292,21 @@ impl ReplayStage {
                         break;
                     }
 
+                    // The identity may change at any time, so this pass uses the keypair read
+                    // here for both the identity check and the tower it saves
+                    let identity_keypair = cluster_info.keypair().clone();
+                    if my_pubkey != identity_keypair.pubkey() {
+                        let my_old_pubkey = my_pubkey;
+                        my_pubkey = identity_keypair.pubkey();
+                        tower = Self::load_tower_for_new_identity(
+                            tower_storage.as_ref(),
+                            &my_pubkey,
+                            &bank_forks,
+                        );
+                        poh_recorder.lock().unwrap().set_id(&my_pubkey);
+                        warn!("Identity changed from {} to {}", my_old_pubkey, my_pubkey);
+                    }
+
                     let start = allocated.get();
                     let mut generate_new_bank_forks_time =
                         Measure::start("generate_new_bank_forks_time");
@@ -313,6 +337,7 @@ impl ReplayStage {
                         &replay_vote_sender,
                         &bank_notification_sender,
                         &rewards_recorder_sender,
+                        &account_history_sender,
                     );
                     replay_active_banks_time.stop();
                     Self::report_memory(&allocated, "replay_active_banks", start);
@@ -333,6 +358,14 @@ impl ReplayStage {
                         &mut progress,
                         &bank_forks,
                     );
+                    // Flag the slots other nodes proved the leader produced
+                    // two versions of
+                    Self::process_duplicate_slots(
+                        &duplicate_slots_receiver,
+                        &mut duplicate_slots,
+                        &mut progress,
+                        forks_root,
+                    );
                     reset_duplicate_slots_time.stop();
 
                     let mut collect_frozen_banks_time = Measure::start("frozen_banks");
@@ -451,10 +484,12 @@ impl ReplayStage {
                             switch_fork_decision,
                             &bank_forks,
                             &mut tower,
+                            tower_storage.as_ref(),
                             &mut progress,
                             &vote_account,
-                            &authorized_voter_keypairs,
+                            &authorized_voter_keypairs.read().unwrap(),
                             &cluster_info,
+                            &identity_keypair,
                             &blockstore,
                             &leader_schedule_cache,
                             &lockouts_sender,
@@ -465,6 +500,7 @@ impl ReplayStage {
                             &block_commitment_cache,
                             &mut heaviest_subtree_fork_choice,
                             &cache_block_time_sender,
+                            &account_history_sender,
                             &bank_notification_sender,
                         )?;
                     };
@@ -707,6 +743,28 @@ impl ReplayStage {
         }
     }
 
+    fn process_duplicate_slots(
+        duplicate_slots_receiver: &DuplicateSlotReceiver,
+        duplicate_slots: &mut BTreeSet<Slot>,
+        progress: &mut ProgressMap,
+        root: Slot,
+    ) {
+        duplicate_slots.extend(duplicate_slots_receiver.try_iter());
+        *duplicate_slots = duplicate_slots.split_off(&(root + 1));
+        for duplicate_slot in duplicate_slots.iter() {
+            if let Some(fork_progress) = progress.get_mut(duplicate_slot) {
+                if !fork_progress.is_duplicate {
+                    fork_progress.is_duplicate = true;
+                    warn!("slot {} was proven to be duplicate", duplicate_slot);
+                    datapoint_warn!(
+                        "replay_stage-duplicate_slot",
+                        ("slot", *duplicate_slot as i64, i64),
+                    );
+                }
+            }
+        }
+    }
+
     fn purge_unconfirmed_duplicate_slot(
         duplicate_slot: Slot,
         ancestors: &mut HashMap<Slot, HashSet<Slot>>,
@@ -1019,10 +1077,12 @@ impl ReplayStage {
         switch_fork_decision: &SwitchForkDecision,
         bank_forks: &Arc<RwLock<BankForks>>,
         tower: &mut Tower,
+        tower_storage: &dyn TowerStorage,
         progress: &mut ProgressMap,
         vote_account_pubkey: &Pubkey,
         authorized_voter_keypairs: &[Arc<Keypair>],
         cluster_info: &Arc<ClusterInfo>,
+        identity_keypair: &Arc<Keypair>,
         blockstore: &Arc<Blockstore>,
         leader_schedule_cache: &Arc<LeaderScheduleCache>,
         lockouts_sender: &Sender<CommitmentAggregationData>,
@@ -1033,6 +1093,7 @@ impl ReplayStage {
         block_commitment_cache: &Arc<RwLock<BlockCommitmentCache>>,
         heaviest_subtree_fork_choice: &mut HeaviestSubtreeForkChoice,
         cache_block_time_sender: &Option<CacheBlockTimeSender>,
+        account_history_sender: &Option<AccountHistorySender>,
         bank_notification_sender: &Option<BankNotificationSender>,
     ) -> Result<()> {
         if bank.is_empty() {
@@ -1043,7 +1104,7 @@ impl ReplayStage {
         let new_root = tower.record_bank_vote(vote);
         let last_vote = tower.last_vote_and_timestamp();
 
-        if let Err(err) = tower.save(&cluster_info.keypair) {
+        if let Err(err) = tower.save(tower_storage, identity_keypair) {
             error!("Unable to save tower: {:?}", err);
             std::process::exit(1);
         }
@@ -1059,6 +1120,17 @@ impl ReplayStage {
             let mut rooted_banks = root_bank.parents();
             rooted_banks.push(root_bank.clone());
             let rooted_slots: Vec<_> = rooted_banks.iter().map(|bank| bank.slot()).collect();
+            // Setting the root squashes the root bank, which drops its parents
+            let mut rooted_chain: Vec<_> = rooted_banks
+                .iter()
+                .map(|bank| (bank.slot(), bank.parent_slot()))
+                .collect();
+            rooted_chain.sort_unstable();
+            Self::record_account_history(
+                &rooted_banks,
+                bank_forks.read().unwrap().root(),
+                account_history_sender,
+            );
             // Call leader schedule_cache.set_root() before blockstore.set_root() because
             // bank_forks.root is consumed by repair_service to update gossip, so we don't want to
             // get shreds for repair on gossip before we update leader schedule, otherwise they may
@@ -1093,6 +1165,9 @@ impl ReplayStage {
                 sender
                     .send(BankNotification::Root(root_bank))
                     .unwrap_or_else(|err| warn!("bank_notification_sender failed: {:?}", err));
+                sender
+                    .send(BankNotification::NewRootedChain(rooted_chain))
+                    .unwrap_or_else(|err| warn!("bank_notification_sender failed: {:?}", err));
             }
             latest_root_senders.iter().for_each(|s| {
                 if let Err(e) = s.send(new_root) {
@@ -1110,6 +1185,7 @@ impl ReplayStage {
         );
         Self::push_vote(
             cluster_info,
+            identity_keypair,
             bank,
             vote_account_pubkey,
             authorized_voter_keypairs,
@@ -1122,6 +1198,7 @@ impl ReplayStage {
 
     fn push_vote(
         cluster_info: &ClusterInfo,
+        node_keypair: &Arc<Keypair>,
         bank: &Arc<Bank>,
         vote_account_pubkey: &Pubkey,
         authorized_voter_keypairs: &[Arc<Keypair>],
@@ -1175,8 +1252,6 @@ impl ReplayStage {
             }
             Some(authorized_voter_keypair) => authorized_voter_keypair,
         };
-        let node_keypair = cluster_info.keypair.clone();
-
         // Send our last few votes along with the new one
         let vote_ix = if bank.slot() > Self::get_unlock_switch_vote_slot(bank.cluster_type()) {
             switch_fork_decision
@@ -1203,6 +1278,28 @@ impl ReplayStage {
         cluster_info.push_vote(tower_index, vote_tx);
     }
 
+    // The tower of the new identity must already exist, since switching
+    // identities without it risks voting against earlier lockouts.
+    fn load_tower_for_new_identity(
+        tower_storage: &dyn TowerStorage,
+        my_pubkey: &Pubkey,
+        bank_forks: &RwLock<BankForks>,
+    ) -> Tower {
+        Tower::restore(tower_storage, my_pubkey)
+            .and_then(|tower| {
+                let root_bank = bank_forks.read().unwrap().root_bank().clone();
+                let slot_history = root_bank.get_slot_history();
+                tower.adjust_lockouts_after_replay(root_bank.slot(), &slot_history)
+            })
+            .unwrap_or_else(|err| {
+                error!(
+                    "Unable to load the tower of the new identity {}: {}",
+                    my_pubkey, err
+                );
+                std::process::exit(1);
+            })
+    }
+
     fn update_commitment_cache(
         bank: Arc<Bank>,
         root: Slot,
@@ -1263,6 +1360,7 @@ impl ReplayStage {
         replay_vote_sender: &ReplayVoteSender,
         bank_notification_sender: &Option<BankNotificationSender>,
         rewards_recorder_sender: &Option<RewardsRecorderSender>,
+        account_history_sender: &Option<AccountHistorySender>,
     ) -> bool {
         let mut did_complete_bank = false;
         let mut tx_count = 0;
@@ -1331,6 +1429,14 @@ impl ReplayStage {
                 did_complete_bank = true;
                 info!("bank frozen: {}", bank.slot());
                 bank.freeze();
+                // Serve the hash to peers asking which version of the slot this node has
+                if let Err(err) = blockstore.insert_bank_hash(bank.slot(), bank.hash()) {
+                    warn!(
+                        "failed to store bank hash of slot {}: {:?}",
+                        bank.slot(),
+                        err
+                    );
+                }
                 heaviest_subtree_fork_choice
                     .add_new_leaf_slot(bank.slot(), Some(bank.parent_slot()));
                 if let Some(sender) = bank_notification_sender {
@@ -1340,6 +1446,11 @@ impl ReplayStage {
                 }
 
                 Self::record_rewards(&bank, &rewards_recorder_sender);
+                if let Some(sender) = account_history_sender {
+                    sender
+                        .send(AccountHistoryNotification::Frozen(bank.clone()))
+                        .unwrap_or_else(|err| warn!("account_history_sender failed: {:?}", err));
+                }
             } else {
                 trace!(
                     "bank {} not completed tick_height: {}, max_tick_height: {}",
@@ -1906,6 +2017,23 @@ impl ReplayStage {
         }
     }
 
+    fn record_account_history(
+        rooted_banks: &[Arc<Bank>],
+        old_root: Slot,
+        account_history_sender: &Option<AccountHistorySender>,
+    ) {
+        if let Some(account_history_sender) = account_history_sender {
+            let new_rooted_banks = rooted_banks
+                .iter()
+                .filter(|bank| bank.slot() > old_root)
+                .cloned()
+                .collect();
+            account_history_sender
+                .send(AccountHistoryNotification::Root(new_rooted_banks))
+                .unwrap_or_else(|err| warn!("account_history_sender failed: {:?}", err));
+        }
+    }
+
     fn cache_block_times(
         blockstore: &Arc<Blockstore>,
         bank_forks: &Arc<RwLock<BankForks>>,
@@ -2487,9 +2615,9 @@ pub(crate) mod tests {
 
         assert_matches!(
             res,
-            Err(
-                BlockstoreProcessorError::FailedToLoadEntries(BlockstoreError::InvalidShredData(_)),
-            )
+            Err(BlockstoreProcessorError::FailedToLoadEntries(
+                BlockstoreError::InvalidShredData(_)
+            ),)
         );
     }
 
@@ -3728,6 +3856,27 @@ pub(crate) mod tests {
         assert!(progress.get(&0).is_some());
     }
 
+    #[test]
+    fn test_process_duplicate_slots() {
+        let (_bank_forks, mut progress) = setup_forks();
+        let (sender, receiver) = unbounded();
+        let mut duplicate_slots = BTreeSet::new();
+        for slot in &[1, 5, 10] {
+            sender.send(*slot).unwrap();
+        }
+        ReplayStage::process_duplicate_slots(&receiver, &mut duplicate_slots, &mut progress, 1);
+        assert!(!progress.get(&1).unwrap().is_duplicate);
+        assert!(progress.get(&5).unwrap().is_duplicate);
+        assert!(!progress.get(&6).unwrap().is_duplicate);
+
+        // Slots not replayed yet are flagged once they are
+        assert_eq!(duplicate_slots, vec![5, 10].into_iter().collect());
+        progress.insert(10, ForkProgress::new(Hash::default(), None, None, 0, 0));
+        ReplayStage::process_duplicate_slots(&receiver, &mut duplicate_slots, &mut progress, 6);
+        assert!(progress.get(&10).unwrap().is_duplicate);
+        assert_eq!(duplicate_slots, vec![10].into_iter().collect());
+    }
+
     #[test]
     fn test_purge_ancestors_descendants() {
         let (bank_forks, _) = setup_forks();
@@ -1,6 +1,7 @@
 //! The `retransmit_stage` retransmits shreds between validators
 
 use crate::{
+    ancestor_hashes_service::AncestorDuplicateSlotsReceiver,
     cluster_info::{compute_retransmit_peers, ClusterInfo, DATA_PLANE_FANOUT},
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
@@ -418,6 +419,7 @@ impl RetransmitStage {
         shred_version: u16,
         cluster_slots: Arc<ClusterSlots>,
         duplicate_slots_reset_sender: DuplicateSlotsResetSender,
+        ancestor_duplicate_slots_receiver: AncestorDuplicateSlotsReceiver,
         verified_vote_receiver: VerifiedVoteReceiver,
         repair_validators: Option<HashSet<Pubkey>>,
         completed_data_sets_sender: CompletedDataSetsSender,
@@ -446,6 +448,7 @@ impl RetransmitStage {
             bank_forks,
             epoch_schedule,
             duplicate_slots_reset_sender,
+            ancestor_duplicate_slots_receiver,
             repair_validators,
         };
         let window_service = WindowService::new(
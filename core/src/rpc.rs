@@ -46,7 +46,7 @@ use solana_runtime::{
 use solana_sdk::{
     account::Account,
     account_utils::StateMut,
-    clock::{Slot, UnixTimestamp},
+    clock::{Slot, UnixTimestamp, MAX_PROCESSING_AGE},
     commitment_config::{CommitmentConfig, CommitmentLevel},
     epoch_info::EpochInfo,
     epoch_schedule::EpochSchedule,
@@ -71,6 +71,7 @@ use std::{
     cmp::{max, min},
     collections::{HashMap, HashSet},
     net::SocketAddr,
+    path::PathBuf,
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -84,7 +85,11 @@ pub const MAX_REQUEST_PAYLOAD_SIZE: usize = 50 * (1 << 10); // 50kB
 pub const PERFORMANCE_SAMPLES_LIMIT: usize = 720;
 
 fn new_response<T>(bank: &Bank, value: T) -> RpcResponse<T> {
-    let context = RpcResponseContext { slot: bank.slot() };
+    new_response_at_slot(bank.slot(), value)
+}
+
+fn new_response_at_slot<T>(slot: Slot, value: T) -> RpcResponse<T> {
+    let context = RpcResponseContext { slot };
     Response { context, value }
 }
 
@@ -103,11 +108,16 @@ pub struct JsonRpcConfig {
     pub enable_validator_exit: bool,
     pub enable_set_log_filter: bool,
     pub enable_rpc_transaction_history: bool,
+    pub enable_rpc_account_history: bool,
     pub identity_pubkey: Pubkey,
     pub faucet_addr: Option<SocketAddr>,
     pub health_check_slot_distance: u64,
     pub enable_bigtable_ledger_storage: bool,
     pub enable_bigtable_ledger_upload: bool,
+    /// Keep long-term ledger storage in flat files under this path instead of BigTable
+    pub local_ledger_storage_path: Option<PathBuf>,
+    /// Send transactions to the leader's TPU over QUIC, falling back to UDP
+    pub send_transactions_over_quic: bool,
 }
 
 #[derive(Clone)]
@@ -122,7 +132,7 @@ pub struct JsonRpcRequestProcessor {
     genesis_hash: Hash,
     transaction_sender: Arc<Mutex<Sender<TransactionInfo>>>,
     runtime_handle: runtime::Handle,
-    bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+    bigtable_ledger_storage: Option<Arc<dyn solana_storage_bigtable::LedgerStorage>>,
     optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
@@ -201,7 +211,7 @@ impl JsonRpcRequestProcessor {
         cluster_info: Arc<ClusterInfo>,
         genesis_hash: Hash,
         runtime: &runtime::Runtime,
-        bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+        bigtable_ledger_storage: Option<Arc<dyn solana_storage_bigtable::LedgerStorage>>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
     ) -> (Self, Receiver<TransactionInfo>) {
         let (sender, receiver) = channel();
@@ -236,7 +246,7 @@ impl JsonRpcRequestProcessor {
         let cluster_info = Arc::new(ClusterInfo::default());
         let tpu_address = cluster_info.my_contact_info().tpu;
         let (sender, receiver) = channel();
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
 
         Self {
             config: JsonRpcConfig::default(),
@@ -270,6 +280,16 @@ impl JsonRpcRequestProcessor {
         let encoding = config.encoding.unwrap_or(UiAccountEncoding::Binary);
         check_slice_and_encoding(&encoding, config.data_slice.is_some())?;
 
+        if let Some(slot) = config.slot {
+            check_min_context_slot(slot, config.min_context_slot)?;
+            let data_slice = config.data_slice;
+            let response = self
+                .get_account_at_slot(pubkey, slot)?
+                .map(|account| encode_account(&bank, pubkey, account, encoding, data_slice))
+                .transpose()?;
+            return Ok(new_response_at_slot(slot, response));
+        }
+        check_min_context_slot(bank.slot(), config.min_context_slot)?;
         let response = get_encoded_account(&bank, pubkey, encoding, config.data_slice)?;
         Ok(new_response(&bank, response))
     }
@@ -286,6 +306,21 @@ impl JsonRpcRequestProcessor {
         let encoding = config.encoding.unwrap_or(UiAccountEncoding::Base64);
         check_slice_and_encoding(&encoding, config.data_slice.is_some())?;
 
+        if let Some(slot) = config.slot {
+            check_min_context_slot(slot, config.min_context_slot)?;
+            let data_slice = config.data_slice;
+            for pubkey in pubkeys {
+                let response_account = self
+                    .get_account_at_slot(&pubkey, slot)?
+                    .map(|account| {
+                        encode_account(&bank, &pubkey, account, encoding.clone(), data_slice)
+                    })
+                    .transpose()?;
+                accounts.push(response_account)
+            }
+            return Ok(new_response_at_slot(slot, accounts));
+        }
+        check_min_context_slot(bank.slot(), config.min_context_slot)?;
         for pubkey in pubkeys {
             let response_account =
                 get_encoded_account(&bank, &pubkey, encoding.clone(), config.data_slice)?;
@@ -294,6 +329,50 @@ impl JsonRpcRequestProcessor {
         Ok(new_response(&bank, accounts))
     }
 
+    // Loads the state of `pubkey` as of `slot`, from BankForks while that bank is still
+    // available, or else from the account history archive
+    fn get_account_at_slot(&self, pubkey: &Pubkey, slot: Slot) -> Result<Option<Account>> {
+        if let Some(bank) = self.bank_forks.read().unwrap().get(slot) {
+            return Ok(bank.get_account(pubkey));
+        }
+        let not_available = || Error::from(RpcCustomError::AccountStateNotAvailable { slot });
+        if !self.config.enable_rpc_account_history || !self.blockstore.is_root(slot) {
+            return Err(not_available());
+        }
+        match self.blockstore.get_first_account_history_root() {
+            Ok(Some(first_root)) if first_root <= slot => {}
+            _ => return Err(not_available()),
+        }
+        match self.blockstore.get_account_history(pubkey, slot) {
+            Ok(Some((_, account))) => Ok(Some(account).filter(|account| account.lamports > 0)),
+            Ok(None) => {
+                // Accounts without history have not changed since archiving began, so the root
+                // bank holds their state at `slot`. That only holds once the service has archived
+                // every root up to the root bank; until then a later change may still be in flight
+                let bank = self.bank_forks.read().unwrap().root_bank().clone();
+                match self.blockstore.get_last_account_history_root() {
+                    Ok(Some(last_root)) if last_root >= bank.slot() => {}
+                    _ => return Err(not_available()),
+                }
+                // If the only archived versions are newer than `slot`, its state at `slot`
+                // predates the archive
+                if self
+                    .blockstore
+                    .has_account_history_after(pubkey, slot)
+                    .unwrap_or(true)
+                {
+                    Err(not_available())
+                } else {
+                    Ok(bank.get_account(pubkey))
+                }
+            }
+            Err(err) => {
+                warn!("get_account_history failed: {:?}", err);
+                Err(not_available())
+            }
+        }
+    }
+
     pub fn get_minimum_balance_for_rent_exemption(
         &self,
         data_len: usize,
@@ -417,6 +496,28 @@ impl JsonRpcRequestProcessor {
         )
     }
 
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: Option<CommitmentConfig>,
+    ) -> RpcResponse<bool> {
+        let bank = self.bank(commitment);
+        // Rooted blockhashes are checked against the slot they were produced in, which may be
+        // stricter than the queue when slots were skipped. Blockhashes of slots that are not
+        // rooted yet are only found in the bank's queue
+        let is_valid = match self
+            .blockstore
+            .get_blockhash_slot(blockhash)
+            .unwrap_or(None)
+        {
+            Some(slot) => slot <= bank.slot() && bank.slot() - slot <= MAX_PROCESSING_AGE as Slot,
+            None => bank
+                .check_hash_age(blockhash, MAX_PROCESSING_AGE)
+                .unwrap_or(false),
+        };
+        new_response(&bank, is_valid)
+    }
+
     fn get_fee_rate_governor(&self) -> RpcResponse<RpcFeeRateGovernor> {
         let bank = self.bank(None);
         let fee_rate_governor = bank.get_fee_rate_governor();
@@ -655,6 +756,31 @@ impl JsonRpcRequestProcessor {
         }
     }
 
+    pub fn get_block_by_hash(
+        &self,
+        blockhash: &Hash,
+        encoding: Option<UiTransactionEncoding>,
+    ) -> Result<Option<EncodedConfirmedBlock>> {
+        if self.config.enable_rpc_transaction_history {
+            if let Some(slot) = self
+                .blockstore
+                .get_blockhash_slot(blockhash)
+                .unwrap_or(None)
+            {
+                if slot
+                    <= self
+                        .block_commitment_cache
+                        .read()
+                        .unwrap()
+                        .highest_confirmed_root()
+                {
+                    return self.get_confirmed_block(slot, encoding);
+                }
+            }
+        }
+        Ok(None)
+    }
+
     pub fn get_confirmed_blocks(
         &self,
         start_slot: Slot,
@@ -1267,12 +1393,15 @@ impl JsonRpcRequestProcessor {
     }
 }
 
-fn verify_transaction(transaction: &Transaction) -> Result<()> {
+fn verify_transaction(transaction: &Transaction, bank: &Bank) -> Result<()> {
     if transaction.verify().is_err() {
         return Err(RpcCustomError::TransactionSignatureVerificationFailure.into());
     }
 
-    if let Err(e) = transaction.verify_precompiles() {
+    if let Err(e) = transaction.verify_precompiles_with_features(
+        bank.secp256k1_program_enabled(),
+        bank.ed25519_program_enabled(),
+    ) {
         return Err(RpcCustomError::TransactionPrecompileVerificationFailure(e).into());
     }
 
@@ -1338,26 +1467,43 @@ fn get_encoded_account(
     encoding: UiAccountEncoding,
     data_slice: Option<UiDataSliceConfig>,
 ) -> Result<Option<UiAccount>> {
-    let mut response = None;
-    if let Some(account) = bank.get_account(pubkey) {
-        if account.owner == spl_token_id_v2_0() && encoding == UiAccountEncoding::JsonParsed {
-            response = Some(get_parsed_token_account(bank.clone(), pubkey, account));
-        } else if (encoding == UiAccountEncoding::Binary || encoding == UiAccountEncoding::Base58)
-            && account.data.len() > 128
-        {
-            let message = "Encoded binary (base 58) data should be less than 128 bytes, please use Base64 encoding.".to_string();
-            return Err(error::Error {
-                code: error::ErrorCode::InvalidRequest,
-                message,
-                data: None,
-            });
-        } else {
-            response = Some(UiAccount::encode(
-                pubkey, account, encoding, None, data_slice,
-            ));
+    bank.get_account(pubkey)
+        .map(|account| encode_account(bank, pubkey, account, encoding, data_slice))
+        .transpose()
+}
+
+fn check_min_context_slot(context_slot: Slot, min_context_slot: Option<Slot>) -> Result<()> {
+    match min_context_slot {
+        Some(min_context_slot) if context_slot < min_context_slot => {
+            Err(RpcCustomError::MinContextSlotNotReached { context_slot }.into())
         }
+        _ => Ok(()),
+    }
+}
+
+fn encode_account(
+    bank: &Arc<Bank>,
+    pubkey: &Pubkey,
+    account: Account,
+    encoding: UiAccountEncoding,
+    data_slice: Option<UiDataSliceConfig>,
+) -> Result<UiAccount> {
+    if account.owner == spl_token_id_v2_0() && encoding == UiAccountEncoding::JsonParsed {
+        Ok(get_parsed_token_account(bank.clone(), pubkey, account))
+    } else if (encoding == UiAccountEncoding::Binary || encoding == UiAccountEncoding::Base58)
+        && account.data.len() > 128
+    {
+        let message = "Encoded binary (base 58) data should be less than 128 bytes, please use Base64 encoding.".to_string();
+        Err(error::Error {
+            code: error::ErrorCode::InvalidRequest,
+            message,
+            data: None,
+        })
+    } else {
+        Ok(UiAccount::encode(
+            pubkey, account, encoding, None, data_slice,
+        ))
     }
-    Ok(response)
 }
 
 /// Use a set of filters to get an iterator of keyed program accounts from a bank
@@ -1623,6 +1769,14 @@ pub trait RpcSol {
         commitment: Option<CommitmentConfig>,
     ) -> Result<RpcResponse<Option<RpcFeeCalculator>>>;
 
+    #[rpc(meta, name = "isBlockhashValid")]
+    fn is_blockhash_valid(
+        &self,
+        meta: Self::Metadata,
+        blockhash: String,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<RpcResponse<bool>>;
+
     #[rpc(meta, name = "getFeeRateGovernor")]
     fn get_fee_rate_governor(
         &self,
@@ -1731,6 +1885,14 @@ pub trait RpcSol {
         encoding: Option<UiTransactionEncoding>,
     ) -> Result<Option<EncodedConfirmedBlock>>;
 
+    #[rpc(meta, name = "getBlockByHash")]
+    fn get_block_by_hash(
+        &self,
+        meta: Self::Metadata,
+        blockhash: String,
+        encoding: Option<UiTransactionEncoding>,
+    ) -> Result<Option<EncodedConfirmedBlock>>;
+
     #[rpc(meta, name = "getBlockTime")]
     fn get_block_time(&self, meta: Self::Metadata, slot: Slot) -> Result<Option<UnixTimestamp>>;
 
@@ -2128,6 +2290,18 @@ impl RpcSol for RpcSolImpl {
         Ok(meta.get_fee_calculator_for_blockhash(&blockhash, commitment))
     }
 
+    fn is_blockhash_valid(
+        &self,
+        meta: Self::Metadata,
+        blockhash: String,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<RpcResponse<bool>> {
+        debug!("is_blockhash_valid rpc request received: {:?}", blockhash);
+        let blockhash =
+            Hash::from_str(&blockhash).map_err(|e| Error::invalid_params(format!("{:?}", e)))?;
+        Ok(meta.is_blockhash_valid(&blockhash, commitment))
+    }
+
     fn get_fee_rate_governor(
         &self,
         meta: Self::Metadata,
@@ -2286,7 +2460,7 @@ impl RpcSol for RpcSolImpl {
             .unwrap_or(0);
 
         if !config.skip_preflight {
-            if let Err(e) = verify_transaction(&transaction) {
+            if let Err(e) = verify_transaction(&transaction, bank) {
                 return Err(e);
             }
 
@@ -2323,14 +2497,14 @@ impl RpcSol for RpcSolImpl {
         let config = config.unwrap_or_default();
         let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Base58);
         let (_, transaction) = deserialize_transaction(data, encoding)?;
+        let bank = &*meta.bank(config.commitment);
 
         if config.sig_verify {
-            if let Err(e) = verify_transaction(&transaction) {
+            if let Err(e) = verify_transaction(&transaction, bank) {
                 return Err(e);
             }
         }
 
-        let bank = &*meta.bank(config.commitment);
         let (result, logs) = bank.simulate_transaction(transaction);
 
         Ok(new_response(
@@ -2428,6 +2602,18 @@ impl RpcSol for RpcSolImpl {
         meta.get_confirmed_blocks_with_limit(start_slot, limit)
     }
 
+    fn get_block_by_hash(
+        &self,
+        meta: Self::Metadata,
+        blockhash: String,
+        encoding: Option<UiTransactionEncoding>,
+    ) -> Result<Option<EncodedConfirmedBlock>> {
+        debug!("get_block_by_hash rpc request received: {:?}", blockhash);
+        let blockhash =
+            Hash::from_str(&blockhash).map_err(|e| Error::invalid_params(format!("{:?}", e)))?;
+        meta.get_block_by_hash(&blockhash, encoding)
+    }
+
     fn get_block_time(&self, meta: Self::Metadata, slot: Slot) -> Result<Option<UnixTimestamp>> {
         meta.get_block_time(slot)
     }
@@ -2859,7 +3045,7 @@ pub mod tests {
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
 
         cluster_info.insert_info(ContactInfo::new_with_pubkey_socketaddr(
             &leader_pubkey,
@@ -3555,6 +3741,151 @@ pub mod tests {
         result["error"].as_object().unwrap();
     }
 
+    #[test]
+    fn test_rpc_get_account_info_at_slot() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let genesis = create_genesis_config(100);
+        let bank = Arc::new(Bank::new(&genesis.genesis_config));
+        bank.transfer(20, &genesis.mint_keypair, &bob_pubkey)
+            .unwrap();
+        let mut request_processor = JsonRpcRequestProcessor::new_from_bank(&bank);
+
+        let address = solana_sdk::pubkey::new_rand();
+        let later_address = solana_sdk::pubkey::new_rand();
+        let blockstore = request_processor.blockstore.clone();
+        blockstore.set_roots(&[2, 5]).unwrap();
+        blockstore
+            .write_account_history(2, &[(address, Account::new(2, 0, &Pubkey::default()))])
+            .unwrap();
+        blockstore
+            .write_account_history(
+                5,
+                &[
+                    (address, Account::new(0, 0, &Pubkey::default())),
+                    (later_address, Account::new(5, 0, &Pubkey::default())),
+                ],
+            )
+            .unwrap();
+
+        let config = |slot| {
+            Some(RpcAccountInfoConfig {
+                slot: Some(slot),
+                ..RpcAccountInfoConfig::default()
+            })
+        };
+        let lamports = |response: RpcResponse<Option<UiAccount>>| {
+            response.value.map(|account| account.lamports)
+        };
+
+        // Banks still in BankForks are served without the archive
+        let response = request_processor
+            .get_account_info(&bob_pubkey, config(0))
+            .unwrap();
+        assert_eq!(response.context.slot, 0);
+        assert_eq!(lamports(response), Some(20));
+        assert!(request_processor
+            .get_account_info(&address, config(2))
+            .is_err());
+
+        request_processor.config.enable_rpc_account_history = true;
+        let response = request_processor
+            .get_account_info(&address, config(2))
+            .unwrap();
+        assert_eq!(response.context.slot, 2);
+        assert_eq!(lamports(response), Some(2));
+        // Skipped slots have no account state of their own
+        let response = request_processor
+            .get_account_info(&address, config(4))
+            .unwrap_err();
+        assert_eq!(
+            response,
+            RpcCustomError::AccountStateNotAvailable { slot: 4 }.into()
+        );
+        assert_eq!(
+            lamports(
+                request_processor
+                    .get_account_info(&address, config(5))
+                    .unwrap()
+            ),
+            None
+        );
+        // Unchanged since archiving began
+        assert_eq!(
+            lamports(
+                request_processor
+                    .get_account_info(&bob_pubkey, config(2))
+                    .unwrap()
+            ),
+            Some(20)
+        );
+        // Only changed after the requested slot, so its state predates the archive
+        assert!(request_processor
+            .get_account_info(&later_address, config(2))
+            .is_err());
+
+        let response = request_processor
+            .get_multiple_accounts(vec![address, bob_pubkey, later_address], config(5))
+            .unwrap();
+        assert_eq!(response.context.slot, 5);
+        assert_eq!(
+            response
+                .value
+                .into_iter()
+                .map(|account| account.map(|account| account.lamports))
+                .collect::<Vec<_>>(),
+            vec![None, Some(20), Some(5)]
+        );
+
+        let min_context_slot = |slot, min_context_slot| {
+            Some(RpcAccountInfoConfig {
+                slot,
+                min_context_slot: Some(min_context_slot),
+                ..RpcAccountInfoConfig::default()
+            })
+        };
+        assert_eq!(
+            request_processor
+                .get_account_info(&bob_pubkey, min_context_slot(None, 1))
+                .unwrap_err(),
+            RpcCustomError::MinContextSlotNotReached { context_slot: 0 }.into()
+        );
+        assert_eq!(
+            request_processor
+                .get_multiple_accounts(vec![address], min_context_slot(Some(2), 3))
+                .unwrap_err(),
+            RpcCustomError::MinContextSlotNotReached { context_slot: 2 }.into()
+        );
+        assert_eq!(
+            lamports(
+                request_processor
+                    .get_account_info(&address, min_context_slot(Some(2), 2))
+                    .unwrap()
+            ),
+            Some(2)
+        );
+
+        // Unchanged accounts are only served from the root bank once every root up to it has
+        // been archived
+        let root_bank = Arc::new(Bank::new_from_parent(&bank, &Pubkey::default(), 7));
+        request_processor.bank_forks =
+            Arc::new(RwLock::new(BankForks::new_from_banks(&[root_bank], 7)));
+        assert_eq!(
+            request_processor
+                .get_account_info(&bob_pubkey, config(2))
+                .unwrap_err(),
+            RpcCustomError::AccountStateNotAvailable { slot: 2 }.into()
+        );
+        blockstore.write_account_history(7, &[]).unwrap();
+        assert_eq!(
+            lamports(
+                request_processor
+                    .get_account_info(&bob_pubkey, config(2))
+                    .unwrap()
+            ),
+            Some(20)
+        );
+    }
+
     #[test]
     fn test_rpc_get_program_accounts() {
         let bob = Keypair::new();
@@ -4152,6 +4483,45 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_is_blockhash_valid() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, bank, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        // A rooted blockhash is valid while its slot is within the bank's age window, even
+        // once it has left the blockhash queue
+        let rooted_blockhash = hash(&[42]);
+        meta.blockstore.set_roots(&[0]).unwrap();
+        meta.blockstore
+            .cache_blockhash(0, &rooted_blockhash)
+            .unwrap();
+        let blockhash = bank.last_blockhash();
+        for (blockhash, is_valid) in &[
+            (blockhash, true),
+            (rooted_blockhash, true),
+            (Hash::default(), false),
+        ] {
+            let req = format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"isBlockhashValid","params":["{:?}"]}}"#,
+                blockhash
+            );
+            let res = io.handle_request_sync(&req, meta.clone());
+            let expected = json!({
+                "jsonrpc": "2.0",
+                "result": {
+                    "context":{"slot":0},
+                    "value":is_valid,
+                },
+                "id": 1
+            });
+            let expected: Response =
+                serde_json::from_value(expected).expect("expected response deserialization");
+            let result: Response = serde_json::from_str(&res.expect("actual response"))
+                .expect("actual response deserialization");
+            assert_eq!(expected, result);
+        }
+    }
+
     #[test]
     fn test_rpc_get_fee_rate_governor() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4251,7 +4621,7 @@ pub mod tests {
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
 
         let mut bad_transaction = system_transaction::transfer(
             &mint_keypair,
@@ -4447,7 +4817,7 @@ pub mod tests {
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
         assert_eq!(request_processor.validator_exit(), false);
         assert_eq!(exit.load(Ordering::Relaxed), false);
     }
@@ -4477,7 +4847,7 @@ pub mod tests {
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
         assert_eq!(request_processor.validator_exit(), true);
         assert_eq!(exit.load(Ordering::Relaxed), true);
     }
@@ -4566,7 +4936,7 @@ pub mod tests {
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
         assert_eq!(
             request_processor.get_block_commitment(0),
             RpcBlockCommitment {
@@ -4742,6 +5112,42 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_get_block_by_hash() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let confirmed_block = meta.blockstore.get_confirmed_block(0).unwrap();
+        let blockhash = Hash::from_str(&confirmed_block.blockhash).unwrap();
+
+        // Not yet indexed
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getBlockByHash","params":["{}"]}}"#,
+            blockhash
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], Value::Null);
+
+        meta.blockstore.cache_blockhash(0, &blockhash).unwrap();
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let block: Option<EncodedConfirmedBlock> =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        let block = block.unwrap();
+        assert_eq!(block.blockhash, confirmed_block.blockhash);
+        assert_eq!(block.parent_slot, confirmed_block.parent_slot);
+        assert_eq!(block.transactions.len(), 3);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getBlockByHash","params":["bad"]}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["error"]["code"], -32602);
+    }
+
     #[test]
     fn test_get_confirmed_blocks() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
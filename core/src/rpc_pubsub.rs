@@ -584,6 +584,8 @@ mod tests {
                 commitment: Some(CommitmentConfig::recent()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
         );
 
@@ -693,6 +695,8 @@ mod tests {
                 commitment: Some(CommitmentConfig::recent()),
                 encoding: Some(UiAccountEncoding::JsonParsed),
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
         );
 
@@ -815,6 +819,8 @@ mod tests {
                 commitment: Some(CommitmentConfig::root()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
         );
 
@@ -869,6 +875,8 @@ mod tests {
                 commitment: Some(CommitmentConfig::root()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
         );
 
@@ -16,6 +16,7 @@ use jsonrpc_http_server::{
     RequestMiddlewareAction, ServerBuilder,
 };
 use regex::Regex;
+use solana_client::quic_client::QuicClient;
 use solana_ledger::blockstore::Blockstore;
 use solana_runtime::{
     bank_forks::{BankForks, SnapshotConfig},
@@ -275,11 +276,12 @@ impl JsonRpcService {
         let (bigtable_ledger_storage, _bigtable_ledger_upload_service) =
             if config.enable_bigtable_ledger_storage || config.enable_bigtable_ledger_upload {
                 runtime
-                    .block_on(solana_storage_bigtable::LedgerStorage::new(
+                    .block_on(solana_storage_bigtable::new_ledger_storage(
+                        config.local_ledger_storage_path.clone(),
                         !config.enable_bigtable_ledger_upload,
                     ))
                     .map(|bigtable_ledger_storage| {
-                        info!("BigTable ledger storage initialized");
+                        info!("Long-term ledger storage initialized");
 
                         let bigtable_ledger_upload_service = Arc::new(BigTableUploadService::new(
                             runtime.handle().clone(),
@@ -295,13 +297,21 @@ impl JsonRpcService {
                         )
                     })
                     .unwrap_or_else(|err| {
-                        error!("Failed to initialize BigTable ledger storage: {:?}", err);
+                        error!("Failed to initialize long-term ledger storage: {:?}", err);
                         (None, None)
                     })
             } else {
                 (None, None)
             };
 
+        let quic_client = if config.send_transactions_over_quic {
+            QuicClient::new(&cluster_info.keypair())
+                .map_err(|err| error!("Failed to create QUIC client: {:?}", err))
+                .ok()
+        } else {
+            None
+        };
+
         let (request_processor, receiver) = JsonRpcRequestProcessor::new(
             config,
             bank_forks.clone(),
@@ -323,6 +333,7 @@ impl JsonRpcService {
             &bank_forks,
             leader_info,
             receiver,
+            quic_client,
         ));
 
         #[cfg(test)]
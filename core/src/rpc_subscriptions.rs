@@ -1077,6 +1077,8 @@ pub(crate) mod tests {
                 commitment: Some(CommitmentConfig::recent()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
             sub_id.clone(),
             subscriber,
@@ -1621,6 +1623,8 @@ pub(crate) mod tests {
                 commitment: Some(CommitmentConfig::single_gossip()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
             sub_id0.clone(),
             subscriber0,
@@ -1702,6 +1706,8 @@ pub(crate) mod tests {
                 commitment: Some(CommitmentConfig::single_gossip()),
                 encoding: None,
                 data_slice: None,
+                slot: None,
+                min_context_slot: None,
             }),
             sub_id1.clone(),
             subscriber1,
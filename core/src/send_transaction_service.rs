@@ -2,6 +2,7 @@
 use crate::cluster_info::ClusterInfo;
 use crate::poh_recorder::PohRecorder;
 use log::*;
+use solana_client::quic_client::QuicClient;
 use solana_metrics::{datapoint_warn, inc_new_counter_info};
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
@@ -88,8 +89,15 @@ impl SendTransactionService {
         bank_forks: &Arc<RwLock<BankForks>>,
         leader_info: Option<LeaderInfo>,
         receiver: Receiver<TransactionInfo>,
+        quic_client: Option<QuicClient>,
     ) -> Self {
-        let thread = Self::retry_thread(tpu_address, receiver, bank_forks.clone(), leader_info);
+        let thread = Self::retry_thread(
+            tpu_address,
+            receiver,
+            bank_forks.clone(),
+            leader_info,
+            quic_client,
+        );
         Self { thread }
     }
 
@@ -98,6 +106,7 @@ impl SendTransactionService {
         receiver: Receiver<TransactionInfo>,
         bank_forks: Arc<RwLock<BankForks>>,
         mut leader_info: Option<LeaderInfo>,
+        quic_client: Option<QuicClient>,
     ) -> JoinHandle<()> {
         let mut last_status_check = Instant::now();
         let mut transactions = HashMap::new();
@@ -120,6 +129,7 @@ impl SendTransactionService {
                             .unwrap_or(&tpu_address);
                         Self::send_transaction(
                             &send_socket,
+                            &quic_client,
                             address,
                             &transaction_info.wire_transaction,
                         );
@@ -145,6 +155,7 @@ impl SendTransactionService {
                             &working_bank,
                             &root_bank,
                             &send_socket,
+                            &quic_client,
                             &tpu_address,
                             &mut transactions,
                             &leader_info,
@@ -163,6 +174,7 @@ impl SendTransactionService {
         working_bank: &Arc<Bank>,
         root_bank: &Arc<Bank>,
         send_socket: &UdpSocket,
+        quic_client: &Option<QuicClient>,
         tpu_address: &SocketAddr,
         transactions: &mut HashMap<Signature, TransactionInfo>,
         leader_info: &Option<LeaderInfo>,
@@ -190,6 +202,7 @@ impl SendTransactionService {
                         inc_new_counter_info!("send_transaction_service-retry", 1);
                         Self::send_transaction(
                             &send_socket,
+                            quic_client,
                             leader_info
                                 .as_ref()
                                 .and_then(|leader_info| leader_info.get_leader_tpu())
@@ -218,10 +231,17 @@ impl SendTransactionService {
 
     fn send_transaction(
         send_socket: &UdpSocket,
+        quic_client: &Option<QuicClient>,
         tpu_address: &SocketAddr,
         wire_transaction: &[u8],
     ) {
-        if let Err(err) = send_socket.send_to(wire_transaction, tpu_address) {
+        let result = match quic_client {
+            Some(quic_client) => quic_client.send_wire_transaction(tpu_address, wire_transaction),
+            None => send_socket
+                .send_to(wire_transaction, tpu_address)
+                .map(|_| ()),
+        };
+        if let Err(err) = result {
             warn!("Failed to send transaction to {}: {:?}", tpu_address, err);
         }
     }
@@ -248,7 +268,7 @@ mod test {
         let (sender, receiver) = channel();
 
         let send_tranaction_service =
-            SendTransactionService::new(tpu_address, &bank_forks, None, receiver);
+            SendTransactionService::new(tpu_address, &bank_forks, None, receiver, None);
 
         drop(sender);
         send_tranaction_service.join().unwrap();
@@ -299,6 +319,7 @@ mod test {
             &working_bank,
             &root_bank,
             &send_socket,
+            &None,
             &tpu_address,
             &mut transactions,
             &None,
@@ -321,6 +342,7 @@ mod test {
             &working_bank,
             &root_bank,
             &send_socket,
+            &None,
             &tpu_address,
             &mut transactions,
             &None,
@@ -343,6 +365,7 @@ mod test {
             &working_bank,
             &root_bank,
             &send_socket,
+            &None,
             &tpu_address,
             &mut transactions,
             &None,
@@ -365,6 +388,7 @@ mod test {
             &working_bank,
             &root_bank,
             &send_socket,
+            &None,
             &tpu_address,
             &mut transactions,
             &None,
@@ -388,6 +412,7 @@ mod test {
             &working_bank,
             &root_bank,
             &send_socket,
+            &None,
             &tpu_address,
             &mut transactions,
             &None,
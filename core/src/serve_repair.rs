@@ -12,19 +12,16 @@ use solana_ledger::{blockstore::Blockstore, shred::Nonce};
 use solana_measure::measure::Measure;
 use solana_measure::thread_mem_usage;
 use solana_metrics::{datapoint_debug, inc_new_counter_debug};
-use solana_perf::packet::{limited_deserialize, Packets, PacketsRecycler};
+use solana_perf::packet::{limited_deserialize, Packet, Packets, PacketsRecycler};
 use solana_sdk::{
-    clock::Slot,
-    pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    timing::duration_as_ms,
+    clock::Slot, hash::Hash, pubkey::Pubkey, signature::Keypair, timing::duration_as_ms,
 };
 use solana_streamer::streamer::{PacketReceiver, PacketSender};
 use std::{
     collections::{HashMap, HashSet},
     net::SocketAddr,
     sync::atomic::{AtomicBool, Ordering},
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, RwLockReadGuard},
     thread::{Builder, JoinHandle},
     time::{Duration, Instant},
 };
@@ -32,6 +29,9 @@ use std::{
 /// the number of slots to respond with when responding to `Orphan` requests
 pub const MAX_ORPHAN_REPAIR_RESPONSES: usize = 10;
 pub const DEFAULT_NONCE: u32 = 42;
+/// the number of (slot, bank hash) pairs to respond with when responding to `AncestorHashes`
+/// requests, so that the response fits in a packet
+pub const MAX_ANCESTOR_RESPONSES: usize = 30;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum RepairType {
@@ -59,6 +59,7 @@ pub struct ServeRepairStats {
     pub window_index: usize,
     pub highest_window_index: usize,
     pub orphan: usize,
+    pub ancestor_hashes: usize,
 }
 
 /// Window protocol messages
@@ -70,13 +71,20 @@ pub enum RepairProtocol {
     WindowIndexWithNonce(ContactInfo, u64, u64, Nonce),
     HighestWindowIndexWithNonce(ContactInfo, u64, u64, Nonce),
     OrphanWithNonce(ContactInfo, u64, Nonce),
+    AncestorHashes(ContactInfo, Slot, Nonce),
+}
+
+/// Response to an `AncestorHashes` request: the hashes of the banks this node froze for the
+/// requested slot and its ancestors, most recent first
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AncestorHashesResponse {
+    pub slot: Slot,
+    pub nonce: Nonce,
+    pub ancestor_hashes: Vec<(Slot, Hash)>,
 }
 
 #[derive(Clone)]
 pub struct ServeRepair {
-    /// set the keypair that will be used to sign repair responses
-    keypair: Arc<Keypair>,
-    my_info: ContactInfo,
     cluster_info: Arc<ClusterInfo>,
 }
 
@@ -91,20 +99,19 @@ impl ServeRepair {
     }
 
     pub fn new(cluster_info: Arc<ClusterInfo>) -> Self {
-        let (keypair, my_info) = { (cluster_info.keypair.clone(), cluster_info.my_contact_info()) };
-        Self {
-            keypair,
-            my_info,
-            cluster_info,
-        }
+        Self { cluster_info }
     }
 
-    pub fn my_info(&self) -> &ContactInfo {
-        &self.my_info
+    pub fn my_info(&self) -> ContactInfo {
+        self.cluster_info.my_contact_info()
     }
 
-    pub fn keypair(&self) -> &Arc<Keypair> {
-        &self.keypair
+    pub fn keypair(&self) -> RwLockReadGuard<Arc<Keypair>> {
+        self.cluster_info.keypair()
+    }
+
+    fn my_id(&self) -> Pubkey {
+        self.cluster_info.id()
     }
 
     fn get_repair_sender(request: &RepairProtocol) -> &ContactInfo {
@@ -115,6 +122,7 @@ impl ServeRepair {
             RepairProtocol::WindowIndexWithNonce(ref from, _, _, _) => from,
             RepairProtocol::HighestWindowIndexWithNonce(ref from, _, _, _) => from,
             RepairProtocol::OrphanWithNonce(ref from, _, _) => from,
+            RepairProtocol::AncestorHashes(ref from, _, _) => from,
         }
     }
 
@@ -129,7 +137,7 @@ impl ServeRepair {
         let now = Instant::now();
 
         //TODO verify from is signed
-        let my_id = me.read().unwrap().keypair.pubkey();
+        let my_id = me.read().unwrap().my_id();
         let from = Self::get_repair_sender(&request);
         if from.id == my_id {
             stats.self_repair += 1;
@@ -146,7 +154,7 @@ impl ServeRepair {
                             from,
                             &from_addr,
                             blockstore,
-                            &me.read().unwrap().my_info,
+                            &me.read().unwrap().my_info(),
                             *slot,
                             *shred_index,
                             *nonce,
@@ -182,6 +190,13 @@ impl ServeRepair {
                         "OrphanWithNonce",
                     )
                 }
+                RepairProtocol::AncestorHashes(_, slot, nonce) => {
+                    stats.ancestor_hashes += 1;
+                    (
+                        Self::run_ancestor_hashes(recycler, &from_addr, blockstore, *slot, *nonce),
+                        "AncestorHashes",
+                    )
+                }
                 _ => (None, "Unsupported repair type"),
             }
         };
@@ -244,7 +259,7 @@ impl ServeRepair {
 
     fn report_reset_stats(me: &Arc<RwLock<Self>>, stats: &mut ServeRepairStats) {
         if stats.self_repair > 0 {
-            let my_id = me.read().unwrap().keypair.pubkey();
+            let my_id = me.read().unwrap().my_id();
             warn!(
                 "{}: Ignored received repair requests from ME: {}",
                 my_id, stats.self_repair,
@@ -266,6 +281,10 @@ impl ServeRepair {
             stats.highest_window_index
         );
         inc_new_counter_debug!("serve_repair-request-orphan", stats.orphan);
+        inc_new_counter_debug!(
+            "serve_repair-request-ancestor-hashes",
+            stats.ancestor_hashes
+        );
 
         *stats = ServeRepairStats::default();
     }
@@ -348,8 +367,7 @@ impl ServeRepair {
         shred_index: u64,
         nonce: Nonce,
     ) -> Result<Vec<u8>> {
-        let req =
-            RepairProtocol::WindowIndexWithNonce(self.my_info.clone(), slot, shred_index, nonce);
+        let req = RepairProtocol::WindowIndexWithNonce(self.my_info(), slot, shred_index, nonce);
         let out = serialize(&req)?;
         Ok(out)
     }
@@ -360,18 +378,20 @@ impl ServeRepair {
         shred_index: u64,
         nonce: Nonce,
     ) -> Result<Vec<u8>> {
-        let req = RepairProtocol::HighestWindowIndexWithNonce(
-            self.my_info.clone(),
-            slot,
-            shred_index,
-            nonce,
-        );
+        let req =
+            RepairProtocol::HighestWindowIndexWithNonce(self.my_info(), slot, shred_index, nonce);
         let out = serialize(&req)?;
         Ok(out)
     }
 
     fn orphan_bytes(&self, slot: Slot, nonce: Nonce) -> Result<Vec<u8>> {
-        let req = RepairProtocol::OrphanWithNonce(self.my_info.clone(), slot, nonce);
+        let req = RepairProtocol::OrphanWithNonce(self.my_info(), slot, nonce);
+        let out = serialize(&req)?;
+        Ok(out)
+    }
+
+    pub fn ancestor_hashes_request_bytes(&self, slot: Slot, nonce: Nonce) -> Result<Vec<u8>> {
+        let req = RepairProtocol::AncestorHashes(self.my_info(), slot, nonce);
         let out = serialize(&req)?;
         Ok(out)
     }
@@ -450,7 +470,7 @@ impl ServeRepair {
         }
     }
 
-    fn repair_peers(
+    pub(crate) fn repair_peers(
         &self,
         repair_validators: &Option<HashSet<Pubkey>>,
         slot: Slot,
@@ -459,7 +479,7 @@ impl ServeRepair {
             repair_validators
                 .iter()
                 .filter_map(|key| {
-                    if *key != self.my_info.id {
+                    if *key != self.my_id() {
                         self.cluster_info.lookup_contact_info(key, |ci| ci.clone())
                     } else {
                         None
@@ -581,6 +601,45 @@ impl ServeRepair {
         }
         Some(res)
     }
+
+    fn run_ancestor_hashes(
+        recycler: &PacketsRecycler,
+        from_addr: &SocketAddr,
+        blockstore: Option<&Arc<Blockstore>>,
+        slot: Slot,
+        nonce: Nonce,
+    ) -> Option<Packets> {
+        let blockstore = blockstore?;
+        let mut ancestor_hashes = vec![];
+        let mut ancestor = slot;
+        // Walk up the fork for as long as this node froze a bank for each ancestor
+        while let Some(bank_hash) = blockstore.get_bank_hash(ancestor) {
+            ancestor_hashes.push((ancestor, bank_hash));
+            if ancestor_hashes.len() >= MAX_ANCESTOR_RESPONSES {
+                break;
+            }
+            match blockstore.meta(ancestor) {
+                Ok(Some(meta)) if meta.is_parent_set() && meta.parent_slot < ancestor => {
+                    ancestor = meta.parent_slot;
+                }
+                _ => break,
+            }
+        }
+        if ancestor_hashes.is_empty() {
+            inc_new_counter_debug!("serve_repair-ancestor-hashes-request-fail", 1);
+            return None;
+        }
+        let response = AncestorHashesResponse {
+            slot,
+            nonce,
+            ancestor_hashes,
+        };
+        Some(Packets::new_with_recycler_data(
+            recycler,
+            "run_ancestor_hashes",
+            vec![Packet::from_data(from_addr, response)],
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -829,6 +888,56 @@ mod tests {
         assert!(one && two);
     }
 
+    #[test]
+    fn test_run_ancestor_hashes() {
+        let recycler = PacketsRecycler::default();
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Arc::new(Blockstore::open(&ledger_path).unwrap());
+            let num_slots = 40;
+            let (shreds, _) = make_many_slot_entries(0, num_slots, 1);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            let bank_hashes: HashMap<_, _> = (5..num_slots)
+                .map(|slot| (slot, Hash::new_unique()))
+                .collect();
+            for (slot, bank_hash) in &bank_hashes {
+                blockstore.insert_bank_hash(*slot, *bank_hash).unwrap();
+            }
+            let run_ancestor_hashes = |slot| {
+                let packets = ServeRepair::run_ancestor_hashes(
+                    &recycler,
+                    &socketaddr_any!(),
+                    Some(&blockstore),
+                    slot,
+                    9,
+                )?;
+                assert_eq!(packets.packets.len(), 1);
+                let packet = &packets.packets[0];
+                let response: AncestorHashesResponse =
+                    limited_deserialize(&packet.data[..packet.meta.size]).unwrap();
+                assert_eq!(response.slot, slot);
+                assert_eq!(response.nonce, 9);
+                Some(response.ancestor_hashes)
+            };
+            let expected = |slots: &mut dyn Iterator<Item = Slot>| -> Vec<_> {
+                slots.map(|slot| (slot, bank_hashes[&slot])).collect()
+            };
+
+            // Responses stop at the first ancestor without a frozen bank
+            assert_eq!(run_ancestor_hashes(8), Some(expected(&mut (5..=8).rev())));
+            // and are bounded to fit in a packet
+            assert_eq!(
+                run_ancestor_hashes(num_slots - 1),
+                Some(expected(
+                    &mut (num_slots - MAX_ANCESTOR_RESPONSES as u64..num_slots).rev()
+                ))
+            );
+            assert_eq!(run_ancestor_hashes(4), None);
+            assert_eq!(run_ancestor_hashes(num_slots), None);
+        }
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_run_orphan() {
         run_orphan(2, 3, 9);
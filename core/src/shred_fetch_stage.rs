@@ -3,13 +3,13 @@
 use bv::BitVec;
 use solana_ledger::blockstore::MAX_DATA_SHREDS_PER_SLOT;
 use solana_ledger::shred::{
-    CODING_SHRED, DATA_SHRED, OFFSET_OF_SHRED_INDEX, OFFSET_OF_SHRED_SLOT, OFFSET_OF_SHRED_TYPE,
-    SIZE_OF_SHRED_INDEX, SIZE_OF_SHRED_SLOT,
+    CODING_SHRED, DATA_SHRED, MERKLE_CODING_SHRED, MERKLE_DATA_SHRED, OFFSET_OF_SHRED_INDEX,
+    OFFSET_OF_SHRED_SLOT, OFFSET_OF_SHRED_TYPE, SIZE_OF_SHRED_INDEX, SIZE_OF_SHRED_SLOT,
 };
 use solana_perf::cuda_runtime::PinnedVec;
 use solana_perf::packet::{limited_deserialize, Packet, PacketsRecycler};
 use solana_perf::recycler::Recycler;
-use solana_runtime::bank_forks::BankForks;
+use solana_runtime::{bank::Bank, bank_forks::BankForks, feature::Feature, feature_set};
 use solana_sdk::clock::Slot;
 use solana_streamer::streamer::{self, PacketReceiver, PacketSender};
 use std::collections::HashMap;
@@ -32,6 +32,31 @@ struct ShredFetchStats {
     slot_bad_deserialize: usize,
     duplicate_shred: usize,
     slot_out_of_range: usize,
+    inactive_merkle_shred: usize,
+}
+
+/// Returns the first slot whose shreds may be Merkle shreds, which is the first slot of the
+/// epoch the `merkle_shreds` feature was activated in
+pub(crate) fn first_merkle_shred_slot(bank: &Bank) -> Option<Slot> {
+    let account = bank.get_account(&feature_set::merkle_shreds::id())?;
+    let activated_at = Feature::from_account(&account)?.activated_at?;
+    let epoch_schedule = bank.epoch_schedule();
+    Some(epoch_schedule.get_first_slot_in_epoch(epoch_schedule.get_epoch(activated_at)))
+}
+
+/// Merkle shreds are dropped until the `merkle_shreds` feature is active for their slot
+pub(crate) fn is_merkle_shred_allowed(
+    shred_type: u8,
+    slot: Slot,
+    merkle_shreds_first_slot: Option<Slot>,
+) -> bool {
+    if shred_type != MERKLE_DATA_SHRED && shred_type != MERKLE_CODING_SHRED {
+        return true;
+    }
+    match merkle_shreds_first_slot {
+        Some(first_slot) => slot >= first_slot,
+        None => false,
+    }
 }
 
 pub struct ShredFetchStage {
@@ -72,6 +97,7 @@ impl ShredFetchStage {
         last_root: Slot,
         last_slot: Slot,
         slots_per_epoch: u64,
+        merkle_shreds_first_slot: Option<Slot>,
         modify: &F,
     ) where
         F: Fn(&mut Packet),
@@ -84,7 +110,13 @@ impl ShredFetchStage {
                 && p.meta.size > OFFSET_OF_SHRED_TYPE
             {
                 let shred_type = p.data[OFFSET_OF_SHRED_TYPE];
-                if shred_type == DATA_SHRED || shred_type == CODING_SHRED {
+                if !is_merkle_shred_allowed(shred_type, slot, merkle_shreds_first_slot) {
+                    stats.inactive_merkle_shred += 1;
+                } else if shred_type == DATA_SHRED
+                    || shred_type == CODING_SHRED
+                    || shred_type == MERKLE_DATA_SHRED
+                    || shred_type == MERKLE_CODING_SHRED
+                {
                     // Shred filter
                     let slot_received =
                         shreds_received
@@ -123,6 +155,7 @@ impl ShredFetchStage {
         let mut last_root = 0;
         let mut last_slot = std::u64::MAX;
         let mut slots_per_epoch = 0;
+        let mut merkle_shreds_first_slot = Some(0);
 
         let mut last_stats = Instant::now();
         let mut stats = ShredFetchStats::default();
@@ -138,6 +171,7 @@ impl ShredFetchStage {
                     last_slot = working_bank.slot();
                     let root_bank = bank_forks_r.root_bank();
                     slots_per_epoch = root_bank.get_slots_in_epoch(root_bank.epoch());
+                    merkle_shreds_first_slot = first_merkle_shred_slot(&working_bank);
                 }
             }
             stats.shred_count += p.packets.len();
@@ -149,6 +183,7 @@ impl ShredFetchStage {
                     last_root,
                     last_slot,
                     slots_per_epoch,
+                    merkle_shreds_first_slot,
                     &modify,
                 );
             });
@@ -162,6 +197,7 @@ impl ShredFetchStage {
                     ("index_out_of_bounds", stats.index_out_of_bounds, i64),
                     ("slot_out_of_range", stats.slot_out_of_range, i64),
                     ("duplicate_shred", stats.duplicate_shred, i64),
+                    ("inactive_merkle_shred", stats.inactive_merkle_shred, i64),
                 );
                 stats = ShredFetchStats::default();
                 last_stats = Instant::now();
@@ -289,6 +325,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(!packet.meta.discard);
@@ -303,6 +340,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(!packet.meta.discard);
@@ -325,6 +363,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert_eq!(stats.index_overrun, 1);
@@ -340,6 +379,7 @@ mod tests {
             3,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(packet.meta.discard);
@@ -352,6 +392,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(!packet.meta.discard);
@@ -364,6 +405,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(packet.meta.discard);
@@ -379,6 +421,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(packet.meta.discard);
@@ -393,11 +436,54 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            Some(0),
             &|_p| {},
         );
         assert!(packet.meta.discard);
     }
 
+    #[test]
+    fn test_merkle_shred_filter() {
+        solana_logger::setup();
+        let mut shreds_received = ShredsReceived::default();
+        let mut packet = Packet::default();
+        let mut stats = ShredFetchStats::default();
+        let last_root = 0;
+        let last_slot = 100;
+        let slots_per_epoch = 10;
+        let shred = Shred::new_from_data(12, 3, 0, None, true, true, 0, 0, 0);
+        shred.copy_to_packet(&mut packet);
+        packet.data[OFFSET_OF_SHRED_TYPE] = MERKLE_DATA_SHRED;
+
+        // Dropped while the feature is inactive, or active from a later epoch
+        for merkle_shreds_first_slot in &[None, Some(20)] {
+            ShredFetchStage::process_packet(
+                &mut packet,
+                &mut shreds_received,
+                &mut stats,
+                last_root,
+                last_slot,
+                slots_per_epoch,
+                *merkle_shreds_first_slot,
+                &|_p| {},
+            );
+            assert!(packet.meta.discard);
+        }
+        assert_eq!(stats.inactive_merkle_shred, 2);
+
+        ShredFetchStage::process_packet(
+            &mut packet,
+            &mut shreds_received,
+            &mut stats,
+            last_root,
+            last_slot,
+            slots_per_epoch,
+            Some(10),
+            &|_p| {},
+        );
+        assert!(!packet.meta.discard);
+    }
+
     #[test]
     fn test_shred_offsets() {
         let shred = Shred::new_from_data(1, 3, 0, None, true, true, 0, 0, 0);
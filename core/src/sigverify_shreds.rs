@@ -1,12 +1,14 @@
 #![allow(clippy::implicit_hasher)]
+use crate::shred_fetch_stage::{first_merkle_shred_slot, is_merkle_shred_allowed};
 use crate::sigverify;
 use crate::sigverify_stage::SigVerifier;
 use solana_ledger::leader_schedule_cache::LeaderScheduleCache;
-use solana_ledger::shred::{OFFSET_OF_SHRED_SLOT, SIZE_OF_SHRED_SLOT};
-use solana_ledger::sigverify_shreds::verify_shreds_gpu;
+use solana_ledger::shred::{OFFSET_OF_SHRED_SLOT, OFFSET_OF_SHRED_TYPE, SIZE_OF_SHRED_SLOT};
+use solana_ledger::sigverify_shreds::{verify_shreds_gpu, MerkleRootCache};
 use solana_perf::packet::{limited_deserialize, Packets};
 use solana_perf::recycler_cache::RecyclerCache;
 use solana_runtime::bank_forks::BankForks;
+use solana_sdk::clock::Slot;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
@@ -15,6 +17,7 @@ pub struct ShredSigVerifier {
     bank_forks: Arc<RwLock<BankForks>>,
     leader_schedule_cache: Arc<LeaderScheduleCache>,
     recycler_cache: RecyclerCache,
+    merkle_root_cache: Arc<MerkleRootCache>,
 }
 
 impl ShredSigVerifier {
@@ -27,6 +30,7 @@ impl ShredSigVerifier {
             bank_forks,
             leader_schedule_cache,
             recycler_cache: RecyclerCache::warmed(),
+            merkle_root_cache: Arc::new(MerkleRootCache::default()),
         }
     }
     fn read_slots(batches: &[Packets]) -> HashSet<u64> {
@@ -46,11 +50,35 @@ impl ShredSigVerifier {
             })
             .collect()
     }
+
+    // Shreds from the TVU sockets skip the fetch stage filter, so Merkle shreds are dropped
+    // here too until the feature is active for their slot
+    fn discard_inactive_merkle_shreds(
+        batches: &mut [Packets],
+        merkle_shreds_first_slot: Option<Slot>,
+    ) {
+        let slot_end = OFFSET_OF_SHRED_SLOT + SIZE_OF_SHRED_SLOT;
+        for packet in batches
+            .iter_mut()
+            .flat_map(|batch| batch.packets.iter_mut())
+        {
+            if packet.meta.discard || packet.meta.size < slot_end {
+                continue;
+            }
+            let shred_type = packet.data[OFFSET_OF_SHRED_TYPE];
+            if let Ok(slot) = limited_deserialize(&packet.data[OFFSET_OF_SHRED_SLOT..slot_end]) {
+                if !is_merkle_shred_allowed(shred_type, slot, merkle_shreds_first_slot) {
+                    packet.meta.discard = true;
+                }
+            }
+        }
+    }
 }
 
 impl SigVerifier for ShredSigVerifier {
     fn verify_batch(&self, mut batches: Vec<Packets>) -> Vec<Packets> {
         let r_bank = self.bank_forks.read().unwrap().working_bank();
+        Self::discard_inactive_merkle_shreds(&mut batches, first_merkle_shred_slot(&r_bank));
         let slots: HashSet<u64> = Self::read_slots(&batches);
         let mut leader_slots: HashMap<u64, [u8; 32]> = slots
             .into_iter()
@@ -63,7 +91,12 @@ impl SigVerifier for ShredSigVerifier {
             .collect();
         leader_slots.insert(std::u64::MAX, [0u8; 32]);
 
-        let r = verify_shreds_gpu(&batches, &leader_slots, &self.recycler_cache);
+        let r = verify_shreds_gpu(
+            &batches,
+            &leader_slots,
+            &self.recycler_cache,
+            &self.merkle_root_cache,
+        );
         sigverify::mark_disabled(&mut batches, &r);
         batches
     }
@@ -73,7 +106,7 @@ impl SigVerifier for ShredSigVerifier {
 pub mod tests {
     use super::*;
     use solana_ledger::genesis_utils::create_genesis_config_with_leader;
-    use solana_ledger::shred::{Shred, Shredder};
+    use solana_ledger::shred::{Shred, Shredder, MERKLE_DATA_SHRED};
     use solana_perf::packet::Packet;
     use solana_runtime::bank::Bank;
     use solana_sdk::signature::{Keypair, Signer};
@@ -168,4 +201,22 @@ pub mod tests {
         assert_eq!(rv[0].packets[0].meta.discard, false);
         assert_eq!(rv[0].packets[1].meta.discard, true);
     }
+
+    #[test]
+    fn test_sigverify_shreds_discard_inactive_merkle_shreds() {
+        let shred = Shred::new_from_data(5, 0xc0de, 0xdead, None, true, true, 0, 0, 0xc0de);
+        let mut batch = vec![Packets::default()];
+        batch[0].packets.resize(2, Packet::default());
+        shred.copy_to_packet(&mut batch[0].packets[0]);
+        shred.copy_to_packet(&mut batch[0].packets[1]);
+        batch[0].packets[1].data[OFFSET_OF_SHRED_TYPE] = MERKLE_DATA_SHRED;
+
+        ShredSigVerifier::discard_inactive_merkle_shreds(&mut batch, Some(5));
+        assert!(!batch[0].packets[0].meta.discard);
+        assert!(!batch[0].packets[1].meta.discard);
+
+        ShredSigVerifier::discard_inactive_merkle_shreds(&mut batch, None);
+        assert!(!batch[0].packets[0].meta.discard);
+        assert!(batch[0].packets[1].meta.discard);
+    }
 }
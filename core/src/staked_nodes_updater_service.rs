@@ -0,0 +1,60 @@
+//! The `staked_nodes_updater_service` keeps the stakes the QUIC TPU listener sizes stream
+//! limits by in sync with the epoch of the root bank.
+
+use solana_ledger::staking_utils::staked_nodes_at_epoch;
+use solana_runtime::bank_forks::BankForks;
+use solana_streamer::quic::StakedNodes;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::Duration,
+};
+
+const UPDATE_INTERVAL_MS: u64 = 5_000;
+const SLEEP_INTERVAL_MS: u64 = 100;
+
+pub struct StakedNodesUpdaterService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl StakedNodesUpdaterService {
+    pub fn new(
+        exit: &Arc<AtomicBool>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        staked_nodes: &Arc<RwLock<StakedNodes>>,
+    ) -> Self {
+        let exit = exit.clone();
+        let bank_forks = bank_forks.clone();
+        let staked_nodes = staked_nodes.clone();
+        let thread_hdl = Builder::new()
+            .name("sol-staked-nodes".to_string())
+            .spawn(move || {
+                let mut elapsed_ms = UPDATE_INTERVAL_MS;
+                while !exit.load(Ordering::Relaxed) {
+                    if elapsed_ms >= UPDATE_INTERVAL_MS {
+                        Self::update(&bank_forks, &staked_nodes);
+                        elapsed_ms = 0;
+                    }
+                    sleep(Duration::from_millis(SLEEP_INTERVAL_MS));
+                    elapsed_ms += SLEEP_INTERVAL_MS;
+                }
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn update(bank_forks: &RwLock<BankForks>, staked_nodes: &RwLock<StakedNodes>) {
+        let root_bank = bank_forks.read().unwrap().root_bank().clone();
+        if let Some(stakes) = staked_nodes_at_epoch(&root_bank, root_bank.epoch()) {
+            *staked_nodes.write().unwrap() = StakedNodes::new(stakes);
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
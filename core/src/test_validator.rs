@@ -2,7 +2,7 @@ use crate::{
     cluster_info::Node,
     contact_info::ContactInfo,
     gossip_service::discover_cluster,
-    validator::{Validator, ValidatorConfig},
+    validator::{Validator, ValidatorConfig, ValidatorStartProgress},
 };
 use solana_ledger::create_new_tmp_ledger;
 use solana_sdk::{
@@ -10,7 +10,10 @@ use solana_sdk::{
     pubkey::Pubkey,
     signature::{Keypair, Signer},
 };
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 pub struct TestValidator {
     pub server: Validator,
@@ -87,9 +90,10 @@ impl TestValidator {
             &node_keypair,
             &ledger_path,
             &voting_keypair.pubkey(),
-            vec![Arc::new(voting_keypair)],
+            Arc::new(RwLock::new(vec![Arc::new(voting_keypair)])),
             None,
             &config,
+            &Arc::new(RwLock::new(ValidatorStartProgress::default())),
         );
         discover_cluster(&contact_info.gossip, 1).expect("Node startup failed");
         TestValidator {
@@ -0,0 +1,373 @@
+//! The `tower_storage` module defines where a validator persists its tower between votes.
+//!
+//! The tower is stored as a signed `SavedTower`. `FileTowerStorage` keeps it in a local
+//! directory, while `NetworkTowerStorage` keeps it in a shared key-value server so that a
+//! standby validator can take over with the primary's latest lockouts.
+
+use crate::consensus::{Result, SavedTower, TowerError};
+use serde::{de::DeserializeOwned, Serialize};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::Duration,
+};
+
+pub trait TowerStorage: fmt::Debug + Send + Sync {
+    /// Load the saved tower of `node_pubkey`. A missing tower is reported as an
+    /// `io::ErrorKind::NotFound` error so that `TowerError::is_file_missing()` holds.
+    fn load(&self, node_pubkey: &Pubkey) -> Result<SavedTower>;
+
+    /// Persist the saved tower of `node_pubkey`
+    fn store(&self, node_pubkey: &Pubkey, saved_tower: &SavedTower) -> Result<()>;
+}
+
+/// Stores each tower in a `tower-<node_pubkey>.bin` file in `tower_path`
+#[derive(Debug)]
+pub struct FileTowerStorage {
+    pub tower_path: PathBuf,
+}
+
+impl FileTowerStorage {
+    pub fn new(tower_path: PathBuf) -> Self {
+        Self { tower_path }
+    }
+
+    pub fn filename(&self, node_pubkey: &Pubkey) -> PathBuf {
+        self.tower_path
+            .join(format!("tower-{}", node_pubkey))
+            .with_extension("bin")
+    }
+
+    fn tmp_filename(&self, node_pubkey: &Pubkey) -> PathBuf {
+        self.filename(node_pubkey).with_extension("bin.new")
+    }
+}
+
+impl TowerStorage for FileTowerStorage {
+    fn load(&self, node_pubkey: &Pubkey) -> Result<SavedTower> {
+        let filename = self.filename(node_pubkey);
+
+        // Ensure to create parent dir here, because load() precedes store() always
+        fs::create_dir_all(&filename.parent().unwrap())?;
+
+        let file = File::open(&filename)?;
+        let mut stream = BufReader::new(file);
+        Ok(bincode::deserialize_from(&mut stream)?)
+    }
+
+    fn store(&self, node_pubkey: &Pubkey, saved_tower: &SavedTower) -> Result<()> {
+        let filename = self.filename(node_pubkey);
+        let new_filename = self.tmp_filename(node_pubkey);
+        {
+            // overwrite anything if exists
+            let mut file = File::create(&new_filename)?;
+            bincode::serialize_into(&mut file, saved_tower)?;
+            // file.sync_all() hurts performance; pipeline sync-ing and submitting votes to the cluster!
+        }
+        fs::rename(&new_filename, &filename)?;
+        // self.tower_path.sync_all() hurts performance same as the above sync
+        Ok(())
+    }
+}
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum TowerStorageRequest {
+    Load {
+        node_pubkey: Pubkey,
+    },
+    // Replace the tower of `node_pubkey` only if the last vote slot of the currently stored
+    // tower is `expected_last_vote_slot`. A missing tower counts as having no last vote.
+    CompareAndSwap {
+        node_pubkey: Pubkey,
+        expected_last_vote_slot: Option<Slot>,
+        last_vote_slot: Option<Slot>,
+        saved_tower: SavedTower,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum TowerStorageResponse {
+    Loaded(Option<SavedTower>),
+    Swapped,
+    Conflict { last_vote_slot: Option<Slot> },
+}
+
+fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<()> {
+    let data = bincode::serialize(message)?;
+    stream.write_all(&(data.len() as u32).to_le_bytes())?;
+    stream.write_all(&data)?;
+    Ok(())
+}
+
+fn read_message<T: DeserializeOwned>(stream: &mut TcpStream) -> Result<T> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(TowerError::StorageError(format!(
+            "message of {} bytes exceeds the limit of {} bytes",
+            len, MAX_MESSAGE_SIZE
+        )));
+    }
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data)?;
+    Ok(bincode::deserialize(&data)?)
+}
+
+/// Stores towers in a `TowerStorageServer` reachable at `server_addr`.
+///
+/// Every store is a compare-and-swap against the last vote slot of the tower this instance
+/// most recently loaded or stored, so once another validator instance has stored a newer
+/// tower for the same identity, this instance can no longer overwrite it.
+#[derive(Debug)]
+pub struct NetworkTowerStorage {
+    server_addr: SocketAddr,
+    last_vote_slots: Mutex<HashMap<Pubkey, Option<Slot>>>,
+}
+
+impl NetworkTowerStorage {
+    pub fn new(server_addr: SocketAddr) -> Self {
+        Self {
+            server_addr,
+            last_vote_slots: Mutex::default(),
+        }
+    }
+
+    fn request(&self, request: &TowerStorageRequest) -> Result<TowerStorageResponse> {
+        let mut stream = TcpStream::connect_timeout(&self.server_addr, NETWORK_TIMEOUT)?;
+        stream.set_read_timeout(Some(NETWORK_TIMEOUT))?;
+        stream.set_write_timeout(Some(NETWORK_TIMEOUT))?;
+        write_message(&mut stream, request)?;
+        read_message(&mut stream)
+    }
+}
+
+impl TowerStorage for NetworkTowerStorage {
+    fn load(&self, node_pubkey: &Pubkey) -> Result<SavedTower> {
+        let response = self.request(&TowerStorageRequest::Load {
+            node_pubkey: *node_pubkey,
+        })?;
+        let saved_tower = match response {
+            TowerStorageResponse::Loaded(saved_tower) => saved_tower,
+            response => {
+                return Err(TowerError::StorageError(format!(
+                    "unexpected response: {:?}",
+                    response
+                )))
+            }
+        };
+        let last_vote_slot = match &saved_tower {
+            Some(saved_tower) => saved_tower.deserialize()?.last_voted_slot(),
+            None => None,
+        };
+        self.last_vote_slots
+            .lock()
+            .unwrap()
+            .insert(*node_pubkey, last_vote_slot);
+        saved_tower.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no tower stored for {}", node_pubkey),
+            )
+            .into()
+        })
+    }
+
+    fn store(&self, node_pubkey: &Pubkey, saved_tower: &SavedTower) -> Result<()> {
+        let last_vote_slot = saved_tower.deserialize()?.last_voted_slot();
+        let mut last_vote_slots = self.last_vote_slots.lock().unwrap();
+        let expected_last_vote_slot = last_vote_slots.get(node_pubkey).copied().flatten();
+        let response = self.request(&TowerStorageRequest::CompareAndSwap {
+            node_pubkey: *node_pubkey,
+            expected_last_vote_slot,
+            last_vote_slot,
+            saved_tower: saved_tower.clone(),
+        })?;
+        match response {
+            TowerStorageResponse::Swapped => {
+                last_vote_slots.insert(*node_pubkey, last_vote_slot);
+                Ok(())
+            }
+            TowerStorageResponse::Conflict { last_vote_slot } => {
+                Err(TowerError::StorageError(format!(
+                    "tower of {} was modified by another instance: expected last vote slot \
+                     {:?}, found {:?}",
+                    node_pubkey, expected_last_vote_slot, last_vote_slot
+                )))
+            }
+            response => Err(TowerError::StorageError(format!(
+                "unexpected response: {:?}",
+                response
+            ))),
+        }
+    }
+}
+
+/// A minimal in-process key-value server backing `NetworkTowerStorage`, for local clusters
+/// and tests. Towers are kept in memory only.
+pub struct TowerStorageServer {
+    local_addr: SocketAddr,
+    exit: Arc<AtomicBool>,
+    thread_hdl: JoinHandle<()>,
+}
+
+impl TowerStorageServer {
+    pub fn new(bind_addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(bind_addr)?;
+        let local_addr = listener.local_addr()?;
+        let exit = Arc::new(AtomicBool::new(false));
+        let exit_ = exit.clone();
+        let thread_hdl = Builder::new()
+            .name("solana-tower-storage-server".to_string())
+            .spawn(move || {
+                let mut towers = HashMap::new();
+                for stream in listener.incoming() {
+                    if exit_.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match stream {
+                        Ok(mut stream) => {
+                            if let Err(err) = Self::handle_connection(&mut stream, &mut towers) {
+                                warn!("tower storage request failed: {}", err);
+                            }
+                        }
+                        Err(err) => warn!("tower storage connection failed: {}", err),
+                    }
+                }
+            })?;
+        Ok(Self {
+            local_addr,
+            exit,
+            thread_hdl,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn close(self) -> thread::Result<()> {
+        self.exit.store(true, Ordering::Relaxed);
+        // Wake up the listener so it observes the exit flag
+        if let Ok(stream) = TcpStream::connect(self.local_addr) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.thread_hdl.join()
+    }
+
+    fn handle_connection(
+        stream: &mut TcpStream,
+        towers: &mut HashMap<Pubkey, (Option<Slot>, SavedTower)>,
+    ) -> Result<()> {
+        stream.set_read_timeout(Some(NETWORK_TIMEOUT))?;
+        stream.set_write_timeout(Some(NETWORK_TIMEOUT))?;
+        let response = match read_message(stream)? {
+            TowerStorageRequest::Load { node_pubkey } => TowerStorageResponse::Loaded(
+                towers
+                    .get(&node_pubkey)
+                    .map(|(_, saved_tower)| saved_tower.clone()),
+            ),
+            TowerStorageRequest::CompareAndSwap {
+                node_pubkey,
+                expected_last_vote_slot,
+                last_vote_slot,
+                saved_tower,
+            } => {
+                let current_last_vote_slot = towers
+                    .get(&node_pubkey)
+                    .and_then(|(last_vote_slot, _)| *last_vote_slot);
+                if current_last_vote_slot == expected_last_vote_slot {
+                    towers.insert(node_pubkey, (last_vote_slot, saved_tower));
+                    TowerStorageResponse::Swapped
+                } else {
+                    TowerStorageResponse::Conflict {
+                        last_vote_slot: current_last_vote_slot,
+                    }
+                }
+            }
+        };
+        write_message(stream, &response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::Tower;
+    use solana_sdk::signature::{Keypair, Signer};
+    use tempfile::TempDir;
+
+    fn new_saved_tower(keypair: &Arc<Keypair>, last_vote_slot: Option<Slot>) -> SavedTower {
+        let mut tower = Tower::new_with_key(&keypair.pubkey());
+        if let Some(slot) = last_vote_slot {
+            tower.record_vote(slot, solana_sdk::hash::Hash::default());
+        }
+        assert_eq!(tower.last_voted_slot(), last_vote_slot);
+        SavedTower::new(&tower, keypair).unwrap()
+    }
+
+    #[test]
+    fn test_file_tower_storage() {
+        let dir = TempDir::new().unwrap();
+        let tower_storage = FileTowerStorage::new(dir.path().to_path_buf());
+        let keypair = Arc::new(Keypair::new());
+        assert!(tower_storage
+            .load(&keypair.pubkey())
+            .unwrap_err()
+            .is_file_missing());
+
+        let saved_tower = new_saved_tower(&keypair, Some(1));
+        tower_storage
+            .store(&keypair.pubkey(), &saved_tower)
+            .unwrap();
+        assert!(tower_storage.filename(&keypair.pubkey()).exists());
+        assert_eq!(tower_storage.load(&keypair.pubkey()).unwrap(), saved_tower);
+    }
+
+    #[test]
+    fn test_network_tower_storage() {
+        let server = TowerStorageServer::new("127.0.0.1:0".parse().unwrap()).unwrap();
+        let primary = NetworkTowerStorage::new(server.local_addr());
+        let standby = NetworkTowerStorage::new(server.local_addr());
+        let keypair = Arc::new(Keypair::new());
+        let node_pubkey = keypair.pubkey();
+
+        assert!(primary.load(&node_pubkey).unwrap_err().is_file_missing());
+        let saved_tower = new_saved_tower(&keypair, Some(1));
+        primary.store(&node_pubkey, &saved_tower).unwrap();
+        let saved_tower = new_saved_tower(&keypair, Some(2));
+        primary.store(&node_pubkey, &saved_tower).unwrap();
+
+        // The standby takes over with the primary's latest tower
+        assert_eq!(standby.load(&node_pubkey).unwrap(), saved_tower);
+        standby
+            .store(&node_pubkey, &new_saved_tower(&keypair, Some(3)))
+            .unwrap();
+
+        // ...after which the primary can no longer overwrite it
+        assert_matches!(
+            primary.store(&node_pubkey, &new_saved_tower(&keypair, Some(4))),
+            Err(TowerError::StorageError(_))
+        );
+        assert_eq!(
+            Tower::restore(&standby, &node_pubkey)
+                .unwrap()
+                .last_voted_slot(),
+            Some(3)
+        );
+        server.close().unwrap();
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
     rpc_subscriptions::RpcSubscriptions,
     sigverify::TransactionSigVerifier,
     sigverify_stage::SigVerifyStage,
+    staked_nodes_updater_service::StakedNodesUpdaterService,
 };
 use crossbeam_channel::unbounded;
 use solana_ledger::{blockstore::Blockstore, blockstore_processor::TransactionStatusSender};
@@ -19,6 +20,7 @@ use solana_runtime::{
     bank_forks::BankForks,
     vote_sender_types::{ReplayVoteReceiver, ReplayVoteSender},
 };
+use solana_streamer::quic::{spawn_server, StakedNodes};
 use std::{
     net::UdpSocket,
     sync::{
@@ -26,7 +28,7 @@ use std::{
         mpsc::{channel, Receiver},
         Arc, Mutex, RwLock,
     },
-    thread,
+    thread::{self, JoinHandle},
 };
 
 pub struct Tpu {
@@ -35,6 +37,7 @@ pub struct Tpu {
     banking_stage: BankingStage,
     cluster_info_vote_listener: ClusterInfoVoteListener,
     broadcast_stage: BroadcastStage,
+    tpu_quic: Option<(JoinHandle<()>, StakedNodesUpdaterService)>,
 }
 
 impl Tpu {
@@ -45,6 +48,7 @@ impl Tpu {
         entry_receiver: Receiver<WorkingBankEntry>,
         retransmit_slots_receiver: RetransmitSlotsReceiver,
         transactions_sockets: Vec<UdpSocket>,
+        tpu_quic_socket: Option<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
         broadcast_sockets: Vec<UdpSocket>,
         subscriptions: &Arc<RpcSubscriptions>,
@@ -59,6 +63,7 @@ impl Tpu {
         replay_vote_receiver: ReplayVoteReceiver,
         replay_vote_sender: ReplayVoteSender,
         bank_notification_sender: Option<BankNotificationSender>,
+        enable_banking_priority_scheduler: bool,
     ) -> Self {
         let (packet_sender, packet_receiver) = channel();
         let fetch_stage = FetchStage::new_with_sender(
@@ -68,6 +73,27 @@ impl Tpu {
             &packet_sender,
             &poh_recorder,
         );
+        // Transactions received over QUIC go through the same sigverify and banking stages
+        let tpu_quic = tpu_quic_socket.and_then(|tpu_quic_socket| {
+            let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+            let identity_keypair = cluster_info.keypair().clone();
+            match spawn_server(
+                tpu_quic_socket,
+                &identity_keypair,
+                packet_sender.clone(),
+                exit.clone(),
+                staked_nodes.clone(),
+            ) {
+                Ok(quic_server) => Some((
+                    quic_server,
+                    StakedNodesUpdaterService::new(exit, &bank_forks, &staked_nodes),
+                )),
+                Err(err) => {
+                    error!("Failed to start the TPU QUIC server: {}", err);
+                    None
+                }
+            }
+        });
         let (verified_sender, verified_receiver) = unbounded();
 
         let sigverify_stage = {
@@ -97,6 +123,7 @@ impl Tpu {
             verified_vote_packets_receiver,
             transaction_status_sender,
             replay_vote_sender,
+            enable_banking_priority_scheduler,
         );
 
         let broadcast_stage = broadcast_type.new_broadcast_stage(
@@ -115,6 +142,7 @@ impl Tpu {
             banking_stage,
             cluster_info_vote_listener,
             broadcast_stage,
+            tpu_quic,
         }
     }
 
@@ -124,6 +152,10 @@ impl Tpu {
         results.push(self.sigverify_stage.join());
         results.push(self.cluster_info_vote_listener.join());
         results.push(self.banking_stage.join());
+        if let Some((quic_server, staked_nodes_updater_service)) = self.tpu_quic {
+            results.push(quic_server.join());
+            results.push(staked_nodes_updater_service.join());
+        }
         let broadcast_result = self.broadcast_stage.join();
         for result in results {
             result?;
@@ -2,7 +2,9 @@
 //! validation pipeline in software.
 
 use crate::{
+    account_history_service::AccountHistorySender,
     accounts_hash_verifier::AccountsHashVerifier,
+    ancestor_hashes_service::AncestorHashesService,
     broadcast_stage::RetransmitSlotsSender,
     cache_block_time_service::CacheBlockTimeSender,
     cluster_info::ClusterInfo,
@@ -10,6 +12,7 @@ use crate::{
     cluster_slots::ClusterSlots,
     completed_data_sets_service::CompletedDataSetsSender,
     consensus::Tower,
+    duplicate_shred_listener::DuplicateShredListener,
     ledger_cleanup_service::LedgerCleanupService,
     optimistically_confirmed_bank_tracker::BankNotificationSender,
     poh_recorder::PohRecorder,
@@ -20,10 +23,12 @@ use crate::{
     shred_fetch_stage::ShredFetchStage,
     sigverify_shreds::ShredSigVerifier,
     sigverify_stage::SigVerifyStage,
+    tower_storage::TowerStorage,
 };
 use crossbeam_channel::unbounded;
 use solana_ledger::{
     blockstore::{Blockstore, CompletedSlotsReceiver},
+    blockstore_db::LedgerRetention,
     blockstore_processor::TransactionStatusSender,
     leader_schedule_cache::LeaderScheduleCache,
 };
@@ -34,10 +39,7 @@ use solana_runtime::{
     snapshot_package::AccountsPackageSender,
     vote_sender_types::ReplayVoteSender,
 };
-use solana_sdk::{
-    pubkey::Pubkey,
-    signature::{Keypair, Signer},
-};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
 use std::{
     collections::HashSet,
     net::UdpSocket,
@@ -54,6 +56,8 @@ pub struct Tvu {
     sigverify_stage: SigVerifyStage,
     retransmit_stage: RetransmitStage,
     replay_stage: ReplayStage,
+    duplicate_shred_listener: DuplicateShredListener,
+    ancestor_hashes_service: AncestorHashesService,
     ledger_cleanup_service: Option<LedgerCleanupService>,
     accounts_background_service: AccountsBackgroundService,
     accounts_hash_verifier: AccountsHashVerifier,
@@ -64,11 +68,13 @@ pub struct Sockets {
     pub repair: UdpSocket,
     pub retransmit: Vec<UdpSocket>,
     pub forwards: Vec<UdpSocket>,
+    pub ancestor_hashes_requests: UdpSocket,
 }
 
 #[derive(Default)]
 pub struct TvuConfig {
     pub max_ledger_shreds: Option<u64>,
+    pub ledger_retention: LedgerRetention,
     pub shred_version: u16,
     pub halt_on_trusted_validators_accounts_hash_mismatch: bool,
     pub trusted_validators: Option<HashSet<Pubkey>>,
@@ -86,7 +92,7 @@ impl Tvu {
     #[allow(clippy::new_ret_no_self, clippy::too_many_arguments)]
     pub fn new(
         vote_account: &Pubkey,
-        authorized_voter_keypairs: Vec<Arc<Keypair>>,
+        authorized_voter_keypairs: Arc<RwLock<Vec<Arc<Keypair>>>>,
         bank_forks: &Arc<RwLock<BankForks>>,
         cluster_info: &Arc<ClusterInfo>,
         sockets: Sockets,
@@ -95,6 +101,7 @@ impl Tvu {
         subscriptions: &Arc<RpcSubscriptions>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         tower: Tower,
+        tower_storage: Arc<dyn TowerStorage>,
         leader_schedule_cache: &Arc<LeaderScheduleCache>,
         exit: &Arc<AtomicBool>,
         completed_slots_receiver: CompletedSlotsReceiver,
@@ -103,6 +110,7 @@ impl Tvu {
         transaction_status_sender: Option<TransactionStatusSender>,
         rewards_recorder_sender: Option<RewardsRecorderSender>,
         cache_block_time_sender: Option<CacheBlockTimeSender>,
+        account_history_sender: Option<AccountHistorySender>,
         snapshot_config_and_package_sender: Option<(SnapshotConfig, AccountsPackageSender)>,
         vote_tracker: Arc<VoteTracker>,
         retransmit_slots_sender: RetransmitSlotsSender,
@@ -112,13 +120,12 @@ impl Tvu {
         bank_notification_sender: Option<BankNotificationSender>,
         tvu_config: TvuConfig,
     ) -> Self {
-        let keypair: Arc<Keypair> = cluster_info.keypair.clone();
-
         let Sockets {
             repair: repair_socket,
             fetch: fetch_sockets,
             retransmit: retransmit_sockets,
             forwards: tvu_forward_sockets,
+            ancestor_hashes_requests: ancestor_hashes_socket,
         } = sockets;
 
         let (fetch_sender, fetch_receiver) = channel();
@@ -145,6 +152,16 @@ impl Tvu {
 
         let cluster_slots = Arc::new(ClusterSlots::default());
         let (duplicate_slots_reset_sender, duplicate_slots_reset_receiver) = unbounded();
+        let (ancestor_duplicate_slots_sender, ancestor_duplicate_slots_receiver) = unbounded();
+        let ancestor_hashes_service = AncestorHashesService::new(
+            exit.clone(),
+            blockstore.clone(),
+            bank_forks.clone(),
+            cluster_info.clone(),
+            ancestor_hashes_socket,
+            tvu_config.repair_validators.clone(),
+            ancestor_duplicate_slots_sender,
+        );
         let retransmit_stage = RetransmitStage::new(
             bank_forks.clone(),
             leader_schedule_cache,
@@ -160,6 +177,7 @@ impl Tvu {
             tvu_config.shred_version,
             cluster_slots.clone(),
             duplicate_slots_reset_sender,
+            ancestor_duplicate_slots_receiver,
             verified_vote_receiver,
             tvu_config.repair_validators,
             completed_data_sets_sender,
@@ -209,9 +227,10 @@ impl Tvu {
         };
 
         let replay_stage_config = ReplayStageConfig {
-            my_pubkey: keypair.pubkey(),
+            my_pubkey: cluster_info.id(),
             vote_account: *vote_account,
             authorized_voter_keypairs,
+            tower_storage,
             exit: exit.clone(),
             subscriptions: subscriptions.clone(),
             leader_schedule_cache: leader_schedule_cache.clone(),
@@ -221,9 +240,20 @@ impl Tvu {
             transaction_status_sender,
             rewards_recorder_sender,
             cache_block_time_sender,
+            account_history_sender,
             bank_notification_sender,
         };
 
+        let (duplicate_slots_sender, duplicate_slots_receiver) = unbounded();
+        let duplicate_shred_listener = DuplicateShredListener::new(
+            exit,
+            cluster_info.clone(),
+            blockstore.clone(),
+            bank_forks.clone(),
+            leader_schedule_cache.clone(),
+            duplicate_slots_sender,
+        );
+
         let replay_stage = ReplayStage::new(
             replay_stage_config,
             blockstore.clone(),
@@ -237,13 +267,16 @@ impl Tvu {
             retransmit_slots_sender,
             duplicate_slots_reset_receiver,
             replay_vote_sender,
+            duplicate_slots_receiver,
         );
 
+        let ledger_retention = tvu_config.ledger_retention;
         let ledger_cleanup_service = tvu_config.max_ledger_shreds.map(|max_ledger_shreds| {
             LedgerCleanupService::new(
                 ledger_cleanup_slot_receiver,
                 blockstore.clone(),
                 max_ledger_shreds,
+                ledger_retention,
                 &exit,
             )
         });
@@ -256,6 +289,8 @@ impl Tvu {
             sigverify_stage,
             retransmit_stage,
             replay_stage,
+            duplicate_shred_listener,
+            ancestor_hashes_service,
             ledger_cleanup_service,
             accounts_background_service,
             accounts_hash_verifier,
@@ -271,6 +306,8 @@ impl Tvu {
         }
         self.accounts_background_service.join()?;
         self.replay_stage.join()?;
+        self.duplicate_shred_listener.join()?;
+        self.ancestor_hashes_service.join()?;
         self.accounts_hash_verifier.join()?;
         Ok(())
     }
@@ -283,6 +320,7 @@ pub mod tests {
         banking_stage::create_test_recorder,
         cluster_info::{ClusterInfo, Node},
         optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
+        tower_storage::FileTowerStorage,
     };
     use serial_test_derive::serial;
     use solana_ledger::{
@@ -291,6 +329,7 @@ pub mod tests {
         genesis_utils::{create_genesis_config, GenesisConfigInfo},
     };
     use solana_runtime::bank::Bank;
+    use solana_sdk::signature::Signer;
     use std::sync::atomic::Ordering;
 
     #[ignore]
@@ -335,7 +374,7 @@ pub mod tests {
         let tower = Tower::new_with_key(&target1_keypair.pubkey());
         let tvu = Tvu::new(
             &vote_keypair.pubkey(),
-            vec![Arc::new(vote_keypair)],
+            Arc::new(RwLock::new(vec![Arc::new(vote_keypair)])),
             &bank_forks,
             &cref1,
             {
@@ -344,6 +383,7 @@ pub mod tests {
                     retransmit: target1.sockets.retransmit_sockets,
                     fetch: target1.sockets.tvu,
                     forwards: target1.sockets.tvu_forwards,
+                    ancestor_hashes_requests: target1.sockets.ancestor_hashes_requests,
                 }
             },
             blockstore,
@@ -356,6 +396,7 @@ pub mod tests {
             )),
             &poh_recorder,
             tower,
+            Arc::new(FileTowerStorage::new(blockstore_path)),
             &leader_schedule_cache,
             &exit,
             completed_slots_receiver,
@@ -365,6 +406,7 @@ pub mod tests {
             None,
             None,
             None,
+            None,
             Arc::new(VoteTracker::new(&bank)),
             retransmit_slots_sender,
             verified_vote_receiver,
@@ -1,6 +1,7 @@
 //! The `validator` module hosts all the validator microservices.
 
 use crate::{
+    account_history_service::{AccountHistorySender, AccountHistoryService},
     broadcast_stage::BroadcastStageType,
     cache_block_time_service::{CacheBlockTimeSender, CacheBlockTimeService},
     cluster_info::{ClusterInfo, Node},
@@ -24,17 +25,19 @@ use crate::{
     serve_repair_service::ServeRepairService,
     sigverify,
     snapshot_packager_service::SnapshotPackagerService,
+    tower_storage::{FileTowerStorage, TowerStorage},
     tpu::Tpu,
     transaction_status_service::TransactionStatusService,
     tvu::{Sockets, Tvu, TvuConfig},
 };
 use crossbeam_channel::{bounded, unbounded};
 use rand::{thread_rng, Rng};
+use solana_accountsdb_plugin::accountsdb_plugin_service::AccountsDbPluginService;
 use solana_banks_server::rpc_banks_service::RpcBanksService;
 use solana_ledger::{
     bank_forks_utils,
     blockstore::{Blockstore, BlockstoreSignals, CompletedSlotsReceiver, PurgeType},
-    blockstore_db::BlockstoreRecoveryMode,
+    blockstore_db::{BlockstoreRecoveryMode, LedgerRetention},
     blockstore_processor::{self, TransactionStatusSender},
     leader_schedule::FixedSchedule,
     leader_schedule_cache::LeaderScheduleCache,
@@ -42,6 +45,8 @@ use solana_ledger::{
 use solana_measure::measure::Measure;
 use solana_metrics::datapoint_info;
 use solana_runtime::{
+    accounts_index::AccountsIndexConfig,
+    accounts_update_notifier_interface::AccountsUpdateNotifier,
     bank::Bank,
     bank_forks::{BankForks, SnapshotConfig},
     commitment::BlockCommitmentCache,
@@ -85,6 +90,7 @@ pub struct ValidatorConfig {
     pub pubsub_config: PubSubConfig,
     pub snapshot_config: Option<SnapshotConfig>,
     pub max_ledger_shreds: Option<u64>,
+    pub ledger_retention: LedgerRetention,
     pub broadcast_stage_type: BroadcastStageType,
     pub enable_partition: Option<Arc<AtomicBool>>,
     pub fixed_leader_schedule: Option<FixedSchedule>,
@@ -103,7 +109,12 @@ pub struct ValidatorConfig {
     pub poh_verify: bool, // Perform PoH verification during blockstore processing at boo
     pub cuda: bool,
     pub require_tower: bool,
+    pub tower_storage: Option<Arc<dyn TowerStorage>>, // None = tower files in the ledger directory
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
+    pub accountsdb_plugin_config_files: Option<Vec<PathBuf>>,
+    pub accounts_index_config: Option<AccountsIndexConfig>,
+    pub enable_tpu_quic: bool,
+    pub banking_priority_scheduler: bool,
 }
 
 impl Default for ValidatorConfig {
@@ -115,6 +126,7 @@ impl Default for ValidatorConfig {
             expected_shred_version: None,
             voting_disabled: false,
             max_ledger_shreds: None,
+            ledger_retention: LedgerRetention::default(),
             account_paths: Vec::new(),
             rpc_config: JsonRpcConfig::default(),
             rpc_addrs: None,
@@ -138,11 +150,40 @@ impl Default for ValidatorConfig {
             poh_verify: true,
             cuda: false,
             require_tower: false,
+            tower_storage: None,
             debug_keys: None,
+            accountsdb_plugin_config_files: None,
+            accounts_index_config: None,
+            enable_tpu_quic: false,
+            banking_priority_scheduler: false,
         }
     }
 }
 
+/// Stages the validator goes through while starting up, reported over the admin RPC
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ValidatorStartProgress {
+    Initializing, // Catch all, default state
+    SearchingForRpcService,
+    DownloadingSnapshot { slot: Slot, rpc_addr: SocketAddr },
+    CleaningBlockStore,
+    CleaningAccounts,
+    LoadingLedger,
+    StartingServices,
+    Halted, // Validator halted due to `--dev-halt-at-slot` argument
+    WaitingForSupermajority,
+
+    // `Running` is the terminal state once the validator fully starts and all services are
+    // operational
+    Running,
+}
+
+impl Default for ValidatorStartProgress {
+    fn default() -> Self {
+        Self::Initializing
+    }
+}
+
 #[derive(Default)]
 pub struct ValidatorExit {
     exits: Vec<Box<dyn FnOnce() + Send + Sync>>,
@@ -168,22 +209,27 @@ struct TransactionHistoryServices {
     rewards_recorder_service: Option<RewardsRecorderService>,
     cache_block_time_sender: Option<CacheBlockTimeSender>,
     cache_block_time_service: Option<CacheBlockTimeService>,
+    account_history_sender: Option<AccountHistorySender>,
+    account_history_service: Option<AccountHistoryService>,
 }
 
 struct RpcServices {
     json_rpc_service: JsonRpcService,
     pubsub_service: PubSubService,
     rpc_banks_service: RpcBanksService,
-    optimistically_confirmed_bank_tracker: OptimisticallyConfirmedBankTracker,
 }
 
 pub struct Validator {
     pub id: Pubkey,
-    validator_exit: Arc<RwLock<Option<ValidatorExit>>>,
+    pub cluster_info: Arc<ClusterInfo>,
+    pub validator_exit: Arc<RwLock<Option<ValidatorExit>>>,
     rpc_service: Option<RpcServices>,
+    optimistically_confirmed_bank_tracker: Option<OptimisticallyConfirmedBankTracker>,
+    accountsdb_plugin_service: Option<AccountsDbPluginService>,
     transaction_status_service: Option<TransactionStatusService>,
     rewards_recorder_service: Option<RewardsRecorderService>,
     cache_block_time_service: Option<CacheBlockTimeService>,
+    account_history_service: Option<AccountHistoryService>,
     sample_performance_service: Option<SamplePerformanceService>,
     gossip_service: GossipService,
     serve_repair_service: ServeRepairService,
@@ -202,9 +248,10 @@ impl Validator {
         identity_keypair: &Arc<Keypair>,
         ledger_path: &Path,
         vote_account: &Pubkey,
-        mut authorized_voter_keypairs: Vec<Arc<Keypair>>,
+        authorized_voter_keypairs: Arc<RwLock<Vec<Arc<Keypair>>>>,
         cluster_entrypoint: Option<&ContactInfo>,
         config: &ValidatorConfig,
+        start_progress: &Arc<RwLock<ValidatorStartProgress>>,
     ) -> Self {
         let id = identity_keypair.pubkey();
         assert_eq!(id, node.info.id);
@@ -212,11 +259,16 @@ impl Validator {
         warn!("identity: {}", id);
         warn!("vote account: {}", vote_account);
 
+        let tower_storage = config
+            .tower_storage
+            .clone()
+            .unwrap_or_else(|| Arc::new(FileTowerStorage::new(ledger_path.to_path_buf())));
+
         if config.voting_disabled {
             warn!("voting disabled");
-            authorized_voter_keypairs.clear();
+            authorized_voter_keypairs.write().unwrap().clear();
         } else {
-            for authorized_voter_keypair in &authorized_voter_keypairs {
+            for authorized_voter_keypair in authorized_voter_keypairs.read().unwrap().iter() {
                 warn!("authorized voter: {}", authorized_voter_keypair.pubkey());
             }
         }
@@ -242,6 +294,7 @@ impl Validator {
 
         if let Some(shred_version) = config.expected_shred_version {
             if let Some(wait_for_supermajority_slot) = config.wait_for_supermajority {
+                *start_progress.write().unwrap() = ValidatorStartProgress::CleaningBlockStore;
                 backup_and_clear_blockstore(
                     ledger_path,
                     wait_for_supermajority_slot + 1,
@@ -251,6 +304,7 @@ impl Validator {
         }
 
         info!("Cleaning accounts paths..");
+        *start_progress.write().unwrap() = ValidatorStartProgress::CleaningAccounts;
         let mut start = Measure::start("clean_accounts_paths");
         for accounts_path in &config.account_paths {
             cleanup_accounts_path(accounts_path);
@@ -264,6 +318,19 @@ impl Validator {
         validator_exit.register_exit(Box::new(move || exit_.store(true, Ordering::Relaxed)));
         let validator_exit = Arc::new(RwLock::new(Some(validator_exit)));
 
+        let accountsdb_plugin_service =
+            config
+                .accountsdb_plugin_config_files
+                .as_ref()
+                .map(|accountsdb_plugin_config_files| {
+                    AccountsDbPluginService::new(accountsdb_plugin_config_files).unwrap_or_else(
+                        |err| {
+                            error!("Failed to load AccountsDb plugins: {:?}", err);
+                            process::exit(1);
+                        },
+                    )
+                });
+
         let (replay_vote_sender, replay_vote_receiver) = unbounded();
         let (
             genesis_config,
@@ -280,6 +347,8 @@ impl Validator {
                 rewards_recorder_service,
                 cache_block_time_sender,
                 cache_block_time_service,
+                account_history_sender,
+                account_history_service,
             },
             tower,
         ) = new_banks_from_ledger(
@@ -287,9 +356,15 @@ impl Validator {
             vote_account,
             config,
             ledger_path,
+            tower_storage.as_ref(),
             config.poh_verify,
             &exit,
+            accountsdb_plugin_service
+                .as_ref()
+                .and_then(|service| service.get_accounts_update_notifier()),
+            start_progress,
         );
+        *start_progress.write().unwrap() = ValidatorStartProgress::StartingServices;
 
         let leader_schedule_cache = Arc::new(leader_schedule_cache);
         let bank = bank_forks.working_bank();
@@ -393,7 +468,28 @@ impl Validator {
         let poh_recorder = Arc::new(Mutex::new(poh_recorder));
 
         let rpc_override_health_check = Arc::new(AtomicBool::new(false));
-        let (rpc_service, bank_notification_sender) =
+        let slot_status_notifier = accountsdb_plugin_service
+            .as_ref()
+            .and_then(|service| service.get_slot_status_notifier());
+        let (bank_notification_sender, optimistically_confirmed_bank_tracker) =
+            if config.rpc_addrs.is_some() || slot_status_notifier.is_some() {
+                let (bank_notification_sender, bank_notification_receiver) = unbounded();
+                (
+                    Some(bank_notification_sender),
+                    Some(OptimisticallyConfirmedBankTracker::new(
+                        bank_notification_receiver,
+                        &exit,
+                        bank_forks.clone(),
+                        optimistically_confirmed_bank.clone(),
+                        subscriptions.clone(),
+                        slot_status_notifier,
+                    )),
+                )
+            } else {
+                (None, None)
+            };
+
+        let rpc_service =
             if let Some((rpc_addr, rpc_pubsub_addr, rpc_banks_addr)) = config.rpc_addrs {
                 if ContactInfo::is_valid_address(&node.info.rpc) {
                     assert!(ContactInfo::is_valid_address(&node.info.rpc_pubsub));
@@ -403,51 +499,39 @@ impl Validator {
                     assert!(!ContactInfo::is_valid_address(&node.info.rpc_banks));
                 }
                 let tpu_address = cluster_info.my_contact_info().tpu;
-                let (bank_notification_sender, bank_notification_receiver) = unbounded();
-                (
-                    Some(RpcServices {
-                        json_rpc_service: JsonRpcService::new(
-                            rpc_addr,
-                            config.rpc_config.clone(),
-                            config.snapshot_config.clone(),
-                            bank_forks.clone(),
-                            block_commitment_cache.clone(),
-                            blockstore.clone(),
-                            cluster_info.clone(),
-                            Some(poh_recorder.clone()),
-                            genesis_config.hash(),
-                            ledger_path,
-                            validator_exit.clone(),
-                            config.trusted_validators.clone(),
-                            rpc_override_health_check.clone(),
-                            optimistically_confirmed_bank.clone(),
-                        ),
-                        pubsub_service: PubSubService::new(
-                            config.pubsub_config.clone(),
-                            &subscriptions,
-                            rpc_pubsub_addr,
-                            &exit,
-                        ),
-                        rpc_banks_service: RpcBanksService::new(
-                            rpc_banks_addr,
-                            tpu_address,
-                            &bank_forks,
-                            &block_commitment_cache,
-                            &exit,
-                        ),
-                        optimistically_confirmed_bank_tracker:
-                            OptimisticallyConfirmedBankTracker::new(
-                                bank_notification_receiver,
-                                &exit,
-                                bank_forks.clone(),
-                                optimistically_confirmed_bank,
-                                subscriptions.clone(),
-                            ),
-                    }),
-                    Some(bank_notification_sender),
-                )
+                Some(RpcServices {
+                    json_rpc_service: JsonRpcService::new(
+                        rpc_addr,
+                        config.rpc_config.clone(),
+                        config.snapshot_config.clone(),
+                        bank_forks.clone(),
+                        block_commitment_cache.clone(),
+                        blockstore.clone(),
+                        cluster_info.clone(),
+                        Some(poh_recorder.clone()),
+                        genesis_config.hash(),
+                        ledger_path,
+                        validator_exit.clone(),
+                        config.trusted_validators.clone(),
+                        rpc_override_health_check.clone(),
+                        optimistically_confirmed_bank,
+                    ),
+                    pubsub_service: PubSubService::new(
+                        config.pubsub_config.clone(),
+                        &subscriptions,
+                        rpc_pubsub_addr,
+                        &exit,
+                    ),
+                    rpc_banks_service: RpcBanksService::new(
+                        rpc_banks_addr,
+                        tpu_address,
+                        &bank_forks,
+                        &block_commitment_cache,
+                        &exit,
+                    ),
+                })
             } else {
-                (None, None)
+                None
             };
 
         if config.dev_halt_at_slot.is_some() {
@@ -460,6 +544,7 @@ impl Validator {
 
             // Park with the RPC service running, ready for inspection!
             warn!("Validator halted");
+            *start_progress.write().unwrap() = ValidatorStartProgress::Halted;
             std::thread::park();
         }
 
@@ -501,7 +586,13 @@ impl Validator {
                 (None, None)
             };
 
-        if wait_for_supermajority(config, &bank, &cluster_info, rpc_override_health_check) {
+        if wait_for_supermajority(
+            config,
+            &bank,
+            &cluster_info,
+            rpc_override_health_check,
+            start_progress,
+        ) {
             std::process::exit(1);
         }
 
@@ -545,12 +636,18 @@ impl Validator {
                     .iter()
                     .map(|s| s.try_clone().expect("Failed to clone TVU forwards Sockets"))
                     .collect(),
+                ancestor_hashes_requests: node
+                    .sockets
+                    .ancestor_hashes_requests
+                    .try_clone()
+                    .expect("Failed to clone ancestor hashes requests socket"),
             },
             blockstore.clone(),
             ledger_signal_receiver,
             &subscriptions,
             &poh_recorder,
             tower,
+            tower_storage,
             &leader_schedule_cache,
             &exit,
             completed_slots_receiver,
@@ -559,6 +656,7 @@ impl Validator {
             transaction_status_sender.clone(),
             rewards_recorder_sender,
             cache_block_time_sender,
+            account_history_sender,
             snapshot_config_and_package_sender,
             vote_tracker.clone(),
             retransmit_slots_sender,
@@ -568,6 +666,7 @@ impl Validator {
             bank_notification_sender.clone(),
             TvuConfig {
                 max_ledger_shreds: config.max_ledger_shreds,
+                ledger_retention: config.ledger_retention,
                 halt_on_trusted_validators_accounts_hash_mismatch: config
                     .halt_on_trusted_validators_accounts_hash_mismatch,
                 shred_version: node.info.shred_version,
@@ -583,6 +682,11 @@ impl Validator {
             entry_receiver,
             retransmit_slots_receiver,
             node.sockets.tpu,
+            if config.enable_tpu_quic {
+                node.sockets.tpu_quic
+            } else {
+                None
+            },
             node.sockets.tpu_forwards,
             node.sockets.broadcast,
             &subscriptions,
@@ -597,17 +701,23 @@ impl Validator {
             replay_vote_receiver,
             replay_vote_sender,
             bank_notification_sender,
+            config.banking_priority_scheduler,
         );
 
         datapoint_info!("validator-new", ("id", id.to_string(), String));
+        *start_progress.write().unwrap() = ValidatorStartProgress::Running;
         Self {
             id,
+            cluster_info,
             gossip_service,
             serve_repair_service,
             rpc_service,
+            optimistically_confirmed_bank_tracker,
+            accountsdb_plugin_service,
             transaction_status_service,
             rewards_recorder_service,
             cache_block_time_service,
+            account_history_service,
             sample_performance_service,
             snapshot_packager_service,
             completed_data_sets_service,
@@ -660,16 +770,24 @@ impl Validator {
     pub fn join(self) -> Result<()> {
         self.poh_service.join()?;
         drop(self.poh_recorder);
+        // The exit flag may have been raised internally, e.g. by gossip detecting a duplicate
+        // instance of this node, so make sure services which do not watch it are closed too.
+        if let Some(x) = self.validator_exit.write().unwrap().take() {
+            x.exit()
+        }
         if let Some(RpcServices {
             json_rpc_service,
             pubsub_service,
             rpc_banks_service,
-            optimistically_confirmed_bank_tracker,
         }) = self.rpc_service
         {
             json_rpc_service.join()?;
             pubsub_service.join()?;
             rpc_banks_service.join()?;
+        }
+        if let Some(optimistically_confirmed_bank_tracker) =
+            self.optimistically_confirmed_bank_tracker
+        {
             optimistically_confirmed_bank_tracker.join()?;
         }
         if let Some(transaction_status_service) = self.transaction_status_service {
@@ -684,6 +802,10 @@ impl Validator {
             cache_block_time_service.join()?;
         }
 
+        if let Some(account_history_service) = self.account_history_service {
+            account_history_service.join()?;
+        }
+
         if let Some(sample_performance_service) = self.sample_performance_service {
             sample_performance_service.join()?;
         }
@@ -697,6 +819,9 @@ impl Validator {
         self.tpu.join()?;
         self.tvu.join()?;
         self.completed_data_sets_service.join()?;
+        if let Some(accountsdb_plugin_service) = self.accountsdb_plugin_service {
+            accountsdb_plugin_service.join()?;
+        }
         self.ip_echo_server.shutdown_now();
 
         Ok(())
@@ -717,7 +842,6 @@ fn post_process_restored_tower(
     validator_identity: &Pubkey,
     vote_account: &Pubkey,
     config: &ValidatorConfig,
-    ledger_path: &Path,
     bank_forks: &BankForks,
 ) -> Tower {
     restored_tower
@@ -760,12 +884,7 @@ fn post_process_restored_tower(
                 );
             }
 
-            Tower::new_from_bankforks(
-                &bank_forks,
-                &ledger_path,
-                &validator_identity,
-                &vote_account,
-            )
+            Tower::new_from_bankforks(&bank_forks, &validator_identity, &vote_account)
         })
 }
 
@@ -775,8 +894,11 @@ fn new_banks_from_ledger(
     vote_account: &Pubkey,
     config: &ValidatorConfig,
     ledger_path: &Path,
+    tower_storage: &dyn TowerStorage,
     poh_verify: bool,
     exit: &Arc<AtomicBool>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    start_progress: &Arc<RwLock<ValidatorStartProgress>>,
 ) -> (
     GenesisConfig,
     BankForks,
@@ -789,6 +911,7 @@ fn new_banks_from_ledger(
     Tower,
 ) {
     info!("loading ledger from {:?}...", ledger_path);
+    *start_progress.write().unwrap() = ValidatorStartProgress::LoadingLedger;
     let genesis_config = open_genesis_config(ledger_path, config.max_genesis_archive_unpacked_size);
 
     // This needs to be limited otherwise the state in the VoteAccount data
@@ -818,7 +941,7 @@ fn new_banks_from_ledger(
         .expect("Failed to open ledger database");
     blockstore.set_no_compaction(config.no_rocksdb_compaction);
 
-    let restored_tower = Tower::restore(ledger_path, &validator_identity);
+    let restored_tower = Tower::restore(tower_storage, &validator_identity);
     if let Ok(tower) = &restored_tower {
         reconcile_blockstore_roots_with_tower(&tower, &blockstore).unwrap_or_else(|err| {
             error!("Failed to reconcile blockstore with tower: {:?}", err);
@@ -832,16 +955,27 @@ fn new_banks_from_ledger(
         new_hard_forks: config.new_hard_forks.clone(),
         frozen_accounts: config.frozen_accounts.clone(),
         debug_keys: config.debug_keys.clone(),
+        accounts_update_notifier,
+        accounts_index_config: config.accounts_index_config.clone(),
         ..blockstore_processor::ProcessOptions::default()
     };
 
     let blockstore = Arc::new(blockstore);
-    let transaction_history_services =
+    let mut transaction_history_services =
         if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_transaction_history {
             initialize_rpc_transaction_history_services(blockstore.clone(), exit)
         } else {
             TransactionHistoryServices::default()
         };
+    if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_account_history {
+        let (account_history_sender, account_history_receiver) = unbounded();
+        transaction_history_services.account_history_sender = Some(account_history_sender);
+        transaction_history_services.account_history_service = Some(AccountHistoryService::new(
+            account_history_receiver,
+            blockstore.clone(),
+            exit,
+        ));
+    }
 
     let (mut bank_forks, mut leader_schedule_cache, snapshot_hash) = bank_forks_utils::load(
         &genesis_config,
@@ -863,7 +997,6 @@ fn new_banks_from_ledger(
         &validator_identity,
         &vote_account,
         &config,
-        &ledger_path,
         &bank_forks,
     );
 
@@ -981,6 +1114,7 @@ fn initialize_rpc_transaction_history_services(
         rewards_recorder_service,
         cache_block_time_sender,
         cache_block_time_service,
+        ..TransactionHistoryServices::default()
     }
 }
 
@@ -990,6 +1124,7 @@ fn wait_for_supermajority(
     bank: &Bank,
     cluster_info: &ClusterInfo,
     rpc_override_health_check: Arc<AtomicBool>,
+    start_progress: &Arc<RwLock<ValidatorStartProgress>>,
 ) -> bool {
     if let Some(wait_for_supermajority) = config.wait_for_supermajority {
         match wait_for_supermajority.cmp(&bank.slot()) {
@@ -1019,6 +1154,7 @@ fn wait_for_supermajority(
         "Waiting for 80% of activated stake at slot {} to be in gossip...",
         bank.slot()
     );
+    *start_progress.write().unwrap() = ValidatorStartProgress::WaitingForSupermajority;
     for i in 1.. {
         let gossip_stake_percent = get_stake_percent_in_gossip(&bank, &cluster_info, i % 10 == 0);
 
@@ -1186,14 +1322,20 @@ mod tests {
             )),
             ..ValidatorConfig::default()
         };
+        let start_progress = Arc::new(RwLock::new(ValidatorStartProgress::default()));
         let validator = Validator::new(
             validator_node,
             &Arc::new(validator_keypair),
             &validator_ledger_path,
             &voting_keypair.pubkey(),
-            vec![voting_keypair.clone()],
+            Arc::new(RwLock::new(vec![voting_keypair.clone()])),
             Some(&leader_node.info),
             &config,
+            &start_progress,
+        );
+        assert_eq!(
+            *start_progress.read().unwrap(),
+            ValidatorStartProgress::Running
         );
         validator.close().unwrap();
         remove_dir_all(validator_ledger_path).unwrap();
@@ -1265,9 +1407,10 @@ mod tests {
                     &Arc::new(validator_keypair),
                     &validator_ledger_path,
                     &vote_account_keypair.pubkey(),
-                    vec![Arc::new(vote_account_keypair)],
+                    Arc::new(RwLock::new(vec![Arc::new(vote_account_keypair)])),
                     Some(&leader_node.info),
                     &config,
+                    &Arc::new(RwLock::new(ValidatorStartProgress::default())),
                 )
             })
             .collect();
@@ -1300,11 +1443,13 @@ mod tests {
         let bank = Arc::new(Bank::new(&genesis_config));
         let mut config = ValidatorConfig::default();
         let rpc_override_health_check = Arc::new(AtomicBool::new(false));
+        let start_progress = Arc::new(RwLock::new(ValidatorStartProgress::default()));
         assert!(!wait_for_supermajority(
             &config,
             &bank,
             &cluster_info,
-            rpc_override_health_check.clone()
+            rpc_override_health_check.clone(),
+            &start_progress,
         ));
 
         // bank=0, wait=1, should fail
@@ -1313,7 +1458,8 @@ mod tests {
             &config,
             &bank,
             &cluster_info,
-            rpc_override_health_check.clone()
+            rpc_override_health_check.clone(),
+            &start_progress,
         ));
 
         // bank=1, wait=0, should pass, bank is past the wait slot
@@ -1323,7 +1469,8 @@ mod tests {
             &config,
             &bank,
             &cluster_info,
-            rpc_override_health_check.clone()
+            rpc_override_health_check.clone(),
+            &start_progress,
         ));
 
         // bank=1, wait=1, equal, but bad hash provided
@@ -1333,7 +1480,8 @@ mod tests {
             &config,
             &bank,
             &cluster_info,
-            rpc_override_health_check
+            rpc_override_health_check,
+            &start_progress,
         ));
     }
 }
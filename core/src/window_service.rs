@@ -83,14 +83,31 @@ pub fn should_retransmit_and_persist(
 }
 
 fn run_check_duplicate(
+    cluster_info: &ClusterInfo,
     blockstore: &Arc<Blockstore>,
     shred_receiver: &CrossbeamReceiver<Shred>,
+    leader_schedule_cache: &LeaderScheduleCache,
+    bank_forks: &RwLock<BankForks>,
 ) -> Result<()> {
     let check_duplicate = |shred: Shred| -> Result<()> {
         if !blockstore.has_duplicate_shreds_in_slot(shred.slot()) {
             if let Some(existing_shred_payload) =
                 blockstore.is_shred_duplicate(shred.slot(), shred.index(), &shred.payload)
             {
+                // Let the rest of the cluster know the leader signed two different shreds
+                let root_bank = bank_forks.read().unwrap().root_bank().clone();
+                if let Err(err) = cluster_info.push_duplicate_shred(
+                    &shred,
+                    existing_shred_payload.clone(),
+                    |slot| leader_schedule_cache.slot_leader_at(slot, Some(&root_bank)),
+                ) {
+                    inc_new_counter_error!("solana-check-duplicate-push-error", 1, 1);
+                    error!(
+                        "failed to push duplicate shred proof for slot {}: {}",
+                        shred.slot(),
+                        err
+                    );
+                }
                 blockstore.store_duplicate_slot(
                     shred.slot(),
                     existing_shred_payload,
@@ -321,7 +338,7 @@ impl WindowService {
             + std::marker::Send
             + std::marker::Sync,
     {
-        let bank_forks = Some(repair_info.bank_forks.clone());
+        let bank_forks = repair_info.bank_forks.clone();
 
         let repair_service = RepairService::new(
             blockstore.clone(),
@@ -336,8 +353,14 @@ impl WindowService {
         let (insert_sender, insert_receiver) = unbounded();
         let (duplicate_sender, duplicate_receiver) = unbounded();
 
-        let t_check_duplicate =
-            Self::start_check_duplicate_thread(exit, &blockstore, duplicate_receiver);
+        let t_check_duplicate = Self::start_check_duplicate_thread(
+            cluster_info.clone(),
+            exit,
+            &blockstore,
+            duplicate_receiver,
+            leader_schedule_cache,
+            bank_forks.clone(),
+        );
 
         let t_insert = Self::start_window_insert_thread(
             exit,
@@ -355,7 +378,7 @@ impl WindowService {
             insert_sender,
             verified_receiver,
             shred_filter,
-            bank_forks,
+            Some(bank_forks),
             retransmit,
         );
 
@@ -368,12 +391,16 @@ impl WindowService {
     }
 
     fn start_check_duplicate_thread(
+        cluster_info: Arc<ClusterInfo>,
         exit: &Arc<AtomicBool>,
         blockstore: &Arc<Blockstore>,
         duplicate_receiver: CrossbeamReceiver<Shred>,
+        leader_schedule_cache: &Arc<LeaderScheduleCache>,
+        bank_forks: Arc<RwLock<BankForks>>,
     ) -> JoinHandle<()> {
         let exit = exit.clone();
         let blockstore = blockstore.clone();
+        let leader_schedule_cache = leader_schedule_cache.clone();
         let handle_error = || {
             inc_new_counter_error!("solana-check-duplicate-error", 1, 1);
         };
@@ -385,7 +412,13 @@ impl WindowService {
                 }
 
                 let mut noop = || {};
-                if let Err(e) = run_check_duplicate(&blockstore, &duplicate_receiver) {
+                if let Err(e) = run_check_duplicate(
+                    &cluster_info,
+                    &blockstore,
+                    &duplicate_receiver,
+                    &leader_schedule_cache,
+                    &bank_forks,
+                ) {
                     if Self::should_exit_on_error(e, &mut noop, &handle_error) {
                         break;
                     }
@@ -548,10 +581,11 @@ impl WindowService {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{contact_info::ContactInfo, duplicate_shred};
     use solana_ledger::{
-        blockstore::{make_many_slot_entries, Blockstore},
+        blockstore::Blockstore,
         entry::{create_ticks, Entry},
-        genesis_utils::create_genesis_config_with_leader,
+        genesis_utils::{create_genesis_config_with_leader, GenesisConfigInfo},
         get_tmp_ledger_path,
         shred::{DataShredHeader, Shredder},
     };
@@ -669,16 +703,46 @@ mod test {
         let blockstore_path = get_tmp_ledger_path!();
         let blockstore = Arc::new(Blockstore::open(&blockstore_path).unwrap());
         let (sender, receiver) = unbounded();
-        let (shreds, _) = make_many_slot_entries(5, 5, 10);
+        let leader_keypair = Arc::new(Keypair::new());
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(100, &leader_keypair.pubkey(), 10);
+        let bank = Bank::new(&genesis_config);
+        let leader_schedule_cache = LeaderScheduleCache::new_from_bank(&bank);
+        let bank_forks = RwLock::new(BankForks::new(bank));
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(ContactInfo::new_localhost(
+            &solana_sdk::pubkey::new_rand(),
+            0,
+        ));
+        let slot = 5;
+        let shreds = local_entries_to_shred(&[Entry::default()], slot, slot - 1, &leader_keypair);
         blockstore
             .insert_shreds(shreds.clone(), None, false)
             .unwrap();
-        let mut duplicate_shred = shreds[1].clone();
-        duplicate_shred.set_slot(shreds[0].slot());
-        let duplicate_shred_slot = duplicate_shred.slot();
-        sender.send(duplicate_shred).unwrap();
-        assert!(!blockstore.has_duplicate_shreds_in_slot(duplicate_shred_slot));
-        run_check_duplicate(&blockstore, &receiver).unwrap();
-        assert!(blockstore.has_duplicate_shreds_in_slot(duplicate_shred_slot));
+        let duplicate_shred = local_entries_to_shred(
+            &create_ticks(1, 1, Hash::new_unique()),
+            slot,
+            slot - 1,
+            &leader_keypair,
+        )
+        .remove(0);
+        sender.send(duplicate_shred.clone()).unwrap();
+        assert!(!blockstore.has_duplicate_shreds_in_slot(slot));
+        run_check_duplicate(
+            &cluster_info,
+            &blockstore,
+            &receiver,
+            &leader_schedule_cache,
+            &bank_forks,
+        )
+        .unwrap();
+        assert!(blockstore.has_duplicate_shreds_in_slot(slot));
+
+        // The proof is pushed to gossip
+        cluster_info.flush_push_queue();
+        let (chunks, _) = cluster_info.get_duplicate_shreds_since(None);
+        let (shred1, shred2) =
+            duplicate_shred::into_shreds(chunks, |_| Some(leader_keypair.pubkey())).unwrap();
+        assert_eq!(shred1, duplicate_shred);
+        assert_eq!(shred2, shreds[0]);
     }
 }
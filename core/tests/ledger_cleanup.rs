@@ -4,6 +4,7 @@
 mod tests {
     use solana_core::ledger_cleanup_service::LedgerCleanupService;
     use solana_ledger::blockstore::{make_many_slot_entries, Blockstore};
+    use solana_ledger::blockstore_db::LedgerRetention;
     use solana_ledger::get_tmp_ledger_path;
     use solana_ledger::shred::Shred;
     use std::collections::VecDeque;
@@ -29,6 +30,7 @@ mod tests {
         pub benchmark_slots: u64,
         pub batch_size: u64,
         pub max_ledger_shreds: u64,
+        pub ledger_retention: LedgerRetention,
         pub entries_per_slot: u64,
         pub stop_size_bytes: u64,
         pub stop_size_iterations: u64,
@@ -143,6 +145,11 @@ mod tests {
         let benchmark_slots = read_env("BENCHMARK_SLOTS", DEFAULT_BENCHMARK_SLOTS);
         let batch_size = read_env("BATCH_SIZE", DEFAULT_BATCH_SIZE);
         let max_ledger_shreds = read_env("MAX_LEDGER_SHREDS", DEFAULT_MAX_LEDGER_SHREDS);
+        let ledger_retention = if read_env("COMPACTION_FILTER_RETENTION", false) {
+            LedgerRetention::CompactionFilter
+        } else {
+            LedgerRetention::Purge
+        };
         let entries_per_slot = read_env("ENTRIES_PER_SLOT", DEFAULT_ENTRIES_PER_SLOT);
         let stop_size_bytes = read_env("STOP_SIZE_BYTES", DEFAULT_STOP_SIZE_BYTES);
         let stop_size_iterations = read_env("STOP_SIZE_ITERATIONS", DEFAULT_STOP_SIZE_ITERATIONS);
@@ -156,6 +163,7 @@ mod tests {
             benchmark_slots,
             batch_size,
             max_ledger_shreds,
+            ledger_retention,
             entries_per_slot,
             stop_size_bytes,
             stop_size_iterations,
@@ -223,8 +231,13 @@ mod tests {
 
         let (sender, receiver) = channel();
         let exit = Arc::new(AtomicBool::new(false));
-        let cleaner =
-            LedgerCleanupService::new(receiver, blockstore.clone(), max_ledger_shreds, &exit);
+        let cleaner = LedgerCleanupService::new(
+            receiver,
+            blockstore.clone(),
+            max_ledger_shreds,
+            config.ledger_retention,
+            &exit,
+        );
 
         let exit_cpu = Arc::new(AtomicBool::new(false));
         let sys = CpuStatsUpdater::new(&exit_cpu);
@@ -380,6 +393,7 @@ mod tests {
             &receiver,
             &blockstore,
             max_ledger_shreds,
+            LedgerRetention::Purge,
             &mut last_purge_slot,
             10,
             &mut last_compaction_slot,
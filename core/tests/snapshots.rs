@@ -93,6 +93,8 @@ mod tests {
                 &[],
                 None,
                 None,
+                None,
+                None,
             );
             bank0.freeze();
             let mut bank_forks = BankForks::new(bank0);
@@ -148,6 +150,8 @@ mod tests {
             old_genesis_config,
             None,
             None,
+            None,
+            None,
         )
         .unwrap();
 
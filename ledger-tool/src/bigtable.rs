@@ -9,19 +9,20 @@ use solana_ledger::{blockstore::Blockstore, blockstore_db::AccessType};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::ConfirmedBlock;
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     process::exit,
     result::Result,
     sync::{atomic::AtomicBool, Arc},
 };
 
 async fn upload(
+    local_path: Option<PathBuf>,
     blockstore: Blockstore,
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -36,8 +37,10 @@ async fn upload(
     .await
 }
 
-async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true).await?;
+async fn first_available_block(
+    local_path: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, true).await?;
     match bigtable.get_first_available_block().await? {
         Some(block) => println!("{}", block),
         None => println!("No blocks available"),
@@ -46,8 +49,8 @@ async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn block(slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn block(local_path: Option<PathBuf>, slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -74,8 +77,12 @@ async fn block(slot: Slot) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn blocks(starting_slot: Slot, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn blocks(
+    local_path: Option<PathBuf>,
+    starting_slot: Slot,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -86,8 +93,12 @@ async fn blocks(starting_slot: Slot, limit: usize) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
-async fn confirm(signature: &Signature, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false)
+async fn confirm(
+    local_path: Option<PathBuf>,
+    signature: &Signature,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, false)
         .await
         .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
 
@@ -119,6 +130,7 @@ async fn confirm(signature: &Signature, verbose: bool) -> Result<(), Box<dyn std
 }
 
 pub async fn transaction_history(
+    local_path: Option<PathBuf>,
     address: &Pubkey,
     mut limit: usize,
     mut before: Option<Signature>,
@@ -127,7 +139,7 @@ pub async fn transaction_history(
     show_transactions: bool,
     query_chunk_size: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true).await?;
+    let bigtable = solana_storage_bigtable::new_ledger_storage(local_path, true).await?;
 
     let mut loaded_block: Option<(Slot, ConfirmedBlock)> = None;
     while limit > 0 {
@@ -215,6 +227,17 @@ impl BigTableSubCommand for App<'_, '_> {
             SubCommand::with_name("bigtable")
                 .about("Ledger data on a BigTable instance")
                 .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("local_storage")
+                        .long("local-storage")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .global(true)
+                        .help(
+                            "Use the ledger storage in flat files under DIR \
+                               instead of a BigTable instance",
+                        ),
+                )
                 .subcommand(
                     SubCommand::with_name("upload")
                         .about("Upload the ledger to BigTable")
@@ -379,6 +402,13 @@ impl BigTableSubCommand for App<'_, '_> {
 
 pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
     let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    // `--local-storage` is global, so it lands in the subcommand matches when given after it
+    let local_path = matches
+        .subcommand()
+        .1
+        .and_then(|arg_matches| arg_matches.value_of("local_storage"))
+        .or_else(|| matches.value_of("local_storage"))
+        .map(PathBuf::from);
 
     let future = match matches.subcommand() {
         ("upload", Some(arg_matches)) => {
@@ -389,22 +419,25 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 crate::open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
 
             runtime.block_on(upload(
+                local_path,
                 blockstore,
                 starting_slot,
                 ending_slot,
                 allow_missing_metadata,
             ))
         }
-        ("first-available-block", Some(_arg_matches)) => runtime.block_on(first_available_block()),
+        ("first-available-block", Some(_arg_matches)) => {
+            runtime.block_on(first_available_block(local_path))
+        }
         ("block", Some(arg_matches)) => {
             let slot = value_t_or_exit!(arg_matches, "slot", Slot);
-            runtime.block_on(block(slot))
+            runtime.block_on(block(local_path, slot))
         }
         ("blocks", Some(arg_matches)) => {
             let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
             let limit = value_t_or_exit!(arg_matches, "limit", usize);
 
-            runtime.block_on(blocks(starting_slot, limit))
+            runtime.block_on(blocks(local_path, starting_slot, limit))
         }
         ("confirm", Some(arg_matches)) => {
             let signature = arg_matches
@@ -414,7 +447,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 .expect("Invalid signature");
             let verbose = arg_matches.is_present("verbose");
 
-            runtime.block_on(confirm(&signature, verbose))
+            runtime.block_on(confirm(local_path, &signature, verbose))
         }
         ("transaction-history", Some(arg_matches)) => {
             let address = pubkey_of(arg_matches, "address").unwrap();
@@ -430,6 +463,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
             let show_transactions = arg_matches.is_present("show_transactions");
 
             runtime.block_on(transaction_history(
+                local_path,
                 &address,
                 limit,
                 before,
@@ -1,6 +1,6 @@
 use clap::{
-    crate_description, crate_name, value_t, value_t_or_exit, values_t_or_exit, App, Arg,
-    ArgMatches, SubCommand,
+    crate_description, crate_name, value_t, value_t_or_exit, values_t, values_t_or_exit, App,
+    AppSettings, Arg, ArgMatches, SubCommand,
 };
 use itertools::Itertools;
 use log::*;
@@ -17,11 +17,15 @@ use solana_ledger::{
     blockstore::{create_new_ledger, Blockstore, PurgeType},
     blockstore_db::{self, AccessType, BlockstoreRecoveryMode, Column, Database},
     blockstore_processor::ProcessOptions,
+    leader_schedule_cache::LeaderScheduleCache,
     rooted_slot_iterator::RootedSlotIterator,
 };
 use solana_runtime::{
     bank::Bank,
     bank_forks::{BankForks, CompressionType, SnapshotConfig},
+    bank_hash_details::BankHashDetails,
+    cost_model::{CostModel, TransactionCost, MAX_BLOCK_UNITS, MAX_WRITABLE_ACCOUNT_UNITS},
+    cost_tracker::CostTracker,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_utils,
     snapshot_utils::SnapshotVersion,
@@ -39,6 +43,7 @@ use solana_sdk::{
     system_program,
 };
 use solana_stake_program::stake_state::{self, StakeState};
+use solana_transaction_status::UiTransactionEncoding;
 use solana_vote_program::{
     self,
     vote_state::{self, VoteState},
@@ -48,7 +53,7 @@ use std::{
     convert::TryInto,
     ffi::OsStr,
     fs::{self, File},
-    io::{self, stdout, BufRead, BufReader, Write},
+    io::{self, stdout, BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
     process::{exit, Command, Stdio},
     str::FromStr,
@@ -58,6 +63,21 @@ use std::{
 mod bigtable;
 use bigtable::*;
 
+mod program;
+use program::{run_program, ExecutionMode, InstructionInput, ProgramSource};
+
+mod snapshot_diff;
+use snapshot_diff::{bank_from_snapshot_archive, SnapshotDiff};
+
+mod transaction_replay;
+use transaction_replay::{
+    replay_slot_execution, replay_slots, replay_transactions, AccountDataEncoding,
+    TransactionFilter,
+};
+
+mod transaction_search;
+use transaction_search::{search_transactions, TransactionSearchFilter, TransactionStatusFilter};
+
 #[derive(PartialEq)]
 enum LedgerOutputMethod {
     Print,
@@ -134,6 +154,107 @@ fn output_entry(
     }
 }
 
+fn compute_slot_cost(slot: Slot, entries: &[Entry], cost_model: &CostModel) {
+    // Number of writable accounts listed in the report
+    const MAX_LISTED_ACCOUNTS: usize = 10;
+
+    // Accumulate the whole block to report its cost, and separately check which transactions
+    // a leader would have deferred to a later block
+    let mut block_cost_tracker = CostTracker::new(std::u64::MAX, std::u64::MAX);
+    let mut limited_cost_tracker = CostTracker::default();
+    let mut total_cost = TransactionCost::default();
+    let mut num_transactions = 0;
+    let mut num_programs = HashMap::new();
+    for transaction in entries.iter().flat_map(|entry| entry.transactions.iter()) {
+        let transaction_cost = cost_model.calculate_cost(transaction);
+        total_cost.signature_cost += transaction_cost.signature_cost;
+        total_cost.write_lock_cost += transaction_cost.write_lock_cost;
+        total_cost.data_bytes_cost += transaction_cost.data_bytes_cost;
+        total_cost.execution_cost += transaction_cost.execution_cost;
+        for program_id in transaction.message.program_ids() {
+            *num_programs.entry(*program_id).or_insert(0) += 1;
+        }
+        block_cost_tracker.add(&transaction_cost);
+        let _ = limited_cost_tracker.try_add(transaction);
+        num_transactions += 1;
+    }
+
+    println!(
+        "Slot {}: entries: {} transactions: {}",
+        slot,
+        entries.len(),
+        num_transactions
+    );
+    println!(
+        "  Block cost: {} of {} ({} transactions over the block or account limits)",
+        total_cost.sum(),
+        MAX_BLOCK_UNITS,
+        limited_cost_tracker.stats().cost_limited_count
+    );
+    println!("    signatures: {}", total_cost.signature_cost);
+    println!("    write locks: {}", total_cost.write_lock_cost);
+    println!("    instruction data: {}", total_cost.data_bytes_cost);
+    println!("    execution: {}", total_cost.execution_cost);
+    println!("  Programs:");
+    for (program_id, count) in num_programs
+        .into_iter()
+        .sorted_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)))
+    {
+        println!(
+            "    {}: {} instructions, {} per instruction",
+            program_id,
+            count,
+            cost_model.find_instruction_cost(&program_id)
+        );
+    }
+    println!(
+        "  Writable accounts (limit {} each):",
+        MAX_WRITABLE_ACCOUNT_UNITS
+    );
+    for (account, cost) in block_cost_tracker
+        .cost_by_writable_accounts()
+        .into_iter()
+        .take(MAX_LISTED_ACCOUNTS)
+    {
+        println!("    {}: {}", account, cost);
+    }
+}
+
+/// Replays the slots after `bank` up to the last of `slots`, and reports the cost of each of
+/// `slots` with the execution costs the cost model learned from the replay so far
+fn compute_slot_costs(
+    bank: Arc<Bank>,
+    leader_schedule_cache: &LeaderScheduleCache,
+    blockstore: &Blockstore,
+    slots: &BTreeSet<Slot>,
+) -> Result<(), String> {
+    let ending_slot = *slots.iter().next_back().unwrap();
+    let mut cost_model = CostModel::new();
+    let mut reported_slots = BTreeSet::new();
+    replay_slots(
+        bank,
+        leader_schedule_cache,
+        blockstore,
+        ending_slot,
+        |bank, entries| {
+            let execute_timings = replay_slot_execution(bank, &entries)?;
+            cost_model.update_execution_costs(&execute_timings);
+            if slots.contains(&bank.slot()) {
+                compute_slot_cost(bank.slot(), &entries, &cost_model);
+                reported_slots.insert(bank.slot());
+            }
+            Ok(())
+        },
+    )?;
+    for slot in slots.difference(&reported_slots) {
+        eprintln!(
+            "Warning: slot {} was not replayed, it is not on the replayed fork",
+            slot
+        );
+    }
+    Ok(())
+}
+
 fn output_slot(
     blockstore: &Blockstore,
     slot: Slot,
@@ -607,6 +728,7 @@ fn analyze_storage(database: &Database) -> Result<(), String> {
         "AddressSignatures",
         AddressSignatures::key_size(),
     )?;
+    analyze_column::<BlockhashSlot>(database, "BlockhashSlot", BlockhashSlot::key_size())?;
     analyze_column::<Rewards>(database, "Rewards", Rewards::key_size())?;
 
     Ok(())
@@ -875,6 +997,39 @@ fn main() {
                     .help("Target db"),
             )
         )
+        .subcommand(
+            SubCommand::with_name("export")
+            .about("Write a range of slots to a portable ledger archive")
+            .arg(
+                Arg::with_name("archive")
+                    .index(1)
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Path of the archive to create"),
+            )
+            .arg(&starting_slot_arg)
+            .arg(
+                Arg::with_name("ending_slot")
+                    .long("ending-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .help("Last slot to export [default: the highest slot in the ledger]"),
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+            .about("Rebuild an empty ledger from a ledger archive created by the export command")
+            .arg(
+                Arg::with_name("archive")
+                    .index(1)
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Path of the archive to import"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("slot")
             .about("Print the contents of one or more slots")
@@ -890,6 +1045,25 @@ fn main() {
             )
             .arg(&allow_dead_slots_arg)
         )
+        .subcommand(
+            SubCommand::with_name("compute-slot-cost")
+            .about("Replay one or more slots to estimate the cost of their transactions, and \
+                    report which accounts and programs the block's cost is spent on")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("slots")
+                    .index(1)
+                    .value_name("SLOTS")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(true)
+                    .help("Slots to compute the cost of"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("dead-slots")
             .arg(&starting_slot_arg)
@@ -971,6 +1145,71 @@ fn main() {
             .about("Prints the hash of the working bank after reading the ledger")
             .arg(&max_genesis_archive_unpacked_size_arg)
         )
+        .subcommand(
+            SubCommand::with_name("bank-hash-details")
+            .about("Write every input of a bank's hash to a file, \
+                    for comparison with another node's using bank-hash-diff")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&halt_at_slot_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("output_file")
+                    .long("output-file")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("Write the details to FILE \
+                           [default: bank-hash-details-<SLOT>-<BANK_HASH>.json]"),
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("bank-hash-diff")
+            .about("Report how the bank hash details written by bank-hash-details differ")
+            .arg(
+                Arg::with_name("first")
+                    .index(1)
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("First bank hash details file"),
+            )
+            .arg(
+                Arg::with_name("second")
+                    .index(2)
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Second bank hash details file"),
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("snapshot-diff")
+            .about("Report the accounts that differ between the banks of two snapshot archives")
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("first")
+                    .index(1)
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("First snapshot archive"),
+            )
+            .arg(
+                Arg::with_name("second")
+                    .index(2)
+                    .value_name("ARCHIVE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Second snapshot archive"),
+            )
+            .arg(
+                Arg::with_name("dump_account_data")
+                    .long("dump-account-data")
+                    .takes_value(false)
+                    .help("Also print each changed account before and after, as parsed JSON"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("bounds")
             .about("Print lowest and highest non-empty slots. Note that there may be empty slots within the bounds")
@@ -1140,6 +1379,190 @@ fn main() {
                     .takes_value(false)
                     .help("Remove all existing stake accounts from the new snapshot.")
             )
+        ).subcommand(
+            SubCommand::with_name("replay-transactions")
+            .about("Replay a slot range and print what each transaction did to its accounts")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("starting_slot")
+                    .long("starting-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .required(true)
+                    .help("First slot to report the transactions of"),
+            )
+            .arg(
+                Arg::with_name("ending_slot")
+                    .long("ending-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .help("Last slot to replay [default: the starting slot]"),
+            )
+            .arg(
+                Arg::with_name("accounts")
+                    .long("account")
+                    .value_name("PUBKEY")
+                    .validator(is_pubkey_or_keypair)
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only report transactions that reference this account"),
+            )
+            .arg(
+                Arg::with_name("programs")
+                    .long("program")
+                    .value_name("PUBKEY")
+                    .validator(is_pubkey_or_keypair)
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only report transactions that invoke this program, \
+                           directly or through an inner instruction"),
+            )
+            .arg(
+                Arg::with_name("output_format")
+                    .long("output")
+                    .value_name("FORMAT")
+                    .takes_value(true)
+                    .possible_values(&["text", "json"])
+                    .default_value("text")
+                    .help("Output format"),
+            )
+            .arg(
+                Arg::with_name("account_data_encoding")
+                    .long("account-data-encoding")
+                    .value_name("ENCODING")
+                    .takes_value(true)
+                    .possible_values(&["hex", "parsed"])
+                    .default_value("hex")
+                    .help("Encoding of changed account data. \
+                           \"parsed\" falls back to hex for data that cannot be parsed"),
+            )
+        ).subcommand(
+            SubCommand::with_name("transactions")
+            .about("Search the transactions of a slot range, using the transaction statuses \
+                    stored in the ledger, and print the matches as JSON, one per line")
+            .arg(&starting_slot_arg)
+            .arg(
+                Arg::with_name("ending_slot")
+                    .long("ending-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .help("Last slot to search [default: the highest slot in the ledger]"),
+            )
+            .arg(
+                Arg::with_name("only_rooted")
+                    .long("only-rooted")
+                    .takes_value(false)
+                    .help("Only search root slots"),
+            )
+            .arg(
+                Arg::with_name("accounts")
+                    .long("account")
+                    .value_name("PUBKEY")
+                    .validator(is_pubkey_or_keypair)
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only print transactions that reference this account"),
+            )
+            .arg(
+                Arg::with_name("programs")
+                    .long("program")
+                    .value_name("PUBKEY")
+                    .validator(is_pubkey_or_keypair)
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only print transactions that invoke this program, \
+                           directly or through an inner instruction"),
+            )
+            .arg(
+                Arg::with_name("status")
+                    .long("status")
+                    .value_name("STATUS")
+                    .takes_value(true)
+                    .possible_values(&["success", "failure"])
+                    .help("Only print transactions with this status"),
+            )
+            .arg(
+                Arg::with_name("logs")
+                    .long("log")
+                    .value_name("SUBSTRING")
+                    .takes_value(true)
+                    .multiple(true)
+                    .help("Only print transactions with a log message containing this substring"),
+            )
+            .arg(
+                Arg::with_name("encoding")
+                    .long("encoding")
+                    .value_name("ENCODING")
+                    .takes_value(true)
+                    .possible_values(&["json", "jsonParsed"])
+                    .default_value("json")
+                    .help("Encoding of the printed transactions"),
+            )
+        ).subcommand(
+            SubCommand::with_name("program")
+            .about("Run BPF programs against the ledger's accounts")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("run")
+                .about("Run a program once against the accounts of a bank, \
+                        without modifying the ledger")
+                .arg(&no_snapshot_arg)
+                .arg(&account_paths_arg)
+                .arg(&halt_at_slot_arg)
+                .arg(&hard_forks_arg)
+                .arg(&max_genesis_archive_unpacked_size_arg)
+                .arg(
+                    Arg::with_name("program")
+                        .index(1)
+                        .value_name("PROGRAM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Path to the program's shared object, \
+                               or the address of a program deployed in the bank"),
+                )
+                .arg(
+                    Arg::with_name("program_id")
+                        .long("program-id")
+                        .value_name("PUBKEY")
+                        .validator(is_pubkey_or_keypair)
+                        .takes_value(true)
+                        .help("Address to run a shared object at \
+                               [default: derived from the shared object's hash]"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("JSON description of the instruction, of the form \
+                               {\"accounts\": [{\"pubkey\": PUBKEY, \"isSigner\": BOOL, \
+                               \"isWritable\": BOOL}], \"data\": {\"hex\": HEX} \
+                               or {\"base64\": BASE64}}"),
+                )
+                .arg(
+                    Arg::with_name("mode")
+                        .long("mode")
+                        .value_name("MODE")
+                        .takes_value(true)
+                        .possible_values(&["interpreter", "jit"])
+                        .default_value("interpreter")
+                        .help("How to execute the program. \
+                               The JIT does not meter compute units"),
+                )
+                .arg(
+                    Arg::with_name("trace")
+                        .long("trace")
+                        .takes_value(false)
+                        .help("Log each BPF instruction as it is executed"),
+                )
+            )
         ).subcommand(
             SubCommand::with_name("accounts")
             .about("Print account contents after processing in the ledger")
@@ -1279,6 +1702,14 @@ fn main() {
         },
     ));
 
+    // Import builds a new ledger, so its ledger path need not exist yet
+    if matches.subcommand_name() == Some("import") {
+        if let Err(err) = fs::create_dir_all(&ledger_path) {
+            eprintln!("Unable to create ledger path: {:?}", err);
+            exit(1);
+        }
+    }
+
     // Canonicalize ledger path to avoid issues with symlink creation
     let ledger_path = fs::canonicalize(&ledger_path).unwrap_or_else(|err| {
         eprintln!("Unable to access ledger path: {:?}", err);
@@ -1332,6 +1763,56 @@ fn main() {
                 }
             }
         }
+        ("export", Some(arg_matches)) => {
+            let archive_path = PathBuf::from(value_t_or_exit!(arg_matches, "archive", String));
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(std::u64::MAX);
+            let blockstore =
+                open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
+            let result = File::create(&archive_path)
+                .map_err(|err| format!("Unable to create {}: {}", archive_path.display(), err))
+                .and_then(|file| {
+                    blockstore
+                        .export_archive(starting_slot, ending_slot, BufWriter::new(file))
+                        .map_err(|err| format!("Export failed: {:?}", err))
+                });
+            match result {
+                Ok(stats) => println!("Exported {} to {}", stats, archive_path.display()),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
+        }
+        ("import", Some(arg_matches)) => {
+            let archive_path = PathBuf::from(value_t_or_exit!(arg_matches, "archive", String));
+            let blockstore = open_blockstore(&ledger_path, AccessType::PrimaryOnly, None);
+            if blockstore
+                .slot_meta_iterator(0)
+                .map(|mut metas| metas.next().is_some())
+                .unwrap_or(true)
+            {
+                eprintln!(
+                    "The ledger at {} is not empty, import only rebuilds an empty ledger",
+                    ledger_path.display()
+                );
+                exit(1);
+            }
+            let result = File::open(&archive_path)
+                .map_err(|err| format!("Unable to open {}: {}", archive_path.display(), err))
+                .and_then(|file| {
+                    blockstore
+                        .import_archive(BufReader::new(file))
+                        .map_err(|err| format!("Import failed: {:?}", err))
+                });
+            match result {
+                Ok(stats) => println!("Imported {} from {}", stats, archive_path.display()),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
+        }
         ("genesis", Some(arg_matches)) => {
             println!("{}", open_genesis_config_by(&ledger_path, arg_matches));
         }
@@ -1428,6 +1909,112 @@ fn main() {
                 }
             }
         }
+        ("bank-hash-details", Some(arg_matches)) => {
+            let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
+            let process_options = ProcessOptions {
+                dev_halt_at_slot,
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            match load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+            ) {
+                Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
+                    let bank = bank_forks.working_bank();
+                    let bank_hash_details = BankHashDetails::new(&bank);
+                    let output_file = arg_matches
+                        .value_of("output_file")
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| {
+                            PathBuf::from(format!(
+                                "bank-hash-details-{}-{}.json",
+                                bank_hash_details.slot, bank_hash_details.bank_hash
+                            ))
+                        });
+                    let result = File::create(&output_file)
+                        .map_err(|err| err.to_string())
+                        .and_then(|file| {
+                            serde_json::to_writer_pretty(file, &bank_hash_details)
+                                .map_err(|err| err.to_string())
+                        });
+                    if let Err(err) = result {
+                        eprintln!("Unable to write {}: {}", output_file.display(), err);
+                        exit(1);
+                    }
+                    println!(
+                        "Bank hash details of slot {} written to {}",
+                        bank_hash_details.slot,
+                        output_file.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("Failed to load ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
+        ("bank-hash-diff", Some(arg_matches)) => {
+            let read_bank_hash_details = |name| {
+                let path = value_t_or_exit!(arg_matches, name, String);
+                File::open(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|file| {
+                        serde_json::from_reader::<_, BankHashDetails>(BufReader::new(file))
+                            .map_err(|err| err.to_string())
+                    })
+                    .unwrap_or_else(|err| {
+                        eprintln!("Unable to read {}: {}", path, err);
+                        exit(1);
+                    })
+            };
+            let first = read_bank_hash_details("first");
+            let second = read_bank_hash_details("second");
+            let differences = first.diff(&second);
+            if differences.is_empty() {
+                println!("No differences");
+            } else {
+                for difference in &differences {
+                    println!("{}", difference);
+                }
+                exit(1);
+            }
+        }
+        ("snapshot-diff", Some(arg_matches)) => {
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let load = |name| {
+                let archive = PathBuf::from(value_t_or_exit!(arg_matches, name, String));
+                bank_from_snapshot_archive(&archive, &genesis_config, &ledger_path).unwrap_or_else(
+                    |err| {
+                        eprintln!("{}", err);
+                        exit(1);
+                    },
+                )
+            };
+            let (first, _first_dir) = load("first");
+            let (second, _second_dir) = load("second");
+            println!(
+                "Comparing slot {} ({}) with slot {} ({})",
+                first.slot(),
+                first.hash(),
+                second.slot(),
+                second.hash()
+            );
+            let diff = SnapshotDiff::new(&first, &second);
+            if diff.is_empty() {
+                println!("No differences");
+            } else {
+                diff.print(arg_matches.is_present("dump_account_data"));
+                exit(1);
+            }
+        }
         ("slot", Some(arg_matches)) => {
             let slots = values_t_or_exit!(arg_matches, "slots", Slot);
             let allow_dead_slots = arg_matches.is_present("allow_dead_slots");
@@ -1449,6 +2036,49 @@ fn main() {
                 }
             }
         }
+        ("compute-slot-cost", Some(arg_matches)) => {
+            let slots: BTreeSet<Slot> = values_t_or_exit!(arg_matches, "slots", Slot)
+                .into_iter()
+                .collect();
+            let starting_slot = *slots.iter().next().unwrap();
+
+            // Replay starts from the bank of the slot just before the first slot
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(starting_slot.saturating_sub(1)),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            match load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+            ) {
+                Ok((bank_forks, leader_schedule_cache, _snapshot_hash)) => {
+                    // The WAL was already recovered while loading the bank forks
+                    let blockstore =
+                        open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
+                    if let Err(err) = compute_slot_costs(
+                        bank_forks.working_bank(),
+                        &leader_schedule_cache,
+                        &blockstore,
+                        &slots,
+                    ) {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Failed to load ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
         ("json", Some(arg_matches)) => {
             let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
             let allow_dead_slots = arg_matches.is_present("allow_dead_slots");
@@ -1893,6 +2523,173 @@ fn main() {
                 }
             }
         }
+        ("replay-transactions", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(starting_slot);
+            if ending_slot < starting_slot {
+                eprintln!(
+                    "The ending slot {} is before the starting slot {}",
+                    ending_slot, starting_slot
+                );
+                exit(1);
+            }
+            let filter = TransactionFilter::new(
+                pubkeys_of(arg_matches, "accounts").unwrap_or_default(),
+                pubkeys_of(arg_matches, "programs").unwrap_or_default(),
+            );
+            let method = match arg_matches.value_of("output_format") {
+                Some("json") => LedgerOutputMethod::Json,
+                _ => LedgerOutputMethod::Print,
+            };
+            let encoding =
+                value_t_or_exit!(arg_matches, "account_data_encoding", AccountDataEncoding);
+
+            // Replay starts from the bank of the slot just before the starting slot
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(starting_slot.saturating_sub(1)),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            match load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+            ) {
+                Ok((bank_forks, leader_schedule_cache, _snapshot_hash)) => {
+                    // The WAL was already recovered while loading the bank forks
+                    let blockstore =
+                        open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
+                    if let Err(err) = replay_transactions(
+                        bank_forks.working_bank(),
+                        &leader_schedule_cache,
+                        &blockstore,
+                        starting_slot,
+                        ending_slot,
+                        &filter,
+                        &method,
+                        encoding,
+                    ) {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Failed to load ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
+        ("transactions", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(std::u64::MAX);
+            let only_rooted = arg_matches.is_present("only_rooted");
+            let filter = TransactionSearchFilter::new(
+                TransactionFilter::new(
+                    pubkeys_of(arg_matches, "accounts").unwrap_or_default(),
+                    pubkeys_of(arg_matches, "programs").unwrap_or_default(),
+                ),
+                value_t!(arg_matches, "status", TransactionStatusFilter).ok(),
+                values_t!(arg_matches, "logs", String).unwrap_or_default(),
+            );
+            let encoding = match arg_matches.value_of("encoding") {
+                Some("jsonParsed") => UiTransactionEncoding::JsonParsed,
+                _ => UiTransactionEncoding::Json,
+            };
+            let blockstore = open_blockstore(
+                &ledger_path,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+            );
+            if let Err(err) = search_transactions(
+                &blockstore,
+                starting_slot,
+                ending_slot,
+                only_rooted,
+                &filter,
+                encoding,
+            ) {
+                eprintln!("{}", err);
+                exit(1);
+            }
+        }
+        ("program", Some(arg_matches)) => match arg_matches.subcommand() {
+            ("run", Some(arg_matches)) => {
+                let program = arg_matches.value_of("program").unwrap();
+                let program = if Path::new(program).is_file() {
+                    ProgramSource::File {
+                        path: PathBuf::from(program),
+                        program_id: pubkey_of(arg_matches, "program_id"),
+                    }
+                } else if let Ok(program_id) = Pubkey::from_str(program) {
+                    ProgramSource::Deployed(program_id)
+                } else {
+                    eprintln!("{} is neither a file nor a program address", program);
+                    exit(1);
+                };
+                let input_path = value_t_or_exit!(arg_matches, "input", String);
+                let input: InstructionInput = match File::open(&input_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|file| {
+                        serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())
+                    }) {
+                    Ok(input) => input,
+                    Err(err) => {
+                        eprintln!("Unable to read {}: {}", input_path, err);
+                        exit(1);
+                    }
+                };
+                let mode = value_t_or_exit!(arg_matches, "mode", ExecutionMode);
+
+                let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
+                let process_options = ProcessOptions {
+                    dev_halt_at_slot,
+                    new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                    poh_verify: false,
+                    ..ProcessOptions::default()
+                };
+                let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+                match load_bank_forks(
+                    arg_matches,
+                    &ledger_path,
+                    &genesis_config,
+                    process_options,
+                    AccessType::TryPrimaryThenSecondary,
+                    wal_recovery_mode,
+                    snapshot_archive_path,
+                ) {
+                    Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
+                        let bank = bank_forks.working_bank();
+                        println!("Running against the bank of slot {}", bank.slot());
+                        if arg_matches.is_present("trace") {
+                            solana_logger::setup_with("solana=info,solana_rbpf=trace");
+                        }
+                        match run_program(&bank, &program, &input, mode) {
+                            Ok(run) => {
+                                run.print();
+                                if run.result.is_err() {
+                                    exit(1);
+                                }
+                            }
+                            Err(err) => {
+                                eprintln!("{}", err);
+                                exit(1);
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to load ledger: {:?}", err);
+                        exit(1);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        },
         ("accounts", Some(arg_matches)) => {
             let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
             let process_options = ProcessOptions {
@@ -0,0 +1,469 @@
+/// Execution of a BPF program against the accounts of a bank loaded from the ledger
+use serde_derive::Deserialize;
+use solana_bpf_loader_program::{
+    bpf_verifier, create_vm,
+    serialization::{deserialize_parameters, serialize_parameters},
+    syscalls::SyscallError,
+    BPFError,
+};
+use solana_rbpf::{
+    error::EbpfError,
+    vm::{EbpfVm, InstructionMeter},
+};
+use solana_runtime::{
+    bank::Bank,
+    feature_set::compute_budget_balancing,
+    log_collector::LogCollector,
+    message_processor::{Executors, PreAccount, ThisInvokeContext},
+    process_instruction::{ComputeBudget, ComputeMeter, InvokeContext},
+};
+use solana_sdk::{
+    account::{Account, KeyedAccount},
+    bpf_loader, bpf_loader_deprecated,
+    entrypoint::SUCCESS,
+    hash::hash,
+    instruction::InstructionError,
+    pubkey::Pubkey,
+};
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionMode {
+    Interpreter,
+    Jit,
+}
+
+impl FromStr for ExecutionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interpreter" => Ok(Self::Interpreter),
+            "jit" => Ok(Self::Jit),
+            _ => Err(format!("Unsupported execution mode: {}", s)),
+        }
+    }
+}
+
+/// Where the program's ELF comes from
+pub enum ProgramSource {
+    /// A shared object on disk, run as if deployed by the current BPF loader at `program_id`
+    File {
+        path: PathBuf,
+        program_id: Option<Pubkey>,
+    },
+    /// A program already deployed in the bank
+    Deployed(Pubkey),
+}
+
+impl ProgramSource {
+    // Returns the program id, the loader that owns it and its ELF
+    fn load(&self, bank: &Bank) -> Result<(Pubkey, Pubkey, Vec<u8>), String> {
+        match self {
+            Self::File { path, program_id } => {
+                let elf = fs::read(path)
+                    .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+                let program_id = program_id.unwrap_or_else(|| Pubkey::new(hash(&elf).as_ref()));
+                Ok((program_id, bpf_loader::id(), elf))
+            }
+            Self::Deployed(program_id) => {
+                let account = bank
+                    .get_account(program_id)
+                    .ok_or_else(|| format!("Program {} not found", program_id))?;
+                if !account.executable
+                    || !(bpf_loader::check_id(&account.owner)
+                        || bpf_loader_deprecated::check_id(&account.owner))
+                {
+                    return Err(format!("{} is not a deployed BPF program", program_id));
+                }
+                Ok((*program_id, account.owner, account.data))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionAccount {
+    pub pubkey: String,
+    #[serde(default)]
+    pub is_signer: bool,
+    #[serde(default)]
+    pub is_writable: bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum InstructionData {
+    Hex(String),
+    Base64(String),
+}
+
+/// The instruction to run, as read from the `--input` JSON file:
+/// `{"accounts": [{"pubkey": "...", "isSigner": false, "isWritable": true}], "data": {"hex": "..."}}`
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct InstructionInput {
+    #[serde(default)]
+    pub accounts: Vec<InstructionAccount>,
+    pub data: Option<InstructionData>,
+}
+
+impl InstructionInput {
+    fn data(&self) -> Result<Vec<u8>, String> {
+        match &self.data {
+            None => Ok(vec![]),
+            Some(InstructionData::Hex(data)) => {
+                hex::decode(data).map_err(|err| format!("Invalid hex instruction data: {}", err))
+            }
+            Some(InstructionData::Base64(data)) => base64::decode(data)
+                .map_err(|err| format!("Invalid base64 instruction data: {}", err)),
+        }
+    }
+
+    fn account_keys(&self) -> Result<Vec<Pubkey>, String> {
+        self.accounts
+            .iter()
+            .map(|account| {
+                Pubkey::from_str(&account.pubkey)
+                    .map_err(|_| format!("Invalid account pubkey: {}", account.pubkey))
+            })
+            .collect()
+    }
+}
+
+// Charges the invoke context's compute meter for each instruction the interpreter executes
+struct RunInstructionMeter {
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+}
+
+impl InstructionMeter for RunInstructionMeter {
+    fn consume(&mut self, amount: u64) {
+        // the interpreter bails itself once the remaining units are exhausted
+        let _ = self.compute_meter.borrow_mut().consume(amount);
+    }
+    fn get_remaining(&self) -> u64 {
+        self.compute_meter.borrow().get_remaining()
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountChange {
+    pub pubkey: Pubkey,
+    pub pre: Account,
+    pub post: Account,
+}
+
+impl AccountChange {
+    fn describe(&self) -> Vec<String> {
+        let (pre, post) = (&self.pre, &self.post);
+        let mut changes = vec![];
+        if pre.lamports != post.lamports {
+            changes.push(format!("lamports {} -> {}", pre.lamports, post.lamports));
+        }
+        if pre.owner != post.owner {
+            changes.push(format!("owner {} -> {}", pre.owner, post.owner));
+        }
+        if pre.executable != post.executable {
+            changes.push(format!(
+                "executable {} -> {}",
+                pre.executable, post.executable
+            ));
+        }
+        if pre.data != post.data {
+            changes.push(format!(
+                "data ({} bytes) {} -> ({} bytes) {}",
+                pre.data.len(),
+                hex::encode(&pre.data),
+                post.data.len(),
+                hex::encode(&post.data)
+            ));
+        }
+        changes
+    }
+}
+
+#[derive(Debug)]
+pub struct ProgramRun {
+    pub program_id: Pubkey,
+    pub logs: Vec<String>,
+    pub compute_units_consumed: u64,
+    pub compute_units_available: u64,
+    /// The value returned by the program's entrypoint, if it ran to completion
+    pub return_value: Option<u64>,
+    pub result: Result<(), String>,
+    pub account_changes: Vec<AccountChange>,
+}
+
+impl ProgramRun {
+    pub fn print(&self) {
+        println!("Program {}", self.program_id);
+        println!("Logs:");
+        for log in &self.logs {
+            println!("  {}", log);
+        }
+        println!(
+            "Compute units: {} of {} consumed",
+            self.compute_units_consumed, self.compute_units_available
+        );
+        match self.return_value {
+            Some(return_value) => println!("Return value: {}", return_value),
+            None => println!("Return value: none"),
+        }
+        match &self.result {
+            Ok(()) => println!("Result: success"),
+            Err(err) => println!("Result: {}", err),
+        }
+        if self.account_changes.is_empty() {
+            println!("No account changes");
+        } else {
+            println!("Account changes:");
+            for change in &self.account_changes {
+                println!("  {}:", change.pubkey);
+                for description in change.describe() {
+                    println!("    {}", description);
+                }
+            }
+        }
+    }
+}
+
+/// Runs `program` once with the instruction described by `input`, using copies of the bank's
+/// accounts. The bank itself is never modified
+pub fn run_program(
+    bank: &Bank,
+    program: &ProgramSource,
+    input: &InstructionInput,
+    mode: ExecutionMode,
+) -> Result<ProgramRun, String> {
+    let (program_id, loader_id, elf) = program.load(bank)?;
+    let instruction_data = input.data()?;
+    let account_keys = input.account_keys()?;
+    let pre_accounts: Vec<Account> = account_keys
+        .iter()
+        .map(|pubkey| bank.get_account(pubkey).unwrap_or_default())
+        .collect();
+    let accounts: Vec<RefCell<Account>> = pre_accounts.iter().cloned().map(RefCell::new).collect();
+    let keyed_accounts: Vec<KeyedAccount> = input
+        .accounts
+        .iter()
+        .zip(account_keys.iter().zip(accounts.iter()))
+        .map(|(meta, (pubkey, account))| {
+            if meta.is_writable {
+                KeyedAccount::new(pubkey, meta.is_signer, account)
+            } else {
+                KeyedAccount::new_readonly(pubkey, meta.is_signer, account)
+            }
+        })
+        .collect();
+
+    let log_collector = Rc::new(LogCollector::default());
+    let compute_budget = ComputeBudget::new(&bank.feature_set);
+    let mut invoke_context = ThisInvokeContext::new(
+        &program_id,
+        bank.rent_collector().rent,
+        input
+            .accounts
+            .iter()
+            .zip(account_keys.iter().zip(pre_accounts.iter()))
+            .map(|(meta, (pubkey, account))| {
+                PreAccount::new(pubkey, account, meta.is_signer, meta.is_writable)
+            })
+            .collect(),
+        bank.builtin_programs().to_vec(),
+        Some(log_collector.clone()),
+        compute_budget,
+        Rc::new(RefCell::new(Executors::default())),
+        None,
+        bank.feature_set.clone(),
+    );
+    let compute_meter = invoke_context.get_compute_meter();
+
+    let executable = EbpfVm::<BPFError>::create_executable_from_elf(&elf, None)
+        .map_err(|err| format!("Invalid program ELF: {}", err))?;
+    let (_, text_bytes) = executable
+        .get_text_bytes()
+        .map_err(|err| format!("Invalid program ELF: {}", err))?;
+    bpf_verifier::check(
+        text_bytes,
+        !bank.feature_set.is_active(&compute_budget_balancing::id()),
+    )
+    .map_err(|err| format!("Program failed verification: {}", err))?;
+
+    let mut parameter_bytes =
+        serialize_parameters(&loader_id, &program_id, &keyed_accounts, &instruction_data)
+            .map_err(|err| format!("Unable to serialize the instruction: {}", err))?;
+
+    let execution = {
+        let (mut vm, heap_region) = create_vm(
+            &loader_id,
+            executable.as_ref(),
+            &keyed_accounts,
+            &mut invoke_context,
+        )
+        .map_err(|err| format!("Failed to create BPF VM: {}", err))?;
+        match mode {
+            ExecutionMode::Interpreter => vm.execute_program_metered(
+                parameter_bytes.as_slice(),
+                &[],
+                &[heap_region],
+                RunInstructionMeter {
+                    compute_meter: compute_meter.clone(),
+                },
+            ),
+            // The JIT neither meters nor bounds checks, so it is only suitable for
+            // programs already known to run under the interpreter
+            ExecutionMode::Jit => vm
+                .jit_compile()
+                .and_then(|()| unsafe { vm.execute_program_jit(&mut parameter_bytes) }),
+        }
+    };
+    let compute_units_consumed = compute_budget.max_units - compute_meter.borrow().get_remaining();
+
+    let (return_value, result) = match execution {
+        Ok(SUCCESS) => (
+            Some(SUCCESS),
+            deserialize_parameters(&loader_id, &keyed_accounts, &parameter_bytes)
+                .map_err(|err| format!("Unable to deserialize the accounts: {}", err)),
+        ),
+        Ok(status) => (
+            Some(status),
+            Err(format!(
+                "Program failed: {}",
+                InstructionError::from(status)
+            )),
+        ),
+        Err(EbpfError::UserError(BPFError::SyscallError(SyscallError::InstructionError(err)))) => {
+            (None, Err(format!("Program failed: {}", err)))
+        }
+        Err(err) => (None, Err(format!("Program failed to run: {}", err))),
+    };
+
+    drop(keyed_accounts);
+    drop(invoke_context);
+    let logs = Rc::try_unwrap(log_collector)
+        .map(Into::into)
+        .unwrap_or_default();
+    let account_changes = account_keys
+        .into_iter()
+        .zip(pre_accounts.into_iter().zip(accounts.into_iter()))
+        .map(|(pubkey, (pre, post))| AccountChange {
+            pubkey,
+            pre,
+            post: post.into_inner(),
+        })
+        .filter(|change| change.pre != change.post)
+        .collect();
+
+    Ok(ProgramRun {
+        program_id,
+        logs,
+        compute_units_consumed,
+        compute_units_available: compute_budget.max_units,
+        return_value,
+        result,
+        account_changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_runtime::genesis_utils::create_genesis_config;
+    use solana_sdk::signature::Signer;
+
+    #[test]
+    fn test_instruction_input() {
+        let pubkey = Pubkey::new_unique();
+        let input: InstructionInput = serde_json::from_str(&format!(
+            r#"{{"accounts": [{{"pubkey": "{}", "isWritable": true}}], "data": {{"hex": "0a0b"}}}}"#,
+            pubkey
+        ))
+        .unwrap();
+        assert_eq!(
+            input.accounts,
+            vec![InstructionAccount {
+                pubkey: pubkey.to_string(),
+                is_signer: false,
+                is_writable: true,
+            }]
+        );
+        assert_eq!(input.account_keys().unwrap(), vec![pubkey]);
+        assert_eq!(input.data().unwrap(), vec![10, 11]);
+
+        let input: InstructionInput =
+            serde_json::from_str(r#"{"data": {"base64": "CgsM"}}"#).unwrap();
+        assert!(input.accounts.is_empty());
+        assert_eq!(input.data().unwrap(), vec![10, 11, 12]);
+
+        let input: InstructionInput = serde_json::from_str("{}").unwrap();
+        assert_eq!(input.data().unwrap(), Vec::<u8>::new());
+
+        let input: InstructionInput =
+            serde_json::from_str(r#"{"accounts": [{"pubkey": "bogus"}], "data": {"hex": "x"}}"#)
+                .unwrap();
+        assert!(input.account_keys().is_err());
+        assert!(input.data().is_err());
+    }
+
+    #[test]
+    fn test_execution_mode() {
+        assert_eq!("interpreter".parse(), Ok(ExecutionMode::Interpreter));
+        assert_eq!("jit".parse(), Ok(ExecutionMode::Jit));
+        assert!("aot".parse::<ExecutionMode>().is_err());
+    }
+
+    #[test]
+    fn test_run_program_rejects_non_program() {
+        let genesis_config_info = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config_info.genesis_config);
+        let input = InstructionInput {
+            accounts: vec![],
+            data: None,
+        };
+
+        let mint = ProgramSource::Deployed(genesis_config_info.mint_keypair.pubkey());
+        assert!(run_program(&bank, &mint, &input, ExecutionMode::Interpreter).is_err());
+        let missing = ProgramSource::Deployed(Pubkey::new_unique());
+        assert!(run_program(&bank, &missing, &input, ExecutionMode::Interpreter).is_err());
+        let garbage = tempfile::NamedTempFile::new().unwrap();
+        fs::write(garbage.path(), b"not an elf").unwrap();
+        let file = ProgramSource::File {
+            path: garbage.path().to_path_buf(),
+            program_id: None,
+        };
+        assert!(
+            run_program(&bank, &file, &input, ExecutionMode::Interpreter)
+                .unwrap_err()
+                .starts_with("Invalid program ELF")
+        );
+    }
+
+    #[test]
+    fn test_run_program() {
+        let genesis_config_info = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config_info.genesis_config);
+        let program = ProgramSource::File {
+            path: PathBuf::from("../programs/bpf_loader/test_elfs/noop_aligned.so"),
+            program_id: None,
+        };
+        let account = Pubkey::new_unique();
+        let input = InstructionInput {
+            accounts: vec![InstructionAccount {
+                pubkey: account.to_string(),
+                is_signer: false,
+                is_writable: true,
+            }],
+            data: Some(InstructionData::Hex("0102".to_string())),
+        };
+
+        let run = run_program(&bank, &program, &input, ExecutionMode::Interpreter).unwrap();
+        assert_eq!(run.result, Ok(()));
+        assert_eq!(run.return_value, Some(SUCCESS));
+        assert!(run.compute_units_consumed > 0);
+        assert!(run.compute_units_consumed <= run.compute_units_available);
+        assert!(run.account_changes.is_empty());
+        assert!(run
+            .logs
+            .contains(&"Program log: Instruction data".to_string()));
+    }
+}
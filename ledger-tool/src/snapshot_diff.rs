@@ -0,0 +1,304 @@
+/// Account level comparison of the banks of two snapshot archives
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_runtime::{bank::Bank, snapshot_utils};
+use solana_sdk::{account::Account, genesis_config::GenesisConfig, pubkey::Pubkey};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+use tempfile::TempDir;
+
+/// Rebuilds the bank of `archive` in a temporary directory under `work_dir`. The directory holds
+/// the bank's append vecs, so it must outlive the bank
+pub fn bank_from_snapshot_archive(
+    archive: &Path,
+    genesis_config: &GenesisConfig,
+    work_dir: &Path,
+) -> Result<(Bank, TempDir), String> {
+    let (_slot, _hash, compression) = archive
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .and_then(snapshot_utils::snapshot_hash_of)
+        .ok_or_else(|| format!("{} is not a snapshot archive", archive.display()))?;
+    let temp_dir = tempfile::Builder::new()
+        .prefix("snapshot-diff.")
+        .tempdir_in(work_dir)
+        .map_err(|err| {
+            format!(
+                "Unable to create a directory in {}: {}",
+                work_dir.display(),
+                err
+            )
+        })?;
+    let snapshot_path = temp_dir.path().join("snapshot");
+    let accounts_path = temp_dir.path().join("accounts");
+    for path in &[&snapshot_path, &accounts_path] {
+        fs::create_dir(path)
+            .map_err(|err| format!("Unable to create {}: {}", path.display(), err))?;
+    }
+
+    let bank = snapshot_utils::bank_from_archive(
+        &[accounts_path],
+        &[],
+        &snapshot_path,
+        archive,
+        compression,
+        genesis_config,
+        None,
+        Some(&solana_ledger::builtins::get(genesis_config.cluster_type)),
+        None,
+        None,
+    )
+    .map_err(|err| format!("Unable to load {}: {}", archive.display(), err))?;
+    Ok((bank, temp_dir))
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// Accounts only present in the second snapshot
+    pub added: Vec<(Pubkey, Account)>,
+    /// Accounts only present in the first snapshot
+    pub removed: Vec<(Pubkey, Account)>,
+    /// Accounts present in both snapshots with different contents, before and after
+    pub modified: Vec<(Pubkey, Account, Account)>,
+    /// Change in the lamports held by the accounts of each owner, for owners that changed
+    pub capitalization_deltas: BTreeMap<Pubkey, i128>,
+}
+
+impl SnapshotDiff {
+    pub fn new(first: &Bank, second: &Bank) -> Self {
+        let accounts = |bank: &Bank| {
+            bank.get_all_accounts_with_modified_slots()
+                .into_iter()
+                .map(|(pubkey, account, _slot)| (pubkey, account))
+                .collect()
+        };
+        Self::from_accounts(accounts(first), accounts(second))
+    }
+
+    fn from_accounts(first: Vec<(Pubkey, Account)>, second: Vec<(Pubkey, Account)>) -> Self {
+        let mut second: HashMap<_, _> = second.into_iter().collect();
+        let mut diff = Self::default();
+        for (pubkey, account) in first {
+            match second.remove(&pubkey) {
+                None => diff.removed.push((pubkey, account)),
+                Some(second_account) if second_account != account => {
+                    diff.modified.push((pubkey, account, second_account))
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added = second.into_iter().collect();
+
+        diff.added.sort_by(|a, b| a.0.cmp(&b.0));
+        diff.removed.sort_by(|a, b| a.0.cmp(&b.0));
+        diff.modified.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut deltas = BTreeMap::new();
+        let befores = diff
+            .removed
+            .iter()
+            .map(|(_, account)| account)
+            .chain(diff.modified.iter().map(|(_, before, _)| before));
+        for account in befores {
+            *deltas.entry(account.owner).or_insert(0) -= i128::from(account.lamports);
+        }
+        let afters = diff
+            .added
+            .iter()
+            .map(|(_, account)| account)
+            .chain(diff.modified.iter().map(|(_, _, after)| after));
+        for account in afters {
+            *deltas.entry(account.owner).or_insert(0) += i128::from(account.lamports);
+        }
+        diff.capitalization_deltas = deltas
+            .into_iter()
+            .filter(|(_, delta)| *delta != 0)
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    pub fn print(&self, dump_account_data: bool) {
+        println!(
+            "{} added, {} removed, {} modified accounts",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len()
+        );
+        for (pubkey, account) in &self.added {
+            println!("added {}: {}", pubkey, describe_account(account));
+        }
+        for (pubkey, account) in &self.removed {
+            println!("removed {}: {}", pubkey, describe_account(account));
+        }
+        for (pubkey, before, after) in &self.modified {
+            println!("modified {}: {}", pubkey, describe_changes(before, after));
+        }
+        if !self.capitalization_deltas.is_empty() {
+            println!("Capitalization change by owner:");
+            for (owner, delta) in &self.capitalization_deltas {
+                println!("  {}: {:+} lamports", owner, delta);
+            }
+        }
+        if dump_account_data {
+            for value in self.changed_accounts_json() {
+                println!("{}", serde_json::to_string_pretty(&value).unwrap());
+            }
+        }
+    }
+
+    /// The changed accounts, before and after, in the `account-decoder` JSON form
+    pub fn changed_accounts_json(&self) -> Vec<Value> {
+        let encode = |pubkey: &Pubkey, account: Option<&Account>| {
+            account.map(|account| {
+                UiAccount::encode(
+                    pubkey,
+                    account.clone(),
+                    UiAccountEncoding::JsonParsed,
+                    None,
+                    None,
+                )
+            })
+        };
+        let mut changes: Vec<_> = self
+            .added
+            .iter()
+            .map(|(pubkey, account)| (pubkey, None, Some(account)))
+            .chain(
+                self.removed
+                    .iter()
+                    .map(|(pubkey, account)| (pubkey, Some(account), None)),
+            )
+            .chain(
+                self.modified
+                    .iter()
+                    .map(|(pubkey, before, after)| (pubkey, Some(before), Some(after))),
+            )
+            .collect();
+        changes.sort_by(|a, b| a.0.cmp(b.0));
+        changes
+            .into_iter()
+            .map(|(pubkey, before, after)| {
+                json!({
+                    "pubkey": pubkey.to_string(),
+                    "before": encode(pubkey, before),
+                    "after": encode(pubkey, after),
+                })
+            })
+            .collect()
+    }
+}
+
+fn describe_account(account: &Account) -> String {
+    format!(
+        "lamports {}, owner {}, executable {}, data length {}",
+        account.lamports,
+        account.owner,
+        account.executable,
+        account.data.len()
+    )
+}
+
+fn describe_changes(before: &Account, after: &Account) -> String {
+    let mut changes = vec![];
+    if before.lamports != after.lamports {
+        changes.push(format!(
+            "lamports {} -> {}",
+            before.lamports, after.lamports
+        ));
+    }
+    if before.owner != after.owner {
+        changes.push(format!("owner {} -> {}", before.owner, after.owner));
+    }
+    if before.executable != after.executable {
+        changes.push(format!(
+            "executable {} -> {}",
+            before.executable, after.executable
+        ));
+    }
+    if before.rent_epoch != after.rent_epoch {
+        changes.push(format!(
+            "rent epoch {} -> {}",
+            before.rent_epoch, after.rent_epoch
+        ));
+    }
+    if before.data != after.data {
+        changes.push(format!(
+            "data changed, length {} -> {}",
+            before.data.len(),
+            after.data.len()
+        ));
+    }
+    changes.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::system_program;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let owner = Pubkey::new_unique();
+        let unchanged = (Pubkey::new_unique(), Account::new(1, 0, &owner));
+        let removed = (Pubkey::new_unique(), Account::new(2, 0, &owner));
+        let added = (
+            Pubkey::new_unique(),
+            Account::new(3, 0, &system_program::id()),
+        );
+        let modified = Pubkey::new_unique();
+        let before = Account::new(4, 1, &owner);
+        let after = Account::new(5, 2, &system_program::id());
+
+        let diff = SnapshotDiff::from_accounts(
+            vec![
+                unchanged.clone(),
+                removed.clone(),
+                (modified, before.clone()),
+            ],
+            vec![unchanged, added.clone(), (modified, after.clone())],
+        );
+        assert_eq!(diff.added, vec![added]);
+        assert_eq!(diff.removed, vec![removed]);
+        assert_eq!(
+            diff.modified,
+            vec![(modified, before.clone(), after.clone())]
+        );
+        assert_eq!(
+            diff.capitalization_deltas,
+            vec![(owner, -6), (system_program::id(), 8)]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            describe_changes(&before, &after),
+            format!(
+                "lamports 4 -> 5, owner {} -> {}, data changed, length 1 -> 2",
+                owner,
+                system_program::id()
+            )
+        );
+
+        let changed_accounts = diff.changed_accounts_json();
+        assert_eq!(changed_accounts.len(), 3);
+        let modified_json = changed_accounts
+            .iter()
+            .find(|value| value["pubkey"] == modified.to_string())
+            .unwrap();
+        assert_eq!(modified_json["before"]["lamports"], 4);
+        assert_eq!(modified_json["after"]["lamports"], 5);
+        let added_json = changed_accounts
+            .iter()
+            .find(|value| value["pubkey"] == diff.added[0].0.to_string())
+            .unwrap();
+        assert!(added_json["before"].is_null());
+
+        assert!(SnapshotDiff::from_accounts(vec![], vec![]).is_empty());
+    }
+}
@@ -0,0 +1,654 @@
+/// Replay of a slot range that reports what each transaction did to its accounts
+use crate::LedgerOutputMethod;
+use crossbeam_channel::unbounded;
+use serde_json::{json, Value};
+use solana_account_decoder::parse_account_data::parse_account_data;
+use solana_ledger::{
+    blockstore::Blockstore,
+    blockstore_processor::{self, TransactionStatusBatch},
+    entry::Entry,
+    leader_schedule_cache::LeaderScheduleCache,
+};
+use solana_runtime::{
+    bank::{Bank, InnerInstructionsList},
+    message_processor::ExecuteDetailsTimings,
+};
+use solana_sdk::{
+    account::Account,
+    clock::{Slot, MAX_PROCESSING_AGE},
+    pubkey::Pubkey,
+    transaction::{Result as TransactionResult, Transaction},
+};
+use solana_transaction_status::{InnerInstructions, UiInnerInstructions};
+use std::{
+    collections::HashSet,
+    io::{stdout, Write},
+    str::FromStr,
+    sync::Arc,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccountDataEncoding {
+    Hex,
+    Parsed,
+}
+
+impl FromStr for AccountDataEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "parsed" => Ok(Self::Parsed),
+            _ => Err(format!("Unsupported account data encoding: {}", s)),
+        }
+    }
+}
+
+/// Selects the transactions to report; an empty filter selects every transaction
+#[derive(Default)]
+pub struct TransactionFilter {
+    accounts: HashSet<Pubkey>,
+    programs: HashSet<Pubkey>,
+}
+
+impl TransactionFilter {
+    pub fn new(accounts: Vec<Pubkey>, programs: Vec<Pubkey>) -> Self {
+        Self {
+            accounts: accounts.into_iter().collect(),
+            programs: programs.into_iter().collect(),
+        }
+    }
+
+    // A transaction matches if it references one of the accounts, or invokes one of the
+    // programs either directly or through an inner instruction
+    pub(crate) fn matches(
+        &self,
+        transaction: &Transaction,
+        inner_instructions: &[InnerInstructions],
+    ) -> bool {
+        if self.accounts.is_empty() && self.programs.is_empty() {
+            return true;
+        }
+        let message = &transaction.message;
+        message
+            .account_keys
+            .iter()
+            .any(|pubkey| self.accounts.contains(pubkey))
+            || message
+                .instructions
+                .iter()
+                .chain(
+                    inner_instructions
+                        .iter()
+                        .flat_map(|inner_instructions| inner_instructions.instructions.iter()),
+                )
+                .any(|instruction| {
+                    message
+                        .account_keys
+                        .get(instruction.program_id_index as usize)
+                        .map(|program_id| self.programs.contains(program_id))
+                        .unwrap_or(false)
+                })
+    }
+}
+
+struct AccountDiff {
+    pubkey: Pubkey,
+    pre: Account,
+    post: Account,
+}
+
+impl AccountDiff {
+    fn encode_data(&self, account: &Account, encoding: AccountDataEncoding) -> Value {
+        if encoding == AccountDataEncoding::Parsed {
+            if let Ok(parsed_account) =
+                parse_account_data(&self.pubkey, &account.owner, &account.data, None)
+            {
+                return json!(parsed_account);
+            }
+        }
+        json!(hex::encode(&account.data))
+    }
+
+    fn to_json(&self, encoding: AccountDataEncoding) -> Value {
+        let mut diff = json!({ "pubkey": self.pubkey.to_string() });
+        if self.pre.lamports != self.post.lamports {
+            diff["lamports"] = json!({ "pre": self.pre.lamports, "post": self.post.lamports });
+        }
+        if self.pre.owner != self.post.owner {
+            diff["owner"] = json!({
+                "pre": self.pre.owner.to_string(),
+                "post": self.post.owner.to_string(),
+            });
+        }
+        if self.pre.data != self.post.data {
+            diff["data"] = json!({
+                "pre": self.encode_data(&self.pre, encoding),
+                "post": self.encode_data(&self.post, encoding),
+            });
+        }
+        diff
+    }
+
+    fn print(&self, encoding: AccountDataEncoding) {
+        println!("  Account {}:", self.pubkey);
+        if self.pre.lamports != self.post.lamports {
+            println!(
+                "    Lamports: {} -> {}",
+                self.pre.lamports, self.post.lamports
+            );
+        }
+        if self.pre.owner != self.post.owner {
+            println!("    Owner: {} -> {}", self.pre.owner, self.post.owner);
+        }
+        if self.pre.data != self.post.data {
+            println!("    Data (pre): {}", self.encode_data(&self.pre, encoding));
+            println!(
+                "    Data (post): {}",
+                self.encode_data(&self.post, encoding)
+            );
+        }
+    }
+}
+
+struct ReplayedTransaction {
+    slot: Slot,
+    transaction: Transaction,
+    status: TransactionResult<()>,
+    log_messages: Vec<String>,
+    inner_instructions: Vec<InnerInstructions>,
+    account_diffs: Vec<AccountDiff>,
+}
+
+impl ReplayedTransaction {
+    fn new(
+        slot: Slot,
+        transaction: Transaction,
+        batch: TransactionStatusBatch,
+        pre_accounts: Vec<Option<Account>>,
+        post_accounts: Vec<Option<Account>>,
+    ) -> Self {
+        let TransactionStatusBatch {
+            mut statuses,
+            mut inner_instructions,
+            mut transaction_logs,
+            ..
+        } = batch;
+        let account_diffs = transaction
+            .message
+            .account_keys
+            .iter()
+            .zip(pre_accounts.into_iter().zip(post_accounts))
+            .filter(|(_, (pre, post))| pre != post)
+            .map(|(pubkey, (pre, post))| AccountDiff {
+                pubkey: *pubkey,
+                pre: pre.unwrap_or_default(),
+                post: post.unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            slot,
+            transaction,
+            status: statuses.pop().map(|(status, _)| status).unwrap_or(Ok(())),
+            log_messages: transaction_logs.pop().unwrap_or_default(),
+            inner_instructions: inner_instructions
+                .pop()
+                .flatten()
+                .map(indexed_inner_instructions)
+                .unwrap_or_default(),
+            account_diffs,
+        }
+    }
+
+    fn output(&self, method: &LedgerOutputMethod, encoding: AccountDataEncoding) {
+        match method {
+            LedgerOutputMethod::Print => self.print(encoding),
+            LedgerOutputMethod::Json => {
+                serde_json::to_writer(stdout(), &self.to_json(encoding))
+                    .expect("serialize transaction");
+                stdout().write_all(b",\n").expect("newline");
+            }
+        }
+    }
+
+    fn to_json(&self, encoding: AccountDataEncoding) -> Value {
+        let inner_instructions: Vec<UiInnerInstructions> = self
+            .inner_instructions
+            .iter()
+            .cloned()
+            .map(UiInnerInstructions::from)
+            .collect();
+        json!({
+            "slot": self.slot,
+            "signature": self.transaction.signatures[0].to_string(),
+            "status": self.status,
+            "logMessages": self.log_messages,
+            "innerInstructions": inner_instructions,
+            "accounts": self
+                .account_diffs
+                .iter()
+                .map(|account_diff| account_diff.to_json(encoding))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, encoding: AccountDataEncoding) {
+        let message = &self.transaction.message;
+        println!(
+            "Slot {} transaction {}",
+            self.slot, self.transaction.signatures[0]
+        );
+        println!("  Status: {:?}", self.status);
+        if !self.log_messages.is_empty() {
+            println!("  Log Messages:");
+            for log_message in &self.log_messages {
+                println!("    {}", log_message);
+            }
+        }
+        if !self.inner_instructions.is_empty() {
+            println!("  Inner Instructions:");
+            for inner_instructions in &self.inner_instructions {
+                for (index, instruction) in inner_instructions.instructions.iter().enumerate() {
+                    let account_key = |index: u8| {
+                        message
+                            .account_keys
+                            .get(index as usize)
+                            .map(ToString::to_string)
+                            .unwrap_or_else(|| format!("<invalid index {}>", index))
+                    };
+                    println!(
+                        "    {}.{}: Program {}, accounts [{}], data {}",
+                        inner_instructions.index,
+                        index,
+                        account_key(instruction.program_id_index),
+                        instruction
+                            .accounts
+                            .iter()
+                            .map(|index| account_key(*index))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        bs58::encode(&instruction.data).into_string(),
+                    );
+                }
+            }
+        }
+        for account_diff in &self.account_diffs {
+            account_diff.print(encoding);
+        }
+    }
+}
+
+// Same shape as the inner instructions recorded in the transaction status column
+fn indexed_inner_instructions(inner_instructions: InnerInstructionsList) -> Vec<InnerInstructions> {
+    inner_instructions
+        .into_iter()
+        .enumerate()
+        .map(|(index, instructions)| InnerInstructions {
+            index: index as u8,
+            instructions,
+        })
+        .filter(|inner_instructions| !inner_instructions.instructions.is_empty())
+        .collect()
+}
+
+// The child of `slot` to replay next, preferring a rooted one
+fn next_replay_slot(blockstore: &Blockstore, slot: Slot) -> Result<Option<Slot>, String> {
+    let slot_meta = blockstore
+        .meta(slot)
+        .map_err(|err| format!("Failed to read slot meta for slot {}: {}", slot, err))?
+        .ok_or_else(|| format!("Slot {} is not available in the blockstore", slot))?;
+    let children: Vec<Slot> = slot_meta
+        .next_slots
+        .into_iter()
+        .filter(|slot| blockstore.is_full(*slot) && !blockstore.is_dead(*slot))
+        .collect();
+    Ok(children
+        .iter()
+        .find(|slot| blockstore.is_root(**slot))
+        .or_else(|| children.iter().min())
+        .copied())
+}
+
+// Replays the entries of `bank`, one transaction at a time so the accounts each transaction
+// references can be captured before and after it executes
+fn replay_slot(
+    bank: &Arc<Bank>,
+    entries: Vec<Entry>,
+    mut report: impl FnMut(ReplayedTransaction),
+) -> Result<(), String> {
+    let (transaction_status_sender, transaction_status_receiver) = unbounded();
+    for entry in entries {
+        if entry.is_tick() {
+            blockstore_processor::process_entries(bank, &[entry], false, None, None)
+                .map_err(|err| format!("Failed to replay slot {}: {}", bank.slot(), err))?;
+            continue;
+        }
+        for transaction in entry.transactions {
+            let load_accounts = || -> Vec<Option<Account>> {
+                transaction
+                    .message
+                    .account_keys
+                    .iter()
+                    .map(|pubkey| bank.get_account(pubkey))
+                    .collect()
+            };
+            let pre_accounts = load_accounts();
+            let transaction_entry = Entry {
+                num_hashes: entry.num_hashes,
+                hash: entry.hash,
+                transactions: vec![transaction.clone()],
+            };
+            blockstore_processor::process_entries(
+                bank,
+                &[transaction_entry],
+                false,
+                Some(transaction_status_sender.clone()),
+                None,
+            )
+            .map_err(|err| {
+                format!(
+                    "Failed to replay transaction {} in slot {}: {}",
+                    transaction.signatures[0],
+                    bank.slot(),
+                    err
+                )
+            })?;
+            let post_accounts = load_accounts();
+            let batch = transaction_status_receiver
+                .try_recv()
+                .map_err(|err| format!("Transaction status not recorded: {}", err))?;
+            report(ReplayedTransaction::new(
+                bank.slot(),
+                transaction,
+                batch,
+                pre_accounts,
+                post_accounts,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Replays the entries of `bank` one entry at a time, and returns the execution time of the
+/// instructions of each program
+pub(crate) fn replay_slot_execution(
+    bank: &Arc<Bank>,
+    entries: &[Entry],
+) -> Result<ExecuteDetailsTimings, String> {
+    let mut execute_timings = ExecuteDetailsTimings::default();
+    for entry in entries {
+        if entry.is_tick() {
+            blockstore_processor::process_entries(bank, &[entry.clone()], false, None, None)
+                .map_err(|err| format!("Failed to replay slot {}: {}", bank.slot(), err))?;
+            continue;
+        }
+        let batch = bank.prepare_batch(&entry.transactions, None);
+        let (
+            mut loaded_accounts,
+            executed,
+            _inner_instructions,
+            _transaction_logs,
+            _retryable_transactions,
+            tx_count,
+            signature_count,
+        ) = bank.load_and_execute_transactions(
+            &batch,
+            MAX_PROCESSING_AGE,
+            false,
+            false,
+            &mut execute_timings,
+        );
+        bank.commit_transactions(
+            batch.transactions(),
+            batch.iteration_order(),
+            &mut loaded_accounts,
+            &executed,
+            tx_count,
+            signature_count,
+        );
+    }
+    Ok(execute_timings)
+}
+
+/// Replays the slots after `bank` up to `ending_slot` with `replay_slot`, following the rooted
+/// fork where there is one. Returns the last bank replayed.
+pub(crate) fn replay_slots(
+    bank: Arc<Bank>,
+    leader_schedule_cache: &LeaderScheduleCache,
+    blockstore: &Blockstore,
+    ending_slot: Slot,
+    mut replay_slot: impl FnMut(&Arc<Bank>, Vec<Entry>) -> Result<(), String>,
+) -> Result<Arc<Bank>, String> {
+    let mut bank = bank;
+    while let Some(slot) = next_replay_slot(blockstore, bank.slot())? {
+        if slot > ending_slot {
+            break;
+        }
+        let collector_id = leader_schedule_cache
+            .slot_leader_at(slot, Some(&bank))
+            .ok_or_else(|| format!("Unable to determine the leader of slot {}", slot))?;
+        let child_bank = Arc::new(Bank::new_from_parent(&bank, &collector_id, slot));
+        let entries = blockstore
+            .get_slot_entries(slot, 0)
+            .map_err(|err| format!("Failed to load entries for slot {}: {}", slot, err))?;
+        replay_slot(&child_bank, entries)?;
+        child_bank.freeze();
+        child_bank.squash();
+        bank = child_bank;
+    }
+    Ok(bank)
+}
+
+/// Replays the slots after `bank` up to `ending_slot`, reporting the transactions selected by
+/// `filter` from `starting_slot` on
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn replay_transactions(
+    bank: Arc<Bank>,
+    leader_schedule_cache: &LeaderScheduleCache,
+    blockstore: &Blockstore,
+    starting_slot: Slot,
+    ending_slot: Slot,
+    filter: &TransactionFilter,
+    method: &LedgerOutputMethod,
+    encoding: AccountDataEncoding,
+) -> Result<(), String> {
+    if bank.slot() >= starting_slot {
+        eprintln!(
+            "Warning: replay starts after slot {}, the snapshot is newer than the starting slot",
+            bank.slot()
+        );
+    }
+    let bank = replay_slots(
+        bank,
+        leader_schedule_cache,
+        blockstore,
+        ending_slot,
+        |bank, entries| {
+            let slot = bank.slot();
+            replay_slot(bank, entries, |replayed_transaction| {
+                if slot >= starting_slot
+                    && filter.matches(
+                        &replayed_transaction.transaction,
+                        &replayed_transaction.inner_instructions,
+                    )
+                {
+                    replayed_transaction.output(method, encoding);
+                }
+            })
+        },
+    )?;
+    if bank.slot() < ending_slot {
+        eprintln!(
+            "Warning: replay stopped at slot {}, no later slots are available",
+            bank.slot()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::{
+        entry::{create_ticks, next_entry},
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+    };
+    use solana_sdk::{
+        hash::Hash,
+        instruction::CompiledInstruction,
+        signature::{Keypair, Signer},
+        system_program, system_transaction,
+    };
+
+    #[test]
+    fn test_replay_slot() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
+
+        let to = Pubkey::new_unique();
+        let transfer = system_transaction::transfer(&mint_keypair, &to, 42, bank0.last_blockhash());
+        let mut entries = vec![next_entry(
+            &bank0.last_blockhash(),
+            1,
+            vec![transfer.clone()],
+        )];
+        entries.extend(create_ticks(
+            genesis_config.ticks_per_slot,
+            0,
+            entries[0].hash,
+        ));
+
+        let mut replayed_transactions = vec![];
+        replay_slot(&bank1, entries, |replayed_transaction| {
+            replayed_transactions.push(replayed_transaction)
+        })
+        .unwrap();
+        assert_eq!(bank1.get_balance(&to), 42);
+
+        assert_eq!(replayed_transactions.len(), 1);
+        let replayed_transaction = &replayed_transactions[0];
+        assert_eq!(replayed_transaction.slot, 1);
+        assert_eq!(replayed_transaction.transaction, transfer);
+        assert_eq!(replayed_transaction.status, Ok(()));
+
+        // Only the two transfer accounts change, the system program does not
+        let account_diffs: Vec<_> = replayed_transaction
+            .account_diffs
+            .iter()
+            .map(|account_diff| {
+                (
+                    account_diff.pubkey,
+                    account_diff.pre.lamports,
+                    account_diff.post.lamports,
+                )
+            })
+            .collect();
+        assert_eq!(
+            account_diffs,
+            vec![(mint_keypair.pubkey(), 10_000, 10_000 - 42), (to, 0, 42)]
+        );
+    }
+
+    #[test]
+    fn test_replay_slot_execution() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
+
+        let to = Pubkey::new_unique();
+        let transfer = system_transaction::transfer(&mint_keypair, &to, 42, bank0.last_blockhash());
+        let mut entries = vec![next_entry(&bank0.last_blockhash(), 1, vec![transfer])];
+        entries.extend(create_ticks(
+            genesis_config.ticks_per_slot,
+            0,
+            entries[0].hash,
+        ));
+
+        let execute_timings = replay_slot_execution(&bank1, &entries).unwrap();
+        assert_eq!(bank1.get_balance(&to), 42);
+        assert_eq!(bank1.tick_height(), genesis_config.ticks_per_slot);
+        assert_eq!(execute_timings.per_program_timings.len(), 1);
+        assert_eq!(
+            execute_timings.per_program_timings[&system_program::id()].count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_transaction_filter() {
+        let from = Keypair::new();
+        let to = Pubkey::new_unique();
+        let transaction = system_transaction::transfer(&from, &to, 1, Hash::default());
+
+        assert!(TransactionFilter::default().matches(&transaction, &[]));
+        assert!(TransactionFilter::new(vec![to], vec![]).matches(&transaction, &[]));
+        assert!(
+            TransactionFilter::new(vec![], vec![system_program::id()]).matches(&transaction, &[])
+        );
+
+        let other_program = Pubkey::new_unique();
+        let filter = TransactionFilter::new(vec![Pubkey::new_unique()], vec![other_program]);
+        assert!(!filter.matches(&transaction, &[]));
+
+        // Programs invoked through inner instructions match too, as long as the transaction
+        // references them
+        let mut transaction = transaction;
+        transaction.message.account_keys.push(other_program);
+        let inner_instructions = vec![InnerInstructions {
+            index: 0,
+            instructions: vec![CompiledInstruction::new(
+                (transaction.message.account_keys.len() - 1) as u8,
+                &(),
+                vec![],
+            )],
+        }];
+        assert!(filter.matches(&transaction, &inner_instructions));
+    }
+
+    #[test]
+    fn test_indexed_inner_instructions() {
+        let instruction = CompiledInstruction::new(0, &(), vec![]);
+        let inner_instructions =
+            indexed_inner_instructions(vec![vec![], vec![instruction.clone()], vec![]]);
+        assert_eq!(inner_instructions.len(), 1);
+        assert_eq!(inner_instructions[0].index, 1);
+        assert_eq!(inner_instructions[0].instructions, vec![instruction]);
+    }
+
+    #[test]
+    fn test_account_diff_to_json() {
+        let pre = Account::new(10, 4, &system_program::id());
+        let mut post = pre.clone();
+        post.lamports = 7;
+        post.data = vec![1, 2, 3, 4];
+        let account_diff = AccountDiff {
+            pubkey: Pubkey::new_unique(),
+            pre,
+            post,
+        };
+
+        let diff = account_diff.to_json(AccountDataEncoding::Hex);
+        assert_eq!(diff["lamports"], json!({ "pre": 10, "post": 7 }));
+        assert_eq!(diff["owner"], Value::Null);
+        assert_eq!(
+            diff["data"],
+            json!({ "pre": "00000000", "post": "01020304" })
+        );
+
+        // Data that no parser understands falls back to hex
+        assert_eq!(
+            account_diff.to_json(AccountDataEncoding::Parsed)["data"],
+            diff["data"]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+/// Offline search of the transactions of a slot range, using the statuses stored in the ledger
+use crate::transaction_replay::TransactionFilter;
+use rayon::prelude::*;
+use solana_ledger::blockstore::Blockstore;
+use solana_sdk::clock::Slot;
+use solana_transaction_status::{
+    ConfirmedTransaction, TransactionWithStatusMeta, UiTransactionEncoding,
+};
+use std::{
+    io::{stdout, Write},
+    str::FromStr,
+};
+
+// Slots are searched in parallel this many at a time, so that matches print in slot order
+// without holding the whole range in memory
+const SEARCH_BATCH_SLOTS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransactionStatusFilter {
+    Success,
+    Failure,
+}
+
+impl FromStr for TransactionStatusFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(Self::Success),
+            "failure" => Ok(Self::Failure),
+            _ => Err(format!("Unsupported transaction status: {}", s)),
+        }
+    }
+}
+
+/// Selects the transactions to print. The account and program filter is combined with the
+/// status and log filters; empty filters select every transaction
+#[derive(Default)]
+pub struct TransactionSearchFilter {
+    transaction_filter: TransactionFilter,
+    status: Option<TransactionStatusFilter>,
+    log_substrings: Vec<String>,
+}
+
+impl TransactionSearchFilter {
+    pub fn new(
+        transaction_filter: TransactionFilter,
+        status: Option<TransactionStatusFilter>,
+        log_substrings: Vec<String>,
+    ) -> Self {
+        Self {
+            transaction_filter,
+            status,
+            log_substrings,
+        }
+    }
+
+    // Transactions without a stored status only match when no filter needs their status
+    fn matches(&self, transaction: &TransactionWithStatusMeta) -> bool {
+        let meta = transaction.meta.as_ref();
+        let inner_instructions = meta
+            .and_then(|meta| meta.inner_instructions.as_deref())
+            .unwrap_or_default();
+        if !self
+            .transaction_filter
+            .matches(&transaction.transaction, inner_instructions)
+        {
+            return false;
+        }
+        if let Some(status) = self.status {
+            let is_success = match meta {
+                Some(meta) => meta.status.is_ok(),
+                None => return false,
+            };
+            if is_success != (status == TransactionStatusFilter::Success) {
+                return false;
+            }
+        }
+        self.log_substrings.is_empty()
+            || meta
+                .and_then(|meta| meta.log_messages.as_ref())
+                .map(|log_messages| {
+                    log_messages.iter().any(|log_message| {
+                        self.log_substrings
+                            .iter()
+                            .any(|log_substring| log_message.contains(log_substring))
+                    })
+                })
+                .unwrap_or(false)
+    }
+}
+
+fn search_slot(
+    blockstore: &Blockstore,
+    slot: Slot,
+    filter: &TransactionSearchFilter,
+) -> Result<Vec<ConfirmedTransaction>, String> {
+    let entries = match blockstore.get_slot_entries(slot, 0) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!(
+                "Warning: skipping slot {}, failed to load entries: {}",
+                slot, err
+            );
+            return Ok(vec![]);
+        }
+    };
+    let mut matches = vec![];
+    for transaction in entries.into_iter().flat_map(|entry| entry.transactions) {
+        let meta = blockstore
+            .read_transaction_status((transaction.signatures[0], slot))
+            .map_err(|err| {
+                format!(
+                    "Failed to read the status of transaction {} in slot {}: {:?}",
+                    transaction.signatures[0], slot, err
+                )
+            })?;
+        let transaction = TransactionWithStatusMeta { transaction, meta };
+        if filter.matches(&transaction) {
+            matches.push(ConfirmedTransaction { slot, transaction });
+        }
+    }
+    Ok(matches)
+}
+
+/// Prints the matching transactions of the slots in [starting_slot, ending_slot] as one
+/// `EncodedConfirmedTransaction` JSON object per line. Dead slots are skipped
+pub fn search_transactions(
+    blockstore: &Blockstore,
+    starting_slot: Slot,
+    ending_slot: Slot,
+    only_rooted: bool,
+    filter: &TransactionSearchFilter,
+    encoding: UiTransactionEncoding,
+) -> Result<(), String> {
+    let slots: Vec<Slot> = blockstore
+        .slot_meta_iterator(starting_slot)
+        .map_err(|err| format!("Failed to load slot metas: {:?}", err))?
+        .map(|(slot, _meta)| slot)
+        .take_while(|slot| *slot <= ending_slot)
+        .filter(|slot| !blockstore.is_dead(*slot) && (!only_rooted || blockstore.is_root(*slot)))
+        .collect();
+
+    let stdout = stdout();
+    let mut stdout = stdout.lock();
+    for batch in slots.chunks(SEARCH_BATCH_SLOTS) {
+        let matches = batch
+            .par_iter()
+            .map(|slot| search_slot(blockstore, *slot, filter))
+            .collect::<Result<Vec<_>, _>>()?;
+        for confirmed_transaction in matches.into_iter().flatten() {
+            let json = serde_json::to_string(&confirmed_transaction.encode(encoding))
+                .map_err(|err| format!("Failed to serialize a transaction: {}", err))?;
+            writeln!(stdout, "{}", json).map_err(|err| format!("{}", err))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::{
+        blockstore::entries_to_test_shreds, entry::next_entry, get_tmp_ledger_path,
+    };
+    use solana_sdk::{
+        hash::Hash,
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::Keypair,
+        system_program, system_transaction,
+        transaction::{Transaction, TransactionError},
+    };
+    use solana_transaction_status::TransactionStatusMeta;
+
+    #[test]
+    fn test_search_slot() {
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path).unwrap();
+            let from = Keypair::new();
+            let to = Pubkey::new_unique();
+            let succeeded = system_transaction::transfer(&from, &to, 1, Hash::default());
+            let failed = system_transaction::transfer(&from, &to, 2, Hash::default());
+            let unknown = system_transaction::transfer(&from, &to, 3, Hash::default());
+            let entries = vec![next_entry(
+                &Hash::default(),
+                1,
+                vec![succeeded.clone(), failed.clone(), unknown.clone()],
+            )];
+            let shreds = entries_to_test_shreds(entries, 1, 0, true, 0);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+
+            let write_status = |transaction: &Transaction, status, log_message: &str| {
+                let meta = TransactionStatusMeta {
+                    status,
+                    log_messages: Some(vec![log_message.to_string()]),
+                    ..TransactionStatusMeta::default()
+                };
+                blockstore
+                    .write_transaction_status(1, transaction.signatures[0], vec![], vec![], &meta)
+                    .unwrap();
+            };
+            write_status(&succeeded, Ok(()), "Program log: transfer done");
+            write_status(
+                &failed,
+                Err(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(1),
+                )),
+                "Program failed to complete",
+            );
+
+            let signatures = |filter: &TransactionSearchFilter| -> Vec<_> {
+                search_slot(&blockstore, 1, filter)
+                    .unwrap()
+                    .into_iter()
+                    .map(|confirmed_transaction| {
+                        assert_eq!(confirmed_transaction.slot, 1);
+                        confirmed_transaction.transaction.transaction.signatures[0]
+                    })
+                    .collect()
+            };
+            assert_eq!(
+                signatures(&TransactionSearchFilter::default()),
+                vec![
+                    succeeded.signatures[0],
+                    failed.signatures[0],
+                    unknown.signatures[0]
+                ]
+            );
+            assert_eq!(
+                signatures(&TransactionSearchFilter::new(
+                    TransactionFilter::new(vec![], vec![system_program::id()]),
+                    Some(TransactionStatusFilter::Success),
+                    vec![],
+                )),
+                vec![succeeded.signatures[0]]
+            );
+            assert_eq!(
+                signatures(&TransactionSearchFilter::new(
+                    TransactionFilter::default(),
+                    Some(TransactionStatusFilter::Failure),
+                    vec![],
+                )),
+                vec![failed.signatures[0]]
+            );
+            assert_eq!(
+                signatures(&TransactionSearchFilter::new(
+                    TransactionFilter::default(),
+                    None,
+                    vec!["nothing".to_string(), "transfer".to_string()],
+                )),
+                vec![succeeded.signatures[0]]
+            );
+            assert!(signatures(&TransactionSearchFilter::new(
+                TransactionFilter::new(vec![Pubkey::new_unique()], vec![]),
+                None,
+                vec![],
+            ))
+            .is_empty());
+
+            // Slots without entries have nothing to match
+            assert!(
+                search_slot(&blockstore, 2, &TransactionSearchFilter::default())
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+}
@@ -46,4 +46,89 @@ fn nominal() {
     let output = run_ledger_tool(&["-l", &ledger_path, "print", "-vvv"]);
     assert!(output.status.success());
     assert_eq!(count_newlines(&output.stdout), ticks + meta_lines);
+
+    // Replaying past the end of the ledger reports no transactions
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "replay-transactions",
+        "--starting-slot",
+        "1",
+    ]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // A ledger of ticks has no transactions to find
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "transactions",
+        "--status",
+        "success",
+        "--encoding",
+        "jsonParsed",
+    ]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Bank hash details of the same ledger do not differ
+    let details_file = format!("{}/bank-hash-details.json", ledger_path);
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "bank-hash-details",
+        "--output-file",
+        &details_file,
+    ]);
+    assert!(output.status.success());
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "bank-hash-diff",
+        &details_file,
+        &details_file,
+    ]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"No differences\n");
+
+    // An exported ledger imports into an identical one, but not on top of itself
+    let archive = format!("{}/ledger.archive", ledger_path);
+    let imported_ledger_path = format!("{}/imported", ledger_path);
+    let output = run_ledger_tool(&["-l", &ledger_path, "export", &archive]);
+    assert!(output.status.success());
+    let output = run_ledger_tool(&["-l", &imported_ledger_path, "import", &archive]);
+    assert!(output.status.success());
+    let output = run_ledger_tool(&["-l", &imported_ledger_path, "import", &archive]);
+    assert!(!output.status.success());
+    let print = |ledger_path: &str| run_ledger_tool(&["-l", ledger_path, "print", "-vvv"]).stdout;
+    assert_eq!(print(&imported_ledger_path), print(&ledger_path));
+
+    // A snapshot does not differ from itself, but does from one of a later slot
+    let snapshot_dir = format!("{}/snapshot-diff", ledger_path);
+    let create_snapshot = |warp_slot: &str| {
+        let output = run_ledger_tool(&[
+            "-l",
+            &ledger_path,
+            "create-snapshot",
+            "0",
+            &snapshot_dir,
+            "--warp-slot",
+            warp_slot,
+        ]);
+        assert!(output.status.success());
+        std::fs::read_dir(&snapshot_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_str().unwrap().to_string())
+            .find(|path| path.contains(&format!("snapshot-{}-", warp_slot)))
+            .unwrap()
+    };
+    let first = create_snapshot("1");
+    let second = create_snapshot("2");
+    let output = run_ledger_tool(&["-l", &ledger_path, "snapshot-diff", &first, &first]);
+    assert!(output.status.success());
+    assert!(output.stdout.ends_with(b"No differences\n"));
+    let output = run_ledger_tool(&["-l", &ledger_path, "snapshot-diff", &first, &second]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("modified SysvarC1ock11111111111111111111111111111111"));
 }
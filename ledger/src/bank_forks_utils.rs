@@ -67,6 +67,8 @@ pub fn load(
                     genesis_config,
                     process_options.debug_keys.clone(),
                     Some(&crate::builtins::get(genesis_config.cluster_type)),
+                    process_options.accounts_update_notifier.clone(),
+                    process_options.accounts_index_config.clone(),
                 )
                 .expect("Load from snapshot failed");
 
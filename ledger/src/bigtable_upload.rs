@@ -21,7 +21,7 @@ const BLOCK_READ_AHEAD_DEPTH: usize = NUM_BLOCKS_TO_UPLOAD_IN_PARALLEL * 2;
 
 pub async fn upload_confirmed_blocks(
     blockstore: Arc<Blockstore>,
-    bigtable: solana_storage_bigtable::LedgerStorage,
+    bigtable: Arc<dyn solana_storage_bigtable::LedgerStorage>,
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
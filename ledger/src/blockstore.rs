@@ -60,6 +60,7 @@ use std::{
 use thiserror::Error;
 use trees::{Tree, TreeWalk};
 
+pub mod blockstore_archive;
 pub mod blockstore_purge;
 
 pub const BLOCKSTORE_DIRECTORY: &str = "rocksdb";
@@ -134,10 +135,12 @@ pub struct Blockstore {
     transaction_status_cf: LedgerColumn<cf::TransactionStatus>,
     address_signatures_cf: LedgerColumn<cf::AddressSignatures>,
     transaction_status_index_cf: LedgerColumn<cf::TransactionStatusIndex>,
+    blockhash_slot_cf: LedgerColumn<cf::BlockhashSlot>,
     active_transaction_status_index: RwLock<u64>,
     rewards_cf: LedgerColumn<cf::Rewards>,
     blocktime_cf: LedgerColumn<cf::Blocktime>,
     perf_samples_cf: LedgerColumn<cf::PerfSamples>,
+    bank_hash_cf: LedgerColumn<cf::BankHash>,
     last_root: Arc<RwLock<Slot>>,
     insert_shreds_lock: Arc<Mutex<()>>,
     pub new_shreds_signals: Vec<SyncSender<bool>>,
@@ -294,9 +297,11 @@ impl Blockstore {
         let transaction_status_cf = db.column();
         let address_signatures_cf = db.column();
         let transaction_status_index_cf = db.column();
+        let blockhash_slot_cf = db.column();
         let rewards_cf = db.column();
         let blocktime_cf = db.column();
         let perf_samples_cf = db.column();
+        let bank_hash_cf = db.column();
 
         let db = Arc::new(db);
 
@@ -340,9 +345,11 @@ impl Blockstore {
             address_signatures_cf,
             transaction_status_index_cf,
             active_transaction_status_index: RwLock::new(active_transaction_status_index),
+            blockhash_slot_cf,
             rewards_cf,
             blocktime_cf,
             perf_samples_cf,
+            bank_hash_cf,
             new_shreds_signals: vec![],
             completed_slots_senders: vec![],
             insert_shreds_lock: Arc::new(Mutex::new(())),
@@ -463,10 +470,13 @@ impl Blockstore {
             .unwrap_or(0)
     }
 
+    /// Iterates over the slot metas from `slot` on. Slots that expired with
+    /// `set_max_expired_slot` are skipped, even before compaction drops them
     pub fn slot_meta_iterator<'a>(
         &'a self,
         slot: Slot,
     ) -> Result<impl Iterator<Item = (Slot, SlotMeta)> + 'a> {
+        let slot = cmp::max(slot, self.db.oldest_slot());
         let meta_iter = self
             .db
             .iter::<cf::SlotMeta>(IteratorMode::From(slot, IteratorDirection::Forward))?;
@@ -1616,6 +1626,42 @@ impl Blockstore {
         self.blocktime_cf.put(slot, &timestamp)
     }
 
+    /// Indexes the blockhash of a rooted slot, which is the hash of its last entry. Stored under
+    /// the active transaction status primary index, so it is purged with the transaction statuses
+    pub fn cache_blockhash(&self, slot: Slot, blockhash: &Hash) -> Result<()> {
+        if !self.is_root(slot) {
+            return Err(BlockstoreError::SlotNotRooted);
+        }
+        let mut w_active_transaction_status_index =
+            self.active_transaction_status_index.write().unwrap();
+        let primary_index = self.get_primary_index(slot, &mut w_active_transaction_status_index)?;
+        self.blockhash_slot_cf
+            .put((primary_index, *blockhash, slot), &true)
+    }
+
+    /// Returns the rooted slot that produced `blockhash`, if it was indexed and not yet purged
+    pub fn get_blockhash_slot(&self, blockhash: &Hash) -> Result<Option<Slot>> {
+        datapoint_info!(
+            "blockstore-rpc-api",
+            ("method", "get_blockhash_slot".to_string(), String)
+        );
+        for primary_index in 0..=1 {
+            let index_iterator = self.blockhash_slot_cf.iter(IteratorMode::From(
+                (primary_index, *blockhash, 0),
+                IteratorDirection::Forward,
+            ))?;
+            for ((i, hash, slot), _) in index_iterator {
+                if i != primary_index || hash != *blockhash {
+                    break;
+                }
+                if slot >= self.db.oldest_slot() && self.is_root(slot) {
+                    return Ok(Some(slot));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     // DEPRECATED as of feature_set::timestamp_correction
     pub fn cache_block_time_from_slot_entries(
         &self,
@@ -1887,7 +1933,7 @@ impl Blockstore {
                 if i != transaction_status_cf_primary_index || sig != signature {
                     break;
                 }
-                if self.is_root(slot) {
+                if slot >= self.db.oldest_slot() && self.is_root(slot) {
                     let status: TransactionStatusMeta = deserialize(&data)?;
                     return Ok((Some((slot, status)), counter));
                 }
@@ -2325,6 +2371,89 @@ impl Blockstore {
         self.perf_samples_cf.put(index, perf_sample)
     }
 
+    /// Archives the accounts modified in rooted `slot`. Versions are keyed by the transaction
+    /// status primary index, so they are purged along with the transaction statuses
+    pub fn write_account_history(&self, slot: Slot, accounts: &[(Pubkey, Account)]) -> Result<()> {
+        // Gate writes to the transaction_status_index_cf, as write_transaction_status does
+        let mut w_active_transaction_status_index =
+            self.active_transaction_status_index.write().unwrap();
+        let primary_index = self.get_primary_index(slot, &mut w_active_transaction_status_index)?;
+        let mut write_batch = self.db.batch()?;
+        for (pubkey, account) in accounts {
+            write_batch.put::<cf::AccountHistory>((primary_index, *pubkey, slot), account)?;
+        }
+        write_batch.put::<cf::AccountHistoryRoots>(slot, &true)?;
+        self.db.write(write_batch)
+    }
+
+    /// Returns the earliest root whose modified accounts have been archived and not purged
+    pub fn get_first_account_history_root(&self) -> Result<Option<Slot>> {
+        Ok(self
+            .db
+            .iter::<cf::AccountHistoryRoots>(IteratorMode::From(
+                self.db.oldest_slot(),
+                IteratorDirection::Forward,
+            ))?
+            .next()
+            .map(|(slot, _)| slot))
+    }
+
+    /// Returns the latest root whose modified accounts have been archived
+    pub fn get_last_account_history_root(&self) -> Result<Option<Slot>> {
+        Ok(self
+            .db
+            .iter::<cf::AccountHistoryRoots>(IteratorMode::End)?
+            .next()
+            .map(|(slot, _)| slot)
+            .filter(|slot| *slot >= self.db.oldest_slot()))
+    }
+
+    /// Returns the most recent archived version of `pubkey` at or before `slot`, along with the
+    /// slot it was stored in
+    pub fn get_account_history(
+        &self,
+        pubkey: &Pubkey,
+        slot: Slot,
+    ) -> Result<Option<(Slot, Account)>> {
+        let mut account_history = None;
+        for primary_index in 0..=1 {
+            let mut iterator = self.db.iter::<cf::AccountHistory>(IteratorMode::From(
+                (primary_index, *pubkey, slot),
+                IteratorDirection::Reverse,
+            ))?;
+            match iterator.next() {
+                Some(((i, key, stored_slot), data))
+                    if i == primary_index
+                        && key == *pubkey
+                        && stored_slot >= self.db.oldest_slot()
+                        && account_history
+                            .as_ref()
+                            .map(|(newest_slot, _)| stored_slot > *newest_slot)
+                            .unwrap_or(true) =>
+                {
+                    account_history = Some((stored_slot, deserialize(&data)?));
+                }
+                _ => {}
+            }
+        }
+        Ok(account_history)
+    }
+
+    /// Returns true if a version of `pubkey` newer than `slot` has been archived
+    pub fn has_account_history_after(&self, pubkey: &Pubkey, slot: Slot) -> Result<bool> {
+        for primary_index in 0..=1 {
+            let mut iterator = self.db.iter::<cf::AccountHistory>(IteratorMode::From(
+                (primary_index, *pubkey, slot.saturating_add(1)),
+                IteratorDirection::Forward,
+            ))?;
+            if matches!(iterator.next(), Some(((i, key, _), _)) if i == primary_index && key == *pubkey)
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     /// Returns the entry vector for the slot starting with `shred_start_index`
     pub fn get_slot_entries(&self, slot: Slot, shred_start_index: u64) -> Result<Vec<Entry>> {
         self.get_slot_entries_with_shred_info(slot, shred_start_index, false)
@@ -2593,6 +2722,17 @@ impl Blockstore {
         self.duplicate_slots_cf.put(slot, &duplicate_slot_proof)
     }
 
+    /// Record the hash of the bank frozen for `slot`
+    pub fn insert_bank_hash(&self, slot: Slot, bank_hash: Hash) -> Result<()> {
+        self.bank_hash_cf.put(slot, &bank_hash)
+    }
+
+    pub fn get_bank_hash(&self, slot: Slot) -> Option<Hash> {
+        self.bank_hash_cf
+            .get(slot)
+            .expect("fetch from BankHash column family failed")
+    }
+
     pub fn get_duplicate_slot(&self, slot: u64) -> Option<DuplicateSlotProof> {
         self.duplicate_slots_cf
             .get(slot)
@@ -2625,6 +2765,13 @@ impl Blockstore {
             .is_some()
     }
 
+    pub fn duplicate_slots_iterator(&self, slot: Slot) -> Result<impl Iterator<Item = Slot> + '_> {
+        let duplicate_slots_iter = self
+            .db
+            .iter::<cf::DuplicateSlots>(IteratorMode::From(slot, IteratorDirection::Forward))?;
+        Ok(duplicate_slots_iter.map(|(slot, _)| slot))
+    }
+
     pub fn orphans_iterator<'a>(&'a self, slot: Slot) -> Result<impl Iterator<Item = u64> + 'a> {
         let orphans_iter = self
             .db
@@ -2660,6 +2807,12 @@ impl Blockstore {
         self.last_root()
     }
 
+    /// Expires the slots up to and including `to_slot`. They read as purged right away, and
+    /// compactions of the slot keyed columns drop them from then on
+    pub fn set_max_expired_slot(&self, to_slot: Slot) {
+        self.db.set_oldest_slot(to_slot.saturating_add(1));
+    }
+
     pub fn storage_size(&self) -> Result<u64> {
         self.db.storage_size()
     }
@@ -5878,6 +6031,44 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_get_blockhash_slot() {
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path).unwrap();
+            let blockhash1 = hash(&[1]);
+            let blockhash2 = hash(&[2]);
+            let blockhash3 = hash(&[3]);
+
+            // Only rooted blockhashes are indexed
+            assert_matches!(
+                blockstore.cache_blockhash(1, &blockhash1),
+                Err(BlockstoreError::SlotNotRooted)
+            );
+            blockstore.set_roots(&[1, 2]).unwrap();
+            blockstore.cache_blockhash(1, &blockhash1).unwrap();
+            blockstore.cache_blockhash(2, &blockhash2).unwrap();
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash1).unwrap(), Some(1));
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash2).unwrap(), Some(2));
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash3).unwrap(), None);
+
+            // Entries of slots that are not rooted are skipped
+            blockstore
+                .blockhash_slot_cf
+                .put((1, blockhash3, 3), &true)
+                .unwrap();
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash3).unwrap(), None);
+            blockstore.set_roots(&[3]).unwrap();
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash3).unwrap(), Some(3));
+
+            // Slots below the oldest slot of the compaction filters are skipped
+            blockstore.db.set_oldest_slot(2);
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash1).unwrap(), None);
+            assert_eq!(blockstore.get_blockhash_slot(&blockhash2).unwrap(), Some(2));
+        }
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_persist_transaction_status() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -6821,6 +7012,58 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_write_get_account_history() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let pubkey = Pubkey::new_unique();
+            let other = Pubkey::new_unique();
+            let account = |lamports| Account::new(lamports, 0, &Pubkey::default());
+
+            assert_eq!(blockstore.get_first_account_history_root().unwrap(), None);
+            assert_eq!(blockstore.get_last_account_history_root().unwrap(), None);
+            blockstore
+                .write_account_history(2, &[(pubkey, account(2)), (other, account(20))])
+                .unwrap();
+            blockstore
+                .write_account_history(5, &[(pubkey, account(5))])
+                .unwrap();
+            assert_eq!(
+                blockstore.get_first_account_history_root().unwrap(),
+                Some(2)
+            );
+            assert_eq!(blockstore.get_last_account_history_root().unwrap(), Some(5));
+
+            assert_eq!(blockstore.get_account_history(&pubkey, 1).unwrap(), None);
+            assert_eq!(
+                blockstore.get_account_history(&pubkey, 2).unwrap(),
+                Some((2, account(2)))
+            );
+            assert_eq!(
+                blockstore.get_account_history(&pubkey, 4).unwrap(),
+                Some((2, account(2)))
+            );
+            assert_eq!(
+                blockstore.get_account_history(&pubkey, 10).unwrap(),
+                Some((5, account(5)))
+            );
+            assert_eq!(
+                blockstore.get_account_history(&other, 10).unwrap(),
+                Some((2, account(20)))
+            );
+
+            assert!(blockstore.has_account_history_after(&pubkey, 1).unwrap());
+            assert!(blockstore.has_account_history_after(&pubkey, 4).unwrap());
+            assert!(!blockstore.has_account_history_after(&pubkey, 5).unwrap());
+            assert!(!blockstore.has_account_history_after(&other, 2).unwrap());
+            assert!(!blockstore
+                .has_account_history_after(&Pubkey::new_unique(), 0)
+                .unwrap());
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_write_get_perf_samples() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -6902,6 +7145,52 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_merkle_recovery() {
+        let slot = 1;
+        let entries = make_slot_entries_with_transactions(100);
+        let leader_keypair = Arc::new(Keypair::new());
+        let shredder = Shredder::new(slot, 0, 1.0, leader_keypair.clone(), 0, 0).unwrap();
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_merkle_shreds(&entries, true, 0);
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let mut leader_schedule_cache = LeaderScheduleCache::new_from_bank(&Bank::new(
+                &create_genesis_config(2).genesis_config,
+            ));
+            leader_schedule_cache.set_fixed_leader_schedule(Some(FixedSchedule {
+                leader_schedule: Arc::new(LeaderSchedule::new_from_schedule(vec![
+                    leader_keypair.pubkey()
+                ])),
+                start_epoch: 0,
+            }));
+            // Every other data shred along with all the coding shreds
+            let leader_schedule_cache = Arc::new(leader_schedule_cache);
+            let shreds: Vec<_> = data_shreds
+                .iter()
+                .step_by(2)
+                .chain(&coding_shreds)
+                .cloned()
+                .collect();
+            blockstore
+                .insert_shreds(shreds, Some(&leader_schedule_cache), false)
+                .unwrap();
+
+            for shred in &data_shreds {
+                assert_eq!(
+                    blockstore
+                        .get_data_shred(slot, shred.index() as u64)
+                        .unwrap()
+                        .unwrap(),
+                    shred.payload
+                );
+            }
+            assert_eq!(blockstore.get_slot_entries(slot, 0).unwrap(), entries);
+            verify_index_integrity(&blockstore, slot);
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_index_integrity() {
         let slot = 1;
@@ -7142,6 +7431,13 @@ pub mod tests {
 
             // Slot is now marked as duplicate
             assert!(blockstore.has_duplicate_shreds_in_slot(slot));
+            assert_eq!(
+                blockstore
+                    .duplicate_slots_iterator(slot)
+                    .unwrap()
+                    .collect::<Vec<_>>(),
+                vec![slot]
+            );
 
             // Check ability to fetch the duplicates
             let duplicate_proof = blockstore.get_duplicate_slot(slot).unwrap();
@@ -7177,10 +7473,16 @@ pub mod tests {
                 .unwrap()
                 .is_none());
             blockstore.set_dead_slot(unconfirmed_slot).unwrap();
+            let bank_hash = Hash::new_unique();
+            blockstore
+                .insert_bank_hash(unconfirmed_slot, bank_hash)
+                .unwrap();
+            assert_eq!(blockstore.get_bank_hash(unconfirmed_slot), Some(bank_hash));
 
             // Purge the slot
             blockstore.clear_unconfirmed_slot(unconfirmed_slot);
             assert!(!blockstore.is_dead(unconfirmed_slot));
+            assert_eq!(blockstore.get_bank_hash(unconfirmed_slot), None);
             assert_eq!(
                 blockstore
                     .meta(unconfirmed_slot)
@@ -0,0 +1,657 @@
+//! Portable archives of a slot range of the blockstore.
+//!
+//! An archive starts with `ARCHIVE_MAGIC` and a little-endian `u32` format version, followed by
+//! a zstd stream of length-prefixed bincode `ArchiveRecord`s. The final record carries the
+//! sha256 of all the record bytes before it. `AddressSignatures` is not archived, it is
+//! reindexed from the transactions of the imported shreds.
+use super::*;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const ARCHIVE_MAGIC: &[u8; 8] = b"SOLLEDGR";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// Bounds the allocation of a single record read from an untrusted archive
+const MAX_ARCHIVE_RECORD_SIZE: u64 = 64 * 1024 * 1024;
+// Bounds the number of slots whose transaction account keys are cached while importing
+const MAX_CACHED_ACCOUNT_KEYS_SLOTS: usize = 64;
+
+#[derive(Debug, Deserialize, Serialize)]
+enum ArchiveRecord {
+    SlotRange {
+        start_slot: Slot,
+        end_slot: Slot,
+    },
+    SlotMeta(SlotMeta),
+    Index(Index),
+    ErasureMeta(Slot, ErasureMeta),
+    DataShred {
+        slot: Slot,
+        index: u64,
+        payload: Vec<u8>,
+    },
+    CodingShred {
+        slot: Slot,
+        index: u64,
+        payload: Vec<u8>,
+    },
+    Root(Slot),
+    TransactionStatus(Slot, Signature, TransactionStatusMeta),
+    Rewards(Slot, Rewards),
+    BlockTime(Slot, UnixTimestamp),
+    PerfSample(Slot, PerfSample),
+    End(Hash),
+}
+
+impl ArchiveRecord {
+    // Checks that a record read from an archive of [start_slot, end_slot] can be imported
+    fn check(&self, start_slot: Slot, end_slot: Slot) -> Result<()> {
+        let slot = match self {
+            ArchiveRecord::SlotRange { .. } => {
+                return Err(invalid_archive("duplicate slot range"));
+            }
+            ArchiveRecord::End(_) => return Ok(()),
+            ArchiveRecord::SlotMeta(meta) => meta.slot,
+            ArchiveRecord::Index(index) => index.slot,
+            ArchiveRecord::DataShred {
+                slot,
+                index,
+                payload,
+            } => {
+                let shred = Shred::new_from_serialized_shred(payload.clone())
+                    .map_err(|_| invalid_archive("malformed data shred"))?;
+                if !shred.is_data() || shred.slot() != *slot || u64::from(shred.index()) != *index {
+                    return Err(invalid_archive("data shred does not match its key"));
+                }
+                *slot
+            }
+            ArchiveRecord::CodingShred {
+                slot,
+                index,
+                payload,
+            } => {
+                let shred = Shred::new_from_serialized_shred(payload.clone())
+                    .map_err(|_| invalid_archive("malformed coding shred"))?;
+                if !shred.is_code() || shred.slot() != *slot || u64::from(shred.index()) != *index {
+                    return Err(invalid_archive("coding shred does not match its key"));
+                }
+                *slot
+            }
+            ArchiveRecord::ErasureMeta(slot, _)
+            | ArchiveRecord::Root(slot)
+            | ArchiveRecord::TransactionStatus(slot, _, _)
+            | ArchiveRecord::Rewards(slot, _)
+            | ArchiveRecord::BlockTime(slot, _)
+            | ArchiveRecord::PerfSample(slot, _) => *slot,
+        };
+        if slot < start_slot || slot > end_slot {
+            return Err(BlockstoreError::InvalidArchive(format!(
+                "slot {} is outside of the archived range",
+                slot
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct BlockstoreArchiveStats {
+    pub slots: usize,
+    pub data_shreds: usize,
+    pub coding_shreds: usize,
+    pub roots: usize,
+    pub transaction_statuses: usize,
+    pub rewards: usize,
+    pub block_times: usize,
+    pub perf_samples: usize,
+}
+
+impl std::fmt::Display for BlockstoreArchiveStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} slots, {} data shreds, {} coding shreds, {} roots, {} transaction statuses, \
+             {} rewards, {} block times, {} performance samples",
+            self.slots,
+            self.data_shreds,
+            self.coding_shreds,
+            self.roots,
+            self.transaction_statuses,
+            self.rewards,
+            self.block_times,
+            self.perf_samples
+        )
+    }
+}
+
+fn archive_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(MAX_ARCHIVE_RECORD_SIZE)
+}
+
+fn invalid_archive(reason: &str) -> BlockstoreError {
+    BlockstoreError::InvalidArchive(reason.to_string())
+}
+
+struct ArchiveWriter<W: Write> {
+    writer: W,
+    hasher: Hasher,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    fn write(&mut self, record: &ArchiveRecord) -> Result<()> {
+        let bytes = archive_options().serialize(record)?;
+        let len = (bytes.len() as u64).to_le_bytes();
+        self.hasher.hashv(&[&len, &bytes]);
+        self.writer.write_all(&len)?;
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        let checksum = self.hasher.clone().result();
+        self.write(&ArchiveRecord::End(checksum))?;
+        Ok(self.writer)
+    }
+}
+
+struct ArchiveReader<R: Read> {
+    reader: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    // Returns the next record, verifying the checksum once the end of the archive is reached
+    fn read(&mut self) -> Result<Option<ArchiveRecord>> {
+        let mut len = [0u8; 8];
+        self.reader.read_exact(&mut len)?;
+        if u64::from_le_bytes(len) > MAX_ARCHIVE_RECORD_SIZE {
+            return Err(invalid_archive("record too large"));
+        }
+        let mut bytes = vec![0u8; u64::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut bytes)?;
+        let record = archive_options().deserialize(&bytes)?;
+        if let ArchiveRecord::End(checksum) = record {
+            if checksum != self.hasher.clone().result() {
+                return Err(invalid_archive("checksum mismatch"));
+            }
+            if self.reader.read(&mut [0u8; 1])? != 0 {
+                return Err(invalid_archive(
+                    "trailing data after the end of the archive",
+                ));
+            }
+            return Ok(None);
+        }
+        self.hasher.hashv(&[&len, &bytes]);
+        Ok(Some(record))
+    }
+}
+
+impl Blockstore {
+    /// Writes the shreds, slot metadata, roots, transaction statuses, rewards, block times and
+    /// performance samples of the slots in [start_slot, end_slot] to an archive
+    pub fn export_archive<W: Write>(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+        mut writer: W,
+    ) -> Result<BlockstoreArchiveStats> {
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        let mut archive = ArchiveWriter {
+            writer: zstd::stream::Encoder::new(writer, 0)?,
+            hasher: Hasher::default(),
+        };
+        let mut stats = BlockstoreArchiveStats::default();
+        archive.write(&ArchiveRecord::SlotRange {
+            start_slot,
+            end_slot,
+        })?;
+
+        for (slot, meta) in self.slot_meta_iterator(start_slot)? {
+            if slot > end_slot {
+                break;
+            }
+            archive.write(&ArchiveRecord::SlotMeta(meta))?;
+            stats.slots += 1;
+            if let Some(index) = self.get_index(slot)? {
+                archive.write(&ArchiveRecord::Index(index))?;
+            }
+            let erasure_metas = self
+                .erasure_meta_cf
+                .iter(IteratorMode::From((slot, 0), IteratorDirection::Forward))?
+                .take_while(|((erasure_slot, _), _)| *erasure_slot == slot);
+            for (_, erasure_meta) in erasure_metas {
+                archive.write(&ArchiveRecord::ErasureMeta(
+                    slot,
+                    deserialize(&erasure_meta)?,
+                ))?;
+            }
+            for ((slot, index), payload) in self.slot_data_iterator(slot, 0)? {
+                archive.write(&ArchiveRecord::DataShred {
+                    slot,
+                    index,
+                    payload: payload.into_vec(),
+                })?;
+                stats.data_shreds += 1;
+            }
+            for ((slot, index), payload) in self.slot_coding_iterator(slot, 0)? {
+                archive.write(&ArchiveRecord::CodingShred {
+                    slot,
+                    index,
+                    payload: payload.into_vec(),
+                })?;
+                stats.coding_shreds += 1;
+            }
+        }
+
+        let from_start = IteratorMode::From(start_slot, IteratorDirection::Forward);
+        for (slot, _) in self.db.iter::<cf::Root>(from_start)? {
+            if slot > end_slot {
+                break;
+            }
+            archive.write(&ArchiveRecord::Root(slot))?;
+            stats.roots += 1;
+        }
+        let from_start = IteratorMode::From(start_slot, IteratorDirection::Forward);
+        let reward_slots: Vec<_> = self
+            .rewards_cf
+            .iter(from_start)?
+            .map(|(slot, _)| slot)
+            .take_while(|slot| *slot <= end_slot)
+            .collect();
+        for slot in reward_slots {
+            if let Some(rewards) = self.read_rewards(slot)? {
+                archive.write(&ArchiveRecord::Rewards(slot, rewards))?;
+                stats.rewards += 1;
+            }
+        }
+        let from_start = IteratorMode::From(start_slot, IteratorDirection::Forward);
+        for (slot, timestamp) in self.blocktime_cf.iter(from_start)? {
+            if slot > end_slot {
+                break;
+            }
+            archive.write(&ArchiveRecord::BlockTime(slot, deserialize(&timestamp)?))?;
+            stats.block_times += 1;
+        }
+        let from_start = IteratorMode::From(start_slot, IteratorDirection::Forward);
+        for (slot, perf_sample) in self.perf_samples_cf.iter(from_start)? {
+            if slot > end_slot {
+                break;
+            }
+            archive.write(&ArchiveRecord::PerfSample(slot, deserialize(&perf_sample)?))?;
+            stats.perf_samples += 1;
+        }
+
+        // Transaction statuses are keyed by signature, so both primary indexes are scanned in
+        // full. A status may be present under both while the primary index is being rotated
+        let mut exported_statuses = HashSet::new();
+        for ((_, signature, slot), status) in
+            self.transaction_status_cf.iter(IteratorMode::Start)?
+        {
+            if slot < start_slot || slot > end_slot || !exported_statuses.insert((signature, slot))
+            {
+                continue;
+            }
+            archive.write(&ArchiveRecord::TransactionStatus(
+                slot,
+                signature,
+                deserialize(&status)?,
+            ))?;
+            stats.transaction_statuses += 1;
+        }
+
+        archive.finish()?.finish()?.flush()?;
+        Ok(stats)
+    }
+
+    /// Writes the contents of an archive made by `export_archive` into this blockstore, and
+    /// reindexes the address signatures of the imported transaction statuses.
+    ///
+    /// The whole archive is read and its checksum verified before anything is written, so an
+    /// archive that is corrupt or truncated leaves the blockstore untouched.
+    pub fn import_archive<R: Read + Seek>(&self, mut reader: R) -> Result<BlockstoreArchiveStats> {
+        Self::read_archive(&mut reader, |_| Ok(()))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut stats = BlockstoreArchiveStats::default();
+        let mut account_keys_by_slot = HashMap::new();
+        Self::read_archive(&mut reader, |record| {
+            self.import_record(record, &mut stats, &mut account_keys_by_slot)
+        })?;
+        Ok(stats)
+    }
+
+    // Reads and checks every record of an archive, passing them to `process_record`. Fails if
+    // the archive is malformed or its checksum does not match
+    fn read_archive<R: Read>(
+        mut reader: R,
+        mut process_record: impl FnMut(ArchiveRecord) -> Result<()>,
+    ) -> Result<()> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(invalid_archive("not a blockstore archive"));
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != ARCHIVE_VERSION {
+            return Err(BlockstoreError::InvalidArchive(format!(
+                "unsupported archive version {}",
+                version
+            )));
+        }
+        let mut archive = ArchiveReader {
+            reader: zstd::stream::read::Decoder::new(reader)?,
+            hasher: Hasher::default(),
+        };
+        let (start_slot, end_slot) = match archive.read()? {
+            Some(ArchiveRecord::SlotRange {
+                start_slot,
+                end_slot,
+            }) => (start_slot, end_slot),
+            _ => return Err(invalid_archive("missing slot range")),
+        };
+        while let Some(record) = archive.read()? {
+            record.check(start_slot, end_slot)?;
+            process_record(record)?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn import_record(
+        &self,
+        record: ArchiveRecord,
+        stats: &mut BlockstoreArchiveStats,
+        account_keys_by_slot: &mut HashMap<Slot, HashMap<Signature, (Vec<Pubkey>, Vec<Pubkey>)>>,
+    ) -> Result<()> {
+        match record {
+            ArchiveRecord::SlotRange { .. } | ArchiveRecord::End(_) => unreachable!(),
+            ArchiveRecord::SlotMeta(meta) => {
+                self.meta_cf.put(meta.slot, &meta)?;
+                stats.slots += 1;
+            }
+            ArchiveRecord::Index(index) => {
+                self.index_cf.put(index.slot, &index)?;
+            }
+            ArchiveRecord::ErasureMeta(slot, erasure_meta) => {
+                self.erasure_meta_cf
+                    .put((slot, erasure_meta.set_index), &erasure_meta)?;
+            }
+            ArchiveRecord::DataShred {
+                slot,
+                index,
+                payload,
+            } => {
+                self.data_shred_cf.put_bytes((slot, index), &payload)?;
+                stats.data_shreds += 1;
+            }
+            ArchiveRecord::CodingShred {
+                slot,
+                index,
+                payload,
+            } => {
+                self.code_shred_cf.put_bytes((slot, index), &payload)?;
+                stats.coding_shreds += 1;
+            }
+            ArchiveRecord::Root(slot) => {
+                self.set_roots(&[slot])?;
+                stats.roots += 1;
+            }
+            ArchiveRecord::TransactionStatus(slot, signature, status) => {
+                // Statuses are archived in signature order, so the account keys of their slots
+                // are cached and the cache is cleared once it holds too many slots
+                if !account_keys_by_slot.contains_key(&slot)
+                    && account_keys_by_slot.len() >= MAX_CACHED_ACCOUNT_KEYS_SLOTS
+                {
+                    account_keys_by_slot.clear();
+                }
+                let (writable_keys, readonly_keys) = account_keys_by_slot
+                    .entry(slot)
+                    .or_insert_with(|| self.account_keys_by_signature(slot))
+                    .get(&signature)
+                    .map(|(writable_keys, readonly_keys)| {
+                        (
+                            writable_keys.iter().collect(),
+                            readonly_keys.iter().collect(),
+                        )
+                    })
+                    .unwrap_or_else(|| {
+                        warn!(
+                            "Transaction {} is not in the shreds of slot {}, its addresses \
+                             will not be indexed",
+                            signature, slot
+                        );
+                        (vec![], vec![])
+                    });
+                self.write_transaction_status(
+                    slot,
+                    signature,
+                    writable_keys,
+                    readonly_keys,
+                    &status,
+                )?;
+                stats.transaction_statuses += 1;
+            }
+            ArchiveRecord::Rewards(slot, rewards) => {
+                self.write_rewards(slot, rewards)?;
+                stats.rewards += 1;
+            }
+            ArchiveRecord::BlockTime(slot, timestamp) => {
+                self.blocktime_cf.put(slot, &timestamp)?;
+                stats.block_times += 1;
+            }
+            ArchiveRecord::PerfSample(slot, perf_sample) => {
+                self.write_perf_sample(slot, &perf_sample)?;
+                stats.perf_samples += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // The writable and readonly account keys of each transaction in the entries of `slot`
+    #[allow(clippy::type_complexity)]
+    fn account_keys_by_signature(
+        &self,
+        slot: Slot,
+    ) -> HashMap<Signature, (Vec<Pubkey>, Vec<Pubkey>)> {
+        self.get_slot_entries(slot, 0)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|entry| entry.transactions)
+            .filter_map(|transaction| {
+                let signature = *transaction.signatures.get(0)?;
+                let (writable_keys, readonly_keys) =
+                    transaction.message.get_account_keys_by_lock_type();
+                Some((
+                    signature,
+                    (
+                        writable_keys.into_iter().copied().collect(),
+                        readonly_keys.into_iter().copied().collect(),
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{blockstore::tests::make_slot_entries_with_transactions, get_tmp_ledger_path};
+    use solana_transaction_status::Reward;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_export_import_archive() {
+        let source_path = get_tmp_ledger_path!();
+        let source = Blockstore::open(&source_path).unwrap();
+        let entries = make_slot_entries_with_transactions(5);
+        for slot in 1..=3 {
+            let shreds = entries_to_test_shreds(entries.clone(), slot, slot - 1, true, 0);
+            source.insert_shreds(shreds, None, false).unwrap();
+        }
+        source.set_roots(&[1, 2, 3]).unwrap();
+        source.cache_block_time(2, 1_600_000_000).unwrap();
+        source
+            .write_rewards(
+                2,
+                vec![Reward {
+                    pubkey: Pubkey::new_unique().to_string(),
+                    lamports: 42,
+                    post_balance: 1042,
+                    reward_type: None,
+                }],
+            )
+            .unwrap();
+        source
+            .write_perf_sample(
+                2,
+                &PerfSample {
+                    num_transactions: 5,
+                    num_slots: 1,
+                    sample_period_secs: 60,
+                },
+            )
+            .unwrap();
+        let status = TransactionStatusMeta {
+            status: Ok(()),
+            fee: 5000,
+            pre_balances: vec![1, 2],
+            post_balances: vec![3, 4],
+            inner_instructions: Some(vec![]),
+            log_messages: Some(vec![]),
+        };
+        let transaction = &entries[0].transactions[0];
+        let (writable_keys, readonly_keys) = transaction.message.get_account_keys_by_lock_type();
+        for slot in 1..=3 {
+            source
+                .write_transaction_status(
+                    slot,
+                    transaction.signatures[0],
+                    writable_keys.clone(),
+                    readonly_keys.clone(),
+                    &status,
+                )
+                .unwrap();
+        }
+
+        let mut archive = vec![];
+        let export_stats = source.export_archive(2, 3, &mut archive).unwrap();
+        assert_eq!(
+            export_stats,
+            BlockstoreArchiveStats {
+                slots: 2,
+                data_shreds: source.get_data_shreds_for_slot(2, 0).unwrap().len() * 2,
+                coding_shreds: 0,
+                roots: 2,
+                transaction_statuses: 2,
+                rewards: 1,
+                block_times: 1,
+                perf_samples: 1,
+            }
+        );
+
+        let target_path = get_tmp_ledger_path!();
+        let target = Blockstore::open(&target_path).unwrap();
+        assert_eq!(
+            target.import_archive(Cursor::new(&archive)).unwrap(),
+            export_stats
+        );
+        assert!(target.meta(1).unwrap().is_none());
+        for slot in 2..=3 {
+            assert_eq!(target.meta(slot).unwrap(), source.meta(slot).unwrap());
+            assert_eq!(
+                target.get_index(slot).unwrap(),
+                source.get_index(slot).unwrap()
+            );
+            assert_eq!(
+                target.get_slot_entries(slot, 0).unwrap(),
+                source.get_slot_entries(slot, 0).unwrap()
+            );
+            assert!(target.is_root(slot));
+        }
+        assert_eq!(
+            target.read_rewards(2).unwrap(),
+            source.read_rewards(2).unwrap()
+        );
+        assert_eq!(target.get_block_time(2).unwrap(), Some(1_600_000_000));
+        assert_eq!(
+            target.get_recent_perf_samples(1).unwrap(),
+            source.get_recent_perf_samples(1).unwrap()
+        );
+        assert_eq!(
+            target
+                .read_transaction_status((transaction.signatures[0], 3))
+                .unwrap(),
+            Some(status)
+        );
+        assert!(target
+            .read_transaction_status((transaction.signatures[0], 1))
+            .unwrap()
+            .is_none());
+        let address_signatures: Vec<_> = target
+            .address_signatures_cf
+            .iter(IteratorMode::Start)
+            .unwrap()
+            .map(|(key, _)| key)
+            .filter(|(_, _, _, signature)| *signature == transaction.signatures[0])
+            .collect();
+        assert_eq!(
+            address_signatures.len(),
+            2 * (writable_keys.len() + readonly_keys.len())
+        );
+        assert!(address_signatures
+            .iter()
+            .all(|(_, _, slot, _)| *slot == 2 || *slot == 3));
+
+        // Corruption is caught by the checksum or the decompressor, and truncation by the
+        // missing end record. Either way nothing is imported
+        let mut corrupt = archive.clone();
+        let middle = corrupt.len() / 2;
+        corrupt[middle] ^= 0xff;
+        let truncated = archive[..archive.len() - 16].to_vec();
+        let corrupt_path = get_tmp_ledger_path!();
+        {
+            let corrupt_target = Blockstore::open(&corrupt_path).unwrap();
+            assert!(corrupt_target
+                .import_archive(Cursor::new(&corrupt))
+                .is_err());
+            assert!(corrupt_target
+                .import_archive(Cursor::new(&truncated))
+                .is_err());
+            assert!(corrupt_target
+                .import_archive(Cursor::new(b"not an archive"))
+                .is_err());
+            assert!(corrupt_target
+                .slot_meta_iterator(0)
+                .unwrap()
+                .next()
+                .is_none());
+            assert!(corrupt_target
+                .data_shred_cf
+                .iter(IteratorMode::Start)
+                .unwrap()
+                .next()
+                .is_none());
+            // Only the dummy status written when the blockstore is opened is present
+            assert!(corrupt_target
+                .transaction_status_cf
+                .iter(IteratorMode::Start)
+                .unwrap()
+                .all(|((_, signature, _), _)| signature == Signature::default()));
+            assert!(!corrupt_target.is_root(2));
+        }
+
+        drop(source);
+        drop(target);
+        Blockstore::destroy(&source_path).expect("Expected successful database destruction");
+        Blockstore::destroy(&target_path).expect("Expected successful database destruction");
+        Blockstore::destroy(&corrupt_path).expect("Expected successful database destruction");
+    }
+}
@@ -145,6 +145,14 @@ impl Blockstore {
             & self
                 .db
                 .delete_range_cf::<cf::PerfSamples>(&mut write_batch, from_slot, to_slot)
+                .is_ok()
+            & self
+                .db
+                .delete_range_cf::<cf::BankHash>(&mut write_batch, from_slot, to_slot)
+                .is_ok()
+            & self
+                .db
+                .delete_range_cf::<cf::AccountHistoryRoots>(&mut write_batch, from_slot, to_slot)
                 .is_ok();
         let mut w_active_transaction_status_index =
             self.active_transaction_status_index.write().unwrap();
@@ -177,6 +185,22 @@ impl Blockstore {
     }
 
     pub fn compact_storage(&self, from_slot: Slot, to_slot: Slot) -> Result<bool> {
+        self.run_compact_storage(from_slot, to_slot, true)
+    }
+
+    /// Compacts the slot keyed columns in [from_slot,to_slot], leaving out the columns keyed by
+    /// the transaction status primary index. Regular compactions seldom reach the oldest slots of
+    /// the slot keyed columns, so this is how expired slots in them get dropped
+    pub fn compact_expired_slots(&self, from_slot: Slot, to_slot: Slot) -> Result<bool> {
+        self.run_compact_storage(from_slot, to_slot, false)
+    }
+
+    fn run_compact_storage(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+        include_special_columns: bool,
+    ) -> Result<bool> {
         if self.no_compaction {
             info!("compact_storage: compaction disabled");
             return Ok(false);
@@ -220,30 +244,47 @@ impl Blockstore {
                 .index_cf
                 .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
+            && (!include_special_columns
+                || self
+                    .transaction_status_cf
+                    .compact_range(0, 2)
+                    .unwrap_or(false)
+                    && self
+                        .address_signatures_cf
+                        .compact_range(0, 2)
+                        .unwrap_or(false)
+                    && self
+                        .transaction_status_index_cf
+                        .compact_range(0, 2)
+                        .unwrap_or(false)
+                    && self.blockhash_slot_cf.compact_range(0, 2).unwrap_or(false))
             && self
-                .transaction_status_cf
-                .compact_range(0, 2)
-                .unwrap_or(false)
-            && self
-                .address_signatures_cf
-                .compact_range(0, 2)
+                .rewards_cf
+                .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
             && self
-                .transaction_status_index_cf
-                .compact_range(0, 2)
+                .blocktime_cf
+                .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
             && self
-                .rewards_cf
+                .perf_samples_cf
                 .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
             && self
-                .blocktime_cf
+                .bank_hash_cf
                 .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
             && self
-                .perf_samples_cf
+                .db
+                .column::<cf::AccountHistoryRoots>()
                 .compact_range(from_slot, to_slot)
-                .unwrap_or(false);
+                .unwrap_or(false)
+            && (!include_special_columns
+                || self
+                    .db
+                    .column::<cf::AccountHistory>()
+                    .compact_range(0, 2)
+                    .unwrap_or(false));
         compact_timer.stop();
         if !result {
             info!("compact_storage incomplete");
@@ -268,6 +309,10 @@ impl Blockstore {
         let mut index1 = self.transaction_status_index_cf.get(1)?.unwrap_or_default();
         for slot in from_slot..to_slot {
             let slot_entries = self.get_any_valid_slot_entries(slot, 0);
+            if let Some(blockhash) = get_last_hash(slot_entries.iter()) {
+                batch.delete::<cf::BlockhashSlot>((0, blockhash, slot))?;
+                batch.delete::<cf::BlockhashSlot>((1, blockhash, slot))?;
+            }
             for transaction in slot_entries
                 .iter()
                 .cloned()
@@ -291,6 +336,7 @@ impl Blockstore {
                 }
             }
         }
+        self.purge_account_history_exact(batch, from_slot, to_slot)?;
         if index0.max_slot >= from_slot && index0.max_slot <= to_slot {
             index0.max_slot = from_slot.saturating_sub(1);
             batch.put::<cf::TransactionStatusIndex>(0, &index0)?;
@@ -302,6 +348,36 @@ impl Blockstore {
         Ok(())
     }
 
+    /// Account history is keyed by pubkey, so its versions in [from_slot,to_slot) can only be
+    /// found by scanning the whole column. The scan is skipped unless one of the slots was an
+    /// archived root, which is never the case for the unrooted slots usually purged exactly.
+    fn purge_account_history_exact(
+        &self,
+        batch: &mut WriteBatch,
+        from_slot: Slot,
+        to_slot: Slot, // Exclusive
+    ) -> Result<()> {
+        let has_archived_root = self
+            .db
+            .iter::<cf::AccountHistoryRoots>(IteratorMode::From(
+                from_slot,
+                IteratorDirection::Forward,
+            ))?
+            .next()
+            .map(|(slot, _)| slot < to_slot)
+            .unwrap_or(false);
+        if !has_archived_root {
+            return Ok(());
+        }
+        for (key, _) in self.db.iter::<cf::AccountHistory>(IteratorMode::Start)? {
+            let (_, _, slot) = key;
+            if slot >= from_slot && slot < to_slot {
+                batch.delete::<cf::AccountHistory>(key)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Purges special columns (using a non-Slot primary-index) by range. Purge occurs if frozen
     /// primary index has a max-slot less than the highest slot being purged.
     fn purge_special_columns_with_primary_index(
@@ -323,6 +399,14 @@ impl Blockstore {
                 & self
                     .db
                     .delete_range_cf::<cf::AddressSignatures>(write_batch, index, index + 1)
+                    .is_ok()
+                & self
+                    .db
+                    .delete_range_cf::<cf::BlockhashSlot>(write_batch, index, index + 1)
+                    .is_ok()
+                & self
+                    .db
+                    .delete_range_cf::<cf::AccountHistory>(write_batch, index, index + 1)
                     .is_ok();
         }
         Ok(())
@@ -417,6 +501,13 @@ pub mod tests {
                     slot >= min_slot || (primary_index == 2 && slot == 0)
                 })
                 .unwrap_or(true)
+            & blockstore
+                .db
+                .iter::<cf::BlockhashSlot>(IteratorMode::Start)
+                .unwrap()
+                .next()
+                .map(|((_, _, slot), _)| slot >= min_slot)
+                .unwrap_or(true)
             & blockstore
                 .db
                 .iter::<cf::Rewards>(IteratorMode::Start)
@@ -455,6 +546,80 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_purge_by_compaction_filter() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let max_slot = 10;
+            let (shreds, _) = make_many_slot_entries(0, max_slot, 5);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            let roots: Vec<_> = (0..max_slot).collect();
+            blockstore.set_roots(&roots).unwrap();
+            let signatures: Vec<_> = (0..max_slot)
+                .map(|slot| {
+                    let random_bytes: Vec<u8> = (0..64).map(|_| rand::random::<u8>()).collect();
+                    let signature = Signature::new(&random_bytes);
+                    blockstore
+                        .write_transaction_status(
+                            slot,
+                            signature,
+                            vec![&Pubkey::new(&random_bytes[0..32])],
+                            vec![&Pubkey::new(&random_bytes[32..])],
+                            &TransactionStatusMeta::default(),
+                        )
+                        .unwrap();
+                    signature
+                })
+                .collect();
+
+            // Expired slots read as purged before any compaction drops them
+            blockstore.set_max_expired_slot(4);
+            assert_eq!(blockstore.lowest_slot(), 5);
+            assert_eq!(
+                blockstore.slot_meta_iterator(0).unwrap().next().unwrap().0,
+                5
+            );
+            assert!(blockstore
+                .get_transaction_status(signatures[4])
+                .unwrap()
+                .is_none());
+            assert!(blockstore
+                .get_transaction_status(signatures[5])
+                .unwrap()
+                .is_some());
+            assert!(blockstore
+                .db
+                .iter::<cf::ShredData>(IteratorMode::Start)
+                .unwrap()
+                .any(|((slot, _), _)| slot < 5));
+
+            blockstore.compact_storage(0, max_slot).unwrap();
+            test_all_empty_or_min(&blockstore, 5);
+            assert!(blockstore
+                .db
+                .iter::<cf::TransactionStatus>(IteratorMode::Start)
+                .unwrap()
+                .all(|((_, _, slot), _)| slot >= 5));
+            assert!(blockstore
+                .db
+                .iter::<cf::AddressSignatures>(IteratorMode::Start)
+                .unwrap()
+                .all(|((_, _, slot, _), _)| slot >= 5));
+            assert_eq!(
+                blockstore
+                    .db
+                    .iter::<cf::ShredData>(IteratorMode::Start)
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .0,
+                (5, 0)
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_purge_huge() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -520,6 +685,99 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_purge_blockhash_slot() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let (shreds, _) = make_many_slot_entries(0, 10, 5);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            let roots: Vec<Slot> = (0..10).collect();
+            blockstore.set_roots(&roots).unwrap();
+            for slot in roots {
+                let entries = blockstore.get_slot_entries(slot, 0).unwrap();
+                let blockhash = get_last_hash(entries.iter()).unwrap();
+                blockstore.cache_blockhash(slot, &blockhash).unwrap();
+            }
+
+            let blockhash_slots = |blockstore: &Blockstore| -> Vec<Slot> {
+                let mut slots: Vec<_> = blockstore
+                    .db
+                    .iter::<cf::BlockhashSlot>(IteratorMode::Start)
+                    .unwrap()
+                    .map(|((_, _, slot), _)| slot)
+                    .collect();
+                slots.sort_unstable();
+                slots
+            };
+            blockstore.run_purge(0, 4, PurgeType::Exact).unwrap();
+            assert_eq!(blockhash_slots(&blockstore), (5..10).collect::<Vec<_>>());
+
+            // Freeze index 0, then purge it along with the transaction statuses
+            blockstore.run_purge(0, 4, PurgeType::PrimaryIndex).unwrap();
+            blockstore
+                .run_purge(0, 10, PurgeType::PrimaryIndex)
+                .unwrap();
+            assert!(blockhash_slots(&blockstore).is_empty());
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_purge_account_history() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let pubkey = Pubkey::new_unique();
+            for slot in 0..10 {
+                blockstore
+                    .write_account_history(
+                        slot,
+                        &[(pubkey, Account::new(slot + 1, 0, &Pubkey::default()))],
+                    )
+                    .unwrap();
+            }
+
+            let account_history_slots = |blockstore: &Blockstore| -> Vec<Slot> {
+                let mut slots: Vec<_> = blockstore
+                    .db
+                    .iter::<cf::AccountHistory>(IteratorMode::Start)
+                    .unwrap()
+                    .map(|((_, _, slot), _)| slot)
+                    .collect();
+                slots.sort_unstable();
+                slots
+            };
+            blockstore.run_purge(0, 4, PurgeType::Exact).unwrap();
+            assert_eq!(
+                account_history_slots(&blockstore),
+                (5..10).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                blockstore.get_first_account_history_root().unwrap(),
+                Some(5)
+            );
+            assert_eq!(blockstore.get_account_history(&pubkey, 4).unwrap(), None);
+
+            // Freeze index 0, then purge it along with the transaction statuses
+            blockstore.run_purge(0, 4, PurgeType::PrimaryIndex).unwrap();
+            blockstore
+                .write_account_history(10, &[(pubkey, Account::new(11, 0, &Pubkey::default()))])
+                .unwrap();
+            blockstore.run_purge(0, 9, PurgeType::PrimaryIndex).unwrap();
+            assert_eq!(account_history_slots(&blockstore), vec![10]);
+            assert_eq!(
+                blockstore.get_first_account_history_root().unwrap(),
+                Some(10)
+            );
+            assert_eq!(
+                blockstore.get_account_history(&pubkey, 10).unwrap(),
+                Some((10, Account::new(11, 0, &Pubkey::default())))
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn test_purge_transaction_status() {
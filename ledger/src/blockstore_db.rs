@@ -5,20 +5,35 @@ use log::*;
 use prost::Message;
 pub use rocksdb::Direction as IteratorDirection;
 use rocksdb::{
-    self, ColumnFamily, ColumnFamilyDescriptor, DBIterator, DBRawIterator, DBRecoveryMode,
-    IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
+    self,
+    compaction_filter::CompactionFilter,
+    compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory},
+    ColumnFamily, ColumnFamilyDescriptor, CompactionDecision, DBIterator, DBRawIterator,
+    DBRecoveryMode, IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
 };
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use solana_runtime::hardened_unpack::UnpackError;
 use solana_sdk::{
+    account::Account,
     clock::{Slot, UnixTimestamp},
+    hash::Hash,
     pubkey::Pubkey,
     signature::Signature,
 };
 use solana_storage_proto::convert::generated;
 use solana_transaction_status::TransactionStatusMeta;
-use std::{collections::HashMap, fs, marker::PhantomData, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    fs,
+    marker::PhantomData,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 use thiserror::Error;
 
 const MAX_WRITE_BUFFER_SIZE: u64 = 256 * 1024 * 1024; // 256MB
@@ -50,12 +65,20 @@ const ADDRESS_SIGNATURES_CF: &str = "address_signatures";
 /// This column family is used for tracking the active primary index for columns that for
 /// query performance reasons should not be indexed by Slot.
 const TRANSACTION_STATUS_INDEX_CF: &str = "transaction_status_index";
+/// Column family for the slots of rooted blockhashes
+const BLOCKHASH_SLOT_CF: &str = "blockhash_slot";
 /// Column family for Rewards
 const REWARDS_CF: &str = "rewards";
 /// Column family for Blocktime
 const BLOCKTIME_CF: &str = "blocktime";
 /// Column family for Performance Samples
 const PERF_SAMPLES_CF: &str = "perf_samples";
+/// Column family for historical account states
+const ACCOUNT_HISTORY_CF: &str = "account_history";
+/// Column family for roots whose modified accounts have been archived
+const ACCOUNT_HISTORY_ROOTS_CF: &str = "account_history_roots";
+/// Column family for the hashes of frozen banks
+const BANK_HASH_CF: &str = "bank_hashes";
 
 #[derive(Error, Debug)]
 pub enum BlockstoreError {
@@ -75,6 +98,7 @@ pub enum BlockstoreError {
     NoVoteTimestampsInRange,
     ProtobufEncodeError(#[from] prost::EncodeError),
     ProtobufDecodeError(#[from] prost::DecodeError),
+    InvalidArchive(String),
 }
 pub type Result<T> = std::result::Result<T, BlockstoreError>;
 
@@ -139,6 +163,10 @@ pub mod columns {
     /// The transaction status index column
     pub struct TransactionStatusIndex;
 
+    #[derive(Debug)]
+    /// The blockhash slot column
+    pub struct BlockhashSlot;
+
     #[derive(Debug)]
     /// The rewards column
     pub struct Rewards;
@@ -150,6 +178,18 @@ pub mod columns {
     #[derive(Debug)]
     /// The performance samples column
     pub struct PerfSamples;
+
+    #[derive(Debug)]
+    /// The account history column
+    pub struct AccountHistory;
+
+    #[derive(Debug)]
+    /// The account history roots column
+    pub struct AccountHistoryRoots;
+
+    #[derive(Debug)]
+    /// The bank hash column
+    pub struct BankHash;
 }
 
 pub enum AccessType {
@@ -200,8 +240,40 @@ impl Into<DBRecoveryMode> for BlockstoreRecoveryMode {
     }
 }
 
+/// How the ledger cleanup service drops the slots beyond `--limit-ledger-size`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LedgerRetention {
+    /// Purge the slot range, then compact it
+    Purge,
+    /// Raise the oldest slot of the compaction filters, so that regular compactions drop the
+    /// slots below it
+    CompactionFilter,
+}
+
+impl Default for LedgerRetention {
+    fn default() -> Self {
+        LedgerRetention::Purge
+    }
+}
+
+// The lowest slot kept by the compaction filters of the slot keyed columns
+#[derive(Default, Clone, Debug)]
+struct OldestSlot(Arc<AtomicU64>);
+
+impl OldestSlot {
+    fn set(&self, oldest_slot: Slot) {
+        // Each compaction copies the value when its filter is created, no other data depends on
+        // it being ordered
+        self.0.store(oldest_slot, Ordering::Relaxed)
+    }
+
+    fn get(&self) -> Slot {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 #[derive(Debug)]
-struct Rocks(rocksdb::DB, ActualAccessType);
+struct Rocks(rocksdb::DB, ActualAccessType, OldestSlot);
 
 impl Rocks {
     fn open(
@@ -210,8 +282,9 @@ impl Rocks {
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<Rocks> {
         use columns::{
-            AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
+            AccountHistory, AccountHistoryRoots, AddressSignatures, BankHash, BlockhashSlot,
+            Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans, PerfSamples,
+            Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
             TransactionStatusIndex,
         };
 
@@ -225,38 +298,83 @@ impl Rocks {
         if let Some(recovery_mode) = recovery_mode {
             db_options.set_wal_recovery_mode(recovery_mode.into());
         }
+        let oldest_slot = OldestSlot::default();
 
         // Column family names
-        let meta_cf_descriptor =
-            ColumnFamilyDescriptor::new(SlotMeta::NAME, get_cf_options(&access_type));
-        let dead_slots_cf_descriptor =
-            ColumnFamilyDescriptor::new(DeadSlots::NAME, get_cf_options(&access_type));
-        let duplicate_slots_cf_descriptor =
-            ColumnFamilyDescriptor::new(DuplicateSlots::NAME, get_cf_options(&access_type));
-        let erasure_meta_cf_descriptor =
-            ColumnFamilyDescriptor::new(ErasureMeta::NAME, get_cf_options(&access_type));
-        let orphans_cf_descriptor =
-            ColumnFamilyDescriptor::new(Orphans::NAME, get_cf_options(&access_type));
-        let root_cf_descriptor =
-            ColumnFamilyDescriptor::new(Root::NAME, get_cf_options(&access_type));
-        let index_cf_descriptor =
-            ColumnFamilyDescriptor::new(Index::NAME, get_cf_options(&access_type));
-        let shred_data_cf_descriptor =
-            ColumnFamilyDescriptor::new(ShredData::NAME, get_cf_options(&access_type));
-        let shred_code_cf_descriptor =
-            ColumnFamilyDescriptor::new(ShredCode::NAME, get_cf_options(&access_type));
-        let transaction_status_cf_descriptor =
-            ColumnFamilyDescriptor::new(TransactionStatus::NAME, get_cf_options(&access_type));
-        let address_signatures_cf_descriptor =
-            ColumnFamilyDescriptor::new(AddressSignatures::NAME, get_cf_options(&access_type));
+        let meta_cf_descriptor = ColumnFamilyDescriptor::new(
+            SlotMeta::NAME,
+            get_slot_keyed_cf_options::<SlotMeta>(&access_type, &oldest_slot),
+        );
+        let dead_slots_cf_descriptor = ColumnFamilyDescriptor::new(
+            DeadSlots::NAME,
+            get_slot_keyed_cf_options::<DeadSlots>(&access_type, &oldest_slot),
+        );
+        let duplicate_slots_cf_descriptor = ColumnFamilyDescriptor::new(
+            DuplicateSlots::NAME,
+            get_slot_keyed_cf_options::<DuplicateSlots>(&access_type, &oldest_slot),
+        );
+        let erasure_meta_cf_descriptor = ColumnFamilyDescriptor::new(
+            ErasureMeta::NAME,
+            get_slot_keyed_cf_options::<ErasureMeta>(&access_type, &oldest_slot),
+        );
+        let orphans_cf_descriptor = ColumnFamilyDescriptor::new(
+            Orphans::NAME,
+            get_slot_keyed_cf_options::<Orphans>(&access_type, &oldest_slot),
+        );
+        let root_cf_descriptor = ColumnFamilyDescriptor::new(
+            Root::NAME,
+            get_slot_keyed_cf_options::<Root>(&access_type, &oldest_slot),
+        );
+        let index_cf_descriptor = ColumnFamilyDescriptor::new(
+            Index::NAME,
+            get_slot_keyed_cf_options::<Index>(&access_type, &oldest_slot),
+        );
+        let shred_data_cf_descriptor = ColumnFamilyDescriptor::new(
+            ShredData::NAME,
+            get_slot_keyed_cf_options::<ShredData>(&access_type, &oldest_slot),
+        );
+        let shred_code_cf_descriptor = ColumnFamilyDescriptor::new(
+            ShredCode::NAME,
+            get_slot_keyed_cf_options::<ShredCode>(&access_type, &oldest_slot),
+        );
+        let transaction_status_cf_descriptor = ColumnFamilyDescriptor::new(
+            TransactionStatus::NAME,
+            get_slot_keyed_cf_options::<TransactionStatus>(&access_type, &oldest_slot),
+        );
+        let address_signatures_cf_descriptor = ColumnFamilyDescriptor::new(
+            AddressSignatures::NAME,
+            get_slot_keyed_cf_options::<AddressSignatures>(&access_type, &oldest_slot),
+        );
         let transaction_status_index_cf_descriptor =
             ColumnFamilyDescriptor::new(TransactionStatusIndex::NAME, get_cf_options(&access_type));
-        let rewards_cf_descriptor =
-            ColumnFamilyDescriptor::new(Rewards::NAME, get_cf_options(&access_type));
-        let blocktime_cf_descriptor =
-            ColumnFamilyDescriptor::new(Blocktime::NAME, get_cf_options(&access_type));
-        let perf_samples_cf_descriptor =
-            ColumnFamilyDescriptor::new(PerfSamples::NAME, get_cf_options(&access_type));
+        let blockhash_slot_cf_descriptor = ColumnFamilyDescriptor::new(
+            BlockhashSlot::NAME,
+            get_slot_keyed_cf_options::<BlockhashSlot>(&access_type, &oldest_slot),
+        );
+        let rewards_cf_descriptor = ColumnFamilyDescriptor::new(
+            Rewards::NAME,
+            get_slot_keyed_cf_options::<Rewards>(&access_type, &oldest_slot),
+        );
+        let blocktime_cf_descriptor = ColumnFamilyDescriptor::new(
+            Blocktime::NAME,
+            get_slot_keyed_cf_options::<Blocktime>(&access_type, &oldest_slot),
+        );
+        let perf_samples_cf_descriptor = ColumnFamilyDescriptor::new(
+            PerfSamples::NAME,
+            get_slot_keyed_cf_options::<PerfSamples>(&access_type, &oldest_slot),
+        );
+        let account_history_cf_descriptor = ColumnFamilyDescriptor::new(
+            AccountHistory::NAME,
+            get_slot_keyed_cf_options::<AccountHistory>(&access_type, &oldest_slot),
+        );
+        let account_history_roots_cf_descriptor = ColumnFamilyDescriptor::new(
+            AccountHistoryRoots::NAME,
+            get_slot_keyed_cf_options::<AccountHistoryRoots>(&access_type, &oldest_slot),
+        );
+        let bank_hash_cf_descriptor = ColumnFamilyDescriptor::new(
+            BankHash::NAME,
+            get_slot_keyed_cf_options::<BankHash>(&access_type, &oldest_slot),
+        );
 
         let cfs = vec![
             (SlotMeta::NAME, meta_cf_descriptor),
@@ -274,9 +392,16 @@ impl Rocks {
                 TransactionStatusIndex::NAME,
                 transaction_status_index_cf_descriptor,
             ),
+            (BlockhashSlot::NAME, blockhash_slot_cf_descriptor),
             (Rewards::NAME, rewards_cf_descriptor),
             (Blocktime::NAME, blocktime_cf_descriptor),
             (PerfSamples::NAME, perf_samples_cf_descriptor),
+            (AccountHistory::NAME, account_history_cf_descriptor),
+            (
+                AccountHistoryRoots::NAME,
+                account_history_roots_cf_descriptor,
+            ),
+            (BankHash::NAME, bank_hash_cf_descriptor),
         ];
 
         // Open the database
@@ -284,12 +409,13 @@ impl Rocks {
             AccessType::PrimaryOnly | AccessType::PrimaryOnlyForMaintenance => Rocks(
                 DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1))?,
                 ActualAccessType::Primary,
+                oldest_slot,
             ),
             AccessType::TryPrimaryThenSecondary => {
                 let names: Vec<_> = cfs.iter().map(|c| c.0).collect();
 
                 match DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1)) {
-                    Ok(db) => Rocks(db, ActualAccessType::Primary),
+                    Ok(db) => Rocks(db, ActualAccessType::Primary, oldest_slot),
                     Err(err) => {
                         let secondary_path = path.join("solana-secondary");
 
@@ -303,6 +429,7 @@ impl Rocks {
                         Rocks(
                             DB::open_cf_as_secondary(&db_options, path, &secondary_path, names)?,
                             ActualAccessType::Secondary,
+                            oldest_slot,
                         )
                     }
                 }
@@ -314,8 +441,9 @@ impl Rocks {
 
     fn columns(&self) -> Vec<&'static str> {
         use columns::{
-            AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
+            AccountHistory, AccountHistoryRoots, AddressSignatures, BankHash, BlockhashSlot,
+            Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans, PerfSamples,
+            Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
             TransactionStatusIndex,
         };
 
@@ -332,9 +460,13 @@ impl Rocks {
             TransactionStatus::NAME,
             AddressSignatures::NAME,
             TransactionStatusIndex::NAME,
+            BlockhashSlot::NAME,
             Rewards::NAME,
             Blocktime::NAME,
             PerfSamples::NAME,
+            AccountHistory::NAME,
+            AccountHistoryRoots::NAME,
+            BankHash::NAME,
         ]
     }
 
@@ -414,6 +546,12 @@ pub trait Column {
     fn as_index(slot: Slot) -> Self::Index;
 }
 
+/// A column whose entries belong to a slot. The compaction filter drops the entries of the
+/// slots below the oldest slot kept
+pub trait SlotKeyedColumn: Column + ColumnName {
+    fn slot(index: Self::Index) -> Slot;
+}
+
 pub trait ColumnName {
     const NAME: &'static str;
 }
@@ -462,6 +600,12 @@ impl<T: SlotColumn> Column for T {
     }
 }
 
+impl<T: SlotColumn + ColumnName> SlotKeyedColumn for T {
+    fn slot(index: u64) -> Slot {
+        index
+    }
+}
+
 impl Column for columns::TransactionStatus {
     type Index = (u64, Signature, Slot);
 
@@ -497,6 +641,12 @@ impl ColumnName for columns::TransactionStatus {
     const NAME: &'static str = TRANSACTION_STATUS_CF;
 }
 
+impl SlotKeyedColumn for columns::TransactionStatus {
+    fn slot(index: Self::Index) -> Slot {
+        index.2
+    }
+}
+
 impl Column for columns::AddressSignatures {
     type Index = (u64, Pubkey, Slot, Signature);
 
@@ -530,6 +680,12 @@ impl ColumnName for columns::AddressSignatures {
     const NAME: &'static str = ADDRESS_SIGNATURES_CF;
 }
 
+impl SlotKeyedColumn for columns::AddressSignatures {
+    fn slot(index: Self::Index) -> Slot {
+        index.2
+    }
+}
+
 impl Column for columns::TransactionStatusIndex {
     type Index = u64;
 
@@ -556,6 +712,47 @@ impl ColumnName for columns::TransactionStatusIndex {
     const NAME: &'static str = TRANSACTION_STATUS_INDEX_CF;
 }
 
+impl Column for columns::BlockhashSlot {
+    type Index = (u64, Hash, Slot);
+
+    fn key((index, blockhash, slot): (u64, Hash, Slot)) -> Vec<u8> {
+        let mut key = vec![0; 8 + 32 + 8]; // size_of u64 + size_of Hash + size_of Slot
+        BigEndian::write_u64(&mut key[0..8], index);
+        key[8..40].clone_from_slice(blockhash.as_ref());
+        BigEndian::write_u64(&mut key[40..48], slot);
+        key
+    }
+
+    fn index(key: &[u8]) -> (u64, Hash, Slot) {
+        let index = BigEndian::read_u64(&key[0..8]);
+        let blockhash = Hash::new(&key[8..40]);
+        let slot = BigEndian::read_u64(&key[40..48]);
+        (index, blockhash, slot)
+    }
+
+    fn primary_index(index: Self::Index) -> u64 {
+        index.0
+    }
+
+    fn as_index(index: u64) -> Self::Index {
+        (index, Hash::default(), 0)
+    }
+}
+
+impl ColumnName for columns::BlockhashSlot {
+    const NAME: &'static str = BLOCKHASH_SLOT_CF;
+}
+
+impl SlotKeyedColumn for columns::BlockhashSlot {
+    fn slot(index: Self::Index) -> Slot {
+        index.2
+    }
+}
+
+impl TypedColumn for columns::BlockhashSlot {
+    type Type = bool;
+}
+
 impl SlotColumn for columns::Rewards {}
 impl ColumnName for columns::Rewards {
     const NAME: &'static str = REWARDS_CF;
@@ -572,6 +769,14 @@ impl TypedColumn for columns::Blocktime {
     type Type = UnixTimestamp;
 }
 
+impl SlotColumn for columns::BankHash {}
+impl ColumnName for columns::BankHash {
+    const NAME: &'static str = BANK_HASH_CF;
+}
+impl TypedColumn for columns::BankHash {
+    type Type = Hash;
+}
+
 impl SlotColumn for columns::PerfSamples {}
 impl ColumnName for columns::PerfSamples {
     const NAME: &'static str = PERF_SAMPLES_CF;
@@ -580,6 +785,55 @@ impl TypedColumn for columns::PerfSamples {
     type Type = blockstore_meta::PerfSample;
 }
 
+impl Column for columns::AccountHistory {
+    type Index = (u64, Pubkey, Slot);
+
+    fn key((index, pubkey, slot): (u64, Pubkey, Slot)) -> Vec<u8> {
+        let mut key = vec![0; 8 + 32 + 8]; // size_of u64 + size_of Pubkey + size_of Slot
+        BigEndian::write_u64(&mut key[0..8], index);
+        key[8..40].clone_from_slice(&pubkey.as_ref()[0..32]);
+        BigEndian::write_u64(&mut key[40..48], slot);
+        key
+    }
+
+    fn index(key: &[u8]) -> (u64, Pubkey, Slot) {
+        let index = BigEndian::read_u64(&key[0..8]);
+        let pubkey = Pubkey::new(&key[8..40]);
+        let slot = BigEndian::read_u64(&key[40..48]);
+        (index, pubkey, slot)
+    }
+
+    fn primary_index(index: Self::Index) -> u64 {
+        index.0
+    }
+
+    fn as_index(index: u64) -> Self::Index {
+        (index, Pubkey::default(), 0)
+    }
+}
+
+impl ColumnName for columns::AccountHistory {
+    const NAME: &'static str = ACCOUNT_HISTORY_CF;
+}
+
+impl SlotKeyedColumn for columns::AccountHistory {
+    fn slot(index: Self::Index) -> Slot {
+        index.2
+    }
+}
+
+impl TypedColumn for columns::AccountHistory {
+    type Type = Account;
+}
+
+impl SlotColumn for columns::AccountHistoryRoots {}
+impl ColumnName for columns::AccountHistoryRoots {
+    const NAME: &'static str = ACCOUNT_HISTORY_ROOTS_CF;
+}
+impl TypedColumn for columns::AccountHistoryRoots {
+    type Type = bool;
+}
+
 impl Column for columns::ShredCode {
     type Index = (u64, u64);
 
@@ -604,6 +858,12 @@ impl ColumnName for columns::ShredCode {
     const NAME: &'static str = CODE_SHRED_CF;
 }
 
+impl SlotKeyedColumn for columns::ShredCode {
+    fn slot(index: Self::Index) -> Slot {
+        index.0
+    }
+}
+
 impl Column for columns::ShredData {
     type Index = (u64, u64);
 
@@ -633,6 +893,12 @@ impl ColumnName for columns::ShredData {
     const NAME: &'static str = DATA_SHRED_CF;
 }
 
+impl SlotKeyedColumn for columns::ShredData {
+    fn slot(index: Self::Index) -> Slot {
+        index.0
+    }
+}
+
 impl SlotColumn for columns::Index {}
 impl ColumnName for columns::Index {
     const NAME: &'static str = INDEX_CF;
@@ -709,6 +975,12 @@ impl Column for columns::ErasureMeta {
 impl ColumnName for columns::ErasureMeta {
     const NAME: &'static str = ERASURE_META_CF;
 }
+
+impl SlotKeyedColumn for columns::ErasureMeta {
+    fn slot(index: Self::Index) -> Slot {
+        index.0
+    }
+}
 impl TypedColumn for columns::ErasureMeta {
     type Type = blockstore_meta::ErasureMeta;
 }
@@ -835,6 +1107,15 @@ impl Database {
     pub fn is_primary_access(&self) -> bool {
         self.backend.is_primary_access()
     }
+
+    /// Sets the slot below which compactions drop the data of the slot keyed columns
+    pub fn set_oldest_slot(&self, oldest_slot: Slot) {
+        self.backend.2.set(oldest_slot);
+    }
+
+    pub fn oldest_slot(&self) -> Slot {
+        self.backend.2.get()
+    }
 }
 
 impl<C> LedgerColumn<C>
@@ -1012,6 +1293,51 @@ impl<'a> WriteBatch<'a> {
     }
 }
 
+// Drops the entries of the slots below `oldest_slot` while compacting column `C`
+struct PurgedSlotFilter<C: SlotKeyedColumn> {
+    oldest_slot: Slot,
+    name: CString,
+    _phantom: PhantomData<C>,
+}
+
+impl<C: SlotKeyedColumn> CompactionFilter for PurgedSlotFilter<C> {
+    fn filter(&mut self, _level: u32, key: &[u8], _value: &[u8]) -> CompactionDecision {
+        if C::slot(C::index(key)) >= self.oldest_slot {
+            CompactionDecision::Keep
+        } else {
+            CompactionDecision::Remove
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        &self.name
+    }
+}
+
+struct PurgedSlotFilterFactory<C: SlotKeyedColumn> {
+    oldest_slot: OldestSlot,
+    name: CString,
+    _phantom: PhantomData<C>,
+}
+
+impl<C: SlotKeyedColumn> CompactionFilterFactory for PurgedSlotFilterFactory<C> {
+    type Filter = PurgedSlotFilter<C>;
+
+    fn create(&mut self, _context: CompactionFilterContext) -> Self::Filter {
+        let oldest_slot = self.oldest_slot.get();
+        PurgedSlotFilter {
+            oldest_slot,
+            name: CString::new(format!("purged_slot_filter({}, {})", C::NAME, oldest_slot))
+                .unwrap(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        &self.name
+    }
+}
+
 fn get_cf_options(access_type: &AccessType) -> Options {
     let mut options = Options::default();
     // 256 * 8 = 2GB. 6 of these columns should take at most 12GB of RAM
@@ -1033,6 +1359,21 @@ fn get_cf_options(access_type: &AccessType) -> Options {
     options
 }
 
+// The options of a column whose entries expire with their slot. These are the columns
+// `Blockstore::purge_slots` clears
+fn get_slot_keyed_cf_options<C: 'static + SlotKeyedColumn>(
+    access_type: &AccessType,
+    oldest_slot: &OldestSlot,
+) -> Options {
+    let mut options = get_cf_options(access_type);
+    options.set_compaction_filter_factory(PurgedSlotFilterFactory::<C> {
+        oldest_slot: oldest_slot.clone(),
+        name: CString::new(format!("purged_slot_filter_factory({})", C::NAME)).unwrap(),
+        _phantom: PhantomData,
+    });
+    options
+}
+
 fn get_db_options(access_type: &AccessType) -> Options {
     let mut options = Options::default();
     options.create_if_missing(true);
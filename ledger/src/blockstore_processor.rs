@@ -15,6 +15,8 @@ use solana_measure::{measure::Measure, thread_mem_usage};
 use solana_metrics::{datapoint_error, inc_new_counter_debug};
 use solana_rayon_threadlimit::get_thread_count;
 use solana_runtime::{
+    accounts_index::AccountsIndexConfig,
+    accounts_update_notifier_interface::AccountsUpdateNotifier,
     bank::{
         Bank, InnerInstructionsList, TransactionBalancesSet, TransactionLogMessages,
         TransactionProcessResult, TransactionResults,
@@ -320,6 +322,8 @@ pub struct ProcessOptions {
     pub new_hard_forks: Option<Vec<Slot>>,
     pub frozen_accounts: Vec<Pubkey>,
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
+    pub accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    pub accounts_index_config: Option<AccountsIndexConfig>,
 }
 
 pub fn process_blockstore(
@@ -344,6 +348,8 @@ pub fn process_blockstore(
         &opts.frozen_accounts,
         opts.debug_keys.clone(),
         Some(&crate::builtins::get(genesis_config.cluster_type)),
+        opts.accounts_update_notifier.clone(),
+        opts.accounts_index_config.clone(),
     );
     let bank0 = Arc::new(bank0);
     info!("processing ledger for slot 0...");
@@ -660,6 +666,7 @@ pub fn confirm_slot(
             &progress.last_entry,
             recyclers.clone(),
             bank.secp256k1_program_enabled(),
+            bank.ed25519_program_enabled(),
         );
         if entry_state.status() == EntryVerificationStatus::Failure {
             warn!("Ledger proof of history failed at slot: {}", slot);
@@ -2829,7 +2836,8 @@ pub mod tests {
         genesis_config: &GenesisConfig,
         account_paths: Vec<PathBuf>,
     ) -> EpochSchedule {
-        let bank = Bank::new_with_paths(&genesis_config, account_paths, &[], None, None);
+        let bank =
+            Bank::new_with_paths(&genesis_config, account_paths, &[], None, None, None, None);
         *bank.epoch_schedule()
     }
 
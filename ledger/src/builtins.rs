@@ -36,7 +36,7 @@ fn feature_builtins() -> Vec<(Builtin, Pubkey)> {
         .collect()
 }
 
-pub(crate) fn get(cluster_type: ClusterType) -> Builtins {
+pub fn get(cluster_type: ClusterType) -> Builtins {
     Builtins {
         genesis_builtins: genesis_builtins(cluster_type),
         feature_builtins: feature_builtins(),
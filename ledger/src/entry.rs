@@ -328,6 +328,7 @@ pub trait EntrySlice {
         start_hash: &Hash,
         recyclers: VerifyRecyclers,
         secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
     ) -> EntryVerificationState;
     fn verify(&self, start_hash: &Hash) -> bool;
     /// Checks that each entry tick has the correct number of hashes. Entry slices do not
@@ -336,12 +337,16 @@ pub trait EntrySlice {
     fn verify_tick_hash_count(&self, tick_hash_count: &mut u64, hashes_per_tick: u64) -> bool;
     /// Counts tick entries
     fn tick_count(&self) -> u64;
-    fn verify_transaction_signatures(&self, secp256k1_program_enabled: bool) -> bool;
+    fn verify_transaction_signatures(
+        &self,
+        secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
+    ) -> bool;
 }
 
 impl EntrySlice for [Entry] {
     fn verify(&self, start_hash: &Hash) -> bool {
-        self.start_verify(start_hash, VerifyRecyclers::default(), true)
+        self.start_verify(start_hash, VerifyRecyclers::default(), true, true)
             .finish_verify(self)
     }
 
@@ -489,15 +494,24 @@ impl EntrySlice for [Entry] {
         }
     }
 
-    fn verify_transaction_signatures(&self, secp256k1_program_enabled: bool) -> bool {
+    fn verify_transaction_signatures(
+        &self,
+        secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
+    ) -> bool {
         PAR_THREAD_POOL.with(|thread_pool| {
             thread_pool.borrow().install(|| {
                 self.par_iter().all(|e| {
                     e.transactions.par_iter().all(|transaction| {
                         let sig_verify = transaction.verify().is_ok();
                         if sig_verify
-                            && secp256k1_program_enabled
-                            && transaction.verify_precompiles().is_err()
+                            && (secp256k1_program_enabled || ed25519_program_enabled)
+                            && transaction
+                                .verify_precompiles_with_features(
+                                    secp256k1_program_enabled,
+                                    ed25519_program_enabled,
+                                )
+                                .is_err()
                         {
                             return false;
                         }
@@ -513,9 +527,11 @@ impl EntrySlice for [Entry] {
         start_hash: &Hash,
         recyclers: VerifyRecyclers,
         secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
     ) -> EntryVerificationState {
         let start = Instant::now();
-        let res = self.verify_transaction_signatures(secp256k1_program_enabled);
+        let res =
+            self.verify_transaction_signatures(secp256k1_program_enabled, ed25519_program_enabled);
         let transaction_duration_us = timing::duration_as_us(&start.elapsed());
         if !res {
             return EntryVerificationState {
@@ -120,6 +120,13 @@ impl Session {
 
         Ok(())
     }
+
+    /// Recover data + coding blocks into both data and coding blocks
+    pub fn reconstruct_blocks(&self, blocks: &mut [(&mut [u8], bool)]) -> Result<()> {
+        self.0.reconstruct(blocks)?;
+
+        Ok(())
+    }
 }
 
 impl Default for Session {
@@ -7,16 +7,17 @@ use bincode::config::Options;
 use core::cell::RefCell;
 use rayon::{
     iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator},
-    slice::ParallelSlice,
+    slice::{ParallelSlice, ParallelSliceMut},
     ThreadPool,
 };
 use serde::{Deserialize, Serialize};
+use solana_merkle_tree::MerkleTree;
 use solana_metrics::datapoint_debug;
 use solana_perf::packet::Packet;
 use solana_rayon_threadlimit::get_thread_count;
 use solana_sdk::{
     clock::Slot,
-    hash::Hash,
+    hash::{hashv, Hash},
     packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
     signature::{Keypair, Signature, Signer},
@@ -51,6 +52,23 @@ pub const OFFSET_OF_SHRED_SLOT: usize = SIZE_OF_SIGNATURE + SIZE_OF_SHRED_TYPE;
 pub const OFFSET_OF_SHRED_INDEX: usize = OFFSET_OF_SHRED_SLOT + SIZE_OF_SHRED_SLOT;
 pub const SHRED_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - SIZE_OF_NONCE;
 
+/// Merkle shreds end with a proof region: one byte holding the number of proof entries,
+/// followed by the sibling hashes from the shred's leaf up to the erasure batch's root.
+/// An erasure batch has at most 2 * MAX_DATA_SHREDS_PER_FEC_BLOCK shreds, so the tree is at
+/// most MAX_MERKLE_PROOF_ENTRIES deep. The leader signs the root instead of every shred.
+pub const SIZE_OF_MERKLE_PROOF_ENTRY: usize = 32;
+pub const MAX_MERKLE_PROOF_ENTRIES: usize = 6;
+pub const SIZE_OF_MERKLE_PROOF: usize = 1 + MAX_MERKLE_PROOF_ENTRIES * SIZE_OF_MERKLE_PROOF_ENTRY;
+pub const OFFSET_OF_MERKLE_PROOF: usize = SHRED_PAYLOAD_SIZE - SIZE_OF_MERKLE_PROOF;
+/// Erasure coding of Merkle shreds skips the signature, which is shared by the whole batch,
+/// and the proof, which differs for every shred
+pub const SIZE_OF_MERKLE_ERASURE_SHARD: usize =
+    OFFSET_OF_MERKLE_PROOF - SIZE_OF_COMMON_SHRED_HEADER - SIZE_OF_CODING_SHRED_HEADER;
+pub const SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD: usize = SIZE_OF_SIGNATURE
+    + SIZE_OF_MERKLE_ERASURE_SHARD
+    - SIZE_OF_COMMON_SHRED_HEADER
+    - SIZE_OF_DATA_SHRED_HEADER;
+
 thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
                     .num_threads(get_thread_count())
                     .thread_name(|ix| format!("shredder_{}", ix))
@@ -60,6 +78,8 @@ thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::
 /// The constants that define if a shred is data or coding
 pub const DATA_SHRED: u8 = 0b1010_0101;
 pub const CODING_SHRED: u8 = 0b0101_1010;
+pub const MERKLE_DATA_SHRED: u8 = 0b1001_0110;
+pub const MERKLE_CODING_SHRED: u8 = 0b0110_1001;
 
 pub const MAX_DATA_SHREDS_PER_FEC_BLOCK: u32 = 32;
 pub const RECOMMENDED_FEC_RATE: f32 = 1.0;
@@ -98,6 +118,20 @@ impl Default for ShredType {
     }
 }
 
+impl ShredType {
+    pub fn is_data(&self) -> bool {
+        self.0 == DATA_SHRED || self.0 == MERKLE_DATA_SHRED
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.0 == CODING_SHRED || self.0 == MERKLE_CODING_SHRED
+    }
+
+    pub fn is_merkle(&self) -> bool {
+        self.0 == MERKLE_DATA_SHRED || self.0 == MERKLE_CODING_SHRED
+    }
+}
+
 /// A common header that is present in data and code shred headers
 #[derive(Serialize, Clone, Deserialize, Default, PartialEq, Debug)]
 pub struct ShredCommonHeader {
@@ -249,7 +283,7 @@ impl Shred {
         // the `expected_data_size`.
         assert!(payload.len() >= expected_data_size);
         payload.truncate(expected_data_size);
-        let shred = if common_header.shred_type == ShredType(CODING_SHRED) {
+        let shred = if common_header.shred_type.is_code() {
             let coding_header: CodingShredHeader =
                 Self::deserialize_obj(&mut start, SIZE_OF_CODING_SHRED_HEADER, &payload)?;
             Self {
@@ -258,7 +292,7 @@ impl Shred {
                 coding_header,
                 payload,
             }
-        } else if common_header.shred_type == ShredType(DATA_SHRED) {
+        } else if common_header.shred_type.is_data() {
             let size_of_data_shred_header = SIZE_OF_DATA_SHRED_HEADER;
             let data_header: DataShredHeader =
                 Self::deserialize_obj(&mut start, size_of_data_shred_header, &payload)?;
@@ -295,7 +329,7 @@ impl Shred {
             &common_header,
         )
         .expect("Failed to write header into shred buffer");
-        if common_header.shred_type == ShredType(DATA_SHRED) {
+        if common_header.shred_type.is_data() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_DATA_SHRED_HEADER,
@@ -303,7 +337,7 @@ impl Shred {
                 &data_header,
             )
             .expect("Failed to write data header into shred buffer");
-        } else if common_header.shred_type == ShredType(CODING_SHRED) {
+        } else if common_header.shred_type.is_code() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_CODING_SHRED_HEADER,
@@ -370,11 +404,36 @@ impl Shred {
         .unwrap();
     }
 
+    fn set_shred_type(&mut self, shred_type: ShredType) {
+        self.common_header.shred_type = shred_type;
+        Self::serialize_obj_into(
+            &mut 0,
+            SIZE_OF_COMMON_SHRED_HEADER,
+            &mut self.payload,
+            &self.common_header,
+        )
+        .unwrap();
+    }
+
     pub fn signature(&self) -> Signature {
         self.common_header.signature
     }
 
+    fn set_signature(&mut self, signature: Signature) {
+        self.payload[..SIZE_OF_SIGNATURE].copy_from_slice(signature.as_ref());
+        self.common_header.signature = signature;
+    }
+
     pub fn seed(&self) -> [u8; 32] {
+        if self.is_merkle() {
+            // All shreds of a Merkle erasure batch share the same signature
+            return hashv(&[
+                self.common_header.signature.as_ref(),
+                &[self.common_header.shred_type.0],
+                &self.index().to_le_bytes(),
+            ])
+            .to_bytes();
+        }
         let mut seed = [0; 32];
         let seed_len = seed.len();
         let sig = self.common_header.signature.as_ref();
@@ -383,10 +442,13 @@ impl Shred {
     }
 
     pub fn is_data(&self) -> bool {
-        self.common_header.shred_type == ShredType(DATA_SHRED)
+        self.common_header.shred_type.is_data()
     }
     pub fn is_code(&self) -> bool {
-        self.common_header.shred_type == ShredType(CODING_SHRED)
+        self.common_header.shred_type.is_code()
+    }
+    pub fn is_merkle(&self) -> bool {
+        self.common_header.shred_type.is_merkle()
     }
 
     pub fn last_in_slot(&self) -> bool {
@@ -429,9 +491,71 @@ impl Shred {
     }
 
     pub fn verify(&self, pubkey: &Pubkey) -> bool {
+        if self.is_merkle() {
+            return match self.merkle_root() {
+                Some(root) => self.signature().verify(pubkey.as_ref(), root.as_ref()),
+                None => false,
+            };
+        }
         self.signature()
             .verify(pubkey.as_ref(), &self.payload[SIZE_OF_SIGNATURE..])
     }
+
+    /// Recomputes the root of this Merkle shred's erasure batch from its contents and proof
+    pub fn merkle_root(&self) -> Option<Hash> {
+        Self::merkle_root_from_payload(&self.payload)
+    }
+
+    /// Like `merkle_root`, but reads the headers straight from a serialized shred, e.g. a packet
+    pub fn merkle_root_from_payload(payload: &[u8]) -> Option<Hash> {
+        if payload.len() < SHRED_PAYLOAD_SIZE {
+            return None;
+        }
+        let mut start = 0;
+        let common_header: ShredCommonHeader =
+            Self::deserialize_obj(&mut start, SIZE_OF_COMMON_SHRED_HEADER, payload).ok()?;
+        let leaf_index = if common_header.shred_type == ShredType(MERKLE_DATA_SHRED) {
+            common_header
+                .index
+                .checked_sub(common_header.fec_set_index)? as usize
+        } else if common_header.shred_type == ShredType(MERKLE_CODING_SHRED) {
+            let coding_header: CodingShredHeader =
+                Self::deserialize_obj(&mut start, SIZE_OF_CODING_SHRED_HEADER, payload).ok()?;
+            usize::from(coding_header.num_data_shreds) + usize::from(coding_header.position)
+        } else {
+            return None;
+        };
+        let proof = &payload[OFFSET_OF_MERKLE_PROOF..SHRED_PAYLOAD_SIZE];
+        let num_entries = usize::from(proof[0]);
+        if num_entries > MAX_MERKLE_PROOF_ENTRIES || leaf_index >> num_entries != 0 {
+            return None;
+        }
+        let siblings: Vec<Hash> = proof[1..]
+            .chunks_exact(SIZE_OF_MERKLE_PROOF_ENTRY)
+            .take(num_entries)
+            .map(Hash::new)
+            .collect();
+        Some(MerkleTree::compute_root_from_path(
+            Self::merkle_leaf(payload),
+            leaf_index,
+            &siblings,
+        ))
+    }
+
+    /// The part of a Merkle shred covered by its leaf hash
+    fn merkle_leaf(payload: &[u8]) -> &[u8] {
+        &payload[SIZE_OF_SIGNATURE..OFFSET_OF_MERKLE_PROOF]
+    }
+
+    fn set_merkle_proof(&mut self, proof: &[Hash]) {
+        assert!(proof.len() <= MAX_MERKLE_PROOF_ENTRIES);
+        let buf = &mut self.payload[OFFSET_OF_MERKLE_PROOF..SHRED_PAYLOAD_SIZE];
+        buf[0] = proof.len() as u8;
+        buf[1..]
+            .chunks_exact_mut(SIZE_OF_MERKLE_PROOF_ENTRY)
+            .zip(proof)
+            .for_each(|(entry, hash)| entry.copy_from_slice(hash.as_ref()));
+    }
 }
 
 #[derive(Debug)]
@@ -590,6 +714,126 @@ impl Shredder {
         coding_shreds
     }
 
+    /// Like `entries_to_shreds`, but generates Merkle shreds. Every data and coding shred of an
+    /// erasure batch carries a proof up to the batch's Merkle root, and the root is the only
+    /// thing that gets signed.
+    pub fn entries_to_merkle_shreds(
+        &self,
+        entries: &[Entry],
+        is_last_in_slot: bool,
+        next_shred_index: u32,
+    ) -> (Vec<Shred>, Vec<Shred>, u32) {
+        let now = Instant::now();
+        let serialized_shreds =
+            bincode::serialize(entries).expect("Expect to serialize all entries");
+        let serialize_time = now.elapsed().as_millis();
+
+        let now = Instant::now();
+
+        let no_header_size = SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD;
+        let num_shreds = (serialized_shreds.len() + no_header_size - 1) / no_header_size;
+        let last_shred_index = next_shred_index + num_shreds as u32 - 1;
+        // 1) Generate unsigned data shreds
+        let mut data_shreds: Vec<Shred> = PAR_THREAD_POOL.with(|thread_pool| {
+            thread_pool.borrow().install(|| {
+                serialized_shreds
+                    .par_chunks(no_header_size)
+                    .enumerate()
+                    .map(|(i, shred_data)| {
+                        let shred_index = next_shred_index + i as u32;
+                        let fec_set_index =
+                            shred_index - (i % MAX_DATA_SHREDS_PER_FEC_BLOCK as usize) as u32;
+                        let (is_last_in_fec_set, is_last_in_slot) = {
+                            if shred_index == last_shred_index {
+                                (true, is_last_in_slot)
+                            } else {
+                                (false, false)
+                            }
+                        };
+
+                        let mut shred = Shred::new_from_data(
+                            self.slot,
+                            shred_index,
+                            (self.slot - self.parent_slot) as u16,
+                            Some(shred_data),
+                            is_last_in_fec_set,
+                            is_last_in_slot,
+                            self.reference_tick,
+                            self.version,
+                            fec_set_index,
+                        );
+                        shred.set_shred_type(ShredType(MERKLE_DATA_SHRED));
+                        shred
+                    })
+                    .collect()
+            })
+        });
+        let gen_data_time = now.elapsed().as_millis();
+
+        let now = Instant::now();
+        // 2) Generate coding shreds, then build and sign the Merkle tree of each erasure batch
+        let coding_shreds: Vec<Shred> = PAR_THREAD_POOL.with(|thread_pool| {
+            thread_pool.borrow().install(|| {
+                data_shreds
+                    .par_chunks_mut(MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
+                    .flat_map(|data_shred_batch| self.finish_merkle_batch(data_shred_batch))
+                    .collect()
+            })
+        });
+        let gen_coding_and_sign_time = now.elapsed().as_millis();
+
+        datapoint_debug!(
+            "shredding-stats",
+            ("slot", self.slot as i64, i64),
+            ("num_data_shreds", data_shreds.len() as i64, i64),
+            ("num_coding_shreds", coding_shreds.len() as i64, i64),
+            ("serializing", serialize_time as i64, i64),
+            ("gen_data", gen_data_time as i64, i64),
+            ("gen_coding_and_sign", gen_coding_and_sign_time as i64, i64),
+        );
+        (data_shreds, coding_shreds, last_shred_index + 1)
+    }
+
+    /// Generates the coding shreds for one erasure batch of Merkle data shreds, and fills in the
+    /// signed root and the proof of every shred in the batch
+    fn finish_merkle_batch(&self, data_shred_batch: &mut [Shred]) -> Vec<Shred> {
+        let mut coding_shreds = Self::generate_merkle_coding_shreds(
+            self.slot,
+            self.fec_rate,
+            data_shred_batch,
+            self.version,
+        );
+        let (root, proofs) = {
+            let leaves: Vec<&[u8]> = data_shred_batch
+                .iter()
+                .chain(&coding_shreds)
+                .map(|shred| Shred::merkle_leaf(&shred.payload))
+                .collect();
+            Self::merkle_root_and_proofs(&leaves)
+        };
+        let signature = self.keypair.sign_message(root.as_ref());
+        data_shred_batch
+            .iter_mut()
+            .chain(coding_shreds.iter_mut())
+            .zip(&proofs)
+            .for_each(|(shred, proof)| {
+                shred.set_signature(signature);
+                shred.set_merkle_proof(proof);
+            });
+        coding_shreds
+    }
+
+    fn merkle_root_and_proofs(leaves: &[&[u8]]) -> (Hash, Vec<Vec<Hash>>) {
+        let tree = MerkleTree::new(leaves);
+        let root = *tree
+            .get_root()
+            .expect("Merkle tree of an empty erasure batch");
+        let proofs = (0..leaves.len())
+            .map(|i| tree.find_path(i).unwrap().siblings().cloned().collect())
+            .collect();
+        (root, proofs)
+    }
+
     pub fn sign_shred(signer: &Keypair, shred: &mut Shred) {
         let signature = signer.sign_message(&shred.payload[SIZE_OF_SIGNATURE..]);
         bincode::serialize_into(&mut shred.payload[..SIZE_OF_SIGNATURE], &signature)
@@ -703,6 +947,84 @@ impl Shredder {
         }
     }
 
+    fn new_empty_merkle_coding_shred(
+        slot: Slot,
+        index: u32,
+        fec_set_index: u32,
+        num_data: usize,
+        num_code: usize,
+        position: usize,
+        version: u16,
+    ) -> Shred {
+        let (mut header, coding_header) = Self::new_coding_shred_header(
+            slot,
+            index,
+            fec_set_index,
+            num_data,
+            num_code,
+            position,
+            version,
+        );
+        header.shred_type = ShredType(MERKLE_CODING_SHRED);
+        Shred::new_empty_from_header(header, DataShredHeader::default(), coding_header)
+    }
+
+    /// Generates the unsigned coding shreds for a batch of Merkle data shreds
+    fn generate_merkle_coding_shreds(
+        slot: Slot,
+        fec_rate: f32,
+        data_shred_batch: &[Shred],
+        version: u16,
+    ) -> Vec<Shred> {
+        assert!(!data_shred_batch.is_empty());
+        if fec_rate == 0.0 {
+            return vec![];
+        }
+        let num_data = data_shred_batch.len();
+        let num_coding = Self::calculate_num_coding_shreds(num_data, fec_rate);
+        let session = Session::new(num_data, num_coding).expect("Failed to create erasure session");
+        let start_index = data_shred_batch[0].common_header.index;
+
+        let data_ptrs: Vec<_> = data_shred_batch
+            .iter()
+            .map(|data| Self::merkle_data_shard(&data.payload))
+            .collect();
+        let mut coding_shreds: Vec<_> = (0..num_coding)
+            .map(|i| {
+                Self::new_empty_merkle_coding_shred(
+                    slot,
+                    start_index + i as u32,
+                    start_index,
+                    num_data,
+                    num_coding,
+                    i,
+                    version,
+                )
+            })
+            .collect();
+        let mut coding_ptrs: Vec<_> = coding_shreds
+            .iter_mut()
+            .map(|shred| Self::merkle_coding_shard_mut(&mut shred.payload))
+            .collect();
+        session
+            .encode(&data_ptrs, coding_ptrs.as_mut_slice())
+            .expect("Failed in erasure encode");
+        coding_shreds
+    }
+
+    fn merkle_data_shard(payload: &[u8]) -> &[u8] {
+        &payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD]
+    }
+
+    fn merkle_data_shard_mut(payload: &mut [u8]) -> &mut [u8] {
+        &mut payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD]
+    }
+
+    fn merkle_coding_shard_mut(payload: &mut [u8]) -> &mut [u8] {
+        &mut payload
+            [SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_CODING_SHRED_HEADER..OFFSET_OF_MERKLE_PROOF]
+    }
+
     fn calculate_num_coding_shreds(num_data_shreds: usize, fec_rate: f32) -> usize {
         if num_data_shreds == 0 {
             0
@@ -750,6 +1072,16 @@ impl Shredder {
         slot: Slot,
     ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
         Self::verify_consistent_shred_payload_sizes(&"try_recovery()", &shreds)?;
+        if shreds[0].is_merkle() {
+            return Self::try_merkle_recovery(
+                shreds,
+                num_data,
+                num_coding,
+                first_index,
+                first_code_index,
+                slot,
+            );
+        }
         let mut recovered_data = vec![];
         let fec_set_size = num_data + num_coding;
 
@@ -837,6 +1169,123 @@ impl Shredder {
         Ok(recovered_data)
     }
 
+    /// Recovers the missing data shreds of a Merkle erasure batch. The whole batch, coding shreds
+    /// included, is reconstructed so that its Merkle tree can be rebuilt: recovered shreds are
+    /// only returned if the rebuilt root matches the signed root of the shreds that were present,
+    /// in which case they get their proofs back.
+    fn try_merkle_recovery(
+        shreds: Vec<Shred>,
+        num_data: usize,
+        num_coding: usize,
+        first_index: usize,
+        first_code_index: usize,
+        slot: Slot,
+    ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
+        let fec_set_size = num_data + num_coding;
+        if num_coding == 0 || shreds.len() >= fec_set_size {
+            return Ok(vec![]);
+        }
+        let signature = shreds[0].signature();
+        let version = shreds[0].version();
+        let root = match shreds[0].merkle_root() {
+            Some(root) => root,
+            None => return Ok(vec![]),
+        };
+
+        let mut present = vec![false; fec_set_size];
+        let mut shred_bufs: Vec<Vec<u8>> = vec![vec![]; fec_set_size];
+        for shred in shreds {
+            if !shred.is_merkle() {
+                continue;
+            }
+            let position = Self::get_shred_index(&shred, num_data, first_index, first_code_index)
+                .checked_sub(first_index);
+            match position {
+                Some(position) if position < fec_set_size && !present[position] => {
+                    present[position] = true;
+                    shred_bufs[position] = shred.payload;
+                }
+                _ => (),
+            }
+        }
+        if present.iter().filter(|was_present| **was_present).count() < num_data {
+            return Err(reed_solomon_erasure::Error::TooFewShardsPresent);
+        }
+        for (position, buf) in shred_bufs.iter_mut().enumerate() {
+            if present[position] {
+                continue;
+            }
+            *buf = if position < num_data {
+                vec![0; SHRED_PAYLOAD_SIZE]
+            } else {
+                let coding_position = position - num_data;
+                Self::new_empty_merkle_coding_shred(
+                    slot,
+                    (first_code_index + coding_position) as u32,
+                    first_index as u32,
+                    num_data,
+                    num_coding,
+                    coding_position,
+                    version,
+                )
+                .payload
+            };
+        }
+
+        let session = Session::new(num_data, num_coding)?;
+        let mut blocks: Vec<(&mut [u8], bool)> = shred_bufs
+            .iter_mut()
+            .enumerate()
+            .map(|(position, buf)| {
+                if position < num_data {
+                    Self::merkle_data_shard_mut(buf)
+                } else {
+                    Self::merkle_coding_shard_mut(buf)
+                }
+            })
+            .zip(present.clone())
+            .collect();
+        session.reconstruct_blocks(&mut blocks)?;
+
+        let (recovered_root, mut proofs) = {
+            let leaves: Vec<&[u8]> = shred_bufs
+                .iter()
+                .map(|buf| Shred::merkle_leaf(buf))
+                .collect();
+            Self::merkle_root_and_proofs(&leaves)
+        };
+        if recovered_root != root {
+            error!(
+                "Recovered shreds of slot {} erasure set {} do not match the signed Merkle root",
+                slot, first_index
+            );
+            return Ok(vec![]);
+        }
+
+        let recovered_data = shred_bufs
+            .into_iter()
+            .zip(proofs.drain(..))
+            .take(num_data)
+            .enumerate()
+            .filter(|(position, _)| !present[*position])
+            .filter_map(|(position, (mut buf, proof))| {
+                buf[..SIZE_OF_SIGNATURE].copy_from_slice(signature.as_ref());
+                let mut shred = Shred::new_from_serialized_shred(buf).ok()?;
+                shred.set_merkle_proof(&proof);
+                if shred.is_merkle()
+                    && shred.is_data()
+                    && shred.slot() == slot
+                    && shred.index() as usize == first_index + position
+                {
+                    Some(shred)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Ok(recovered_data)
+    }
+
     /// Combines all shreds to recreate the original buffer
     pub fn deshred(shreds: &[Shred]) -> std::result::Result<Vec<u8>, reed_solomon_erasure::Error> {
         let num_data = shreds.len();
@@ -874,11 +1323,15 @@ impl Shredder {
     }
 
     fn reassemble_payload(num_data: usize, data_shred_bufs: Vec<&Vec<u8>>) -> Vec<u8> {
-        let valid_data_len = SHRED_PAYLOAD_SIZE - SIZE_OF_DATA_SHRED_IGNORED_TAIL;
         data_shred_bufs[..num_data]
             .iter()
             .flat_map(|data| {
                 let offset = SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_DATA_SHRED_HEADER;
+                let valid_data_len = if data[OFFSET_OF_SHRED_TYPE] == MERKLE_DATA_SHRED {
+                    SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD
+                } else {
+                    SHRED_PAYLOAD_SIZE - SIZE_OF_DATA_SHRED_IGNORED_TAIL
+                };
                 data[offset..valid_data_len].iter()
             })
             .cloned()
@@ -1002,6 +1455,12 @@ pub mod tests {
             SIZE_OF_SHRED_INDEX,
             bincode::serialized_size(&ShredCommonHeader::default().index).unwrap() as usize
         );
+        assert_eq!(SIZE_OF_MERKLE_PROOF_ENTRY, size_of::<Hash>());
+        assert_eq!(
+            2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize,
+            1 << MAX_MERKLE_PROOF_ENTRIES
+        );
+        assert!((SIZE_OF_SIGNATURE..OFFSET_OF_MERKLE_PROOF).len() >= SIZE_OF_MERKLE_ERASURE_SHARD);
     }
 
     fn verify_test_code_shred(shred: &Shred, index: u32, slot: Slot, pk: &Pubkey, verify: bool) {
@@ -1648,4 +2107,139 @@ pub mod tests {
             })
         );
     }
+
+    fn make_merkle_shreds(
+        keypair: &Arc<Keypair>,
+        num_entries: usize,
+        fec_rate: f32,
+    ) -> (Vec<Entry>, Vec<Shred>, Vec<Shred>) {
+        let shredder = Shredder::new(11, 10, fec_rate, keypair.clone(), 0, 7)
+            .expect("Failed in creating shredder");
+        let entries: Vec<_> = (0..num_entries)
+            .map(|_| {
+                let keypair0 = Keypair::new();
+                let keypair1 = Keypair::new();
+                let tx0 =
+                    system_transaction::transfer(&keypair0, &keypair1.pubkey(), 1, Hash::default());
+                Entry::new(&Hash::default(), 1, vec![tx0])
+            })
+            .collect();
+        let (data_shreds, coding_shreds, next_index) =
+            shredder.entries_to_merkle_shreds(&entries, true, 3);
+        assert_eq!(next_index, 3 + data_shreds.len() as u32);
+        (entries, data_shreds, coding_shreds)
+    }
+
+    #[test]
+    fn test_merkle_shreds() {
+        let keypair = Arc::new(Keypair::new());
+        let (entries, data_shreds, coding_shreds) = make_merkle_shreds(&keypair, 500, 1.0);
+        assert!(data_shreds.len() > MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+        assert_eq!(data_shreds.len(), coding_shreds.len());
+
+        let wrong_keypair = Keypair::new();
+        for shred in data_shreds.iter().chain(&coding_shreds) {
+            assert!(shred.is_merkle());
+            assert!(shred.verify(&keypair.pubkey()));
+            assert!(!shred.verify(&wrong_keypair.pubkey()));
+            let deserialized_shred =
+                Shred::new_from_serialized_shred(shred.payload.clone()).unwrap();
+            assert_eq!(deserialized_shred, *shred);
+        }
+        // Only one signature per erasure batch
+        let signatures: HashSet<_> = data_shreds.iter().map(Shred::signature).collect();
+        assert_eq!(
+            signatures.len(),
+            (data_shreds.len() + MAX_DATA_SHREDS_PER_FEC_BLOCK as usize - 1)
+                / MAX_DATA_SHREDS_PER_FEC_BLOCK as usize
+        );
+        let seeds: HashSet<_> = data_shreds.iter().map(Shred::seed).collect();
+        assert_eq!(seeds.len(), data_shreds.len());
+
+        // Any change to the shred contents invalidates it
+        let mut shred = data_shreds[5].clone();
+        shred.payload[SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_DATA_SHRED_HEADER] ^= 1;
+        assert!(!shred.verify(&keypair.pubkey()));
+        let mut shred = coding_shreds[5].clone();
+        shred.payload[OFFSET_OF_MERKLE_PROOF + 1] ^= 1;
+        assert!(!shred.verify(&keypair.pubkey()));
+
+        let deshred_payload = Shredder::deshred(&data_shreds).unwrap();
+        let deshred_entries: Vec<Entry> = bincode::deserialize(&deshred_payload).unwrap();
+        assert_eq!(entries, deshred_entries);
+    }
+
+    #[test]
+    fn test_merkle_shreds_without_coding() {
+        let keypair = Arc::new(Keypair::new());
+        let (entries, data_shreds, coding_shreds) = make_merkle_shreds(&keypair, 5, 0.0);
+        assert!(coding_shreds.is_empty());
+        assert!(data_shreds
+            .iter()
+            .all(|shred| shred.verify(&keypair.pubkey())));
+        let deshred_payload = Shredder::deshred(&data_shreds).unwrap();
+        let deshred_entries: Vec<Entry> = bincode::deserialize(&deshred_payload).unwrap();
+        assert_eq!(entries, deshred_entries);
+    }
+
+    #[test]
+    fn test_merkle_recovery() {
+        let keypair = Arc::new(Keypair::new());
+        let (_entries, data_shreds, coding_shreds) = make_merkle_shreds(&keypair, 100, 1.0);
+        let num_data = data_shreds.len();
+        assert!(num_data > 4 && num_data <= MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+        let first_index = data_shreds[0].index() as usize;
+        let first_code_index = coding_shreds[0].index() as usize;
+
+        // Drop every other data shred, and one coding shred
+        let shreds: Vec<_> = data_shreds
+            .iter()
+            .step_by(2)
+            .chain(coding_shreds.iter().skip(1))
+            .cloned()
+            .collect();
+        let recovered = Shredder::try_recovery(
+            shreds.clone(),
+            num_data,
+            coding_shreds.len(),
+            first_index,
+            first_code_index,
+            11,
+        )
+        .unwrap();
+        let expected: Vec<_> = data_shreds.iter().skip(1).step_by(2).cloned().collect();
+        assert_eq!(recovered, expected);
+        assert!(recovered
+            .iter()
+            .all(|shred| shred.verify(&keypair.pubkey())));
+
+        // A coding shred which does not belong to the signed tree poisons the recovery
+        let mut bad_shreds = shreds;
+        let last = bad_shreds.len() - 1;
+        bad_shreds[last].payload[SIZE_OF_COMMON_SHRED_HEADER + SIZE_OF_CODING_SHRED_HEADER] ^= 1;
+        let recovered = Shredder::try_recovery(
+            bad_shreds,
+            num_data,
+            coding_shreds.len(),
+            first_index,
+            first_code_index,
+            11,
+        )
+        .unwrap();
+        assert!(recovered.is_empty());
+
+        // Not enough shreds
+        let shreds: Vec<_> = data_shreds.iter().skip(1).cloned().collect();
+        assert_matches!(
+            Shredder::try_recovery(
+                shreds[..num_data / 2].to_vec(),
+                num_data,
+                coding_shreds.len(),
+                first_index,
+                first_code_index,
+                11,
+            ),
+            Err(reed_solomon_erasure::Error::TooFewShardsPresent)
+        );
+    }
 }
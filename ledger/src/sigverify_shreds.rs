@@ -1,5 +1,5 @@
 #![allow(clippy::implicit_hasher)]
-use crate::shred::{ShredType, SIZE_OF_NONCE};
+use crate::shred::{Shred, ShredType, OFFSET_OF_SHRED_TYPE, SIZE_OF_NONCE};
 use rayon::{
     iter::{
         IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
@@ -18,14 +18,130 @@ use solana_perf::{
 use solana_rayon_threadlimit::get_thread_count;
 use solana_sdk::{
     clock::Slot,
+    hash::Hash,
     pubkey::Pubkey,
     signature::Signature,
     signature::{Keypair, Signer},
 };
-use std::sync::Arc;
-use std::{collections::HashMap, mem::size_of};
+use std::sync::{Arc, RwLock};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+};
 
 pub const SIGN_SHRED_GPU_MIN: usize = 256;
+const MAX_MERKLE_ROOT_CACHE_SLOTS: usize = 1024;
+
+/// Merkle roots whose signature has already been verified against the slot leader. All the
+/// shreds of a Merkle erasure batch share one signed root, so only the first of them pays for
+/// an ed25519 verification.
+#[derive(Default)]
+pub struct MerkleRootCache(RwLock<BTreeMap<Slot, HashSet<(Hash, Signature)>>>);
+
+impl MerkleRootCache {
+    fn contains(&self, slot: Slot, root: &Hash, signature: &Signature) -> bool {
+        self.0
+            .read()
+            .unwrap()
+            .get(&slot)
+            .map(|roots| roots.contains(&(*root, *signature)))
+            .unwrap_or(false)
+    }
+
+    fn insert(&self, slot: Slot, root: Hash, signature: Signature) {
+        let mut slots = self.0.write().unwrap();
+        slots
+            .entry(slot)
+            .or_insert_with(HashSet::new)
+            .insert((root, signature));
+        while slots.len() > MAX_MERKLE_ROOT_CACHE_SLOTS {
+            let oldest_slot = *slots.keys().next().unwrap();
+            slots.remove(&oldest_slot);
+        }
+    }
+}
+
+fn is_merkle_packet(packet: &Packet) -> bool {
+    !packet.meta.discard
+        && packet.meta.size > OFFSET_OF_SHRED_TYPE
+        && ShredType(packet.data[OFFSET_OF_SHRED_TYPE]).is_merkle()
+}
+
+/// Returns the slot, the recomputed Merkle root and the signature of a Merkle shred packet
+fn merkle_packet_root(packet: &Packet) -> Option<(Slot, Hash, Signature)> {
+    let slot_start = size_of::<Signature>() + size_of::<ShredType>();
+    let slot_end = slot_start + size_of::<u64>();
+    if packet.meta.size < slot_end {
+        return None;
+    }
+    let slot: Slot = limited_deserialize(&packet.data[slot_start..slot_end]).ok()?;
+    let root = Shred::merkle_root_from_payload(&packet.data[..packet.meta.size])?;
+    let signature = Signature::new(&packet.data[..size_of::<Signature>()]);
+    Some((slot, root, signature))
+}
+
+/// Verifies the Merkle shreds of `batches`, overwriting their entries in `rvs`. Every distinct
+/// root is verified once, and is then cached for the rest of its erasure batch.
+fn verify_merkle_shreds(
+    batches: &[Packets],
+    slot_leaders: &HashMap<u64, [u8; 32]>,
+    merkle_root_cache: &MerkleRootCache,
+    rvs: &mut [Vec<u8>],
+) {
+    use rayon::prelude::*;
+    let roots: Vec<Vec<Option<(Slot, Hash, Signature)>>> = SIGVERIFY_THREAD_POOL.install(|| {
+        batches
+            .into_par_iter()
+            .map(|p| {
+                p.packets
+                    .par_iter()
+                    .map(|packet| {
+                        if is_merkle_packet(packet) {
+                            merkle_packet_root(packet)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    });
+    let unverified: HashSet<(Slot, Hash, Signature)> = roots
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|(slot, root, signature)| !merkle_root_cache.contains(*slot, root, signature))
+        .cloned()
+        .collect();
+    let count = unverified.len();
+    let verified: Vec<(Slot, Hash, Signature)> = SIGVERIFY_THREAD_POOL.install(|| {
+        unverified
+            .into_par_iter()
+            .filter(|(slot, root, signature)| match slot_leaders.get(slot) {
+                Some(pubkey) => signature.verify(pubkey, root.as_ref()),
+                None => false,
+            })
+            .collect()
+    });
+    for (slot, root, signature) in verified {
+        merkle_root_cache.insert(slot, root, signature);
+    }
+    inc_new_counter_debug!("ed25519_merkle_shred_verify", count);
+
+    for ((batch, roots), rvs) in batches.iter().zip(roots).zip(rvs.iter_mut()) {
+        for ((packet, root), rv) in batch.packets.iter().zip(roots).zip(rvs.iter_mut()) {
+            if is_merkle_packet(packet) {
+                *rv = match root {
+                    Some((slot, root, signature)) => {
+                        slot_leaders.contains_key(&slot)
+                            && merkle_root_cache.contains(slot, &root, &signature)
+                    }
+                    None => false,
+                } as u8;
+            }
+        }
+    }
+}
 
 lazy_static! {
     pub static ref SIGVERIFY_THREAD_POOL: ThreadPool = rayon::ThreadPoolBuilder::new()
@@ -43,6 +159,7 @@ lazy_static! {
 ///   ...
 /// }
 /// Signature is the first thing in the packet, and slot is the first thing in the signed message.
+/// Merkle shreds sign the root of their erasure batch instead.
 pub fn verify_shred_cpu(packet: &Packet, slot_leaders: &HashMap<u64, [u8; 32]>) -> Option<u8> {
     let sig_start = 0;
     let sig_end = size_of::<Signature>();
@@ -69,28 +186,47 @@ pub fn verify_shred_cpu(packet: &Packet, slot_leaders: &HashMap<u64, [u8; 32]>)
     }
     let signature = Signature::new(&packet.data[sig_start..sig_end]);
     trace!("signature {}", signature);
-    if !signature.verify(pubkey, &packet.data[msg_start..msg_end]) {
+    let verified = if is_merkle_packet(packet) {
+        match Shred::merkle_root_from_payload(&packet.data[..packet.meta.size]) {
+            Some(root) => signature.verify(pubkey, root.as_ref()),
+            None => false,
+        }
+    } else {
+        signature.verify(pubkey, &packet.data[msg_start..msg_end])
+    };
+    if !verified {
         return Some(0);
     }
     Some(1)
 }
 
-fn verify_shreds_cpu(batches: &[Packets], slot_leaders: &HashMap<u64, [u8; 32]>) -> Vec<Vec<u8>> {
+fn verify_shreds_cpu(
+    batches: &[Packets],
+    slot_leaders: &HashMap<u64, [u8; 32]>,
+    merkle_root_cache: &MerkleRootCache,
+) -> Vec<Vec<u8>> {
     use rayon::prelude::*;
     let count = batch_size(batches);
     debug!("CPU SHRED ECDSA for {}", count);
-    let rv = SIGVERIFY_THREAD_POOL.install(|| {
+    let mut rv: Vec<Vec<u8>> = SIGVERIFY_THREAD_POOL.install(|| {
         batches
             .into_par_iter()
             .map(|p| {
                 p.packets
                     .par_iter()
-                    .map(|p| verify_shred_cpu(p, slot_leaders).unwrap_or(0))
+                    .map(|p| {
+                        if is_merkle_packet(p) {
+                            0
+                        } else {
+                            verify_shred_cpu(p, slot_leaders).unwrap_or(0)
+                        }
+                    })
                     .collect()
             })
             .collect()
     });
     inc_new_counter_debug!("ed25519_shred_verify_cpu", count);
+    verify_merkle_shreds(batches, slot_leaders, merkle_root_cache, &mut rv);
     rv
 }
 
@@ -113,7 +249,11 @@ fn slot_key_data_for_gpu<
                     .map(|packet| {
                         let slot_start = size_of::<Signature>() + size_of::<ShredType>();
                         let slot_end = slot_start + size_of::<u64>();
-                        if packet.meta.size < slot_end || packet.meta.discard {
+                        // Merkle shreds are verified separately, see verify_merkle_shreds
+                        if packet.meta.size < slot_end
+                            || packet.meta.discard
+                            || is_merkle_packet(packet)
+                        {
                             return std::u64::MAX;
                         }
                         let slot: Option<u64> =
@@ -223,10 +363,11 @@ pub fn verify_shreds_gpu(
     batches: &[Packets],
     slot_leaders: &HashMap<u64, [u8; 32]>,
     recycler_cache: &RecyclerCache,
+    merkle_root_cache: &MerkleRootCache,
 ) -> Vec<Vec<u8>> {
     let api = perf_libs::api();
     if api.is_none() {
-        return verify_shreds_cpu(batches, slot_leaders);
+        return verify_shreds_cpu(batches, slot_leaders, merkle_root_cache);
     }
     let api = api.unwrap();
 
@@ -290,6 +431,7 @@ pub fn verify_shreds_gpu(
     trace!("out buf {:?}", out);
 
     sigverify::copy_return_values(&v_sig_lens, &out, &mut rvs);
+    verify_merkle_shreds(batches, slot_leaders, merkle_root_cache, &mut rvs);
 
     inc_new_counter_debug!("ed25519_shred_verify_gpu", count);
     rvs
@@ -529,7 +671,7 @@ pub mod tests {
             .iter()
             .cloned()
             .collect();
-        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![1]]);
 
         let wrong_keypair = Keypair::new();
@@ -537,11 +679,11 @@ pub mod tests {
             .iter()
             .cloned()
             .collect();
-        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![0]]);
 
         let leader_slots = HashMap::new();
-        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![0]]);
 
         let leader_slots = [(slot, keypair.pubkey().to_bytes())]
@@ -549,7 +691,7 @@ pub mod tests {
             .cloned()
             .collect();
         batch[0].packets[0].meta.size = 0;
-        let rv = verify_shreds_cpu(&batch, &leader_slots);
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![0]]);
     }
 
@@ -587,7 +729,12 @@ pub mod tests {
         .iter()
         .cloned()
         .collect();
-        let rv = verify_shreds_gpu(&batch, &leader_slots, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &leader_slots,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![1]]);
 
         let wrong_keypair = Keypair::new();
@@ -598,11 +745,21 @@ pub mod tests {
         .iter()
         .cloned()
         .collect();
-        let rv = verify_shreds_gpu(&batch, &leader_slots, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &leader_slots,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![0]]);
 
         let leader_slots = [(std::u64::MAX, [0u8; 32])].iter().cloned().collect();
-        let rv = verify_shreds_gpu(&batch, &leader_slots, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &leader_slots,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![0]]);
 
         batch[0].packets[0].meta.size = 0;
@@ -613,7 +770,12 @@ pub mod tests {
         .iter()
         .cloned()
         .collect();
-        let rv = verify_shreds_gpu(&batch, &leader_slots, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &leader_slots,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![0]]);
     }
 
@@ -656,14 +818,24 @@ pub mod tests {
         .cloned()
         .collect();
         //unsigned
-        let rv = verify_shreds_gpu(&batch, &pubkeys, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &pubkeys,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![0; num_packets]; num_batches]);
         //signed
         sign_shreds_gpu(&keypair, &pinned_keypair, &mut batch, &recycler_cache);
-        let rv = verify_shreds_cpu(&batch, &pubkeys);
+        let rv = verify_shreds_cpu(&batch, &pubkeys, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![1; num_packets]; num_batches]);
 
-        let rv = verify_shreds_gpu(&batch, &pubkeys, &recycler_cache);
+        let rv = verify_shreds_gpu(
+            &batch,
+            &pubkeys,
+            &recycler_cache,
+            &MerkleRootCache::default(),
+        );
         assert_eq!(rv, vec![vec![1; num_packets]; num_batches]);
     }
 
@@ -699,11 +871,11 @@ pub mod tests {
         .cloned()
         .collect();
         //unsigned
-        let rv = verify_shreds_cpu(&batch, &pubkeys);
+        let rv = verify_shreds_cpu(&batch, &pubkeys, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![0]]);
         //signed
         sign_shreds_cpu(&keypair, &mut batch);
-        let rv = verify_shreds_cpu(&batch, &pubkeys);
+        let rv = verify_shreds_cpu(&batch, &pubkeys, &MerkleRootCache::default());
         assert_eq!(rv, vec![vec![1]]);
     }
 
@@ -711,4 +883,64 @@ pub mod tests {
     fn test_sigverify_shreds_sign_cpu() {
         run_test_sigverify_shreds_sign_cpu(0xdead_c0de);
     }
+
+    #[test]
+    fn test_sigverify_merkle_shreds() {
+        solana_logger::setup();
+        let slot = 0xdead_c0de;
+        let keypair = Arc::new(Keypair::new());
+        let shredder = Shredder::new(slot, slot - 1, 1.0, keypair.clone(), 0, 0).unwrap();
+        let entries = crate::entry::create_ticks(1000, 0, Hash::default());
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_merkle_shreds(&entries, true, 0);
+        let num_roots = data_shreds
+            .iter()
+            .map(Shred::signature)
+            .collect::<HashSet<_>>()
+            .len();
+        assert!(num_roots > 1);
+
+        let mut packets = Packets::default();
+        packets
+            .packets
+            .resize(data_shreds.len() + coding_shreds.len(), Packet::default());
+        for (shred, packet) in data_shreds
+            .iter()
+            .chain(&coding_shreds)
+            .zip(packets.packets.iter_mut())
+        {
+            shred.copy_to_packet(packet);
+        }
+        let num_packets = packets.packets.len();
+        let mut batch = vec![packets];
+
+        let leader_slots = [(slot, keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            verify_shred_cpu(&batch[0].packets[0], &leader_slots),
+            Some(1)
+        );
+        let cache = MerkleRootCache::default();
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &cache);
+        assert_eq!(rv, vec![vec![1; num_packets]]);
+        assert_eq!(cache.0.read().unwrap()[&slot].len(), num_roots);
+        // Cached roots still require the packet to prove its way up to them
+        batch[0].packets[1].data[SIZE_OF_DATA_SHRED_PAYLOAD / 2] ^= 1;
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &cache);
+        assert_eq!(rv[0][0], 1);
+        assert_eq!(rv[0][1], 0);
+
+        let wrong_keypair = Keypair::new();
+        let leader_slots = [(slot, wrong_keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            verify_shred_cpu(&batch[0].packets[0], &leader_slots),
+            Some(0)
+        );
+        let rv = verify_shreds_cpu(&batch, &leader_slots, &MerkleRootCache::default());
+        assert_eq!(rv, vec![vec![0; num_packets]]);
+    }
 }
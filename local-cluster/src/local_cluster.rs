@@ -9,7 +9,7 @@ use solana_core::{
     cluster_info::{Node, VALIDATOR_PORT_RANGE},
     contact_info::ContactInfo,
     gossip_service::discover_cluster,
-    validator::{Validator, ValidatorConfig},
+    validator::{Validator, ValidatorConfig, ValidatorStartProgress},
 };
 use solana_ledger::create_new_tmp_ledger;
 use solana_runtime::genesis_utils::{
@@ -41,7 +41,7 @@ use std::{
     collections::HashMap,
     io::{Error, ErrorKind, Result},
     iter,
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
 #[derive(Clone, Debug)]
@@ -202,9 +202,10 @@ impl LocalCluster {
             &leader_keypair,
             &leader_ledger_path,
             &leader_vote_keypair.pubkey(),
-            vec![leader_vote_keypair.clone()],
+            Arc::new(RwLock::new(vec![leader_vote_keypair.clone()])),
             None,
             &leader_config,
+            &Arc::new(RwLock::new(ValidatorStartProgress::default())),
         );
 
         let mut validators = HashMap::new();
@@ -349,9 +350,10 @@ impl LocalCluster {
             &validator_keypair,
             &ledger_path,
             &voting_keypair.pubkey(),
-            vec![voting_keypair.clone()],
+            Arc::new(RwLock::new(vec![voting_keypair.clone()])),
             Some(&self.entry_point_info),
             &config,
+            &Arc::new(RwLock::new(ValidatorStartProgress::default())),
         );
 
         let validator_pubkey = validator_keypair.pubkey();
@@ -640,9 +642,10 @@ impl Cluster for LocalCluster {
             &validator_info.keypair,
             &validator_info.ledger_path,
             &validator_info.voting_keypair.pubkey(),
-            vec![validator_info.voting_keypair.clone()],
+            Arc::new(RwLock::new(vec![validator_info.voting_keypair.clone()])),
             entry_point_info,
             &cluster_validator_info.config,
+            &Arc::new(RwLock::new(ValidatorStartProgress::default())),
         );
 
         cluster_validator_info.validator = Some(restarted_node);
@@ -8,17 +8,19 @@ use solana_client::{
 };
 use solana_core::{
     broadcast_stage::BroadcastStageType,
-    cluster_info::VALIDATOR_PORT_RANGE,
+    cluster_info::{Node, VALIDATOR_PORT_RANGE},
     consensus::{Tower, SWITCH_FORK_THRESHOLD, VOTE_THRESHOLD_DEPTH},
     gossip_service::discover_cluster,
     optimistic_confirmation_verifier::OptimisticConfirmationVerifier,
-    validator::ValidatorConfig,
+    tower_storage::FileTowerStorage,
+    validator::{Validator, ValidatorConfig, ValidatorStartProgress},
 };
 use solana_download_utils::download_snapshot;
 use solana_ledger::{
     ancestor_iterator::AncestorIterator,
     blockstore::{Blockstore, PurgeType},
     blockstore_db::AccessType,
+    create_new_tmp_ledger,
     leader_schedule::FixedSchedule,
     leader_schedule::LeaderSchedule,
 };
@@ -50,8 +52,8 @@ use std::{
     io::Read,
     iter,
     path::{Path, PathBuf},
-    sync::Arc,
-    thread::sleep,
+    sync::{mpsc::channel, Arc, RwLock},
+    thread::{self, sleep},
     time::Duration,
 };
 use tempfile::TempDir;
@@ -740,6 +742,63 @@ fn test_listener_startup() {
     assert_eq!(cluster_nodes.len(), 4);
 }
 
+#[test]
+#[serial]
+fn test_duplicate_node_instance() {
+    solana_logger::setup();
+    let config = ClusterConfig {
+        node_stakes: vec![100, 10],
+        cluster_lamports: 1_000,
+        validator_configs: vec![ValidatorConfig::default(); 2],
+        ..ClusterConfig::default()
+    };
+    let cluster = LocalCluster::new(&config);
+    cluster.check_for_new_roots(2, &"test_duplicate_node_instance");
+    // Nodes ignore gossip from their own pubkey, so duplicate the validator which is not the
+    // entrypoint and let the entrypoint relay the instances of the two
+    let (identity_keypair, voting_keypair) = {
+        let info = &cluster
+            .validators
+            .values()
+            .find(|validator| validator.info.keypair.pubkey() != cluster.entry_point_info.id)
+            .unwrap()
+            .info;
+        (info.keypair.clone(), info.voting_keypair.clone())
+    };
+
+    // Mistakenly start another instance of the same validator
+    let node = Node::new_localhost_with_pubkey(&identity_keypair.pubkey());
+    let (ledger_path, _blockhash) = create_new_tmp_ledger!(&cluster.genesis_config);
+    let mut validator_config = ValidatorConfig::default();
+    validator_config.rpc_addrs = Some((node.info.rpc, node.info.rpc_pubsub, node.info.rpc_banks));
+    validator_config.account_paths = vec![ledger_path.join("accounts")];
+    let duplicate = Validator::new(
+        node,
+        &identity_keypair,
+        &ledger_path,
+        &voting_keypair.pubkey(),
+        Arc::new(RwLock::new(vec![voting_keypair.clone()])),
+        Some(&cluster.entry_point_info),
+        &validator_config,
+        &Arc::new(RwLock::new(ValidatorStartProgress::default())),
+    );
+
+    // The newer instance notices the running one through gossip and shuts itself down
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        duplicate.join().unwrap();
+        sender.send(()).unwrap();
+    });
+    assert!(
+        receiver.recv_timeout(Duration::from_secs(60)).is_ok(),
+        "duplicate validator instance did not exit"
+    );
+
+    // While the original one keeps running
+    cluster.check_for_new_roots(4, &"test_duplicate_node_instance");
+    let _ = fs::remove_dir_all(&ledger_path);
+}
+
 #[test]
 #[serial]
 fn test_mainnet_beta_cluster_type() {
@@ -1512,6 +1571,7 @@ fn test_validator_saves_tower() {
         .info
         .ledger_path
         .clone();
+    let tower_storage = FileTowerStorage::new(ledger_path.clone());
 
     // Wait for some votes to be generated
     let mut last_replayed_root;
@@ -1529,7 +1589,7 @@ fn test_validator_saves_tower() {
 
     // Stop validator and check saved tower
     let validator_info = cluster.exit_node(&validator_id);
-    let tower1 = Tower::restore(&ledger_path, &validator_id).unwrap();
+    let tower1 = Tower::restore(&tower_storage, &validator_id).unwrap();
     trace!("tower1: {:?}", tower1);
     assert_eq!(tower1.root(), 0);
 
@@ -1554,14 +1614,16 @@ fn test_validator_saves_tower() {
         .get_slot_with_commitment(CommitmentConfig::recent())
         .unwrap();
     let validator_info = cluster.exit_node(&validator_id);
-    let tower2 = Tower::restore(&ledger_path, &validator_id).unwrap();
+    let tower2 = Tower::restore(&tower_storage, &validator_id).unwrap();
     trace!("tower2: {:?}", tower2);
     assert_eq!(tower2.root(), last_replayed_root);
     last_replayed_root = recent_slot;
 
     // Rollback saved tower to `tower1` to simulate a validator starting from a newer snapshot
     // without having to wait for that snapshot to be generated in this test
-    tower1.save(&validator_identity_keypair).unwrap();
+    tower1
+        .save(&tower_storage, &validator_identity_keypair)
+        .unwrap();
 
     cluster.restart_node(&validator_id, validator_info);
     let validator_client = cluster.get_validator_client(&validator_id).unwrap();
@@ -1583,7 +1645,7 @@ fn test_validator_saves_tower() {
 
     // Check the new root is reflected in the saved tower state
     let mut validator_info = cluster.exit_node(&validator_id);
-    let tower3 = Tower::restore(&ledger_path, &validator_id).unwrap();
+    let tower3 = Tower::restore(&tower_storage, &validator_id).unwrap();
     trace!("tower3: {:?}", tower3);
     assert!(tower3.root() > last_replayed_root);
 
@@ -1611,7 +1673,7 @@ fn test_validator_saves_tower() {
 
     cluster.close_preserve_ledgers();
 
-    let tower4 = Tower::restore(&ledger_path, &validator_id).unwrap();
+    let tower4 = Tower::restore(&tower_storage, &validator_id).unwrap();
     trace!("tower4: {:?}", tower4);
     // should tower4 advance 1 slot compared to tower3????
     assert_eq!(tower4.root(), tower3.root() + 1);
@@ -1631,7 +1693,8 @@ fn purge_slots(blockstore: &Blockstore, start_slot: Slot, slot_count: Slot) {
 }
 
 fn last_vote_in_tower(ledger_path: &Path, node_pubkey: &Pubkey) -> Option<Slot> {
-    let tower = Tower::restore(&ledger_path, &node_pubkey);
+    let tower_storage = FileTowerStorage::new(ledger_path.to_path_buf());
+    let tower = Tower::restore(&tower_storage, &node_pubkey);
     if let Err(tower_err) = tower {
         if tower_err.is_file_missing() {
             return None;
@@ -1640,7 +1703,7 @@ fn last_vote_in_tower(ledger_path: &Path, node_pubkey: &Pubkey) -> Option<Slot>
         }
     }
     // actually saved tower must have at least one vote.
-    let last_vote = Tower::restore(&ledger_path, &node_pubkey)
+    let last_vote = Tower::restore(&tower_storage, &node_pubkey)
         .unwrap()
         .last_voted_slot()
         .unwrap();
@@ -1648,7 +1711,8 @@ fn last_vote_in_tower(ledger_path: &Path, node_pubkey: &Pubkey) -> Option<Slot>
 }
 
 fn remove_tower(ledger_path: &Path, node_pubkey: &Pubkey) {
-    fs::remove_file(Tower::get_filename(&ledger_path, &node_pubkey)).unwrap();
+    let tower_storage = FileTowerStorage::new(ledger_path.to_path_buf());
+    fs::remove_file(tower_storage.filename(&node_pubkey)).unwrap();
 }
 
 // A bit convoluted test case; but this roughly follows this test theoretical scenario:
@@ -36,6 +36,11 @@ impl<'a> ProofEntry<'a> {
         assert!((None == left_sibling) ^ (None == right_sibling));
         Self(target, left_sibling, right_sibling)
     }
+
+    /// The hash combined with the child node to produce `target`
+    pub fn sibling(&self) -> &'a Hash {
+        self.1.or(self.2).unwrap()
+    }
 }
 
 #[derive(Debug, Default, PartialEq)]
@@ -60,6 +65,11 @@ impl<'a> Proof<'a> {
         });
         matches!(result, Some(_))
     }
+
+    /// The sibling hashes along the path, from the leaf level up to the root
+    pub fn siblings(&self) -> impl Iterator<Item = &'a Hash> + '_ {
+        self.0.iter().map(ProofEntry::sibling)
+    }
 }
 
 impl MerkleTree {
@@ -135,6 +145,23 @@ impl MerkleTree {
         mt
     }
 
+    /// Recomputes the root of a tree from one of its leaf items, the index of that leaf and the
+    /// sibling hashes of its path, as returned by `Proof::siblings`
+    pub fn compute_root_from_path(item: &[u8], index: usize, siblings: &[Hash]) -> Hash {
+        let leaf = hash_leaf!(item);
+        let (root, _) = siblings
+            .iter()
+            .fold((leaf, index), |(node, index), sibling| {
+                let hash = if index % 2 == 0 {
+                    hash_intermediate!(node, sibling)
+                } else {
+                    hash_intermediate!(sibling, node)
+                };
+                (hash, index / 2)
+            });
+        root
+    }
+
     pub fn get_root(&self) -> Option<&Hash> {
         self.nodes.iter().last()
     }
@@ -247,6 +274,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compute_root_from_path() {
+        for len in 1..TEST.len() {
+            let items = &TEST[..len];
+            let mt = MerkleTree::new(items);
+            for (i, s) in items.iter().enumerate() {
+                let path = mt.find_path(i).unwrap();
+                let siblings: Vec<Hash> = path.siblings().cloned().collect();
+                let root = MerkleTree::compute_root_from_path(s, i, &siblings);
+                assert_eq!(Some(&root), mt.get_root());
+                let root = MerkleTree::compute_root_from_path(&BAD[0], i, &siblings);
+                assert_ne!(Some(&root), mt.get_root());
+            }
+        }
+    }
+
     #[test]
     fn test_proof_entry_instantiation_lsib_set() {
         ProofEntry::new(&Hash::default(), Some(&Hash::default()), None);
@@ -800,6 +800,9 @@ impl InvokeContext for MockInvokeContext {
     fn get_caller(&self) -> Result<&Pubkey, InstructionError> {
         Ok(&self.key)
     }
+    fn invoke_depth(&self) -> usize {
+        1
+    }
     fn get_programs(&self) -> &[(Pubkey, ProcessInstruction)] {
         &[]
     }
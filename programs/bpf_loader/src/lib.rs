@@ -269,7 +269,8 @@ impl Executor for BPFExecutor {
             let after = compute_meter.borrow().get_remaining();
             log!(
                 logger,
-                "BPF program consumed {} of {} units",
+                "Program {} consumed {} of {} compute units",
+                program.unsigned_key(),
                 before - after,
                 before
             );
@@ -387,6 +388,9 @@ mod tests {
         fn get_caller(&self) -> Result<&Pubkey, InstructionError> {
             Ok(&self.key)
         }
+        fn invoke_depth(&self) -> usize {
+            1
+        }
         fn get_programs(&self) -> &[(Pubkey, ProcessInstruction)] {
             &[]
         }
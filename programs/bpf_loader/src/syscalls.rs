@@ -10,6 +10,7 @@ use solana_rbpf::{
 use solana_runtime::{
     feature_set::{
         pubkey_log_syscall_enabled, ristretto_mul_syscall_enabled, sha256_syscall_enabled,
+        sol_log_compute_units_syscall,
     },
     message_processor::MessageProcessor,
     process_instruction::{ComputeMeter, InvokeContext, Logger},
@@ -134,6 +135,17 @@ pub fn register_syscalls<'a>(
         )?;
     }
 
+    if invoke_context.is_feature_active(&sol_log_compute_units_syscall::id()) {
+        vm.register_syscall_with_context_ex(
+            "sol_log_compute_units_",
+            Box::new(SyscallLogBpfComputeUnits {
+                cost: 0,
+                compute_meter: invoke_context.get_compute_meter(),
+                logger: invoke_context.get_logger(),
+            }),
+        )?;
+    }
+
     if invoke_context.is_feature_active(&sha256_syscall_enabled::id()) {
         vm.register_syscall_with_context_ex(
             "sol_sha256",
@@ -414,7 +426,39 @@ impl SyscallObject<BPFError> for SyscallLogU64 {
     }
 }
 
-/// Log 5 64-bit values
+/// Log current compute consumption
+pub struct SyscallLogBpfComputeUnits {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    logger: Rc<RefCell<dyn Logger>>,
+}
+impl SyscallObject<BPFError> for SyscallLogBpfComputeUnits {
+    fn call(
+        &mut self,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _ro_regions: &[MemoryRegion],
+        _rw_regions: &[MemoryRegion],
+    ) -> Result<u64, EbpfError<BPFError>> {
+        self.compute_meter.consume(self.cost)?;
+        let mut logger = self
+            .logger
+            .try_borrow_mut()
+            .map_err(|_| SyscallError::InvokeContextBorrowFailed)?;
+        if logger.log_enabled() {
+            logger.log(&format!(
+                "Program consumption: {} units remaining",
+                self.compute_meter.borrow().get_remaining()
+            ));
+        }
+        Ok(0)
+    }
+}
+
+/// Log a `Pubkey`
 pub struct SyscallLogPubkey<'a> {
     cost: u64,
     compute_meter: Rc<RefCell<dyn ComputeMeter>>,
@@ -1513,6 +1557,28 @@ mod tests {
         assert_eq!(log.borrow()[0], "Program log: 0x1, 0x2, 0x3, 0x4, 0x5");
     }
 
+    #[test]
+    fn test_syscall_sol_log_compute_units() {
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter { remaining: 100 }));
+        let log = Rc::new(RefCell::new(vec![]));
+        let logger: Rc<RefCell<dyn Logger>> =
+            Rc::new(RefCell::new(MockLogger { log: log.clone() }));
+        let mut syscall_sol_log_compute_units = SyscallLogBpfComputeUnits {
+            cost: 10,
+            compute_meter,
+            logger,
+        };
+        let ro_regions = &[MemoryRegion::default()];
+        let rw_regions = &[MemoryRegion::default()];
+
+        syscall_sol_log_compute_units
+            .call(0, 0, 0, 0, 0, ro_regions, rw_regions)
+            .unwrap();
+        assert_eq!(log.borrow().len(), 1);
+        assert_eq!(log.borrow()[0], "Program consumption: 90 units remaining");
+    }
+
     #[test]
     fn test_syscall_sol_pubkey() {
         let pubkey = Pubkey::from_str("MoqiU1vryuCGQSxFKA1SZ316JdLEFFhoAu6cKUNk7dN").unwrap();
@@ -42,6 +42,8 @@ fn test_accounts_create(bencher: &mut Bencher) {
         &[],
         None,
         None,
+        None,
+        None,
     );
     bencher.iter(|| {
         let mut pubkeys: Vec<Pubkey> = vec![];
@@ -58,6 +60,8 @@ fn test_accounts_squash(bencher: &mut Bencher) {
         &[],
         None,
         None,
+        None,
+        None,
     ));
     let mut pubkeys: Vec<Pubkey> = vec![];
     deposit_many(&bank1, &mut pubkeys, 250_000);
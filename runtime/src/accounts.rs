@@ -2,7 +2,7 @@ use crate::{
     accounts_db::{
         AccountInfo, AccountStorage, AccountsDB, AppendVecId, BankHashInfo, ErrorCounters,
     },
-    accounts_index::{AccountsIndex, Ancestors},
+    accounts_index::{AccountsIndex, AccountsIndexConfig, Ancestors},
     append_vec::StoredAccount,
     bank::{HashAgeKind, TransactionProcessResult},
     blockhash_queue::BlockhashQueue,
@@ -72,8 +72,20 @@ pub enum AccountAddressFilter {
 
 impl Accounts {
     pub fn new(paths: Vec<PathBuf>, cluster_type: &ClusterType) -> Self {
+        Self::new_with_config(paths, cluster_type, None)
+    }
+
+    pub fn new_with_config(
+        paths: Vec<PathBuf>,
+        cluster_type: &ClusterType,
+        accounts_index_config: Option<AccountsIndexConfig>,
+    ) -> Self {
         Self {
-            accounts_db: Arc::new(AccountsDB::new(paths, cluster_type)),
+            accounts_db: Arc::new(AccountsDB::new_with_config(
+                paths,
+                cluster_type,
+                accounts_index_config,
+            )),
             account_locks: Mutex::new(HashSet::new()),
             readonly_locks: Arc::new(RwLock::new(Some(HashMap::new()))),
             ..Self::default()
@@ -314,6 +326,8 @@ impl Accounts {
         let fee_config = FeeConfig {
             secp256k1_program_enabled: feature_set
                 .is_active(&feature_set::secp256k1_program_enabled::id()),
+            ed25519_program_enabled: feature_set
+                .is_active(&feature_set::ed25519_program_enabled::id()),
         };
         OrderedIterator::new(txs, txs_iteration_order)
             .zip(lock_results.into_iter())
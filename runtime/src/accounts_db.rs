@@ -19,7 +19,8 @@
 //! commit for each slot entry would be indexed.
 
 use crate::{
-    accounts_index::{AccountsIndex, Ancestors, SlotList, SlotSlice},
+    accounts_index::{AccountsIndex, AccountsIndexConfig, Ancestors, SlotList, SlotSlice},
+    accounts_update_notifier_interface::AccountsUpdateNotifier,
     append_vec::{AppendVec, StoredAccount, StoredMeta},
 };
 use blake3::traits::digest::Digest;
@@ -80,7 +81,7 @@ pub struct ErrorCounters {
     pub not_allowed_during_cluster_maintenance: usize,
 }
 
-#[derive(Default, Debug, PartialEq, Clone)]
+#[derive(Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct AccountInfo {
     /// index identifying the append storage
     store_id: AppendVecId,
@@ -120,6 +121,12 @@ impl Versioned for (u64, AccountInfo) {
     }
 }
 
+impl Versioned for (u64, Hash, Account) {
+    fn version(&self) -> u64 {
+        self.0
+    }
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct AccountStorage(pub HashMap<Slot, SlotStores>);
 
@@ -428,6 +435,9 @@ pub struct AccountsDB {
     stats: AccountsStats,
 
     pub cluster_type: Option<ClusterType>,
+
+    /// Observer notified of every account write, e.g. an AccountsDb plugin
+    pub accounts_update_notifier: Option<AccountsUpdateNotifier>,
 }
 
 #[derive(Debug, Default)]
@@ -489,17 +499,28 @@ impl Default for AccountsDB {
             frozen_accounts: HashMap::new(),
             stats: AccountsStats::default(),
             cluster_type: None,
+            accounts_update_notifier: None,
         }
     }
 }
 
 impl AccountsDB {
     pub fn new(paths: Vec<PathBuf>, cluster_type: &ClusterType) -> Self {
+        Self::new_with_config(paths, cluster_type, None)
+    }
+
+    pub fn new_with_config(
+        paths: Vec<PathBuf>,
+        cluster_type: &ClusterType,
+        accounts_index_config: Option<AccountsIndexConfig>,
+    ) -> Self {
+        let accounts_index = RwLock::new(AccountsIndex::new(accounts_index_config.as_ref()));
         let new = if !paths.is_empty() {
             Self {
                 paths,
                 temp_paths: None,
                 cluster_type: Some(*cluster_type),
+                accounts_index,
                 ..Self::default()
             }
         } else {
@@ -510,6 +531,7 @@ impl AccountsDB {
                 paths,
                 temp_paths: Some(temp_dirs),
                 cluster_type: Some(*cluster_type),
+                accounts_index,
                 ..Self::default()
             }
         };
@@ -858,11 +880,7 @@ impl AccountsDB {
         if !dead_keys.is_empty() {
             let mut accounts_index = self.accounts_index.write().unwrap();
             for key in &dead_keys {
-                if let Some((_ref_count, list)) = accounts_index.account_maps.get(key) {
-                    if list.read().unwrap().is_empty() {
-                        accounts_index.account_maps.remove(key);
-                    }
-                }
+                accounts_index.remove_if_empty(key);
             }
         }
     }
@@ -1697,7 +1715,8 @@ impl AccountsDB {
         accounts: &[(&Pubkey, &Account)],
         hashes: &[Hash],
     ) -> Vec<AccountInfo> {
-        let mut current_version = self.bulk_assign_write_version(accounts.len());
+        let first_version = self.bulk_assign_write_version(accounts.len());
+        let mut current_version = first_version;
         let write_version_producer = std::iter::from_fn(move || {
             let ret = current_version;
             current_version += 1;
@@ -1705,13 +1724,100 @@ impl AccountsDB {
         });
 
         let storage_finder = |slot| self.find_storage_candidate(slot);
-        self.store_accounts_to(
+        let infos = self.store_accounts_to(
             slot,
             accounts,
             hashes,
             storage_finder,
             write_version_producer,
-        )
+        );
+        self.notify_account_update(slot, accounts, first_version);
+        infos
+    }
+
+    fn notify_account_update(
+        &self,
+        slot: Slot,
+        accounts: &[(&Pubkey, &Account)],
+        first_write_version: u64,
+    ) {
+        if let Some(accounts_update_notifier) = &self.accounts_update_notifier {
+            for (i, (pubkey, account)) in accounts.iter().enumerate() {
+                accounts_update_notifier.notify_account_update(
+                    slot,
+                    pubkey,
+                    account,
+                    first_write_version + i as u64,
+                );
+            }
+        }
+    }
+
+    /// Stream the latest stored version of every account to the `accounts_update_notifier`,
+    /// newest slot first, once the storages have been restored from a snapshot
+    pub fn notify_account_restore_from_snapshot(&self) {
+        let accounts_update_notifier = match &self.accounts_update_notifier {
+            Some(accounts_update_notifier) => accounts_update_notifier,
+            None => return,
+        };
+        let mut measure = Measure::start("notify_account_restore_from_snapshot");
+        let storage = self.storage.read().unwrap();
+        let mut slots: Vec<Slot> = storage.0.keys().cloned().collect();
+        slots.sort_by(|a, b| b.cmp(a));
+
+        let mut notified_accounts: HashSet<Pubkey> = HashSet::new();
+        let mut notified_count = 0;
+        for slot in slots {
+            let accumulator: Vec<HashMap<Pubkey, (u64, Account)>> = self
+                .scan_account_storage_inner(
+                    slot,
+                    |stored_account: &StoredAccount,
+                     _store_id: AppendVecId,
+                     accum: &mut HashMap<Pubkey, (u64, Account)>| {
+                        let write_version = stored_account.meta.write_version;
+                        match accum.get(&stored_account.meta.pubkey) {
+                            Some((existing_version, _)) if *existing_version > write_version => {}
+                            _ => {
+                                accum.insert(
+                                    stored_account.meta.pubkey,
+                                    (write_version, stored_account.clone_account()),
+                                );
+                            }
+                        }
+                    },
+                    &storage,
+                );
+
+            let mut slot_accounts: HashMap<Pubkey, (u64, Account)> = HashMap::new();
+            for accumulator_entry in accumulator.into_iter() {
+                for (pubkey, (write_version, account)) in accumulator_entry {
+                    match slot_accounts.get(&pubkey) {
+                        Some((existing_version, _)) if *existing_version > write_version => {}
+                        _ => {
+                            slot_accounts.insert(pubkey, (write_version, account));
+                        }
+                    }
+                }
+            }
+
+            for (pubkey, (write_version, account)) in slot_accounts {
+                if notified_accounts.insert(pubkey) {
+                    accounts_update_notifier.notify_account_restore_from_snapshot(
+                        slot,
+                        &pubkey,
+                        &account,
+                        write_version,
+                    );
+                    notified_count += 1;
+                }
+            }
+        }
+        accounts_update_notifier.notify_end_of_restore_from_snapshot();
+        measure.stop();
+        info!(
+            "{}: notified {} accounts from snapshot",
+            measure, notified_count
+        );
     }
 
     fn store_accounts_to<F: FnMut(Slot) -> Arc<AccountStorageEntry>, P: Iterator<Item = u64>>(
@@ -2095,6 +2201,37 @@ impl AccountsDB {
         ret
     }
 
+    /// The latest version of each account stored in `slot` along with the hash it contributes
+    /// to `get_accounts_delta_hash()`, ordered by pubkey
+    pub fn get_accounts_delta_hash_details(&self, slot: Slot) -> Vec<(Pubkey, Hash, Account)> {
+        let mut accumulator: Vec<HashMap<Pubkey, (u64, Hash, Account)>> = self
+            .scan_account_storage(
+                slot,
+                |stored_account: &StoredAccount,
+                 _store_id: AppendVecId,
+                 accum: &mut HashMap<Pubkey, (u64, Hash, Account)>| {
+                    accum.insert(
+                        stored_account.meta.pubkey,
+                        (
+                            stored_account.meta.write_version,
+                            *stored_account.hash,
+                            stored_account.clone_account(),
+                        ),
+                    );
+                },
+            );
+        let mut account_maps = HashMap::new();
+        while let Some(maps) = accumulator.pop() {
+            AccountsDB::merge(&mut account_maps, &maps);
+        }
+        let mut details: Vec<_> = account_maps
+            .into_iter()
+            .map(|(pubkey, (_, hash, account))| (pubkey, hash, account))
+            .collect();
+        details.sort_by(|a, b| a.0.cmp(&b.0));
+        details
+    }
+
     fn update_index(
         &self,
         slot: Slot,
@@ -2429,8 +2566,8 @@ impl AccountsDB {
         }
 
         let mut counts = HashMap::new();
-        for slot_list in accounts_index.account_maps.values() {
-            for (_slot, account_entry) in slot_list.1.read().unwrap().iter() {
+        for entry in accounts_index.account_maps.values() {
+            for (_slot, account_entry) in accounts_index.read_slot_list(entry).iter() {
                 *counts.entry(account_entry.store_id).or_insert(0) += 1;
             }
         }
@@ -2471,9 +2608,10 @@ impl AccountsDB {
             .collect();
         roots.sort();
         info!("{}: accounts_index roots: {:?}", label, roots,);
-        for (pubkey, list) in &self.accounts_index.read().unwrap().account_maps {
+        let accounts_index = self.accounts_index.read().unwrap();
+        for (pubkey, entry) in &accounts_index.account_maps {
             info!("  key: {}", pubkey);
-            info!("      slots: {:?}", *list.1.read().unwrap());
+            info!("      slots: {:?}", *accounts_index.read_slot_list(entry));
         }
     }
 
@@ -2515,7 +2653,11 @@ impl AccountsDB {
 pub mod tests {
     // TODO: all the bank tests are bank specific, issue: 2194
     use super::*;
-    use crate::{accounts_index::RefCount, append_vec::AccountMeta};
+    use crate::{
+        accounts_index::RefCount,
+        accounts_update_notifier_interface::AccountsUpdateNotifierInterface,
+        append_vec::AccountMeta,
+    };
     use assert_matches::assert_matches;
     use rand::{thread_rng, Rng};
     use solana_sdk::{account::Account, hash::HASH_BYTES};
@@ -2764,14 +2906,13 @@ pub mod tests {
         assert!(db.load_slow(&ancestors, &key).is_none());
         assert!(db.bank_hashes.read().unwrap().get(&unrooted_slot).is_none());
         assert!(db.storage.read().unwrap().0.get(&unrooted_slot).is_none());
-        assert!(db
-            .accounts_index
-            .read()
-            .unwrap()
+        let accounts_index = db.accounts_index.read().unwrap();
+        assert!(accounts_index
             .account_maps
             .get(&key)
-            .map(|pubkey_entry| pubkey_entry.1.read().unwrap().is_empty())
+            .map(|pubkey_entry| accounts_index.read_slot_list(pubkey_entry).is_empty())
             .unwrap_or(true));
+        drop(accounts_index);
         assert!(db
             .accounts_index
             .read()
@@ -3249,6 +3390,55 @@ pub mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_accounts_db_disk_index() {
+        solana_logger::setup();
+        let index_dir = tempfile::TempDir::new().unwrap();
+        let accounts = AccountsDB::new_with_config(
+            Vec::new(),
+            &ClusterType::Development,
+            Some(AccountsIndexConfig {
+                drives: Some(vec![index_dir.path().to_path_buf()]),
+                index_limit_mb: Some(0),
+            }),
+        );
+        assert!(accounts
+            .accounts_index
+            .read()
+            .unwrap()
+            .is_disk_index_enabled());
+
+        let mut pubkeys: Vec<Pubkey> = vec![];
+        create_account(&accounts, &mut pubkeys, 0, 100, 0, 0);
+        accounts.add_root(0);
+        check_accounts(&accounts, &pubkeys, 0, 100, 1);
+        // Only the slot list of the last account read is left in memory
+        assert_eq!(
+            accounts.accounts_index.read().unwrap().resident_bytes(),
+            std::mem::size_of::<(Slot, AccountInfo)>() as u64
+        );
+
+        // updates in a later root purge the older rooted entries from the paged out index
+        let zero_lamport_account = Account::new(0, 0, &Account::default().owner);
+        for pubkey in &pubkeys[..10] {
+            accounts.store(1, &[(pubkey, &zero_lamport_account)]);
+        }
+        accounts.add_root(1);
+        accounts.clean_accounts(None);
+        let accounts_index = accounts.accounts_index.read().unwrap();
+        for pubkey in &pubkeys[..10] {
+            let (slot_list, index) = accounts_index.get(pubkey, None, None).unwrap();
+            assert_eq!(slot_list.len(), 1);
+            assert_eq!(slot_list[index].0, 1);
+            assert_eq!(slot_list[index].1.lamports, 0);
+        }
+        for (i, pubkey) in pubkeys.iter().enumerate().skip(10) {
+            let (slot_list, index) = accounts_index.get(pubkey, None, None).unwrap();
+            assert_eq!(slot_list[index].0, 0);
+            assert_eq!(slot_list[index].1.lamports, i as u64 + 1);
+        }
+    }
+
     #[test]
     fn test_clean_old_with_normal_account() {
         solana_logger::setup();
@@ -3631,7 +3821,7 @@ pub mod tests {
         let (slot_list_len, index_slot) = {
             let index = accounts.accounts_index.read().unwrap();
             let account_entry = index.account_maps.get(&pubkey).unwrap();
-            let slot_list = account_entry.1.read().unwrap();
+            let slot_list = index.read_slot_list(account_entry);
             (slot_list.len(), slot_list[0].0)
         };
         assert_eq!(slot_list_len, 1);
@@ -4206,6 +4396,52 @@ pub mod tests {
         assert_eq!(bank_hash.stats.num_executable_accounts, 1);
     }
 
+    #[test]
+    fn test_get_accounts_delta_hash_details() {
+        let db = AccountsDB::new(Vec::new(), &ClusterType::Development);
+
+        let key0 = solana_sdk::pubkey::new_rand();
+        let key1 = solana_sdk::pubkey::new_rand();
+        let some_slot: Slot = 0;
+        let account0 = Account::new(1, 0, &key0);
+        let mut account1 = Account::new(2, 3, &key1);
+
+        db.store(some_slot, &[(&key0, &account0), (&key1, &account1)]);
+        account1.lamports = 3;
+        db.store(some_slot, &[(&key1, &account1)]);
+
+        let details = db.get_accounts_delta_hash_details(some_slot);
+        let mut expected_keys = vec![key0, key1];
+        expected_keys.sort();
+        assert_eq!(
+            details
+                .iter()
+                .map(|(pubkey, _, _)| *pubkey)
+                .collect::<Vec<_>>(),
+            expected_keys
+        );
+
+        // Only the latest version of each account is reported
+        let (_, hash1, stored_account1) = details
+            .iter()
+            .find(|(pubkey, _, _)| *pubkey == key1)
+            .unwrap();
+        assert_eq!(*stored_account1, account1);
+        assert_eq!(
+            *hash1,
+            AccountsDB::hash_account(some_slot, &account1, &key1, &ClusterType::Development)
+        );
+
+        let hashes = details
+            .into_iter()
+            .map(|(pubkey, hash, _)| (pubkey, hash, 0))
+            .collect();
+        assert_eq!(
+            AccountsDB::accumulate_account_hashes(hashes),
+            db.get_accounts_delta_hash(some_slot)
+        );
+    }
+
     #[test]
     fn test_verify_bank_hash() {
         use BankHashVerificationError::*;
@@ -5028,4 +5264,91 @@ pub mod tests {
             3
         );
     }
+
+    // (slot, pubkey, lamports, write_version, is_restore)
+    type RecordedUpdate = (Slot, Pubkey, u64, u64, bool);
+
+    #[derive(Debug, Default)]
+    pub(crate) struct AccountsUpdateRecorder {
+        pub(crate) updates: Mutex<Vec<RecordedUpdate>>,
+        pub(crate) end_of_restore: AtomicBool,
+    }
+
+    impl AccountsUpdateNotifierInterface for AccountsUpdateRecorder {
+        fn notify_account_update(
+            &self,
+            slot: Slot,
+            pubkey: &Pubkey,
+            account: &Account,
+            write_version: u64,
+        ) {
+            self.updates.lock().unwrap().push((
+                slot,
+                *pubkey,
+                account.lamports,
+                write_version,
+                false,
+            ));
+        }
+
+        fn notify_account_restore_from_snapshot(
+            &self,
+            slot: Slot,
+            pubkey: &Pubkey,
+            account: &Account,
+            write_version: u64,
+        ) {
+            self.updates.lock().unwrap().push((
+                slot,
+                *pubkey,
+                account.lamports,
+                write_version,
+                true,
+            ));
+        }
+
+        fn notify_end_of_restore_from_snapshot(&self) {
+            self.end_of_restore.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_accounts_update_notifier() {
+        let recorder = Arc::new(AccountsUpdateRecorder::default());
+        let mut db = AccountsDB::new(Vec::new(), &ClusterType::Development);
+        db.accounts_update_notifier = Some(recorder.clone());
+
+        let key0 = Pubkey::new_unique();
+        let key1 = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        db.store(
+            0,
+            &[
+                (&key0, &Account::new(1, 0, &owner)),
+                (&key1, &Account::new(2, 0, &owner)),
+            ],
+        );
+        db.store(1, &[(&key0, &Account::new(3, 0, &owner))]);
+        db.store(1, &[(&key0, &Account::new(0, 0, &owner))]);
+
+        let updates = recorder.updates.lock().unwrap().clone();
+        assert_eq!(
+            updates,
+            vec![
+                (0, key0, 1, 0, false),
+                (0, key1, 2, 1, false),
+                (1, key0, 3, 2, false),
+                (1, key0, 0, 3, false),
+            ]
+        );
+        assert!(!recorder.end_of_restore.load(Ordering::Relaxed));
+
+        // Only the latest version of each account is replayed on restore
+        recorder.updates.lock().unwrap().clear();
+        db.notify_account_restore_from_snapshot();
+        let mut updates = recorder.updates.lock().unwrap().clone();
+        updates.sort_by_key(|update| update.3);
+        assert_eq!(updates, vec![(0, key1, 2, 1, true), (1, key0, 0, 3, true)]);
+        assert!(recorder.end_of_restore.load(Ordering::Relaxed));
+    }
 }
@@ -1,9 +1,16 @@
+use crate::bucket_map::{BucketMap, DiskLocation};
+use log::*;
+use serde::{de::DeserializeOwned, Serialize};
+use solana_measure::measure::Measure;
 use solana_sdk::{clock::Slot, pubkey::Pubkey};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::{
+    cmp,
     collections::{BTreeMap, HashMap, HashSet},
-    ops::RangeBounds,
-    sync::{RwLock, RwLockReadGuard},
+    mem,
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    path::PathBuf,
+    sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 pub type SlotList<T> = Vec<(Slot, T)>;
@@ -11,9 +18,113 @@ pub type SlotSlice<'s, T> = &'s [(Slot, T)];
 pub type Ancestors = HashMap<Slot, usize>;
 
 pub type RefCount = u64;
-type AccountMapEntry<T> = (AtomicU64, RwLock<SlotList<T>>);
 pub type AccountMap<K, V> = BTreeMap<K, V>;
 
+pub const DEFAULT_ACCOUNTS_INDEX_LIMIT_MB: usize = 1024;
+const DISK_INDEX_NUM_BUCKETS: usize = 256;
+// When the resident slot lists exceed the limit, page them out until they use this
+// percentage of it
+const FLUSH_TARGET_PERCENT: u64 = 90;
+
+#[derive(Debug, Default, Clone)]
+pub struct AccountsIndexConfig {
+    /// Directories for the memory-mapped bucket files slot lists are paged out to.
+    /// The index is kept entirely in memory when None.
+    pub drives: Option<Vec<PathBuf>>,
+    /// Memory, in megabytes, resident slot lists may use before they are paged out
+    pub index_limit_mb: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct AccountMapEntry<T> {
+    ref_count: AtomicU64,
+    slot_list: RwLock<SlotList<T>>,
+    // Where the slot list lives while paged out. Only changed with the slot list write lock
+    // held, so it is stable while either slot list lock is held.
+    disk_location: Mutex<Option<DiskLocation>>,
+    // Flush age of the last access. Entries not accessed since the last flush are paged out first.
+    last_access: AtomicU64,
+}
+
+impl<T> AccountMapEntry<T> {
+    fn new(ref_count: RefCount, slot_list: SlotList<T>, age: u64) -> Self {
+        Self {
+            ref_count: AtomicU64::new(ref_count),
+            slot_list: RwLock::new(slot_list),
+            disk_location: Mutex::new(None),
+            last_access: AtomicU64::new(age),
+        }
+    }
+
+    pub fn ref_count(&self) -> RefCount {
+        self.ref_count.load(Ordering::Relaxed)
+    }
+
+    fn is_paged_out(&self) -> bool {
+        self.disk_location.lock().unwrap().is_some()
+    }
+}
+
+/// Write access to a slot list which keeps the index's count of resident bytes in step with
+/// the list's allocation
+pub struct SlotListWriteGuard<'a, T> {
+    slot_list: RwLockWriteGuard<'a, SlotList<T>>,
+    resident_bytes: &'a AtomicU64,
+    capacity: usize,
+}
+
+impl<'a, T> Deref for SlotListWriteGuard<'a, T> {
+    type Target = SlotList<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.slot_list
+    }
+}
+
+impl<'a, T> DerefMut for SlotListWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.slot_list
+    }
+}
+
+impl<'a, T> Drop for SlotListWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        let capacity = self.slot_list.capacity();
+        match capacity.cmp(&self.capacity) {
+            cmp::Ordering::Greater => {
+                self.resident_bytes.fetch_add(
+                    slot_list_bytes::<T>(capacity - self.capacity),
+                    Ordering::Relaxed,
+                );
+            }
+            cmp::Ordering::Less => {
+                self.resident_bytes.fetch_sub(
+                    slot_list_bytes::<T>(self.capacity - capacity),
+                    Ordering::Relaxed,
+                );
+            }
+            cmp::Ordering::Equal => {}
+        }
+    }
+}
+
+fn slot_list_bytes<T>(capacity: usize) -> u64 {
+    (capacity * mem::size_of::<(Slot, T)>()) as u64
+}
+
+#[derive(Debug)]
+struct DiskIndex {
+    bucket_map: BucketMap,
+    limit_bytes: u64,
+    // Stamped on entries as they are accessed, bumped by every flush
+    age: AtomicU64,
+    flushing: AtomicBool,
+    // Each flush resumes its sweep of the index after the last entry the previous one looked at
+    flush_cursor: Mutex<Pubkey>,
+    // Resident bytes above which the next flush runs. Raised past the limit when a flush could
+    // not reach its target, so a pinned working set is not swept on every access.
+    flush_above_bytes: AtomicU64,
+}
+
 #[derive(Debug, Default)]
 pub struct AccountsIndex<T> {
     pub account_maps: AccountMap<Pubkey, AccountMapEntry<T>>,
@@ -21,16 +132,206 @@ pub struct AccountsIndex<T> {
     pub roots: HashSet<Slot>,
     pub uncleaned_roots: HashSet<Slot>,
     pub previous_uncleaned_roots: HashSet<Slot>,
+
+    // Slot lists are paged out to disk when set, otherwise they all stay in memory
+    disk: Option<DiskIndex>,
+    // Bytes allocated by the slot lists currently in memory
+    resident_bytes: AtomicU64,
 }
 
-impl<'a, T: 'a + Clone> AccountsIndex<T> {
+impl<'a, T: 'a + Clone + Serialize + DeserializeOwned> AccountsIndex<T> {
+    pub fn new(config: Option<&AccountsIndexConfig>) -> Self {
+        let disk = config.and_then(|config| {
+            config.drives.as_ref().map(|drives| {
+                let limit_mb = config
+                    .index_limit_mb
+                    .unwrap_or(DEFAULT_ACCOUNTS_INDEX_LIMIT_MB);
+                info!(
+                    "AccountsIndex paging slot lists to {:?} above {} MB",
+                    drives, limit_mb
+                );
+                DiskIndex {
+                    bucket_map: BucketMap::new(drives, DISK_INDEX_NUM_BUCKETS),
+                    limit_bytes: limit_mb as u64 * 1024 * 1024,
+                    age: AtomicU64::new(0),
+                    flushing: AtomicBool::new(false),
+                    flush_cursor: Mutex::new(Pubkey::default()),
+                    flush_above_bytes: AtomicU64::new(limit_mb as u64 * 1024 * 1024),
+                }
+            })
+        });
+        Self {
+            account_maps: AccountMap::default(),
+            roots: HashSet::new(),
+            uncleaned_roots: HashSet::new(),
+            previous_uncleaned_roots: HashSet::new(),
+            disk,
+            resident_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_disk_index_enabled(&self) -> bool {
+        self.disk.is_some()
+    }
+
+    /// Bytes allocated by the slot lists currently held in memory
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident_bytes.load(Ordering::Relaxed)
+    }
+
+    fn current_age(&self) -> u64 {
+        self.disk
+            .as_ref()
+            .map(|disk| disk.age.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    fn touch(&self, entry: &AccountMapEntry<T>) {
+        if self.disk.is_some() {
+            entry
+                .last_access
+                .store(self.current_age(), Ordering::Relaxed);
+        }
+    }
+
+    /// Lock the slot list of `entry` for reading, paging it back into memory if needed
+    pub fn read_slot_list<'b>(
+        &self,
+        entry: &'b AccountMapEntry<T>,
+    ) -> RwLockReadGuard<'b, SlotList<T>> {
+        loop {
+            self.touch(entry);
+            let slot_list = entry.slot_list.read().unwrap();
+            if !entry.is_paged_out() {
+                return slot_list;
+            }
+            drop(slot_list);
+            // Make room before paging in, so the flush does not immediately page this entry out
+            self.maybe_flush();
+            drop(self.write_slot_list(entry));
+        }
+    }
+
+    /// Lock the slot list of `entry` for writing, paging it back into memory if needed
+    pub fn write_slot_list<'b>(
+        &'b self,
+        entry: &'b AccountMapEntry<T>,
+    ) -> SlotListWriteGuard<'b, T> {
+        self.touch(entry);
+        let mut slot_list = entry.slot_list.write().unwrap();
+        let disk_location = entry.disk_location.lock().unwrap().take();
+        if let Some(disk_location) = disk_location {
+            let disk = self.disk.as_ref().unwrap();
+            *slot_list = bincode::deserialize(&disk.bucket_map.read(&disk_location)).unwrap();
+            disk.bucket_map.free(disk_location);
+            self.resident_bytes.fetch_add(
+                slot_list_bytes::<T>(slot_list.capacity()),
+                Ordering::Relaxed,
+            );
+        }
+        SlotListWriteGuard {
+            capacity: slot_list.capacity(),
+            slot_list,
+            resident_bytes: &self.resident_bytes,
+        }
+    }
+
+    // Write the slot list of `entry` to disk and free it from memory. Entries locked by
+    // someone else are skipped.
+    fn page_out(&self, disk: &DiskIndex, pubkey: &Pubkey, entry: &AccountMapEntry<T>) -> bool {
+        let mut slot_list = match entry.slot_list.try_write() {
+            Ok(slot_list) => slot_list,
+            Err(_) => return false,
+        };
+        let mut disk_location = entry.disk_location.lock().unwrap();
+        if disk_location.is_some() || slot_list.capacity() == 0 {
+            return false;
+        }
+        let capacity = slot_list.capacity();
+        if !slot_list.is_empty() {
+            let data = bincode::serialize(&*slot_list).unwrap();
+            // Slot lists too large for the bucket map stay in memory
+            match disk.bucket_map.write(pubkey, &data) {
+                Some(location) => *disk_location = Some(location),
+                None => return false,
+            }
+        }
+        *slot_list = SlotList::new();
+        self.resident_bytes
+            .fetch_sub(slot_list_bytes::<T>(capacity), Ordering::Relaxed);
+        true
+    }
+
+    // Page out slot lists once they use more memory than the configured limit, least recently
+    // used first. The index is swept from where the last flush stopped, so entries already paged
+    // out are not revisited by every flush.
+    fn maybe_flush(&self) {
+        let disk = match &self.disk {
+            Some(disk) => disk,
+            None => return,
+        };
+        if self.resident_bytes() <= disk.flush_above_bytes.load(Ordering::Relaxed)
+            || disk
+                .flushing
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        let mut flush_time = Measure::start("flush");
+        let target_bytes = disk.limit_bytes / 100 * FLUSH_TARGET_PERCENT;
+        let age = disk.age.fetch_add(1, Ordering::Relaxed);
+        let mut flush_cursor = disk.flush_cursor.lock().unwrap();
+        let start = *flush_cursor;
+        let mut paged_out = 0;
+        let mut visited = 0;
+        for only_stale in &[true, false] {
+            let sweep = self
+                .account_maps
+                .range((Bound::Excluded(start), Bound::Unbounded))
+                .chain(self.account_maps.range(..=start));
+            for (pubkey, entry) in sweep {
+                if self.resident_bytes() <= target_bytes {
+                    break;
+                }
+                visited += 1;
+                *flush_cursor = *pubkey;
+                if *only_stale && entry.last_access.load(Ordering::Relaxed) >= age {
+                    continue;
+                }
+                if self.page_out(disk, pubkey, entry) {
+                    paged_out += 1;
+                }
+            }
+        }
+        drop(flush_cursor);
+        let resident_bytes = self.resident_bytes();
+        let flush_above_bytes = if resident_bytes > target_bytes {
+            // Wait for the index to grow by the flush headroom before sweeping it again
+            resident_bytes + (disk.limit_bytes - target_bytes)
+        } else {
+            disk.limit_bytes
+        };
+        disk.flush_above_bytes
+            .store(flush_above_bytes, Ordering::Relaxed);
+        flush_time.stop();
+        datapoint_info!(
+            "accounts_index-flush",
+            ("paged_out", paged_out, i64),
+            ("visited", visited, i64),
+            ("resident_bytes", resident_bytes, i64),
+            ("flush_us", flush_time.as_us(), i64),
+        );
+        disk.flushing.store(false, Ordering::Release);
+    }
+
     fn do_scan_accounts<F, I>(&self, ancestors: &Ancestors, mut func: F, iter: I)
     where
         F: FnMut(&Pubkey, (&T, Slot)),
         I: Iterator<Item = (&'a Pubkey, &'a AccountMapEntry<T>)>,
     {
-        for (pubkey, list) in iter {
-            let list_r = &list.1.read().unwrap();
+        for (pubkey, entry) in iter {
+            let list_r = &self.read_slot_list(entry);
             if let Some(index) = self.latest_slot(Some(ancestors), &list_r, None) {
                 func(pubkey, (&list_r[index].1, list_r[index].0));
             }
@@ -64,25 +365,22 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
 
     // returns the rooted entries and the storage ref count
     pub fn would_purge(&self, pubkey: &Pubkey) -> (SlotList<T>, RefCount) {
-        let (ref_count, slots_list) = self.account_maps.get(&pubkey).unwrap();
-        let slots_list_r = &slots_list.read().unwrap();
-        (
-            self.get_rooted_entries(&slots_list_r),
-            ref_count.load(Ordering::Relaxed),
-        )
+        let entry = self.account_maps.get(&pubkey).unwrap();
+        let slots_list_r = &self.read_slot_list(entry);
+        (self.get_rooted_entries(&slots_list_r), entry.ref_count())
     }
 
     // filter any rooted entries and return them along with a bool that indicates
     // if this account has no more entries.
     pub fn purge(&self, pubkey: &Pubkey) -> (SlotList<T>, bool) {
-        let list = &mut self.account_maps.get(&pubkey).unwrap().1.write().unwrap();
+        let list = &mut self.write_slot_list(self.account_maps.get(&pubkey).unwrap());
         let reclaims = self.get_rooted_entries(&list);
         list.retain(|(slot, _)| !self.is_root(*slot));
         (reclaims, list.is_empty())
     }
 
     pub fn purge_exact(&self, pubkey: &Pubkey, slots: HashSet<Slot>) -> (SlotList<T>, bool) {
-        let list = &mut self.account_maps.get(&pubkey).unwrap().1.write().unwrap();
+        let list = &mut self.write_slot_list(self.account_maps.get(&pubkey).unwrap());
         let reclaims = list
             .iter()
             .filter(|(slot, _)| slots.contains(&slot))
@@ -132,8 +430,8 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
         ancestors: Option<&Ancestors>,
         max_root: Option<Slot>,
     ) -> Option<(RwLockReadGuard<SlotList<T>>, usize)> {
-        self.account_maps.get(pubkey).and_then(|list| {
-            let list_r = list.1.read().unwrap();
+        self.account_maps.get(pubkey).and_then(|entry| {
+            let list_r = self.read_slot_list(entry);
             let lock = &list_r;
             let found_index = self.latest_slot(ancestors, &lock, max_root)?;
             Some((list_r, found_index))
@@ -167,9 +465,16 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
         account_info: T,
         reclaims: &mut SlotList<T>,
     ) {
-        self.account_maps
-            .entry(*pubkey)
-            .or_insert_with(|| (AtomicU64::new(0), RwLock::new(SlotList::with_capacity(32))));
+        let age = self.current_age();
+        let resident_bytes = &self.resident_bytes;
+        self.account_maps.entry(*pubkey).or_insert_with(|| {
+            let slot_list = SlotList::with_capacity(32);
+            resident_bytes.fetch_add(
+                slot_list_bytes::<T>(slot_list.capacity()),
+                Ordering::Relaxed,
+            );
+            AccountMapEntry::new(0, slot_list, age)
+        });
         self.update(slot, pubkey, account_info, reclaims);
     }
 
@@ -184,8 +489,8 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
         account_info: T,
         reclaims: &mut SlotList<T>,
     ) -> Option<T> {
-        if let Some(lock) = self.account_maps.get(pubkey) {
-            let list = &mut lock.1.write().unwrap();
+        if let Some(entry) = self.account_maps.get(pubkey) {
+            let mut list = self.write_slot_list(entry);
             // filter out other dirty entries from the same slot
             let mut same_slot_previous_updates: Vec<(usize, (Slot, &T))> = list
                 .iter()
@@ -206,9 +511,11 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
                 list.remove(list_index);
             } else {
                 // Only increment ref count if the account was not prevously updated in this slot
-                lock.0.fetch_add(1, Ordering::Relaxed);
+                entry.ref_count.fetch_add(1, Ordering::Relaxed);
             }
             list.push((slot, account_info));
+            drop(list);
+            self.maybe_flush();
             None
         } else {
             Some(account_info)
@@ -218,14 +525,14 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
     pub fn unref_from_storage(&self, pubkey: &Pubkey) {
         let locked_entry = self.account_maps.get(pubkey);
         if let Some(entry) = locked_entry {
-            entry.0.fetch_sub(1, Ordering::Relaxed);
+            entry.ref_count.fetch_sub(1, Ordering::Relaxed);
         }
     }
 
     pub fn ref_count_from_storage(&self, pubkey: &Pubkey) -> RefCount {
         let locked_entry = self.account_maps.get(pubkey);
         if let Some(entry) = locked_entry {
-            entry.0.load(Ordering::Relaxed)
+            entry.ref_count()
         } else {
             0
         }
@@ -256,7 +563,7 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
         max_clean_root: Option<Slot>,
     ) {
         if let Some(locked_entry) = self.account_maps.get(pubkey) {
-            let mut list = locked_entry.1.write().unwrap();
+            let mut list = self.write_slot_list(locked_entry);
             self.purge_older_root_entries(&mut list, reclaims, max_clean_root);
         }
     }
@@ -268,7 +575,7 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
         reclaims: &mut SlotList<T>,
     ) {
         if let Some(entry) = self.account_maps.get(pubkey) {
-            let mut list = entry.1.write().unwrap();
+            let mut list = self.write_slot_list(entry);
             list.retain(|(slot, entry)| {
                 if *slot == purge_slot {
                     reclaims.push((*slot, entry.clone()));
@@ -279,11 +586,28 @@ impl<'a, T: 'a + Clone> AccountsIndex<T> {
     }
 
     pub fn add_index(&mut self, slot: Slot, pubkey: &Pubkey, account_info: T) {
-        let entry = self
-            .account_maps
+        let age = self.current_age();
+        self.account_maps
             .entry(*pubkey)
-            .or_insert_with(|| (AtomicU64::new(1), RwLock::new(vec![])));
-        entry.1.write().unwrap().push((slot, account_info));
+            .or_insert_with(|| AccountMapEntry::new(1, vec![], age));
+        let entry = self.account_maps.get(pubkey).unwrap();
+        self.write_slot_list(entry).push((slot, account_info));
+        self.maybe_flush();
+    }
+
+    /// Remove the entry for `pubkey` if its slot list is empty
+    pub fn remove_if_empty(&mut self, pubkey: &Pubkey) {
+        let is_empty = self
+            .account_maps
+            .get(pubkey)
+            .map(|entry| self.read_slot_list(entry).is_empty())
+            .unwrap_or(false);
+        if is_empty {
+            let entry = self.account_maps.remove(pubkey).unwrap();
+            let capacity = entry.slot_list.read().unwrap().capacity();
+            self.resident_bytes
+                .fetch_sub(slot_list_bytes::<T>(capacity), Ordering::Relaxed);
+        }
     }
 
     pub fn can_purge(max_root: Slot, slot: Slot) -> bool {
@@ -679,4 +1003,148 @@ mod tests {
         assert_eq!(reclaims, vec![(1, true), (2, true)]);
         assert_eq!(slot_list, vec![(5, true), (9, true)]);
     }
+
+    fn new_disk_index<T: Clone + Serialize + DeserializeOwned + Default>(
+        drive: &tempfile::TempDir,
+    ) -> AccountsIndex<T> {
+        // A zero limit pages every slot list out as soon as it is written
+        AccountsIndex::new(Some(&AccountsIndexConfig {
+            drives: Some(vec![drive.path().to_path_buf()]),
+            index_limit_mb: Some(0),
+        }))
+    }
+
+    #[test]
+    fn test_disk_index_get_and_scan() {
+        let drive = tempfile::TempDir::new().unwrap();
+        let mut index = new_disk_index::<u64>(&drive);
+        assert!(index.is_disk_index_enabled());
+        let keys: Vec<_> = (0..100).map(|_| Pubkey::new_unique()).collect();
+        let mut gc = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            index.insert(0, key, i as u64, &mut gc);
+            index.insert(1, key, i as u64 + 1000, &mut gc);
+        }
+        assert!(gc.is_empty());
+        assert_eq!(index.resident_bytes(), 0);
+        assert!(index
+            .account_maps
+            .values()
+            .all(|entry| entry.is_paged_out()));
+
+        let ancestors = vec![(0, 0)].into_iter().collect();
+        for (i, key) in keys.iter().enumerate() {
+            let (list, idx) = index.get(key, Some(&ancestors), None).unwrap();
+            assert_eq!(list[idx], (0, i as u64));
+            assert_eq!(list.len(), 2);
+        }
+        index.add_root(1);
+        let mut found = HashMap::new();
+        index.scan_accounts(&Ancestors::new(), |pubkey, (value, slot)| {
+            assert_eq!(slot, 1);
+            found.insert(*pubkey, *value);
+        });
+        assert_eq!(found.len(), keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(found[key], i as u64 + 1000);
+            assert_eq!(index.ref_count_from_storage(key), 2);
+        }
+
+        let mut sorted_keys = keys;
+        sorted_keys.sort();
+        let mut scanned = vec![];
+        index.range_scan_accounts(
+            &Ancestors::new(),
+            sorted_keys[10]..sorted_keys[20],
+            |pubkey, _| scanned.push(*pubkey),
+        );
+        assert_eq!(scanned, sorted_keys[10..20].to_vec());
+    }
+
+    #[test]
+    fn test_disk_index_update_purge_and_clean() {
+        let drive = tempfile::TempDir::new().unwrap();
+        let mut index = new_disk_index::<u64>(&drive);
+        let key = Pubkey::new_unique();
+        let mut gc = Vec::new();
+        index.insert(1, &key, 12, &mut gc);
+        // same slot update reclaims the paged out value
+        assert_eq!(None, index.update(1, &key, 10, &mut gc));
+        assert_eq!(gc, vec![(1, 12)]);
+        assert_eq!(index.ref_count_from_storage(&key), 1);
+
+        index.insert(2, &key, 20, &mut gc);
+        index.insert(3, &key, 30, &mut gc);
+        index.add_root(1);
+        index.add_root(2);
+        let mut reclaims = vec![];
+        index.clean_rooted_entries(&key, &mut reclaims, None);
+        assert_eq!(reclaims, vec![(1, 10)]);
+        assert_eq!(index.would_purge(&key), (vec![(2, 20)], 3));
+
+        let mut reclaims = vec![];
+        index.clean_unrooted_entries_by_slot(3, &key, &mut reclaims);
+        assert_eq!(reclaims, vec![(3, 30)]);
+        assert_eq!(index.purge(&key), (vec![(2, 20)], true));
+
+        index.remove_if_empty(&key);
+        assert!(index.account_maps.get(&key).is_none());
+        assert_eq!(index.resident_bytes(), 0);
+    }
+
+    #[test]
+    fn test_disk_index_limit() {
+        let drive = tempfile::TempDir::new().unwrap();
+        let mut index = AccountsIndex::<u64>::new(Some(&AccountsIndexConfig {
+            drives: Some(vec![drive.path().to_path_buf()]),
+            index_limit_mb: Some(1),
+        }));
+        let mut gc = Vec::new();
+        let keys: Vec<_> = (0..10_000).map(|_| Pubkey::new_unique()).collect();
+        for key in &keys {
+            index.insert(0, key, 1, &mut gc);
+        }
+        assert!(index.resident_bytes() <= 1024 * 1024);
+        assert!(index
+            .account_maps
+            .values()
+            .any(|entry| entry.is_paged_out()));
+        index.add_root(0);
+        for key in &keys {
+            let (list, idx) = index.get(key, None, None).unwrap();
+            assert_eq!(list[idx], (0, 1));
+        }
+    }
+
+    #[test]
+    fn test_disk_index_keeps_oversized_slot_list() {
+        let drive = tempfile::TempDir::new().unwrap();
+        let index = new_disk_index::<u64>(&drive);
+        let disk = index.disk.as_ref().unwrap();
+        let key = Pubkey::new_unique();
+        let slot_list_len = BucketMap::max_blob_len() / mem::size_of::<(Slot, u64)>() + 1;
+        let entry = AccountMapEntry::new(1, vec![(0, 0); slot_list_len], 0);
+        assert!(!index.page_out(disk, &key, &entry));
+        assert!(!entry.is_paged_out());
+        assert_eq!(index.read_slot_list(&entry).len(), slot_list_len);
+
+        let entry = AccountMapEntry::new(1, vec![(0, 0)], 0);
+        assert!(index.page_out(disk, &key, &entry));
+        assert!(entry.is_paged_out());
+    }
+
+    #[test]
+    fn test_remove_if_empty() {
+        let mut index = AccountsIndex::<bool>::default();
+        let key = Pubkey::new_unique();
+        let mut gc = Vec::new();
+        index.insert(0, &key, true, &mut gc);
+        index.remove_if_empty(&key);
+        assert!(index.account_maps.get(&key).is_some());
+        index.add_root(0);
+        index.purge(&key);
+        index.remove_if_empty(&key);
+        assert!(index.account_maps.get(&key).is_none());
+        assert_eq!(index.resident_bytes(), 0);
+    }
 }
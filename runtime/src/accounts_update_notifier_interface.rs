@@ -0,0 +1,33 @@
+//! The `accounts_update_notifier_interface` module defines the hooks `AccountsDB` calls to
+//! stream account writes to an external observer, such as an AccountsDb plugin
+use {
+    solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey},
+    std::{fmt::Debug, sync::Arc},
+};
+
+pub trait AccountsUpdateNotifierInterface: Debug {
+    /// Notified when an account is updated at runtime, due to transaction activities
+    fn notify_account_update(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &Account,
+        write_version: u64,
+    );
+
+    /// Notified when the AccountsDB is initialized at start when restored
+    /// from a snapshot.
+    fn notify_account_restore_from_snapshot(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &Account,
+        write_version: u64,
+    );
+
+    /// Notified once all the accounts of a snapshot have been streamed, or once the genesis
+    /// accounts have been stored when starting without a snapshot
+    fn notify_end_of_restore_from_snapshot(&self);
+}
+
+pub type AccountsUpdateNotifier = Arc<dyn AccountsUpdateNotifierInterface + Sync + Send>;
@@ -7,8 +7,9 @@ use crate::{
         AccountAddressFilter, Accounts, TransactionAccounts, TransactionLoadResult,
         TransactionLoaders,
     },
-    accounts_db::{ErrorCounters, SnapshotStorages},
-    accounts_index::Ancestors,
+    accounts_db::{AccountsDB, ErrorCounters, SnapshotStorages},
+    accounts_index::{AccountsIndexConfig, Ancestors},
+    accounts_update_notifier_interface::AccountsUpdateNotifier,
     blockhash_queue::BlockhashQueue,
     builtins,
     epoch_stakes::{EpochStakes, NodeVoteAccounts},
@@ -16,7 +17,7 @@ use crate::{
     feature_set::{self, FeatureSet},
     instruction_recorder::InstructionRecorder,
     log_collector::LogCollector,
-    message_processor::{Executors, MessageProcessor},
+    message_processor::{ExecuteDetailsTimings, Executors, MessageProcessor},
     process_instruction::{
         ErasedProcessInstruction, ErasedProcessInstructionWithContext, Executor,
         ProcessInstruction, ProcessInstructionWithContext,
@@ -725,7 +726,7 @@ impl Default for BlockhashQueue {
 
 impl Bank {
     pub fn new(genesis_config: &GenesisConfig) -> Self {
-        Self::new_with_paths(&genesis_config, Vec::new(), &[], None, None)
+        Self::new_with_paths(&genesis_config, Vec::new(), &[], None, None, None, None)
     }
 
     pub fn new_with_paths(
@@ -734,15 +735,27 @@ impl Bank {
         frozen_account_pubkeys: &[Pubkey],
         debug_keys: Option<Arc<HashSet<Pubkey>>>,
         additional_builtins: Option<&Builtins>,
+        accounts_update_notifier: Option<AccountsUpdateNotifier>,
+        accounts_index_config: Option<AccountsIndexConfig>,
     ) -> Self {
         let mut bank = Self::default();
         bank.transaction_debug_keys = debug_keys;
         bank.cluster_type = Some(genesis_config.cluster_type);
         bank.ancestors.insert(bank.slot(), 0);
 
-        bank.rc.accounts = Arc::new(Accounts::new(paths, &genesis_config.cluster_type));
+        let mut accounts_db =
+            AccountsDB::new_with_config(paths, &genesis_config.cluster_type, accounts_index_config);
+        accounts_db.accounts_update_notifier = accounts_update_notifier;
+        bank.rc.accounts = Arc::new(Accounts::new_empty(accounts_db));
         bank.process_genesis_config(genesis_config);
         bank.finish_init(genesis_config, additional_builtins);
+        // The genesis accounts were streamed as updates; tell the notifier startup is done, as
+        // the snapshot path does once its accounts are restored
+        if let Some(accounts_update_notifier) =
+            &bank.rc.accounts.accounts_db.accounts_update_notifier
+        {
+            accounts_update_notifier.notify_end_of_restore_from_snapshot();
+        }
 
         // Freeze accounts after process_genesis_config creates the initial append vecs
         Arc::get_mut(&mut Arc::get_mut(&mut bank.rc.accounts).unwrap().accounts_db)
@@ -1613,6 +1626,19 @@ impl Bank {
         self.parent_slot
     }
 
+    pub fn parent_hash(&self) -> Hash {
+        self.parent_hash
+    }
+
+    pub fn rent_collector(&self) -> &RentCollector {
+        &self.rent_collector
+    }
+
+    /// The native programs available to this bank's transactions
+    pub fn builtin_programs(&self) -> &[(Pubkey, ProcessInstruction)] {
+        self.message_processor.programs()
+    }
+
     fn process_genesis_config(&mut self, genesis_config: &GenesisConfig) {
         // Bootstrap validator collects fees until `new_from_parent` is called.
         self.fee_rate_governor = genesis_config.fee_rate_governor.clone();
@@ -1888,7 +1914,13 @@ impl Bank {
             _retryable_transactions,
             _transaction_count,
             _signature_count,
-        ) = self.load_and_execute_transactions(&batch, MAX_PROCESSING_AGE, false, true);
+        ) = self.load_and_execute_transactions(
+            &batch,
+            MAX_PROCESSING_AGE,
+            false,
+            true,
+            &mut ExecuteDetailsTimings::default(),
+        );
 
         let transaction_result = executed[0].0.clone().map(|_| ());
         let log_messages = transaction_logs
@@ -1998,6 +2030,33 @@ impl Bank {
             })
             .collect()
     }
+    // The precompile programs do nothing when executed; their instructions are checked here so
+    // that the bank does not depend on them having been verified upstream
+    fn check_precompiles(
+        &self,
+        txs: &[Transaction],
+        iteration_order: Option<&[usize]>,
+        lock_results: Vec<TransactionProcessResult>,
+        error_counters: &mut ErrorCounters,
+    ) -> Vec<TransactionProcessResult> {
+        let secp256k1_program_enabled = self.secp256k1_program_enabled();
+        let ed25519_program_enabled = self.ed25519_program_enabled();
+        OrderedIterator::new(txs, iteration_order)
+            .zip(lock_results.into_iter())
+            .map(|((_, tx), lock_res)| {
+                if lock_res.0.is_ok() {
+                    if let Err(err) = tx.verify_precompiles_with_features(
+                        secp256k1_program_enabled,
+                        ed25519_program_enabled,
+                    ) {
+                        error_counters.invalid_account_index += 1;
+                        return (Err(err), lock_res.1);
+                    }
+                }
+                lock_res
+            })
+            .collect()
+    }
     fn filter_by_vote_transactions(
         &self,
         txs: &[Transaction],
@@ -2085,16 +2144,21 @@ impl Bank {
         );
         let sigcheck_results =
             self.check_signatures(txs, iteration_order, age_results, &mut error_counters);
+        let precompile_results = if self.ed25519_program_enabled() {
+            self.check_precompiles(txs, iteration_order, sigcheck_results, &mut error_counters)
+        } else {
+            sigcheck_results
+        };
         if self.upgrade_epoch() {
             // Reject all non-vote transactions
             self.filter_by_vote_transactions(
                 txs,
                 iteration_order,
-                sigcheck_results,
+                precompile_results,
                 &mut error_counters,
             )
         } else {
-            sigcheck_results
+            precompile_results
         }
     }
 
@@ -2316,6 +2380,7 @@ impl Bank {
         max_age: usize,
         enable_cpi_recording: bool,
         enable_log_recording: bool,
+        timings: &mut ExecuteDetailsTimings,
     ) -> (
         Vec<(Result<TransactionLoadResult>, Option<HashAgeKind>)>,
         Vec<TransactionProcessResult>,
@@ -2402,6 +2467,7 @@ impl Bank {
                         executors.clone(),
                         instruction_recorders.as_deref(),
                         self.feature_set.clone(),
+                        timings,
                     );
 
                     if enable_log_recording {
@@ -2501,6 +2567,7 @@ impl Bank {
 
         let fee_config = FeeConfig {
             secp256k1_program_enabled: self.secp256k1_program_enabled(),
+            ed25519_program_enabled: self.ed25519_program_enabled(),
         };
 
         let results = OrderedIterator::new(txs, iteration_order)
@@ -3197,6 +3264,7 @@ impl Bank {
             max_age,
             enable_cpi_recording,
             enable_log_recording,
+            &mut ExecuteDetailsTimings::default(),
         );
 
         let results = self.commit_transactions(
@@ -3895,6 +3963,11 @@ impl Bank {
             .is_active(&feature_set::secp256k1_program_enabled::id())
     }
 
+    pub fn ed25519_program_enabled(&self) -> bool {
+        self.feature_set
+            .is_active(&feature_set::ed25519_program_enabled::id())
+    }
+
     pub fn no_overflow_rent_distribution_enabled(&self) -> bool {
         self.feature_set
             .is_active(&feature_set::no_overflow_rent_distribution::id())
@@ -4211,6 +4284,31 @@ mod tests {
         assert_eq!(rent.lamports_per_byte_year, 5);
     }
 
+    #[test]
+    fn test_bank_new_notifies_end_of_startup() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let recorder = Arc::new(crate::accounts_db::tests::AccountsUpdateRecorder::default());
+        let bank = Bank::new_with_paths(
+            &genesis_config,
+            Vec::new(),
+            &[],
+            None,
+            None,
+            Some(recorder.clone()),
+            None,
+        );
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), 10_000);
+        assert!(recorder
+            .updates
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|update| update.1 == mint_keypair.pubkey() && !update.4));
+        assert!(recorder
+            .end_of_restore
+            .load(std::sync::atomic::Ordering::Relaxed));
+    }
+
     #[test]
     fn test_bank_block_height() {
         let (genesis_config, _mint_keypair) = create_genesis_config(1);
@@ -6741,6 +6839,43 @@ mod tests {
         assert!(batch2.lock_results()[0].is_ok());
     }
 
+    #[test]
+    fn test_bank_rejects_failed_precompile() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(10_000);
+        activate_all_features(&mut genesis_config);
+        let bank = Bank::new(&genesis_config);
+        assert!(bank.ed25519_program_enabled());
+
+        let signer = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+        let instruction = solana_sdk::ed25519_instruction::new_ed25519_instruction(&signer, b"hi");
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction.clone()],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            bank.last_blockhash(),
+        );
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+        // Flip a byte of the signed message
+        let mut instruction = instruction;
+        let last = instruction.data.len() - 1;
+        instruction.data[last] ^= 0xff;
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            bank.last_blockhash(),
+        );
+        let balance = bank.get_balance(&mint_keypair.pubkey());
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(TransactionError::InvalidAccountIndex)
+        );
+        // Rejected without being charged a fee
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), balance);
+        assert_eq!(bank.get_signature_status(&tx.signatures[0]), None);
+    }
+
     #[test]
     fn test_bank_invalid_account_index() {
         let (genesis_config, mint_keypair) = create_genesis_config(1);
@@ -0,0 +1,268 @@
+//! The inputs to a bank's hash, recorded so that the bank hashes of two nodes that disagree can
+//! be compared input by input
+use crate::bank::Bank;
+use byteorder::{ByteOrder, LittleEndian};
+use solana_sdk::clock::{Epoch, Slot};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BankHashAccount {
+    pub pubkey: String,
+    /// The account's contribution to the accounts delta hash
+    pub hash: String,
+    pub lamports: u64,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+    /// Base64 encoded account data
+    pub data: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BankHashDetails {
+    pub slot: Slot,
+    pub bank_hash: String,
+    pub parent_bank_hash: String,
+    pub accounts_delta_hash: String,
+    pub signature_count: u64,
+    pub last_blockhash: String,
+    /// Number of hard forks activated at this slot, if any
+    pub hard_fork_count: Option<u64>,
+    /// Every account stored in the slot, ordered by pubkey
+    pub accounts: Vec<BankHashAccount>,
+}
+
+impl BankHashDetails {
+    /// Records the hash inputs of `bank`, which must be frozen
+    pub fn new(bank: &Bank) -> Self {
+        assert!(bank.is_frozen());
+        let slot = bank.slot();
+        let accounts = bank
+            .rc
+            .accounts
+            .accounts_db
+            .get_accounts_delta_hash_details(slot)
+            .into_iter()
+            .map(|(pubkey, hash, account)| BankHashAccount {
+                pubkey: pubkey.to_string(),
+                hash: hash.to_string(),
+                lamports: account.lamports,
+                owner: account.owner.to_string(),
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+                data: base64::encode(&account.data),
+            })
+            .collect();
+        let hard_fork_count = bank
+            .hard_forks()
+            .read()
+            .unwrap()
+            .get_hash_data(slot, bank.parent_slot())
+            .map(|buf| LittleEndian::read_u64(&buf));
+
+        Self {
+            slot,
+            bank_hash: bank.hash().to_string(),
+            parent_bank_hash: bank.parent_hash().to_string(),
+            accounts_delta_hash: bank.rc.accounts.bank_hash_at(slot).to_string(),
+            signature_count: bank.signature_count(),
+            last_blockhash: bank.last_blockhash().to_string(),
+            hard_fork_count,
+            accounts,
+        }
+    }
+
+    /// Describes each difference between `self` and `other`, one per line. Nothing is returned
+    /// if the two are identical
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let mut differences = vec![];
+        let mut compare = |name: &str, a: String, b: String| {
+            if a != b {
+                differences.push(format!("{}: {} != {}", name, a, b));
+            }
+        };
+        compare("slot", self.slot.to_string(), other.slot.to_string());
+        compare("bank hash", self.bank_hash.clone(), other.bank_hash.clone());
+        compare(
+            "parent bank hash",
+            self.parent_bank_hash.clone(),
+            other.parent_bank_hash.clone(),
+        );
+        compare(
+            "accounts delta hash",
+            self.accounts_delta_hash.clone(),
+            other.accounts_delta_hash.clone(),
+        );
+        compare(
+            "signature count",
+            self.signature_count.to_string(),
+            other.signature_count.to_string(),
+        );
+        compare(
+            "last blockhash",
+            self.last_blockhash.clone(),
+            other.last_blockhash.clone(),
+        );
+        compare(
+            "hard fork count",
+            format!("{:?}", self.hard_fork_count),
+            format!("{:?}", other.hard_fork_count),
+        );
+
+        let mut accounts: BTreeMap<&str, (Option<&BankHashAccount>, Option<&BankHashAccount>)> =
+            BTreeMap::new();
+        for account in &self.accounts {
+            accounts.entry(&account.pubkey).or_default().0 = Some(account);
+        }
+        for account in &other.accounts {
+            accounts.entry(&account.pubkey).or_default().1 = Some(account);
+        }
+        for (pubkey, accounts) in accounts {
+            match accounts {
+                (Some(account), None) => differences.push(format!(
+                    "account {}: only in the first, {}",
+                    pubkey,
+                    describe_account(account)
+                )),
+                (None, Some(account)) => differences.push(format!(
+                    "account {}: only in the second, {}",
+                    pubkey,
+                    describe_account(account)
+                )),
+                (Some(a), Some(b)) if a != b => {
+                    differences.push(format!("account {}: {}", pubkey, diff_accounts(a, b)))
+                }
+                _ => {}
+            }
+        }
+        differences
+    }
+}
+
+fn describe_account(account: &BankHashAccount) -> String {
+    format!(
+        "hash {}, lamports {}, owner {}, executable {}, rent epoch {}, data length {}",
+        account.hash,
+        account.lamports,
+        account.owner,
+        account.executable,
+        account.rent_epoch,
+        base64::decode(&account.data).map_or(0, |data| data.len()),
+    )
+}
+
+fn diff_accounts(a: &BankHashAccount, b: &BankHashAccount) -> String {
+    let mut differences = vec![];
+    if a.hash != b.hash {
+        differences.push(format!("hash {} != {}", a.hash, b.hash));
+    }
+    if a.lamports != b.lamports {
+        differences.push(format!("lamports {} != {}", a.lamports, b.lamports));
+    }
+    if a.owner != b.owner {
+        differences.push(format!("owner {} != {}", a.owner, b.owner));
+    }
+    if a.executable != b.executable {
+        differences.push(format!("executable {} != {}", a.executable, b.executable));
+    }
+    if a.rent_epoch != b.rent_epoch {
+        differences.push(format!("rent epoch {} != {}", a.rent_epoch, b.rent_epoch));
+    }
+    if a.data != b.data {
+        let a_data = base64::decode(&a.data).unwrap_or_default();
+        let b_data = base64::decode(&b.data).unwrap_or_default();
+        let first_difference = a_data
+            .iter()
+            .zip(&b_data)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| a_data.len().min(b_data.len()));
+        differences.push(format!(
+            "data length {} vs {}, first difference at offset {}",
+            a_data.len(),
+            b_data.len(),
+            first_difference
+        ));
+    }
+    differences.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genesis_utils::{create_genesis_config, GenesisConfigInfo};
+    use solana_sdk::{pubkey::Pubkey, signature::Signer};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_bank_hash_details() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        let to = Pubkey::new_unique();
+        bank1.transfer(42, &mint_keypair, &to).unwrap();
+        bank1.freeze();
+
+        let details = BankHashDetails::new(&bank1);
+        assert_eq!(details.slot, 1);
+        assert_eq!(details.bank_hash, bank1.hash().to_string());
+        assert_eq!(details.parent_bank_hash, bank0.hash().to_string());
+        assert_eq!(details.signature_count, 1);
+        assert_eq!(details.last_blockhash, bank1.last_blockhash().to_string());
+        assert_eq!(details.hard_fork_count, None);
+        let to_account = details
+            .accounts
+            .iter()
+            .find(|account| account.pubkey == to.to_string())
+            .unwrap();
+        assert_eq!(to_account.lamports, 42);
+        assert!(details
+            .accounts
+            .iter()
+            .any(|account| account.pubkey == mint_keypair.pubkey().to_string()));
+        assert!(details.diff(&details).is_empty());
+    }
+
+    #[test]
+    fn test_bank_hash_details_diff() {
+        let account = |pubkey: &str, lamports| BankHashAccount {
+            pubkey: pubkey.to_string(),
+            hash: format!("hash-{}-{}", pubkey, lamports),
+            lamports,
+            data: base64::encode(&[1, 2, 3]),
+            ..BankHashAccount::default()
+        };
+        let details = BankHashDetails {
+            slot: 1,
+            bank_hash: "bank".to_string(),
+            accounts: vec![account("a", 1), account("b", 2)],
+            ..BankHashDetails::default()
+        };
+
+        let mut other = details.clone();
+        other.bank_hash = "other bank".to_string();
+        other.accounts = vec![account("b", 3), account("c", 4)];
+        other.accounts[0].data = base64::encode(&[1, 5]);
+
+        assert_eq!(
+            details.diff(&other),
+            vec![
+                "bank hash: bank != other bank".to_string(),
+                "account a: only in the first, hash hash-a-1, lamports 1, owner , \
+                 executable false, rent epoch 0, data length 3"
+                    .to_string(),
+                "account b: hash hash-b-2 != hash-b-3, lamports 2 != 3, \
+                 data length 3 vs 2, first difference at offset 1"
+                    .to_string(),
+                "account c: only in the second, hash hash-c-4, lamports 4, owner , \
+                 executable false, rent epoch 0, data length 3"
+                    .to_string(),
+            ]
+        );
+    }
+}
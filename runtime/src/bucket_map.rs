@@ -0,0 +1,332 @@
+//! The `bucket_map` module stores byte blobs keyed by pubkey in memory-mapped files so they
+//! can be dropped from memory. Pubkeys are partitioned into buckets by their leading bits, and
+//! each bucket keeps one file per cell size, growing it by doubling as cells are handed out.
+//! The AccountsIndex uses it to page slot lists out of memory. Every map keeps its files in
+//! its own subdirectory of the given paths, which is removed when the map is dropped.
+
+use log::*;
+use memmap::MmapMut;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    convert::TryInto,
+    fmt,
+    fs::{create_dir, create_dir_all, remove_dir_all, remove_file, File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Size of the smallest cell, every larger size class doubles it
+const MIN_CELL_SIZE: usize = 64;
+const NUM_SIZE_CLASSES: usize = 16;
+/// Number of cells a storage file is created with
+const INITIAL_CAPACITY: u64 = 1024;
+/// Each cell starts with the length of the blob it holds
+const CELL_HEADER_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Where a blob was written, handed back to `read` and `free`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskLocation {
+    bucket: u32,
+    size_class: u8,
+    index: u64,
+}
+
+struct BucketStorage {
+    path: PathBuf,
+    file: File,
+    mmap: MmapMut,
+    cell_size: usize,
+    capacity: u64,
+    /// Cells at or above `next_unused` have never been handed out
+    next_unused: u64,
+    free_list: Vec<u64>,
+}
+
+impl Drop for BucketStorage {
+    fn drop(&mut self) {
+        let _ignored = remove_file(&self.path);
+    }
+}
+
+impl BucketStorage {
+    fn new(path: PathBuf, cell_size: usize) -> Self {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .unwrap_or_else(|err| panic!("Unable to create bucket file {:?}: {}", path, err));
+        let mmap = Self::map(&file, &path, cell_size, INITIAL_CAPACITY);
+        Self {
+            path,
+            file,
+            mmap,
+            cell_size,
+            capacity: INITIAL_CAPACITY,
+            next_unused: 0,
+            free_list: vec![],
+        }
+    }
+
+    fn map(file: &File, path: &Path, cell_size: usize, capacity: u64) -> MmapMut {
+        file.set_len(capacity * cell_size as u64)
+            .unwrap_or_else(|err| panic!("Unable to grow bucket file {:?}: {}", path, err));
+        //UNSAFE: Required to create a Mmap. The file is private to this storage.
+        unsafe { MmapMut::map_mut(file) }
+            .unwrap_or_else(|err| panic!("Unable to map bucket file {:?}: {}", path, err))
+    }
+
+    fn allocate(&mut self) -> u64 {
+        if let Some(index) = self.free_list.pop() {
+            return index;
+        }
+        if self.next_unused == self.capacity {
+            self.capacity *= 2;
+            self.mmap = Self::map(&self.file, &self.path, self.cell_size, self.capacity);
+        }
+        self.next_unused += 1;
+        self.next_unused - 1
+    }
+
+    fn cell(&self, index: u64) -> &[u8] {
+        let start = index as usize * self.cell_size;
+        &self.mmap[start..start + self.cell_size]
+    }
+
+    fn cell_mut(&mut self, index: u64) -> &mut [u8] {
+        let start = index as usize * self.cell_size;
+        &mut self.mmap[start..start + self.cell_size]
+    }
+}
+
+#[derive(Default)]
+struct Bucket {
+    storages: Vec<Option<BucketStorage>>,
+}
+
+pub struct BucketMap {
+    buckets: Vec<Mutex<Bucket>>,
+    bucket_bits: u32,
+    /// The subdirectories of this map, one per path it was created with
+    paths: Vec<PathBuf>,
+}
+
+impl Drop for BucketMap {
+    fn drop(&mut self) {
+        // Unmap and remove the bucket files before their directories
+        self.buckets.clear();
+        for path in &self.paths {
+            let _ignored = remove_dir_all(path);
+        }
+    }
+}
+
+impl fmt::Debug for BucketMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BucketMap")
+            .field("num_buckets", &self.buckets.len())
+            .field("paths", &self.paths)
+            .finish()
+    }
+}
+
+impl BucketMap {
+    /// Create a map with `num_buckets` buckets, which is rounded up to a power of two, spreading
+    /// the bucket files over a new subdirectory of each of `paths`
+    pub fn new(paths: &[PathBuf], num_buckets: usize) -> Self {
+        assert!(!paths.is_empty());
+        let num_buckets = num_buckets.max(1).next_power_of_two();
+        Self {
+            buckets: (0..num_buckets).map(|_| Mutex::default()).collect(),
+            bucket_bits: num_buckets.trailing_zeros(),
+            paths: paths
+                .iter()
+                .map(|path| Self::create_unique_dir(path))
+                .collect(),
+        }
+    }
+
+    fn create_unique_dir(path: &Path) -> PathBuf {
+        create_dir_all(path).expect("Create directory failed.");
+        loop {
+            let dir = path.join(format!("bucket_map-{:016x}", rand::random::<u64>()));
+            match create_dir(&dir) {
+                Ok(()) => return dir,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => panic!("Unable to create bucket map directory {:?}: {}", dir, err),
+            }
+        }
+    }
+
+    pub fn num_buckets(&self) -> usize {
+        self.buckets.len()
+    }
+
+    fn bucket_index(&self, pubkey: &Pubkey) -> usize {
+        if self.bucket_bits == 0 {
+            return 0;
+        }
+        let prefix = u64::from_be_bytes(pubkey.as_ref()[0..8].try_into().unwrap());
+        (prefix >> (64 - self.bucket_bits)) as usize
+    }
+
+    // None when `len` does not fit in the largest cell size
+    fn size_class(len: usize) -> Option<usize> {
+        let needed = len + CELL_HEADER_SIZE;
+        let mut size_class = 0;
+        while MIN_CELL_SIZE << size_class < needed {
+            size_class += 1;
+            if size_class == NUM_SIZE_CLASSES {
+                return None;
+            }
+        }
+        Some(size_class)
+    }
+
+    /// Largest blob `write` accepts
+    pub fn max_blob_len() -> usize {
+        (MIN_CELL_SIZE << (NUM_SIZE_CLASSES - 1)) - CELL_HEADER_SIZE
+    }
+
+    fn storage_path(&self, bucket: usize, size_class: usize) -> PathBuf {
+        self.paths[bucket % self.paths.len()].join(format!("bucket-{}-{}", bucket, size_class))
+    }
+
+    /// Write `data` into the bucket of `pubkey`, returning where it was stored, or None if it
+    /// is larger than `max_blob_len`
+    pub fn write(&self, pubkey: &Pubkey, data: &[u8]) -> Option<DiskLocation> {
+        let bucket_index = self.bucket_index(pubkey);
+        let size_class = Self::size_class(data.len())?;
+        let mut bucket = self.buckets[bucket_index].lock().unwrap();
+        if bucket.storages.len() <= size_class {
+            bucket.storages.resize_with(size_class + 1, || None);
+        }
+        if bucket.storages[size_class].is_none() {
+            let path = self.storage_path(bucket_index, size_class);
+            trace!("creating bucket storage {:?}", path);
+            bucket.storages[size_class] =
+                Some(BucketStorage::new(path, MIN_CELL_SIZE << size_class));
+        }
+        let storage = bucket.storages[size_class].as_mut().unwrap();
+        let index = storage.allocate();
+        let cell = storage.cell_mut(index);
+        cell[..CELL_HEADER_SIZE].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        cell[CELL_HEADER_SIZE..CELL_HEADER_SIZE + data.len()].copy_from_slice(data);
+        Some(DiskLocation {
+            bucket: bucket_index as u32,
+            size_class: size_class as u8,
+            index,
+        })
+    }
+
+    /// Read back the blob stored at `location`
+    pub fn read(&self, location: &DiskLocation) -> Vec<u8> {
+        let bucket = self.buckets[location.bucket as usize].lock().unwrap();
+        let storage = bucket.storages[location.size_class as usize]
+            .as_ref()
+            .unwrap();
+        let cell = storage.cell(location.index);
+        let len = u32::from_le_bytes(cell[..CELL_HEADER_SIZE].try_into().unwrap()) as usize;
+        cell[CELL_HEADER_SIZE..CELL_HEADER_SIZE + len].to_vec()
+    }
+
+    /// Release the cell at `location` so it can be reused
+    pub fn free(&self, location: DiskLocation) {
+        let mut bucket = self.buckets[location.bucket as usize].lock().unwrap();
+        let storage = bucket.storages[location.size_class as usize]
+            .as_mut()
+            .unwrap();
+        storage.free_list.push(location.index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_map_read_write_free() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bucket_map = BucketMap::new(&[dir.path().to_path_buf()], 3);
+        assert_eq!(bucket_map.num_buckets(), 4);
+
+        let pubkey = Pubkey::new_unique();
+        let small = vec![1u8; 10];
+        let large = vec![2u8; 1000];
+        let small_location = bucket_map.write(&pubkey, &small).unwrap();
+        let large_location = bucket_map.write(&pubkey, &large).unwrap();
+        assert_ne!(small_location.size_class, large_location.size_class);
+        assert_eq!(bucket_map.read(&small_location), small);
+        assert_eq!(bucket_map.read(&large_location), large);
+
+        // freed cells are handed out again
+        bucket_map.free(small_location);
+        let other = vec![3u8; 20];
+        let other_location = bucket_map.write(&pubkey, &other).unwrap();
+        assert_eq!(other_location, small_location);
+        assert_eq!(bucket_map.read(&other_location), other);
+        assert_eq!(bucket_map.read(&large_location), large);
+    }
+
+    #[test]
+    fn test_bucket_map_grow() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bucket_map = BucketMap::new(&[dir.path().to_path_buf()], 1);
+        let pubkey = Pubkey::new_unique();
+        let locations: Vec<_> = (0..INITIAL_CAPACITY * 2 + 1)
+            .map(|i| (i, bucket_map.write(&pubkey, &i.to_le_bytes()).unwrap()))
+            .collect();
+        for (i, location) in locations {
+            assert_eq!(bucket_map.read(&location), i.to_le_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_bucket_map_blob_too_large() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bucket_map = BucketMap::new(&[dir.path().to_path_buf()], 1);
+        let pubkey = Pubkey::new_unique();
+        let largest = vec![4u8; BucketMap::max_blob_len()];
+        let location = bucket_map.write(&pubkey, &largest).unwrap();
+        assert_eq!(bucket_map.read(&location), largest);
+        assert_eq!(
+            bucket_map.write(&pubkey, &vec![5u8; BucketMap::max_blob_len() + 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bucket_map_dirs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bucket_map1 = BucketMap::new(&[dir.path().to_path_buf()], 1);
+        let bucket_map2 = BucketMap::new(&[dir.path().to_path_buf()], 1);
+        assert_ne!(bucket_map1.paths, bucket_map2.paths);
+
+        // Maps sharing a path keep their files apart
+        let pubkey = Pubkey::new_unique();
+        let location1 = bucket_map1.write(&pubkey, &[1u8; 10]).unwrap();
+        let location2 = bucket_map2.write(&pubkey, &[2u8; 10]).unwrap();
+        assert_eq!(location1, location2);
+        assert_eq!(bucket_map1.read(&location1), vec![1u8; 10]);
+        assert_eq!(bucket_map2.read(&location2), vec![2u8; 10]);
+
+        let path1 = bucket_map1.paths[0].clone();
+        assert!(path1.is_dir());
+        drop(bucket_map1);
+        assert!(!path1.exists());
+        assert_eq!(bucket_map2.read(&location2), vec![2u8; 10]);
+    }
+
+    #[test]
+    fn test_bucket_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bucket_map = BucketMap::new(&[dir.path().to_path_buf()], 16);
+        let mut low = [0u8; 32];
+        low[0] = 0x0f;
+        let mut high = [0u8; 32];
+        high[0] = 0xf0;
+        assert_eq!(bucket_map.bucket_index(&Pubkey::new(&low)), 0);
+        assert_eq!(bucket_map.bucket_index(&Pubkey::new(&high)), 15);
+    }
+}
@@ -0,0 +1,300 @@
+//! The `cost_model` module estimates the cost of executing a transaction, in compute units,
+//! from its signatures, write locks, instruction data and the recent execution history of the
+//! programs it invokes.
+use crate::message_processor::ExecuteDetailsTimings;
+use lazy_static::lazy_static;
+use solana_sdk::{
+    bpf_loader, bpf_loader_deprecated, ed25519_program, pubkey::Pubkey, secp256k1_program,
+    system_program, transaction::Transaction,
+};
+use std::collections::HashMap;
+
+/// Number of compute units charged for each microsecond of execution
+pub const COMPUTE_UNIT_TO_US_RATIO: u64 = 30;
+/// Cost of verifying one signature
+pub const SIGNATURE_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 24;
+/// Cost of taking one write lock
+pub const WRITE_LOCK_UNITS: u64 = COMPUTE_UNIT_TO_US_RATIO * 10;
+/// Number of bytes of instruction data charged as one compute unit
+pub const DATA_BYTES_PER_UNIT: u64 = 550 / COMPUTE_UNIT_TO_US_RATIO;
+/// Cost of an instruction whose program has no execution history yet
+pub const DEFAULT_INSTRUCTION_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 200;
+
+/// Target wall clock time to replay a block, in microseconds
+const MAX_BLOCK_REPLAY_TIME_US: u64 = 400_000;
+/// Number of transactions that can be replayed in parallel
+const MAX_CONCURRENCY: u64 = 10;
+/// Maximum cost of all the transactions in one block
+pub const MAX_BLOCK_UNITS: u64 =
+    MAX_BLOCK_REPLAY_TIME_US * COMPUTE_UNIT_TO_US_RATIO * MAX_CONCURRENCY;
+/// Maximum cost of the transactions write locking the same account in one block. Transactions
+/// that write the same account are replayed sequentially, so this is the cost that can be
+/// replayed by a single thread in the block's replay time.
+pub const MAX_WRITABLE_ACCOUNT_UNITS: u64 = MAX_BLOCK_REPLAY_TIME_US * COMPUTE_UNIT_TO_US_RATIO;
+
+/// Maximum number of programs whose execution cost is tracked
+const MAX_EXECUTE_COST_TABLE_SIZE: usize = 2048;
+
+lazy_static! {
+    /// Cost of the builtin programs, which are not measured
+    static ref BUILTIN_INSTRUCTION_COSTS: HashMap<Pubkey, u64> = [
+        (system_program::ID, COMPUTE_UNIT_TO_US_RATIO * 5),
+        (solana_config_program::ID, COMPUTE_UNIT_TO_US_RATIO * 15),
+        (solana_stake_program::ID, COMPUTE_UNIT_TO_US_RATIO * 25),
+        (solana_vote_program::ID, COMPUTE_UNIT_TO_US_RATIO * 70),
+        (secp256k1_program::ID, COMPUTE_UNIT_TO_US_RATIO * 24),
+        (ed25519_program::ID, COMPUTE_UNIT_TO_US_RATIO * 24),
+        (bpf_loader_deprecated::ID, COMPUTE_UNIT_TO_US_RATIO * 38),
+        (bpf_loader::ID, COMPUTE_UNIT_TO_US_RATIO * 38),
+    ]
+    .iter()
+    .cloned()
+    .collect();
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransactionCost {
+    pub writable_accounts: Vec<Pubkey>,
+    pub signature_cost: u64,
+    pub write_lock_cost: u64,
+    pub data_bytes_cost: u64,
+    pub execution_cost: u64,
+}
+
+impl TransactionCost {
+    pub fn sum(&self) -> u64 {
+        self.signature_cost + self.write_lock_cost + self.data_bytes_cost + self.execution_cost
+    }
+}
+
+/// Rolling average of the measured execution cost of each program, bounded to
+/// `MAX_EXECUTE_COST_TABLE_SIZE` programs. The least frequently measured program is evicted to
+/// make room for a new one.
+#[derive(Debug, Default)]
+struct ExecuteCostTable {
+    table: HashMap<Pubkey, (u64, u64)>,
+}
+
+impl ExecuteCostTable {
+    fn get(&self, program_id: &Pubkey) -> Option<u64> {
+        self.table.get(program_id).map(|(cost, _)| *cost)
+    }
+
+    fn upsert(&mut self, program_id: &Pubkey, cost: u64) {
+        if !self.table.contains_key(program_id) && self.table.len() >= MAX_EXECUTE_COST_TABLE_SIZE {
+            if let Some(evicted) = self
+                .table
+                .iter()
+                .min_by_key(|(_, (_, occurrences))| *occurrences)
+                .map(|(program_id, _)| *program_id)
+            {
+                self.table.remove(&evicted);
+            }
+        }
+        let (average, occurrences) = self.table.entry(*program_id).or_insert((0, 0));
+        *average = ((*average as u128 * *occurrences as u128 + cost as u128)
+            / (*occurrences as u128 + 1)) as u64;
+        *occurrences = occurrences.saturating_add(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct CostModel {
+    instruction_execution_cost_table: ExecuteCostTable,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CostModel {
+    pub fn new() -> Self {
+        Self {
+            instruction_execution_cost_table: ExecuteCostTable::default(),
+        }
+    }
+
+    /// Estimate the cost of `transaction`
+    pub fn calculate_cost(&self, transaction: &Transaction) -> TransactionCost {
+        let message = transaction.message();
+        let writable_accounts: Vec<_> = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                if message.is_writable(i) {
+                    Some(*key)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let data_bytes: u64 = message
+            .instructions
+            .iter()
+            .map(|instruction| instruction.data.len() as u64)
+            .sum();
+        let execution_cost = message
+            .instructions
+            .iter()
+            .map(|instruction| {
+                self.find_instruction_cost(instruction.program_id(&message.account_keys))
+            })
+            .sum();
+
+        TransactionCost {
+            signature_cost: SIGNATURE_COST * transaction.signatures.len() as u64,
+            write_lock_cost: WRITE_LOCK_UNITS * writable_accounts.len() as u64,
+            data_bytes_cost: data_bytes / DATA_BYTES_PER_UNIT,
+            execution_cost,
+            writable_accounts,
+        }
+    }
+
+    /// Record a measured execution cost of one instruction of `program_id`
+    pub fn upsert_instruction_cost(&mut self, program_id: &Pubkey, cost: u64) {
+        if Self::builtin_instruction_cost(program_id).is_none() {
+            self.instruction_execution_cost_table
+                .upsert(program_id, cost);
+        }
+    }
+
+    /// Record the average measured execution cost of the instructions of each program in
+    /// `timings`
+    pub fn update_execution_costs(&mut self, timings: &ExecuteDetailsTimings) {
+        for (program_id, program_timing) in &timings.per_program_timings {
+            if program_timing.count == 0 {
+                continue;
+            }
+            let instruction_cost = program_timing
+                .accumulated_us
+                .saturating_mul(COMPUTE_UNIT_TO_US_RATIO)
+                / u64::from(program_timing.count);
+            self.upsert_instruction_cost(program_id, instruction_cost);
+        }
+    }
+
+    /// The estimated cost of executing one instruction of `program_id`
+    pub fn find_instruction_cost(&self, program_id: &Pubkey) -> u64 {
+        Self::builtin_instruction_cost(program_id)
+            .or_else(|| self.instruction_execution_cost_table.get(program_id))
+            .unwrap_or(DEFAULT_INSTRUCTION_COST)
+    }
+
+    fn builtin_instruction_cost(program_id: &Pubkey) -> Option<u64> {
+        BUILTIN_INSTRUCTION_COSTS.get(program_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        signature::{Keypair, Signer},
+        system_instruction, system_transaction,
+    };
+
+    #[test]
+    fn test_calculate_cost_transfer() {
+        let from = Keypair::new();
+        let to = Pubkey::new_unique();
+        let transaction = system_transaction::transfer(&from, &to, 1, Hash::default());
+        let data_len = transaction.message.instructions[0].data.len() as u64;
+
+        let cost = CostModel::new().calculate_cost(&transaction);
+        assert_eq!(
+            cost,
+            TransactionCost {
+                writable_accounts: vec![from.pubkey(), to],
+                signature_cost: SIGNATURE_COST,
+                write_lock_cost: 2 * WRITE_LOCK_UNITS,
+                data_bytes_cost: data_len / DATA_BYTES_PER_UNIT,
+                execution_cost: COMPUTE_UNIT_TO_US_RATIO * 5,
+            }
+        );
+        assert_eq!(
+            cost.sum(),
+            SIGNATURE_COST
+                + 2 * WRITE_LOCK_UNITS
+                + data_len / DATA_BYTES_PER_UNIT
+                + COMPUTE_UNIT_TO_US_RATIO * 5
+        );
+    }
+
+    #[test]
+    fn test_calculate_cost_program_history() {
+        let payer = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let instructions = vec![
+            Instruction::new(
+                program_id,
+                &vec![0u8; 110],
+                vec![AccountMeta::new_readonly(readonly, false)],
+            ),
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+        ];
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        let transaction = Transaction::new(&[&payer], message, Hash::default());
+
+        let mut cost_model = CostModel::new();
+        let cost = cost_model.calculate_cost(&transaction);
+        assert!(!cost.writable_accounts.contains(&readonly));
+        assert_eq!(
+            cost.execution_cost,
+            DEFAULT_INSTRUCTION_COST + COMPUTE_UNIT_TO_US_RATIO * 5
+        );
+
+        cost_model.upsert_instruction_cost(&program_id, 1_000);
+        cost_model.upsert_instruction_cost(&program_id, 3_000);
+        assert_eq!(cost_model.find_instruction_cost(&program_id), 2_000);
+        let cost = cost_model.calculate_cost(&transaction);
+        assert_eq!(cost.execution_cost, 2_000 + COMPUTE_UNIT_TO_US_RATIO * 5);
+
+        // Builtin costs are fixed
+        cost_model.upsert_instruction_cost(&system_program::id(), 1);
+        assert_eq!(
+            cost_model.find_instruction_cost(&system_program::id()),
+            COMPUTE_UNIT_TO_US_RATIO * 5
+        );
+    }
+
+    #[test]
+    fn test_update_execution_costs() {
+        let program_id = Pubkey::new_unique();
+        let mut timings = ExecuteDetailsTimings::default();
+        timings.accumulate_program(&program_id, 100);
+        timings.accumulate_program(&program_id, 300);
+        timings.accumulate_program(&system_program::id(), 1_000);
+
+        let mut cost_model = CostModel::new();
+        cost_model.update_execution_costs(&timings);
+        assert_eq!(
+            cost_model.find_instruction_cost(&program_id),
+            200 * COMPUTE_UNIT_TO_US_RATIO
+        );
+        // Builtin programs keep their fixed cost
+        assert_eq!(
+            cost_model.find_instruction_cost(&system_program::id()),
+            COMPUTE_UNIT_TO_US_RATIO * 5
+        );
+    }
+
+    #[test]
+    fn test_execute_cost_table_eviction() {
+        let mut table = ExecuteCostTable::default();
+        let frequent = Pubkey::new_unique();
+        table.upsert(&frequent, 10);
+        table.upsert(&frequent, 10);
+        for _ in 0..MAX_EXECUTE_COST_TABLE_SIZE * 2 {
+            table.upsert(&Pubkey::new_unique(), 1);
+        }
+        assert_eq!(table.table.len(), MAX_EXECUTE_COST_TABLE_SIZE);
+        assert_eq!(table.get(&frequent), Some(10));
+    }
+}
@@ -0,0 +1,260 @@
+//! The `cost_tracker` module accumulates the estimated cost of the transactions packed into a
+//! block, and rejects transactions that would push the block, or any account it write locks,
+//! over its cost limit.
+use crate::cost_model::{CostModel, TransactionCost, MAX_BLOCK_UNITS, MAX_WRITABLE_ACCOUNT_UNITS};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, transaction::Transaction};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostTrackerError {
+    /// The transaction would exceed the block's cost limit
+    WouldExceedBlockMaxLimit,
+    /// The transaction would exceed the cost limit of one of its writable accounts
+    WouldExceedAccountMaxLimit,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CostTrackerStats {
+    pub bank_slot: Slot,
+    pub transaction_count: u64,
+    pub block_cost: u64,
+    pub max_account_cost: u64,
+    pub max_cost_account: Pubkey,
+    pub cost_limited_count: u64,
+}
+
+#[derive(Debug)]
+pub struct CostTracker {
+    cost_model: CostModel,
+    account_cost_limit: u64,
+    block_cost_limit: u64,
+    current_bank_slot: Slot,
+    block_cost: u64,
+    cost_by_writable_accounts: HashMap<Pubkey, u64>,
+    transaction_count: u64,
+    cost_limited_count: u64,
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        Self::new(MAX_WRITABLE_ACCOUNT_UNITS, MAX_BLOCK_UNITS)
+    }
+}
+
+impl CostTracker {
+    pub fn new(account_cost_limit: u64, block_cost_limit: u64) -> Self {
+        assert!(account_cost_limit <= block_cost_limit);
+        Self {
+            cost_model: CostModel::new(),
+            account_cost_limit,
+            block_cost_limit,
+            current_bank_slot: 0,
+            block_cost: 0,
+            cost_by_writable_accounts: HashMap::new(),
+            transaction_count: 0,
+            cost_limited_count: 0,
+        }
+    }
+
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    pub fn cost_model_mut(&mut self) -> &mut CostModel {
+        &mut self.cost_model
+    }
+
+    /// Start tracking the block of `slot`, reporting and clearing the costs of the previous
+    /// block if it was a different slot
+    pub fn reset_if_new_bank(&mut self, slot: Slot) {
+        if slot != self.current_bank_slot {
+            if self.transaction_count > 0 || self.cost_limited_count > 0 {
+                let stats = self.stats();
+                datapoint_info!(
+                    "cost_tracker_stats",
+                    ("bank_slot", stats.bank_slot as i64, i64),
+                    ("transaction_count", stats.transaction_count as i64, i64),
+                    ("block_cost", stats.block_cost as i64, i64),
+                    ("max_account_cost", stats.max_account_cost as i64, i64),
+                    (
+                        "max_cost_account",
+                        stats.max_cost_account.to_string(),
+                        String
+                    ),
+                    ("cost_limited_count", stats.cost_limited_count as i64, i64),
+                );
+            }
+            self.current_bank_slot = slot;
+            self.block_cost = 0;
+            self.cost_by_writable_accounts.clear();
+            self.transaction_count = 0;
+            self.cost_limited_count = 0;
+        }
+    }
+
+    /// Check whether a transaction costing `transaction_cost` fits in the current block
+    pub fn would_fit(&self, transaction_cost: &TransactionCost) -> Result<(), CostTrackerError> {
+        let cost = transaction_cost.sum();
+        if self.block_cost.saturating_add(cost) > self.block_cost_limit {
+            return Err(CostTrackerError::WouldExceedBlockMaxLimit);
+        }
+        if cost > self.account_cost_limit {
+            return Err(CostTrackerError::WouldExceedAccountMaxLimit);
+        }
+        for account in &transaction_cost.writable_accounts {
+            if let Some(account_cost) = self.cost_by_writable_accounts.get(account) {
+                if account_cost.saturating_add(cost) > self.account_cost_limit {
+                    return Err(CostTrackerError::WouldExceedAccountMaxLimit);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the cost of `transaction` to the current block if it fits, and return that cost
+    pub fn try_add(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<TransactionCost, CostTrackerError> {
+        let transaction_cost = self.cost_model.calculate_cost(transaction);
+        if let Err(err) = self.would_fit(&transaction_cost) {
+            self.cost_limited_count += 1;
+            return Err(err);
+        }
+        self.add(&transaction_cost);
+        Ok(transaction_cost)
+    }
+
+    /// Add `transaction_cost` to the current block without checking the limits
+    pub fn add(&mut self, transaction_cost: &TransactionCost) {
+        let cost = transaction_cost.sum();
+        for account in &transaction_cost.writable_accounts {
+            *self.cost_by_writable_accounts.entry(*account).or_insert(0) += cost;
+        }
+        self.block_cost += cost;
+        self.transaction_count += 1;
+    }
+
+    /// Remove `transaction_cost`, previously added to the current block, for a transaction that
+    /// was not committed
+    pub fn remove(&mut self, transaction_cost: &TransactionCost) {
+        let cost = transaction_cost.sum();
+        for account in &transaction_cost.writable_accounts {
+            if let Some(account_cost) = self.cost_by_writable_accounts.get_mut(account) {
+                *account_cost = account_cost.saturating_sub(cost);
+            }
+        }
+        self.block_cost = self.block_cost.saturating_sub(cost);
+        self.transaction_count = self.transaction_count.saturating_sub(1);
+    }
+
+    pub fn stats(&self) -> CostTrackerStats {
+        let (max_cost_account, max_account_cost) = self
+            .cost_by_writable_accounts
+            .iter()
+            .max_by_key(|(_, cost)| **cost)
+            .map(|(account, cost)| (*account, *cost))
+            .unwrap_or_default();
+        CostTrackerStats {
+            bank_slot: self.current_bank_slot,
+            transaction_count: self.transaction_count,
+            block_cost: self.block_cost,
+            max_account_cost,
+            max_cost_account,
+            cost_limited_count: self.cost_limited_count,
+        }
+    }
+
+    /// The accumulated cost of each writable account in the current block, most expensive first
+    pub fn cost_by_writable_accounts(&self) -> Vec<(Pubkey, u64)> {
+        let mut accounts: Vec<_> = self
+            .cost_by_writable_accounts
+            .iter()
+            .map(|(account, cost)| (*account, *cost))
+            .collect();
+        accounts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        accounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+    };
+
+    #[test]
+    fn test_cost_tracker_account_limit() {
+        let payer = Keypair::new();
+        let transfer = |lamports| {
+            system_transaction::transfer(&payer, &Pubkey::new_unique(), lamports, Hash::default())
+        };
+        let cost = CostModel::new().calculate_cost(&transfer(1)).sum();
+
+        // Room for two transactions writing the payer, and more in the block
+        let mut cost_tracker = CostTracker::new(cost * 2, cost * 10);
+        assert!(cost_tracker.try_add(&transfer(1)).is_ok());
+        let second = cost_tracker.try_add(&transfer(2)).unwrap();
+        assert_eq!(
+            cost_tracker.try_add(&transfer(3)),
+            Err(CostTrackerError::WouldExceedAccountMaxLimit)
+        );
+
+        // Other accounts are unaffected
+        let other = system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            1,
+            Hash::default(),
+        );
+        assert!(cost_tracker.try_add(&other).is_ok());
+
+        // Removing an uncommitted transaction frees its cost
+        cost_tracker.remove(&second);
+        assert!(cost_tracker.try_add(&transfer(3)).is_ok());
+
+        let stats = cost_tracker.stats();
+        assert_eq!(stats.transaction_count, 3);
+        assert_eq!(stats.block_cost, cost * 3);
+        assert_eq!(stats.max_cost_account, payer.pubkey());
+        assert_eq!(stats.max_account_cost, cost * 2);
+        assert_eq!(stats.cost_limited_count, 1);
+        assert_eq!(
+            cost_tracker.cost_by_writable_accounts()[0],
+            (payer.pubkey(), cost * 2)
+        );
+    }
+
+    #[test]
+    fn test_cost_tracker_block_limit() {
+        let transfer = || {
+            system_transaction::transfer(&Keypair::new(), &Pubkey::new_unique(), 1, Hash::default())
+        };
+        let cost = CostModel::new().calculate_cost(&transfer()).sum();
+
+        let mut cost_tracker = CostTracker::new(cost, cost * 2);
+        cost_tracker.reset_if_new_bank(1);
+        assert!(cost_tracker.try_add(&transfer()).is_ok());
+        assert!(cost_tracker.try_add(&transfer()).is_ok());
+        assert_eq!(
+            cost_tracker.try_add(&transfer()),
+            Err(CostTrackerError::WouldExceedBlockMaxLimit)
+        );
+
+        // The same bank keeps its costs, a new one starts empty
+        cost_tracker.reset_if_new_bank(1);
+        assert_eq!(cost_tracker.stats().block_cost, cost * 2);
+        cost_tracker.reset_if_new_bank(2);
+        assert_eq!(
+            cost_tracker.stats(),
+            CostTrackerStats {
+                bank_slot: 2,
+                ..CostTrackerStats::default()
+            }
+        );
+        assert!(cost_tracker.try_add(&transfer()).is_ok());
+    }
+}
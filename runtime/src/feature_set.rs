@@ -73,6 +73,18 @@ pub mod pull_request_ping_pong_check {
     solana_sdk::declare_id!("5RzEHTnf6D7JPZCvwEzjM19kzBsyjSU3HoMfXaQmVgnZ");
 }
 
+pub mod ed25519_program_enabled {
+    solana_sdk::declare_id!("8jgyT2rRLKDG6HoGSRuuzK85yWw5vvMECezzWV7jn33V");
+}
+
+pub mod sol_log_compute_units_syscall {
+    solana_sdk::declare_id!("BFKWTjrpyztwyMopGUH29pDLBchJQSb2HwCvud6JPAgx");
+}
+
+pub mod merkle_shreds {
+    solana_sdk::declare_id!("GnfD64rAGD4GwsxbwNbLo7g7mU9rT6dS4ZXusq4GrTWm");
+}
+
 lazy_static! {
     /// Map of feature identifiers to user-visible description
     pub static ref FEATURE_NAMES: HashMap<Pubkey, &'static str> = [
@@ -93,6 +105,9 @@ lazy_static! {
         (cumulative_rent_related_fixes::id(), "rent fixes (#10206, #10468, #11342)"),
         (pubkey_log_syscall_enabled::id(), "pubkey log syscall"),
         (pull_request_ping_pong_check::id(), "ping-pong packet check #12794"),
+        (ed25519_program_enabled::id(), "ed25519 program"),
+        (sol_log_compute_units_syscall::id(), "sol_log_compute_units syscall"),
+        (merkle_shreds::id(), "merkle shreds"),
         /*************** ADD NEW FEATURES HERE ***************/
     ]
     .iter()
@@ -3,15 +3,20 @@ pub mod accounts;
 pub mod accounts_background_service;
 pub mod accounts_db;
 pub mod accounts_index;
+pub mod accounts_update_notifier_interface;
 pub mod append_vec;
 pub mod bank;
 pub mod bank_client;
 pub mod bank_forks;
+pub mod bank_hash_details;
 pub mod bank_utils;
 mod blockhash_queue;
 pub mod bloom;
+pub mod bucket_map;
 pub mod builtins;
 pub mod commitment;
+pub mod cost_model;
+pub mod cost_tracker;
 pub mod epoch_stakes;
 pub mod feature;
 pub mod feature_set;
@@ -11,6 +11,7 @@ use crate::{
 };
 use log::*;
 use serde::{Deserialize, Serialize};
+use solana_measure::measure::Measure;
 use solana_sdk::{
     account::{create_keyed_readonly_accounts, Account, KeyedAccount},
     clock::Epoch,
@@ -46,6 +47,26 @@ impl Executors {
     }
 }
 
+/// Accumulated execution time of the instructions of one program
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProgramTiming {
+    pub accumulated_us: u64,
+    pub count: u32,
+}
+
+/// Execution time of the instructions processed, by program
+#[derive(Debug, Default)]
+pub struct ExecuteDetailsTimings {
+    pub per_program_timings: HashMap<Pubkey, ProgramTiming>,
+}
+impl ExecuteDetailsTimings {
+    pub fn accumulate_program(&mut self, program_id: &Pubkey, us: u64) {
+        let program_timing = self.per_program_timings.entry(*program_id).or_default();
+        program_timing.accumulated_us = program_timing.accumulated_us.saturating_add(us);
+        program_timing.count = program_timing.count.saturating_add(1);
+    }
+}
+
 // The relevant state of an account before an Instruction executes, used
 // to verify account integrity after the Instruction completes
 #[derive(Clone, Debug, Default)]
@@ -280,6 +301,9 @@ impl InvokeContext for ThisInvokeContext {
             .last()
             .ok_or(InstructionError::GenericError)
     }
+    fn invoke_depth(&self) -> usize {
+        self.program_ids.len()
+    }
     fn get_programs(&self) -> &[(Pubkey, ProcessInstruction)] {
         &self.programs
     }
@@ -404,6 +428,11 @@ impl MessageProcessor {
         }
     }
 
+    /// The static entrypoints added with `add_program`
+    pub fn programs(&self) -> &[(Pubkey, ProcessInstruction)] {
+        &self.programs
+    }
+
     pub fn add_loader(
         &mut self,
         program_id: Pubkey,
@@ -503,6 +532,20 @@ impl MessageProcessor {
         Err(InstructionError::UnsupportedProgramId)
     }
 
+    /// Log the invocation of a program at the current depth of the invocation stack
+    fn log_invoke(program_id: &Pubkey, invoke_context: &dyn InvokeContext) {
+        let logger = invoke_context.get_logger();
+        if let Ok(mut logger) = logger.try_borrow_mut() {
+            if logger.log_enabled() {
+                logger.log(&format!(
+                    "Program {} invoke [{}]",
+                    program_id,
+                    invoke_context.invoke_depth()
+                ));
+            }
+        };
+    }
+
     /// Process a cross-program instruction
     /// This method calls the instruction's program entrypoint function
     pub fn process_cross_program_instruction(
@@ -521,7 +564,9 @@ impl MessageProcessor {
                 Self::create_keyed_accounts(message, instruction, executable_accounts, accounts)?;
 
             // Invoke callee
-            invoke_context.push(instruction.program_id(&message.account_keys))?;
+            let program_id = instruction.program_id(&message.account_keys);
+            invoke_context.push(program_id)?;
+            Self::log_invoke(program_id, invoke_context);
             let mut result =
                 self.process_instruction(&keyed_accounts, &instruction.data, invoke_context);
             if result.is_ok() {
@@ -696,6 +741,10 @@ impl MessageProcessor {
         );
         let keyed_accounts =
             Self::create_keyed_accounts(message, instruction, executable_accounts, accounts)?;
+        Self::log_invoke(
+            instruction.program_id(&message.account_keys),
+            &invoke_context,
+        );
         self.process_instruction(&keyed_accounts, &instruction.data, &mut invoke_context)?;
         Self::verify(
             message,
@@ -722,12 +771,14 @@ impl MessageProcessor {
         executors: Rc<RefCell<Executors>>,
         instruction_recorders: Option<&[InstructionRecorder]>,
         feature_set: Arc<FeatureSet>,
+        timings: &mut ExecuteDetailsTimings,
     ) -> Result<(), TransactionError> {
         for (instruction_index, instruction) in message.instructions.iter().enumerate() {
             let instruction_recorder = instruction_recorders
                 .as_ref()
                 .map(|recorders| recorders[instruction_index].clone());
-            self.execute_instruction(
+            let mut time = Measure::start("execute_instruction");
+            let result = self.execute_instruction(
                 message,
                 instruction,
                 &loaders[instruction_index],
@@ -738,8 +789,11 @@ impl MessageProcessor {
                 instruction_recorder,
                 instruction_index,
                 feature_set.clone(),
-            )
-            .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
+            );
+            time.stop();
+            timings.accumulate_program(instruction.program_id(&message.account_keys), time.as_us());
+            result
+                .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
         }
         Ok(())
     }
@@ -1317,19 +1371,32 @@ mod tests {
             Some(&from_pubkey),
         );
 
+        let log_collector = Rc::new(LogCollector::default());
+        let mut timings = ExecuteDetailsTimings::default();
         let result = message_processor.process_message(
             &message,
             &loaders,
             &accounts,
             &rent_collector,
-            None,
+            Some(log_collector.clone()),
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut timings,
         );
         assert_eq!(result, Ok(()));
+        assert_eq!(timings.per_program_timings.len(), 1);
+        assert_eq!(
+            timings.per_program_timings[&mock_system_program_id].count,
+            1
+        );
         assert_eq!(accounts[0].borrow().lamports, 100);
         assert_eq!(accounts[1].borrow().lamports, 0);
+        let log: Vec<String> = Rc::try_unwrap(log_collector).unwrap_or_default().into();
+        assert_eq!(
+            log,
+            vec![format!("Program {} invoke [1]", mock_system_program_id)]
+        );
 
         let message = Message::new(
             &[Instruction::new(
@@ -1349,6 +1416,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
         );
         assert_eq!(
             result,
@@ -1376,6 +1444,7 @@ mod tests {
             executors,
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
         );
         assert_eq!(
             result,
@@ -1486,6 +1555,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
         );
         assert_eq!(
             result,
@@ -1513,6 +1583,7 @@ mod tests {
             executors.clone(),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
         );
         assert_eq!(result, Ok(()));
 
@@ -1537,6 +1608,7 @@ mod tests {
             executors,
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
         );
         assert_eq!(result, Ok(()));
         assert_eq!(accounts[0].borrow().lamports, 80);
@@ -56,6 +56,8 @@ pub trait InvokeContext {
     ) -> Result<(), InstructionError>;
     /// Get the program ID of the currently executing program
     fn get_caller(&self) -> Result<&Pubkey, InstructionError>;
+    /// Get the depth of the invocation stack, the original caller is at depth 1
+    fn invoke_depth(&self) -> usize;
     /// Get a list of built-in programs
     fn get_programs(&self) -> &[(Pubkey, ProcessInstruction)];
     /// Get this invocation's logger
@@ -2,7 +2,8 @@ use {
     crate::{
         accounts::Accounts,
         accounts_db::{AccountStorageEntry, AccountsDB, AppendVecId, BankHashInfo},
-        accounts_index::Ancestors,
+        accounts_index::{AccountsIndexConfig, Ancestors},
+        accounts_update_notifier_interface::AccountsUpdateNotifier,
         append_vec::AppendVec,
         bank::{Bank, BankFieldsToDeserialize, BankRc, Builtins},
         blockhash_queue::BlockhashQueue,
@@ -126,6 +127,8 @@ pub(crate) fn bank_from_stream<R, P>(
     frozen_account_pubkeys: &[Pubkey],
     debug_keys: Option<Arc<HashSet<Pubkey>>>,
     additional_builtins: Option<&Builtins>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    accounts_index_config: Option<AccountsIndexConfig>,
 ) -> std::result::Result<Bank, Error>
 where
     R: Read,
@@ -144,6 +147,8 @@ where
                 append_vecs_path,
                 debug_keys,
                 additional_builtins,
+                accounts_update_notifier,
+                accounts_index_config,
             )?;
             Ok(bank)
         }};
@@ -230,6 +235,8 @@ fn reconstruct_bank_from_fields<E, P>(
     append_vecs_path: P,
     debug_keys: Option<Arc<HashSet<Pubkey>>>,
     additional_builtins: Option<&Builtins>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    accounts_index_config: Option<AccountsIndexConfig>,
 ) -> Result<Bank, Error>
 where
     E: Into<AccountStorageEntry>,
@@ -240,8 +247,11 @@ where
         account_paths,
         append_vecs_path,
         &genesis_config.cluster_type,
+        accounts_index_config,
     )?;
     accounts_db.freeze_accounts(&bank_fields.ancestors, frozen_account_pubkeys);
+    accounts_db.accounts_update_notifier = accounts_update_notifier;
+    accounts_db.notify_account_restore_from_snapshot();
 
     let bank_rc = BankRc::new(Accounts::new_empty(accounts_db), bank_fields.slot);
     let bank = Bank::new_from_fields(
@@ -260,12 +270,14 @@ fn reconstruct_accountsdb_from_fields<E, P>(
     account_paths: &[PathBuf],
     stream_append_vecs_path: P,
     cluster_type: &ClusterType,
+    accounts_index_config: Option<AccountsIndexConfig>,
 ) -> Result<AccountsDB, Error>
 where
     E: Into<AccountStorageEntry>,
     P: AsRef<Path>,
 {
-    let accounts_db = AccountsDB::new(account_paths.to_vec(), cluster_type);
+    let accounts_db =
+        AccountsDB::new_with_config(account_paths.to_vec(), cluster_type, accounts_index_config);
 
     let AccountsDbFields(storage, version, slot, bank_hash_info) = accounts_db_fields;
 
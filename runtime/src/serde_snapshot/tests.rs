@@ -70,6 +70,7 @@ where
         account_paths,
         stream_append_vecs_path,
         &ClusterType::Development,
+        None,
     )
 }
 
@@ -213,6 +214,8 @@ fn test_bank_serialize_style(serde_style: SerdeStyle) {
         &[],
         None,
         None,
+        None,
+        None,
     )
     .unwrap();
     dbank.src = ref_sc;
@@ -1,4 +1,6 @@
 use crate::{
+    accounts_index::AccountsIndexConfig,
+    accounts_update_notifier_interface::AccountsUpdateNotifier,
     bank::{Bank, BankSlotDelta, Builtins},
     bank_forks::CompressionType,
     hardened_unpack::{unpack_snapshot, UnpackError},
@@ -579,6 +581,8 @@ pub fn bank_from_archive<P: AsRef<Path>>(
     genesis_config: &GenesisConfig,
     debug_keys: Option<Arc<HashSet<Pubkey>>>,
     additional_builtins: Option<&Builtins>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    accounts_index_config: Option<AccountsIndexConfig>,
 ) -> Result<Bank> {
     // Untar the snapshot into a temp directory under `snapshot_config.snapshot_path()`
     let unpack_dir = tempfile::tempdir_in(snapshot_path)?;
@@ -601,6 +605,8 @@ pub fn bank_from_archive<P: AsRef<Path>>(
         genesis_config,
         debug_keys,
         additional_builtins,
+        accounts_update_notifier,
+        accounts_index_config,
     )?;
 
     if !bank.verify_snapshot_bank() {
@@ -660,7 +666,7 @@ fn compression_type_from_str(compress: &str) -> Option<CompressionType> {
     }
 }
 
-fn snapshot_hash_of(archive_filename: &str) -> Option<(Slot, Hash, CompressionType)> {
+pub fn snapshot_hash_of(archive_filename: &str) -> Option<(Slot, Hash, CompressionType)> {
     let snapshot_filename_regex =
         Regex::new(r"snapshot-(\d+)-([[:alnum:]]+)\.tar\.(bz2|zst|gz)$").unwrap();
 
@@ -761,6 +767,8 @@ fn rebuild_bank_from_snapshots<P>(
     genesis_config: &GenesisConfig,
     debug_keys: Option<Arc<HashSet<Pubkey>>>,
     additional_builtins: Option<&Builtins>,
+    accounts_update_notifier: Option<AccountsUpdateNotifier>,
+    accounts_index_config: Option<AccountsIndexConfig>,
 ) -> Result<Bank>
 where
     P: AsRef<Path>,
@@ -794,6 +802,8 @@ where
                 frozen_account_pubkeys,
                 debug_keys,
                 additional_builtins,
+                accounts_update_notifier,
+                accounts_index_config,
             ),
         }?)
     })?;
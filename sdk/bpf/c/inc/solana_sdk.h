@@ -133,6 +133,12 @@ void sol_log_(const char *, uint64_t);
 void sol_log_64_(uint64_t, uint64_t, uint64_t, uint64_t, uint64_t);
 #define sol_log_64 sol_log_64_
 
+/**
+ * Prints the current compute unit consumption to stdout
+ */
+void sol_log_compute_units_();
+#define sol_log_compute_units() sol_log_compute_units_()
+
 /**
  * Size of Public key in bytes
  */
@@ -609,6 +615,9 @@ void sol_log_(const char *s, uint64_t len) {
 void sol_log_64(uint64_t arg1, uint64_t arg2, uint64_t arg3, uint64_t arg4, uint64_t arg5) {
   printf("sol_log_64: %llu, %llu, %llu, %llu, %llu\n", arg1, arg2, arg3, arg4, arg5);
 }
+void sol_log_compute_units_() {
+  printf("sol_log_compute_units\n");
+}
 #endif
 
 #ifdef __cplusplus
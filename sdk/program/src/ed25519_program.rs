@@ -0,0 +1 @@
+crate::declare_id!("Ed25519SigVerify111111111111111111111111111");
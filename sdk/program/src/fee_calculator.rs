@@ -1,4 +1,5 @@
 use crate::clock::{DEFAULT_TICKS_PER_SECOND, DEFAULT_TICKS_PER_SLOT};
+use crate::ed25519_program;
 use crate::message::Message;
 use crate::secp256k1_program;
 use log::*;
@@ -21,12 +22,14 @@ impl Default for FeeCalculator {
 
 pub struct FeeConfig {
     pub secp256k1_program_enabled: bool,
+    pub ed25519_program_enabled: bool,
 }
 
 impl Default for FeeConfig {
     fn default() -> Self {
         Self {
             secp256k1_program_enabled: true,
+            ed25519_program_enabled: true,
         }
     }
 }
@@ -43,22 +46,22 @@ impl FeeCalculator {
     }
 
     pub fn calculate_fee_with_config(&self, message: &Message, fee_config: &FeeConfig) -> u64 {
-        let mut num_secp256k1_signatures: u64 = 0;
-        if fee_config.secp256k1_program_enabled {
-            for instruction in &message.instructions {
-                let program_index = instruction.program_id_index as usize;
-                // Transaction may not be sanitized here
-                if program_index < message.account_keys.len() {
-                    let id = message.account_keys[program_index];
-                    if secp256k1_program::check_id(&id) && !instruction.data.is_empty() {
-                        num_secp256k1_signatures += instruction.data[0] as u64;
-                    }
+        let mut num_precompile_signatures: u64 = 0;
+        for instruction in &message.instructions {
+            let program_index = instruction.program_id_index as usize;
+            // Transaction may not be sanitized here
+            if program_index < message.account_keys.len() && !instruction.data.is_empty() {
+                let id = message.account_keys[program_index];
+                if (fee_config.secp256k1_program_enabled && secp256k1_program::check_id(&id))
+                    || (fee_config.ed25519_program_enabled && ed25519_program::check_id(&id))
+                {
+                    num_precompile_signatures += instruction.data[0] as u64;
                 }
             }
         }
 
         self.lamports_per_signature
-            * (u64::from(message.header.num_required_signatures) + num_secp256k1_signatures)
+            * (u64::from(message.header.num_required_signatures) + num_precompile_signatures)
     }
 }
 
@@ -263,7 +266,8 @@ mod tests {
             FeeCalculator::new(1).calculate_fee_with_config(
                 &message,
                 &FeeConfig {
-                    secp256k1_program_enabled: false
+                    secp256k1_program_enabled: false,
+                    ed25519_program_enabled: true,
                 }
             ),
             1
@@ -275,6 +279,32 @@ mod tests {
         assert_eq!(FeeCalculator::new(1).calculate_fee(&message), 11);
     }
 
+    #[test]
+    fn test_fee_calculator_calculate_fee_ed25519() {
+        use crate::instruction::Instruction;
+        let pubkey0 = Pubkey::new(&[0; 32]);
+        let pubkey1 = Pubkey::new(&[1; 32]);
+        let ix0 = system_instruction::transfer(&pubkey0, &pubkey1, 1);
+        let ed25519_instruction = Instruction {
+            program_id: crate::ed25519_program::id(),
+            accounts: vec![],
+            data: vec![3],
+        };
+
+        let message = Message::new(&[ix0, ed25519_instruction], Some(&pubkey0));
+        assert_eq!(FeeCalculator::new(1).calculate_fee(&message), 4);
+        assert_eq!(
+            FeeCalculator::new(1).calculate_fee_with_config(
+                &message,
+                &FeeConfig {
+                    secp256k1_program_enabled: true,
+                    ed25519_program_enabled: false,
+                }
+            ),
+            1
+        );
+    }
+
     #[test]
     fn test_fee_rate_governor_derived_default() {
         solana_logger::setup();
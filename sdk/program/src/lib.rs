@@ -11,6 +11,7 @@ pub mod bpf_loader;
 pub mod bpf_loader_deprecated;
 pub mod clock;
 pub mod decode_error;
+pub mod ed25519_program;
 pub mod entrypoint;
 pub mod entrypoint_deprecated;
 pub mod epoch_schedule;
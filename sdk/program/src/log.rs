@@ -63,6 +63,23 @@ extern "C" {
     fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64);
 }
 
+/// Prints the remaining compute units the program may consume
+#[inline]
+pub fn sol_log_compute_units() {
+    #[cfg(target_arch = "bpf")]
+    unsafe {
+        sol_log_compute_units_();
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    crate::program_stubs::sol_log_compute_units();
+}
+
+#[cfg(target_arch = "bpf")]
+extern "C" {
+    fn sol_log_compute_units_();
+}
+
 /// Prints the hexadecimal representation of a slice
 ///
 /// @param slice - The array to print
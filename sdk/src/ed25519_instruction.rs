@@ -0,0 +1,326 @@
+#![cfg(feature = "full")]
+
+use crate::instruction::Instruction;
+use ed25519_dalek::{ed25519::signature::Signature as _, Signer};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq)]
+pub enum Ed25519Error {
+    InvalidPublicKey,
+    InvalidSignature,
+    InvalidDataOffsets,
+    InvalidInstructionDataSize,
+}
+
+pub const PUBKEY_SERIALIZED_SIZE: usize = 32;
+pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
+pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+// bytes of [num_signatures, padding] ahead of the offsets
+pub const SIGNATURE_OFFSETS_START: usize = 2;
+// instruction index that refers to the ed25519 instruction itself
+pub const CURRENT_INSTRUCTION_INDEX: u16 = u16::MAX;
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct Ed25519SignatureOffsets {
+    pub signature_offset: u16, // offset to ed25519 signature of 64 bytes
+    pub signature_instruction_index: u16,
+    pub public_key_offset: u16, // offset to public key of 32 bytes
+    pub public_key_instruction_index: u16,
+    pub message_data_offset: u16, // offset to start of message data
+    pub message_data_size: u16,   // size of message data
+    pub message_instruction_index: u16,
+}
+
+pub fn new_ed25519_instruction(keypair: &ed25519_dalek::Keypair, message: &[u8]) -> Instruction {
+    let signature = keypair.sign(message).to_bytes();
+    let pubkey = keypair.public.to_bytes();
+    assert_eq!(pubkey.len(), PUBKEY_SERIALIZED_SIZE);
+    assert_eq!(signature.len(), SIGNATURE_SERIALIZED_SIZE);
+
+    let data_start = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    let public_key_offset = data_start;
+    let signature_offset = public_key_offset + pubkey.len();
+    let message_data_offset = signature_offset + signature.len();
+
+    let mut instruction_data = vec![0u8; message_data_offset + message.len()];
+    let num_signatures = 1;
+    instruction_data[0] = num_signatures;
+    instruction_data[public_key_offset..signature_offset].copy_from_slice(&pubkey);
+    instruction_data[signature_offset..message_data_offset].copy_from_slice(&signature);
+    instruction_data[message_data_offset..].copy_from_slice(message);
+
+    let offsets = Ed25519SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: CURRENT_INSTRUCTION_INDEX,
+        public_key_offset: public_key_offset as u16,
+        public_key_instruction_index: CURRENT_INSTRUCTION_INDEX,
+        message_data_offset: message_data_offset as u16,
+        message_data_size: message.len() as u16,
+        message_instruction_index: CURRENT_INSTRUCTION_INDEX,
+    };
+    let writer = std::io::Cursor::new(&mut instruction_data[SIGNATURE_OFFSETS_START..data_start]);
+    bincode::serialize_into(writer, &offsets).unwrap();
+
+    Instruction {
+        program_id: crate::ed25519_program::id(),
+        accounts: vec![],
+        data: instruction_data,
+    }
+}
+
+fn get_data_slice<'a>(
+    data: &'a [u8],
+    instruction_datas: &'a [&[u8]],
+    instruction_index: u16,
+    offset_start: u16,
+    size: usize,
+) -> Result<&'a [u8], Ed25519Error> {
+    let instruction = if instruction_index == CURRENT_INSTRUCTION_INDEX {
+        data
+    } else {
+        let instruction_index = instruction_index as usize;
+        if instruction_index >= instruction_datas.len() {
+            return Err(Ed25519Error::InvalidDataOffsets);
+        }
+        instruction_datas[instruction_index]
+    };
+    let start = offset_start as usize;
+    let end = start.saturating_add(size);
+    if end > instruction.len() {
+        return Err(Ed25519Error::InvalidDataOffsets);
+    }
+
+    Ok(&instruction[start..end])
+}
+
+pub fn verify_signatures(data: &[u8], instruction_datas: &[&[u8]]) -> Result<(), Ed25519Error> {
+    if data.len() < SIGNATURE_OFFSETS_START {
+        return Err(Ed25519Error::InvalidInstructionDataSize);
+    }
+    let count = data[0] as usize;
+    if count == 0 && data.len() > SIGNATURE_OFFSETS_START {
+        return Err(Ed25519Error::InvalidInstructionDataSize);
+    }
+    let expected_data_size = SIGNATURE_OFFSETS_START + count * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    if data.len() < expected_data_size {
+        return Err(Ed25519Error::InvalidInstructionDataSize);
+    }
+    for i in 0..count {
+        let start = SIGNATURE_OFFSETS_START + i * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+        let end = start + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+
+        let offsets: Ed25519SignatureOffsets = bincode::deserialize(&data[start..end])
+            .map_err(|_| Ed25519Error::InvalidDataOffsets)?;
+
+        // Parse out signature
+        let signature = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            SIGNATURE_SERIALIZED_SIZE,
+        )?;
+        let signature = ed25519_dalek::Signature::from_bytes(signature)
+            .map_err(|_| Ed25519Error::InvalidSignature)?;
+
+        // Parse out pubkey
+        let pubkey = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            PUBKEY_SERIALIZED_SIZE,
+        )?;
+        let pubkey = ed25519_dalek::PublicKey::from_bytes(pubkey)
+            .map_err(|_| Ed25519Error::InvalidPublicKey)?;
+
+        // Parse out message
+        let message = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        )?;
+
+        pubkey
+            .verify_strict(message, &signature)
+            .map_err(|_| Ed25519Error::InvalidSignature)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{
+        hash::Hash,
+        signature::{Keypair, Signer as _},
+        transaction::Transaction,
+    };
+    use rand::{thread_rng, Rng};
+
+    fn test_case(
+        num_signatures: u8,
+        offsets: &Ed25519SignatureOffsets,
+    ) -> Result<(), Ed25519Error> {
+        let mut instruction_data =
+            vec![0u8; SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        instruction_data[0] = num_signatures;
+        let writer = std::io::Cursor::new(&mut instruction_data[SIGNATURE_OFFSETS_START..]);
+        bincode::serialize_into(writer, &offsets).unwrap();
+
+        verify_signatures(&instruction_data, &[&[0u8; 100]])
+    }
+
+    #[test]
+    fn test_invalid_offsets() {
+        solana_logger::setup();
+
+        let mut instruction_data =
+            vec![0u8; SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        let offsets = Ed25519SignatureOffsets::default();
+        instruction_data[0] = 1;
+        let writer = std::io::Cursor::new(&mut instruction_data[SIGNATURE_OFFSETS_START..]);
+        bincode::serialize_into(writer, &offsets).unwrap();
+        instruction_data.truncate(instruction_data.len() - 1);
+
+        assert_eq!(
+            verify_signatures(&instruction_data, &[&[0u8; 100]]),
+            Err(Ed25519Error::InvalidInstructionDataSize)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            signature_instruction_index: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            message_instruction_index: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            public_key_instruction_index: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_message_data_offsets() {
+        let offsets = Ed25519SignatureOffsets {
+            message_data_offset: 99,
+            message_data_size: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(test_case(1, &offsets), Err(Ed25519Error::InvalidSignature));
+
+        let offsets = Ed25519SignatureOffsets {
+            message_data_offset: 100,
+            message_data_size: 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            message_data_offset: std::u16::MAX,
+            message_data_size: std::u16::MAX,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_pubkey_offset() {
+        let offsets = Ed25519SignatureOffsets {
+            public_key_offset: std::u16::MAX,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            public_key_offset: 100 - PUBKEY_SERIALIZED_SIZE as u16 + 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_signature_offset() {
+        let offsets = Ed25519SignatureOffsets {
+            signature_offset: std::u16::MAX,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+
+        let offsets = Ed25519SignatureOffsets {
+            signature_offset: 100 - SIGNATURE_SERIALIZED_SIZE as u16 + 1,
+            ..Ed25519SignatureOffsets::default()
+        };
+        assert_eq!(
+            test_case(1, &offsets),
+            Err(Ed25519Error::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_ed25519() {
+        solana_logger::setup();
+        let offsets = Ed25519SignatureOffsets::default();
+        assert_eq!(
+            bincode::serialized_size(&offsets).unwrap() as usize,
+            SIGNATURE_OFFSETS_SERIALIZED_SIZE
+        );
+
+        let privkey = ed25519_dalek::Keypair::generate(&mut thread_rng());
+        let message_arr = b"hello";
+        let mut instruction = new_ed25519_instruction(&privkey, message_arr);
+        let mint_keypair = Keypair::new();
+
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction.clone()],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            Hash::default(),
+        );
+
+        assert!(tx.verify_precompiles_with_features(false, true).is_ok());
+
+        let index = thread_rng().gen_range(0, instruction.data.len());
+        instruction.data[index] = instruction.data[index].wrapping_add(12);
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&mint_keypair.pubkey()),
+            &[&mint_keypair],
+            Hash::default(),
+        );
+        assert!(tx.verify_precompiles_with_features(false, true).is_err());
+    }
+}
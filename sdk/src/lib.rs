@@ -10,6 +10,7 @@ pub mod builtins;
 pub mod client;
 pub mod commitment_config;
 pub mod deserialize_utils;
+pub mod ed25519_instruction;
 pub mod entrypoint;
 pub mod entrypoint_deprecated;
 pub mod entrypoint_native;
@@ -5,6 +5,7 @@
 use crate::sanitize::{Sanitize, SanitizeError};
 use crate::secp256k1::verify_eth_addresses;
 use crate::{
+    ed25519_instruction::verify_signatures,
     hash::Hash,
     instruction::{CompiledInstruction, Instruction, InstructionError},
     message::Message,
@@ -20,7 +21,9 @@ use std::result;
 use thiserror::Error;
 
 /// Reasons a transaction might be rejected.
-#[derive(Error, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, AbiExample, AbiEnumVisitor)]
+#[derive(
+    Error, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, AbiExample, AbiEnumVisitor,
+)]
 pub enum TransactionError {
     /// An account is already being processed in another transaction in a way
     /// that does not support parallelism
@@ -336,14 +339,28 @@ impl Transaction {
         }
     }
 
+    /// Verify the secp256k1 precompile instructions of the transaction
     pub fn verify_precompiles(&self) -> Result<()> {
+        self.verify_precompiles_with_features(true, false)
+    }
+
+    /// Verify the instructions of the precompiles enabled on the cluster
+    pub fn verify_precompiles_with_features(
+        &self,
+        secp256k1_program_enabled: bool,
+        ed25519_program_enabled: bool,
+    ) -> Result<()> {
         for instruction in &self.message().instructions {
             // The Transaction may not be sanitized at this point
             if instruction.program_id_index as usize >= self.message().account_keys.len() {
                 return Err(TransactionError::AccountNotFound);
             }
             let program_id = &self.message().account_keys[instruction.program_id_index as usize];
-            if crate::secp256k1_program::check_id(program_id) {
+            let is_secp256k1 =
+                secp256k1_program_enabled && crate::secp256k1_program::check_id(program_id);
+            let is_ed25519 =
+                ed25519_program_enabled && crate::ed25519_program::check_id(program_id);
+            if is_secp256k1 || is_ed25519 {
                 let instruction_datas: Vec<_> = self
                     .message()
                     .instructions
@@ -351,8 +368,13 @@ impl Transaction {
                     .map(|instruction| instruction.data.as_ref())
                     .collect();
                 let data = &instruction.data;
-                let e = verify_eth_addresses(data, &instruction_datas);
-                e.map_err(|_| TransactionError::InvalidAccountIndex)?;
+                if is_secp256k1 {
+                    let e = verify_eth_addresses(data, &instruction_datas);
+                    e.map_err(|_| TransactionError::InvalidAccountIndex)?;
+                } else {
+                    let e = verify_signatures(data, &instruction_datas);
+                    e.map_err(|_| TransactionError::InvalidAccountIndex)?;
+                }
             }
         }
         Ok(())
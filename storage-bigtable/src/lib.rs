@@ -1,3 +1,4 @@
+use async_trait::async_trait;
 use log::*;
 use serde::{Deserialize, Serialize};
 use solana_sdk::{
@@ -12,7 +13,7 @@ use solana_transaction_status::{
     ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Reward,
     TransactionStatus, TransactionStatusMeta, TransactionWithStatusMeta,
 };
-use std::{collections::HashMap, convert::TryInto};
+use std::{collections::HashMap, convert::TryInto, path::PathBuf, sync::Arc};
 use thiserror::Error;
 
 #[macro_use]
@@ -21,8 +22,11 @@ extern crate serde_derive;
 mod access_token;
 mod bigtable;
 mod compression;
+mod local_storage;
 mod root_ca_certificate;
 
+pub use local_storage::LocalLedgerStorage;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("BigTable: {0}")]
@@ -39,6 +43,12 @@ pub enum Error {
 
     #[error("Signature not found")]
     SignatureNotFound,
+
+    #[error("Object is corrupt: {0}")]
+    ObjectCorrupt(String),
+
+    #[error("Ledger storage is read-only")]
+    ReadOnly,
 }
 
 impl std::convert::From<bigtable::Error> for Error {
@@ -269,19 +279,205 @@ struct TransactionByAddrInfo {
     memo: Option<String>,          // Transaction memo
 }
 
+/// Long-term storage of confirmed blocks and transaction history, for ledger data that has been
+/// purged from the blockstore
+#[async_trait]
+pub trait LedgerStorage: Send + Sync {
+    /// Return the available slot that contains a block
+    async fn get_first_available_block(&self) -> Result<Option<Slot>>;
+
+    /// Fetch the next slots after the provided slot that contains a block
+    ///
+    /// start_slot: slot to start the search from (inclusive)
+    /// limit: stop after this many slots have been found.
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>>;
+
+    /// Fetch the confirmed block from the desired slot
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock>;
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus>;
+
+    /// Fetch a confirmed transaction
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>>;
+
+    /// Get confirmed signatures for the provided address, in descending ledger order
+    ///
+    /// address: address to search for
+    /// before_signature: start with the first signature older than this one
+    /// limit: stop after this many signatures.
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<
+        Vec<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    >;
+
+    // Upload a new confirmed block and associated meta data.
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()>;
+}
+
+/// Connects to the BigTable instance, or opens the flat file storage at `local_path` if one is
+/// given
+pub async fn new_ledger_storage(
+    local_path: Option<PathBuf>,
+    read_only: bool,
+) -> Result<Arc<dyn LedgerStorage>> {
+    Ok(match local_path {
+        Some(local_path) => Arc::new(LocalLedgerStorage::new(local_path, read_only)?),
+        None => Arc::new(BigTableLedgerStorage::new(read_only).await?),
+    })
+}
+
+type TxCells = Vec<(String, TransactionInfo)>;
+type TxByAddrCells = Vec<(String, Vec<TransactionByAddrInfo>)>;
+
+// The rows of the `tx` and `tx-by-addr` tables that describe the transactions of a block
+fn transaction_cells(slot: Slot, confirmed_block: &ConfirmedBlock) -> (TxCells, TxByAddrCells) {
+    let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
+
+    let mut tx_cells = vec![];
+    for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
+        let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
+        let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
+        let index = index as u32;
+        let signature = transaction.signatures[0];
+
+        for address in &transaction.message.account_keys {
+            if !is_sysvar_id(&address) {
+                by_addr
+                    .entry(address)
+                    .or_default()
+                    .push(TransactionByAddrInfo {
+                        signature,
+                        err: err.clone(),
+                        index,
+                        memo: None, // TODO
+                    });
+            }
+        }
+
+        tx_cells.push((
+            signature.to_string(),
+            TransactionInfo {
+                slot,
+                index,
+                err,
+                memo: None, // TODO
+            },
+        ));
+    }
+
+    let tx_by_addr_cells = by_addr
+        .into_iter()
+        .map(|(address, transaction_info_by_addr)| {
+            (
+                format!("{}/{}", address, slot_to_key(!slot)),
+                transaction_info_by_addr,
+            )
+        })
+        .collect();
+    (tx_cells, tx_by_addr_cells)
+}
+
+// The transaction at `index` of `block`, if it is the one with `signature`
+fn confirmed_transaction_in_block(
+    signature: &Signature,
+    slot: Slot,
+    index: u32,
+    block: ConfirmedBlock,
+) -> Option<ConfirmedTransaction> {
+    match block.transactions.into_iter().nth(index as usize) {
+        None => {
+            warn!("Transaction info for {} is corrupt", signature);
+            None
+        }
+        Some(bucket_block_transaction) => {
+            if bucket_block_transaction.transaction.signatures[0] != *signature {
+                warn!(
+                    "Transaction info or confirmed block for {} is corrupt",
+                    signature
+                );
+                None
+            } else {
+                Some(ConfirmedTransaction {
+                    slot,
+                    transaction: bucket_block_transaction,
+                })
+            }
+        }
+    }
+}
+
+// Collects up to `limit` signatures from `tx-by-addr` rows listed from `first_slot` down to
+// `last_slot`, excluding the `before` and `until` transactions and those beyond them
+fn signatures_for_address_from_rows<I>(
+    rows: I,
+    (first_slot, before_transaction_index): (Slot, u32),
+    (last_slot, until_transaction_index): (Slot, u32),
+    limit: usize,
+) -> Vec<(ConfirmedTransactionStatusWithSignature, u32)>
+where
+    I: IntoIterator<Item = (Slot, Vec<TransactionByAddrInfo>)>,
+{
+    let mut infos = vec![];
+    'outer: for (slot, mut cell_data) in rows {
+        cell_data.reverse();
+        for tx_by_addr_info in cell_data.into_iter() {
+            // Filter out records before `before_transaction_index`
+            if slot == first_slot && tx_by_addr_info.index >= before_transaction_index {
+                continue;
+            }
+            // Filter out records after `until_transaction_index`
+            if slot == last_slot && tx_by_addr_info.index <= until_transaction_index {
+                continue;
+            }
+            infos.push((
+                ConfirmedTransactionStatusWithSignature {
+                    signature: tx_by_addr_info.signature,
+                    slot,
+                    err: tx_by_addr_info.err,
+                    memo: tx_by_addr_info.memo,
+                },
+                tx_by_addr_info.index,
+            ));
+            // Respect limit
+            if infos.len() >= limit {
+                break 'outer;
+            }
+        }
+    }
+    infos
+}
+
+/// `LedgerStorage` on Google Cloud BigTable
 #[derive(Clone)]
-pub struct LedgerStorage {
+pub struct BigTableLedgerStorage {
     connection: bigtable::BigTableConnection,
 }
 
-impl LedgerStorage {
+impl BigTableLedgerStorage {
     pub async fn new(read_only: bool) -> Result<Self> {
         let connection = bigtable::BigTableConnection::new("solana-ledger", read_only).await?;
         Ok(Self { connection })
     }
+}
 
-    /// Return the available slot that contains a block
-    pub async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+#[async_trait]
+impl LedgerStorage for BigTableLedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable.get_row_keys("blocks", None, None, 1).await?;
         if blocks.is_empty() {
@@ -290,11 +486,7 @@ impl LedgerStorage {
         Ok(key_to_slot(&blocks[0]))
     }
 
-    /// Fetch the next slots after the provided slot that contains a block
-    ///
-    /// start_slot: slot to start the search from (inclusive)
-    /// limit: stop after this many slots have been found.
-    pub async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable
             .get_row_keys("blocks", Some(slot_to_key(start_slot)), None, limit as i64)
@@ -302,8 +494,7 @@ impl LedgerStorage {
         Ok(blocks.into_iter().filter_map(|s| key_to_slot(&s)).collect())
     }
 
-    /// Fetch the confirmed block from the desired slot
-    pub async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
         let mut bigtable = self.connection.client();
         let block_cell_data = bigtable
             .get_protobuf_or_bincode_cell::<StoredConfirmedBlock, generated::ConfirmedBlock>(
@@ -319,7 +510,7 @@ impl LedgerStorage {
         })
     }
 
-    pub async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
         let mut bigtable = self.connection.client();
         let transaction_info = bigtable
             .get_bincode_cell::<TransactionInfo>("tx", signature.to_string())
@@ -327,8 +518,7 @@ impl LedgerStorage {
         Ok(transaction_info.into())
     }
 
-    /// Fetch a confirmed transaction
-    pub async fn get_confirmed_transaction(
+    async fn get_confirmed_transaction(
         &self,
         signature: &Signature,
     ) -> Result<Option<ConfirmedTransaction>> {
@@ -341,34 +531,12 @@ impl LedgerStorage {
 
         // Load the block and return the transaction
         let block = self.get_confirmed_block(slot).await?;
-        match block.transactions.into_iter().nth(index as usize) {
-            None => {
-                warn!("Transaction info for {} is corrupt", signature);
-                Ok(None)
-            }
-            Some(bucket_block_transaction) => {
-                if bucket_block_transaction.transaction.signatures[0] != *signature {
-                    warn!(
-                        "Transaction info or confirmed block for {} is corrupt",
-                        signature
-                    );
-                    Ok(None)
-                } else {
-                    Ok(Some(ConfirmedTransaction {
-                        slot,
-                        transaction: bucket_block_transaction,
-                    }))
-                }
-            }
-        }
+        Ok(confirmed_transaction_in_block(
+            signature, slot, index, block,
+        ))
     }
 
-    /// Get confirmed signatures for the provided address, in descending ledger order
-    ///
-    /// address: address to search for
-    /// before_signature: start with the first signature older than this one
-    /// limit: stop after this many signatures.
-    pub async fn get_confirmed_signatures_for_address(
+    async fn get_confirmed_signatures_for_address(
         &self,
         address: &Pubkey,
         before_signature: Option<&Signature>,
@@ -407,8 +575,6 @@ impl LedgerStorage {
             }
         };
 
-        let mut infos = vec![];
-
         let starting_slot_tx_len = bigtable
             .get_bincode_cell::<Vec<TransactionByAddrInfo>>(
                 "tx-by-addr",
@@ -429,94 +595,34 @@ impl LedgerStorage {
             )
             .await?;
 
-        'outer: for (row_key, data) in tx_by_addr_data {
+        let mut rows = vec![];
+        for (row_key, data) in tx_by_addr_data {
             let slot = !key_to_slot(&row_key[address_prefix.len()..]).ok_or_else(|| {
                 bigtable::Error::ObjectCorrupt(format!(
                     "Failed to convert key to slot: tx-by-addr/{}",
                     row_key
                 ))
             })?;
-            let mut cell_data: Vec<TransactionByAddrInfo> =
+            let cell_data: Vec<TransactionByAddrInfo> =
                 bigtable::deserialize_bincode_cell_data(&data, "tx-by-addr", row_key)?;
-            cell_data.reverse();
-            for tx_by_addr_info in cell_data.into_iter() {
-                // Filter out records before `before_transaction_index`
-                if slot == first_slot && tx_by_addr_info.index >= before_transaction_index {
-                    continue;
-                }
-                // Filter out records after `until_transaction_index`
-                if slot == last_slot && tx_by_addr_info.index <= until_transaction_index {
-                    continue;
-                }
-                infos.push((
-                    ConfirmedTransactionStatusWithSignature {
-                        signature: tx_by_addr_info.signature,
-                        slot,
-                        err: tx_by_addr_info.err,
-                        memo: tx_by_addr_info.memo,
-                    },
-                    tx_by_addr_info.index,
-                ));
-                // Respect limit
-                if infos.len() >= limit {
-                    break 'outer;
-                }
-            }
+            rows.push((slot, cell_data));
         }
-        Ok(infos)
+        Ok(signatures_for_address_from_rows(
+            rows,
+            (first_slot, before_transaction_index),
+            (last_slot, until_transaction_index),
+            limit,
+        ))
     }
 
-    // Upload a new confirmed block and associated meta data.
-    pub async fn upload_confirmed_block(
+    async fn upload_confirmed_block(
         &self,
         slot: Slot,
         confirmed_block: ConfirmedBlock,
     ) -> Result<()> {
         let mut bytes_written = 0;
 
-        let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
-
-        let mut tx_cells = vec![];
-        for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
-            let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
-            let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
-            let index = index as u32;
-            let signature = transaction.signatures[0];
-
-            for address in &transaction.message.account_keys {
-                if !is_sysvar_id(&address) {
-                    by_addr
-                        .entry(address)
-                        .or_default()
-                        .push(TransactionByAddrInfo {
-                            signature,
-                            err: err.clone(),
-                            index,
-                            memo: None, // TODO
-                        });
-                }
-            }
-
-            tx_cells.push((
-                signature.to_string(),
-                TransactionInfo {
-                    slot,
-                    index,
-                    err,
-                    memo: None, // TODO
-                },
-            ));
-        }
-
-        let tx_by_addr_cells: Vec<_> = by_addr
-            .into_iter()
-            .map(|(address, transaction_info_by_addr)| {
-                (
-                    format!("{}/{}", address, slot_to_key(!slot)),
-                    transaction_info_by_addr,
-                )
-            })
-            .collect();
+        let (tx_cells, tx_by_addr_cells) = transaction_cells(slot, &confirmed_block);
 
         if !tx_cells.is_empty() {
             bytes_written += self
@@ -0,0 +1,497 @@
+//! `LedgerStorage` in compressed flat files on the local filesystem, laid out like the BigTable
+//! tables: one directory per table and one file per row. Row keys containing a `/` are stored in
+//! subdirectories, so a `tx-by-addr` listing only reads the directory of the one address, and
+//! block rows are sharded by the prefix of their slot key. The filesystem work runs on tokio's
+//! blocking thread pool
+use crate::{
+    compression::{compress_best, decompress},
+    confirmed_transaction_in_block, key_to_slot, signatures_for_address_from_rows, slot_to_key,
+    transaction_cells, Error, LedgerStorage, Result, TransactionByAddrInfo, TransactionInfo,
+};
+use async_trait::async_trait;
+use log::*;
+use prost::Message;
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
+use solana_storage_proto::convert::generated;
+use solana_transaction_status::{
+    ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature,
+    TransactionStatus,
+};
+use std::{
+    convert::TryInto,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+// Rows are written under this suffix and then renamed, so readers never see a partial row
+const TMP_SUFFIX: &str = ".tmp";
+
+// Length of the slot key prefix that names the shard directory of a block row, so each shard
+// holds the blocks of 65536 consecutive slots
+const BLOCK_SHARD_KEY_LEN: usize = 12;
+
+fn block_row_key(slot: Slot) -> String {
+    let key = slot_to_key(slot);
+    format!("{}/{}", &key[..BLOCK_SHARD_KEY_LEN], key)
+}
+
+#[derive(Clone)]
+pub struct LocalLedgerStorage {
+    path: PathBuf,
+    read_only: bool,
+}
+
+impl LocalLedgerStorage {
+    pub fn new(path: PathBuf, read_only: bool) -> Result<Self> {
+        if !read_only {
+            for table in &["blocks", "tx", "tx-by-addr"] {
+                fs::create_dir_all(path.join(table))?;
+            }
+        }
+        Ok(Self { path, read_only })
+    }
+
+    fn row_path(&self, table: &str, key: &str) -> PathBuf {
+        self.path.join(table).join(key)
+    }
+
+    // The row keys of `dir` in lexical order, an empty list if `dir` does not exist
+    fn row_keys(dir: &Path) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        let mut keys = vec![];
+        for entry in entries {
+            if let Some(key) = entry?.file_name().to_str() {
+                if !key.ends_with(TMP_SUFFIX) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    // Returns None if the row does not exist
+    fn read_row(&self, table: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.row_path(table, key)) {
+            Ok(data) => Ok(Some(decompress(&data)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn read_bincode_row<T>(&self, table: &str, key: &str) -> Result<Option<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.read_row(table, key)?
+            .map(|data| {
+                bincode::deserialize(&data).map_err(|err| {
+                    warn!("Failed to deserialize {}/{}: {}", table, key, err);
+                    Error::ObjectCorrupt(format!("{}/{}", table, key))
+                })
+            })
+            .transpose()
+    }
+
+    fn write_row(&self, table: &str, key: &str, data: &[u8]) -> Result<usize> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let path = self.row_path(table, key);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = compress_best(data)?;
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(TMP_SUFFIX);
+        fs::write(&tmp_path, &data)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(data.len())
+    }
+
+    fn write_bincode_rows<T>(&self, table: &str, rows: &[(String, T)]) -> Result<usize>
+    where
+        T: serde::ser::Serialize,
+    {
+        let mut bytes_written = 0;
+        for (key, row) in rows {
+            bytes_written += self.write_row(table, key, &bincode::serialize(row).unwrap())?;
+        }
+        Ok(bytes_written)
+    }
+
+    fn transaction_info(&self, signature: &Signature) -> Result<TransactionInfo> {
+        self.read_bincode_row("tx", &signature.to_string())?
+            .ok_or(Error::SignatureNotFound)
+    }
+
+    // Runs `f` on the blocking thread pool, so filesystem access does not stall the executor
+    async fn run_blocking<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Self) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || f(&storage))
+            .await
+            .map_err(|err| Error::IoError(io::Error::new(ErrorKind::Other, err)))?
+    }
+
+    // Only reads the shards from the one of `start_slot` until `limit` blocks are found
+    fn confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        let blocks_dir = self.path.join("blocks");
+        let start_key = slot_to_key(start_slot);
+        let start_shard = &start_key[..BLOCK_SHARD_KEY_LEN];
+        let mut slots = vec![];
+        for shard in Self::row_keys(&blocks_dir)?
+            .into_iter()
+            .filter(|shard| shard.as_str() >= start_shard)
+        {
+            if slots.len() >= limit {
+                break;
+            }
+            let remaining = limit - slots.len();
+            slots.extend(
+                Self::row_keys(&blocks_dir.join(&shard))?
+                    .into_iter()
+                    .filter(|key| *key >= start_key)
+                    .filter_map(|key| key_to_slot(&key))
+                    .take(remaining),
+            );
+        }
+        Ok(slots)
+    }
+
+    fn confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        let key = block_row_key(slot);
+        let data = self
+            .read_row("blocks", &key)?
+            .ok_or(Error::BlockNotFound(slot))?;
+        generated::ConfirmedBlock::decode(&data[..])
+            .ok()
+            .and_then(|block| block.try_into().ok())
+            .ok_or_else(|| Error::ObjectCorrupt(format!("blocks/{}", key)))
+    }
+
+    fn confirmed_transaction(&self, signature: &Signature) -> Result<Option<ConfirmedTransaction>> {
+        let TransactionInfo { slot, index, .. } = self.transaction_info(signature)?;
+        let block = self.confirmed_block(slot)?;
+        Ok(confirmed_transaction_in_block(
+            signature, slot, index, block,
+        ))
+    }
+
+    fn confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<(ConfirmedTransactionStatusWithSignature, u32)>> {
+        let (first_slot, before_transaction_index) = match before_signature {
+            None => (Slot::MAX, 0),
+            Some(before_signature) => {
+                let TransactionInfo { slot, index, .. } =
+                    self.transaction_info(before_signature)?;
+                (slot, index)
+            }
+        };
+        let (last_slot, until_transaction_index) = match until_signature {
+            None => (0, u32::MAX),
+            Some(until_signature) => {
+                let TransactionInfo { slot, index, .. } = self.transaction_info(until_signature)?;
+                (slot, index)
+            }
+        };
+
+        // Every row holds at least one transaction, so besides the row of the first slot, whose
+        // transactions may all be filtered out, `limit` rows are enough
+        let address_dir = self.path.join("tx-by-addr").join(address.to_string());
+        let (start_key, end_key) = (slot_to_key(!first_slot), slot_to_key(!last_slot));
+        let mut rows = vec![];
+        for key in Self::row_keys(&address_dir)?
+            .into_iter()
+            .filter(|key| *key >= start_key && *key <= end_key)
+            .take(limit.saturating_add(1))
+        {
+            let slot = !key_to_slot(&key).ok_or_else(|| {
+                Error::ObjectCorrupt(format!(
+                    "Failed to convert key to slot: tx-by-addr/{}/{}",
+                    address, key
+                ))
+            })?;
+            let row_key = format!("{}/{}", address, key);
+            let cell_data: Vec<TransactionByAddrInfo> = self
+                .read_bincode_row("tx-by-addr", &row_key)?
+                .ok_or_else(|| Error::ObjectCorrupt(format!("tx-by-addr/{}", row_key)))?;
+            rows.push((slot, cell_data));
+        }
+        Ok(signatures_for_address_from_rows(
+            rows,
+            (first_slot, before_transaction_index),
+            (last_slot, until_transaction_index),
+            limit,
+        ))
+    }
+
+    fn store_confirmed_block(&self, slot: Slot, confirmed_block: ConfirmedBlock) -> Result<()> {
+        let (tx_cells, tx_by_addr_cells) = transaction_cells(slot, &confirmed_block);
+        let mut bytes_written = self.write_bincode_rows("tx", &tx_cells)?;
+        bytes_written += self.write_bincode_rows("tx-by-addr", &tx_by_addr_cells)?;
+
+        // Store the block itself last, so that partially stored blocks are never visible
+        let num_transactions = confirmed_block.transactions.len();
+        let block: generated::ConfirmedBlock = confirmed_block.into();
+        let mut buf = Vec::with_capacity(block.encoded_len());
+        block.encode(&mut buf).unwrap();
+        bytes_written += self.write_row("blocks", &block_row_key(slot), &buf)?;
+        info!(
+            "stored block for slot {}: {} transactions, {} bytes",
+            slot, num_transactions, bytes_written
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerStorage for LocalLedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+        self.run_blocking(|storage| Ok(storage.confirmed_blocks(0, 1)?.first().cloned()))
+            .await
+    }
+
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        self.run_blocking(move |storage| storage.confirmed_blocks(start_slot, limit))
+            .await
+    }
+
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        self.run_blocking(move |storage| storage.confirmed_block(slot))
+            .await
+    }
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+        let signature = *signature;
+        self.run_blocking(move |storage| Ok(storage.transaction_info(&signature)?.into()))
+            .await
+    }
+
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>> {
+        let signature = *signature;
+        self.run_blocking(move |storage| storage.confirmed_transaction(&signature))
+            .await
+    }
+
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<(ConfirmedTransactionStatusWithSignature, u32)>> {
+        let address = *address;
+        let before_signature = before_signature.cloned();
+        let until_signature = until_signature.cloned();
+        self.run_blocking(move |storage| {
+            storage.confirmed_signatures_for_address(
+                &address,
+                before_signature.as_ref(),
+                until_signature.as_ref(),
+                limit,
+            )
+        })
+        .await
+    }
+
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()> {
+        self.run_blocking(move |storage| storage.store_confirmed_block(slot, confirmed_block))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash, signature::Keypair, system_transaction, transaction::TransactionError,
+    };
+    use solana_transaction_status::{TransactionStatusMeta, TransactionWithStatusMeta};
+    use std::future::Future;
+    use tokio::runtime::Runtime;
+
+    fn confirmed_block(
+        parent_slot: Slot,
+        transactions: Vec<TransactionWithStatusMeta>,
+    ) -> ConfirmedBlock {
+        ConfirmedBlock {
+            previous_blockhash: Hash::new_unique().to_string(),
+            blockhash: Hash::new_unique().to_string(),
+            parent_slot,
+            transactions,
+            rewards: vec![],
+            block_time: Some(1_600_000_000),
+        }
+    }
+
+    fn transfer(
+        from: &Keypair,
+        to: &Pubkey,
+        lamports: u64,
+        failed: bool,
+    ) -> TransactionWithStatusMeta {
+        let status = if failed {
+            Err(TransactionError::AccountNotFound)
+        } else {
+            Ok(())
+        };
+        TransactionWithStatusMeta {
+            transaction: system_transaction::transfer(from, to, lamports, Hash::default()),
+            meta: Some(TransactionStatusMeta {
+                status,
+                fee: 5000,
+                pre_balances: vec![10, 0, 1],
+                post_balances: vec![9, 1, 1],
+                // What a protobuf round trip makes of missing inner instructions and logs
+                inner_instructions: Some(vec![]),
+                log_messages: Some(vec![]),
+            }),
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        Runtime::new().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_local_ledger_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalLedgerStorage::new(dir.path().to_path_buf(), false).unwrap();
+        assert_eq!(block_on(storage.get_first_available_block()).unwrap(), None);
+
+        let from = Keypair::new();
+        let to = Pubkey::new_unique();
+        let tx1 = transfer(&from, &to, 1, false);
+        let tx2 = transfer(&from, &to, 2, true);
+        let tx3 = transfer(&from, &to, 3, false);
+        let signature = |tx: &TransactionWithStatusMeta| tx.transaction.signatures[0];
+        let block3 = confirmed_block(2, vec![tx1.clone(), tx2.clone()]);
+        let block5 = confirmed_block(3, vec![tx3.clone()]);
+        block_on(storage.upload_confirmed_block(3, block3.clone())).unwrap();
+        block_on(storage.upload_confirmed_block(5, block5)).unwrap();
+
+        assert_eq!(
+            block_on(storage.get_first_available_block()).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(0, 10)).unwrap(),
+            vec![3, 5]
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(4, 10)).unwrap(),
+            vec![5]
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(0, 1)).unwrap(),
+            vec![3]
+        );
+
+        let block = block_on(storage.get_confirmed_block(3)).unwrap();
+        assert_eq!(block.blockhash, block3.blockhash);
+        assert_eq!(block.transactions, block3.transactions);
+        assert!(matches!(
+            block_on(storage.get_confirmed_block(4)),
+            Err(Error::BlockNotFound(4))
+        ));
+
+        let status = block_on(storage.get_signature_status(&signature(&tx2))).unwrap();
+        assert_eq!(status.slot, 3);
+        assert_eq!(status.err, Some(TransactionError::AccountNotFound));
+        assert!(matches!(
+            block_on(storage.get_signature_status(&Signature::default())),
+            Err(Error::SignatureNotFound)
+        ));
+        let transaction = block_on(storage.get_confirmed_transaction(&signature(&tx3)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.slot, 5);
+        assert_eq!(transaction.transaction, tx3);
+
+        let signatures = |before: Option<Signature>, until: Option<Signature>, limit| {
+            block_on(storage.get_confirmed_signatures_for_address(
+                &to,
+                before.as_ref(),
+                until.as_ref(),
+                limit,
+            ))
+            .unwrap()
+            .into_iter()
+            .map(|(info, index)| (info.signature, info.slot, index))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            signatures(None, None, 10),
+            vec![
+                (signature(&tx3), 5, 0),
+                (signature(&tx2), 3, 1),
+                (signature(&tx1), 3, 0)
+            ]
+        );
+        assert_eq!(
+            signatures(None, None, 2),
+            vec![(signature(&tx3), 5, 0), (signature(&tx2), 3, 1)]
+        );
+        assert_eq!(
+            signatures(Some(signature(&tx2)), None, 10),
+            vec![(signature(&tx1), 3, 0)]
+        );
+        assert_eq!(
+            signatures(None, Some(signature(&tx1)), 10),
+            vec![(signature(&tx3), 5, 0), (signature(&tx2), 3, 1)]
+        );
+
+        // Listings continue into the following block shards
+        let far_slot = 2 * 65536 + 1;
+        block_on(storage.upload_confirmed_block(far_slot, confirmed_block(5, vec![]))).unwrap();
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(4, 10)).unwrap(),
+            vec![5, far_slot]
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(6, 10)).unwrap(),
+            vec![far_slot]
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_blocks(0, 2)).unwrap(),
+            vec![3, 5]
+        );
+        assert_eq!(
+            block_on(storage.get_confirmed_block(far_slot))
+                .unwrap()
+                .parent_slot,
+            5
+        );
+
+        let read_only = LocalLedgerStorage::new(dir.path().to_path_buf(), true).unwrap();
+        assert_eq!(
+            block_on(read_only.get_confirmed_blocks(0, 10)).unwrap(),
+            vec![3, 5, far_slot]
+        );
+        assert!(matches!(
+            block_on(read_only.upload_confirmed_block(6, confirmed_block(5, vec![]))),
+            Err(Error::ReadOnly)
+        ));
+    }
+}
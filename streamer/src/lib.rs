@@ -1,4 +1,5 @@
 pub mod packet;
+pub mod quic;
 pub mod recvmmsg;
 pub mod sendmmsg;
 pub mod streamer;
@@ -0,0 +1,2258 @@
+//! The `quic` module implements the subset of QUIC version 1 that the TPU uses to receive
+//! transactions. Every transaction is sent on its own unidirectional stream, both ends
+//! authenticate with a self-signed certificate of their node identity, and the number of
+//! streams a client may have open at once is proportional to its stake in the current epoch.
+//!
+//! Only what the TPU needs is implemented: 8 byte connection ids, no key updates, no
+//! connection migration, no 0-RTT and no congestion control beyond a cap on the number of
+//! packets in flight.
+
+use crate::{
+    packet::{Packet, Packets, NUM_PACKETS, PACKET_DATA_SIZE},
+    streamer::PacketSender,
+};
+use rand::{thread_rng, Rng};
+use ring::{aead, hkdf};
+use rustls::{
+    quic::{ClientQuicExt, DirectionalKeys, Keys, QuicExt, ServerQuicExt},
+    ClientCertVerified, ClientCertVerifier, ClientSession, DistinguishedNames,
+    NoServerSessionStorage, ProtocolVersion, RootCertStore, ServerCertVerified, ServerCertVerifier,
+    ServerSession, Session, TLSError,
+};
+pub use rustls::{Certificate, ClientConfig, PrivateKey, ServerConfig};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::SendError,
+        Arc, RwLock,
+    },
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// The QUIC listener of a node is bound to its TPU port plus this offset
+pub const QUIC_PORT_OFFSET: u16 = 6;
+/// Streams an unstaked peer may have open at once
+pub const QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS: usize = 128;
+/// Streams shared among all staked peers in proportion to their stake
+pub const QUIC_TOTAL_STAKED_CONCURRENT_STREAMS: usize = 100_000;
+pub const QUIC_MAX_CONNECTIONS: usize = 2_500;
+pub const QUIC_MAX_DATAGRAM_SIZE: usize = 1350;
+pub const QUIC_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+pub const QUIC_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Transport error codes, RFC 9000 section 20.1
+const NO_ERROR: u64 = 0x0;
+const FLOW_CONTROL_ERROR: u64 = 0x3;
+const STREAM_LIMIT_ERROR: u64 = 0x4;
+const STREAM_STATE_ERROR: u64 = 0x5;
+const FINAL_SIZE_ERROR: u64 = 0x6;
+const FRAME_ENCODING_ERROR: u64 = 0x7;
+const TRANSPORT_PARAMETER_ERROR: u64 = 0x8;
+const PROTOCOL_VIOLATION: u64 = 0xa;
+const CRYPTO_BUFFER_EXCEEDED: u64 = 0xd;
+const CRYPTO_ERROR: u64 = 0x100;
+// TLS alerts sent as CRYPTO_ERROR + alert
+const BAD_CERTIFICATE_ALERT: u64 = 42;
+const INTERNAL_ERROR_ALERT: u64 = 80;
+
+const QUIC_VERSION: u32 = 1;
+const INITIAL_SALT: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+const SERVER_NAME: &str = "solana-tpu";
+const CONNECTION_ID_LEN: usize = 8;
+const MAX_CONNECTION_ID_LEN: usize = 20;
+const PACKET_NUMBER_LEN: usize = 4;
+const SAMPLE_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+const MIN_INITIAL_DATAGRAM_SIZE: usize = 1200;
+const MAX_CRYPTO_FRAME_DATA: usize = 1000;
+const MAX_CRYPTO_BUFFER: u64 = 64 * 1024;
+const MAX_ACK_RANGES: usize = 32;
+const MAX_ACK_DELAY: Duration = Duration::from_millis(5);
+const ACK_ELICITING_THRESHOLD: usize = 8;
+const INITIAL_RTT: Duration = Duration::from_millis(100);
+const TIMER_GRANULARITY: Duration = Duration::from_millis(1);
+// How long the server waits for a datagram before checking whether it should exit
+const MAX_RECV_TIMEOUT: Duration = Duration::from_millis(100);
+const PACKET_THRESHOLD: u64 = 3;
+const MAX_PTO_BACKOFF: u32 = 6;
+const MAX_PACKETS_IN_FLIGHT: usize = 256;
+const MAX_QUEUED_STREAMS: usize = 10_000;
+
+// Frame types, RFC 9000 section 19
+const PADDING_FRAME: u8 = 0x00;
+const PING_FRAME: u8 = 0x01;
+const ACK_FRAME: u8 = 0x02;
+const ACK_ECN_FRAME: u8 = 0x03;
+const CRYPTO_FRAME: u8 = 0x06;
+const STREAM_FRAME: u8 = 0x08;
+const STREAM_FRAME_FIN: u8 = 0x01;
+const STREAM_FRAME_LEN: u8 = 0x02;
+const STREAM_FRAME_OFF: u8 = 0x04;
+const MAX_DATA_FRAME: u8 = 0x10;
+const MAX_STREAM_DATA_FRAME: u8 = 0x11;
+const MAX_STREAMS_BIDI_FRAME: u8 = 0x12;
+const MAX_STREAMS_UNI_FRAME: u8 = 0x13;
+const DATA_BLOCKED_FRAME: u8 = 0x14;
+const STREAM_DATA_BLOCKED_FRAME: u8 = 0x15;
+const STREAMS_BLOCKED_BIDI_FRAME: u8 = 0x16;
+const STREAMS_BLOCKED_UNI_FRAME: u8 = 0x17;
+const CONNECTION_CLOSE_FRAME: u8 = 0x1c;
+const APPLICATION_CLOSE_FRAME: u8 = 0x1d;
+const HANDSHAKE_DONE_FRAME: u8 = 0x1e;
+
+// Transport parameters, RFC 9000 section 18.2
+const ORIGINAL_DESTINATION_CONNECTION_ID: u64 = 0x00;
+const MAX_IDLE_TIMEOUT: u64 = 0x01;
+const INITIAL_MAX_DATA: u64 = 0x04;
+const INITIAL_MAX_STREAM_DATA_UNI: u64 = 0x07;
+const INITIAL_MAX_STREAMS_UNI: u64 = 0x09;
+const INITIAL_SOURCE_CONNECTION_ID: u64 = 0x0f;
+
+// DER encodings of an Ed25519 certificate, RFC 8410
+const ED25519_ALGORITHM_ID: &[u8] = &[0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70];
+const ED25519_PUBLIC_KEY_PREFIX: &[u8] =
+    &[0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+const ED25519_PKCS8_PREFIX: &[u8] = &[
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+#[derive(Error, Debug)]
+pub enum QuicError {
+    #[error("TLS error: {0}")]
+    Tls(#[from] TLSError),
+
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+
+    #[error("stream data exceeds {} bytes", PACKET_DATA_SIZE)]
+    StreamTooLarge,
+
+    #[error("too many queued streams")]
+    TooManyQueuedStreams,
+}
+
+/// Stakes of the current epoch, used to size the stream limits of peers
+#[derive(Debug, Default)]
+pub struct StakedNodes {
+    pub stakes: HashMap<Pubkey, u64>,
+    pub total_stake: u64,
+}
+
+impl StakedNodes {
+    pub fn new(stakes: HashMap<Pubkey, u64>) -> Self {
+        let total_stake = stakes.values().sum();
+        Self {
+            stakes,
+            total_stake,
+        }
+    }
+}
+
+/// Returns how many unidirectional streams a peer with `peer_stake` may have open at once
+pub fn compute_max_allowed_uni_streams(peer_stake: u64, total_stake: u64) -> usize {
+    if peer_stake == 0 || total_stake == 0 {
+        QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+    } else {
+        let staked_streams = u128::from(peer_stake) * QUIC_TOTAL_STAKED_CONCURRENT_STREAMS as u128
+            / u128::from(total_stake);
+        cmp::max(
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS,
+            staked_streams as usize,
+        )
+    }
+}
+
+fn der_tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    let len = value.len();
+    if len < 0x80 {
+        der.push(len as u8);
+    } else if len < 0x100 {
+        der.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        der.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+    }
+    der.extend_from_slice(value);
+    der
+}
+
+// Returns the tag and value of the first DER element of `der`, and what follows it
+fn der_next(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, der) = der.split_first()?;
+    let (&len, der) = der.split_first()?;
+    let (len, der) = match len {
+        0..=0x7f => (usize::from(len), der),
+        0x81 => (usize::from(*der.first()?), der.get(1..)?),
+        0x82 => (
+            usize::from(*der.first()?) << 8 | usize::from(*der.get(1)?),
+            der.get(2..)?,
+        ),
+        _ => return None,
+    };
+    if der.len() < len {
+        return None;
+    }
+    Some((tag, &der[..len], &der[len..]))
+}
+
+/// Creates a self-signed Ed25519 certificate of `keypair` and the PKCS#8 encoding of its key
+pub fn new_self_signed_tls_certificate(keypair: &Keypair) -> (Certificate, PrivateKey) {
+    let keypair_bytes = keypair.to_bytes();
+    let key = [ED25519_PKCS8_PREFIX, &keypair_bytes[..32]].concat();
+
+    let common_name = [
+        &[0x06, 0x03, 0x55, 0x04, 0x03][..],
+        &der_tlv(0x0c, b"Solana node"),
+    ]
+    .concat();
+    let name = der_tlv(0x30, &der_tlv(0x31, &der_tlv(0x30, &common_name)));
+    let validity = der_tlv(
+        0x30,
+        &[
+            der_tlv(0x17, b"700101000000Z"),
+            der_tlv(0x18, b"99991231235959Z"),
+        ]
+        .concat(),
+    );
+    let public_key = der_tlv(
+        0x30,
+        &[ED25519_PUBLIC_KEY_PREFIX, keypair.pubkey().as_ref()].concat(),
+    );
+    // webpki rejects certificates without extensions, so add a non-critical basic constraints
+    let basic_constraints = &[0x06, 0x03, 0x55, 0x1d, 0x13, 0x04, 0x02, 0x30, 0x00];
+    let extensions = der_tlv(0xa3, &der_tlv(0x30, &der_tlv(0x30, basic_constraints)));
+    let tbs_certificate = der_tlv(
+        0x30,
+        &[
+            &[0xa0, 0x03, 0x02, 0x01, 0x02][..],
+            &[0x02, 0x01, 0x01],
+            ED25519_ALGORITHM_ID,
+            &name,
+            &validity,
+            &name,
+            &public_key,
+            &extensions,
+        ]
+        .concat(),
+    );
+    let signature = keypair.sign_message(&tbs_certificate);
+    let certificate = der_tlv(
+        0x30,
+        &[
+            &tbs_certificate[..],
+            ED25519_ALGORITHM_ID,
+            &der_tlv(0x03, &[&[0][..], signature.as_ref()].concat()),
+        ]
+        .concat(),
+    );
+    (Certificate(certificate), PrivateKey(key))
+}
+
+/// Returns the node identity of an Ed25519 certificate
+pub fn get_pubkey_from_tls_certificate(certificate: &Certificate) -> Option<Pubkey> {
+    // rustls checks the handshake signature against the key webpki parses, so the certificate
+    // has to be acceptable to webpki as well
+    webpki::EndEntityCert::from(&certificate.0).ok()?;
+    let (_, certificate, _) = der_next(&certificate.0)?;
+    let (_, tbs_certificate, _) = der_next(certificate)?;
+    // Skip the version, serial number, signature algorithm, issuer, validity and subject
+    let mut fields = tbs_certificate;
+    for _ in 0..6 {
+        fields = der_next(fields)?.2;
+    }
+    let (tag, public_key, _) = der_next(fields)?;
+    if tag != 0x30
+        || public_key.len() != ED25519_PUBLIC_KEY_PREFIX.len() + 32
+        || !public_key.starts_with(ED25519_PUBLIC_KEY_PREFIX)
+    {
+        return None;
+    }
+    Some(Pubkey::new(&public_key[ED25519_PUBLIC_KEY_PREFIX.len()..]))
+}
+
+// Certificates are self-signed, so any certificate of an Ed25519 key is accepted. The handshake
+// signature proves the peer holds the key, which makes the key the peer's identity
+struct IdentityCertificateVerifier;
+
+impl IdentityCertificateVerifier {
+    fn verify(presented_certs: &[Certificate]) -> Result<(), TLSError> {
+        match presented_certs {
+            [certificate] if get_pubkey_from_tls_certificate(certificate).is_some() => Ok(()),
+            _ => Err(TLSError::General(
+                "expected a single node identity certificate".to_string(),
+            )),
+        }
+    }
+}
+
+impl ServerCertVerifier for IdentityCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        _dns_name: webpki::DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        Self::verify(presented_certs).map(|_| ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for IdentityCertificateVerifier {
+    fn client_auth_root_subjects(
+        &self,
+        _sni: Option<&webpki::DNSName>,
+    ) -> Option<DistinguishedNames> {
+        Some(DistinguishedNames::new())
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[Certificate],
+        _sni: Option<&webpki::DNSName>,
+    ) -> Result<ClientCertVerified, TLSError> {
+        Self::verify(presented_certs).map(|_| ClientCertVerified::assertion())
+    }
+}
+
+pub fn new_server_config(keypair: &Keypair) -> Result<Arc<ServerConfig>, QuicError> {
+    let (certificate, key) = new_self_signed_tls_certificate(keypair);
+    let mut config = ServerConfig::new(Arc::new(IdentityCertificateVerifier));
+    config.versions = vec![ProtocolVersion::TLSv1_3];
+    config.session_storage = Arc::new(NoServerSessionStorage {});
+    config.set_single_cert(vec![certificate], key)?;
+    Ok(Arc::new(config))
+}
+
+pub fn new_client_config(keypair: &Keypair) -> Result<Arc<ClientConfig>, QuicError> {
+    let (certificate, key) = new_self_signed_tls_certificate(keypair);
+    let mut config = ClientConfig::new();
+    config.versions = vec![ProtocolVersion::TLSv1_3];
+    config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(IdentityCertificateVerifier));
+    config.set_single_client_cert(vec![certificate], key)?;
+    Ok(Arc::new(config))
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: u64) {
+    if value < 1 << 6 {
+        buf.push(value as u8);
+    } else if value < 1 << 14 {
+        buf.extend_from_slice(&(value as u16 | 0x4000).to_be_bytes());
+    } else if value < 1 << 30 {
+        buf.extend_from_slice(&(value as u32 | 0x8000_0000).to_be_bytes());
+    } else {
+        assert!(value < 1 << 62);
+        buf.extend_from_slice(&(value | 0xc000_0000_0000_0000).to_be_bytes());
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position == self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let first = self.u8()?;
+        let rest = self.bytes((1 << (first >> 6)) - 1)?;
+        Some(rest.iter().fold(u64::from(first & 0x3f), |value, byte| {
+            value << 8 | u64::from(*byte)
+        }))
+    }
+}
+
+// RFC 9000 appendix A.3
+fn decode_packet_number(largest: Option<u64>, truncated: u64, len: usize) -> u64 {
+    let expected = largest.map_or(0, |largest| largest + 1);
+    let window = 1u64 << (len * 8);
+    let half_window = window / 2;
+    let candidate = (expected & !(window - 1)) | truncated;
+    if candidate + half_window <= expected && candidate < (1 << 62) - window {
+        candidate + window
+    } else if candidate > expected + half_window && candidate >= window {
+        candidate - window
+    } else {
+        candidate
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct TransportParameters {
+    original_destination_connection_id: Option<Vec<u8>>,
+    max_idle_timeout: u64,
+    initial_max_data: u64,
+    initial_max_stream_data_uni: u64,
+    initial_max_streams_uni: u64,
+    initial_source_connection_id: Vec<u8>,
+}
+
+impl TransportParameters {
+    fn encode(&self) -> Vec<u8> {
+        fn write_parameter(buf: &mut Vec<u8>, id: u64, value: &[u8]) {
+            write_varint(buf, id);
+            write_varint(buf, value.len() as u64);
+            buf.extend_from_slice(value);
+        }
+        fn write_integer_parameter(buf: &mut Vec<u8>, id: u64, value: u64) {
+            let mut encoded = vec![];
+            write_varint(&mut encoded, value);
+            write_parameter(buf, id, &encoded);
+        }
+
+        let mut buf = vec![];
+        if let Some(connection_id) = &self.original_destination_connection_id {
+            write_parameter(&mut buf, ORIGINAL_DESTINATION_CONNECTION_ID, connection_id);
+        }
+        write_integer_parameter(&mut buf, MAX_IDLE_TIMEOUT, self.max_idle_timeout);
+        write_integer_parameter(&mut buf, INITIAL_MAX_DATA, self.initial_max_data);
+        write_integer_parameter(
+            &mut buf,
+            INITIAL_MAX_STREAM_DATA_UNI,
+            self.initial_max_stream_data_uni,
+        );
+        write_integer_parameter(
+            &mut buf,
+            INITIAL_MAX_STREAMS_UNI,
+            self.initial_max_streams_uni,
+        );
+        write_parameter(
+            &mut buf,
+            INITIAL_SOURCE_CONNECTION_ID,
+            &self.initial_source_connection_id,
+        );
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(buf);
+        let mut parameters = Self::default();
+        let mut initial_source_connection_id = None;
+        while !reader.is_empty() {
+            let id = reader.varint()?;
+            let len = reader.varint()?;
+            let value = reader.bytes(len as usize)?;
+            let integer = || {
+                let mut reader = Reader::new(value);
+                let integer = reader.varint()?;
+                if reader.is_empty() {
+                    Some(integer)
+                } else {
+                    None
+                }
+            };
+            match id {
+                ORIGINAL_DESTINATION_CONNECTION_ID => {
+                    parameters.original_destination_connection_id = Some(value.to_vec())
+                }
+                MAX_IDLE_TIMEOUT => parameters.max_idle_timeout = integer()?,
+                INITIAL_MAX_DATA => parameters.initial_max_data = integer()?,
+                INITIAL_MAX_STREAM_DATA_UNI => parameters.initial_max_stream_data_uni = integer()?,
+                INITIAL_MAX_STREAMS_UNI => parameters.initial_max_streams_uni = integer()?,
+                INITIAL_SOURCE_CONNECTION_ID => initial_source_connection_id = Some(value.to_vec()),
+                _ => {}
+            }
+        }
+        parameters.initial_source_connection_id = initial_source_connection_id?;
+        Some(parameters)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SpaceId {
+    Initial,
+    Handshake,
+    Data,
+}
+
+const SPACES: [SpaceId; 3] = [SpaceId::Initial, SpaceId::Handshake, SpaceId::Data];
+
+impl SpaceId {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn next(self) -> Self {
+        match self {
+            SpaceId::Initial => SpaceId::Handshake,
+            SpaceId::Handshake | SpaceId::Data => SpaceId::Data,
+        }
+    }
+}
+
+struct PacketHeader<'a> {
+    space: SpaceId,
+    long: bool,
+    dcid: &'a [u8],
+    scid: &'a [u8],
+    packet_number_offset: usize,
+    len: usize,
+}
+
+impl<'a> PacketHeader<'a> {
+    fn parse(buf: &'a [u8]) -> Option<Self> {
+        let first = *buf.first()?;
+        if first & 0x40 == 0 {
+            return None;
+        }
+        if first & 0x80 == 0 {
+            return Some(Self {
+                space: SpaceId::Data,
+                long: false,
+                dcid: buf.get(1..1 + CONNECTION_ID_LEN)?,
+                scid: &[],
+                packet_number_offset: 1 + CONNECTION_ID_LEN,
+                len: buf.len(),
+            });
+        }
+        let mut reader = Reader::new(buf);
+        reader.u8()?;
+        let mut version = [0u8; 4];
+        version.copy_from_slice(reader.bytes(4)?);
+        if u32::from_be_bytes(version) != QUIC_VERSION {
+            return None;
+        }
+        let dcid_len = usize::from(reader.u8()?);
+        if dcid_len > MAX_CONNECTION_ID_LEN {
+            return None;
+        }
+        let dcid = reader.bytes(dcid_len)?;
+        let scid_len = usize::from(reader.u8()?);
+        if scid_len > MAX_CONNECTION_ID_LEN {
+            return None;
+        }
+        let scid = reader.bytes(scid_len)?;
+        let space = match (first >> 4) & 0x03 {
+            0 => {
+                let token_len = reader.varint()?;
+                reader.bytes(token_len as usize)?;
+                SpaceId::Initial
+            }
+            2 => SpaceId::Handshake,
+            // 0-RTT and Retry packets are never sent
+            _ => return None,
+        };
+        let len = reader.varint()? as usize;
+        let packet_number_offset = reader.position;
+        let len = packet_number_offset.checked_add(len)?;
+        if len > buf.len() {
+            return None;
+        }
+        Some(Self {
+            space,
+            long: true,
+            dcid,
+            scid,
+            packet_number_offset,
+            len,
+        })
+    }
+}
+
+// Removes the header protection of `packet` and decrypts its payload in place, returning the
+// packet number and the payload
+fn open_packet<'a>(
+    packet: &'a mut [u8],
+    packet_number_offset: usize,
+    long: bool,
+    keys: &DirectionalKeys,
+    largest_packet_number: Option<u64>,
+) -> Option<(u64, &'a [u8])> {
+    let sample_offset = packet_number_offset + PACKET_NUMBER_LEN;
+    let sample = packet.get(sample_offset..sample_offset + SAMPLE_LEN)?;
+    let mask = keys.header.new_mask(sample).ok()?;
+    packet[0] ^= mask[0] & if long { 0x0f } else { 0x1f };
+    let packet_number_len = usize::from(packet[0] & 0x03) + 1;
+    let mut truncated_packet_number = 0;
+    for i in 0..packet_number_len {
+        packet[packet_number_offset + i] ^= mask[1 + i];
+        truncated_packet_number =
+            truncated_packet_number << 8 | u64::from(packet[packet_number_offset + i]);
+    }
+    let packet_number = decode_packet_number(
+        largest_packet_number,
+        truncated_packet_number,
+        packet_number_len,
+    );
+    let (header, payload) = packet.split_at_mut(packet_number_offset + packet_number_len);
+    let payload = keys
+        .packet
+        .key
+        .open_in_place(
+            keys.packet.iv.nonce_for(packet_number),
+            aead::Aad::from(&header[..]),
+            payload,
+        )
+        .ok()?;
+    Some((packet_number, payload))
+}
+
+// Encrypts `payload` and protects the header. The Length field of a long header is the last two
+// bytes of `header`
+fn seal_packet(
+    mut header: Vec<u8>,
+    long: bool,
+    packet_number: u64,
+    mut payload: Vec<u8>,
+    keys: &DirectionalKeys,
+) -> Vec<u8> {
+    if long {
+        let len = (PACKET_NUMBER_LEN + payload.len() + TAG_LEN) as u16 | 0x4000;
+        let header_len = header.len();
+        header[header_len - 2..].copy_from_slice(&len.to_be_bytes());
+    }
+    let packet_number_offset = header.len();
+    header.extend_from_slice(&(packet_number as u32).to_be_bytes());
+    let tag = keys
+        .packet
+        .key
+        .seal_in_place_separate_tag(
+            keys.packet.iv.nonce_for(packet_number),
+            aead::Aad::from(&header[..]),
+            &mut payload,
+        )
+        .expect("seal packet");
+    let mut packet = header;
+    packet.extend_from_slice(&payload);
+    packet.extend_from_slice(tag.as_ref());
+    let sample_offset = packet_number_offset + PACKET_NUMBER_LEN;
+    let mask = keys
+        .header
+        .new_mask(&packet[sample_offset..sample_offset + SAMPLE_LEN])
+        .expect("header protection mask");
+    packet[0] ^= mask[0] & if long { 0x0f } else { 0x1f };
+    for i in 0..PACKET_NUMBER_LEN {
+        packet[packet_number_offset + i] ^= mask[1 + i];
+    }
+    packet
+}
+
+enum Frame<'a> {
+    Padding,
+    Ping,
+    // Acknowledged packet number ranges, inclusive and in descending order
+    Ack(Vec<(u64, u64)>),
+    Crypto {
+        offset: u64,
+        data: &'a [u8],
+    },
+    Stream {
+        id: u64,
+        offset: u64,
+        data: &'a [u8],
+        fin: bool,
+    },
+    MaxStreamsUni(u64),
+    ConnectionClose(u64),
+    HandshakeDone,
+    // Flow control frames, which are not enforced
+    Ignored,
+}
+
+impl<'a> Frame<'a> {
+    fn decode(reader: &mut Reader<'a>) -> Option<Self> {
+        let frame_type = reader.u8()?;
+        let frame = match frame_type {
+            PADDING_FRAME => Frame::Padding,
+            PING_FRAME => Frame::Ping,
+            ACK_FRAME | ACK_ECN_FRAME => {
+                let largest = reader.varint()?;
+                let _ack_delay = reader.varint()?;
+                let range_count = reader.varint()?;
+                let first_range = reader.varint()?;
+                let mut smallest = largest.checked_sub(first_range)?;
+                let mut ranges = vec![(smallest, largest)];
+                for _ in 0..range_count {
+                    let gap = reader.varint()?;
+                    let len = reader.varint()?;
+                    let end = smallest.checked_sub(gap)?.checked_sub(2)?;
+                    smallest = end.checked_sub(len)?;
+                    if ranges.len() < MAX_ACK_RANGES {
+                        ranges.push((smallest, end));
+                    }
+                }
+                if frame_type == ACK_ECN_FRAME {
+                    for _ in 0..3 {
+                        reader.varint()?;
+                    }
+                }
+                Frame::Ack(ranges)
+            }
+            CRYPTO_FRAME => {
+                let offset = reader.varint()?;
+                let len = reader.varint()?;
+                Frame::Crypto {
+                    offset,
+                    data: reader.bytes(len as usize)?,
+                }
+            }
+            STREAM_FRAME..=0x0f => {
+                let id = reader.varint()?;
+                let offset = if frame_type & STREAM_FRAME_OFF != 0 {
+                    reader.varint()?
+                } else {
+                    0
+                };
+                let data = if frame_type & STREAM_FRAME_LEN != 0 {
+                    let len = reader.varint()?;
+                    reader.bytes(len as usize)?
+                } else {
+                    reader.bytes(reader.buf.len() - reader.position)?
+                };
+                offset.checked_add(data.len() as u64)?;
+                Frame::Stream {
+                    id,
+                    offset,
+                    data,
+                    fin: frame_type & STREAM_FRAME_FIN != 0,
+                }
+            }
+            MAX_STREAMS_UNI_FRAME => Frame::MaxStreamsUni(reader.varint()?),
+            MAX_DATA_FRAME
+            | MAX_STREAMS_BIDI_FRAME
+            | DATA_BLOCKED_FRAME
+            | STREAMS_BLOCKED_BIDI_FRAME
+            | STREAMS_BLOCKED_UNI_FRAME => {
+                reader.varint()?;
+                Frame::Ignored
+            }
+            MAX_STREAM_DATA_FRAME | STREAM_DATA_BLOCKED_FRAME => {
+                reader.varint()?;
+                reader.varint()?;
+                Frame::Ignored
+            }
+            CONNECTION_CLOSE_FRAME | APPLICATION_CLOSE_FRAME => {
+                let error_code = reader.varint()?;
+                if frame_type == CONNECTION_CLOSE_FRAME {
+                    reader.varint()?;
+                }
+                let reason_len = reader.varint()?;
+                reader.bytes(reason_len as usize)?;
+                Frame::ConnectionClose(error_code)
+            }
+            HANDSHAKE_DONE_FRAME => Frame::HandshakeDone,
+            _ => return None,
+        };
+        Some(frame)
+    }
+
+    fn is_ack_eliciting(&self) -> bool {
+        !matches!(
+            self,
+            Frame::Padding | Frame::Ack(_) | Frame::ConnectionClose(_)
+        )
+    }
+
+    fn is_allowed_during_handshake(&self) -> bool {
+        matches!(
+            self,
+            Frame::Padding
+                | Frame::Ping
+                | Frame::Ack(_)
+                | Frame::Crypto { .. }
+                | Frame::ConnectionClose(_)
+        )
+    }
+}
+
+// Frames that are retransmitted when the packet carrying them is lost
+enum SentFrame {
+    Ping,
+    Crypto { offset: u64, data: Vec<u8> },
+    Stream { id: u64, data: Vec<u8> },
+    MaxStreamsUni,
+    HandshakeDone,
+}
+
+impl SentFrame {
+    fn encode(&self, buf: &mut Vec<u8>, max_streams: u64) {
+        match self {
+            SentFrame::Ping => buf.push(PING_FRAME),
+            SentFrame::Crypto { offset, data } => {
+                buf.push(CRYPTO_FRAME);
+                write_varint(buf, *offset);
+                write_varint(buf, data.len() as u64);
+                buf.extend_from_slice(data);
+            }
+            SentFrame::Stream { id, data } => {
+                // The whole stream always fits in one frame
+                buf.push(STREAM_FRAME | STREAM_FRAME_LEN | STREAM_FRAME_FIN);
+                write_varint(buf, *id);
+                write_varint(buf, data.len() as u64);
+                buf.extend_from_slice(data);
+            }
+            SentFrame::MaxStreamsUni => {
+                buf.push(MAX_STREAMS_UNI_FRAME);
+                write_varint(buf, max_streams);
+            }
+            SentFrame::HandshakeDone => buf.push(HANDSHAKE_DONE_FRAME),
+        }
+    }
+}
+
+struct SentPacket {
+    time_sent: Instant,
+    frames: Vec<SentFrame>,
+}
+
+#[derive(Default)]
+struct PacketSpace {
+    keys: Option<Keys>,
+    next_packet_number: u64,
+    largest_received: Option<u64>,
+    // Received packet numbers, inclusive ranges in ascending order
+    received: VecDeque<(u64, u64)>,
+    ack_deadline: Option<Instant>,
+    ack_eliciting_since_ack: usize,
+    crypto_send_offset: u64,
+    crypto_read_offset: u64,
+    crypto_received: BTreeMap<u64, Vec<u8>>,
+    pending: VecDeque<SentFrame>,
+    // Ack-eliciting packets waiting for an acknowledgement
+    sent: BTreeMap<u64, SentPacket>,
+    probe: bool,
+}
+
+impl PacketSpace {
+    fn is_duplicate(&self, packet_number: u64) -> bool {
+        match self.received.front() {
+            Some((smallest, _)) if packet_number < *smallest => true,
+            _ => self
+                .received
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&packet_number)),
+        }
+    }
+
+    fn on_packet_received(&mut self, packet_number: u64, ack_eliciting: bool, now: Instant) {
+        self.largest_received = cmp::max(self.largest_received, Some(packet_number));
+        let position = self
+            .received
+            .iter()
+            .position(|(start, _)| *start > packet_number)
+            .unwrap_or_else(|| self.received.len());
+        self.received
+            .insert(position, (packet_number, packet_number));
+        if position + 1 < self.received.len() && self.received[position + 1].0 == packet_number + 1
+        {
+            self.received[position].1 = self.received[position + 1].1;
+            self.received.remove(position + 1);
+        }
+        if position > 0 && self.received[position - 1].1 + 1 == packet_number {
+            self.received[position - 1].1 = self.received[position].1;
+            self.received.remove(position);
+        }
+        while self.received.len() > MAX_ACK_RANGES {
+            self.received.pop_front();
+        }
+        if ack_eliciting {
+            self.ack_eliciting_since_ack += 1;
+            if self.ack_deadline.is_none() {
+                self.ack_deadline = Some(now + MAX_ACK_DELAY);
+            }
+        }
+    }
+
+    fn encode_ack(&self, buf: &mut Vec<u8>) {
+        let mut ranges = self.received.iter().rev();
+        let (mut smallest, largest) = match ranges.next() {
+            Some(range) => *range,
+            None => return,
+        };
+        buf.push(ACK_FRAME);
+        write_varint(buf, largest);
+        write_varint(buf, 0);
+        write_varint(buf, self.received.len() as u64 - 1);
+        write_varint(buf, largest - smallest);
+        for (start, end) in ranges {
+            write_varint(buf, smallest - end - 2);
+            write_varint(buf, end - start);
+            smallest = *start;
+        }
+    }
+
+    fn queue_crypto(&mut self, data: &[u8]) {
+        for chunk in data.chunks(MAX_CRYPTO_FRAME_DATA) {
+            self.pending.push_back(SentFrame::Crypto {
+                offset: self.crypto_send_offset,
+                data: chunk.to_vec(),
+            });
+            self.crypto_send_offset += chunk.len() as u64;
+        }
+    }
+
+    fn discard(&mut self) {
+        *self = Self {
+            next_packet_number: self.next_packet_number,
+            ..Self::default()
+        };
+    }
+}
+
+#[derive(Default)]
+struct RecvStream {
+    data: Vec<u8>,
+    // Received byte ranges, half-open and in ascending order
+    received: Vec<(usize, usize)>,
+    final_size: Option<usize>,
+}
+
+impl RecvStream {
+    // Returns false if the data contradicts the final size of the stream
+    fn write(&mut self, offset: usize, data: &[u8], fin: bool) -> bool {
+        let end = offset + data.len();
+        let received_end = self.received.last().map_or(0, |(_, end)| *end);
+        match self.final_size {
+            Some(final_size) if end > final_size || (fin && end != final_size) => return false,
+            None if fin && received_end > end => return false,
+            _ => {}
+        }
+        if fin {
+            self.final_size = Some(end);
+        }
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[offset..end].copy_from_slice(data);
+        self.received.push((offset, end));
+        self.received.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(self.received.len());
+        for (start, end) in self.received.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = cmp::max(last.1, end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.received = merged;
+        true
+    }
+
+    fn is_complete(&self) -> bool {
+        match self.final_size {
+            Some(0) => true,
+            Some(final_size) => self.received == [(0, final_size)],
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Handshake,
+    Established,
+    Closing(u64),
+    Closed,
+}
+
+enum TlsSession {
+    Client(ClientSession),
+    Server(ServerSession),
+}
+
+impl TlsSession {
+    fn read_hs(&mut self, plaintext: &[u8]) -> Result<(), TLSError> {
+        match self {
+            TlsSession::Client(session) => session.read_hs(plaintext),
+            TlsSession::Server(session) => session.read_hs(plaintext),
+        }
+    }
+
+    fn write_hs(&mut self, buf: &mut Vec<u8>) -> Option<Keys> {
+        match self {
+            TlsSession::Client(session) => session.write_hs(buf),
+            TlsSession::Server(session) => session.write_hs(buf),
+        }
+    }
+
+    fn alert(&self) -> Option<u64> {
+        match self {
+            TlsSession::Client(session) => session.get_alert(),
+            TlsSession::Server(session) => session.get_alert(),
+        }
+        .map(|alert| u64::from(alert.get_u8()))
+    }
+
+    fn is_handshaking(&self) -> bool {
+        match self {
+            TlsSession::Client(session) => session.is_handshaking(),
+            TlsSession::Server(session) => session.is_handshaking(),
+        }
+    }
+
+    fn peer_certificates(&self) -> Option<Vec<Certificate>> {
+        match self {
+            TlsSession::Client(session) => session.get_peer_certificates(),
+            TlsSession::Server(session) => session.get_peer_certificates(),
+        }
+    }
+
+    fn transport_parameters(&self) -> Option<&[u8]> {
+        match self {
+            TlsSession::Client(session) => session.get_quic_transport_parameters(),
+            TlsSession::Server(session) => session.get_quic_transport_parameters(),
+        }
+    }
+}
+
+struct RttEstimator {
+    smoothed: Option<Duration>,
+    variance: Duration,
+}
+
+impl Default for RttEstimator {
+    fn default() -> Self {
+        Self {
+            smoothed: None,
+            variance: INITIAL_RTT / 2,
+        }
+    }
+}
+
+impl RttEstimator {
+    fn update(&mut self, sample: Duration) {
+        match self.smoothed {
+            None => {
+                self.smoothed = Some(sample);
+                self.variance = sample / 2;
+            }
+            Some(smoothed) => {
+                let difference = if smoothed > sample {
+                    smoothed - sample
+                } else {
+                    sample - smoothed
+                };
+                self.variance = (self.variance * 3 + difference) / 4;
+                self.smoothed = Some((smoothed * 7 + sample) / 8);
+            }
+        }
+    }
+
+    fn probe_timeout(&self) -> Duration {
+        self.smoothed.unwrap_or(INITIAL_RTT) + cmp::max(self.variance * 4, TIMER_GRANULARITY)
+    }
+}
+
+fn new_connection_id() -> Vec<u8> {
+    thread_rng().gen::<[u8; CONNECTION_ID_LEN]>().to_vec()
+}
+
+/// A QUIC connection. It does no I/O itself: datagrams received from the peer are passed to
+/// `handle_datagram`, and the ones to send are obtained with `poll_transmit` or sent by
+/// `transmit`
+pub struct Connection {
+    side: Side,
+    tls: TlsSession,
+    state: State,
+    error_code: Option<u64>,
+    remote_addr: SocketAddr,
+    local_cid: Vec<u8>,
+    remote_cid: Vec<u8>,
+    // The destination connection id of the client's first Initial packet
+    initial_dcid: Vec<u8>,
+    remote_cid_updated: bool,
+    spaces: [PacketSpace; 3],
+    // The space TLS handshake data is written in
+    tls_space: SpaceId,
+    peer_pubkey: Option<Pubkey>,
+    rtt: RttEstimator,
+    pto_count: u32,
+    handshake_deadline: Instant,
+    idle_deadline: Instant,
+    // Client: streams the server allows, streams opened and data waiting for a stream
+    peer_max_streams: u64,
+    next_stream: u64,
+    queued_streams: VecDeque<Vec<u8>>,
+    // Server: streams the client may open and how many of them may be open at once
+    max_streams: u64,
+    max_concurrent_streams: u64,
+    max_streams_pending: bool,
+    recv_streams: HashMap<u64, RecvStream>,
+    // Indexes of completed streams: all below `completed_below` and those in `completed`
+    completed_below: u64,
+    completed: BTreeSet<u64>,
+    num_completed_streams: u64,
+    received_streams: Vec<Vec<u8>>,
+}
+
+impl Connection {
+    /// Starts a connection to the server at `remote_addr`
+    pub fn connect(config: &Arc<ClientConfig>, remote_addr: SocketAddr, now: Instant) -> Self {
+        let local_cid = new_connection_id();
+        let initial_dcid = new_connection_id();
+        let parameters = TransportParameters {
+            max_idle_timeout: QUIC_IDLE_TIMEOUT.as_millis() as u64,
+            initial_source_connection_id: local_cid.clone(),
+            ..TransportParameters::default()
+        };
+        let server_name = webpki::DNSNameRef::try_from_ascii_str(SERVER_NAME).unwrap();
+        let session = ClientSession::new_quic(config, server_name, parameters.encode());
+        let mut connection = Self::new(
+            Side::Client,
+            TlsSession::Client(session),
+            remote_addr,
+            local_cid,
+            initial_dcid.clone(),
+            initial_dcid,
+            now,
+        );
+        connection.write_tls();
+        connection
+    }
+
+    fn accept(
+        config: &Arc<ServerConfig>,
+        remote_addr: SocketAddr,
+        initial_dcid: &[u8],
+        remote_cid: &[u8],
+        now: Instant,
+    ) -> Self {
+        let local_cid = new_connection_id();
+        let parameters = TransportParameters {
+            original_destination_connection_id: Some(initial_dcid.to_vec()),
+            max_idle_timeout: QUIC_IDLE_TIMEOUT.as_millis() as u64,
+            initial_max_data: (QUIC_TOTAL_STAKED_CONCURRENT_STREAMS * PACKET_DATA_SIZE) as u64,
+            initial_max_stream_data_uni: PACKET_DATA_SIZE as u64,
+            initial_max_streams_uni: QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS as u64,
+            initial_source_connection_id: local_cid.clone(),
+        };
+        let session = ServerSession::new_quic(config, parameters.encode());
+        Self::new(
+            Side::Server,
+            TlsSession::Server(session),
+            remote_addr,
+            local_cid,
+            remote_cid.to_vec(),
+            initial_dcid.to_vec(),
+            now,
+        )
+    }
+
+    fn new(
+        side: Side,
+        tls: TlsSession,
+        remote_addr: SocketAddr,
+        local_cid: Vec<u8>,
+        remote_cid: Vec<u8>,
+        initial_dcid: Vec<u8>,
+        now: Instant,
+    ) -> Self {
+        let mut spaces = [
+            PacketSpace::default(),
+            PacketSpace::default(),
+            PacketSpace::default(),
+        ];
+        let initial_salt = hkdf::Salt::new(hkdf::HKDF_SHA256, &INITIAL_SALT);
+        spaces[SpaceId::Initial.index()].keys = Some(Keys::initial(
+            &initial_salt,
+            &initial_dcid,
+            side == Side::Client,
+        ));
+        Self {
+            side,
+            tls,
+            state: State::Handshake,
+            error_code: None,
+            remote_addr,
+            local_cid,
+            remote_cid,
+            initial_dcid,
+            remote_cid_updated: side == Side::Server,
+            spaces,
+            tls_space: SpaceId::Initial,
+            peer_pubkey: None,
+            rtt: RttEstimator::default(),
+            pto_count: 0,
+            handshake_deadline: now + QUIC_HANDSHAKE_TIMEOUT,
+            idle_deadline: now + QUIC_IDLE_TIMEOUT,
+            peer_max_streams: 0,
+            next_stream: 0,
+            queued_streams: VecDeque::new(),
+            max_streams: QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS as u64,
+            max_concurrent_streams: QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS as u64,
+            max_streams_pending: false,
+            recv_streams: HashMap::new(),
+            completed_below: 0,
+            completed: BTreeSet::new(),
+            num_completed_streams: 0,
+            received_streams: vec![],
+        }
+    }
+
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// The node identity of the peer, known once the connection is established
+    pub fn peer_pubkey(&self) -> Option<Pubkey> {
+        self.peer_pubkey
+    }
+
+    pub fn is_established(&self) -> bool {
+        self.state == State::Established
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    /// The error code the connection was closed with, by either end. Connections that time
+    /// out have none
+    pub fn error_code(&self) -> Option<u64> {
+        self.error_code
+    }
+
+    pub fn close(&mut self) {
+        self.close_with_error(NO_ERROR);
+    }
+
+    fn close_with_error(&mut self, error_code: u64) {
+        if let State::Handshake | State::Established = self.state {
+            self.state = State::Closing(error_code);
+            self.error_code = Some(error_code);
+        }
+    }
+
+    /// Queues `data` to be sent on a new stream once the connection is established and the
+    /// server allows another stream
+    pub fn send_stream(&mut self, data: Vec<u8>) -> Result<(), QuicError> {
+        if data.len() > PACKET_DATA_SIZE {
+            return Err(QuicError::StreamTooLarge);
+        }
+        if self.queued_streams.len() >= MAX_QUEUED_STREAMS {
+            return Err(QuicError::TooManyQueuedStreams);
+        }
+        self.queued_streams.push_back(data);
+        Ok(())
+    }
+
+    /// Removes the data of the streams that have not been opened yet
+    pub fn take_queued_streams(&mut self) -> Vec<Vec<u8>> {
+        self.queued_streams.drain(..).collect()
+    }
+
+    /// Removes the data of the streams the peer has completed
+    pub fn take_received_streams(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.received_streams)
+    }
+
+    /// Sets how many streams the peer may have open at once
+    pub fn set_max_concurrent_streams(&mut self, max_concurrent_streams: u64) {
+        self.max_concurrent_streams = max_concurrent_streams;
+        self.update_max_streams();
+    }
+
+    pub fn handle_datagram(&mut self, datagram: &mut [u8], now: Instant) {
+        let mut offset = 0;
+        while offset < datagram.len() {
+            if let State::Closing(_) | State::Closed = self.state {
+                break;
+            }
+            match self.handle_packet(&mut datagram[offset..], now) {
+                Some(len) => offset += len,
+                None => break,
+            }
+        }
+    }
+
+    // Returns the length of the packet at the start of `buf`, if it can be parsed
+    fn handle_packet(&mut self, buf: &mut [u8], now: Instant) -> Option<usize> {
+        let header = PacketHeader::parse(buf)?;
+        let (space_id, long, len, packet_number_offset) = (
+            header.space,
+            header.long,
+            header.len,
+            header.packet_number_offset,
+        );
+        let scid = header.scid.to_vec();
+        // The server only reads application data once it knows who the client is
+        if space_id == SpaceId::Data && self.state != State::Established {
+            return Some(len);
+        }
+        let space = &self.spaces[space_id.index()];
+        let keys = match &space.keys {
+            Some(keys) => keys,
+            None => return Some(len),
+        };
+        let (packet_number, payload) = match open_packet(
+            &mut buf[..len],
+            packet_number_offset,
+            long,
+            &keys.remote,
+            space.largest_received,
+        ) {
+            Some(packet) => packet,
+            None => return Some(len),
+        };
+        if space.is_duplicate(packet_number) {
+            return Some(len);
+        }
+        self.idle_deadline = now + QUIC_IDLE_TIMEOUT;
+        if !self.remote_cid_updated {
+            self.remote_cid = scid;
+            self.remote_cid_updated = true;
+        }
+        if self.side == Side::Server && space_id == SpaceId::Handshake {
+            self.spaces[SpaceId::Initial.index()].discard();
+        }
+        match self.handle_frames(space_id, payload, now) {
+            Ok(ack_eliciting) => {
+                self.spaces[space_id.index()].on_packet_received(packet_number, ack_eliciting, now)
+            }
+            Err(error_code) => self.close_with_error(error_code),
+        }
+        Some(len)
+    }
+
+    // Returns whether the frames must be acknowledged
+    fn handle_frames(
+        &mut self,
+        space_id: SpaceId,
+        payload: &[u8],
+        now: Instant,
+    ) -> Result<bool, u64> {
+        let mut reader = Reader::new(payload);
+        let mut ack_eliciting = false;
+        while !reader.is_empty() {
+            let frame = Frame::decode(&mut reader).ok_or(FRAME_ENCODING_ERROR)?;
+            if space_id != SpaceId::Data && !frame.is_allowed_during_handshake() {
+                return Err(PROTOCOL_VIOLATION);
+            }
+            ack_eliciting |= frame.is_ack_eliciting();
+            match frame {
+                Frame::Padding | Frame::Ping | Frame::Ignored => {}
+                Frame::Ack(ranges) => self.on_ack(space_id, &ranges, now)?,
+                Frame::Crypto { offset, data } => self.on_crypto(space_id, offset, data)?,
+                Frame::Stream {
+                    id,
+                    offset,
+                    data,
+                    fin,
+                } => self.on_stream(id, offset, data, fin)?,
+                Frame::MaxStreamsUni(max_streams) => {
+                    self.peer_max_streams = cmp::max(self.peer_max_streams, max_streams)
+                }
+                Frame::ConnectionClose(error_code) => {
+                    self.state = State::Closed;
+                    self.error_code = Some(error_code);
+                    return Ok(false);
+                }
+                Frame::HandshakeDone => {
+                    if self.side == Side::Server {
+                        return Err(PROTOCOL_VIOLATION);
+                    }
+                    self.spaces[SpaceId::Handshake.index()].discard();
+                }
+            }
+        }
+        Ok(ack_eliciting)
+    }
+
+    fn on_ack(
+        &mut self,
+        space_id: SpaceId,
+        ranges: &[(u64, u64)],
+        now: Instant,
+    ) -> Result<(), u64> {
+        let space = &mut self.spaces[space_id.index()];
+        let largest_acked = ranges[0].1;
+        if largest_acked >= space.next_packet_number {
+            return Err(PROTOCOL_VIOLATION);
+        }
+        let mut rtt_sample = None;
+        let mut newly_acked = false;
+        for (start, end) in ranges {
+            let acked: Vec<u64> = space.sent.range(start..=end).map(|(pn, _)| *pn).collect();
+            for packet_number in acked {
+                let packet = space.sent.remove(&packet_number).unwrap();
+                if packet_number == largest_acked {
+                    rtt_sample = Some(now.saturating_duration_since(packet.time_sent));
+                }
+                newly_acked = true;
+            }
+        }
+        // Packets sent well before the largest acknowledged one are lost
+        let lost: Vec<u64> = match largest_acked.checked_sub(PACKET_THRESHOLD) {
+            Some(threshold) => space.sent.range(..=threshold).map(|(pn, _)| *pn).collect(),
+            None => vec![],
+        };
+        let lost: Vec<SentPacket> = lost
+            .into_iter()
+            .map(|packet_number| space.sent.remove(&packet_number).unwrap())
+            .collect();
+        if let Some(rtt_sample) = rtt_sample {
+            self.rtt.update(rtt_sample);
+        }
+        if newly_acked {
+            self.pto_count = 0;
+        }
+        for packet in lost {
+            self.on_packet_lost(space_id, packet);
+        }
+        Ok(())
+    }
+
+    fn on_packet_lost(&mut self, space_id: SpaceId, packet: SentPacket) {
+        for frame in packet.frames {
+            match frame {
+                SentFrame::Ping => {}
+                SentFrame::MaxStreamsUni => {
+                    if !self.max_streams_pending {
+                        self.max_streams_pending = true;
+                        self.spaces[space_id.index()].pending.push_back(frame);
+                    }
+                }
+                frame => self.spaces[space_id.index()].pending.push_back(frame),
+            }
+        }
+    }
+
+    fn on_crypto(&mut self, space_id: SpaceId, offset: u64, data: &[u8]) -> Result<(), u64> {
+        let space = &mut self.spaces[space_id.index()];
+        let end = offset + data.len() as u64;
+        if end <= space.crypto_read_offset {
+            return Ok(());
+        }
+        if end > space.crypto_read_offset + MAX_CRYPTO_BUFFER {
+            return Err(CRYPTO_BUFFER_EXCEEDED);
+        }
+        space.crypto_received.insert(offset, data.to_vec());
+        let mut readable = vec![];
+        while let Some(chunk_offset) = space.crypto_received.keys().next().copied() {
+            if chunk_offset > space.crypto_read_offset {
+                break;
+            }
+            let chunk = space.crypto_received.remove(&chunk_offset).unwrap();
+            let chunk_end = chunk_offset + chunk.len() as u64;
+            if chunk_end > space.crypto_read_offset {
+                readable.extend_from_slice(
+                    &chunk[(space.crypto_read_offset - chunk_offset) as usize..],
+                );
+                space.crypto_read_offset = chunk_end;
+            }
+        }
+        if readable.is_empty() {
+            return Ok(());
+        }
+        if let Err(err) = self.tls.read_hs(&readable) {
+            debug!("quic handshake with {} failed: {}", self.remote_addr, err);
+            return Err(CRYPTO_ERROR + self.tls.alert().unwrap_or(INTERNAL_ERROR_ALERT));
+        }
+        self.write_tls();
+        if self.state == State::Handshake && !self.tls.is_handshaking() {
+            self.on_handshake_complete()?;
+        }
+        Ok(())
+    }
+
+    fn write_tls(&mut self) {
+        loop {
+            let mut buf = vec![];
+            let keys = self.tls.write_hs(&mut buf);
+            self.spaces[self.tls_space.index()].queue_crypto(&buf);
+            match keys {
+                Some(keys) => {
+                    self.tls_space = self.tls_space.next();
+                    self.spaces[self.tls_space.index()].keys = Some(keys);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn on_handshake_complete(&mut self) -> Result<(), u64> {
+        self.peer_pubkey = Some(
+            self.tls
+                .peer_certificates()
+                .and_then(|certificates| get_pubkey_from_tls_certificate(certificates.first()?))
+                .ok_or(CRYPTO_ERROR + BAD_CERTIFICATE_ALERT)?,
+        );
+        let parameters = self
+            .tls
+            .transport_parameters()
+            .and_then(TransportParameters::decode)
+            .ok_or(TRANSPORT_PARAMETER_ERROR)?;
+        if parameters.initial_source_connection_id != self.remote_cid {
+            return Err(TRANSPORT_PARAMETER_ERROR);
+        }
+        match self.side {
+            Side::Client => {
+                if parameters.original_destination_connection_id.as_ref()
+                    != Some(&self.initial_dcid)
+                {
+                    return Err(TRANSPORT_PARAMETER_ERROR);
+                }
+                self.peer_max_streams = parameters.initial_max_streams_uni;
+            }
+            Side::Server => {
+                self.spaces[SpaceId::Data.index()]
+                    .pending
+                    .push_back(SentFrame::HandshakeDone);
+                self.spaces[SpaceId::Handshake.index()].discard();
+            }
+        }
+        self.state = State::Established;
+        Ok(())
+    }
+
+    fn on_stream(&mut self, id: u64, offset: u64, data: &[u8], fin: bool) -> Result<(), u64> {
+        // Only the client opens streams, and they are unidirectional
+        if self.side == Side::Client || id & 0x03 != 0x02 {
+            return Err(STREAM_STATE_ERROR);
+        }
+        let index = id >> 2;
+        if index >= self.max_streams {
+            return Err(STREAM_LIMIT_ERROR);
+        }
+        if index < self.completed_below || self.completed.contains(&index) {
+            return Ok(());
+        }
+        if offset + data.len() as u64 > PACKET_DATA_SIZE as u64 {
+            return Err(FLOW_CONTROL_ERROR);
+        }
+        let stream = self.recv_streams.entry(index).or_default();
+        if !stream.write(offset as usize, data, fin) {
+            return Err(FINAL_SIZE_ERROR);
+        }
+        if stream.is_complete() {
+            let stream = self.recv_streams.remove(&index).unwrap();
+            if !stream.data.is_empty() {
+                self.received_streams.push(stream.data);
+            }
+            self.completed.insert(index);
+            while self.completed.remove(&self.completed_below) {
+                self.completed_below += 1;
+            }
+            self.num_completed_streams += 1;
+            self.update_max_streams();
+        }
+        Ok(())
+    }
+
+    fn update_max_streams(&mut self) {
+        let max_streams = self.num_completed_streams + self.max_concurrent_streams;
+        if max_streams > self.max_streams {
+            self.max_streams = max_streams;
+            if !self.max_streams_pending {
+                self.max_streams_pending = true;
+                self.spaces[SpaceId::Data.index()]
+                    .pending
+                    .push_back(SentFrame::MaxStreamsUni);
+            }
+        }
+    }
+
+    fn open_streams(&mut self) {
+        while self.next_stream < self.peer_max_streams {
+            let data = match self.queued_streams.pop_front() {
+                Some(data) => data,
+                None => break,
+            };
+            let id = self.next_stream << 2 | 0x02;
+            self.next_stream += 1;
+            self.spaces[SpaceId::Data.index()]
+                .pending
+                .push_back(SentFrame::Stream { id, data });
+        }
+    }
+
+    /// Returns the next datagram to send to the peer
+    pub fn poll_transmit(&mut self, now: Instant) -> Option<Vec<u8>> {
+        match self.state {
+            State::Closed => return None,
+            State::Closing(error_code) => {
+                self.state = State::Closed;
+                return self.build_close(error_code);
+            }
+            State::Established if self.side == Side::Client => self.open_streams(),
+            State::Handshake | State::Established => {}
+        }
+        SPACES
+            .iter()
+            .find_map(|space_id| self.build_packet(*space_id, now))
+    }
+
+    /// Sends every pending datagram on `socket`
+    pub fn transmit(&mut self, socket: &UdpSocket, now: Instant) {
+        while let Some(datagram) = self.poll_transmit(now) {
+            if let Err(err) = socket.send_to(&datagram, self.remote_addr) {
+                debug!(
+                    "failed to send quic datagram to {}: {:?}",
+                    self.remote_addr, err
+                );
+                break;
+            }
+        }
+    }
+
+    fn build_packet(&mut self, space_id: SpaceId, now: Instant) -> Option<Vec<u8>> {
+        if space_id == SpaceId::Data && self.state != State::Established {
+            return None;
+        }
+        let budget =
+            QUIC_MAX_DATAGRAM_SIZE - self.header_len(space_id) - PACKET_NUMBER_LEN - TAG_LEN;
+        let max_streams = self.max_streams;
+        let space = &mut self.spaces[space_id.index()];
+        space.keys.as_ref()?;
+        let ack_due = space.ack_deadline.map_or(false, |deadline| {
+            space_id != SpaceId::Data
+                || now >= deadline
+                || space.ack_eliciting_since_ack >= ACK_ELICITING_THRESHOLD
+        });
+        let can_send = space_id != SpaceId::Data || space.sent.len() < MAX_PACKETS_IN_FLIGHT;
+        if !ack_due && !space.probe && (space.pending.is_empty() || !can_send) {
+            return None;
+        }
+
+        let mut payload = vec![];
+        if space.ack_deadline.is_some() {
+            space.encode_ack(&mut payload);
+            space.ack_deadline = None;
+            space.ack_eliciting_since_ack = 0;
+        }
+        let mut frames = vec![];
+        while let Some(frame) = space.pending.front().filter(|_| can_send) {
+            let mut encoded = vec![];
+            frame.encode(&mut encoded, max_streams);
+            if payload.len() + encoded.len() > budget {
+                break;
+            }
+            payload.extend_from_slice(&encoded);
+            frames.push(space.pending.pop_front().unwrap());
+        }
+        if space.probe && frames.is_empty() {
+            payload.push(PING_FRAME);
+            frames.push(SentFrame::Ping);
+        }
+        space.probe = false;
+        if frames
+            .iter()
+            .any(|frame| matches!(frame, SentFrame::MaxStreamsUni))
+        {
+            self.max_streams_pending = false;
+        }
+        if !frames.is_empty() {
+            let packet_number = self.spaces[space_id.index()].next_packet_number;
+            self.spaces[space_id.index()].sent.insert(
+                packet_number,
+                SentPacket {
+                    time_sent: now,
+                    frames,
+                },
+            );
+        }
+        let datagram = self.seal(space_id, payload);
+        if self.side == Side::Client && space_id == SpaceId::Handshake {
+            self.spaces[SpaceId::Initial.index()].discard();
+        }
+        Some(datagram)
+    }
+
+    fn build_close(&mut self, error_code: u64) -> Option<Vec<u8>> {
+        let space_id = *SPACES.iter().rev().find(|space_id| {
+            self.spaces[space_id.index()].keys.is_some()
+                && (**space_id != SpaceId::Data || self.tls_space == SpaceId::Data)
+        })?;
+        let mut payload = vec![CONNECTION_CLOSE_FRAME];
+        write_varint(&mut payload, error_code);
+        write_varint(&mut payload, 0);
+        write_varint(&mut payload, 0);
+        Some(self.seal(space_id, payload))
+    }
+
+    fn header_len(&self, space_id: SpaceId) -> usize {
+        match space_id {
+            // First byte, version, connection ids and lengths, token length for Initial, Length
+            SpaceId::Initial => {
+                1 + 4 + 1 + self.remote_cid.len() + 1 + self.local_cid.len() + 1 + 2
+            }
+            SpaceId::Handshake => 1 + 4 + 1 + self.remote_cid.len() + 1 + self.local_cid.len() + 2,
+            SpaceId::Data => 1 + self.remote_cid.len(),
+        }
+    }
+
+    fn seal(&mut self, space_id: SpaceId, mut payload: Vec<u8>) -> Vec<u8> {
+        let mut header = vec![];
+        match space_id {
+            SpaceId::Initial | SpaceId::Handshake => {
+                let packet_type = if space_id == SpaceId::Initial { 0 } else { 2 };
+                header.push(0xc0 | packet_type << 4 | (PACKET_NUMBER_LEN as u8 - 1));
+                header.extend_from_slice(&QUIC_VERSION.to_be_bytes());
+                header.push(self.remote_cid.len() as u8);
+                header.extend_from_slice(&self.remote_cid);
+                header.push(self.local_cid.len() as u8);
+                header.extend_from_slice(&self.local_cid);
+                if space_id == SpaceId::Initial {
+                    header.push(0);
+                }
+                header.extend_from_slice(&[0, 0]);
+            }
+            SpaceId::Data => {
+                header.push(0x40 | (PACKET_NUMBER_LEN as u8 - 1));
+                header.extend_from_slice(&self.remote_cid);
+            }
+        }
+        // Datagrams carrying a client's Initial packet are padded to make amplification
+        // attacks pointless
+        if self.side == Side::Client && space_id == SpaceId::Initial {
+            let min_payload_len =
+                MIN_INITIAL_DATAGRAM_SIZE - header.len() - PACKET_NUMBER_LEN - TAG_LEN;
+            if payload.len() < min_payload_len {
+                payload.resize(min_payload_len, PADDING_FRAME);
+            }
+        }
+        let space = &mut self.spaces[space_id.index()];
+        let packet_number = space.next_packet_number;
+        space.next_packet_number += 1;
+        let keys = space.keys.as_ref().unwrap();
+        seal_packet(
+            header,
+            space_id != SpaceId::Data,
+            packet_number,
+            payload,
+            &keys.local,
+        )
+    }
+
+    fn probe_timeout(&self, space_id: SpaceId) -> Option<Instant> {
+        let (_, packet) = self.spaces[space_id.index()].sent.iter().next_back()?;
+        let mut duration = self.rtt.probe_timeout();
+        if space_id == SpaceId::Data {
+            duration += MAX_ACK_DELAY;
+        }
+        Some(packet.time_sent + duration * 2u32.pow(cmp::min(self.pto_count, MAX_PTO_BACKOFF)))
+    }
+
+    /// Returns when `handle_timeout` must be called next
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        if self.state == State::Closed {
+            return None;
+        }
+        let mut timeout = self.idle_deadline;
+        if self.state == State::Handshake {
+            timeout = cmp::min(timeout, self.handshake_deadline);
+        }
+        for space_id in SPACES.iter() {
+            if let Some(probe_timeout) = self.probe_timeout(*space_id) {
+                timeout = cmp::min(timeout, probe_timeout);
+            }
+        }
+        if let Some(ack_deadline) = self.spaces[SpaceId::Data.index()].ack_deadline {
+            timeout = cmp::min(timeout, ack_deadline);
+        }
+        Some(timeout)
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        if self.state == State::Closed {
+            return;
+        }
+        if now >= self.idle_deadline
+            || (self.state == State::Handshake && now >= self.handshake_deadline)
+        {
+            debug!("quic connection to {} timed out", self.remote_addr);
+            self.state = State::Closed;
+            return;
+        }
+        let expired_space = SPACES
+            .iter()
+            .filter_map(|space_id| Some((self.probe_timeout(*space_id)?, *space_id)))
+            .min_by_key(|(probe_timeout, _)| *probe_timeout)
+            .filter(|(probe_timeout, _)| now >= *probe_timeout);
+        // Resend everything that is not acknowledged, or a PING if that is only PINGs
+        if let Some((_, space_id)) = expired_space {
+            self.pto_count += 1;
+            let space = &mut self.spaces[space_id.index()];
+            space.probe = true;
+            let packets = std::mem::take(&mut space.sent);
+            for packet in packets.into_iter().map(|(_, packet)| packet) {
+                self.on_packet_lost(space_id, packet);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct QuicServerStats {
+    new_connections: usize,
+    refused_connections: usize,
+    closed_connections: usize,
+    datagrams: usize,
+    transactions: usize,
+}
+
+impl QuicServerStats {
+    fn report(&mut self, connections: usize) {
+        datapoint_info!(
+            "quic-server",
+            ("connections", connections, i64),
+            ("new_connections", self.new_connections, i64),
+            ("refused_connections", self.refused_connections, i64),
+            ("closed_connections", self.closed_connections, i64),
+            ("datagrams", self.datagrams, i64),
+            ("transactions", self.transactions, i64),
+        );
+        *self = Self::default();
+    }
+}
+
+struct QuicServer {
+    socket: UdpSocket,
+    config: Arc<ServerConfig>,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+    packet_sender: PacketSender,
+    // Connections by local connection id, and the ids of connections still addressed by the
+    // connection id the client picked
+    connections: HashMap<Vec<u8>, Connection>,
+    initial_cids: HashMap<(SocketAddr, Vec<u8>), Vec<u8>>,
+    packets: Packets,
+    next_timeout: Instant,
+    stats: QuicServerStats,
+    last_report: Instant,
+}
+
+impl QuicServer {
+    fn run_once(&mut self) -> Result<(), SendError<Packets>> {
+        let mut buf = [0u8; 2048];
+        let timeout = self
+            .next_timeout
+            .saturating_duration_since(Instant::now())
+            .max(TIMER_GRANULARITY)
+            .min(MAX_RECV_TIMEOUT);
+        let _ = self.socket.set_read_timeout(Some(timeout));
+        match self.socket.recv_from(&mut buf) {
+            Ok((len, from)) => self.handle_datagram(&mut buf[..len], from),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {}
+            Err(err) => warn!("quic server receive error: {:?}", err),
+        }
+        let now = Instant::now();
+        if now >= self.next_timeout {
+            self.handle_timeouts(now);
+        }
+        if !self.packets.packets.is_empty() {
+            self.packet_sender.send(std::mem::take(&mut self.packets))?;
+        }
+        if self.last_report.elapsed() > Duration::from_secs(2) {
+            self.stats.report(self.connections.len());
+            self.last_report = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn handle_datagram(&mut self, datagram: &mut [u8], from: SocketAddr) {
+        self.stats.datagrams += 1;
+        let now = Instant::now();
+        let (space, dcid, scid) = match PacketHeader::parse(datagram) {
+            Some(header) => (header.space, header.dcid.to_vec(), header.scid.to_vec()),
+            None => return,
+        };
+        let local_cid = if self.connections.contains_key(&dcid) {
+            dcid
+        } else if let Some(local_cid) = self.initial_cids.get(&(from, dcid.clone())) {
+            local_cid.clone()
+        } else {
+            if space != SpaceId::Initial
+                || datagram.len() < MIN_INITIAL_DATAGRAM_SIZE
+                || dcid.len() < CONNECTION_ID_LEN
+            {
+                return;
+            }
+            if self.connections.len() >= QUIC_MAX_CONNECTIONS {
+                self.stats.refused_connections += 1;
+                return;
+            }
+            let connection = Connection::accept(&self.config, from, &dcid, &scid, now);
+            let local_cid = connection.local_cid.clone();
+            self.initial_cids.insert((from, dcid), local_cid.clone());
+            self.connections.insert(local_cid.clone(), connection);
+            self.stats.new_connections += 1;
+            local_cid
+        };
+        let connection = self.connections.get_mut(&local_cid).unwrap();
+        if connection.remote_addr != from {
+            return;
+        }
+        let was_established = connection.is_established();
+        connection.handle_datagram(datagram, now);
+        if !was_established && connection.is_established() {
+            let staked_nodes = self.staked_nodes.read().unwrap();
+            let peer_stake = connection
+                .peer_pubkey
+                .and_then(|pubkey| staked_nodes.stakes.get(&pubkey).copied())
+                .unwrap_or_default();
+            connection.set_max_concurrent_streams(compute_max_allowed_uni_streams(
+                peer_stake,
+                staked_nodes.total_stake,
+            ) as u64);
+        }
+        connection.transmit(&self.socket, now);
+        for data in connection.take_received_streams() {
+            let mut packet = Packet::default();
+            packet.data[..data.len()].copy_from_slice(&data);
+            packet.meta.size = data.len();
+            packet.meta.set_addr(&from);
+            self.packets.packets.push(packet);
+            self.stats.transactions += 1;
+        }
+        if let Some(timeout) = connection.poll_timeout() {
+            self.next_timeout = cmp::min(self.next_timeout, timeout);
+        }
+        if self.packets.packets.len() >= NUM_PACKETS {
+            let _ = self.packet_sender.send(std::mem::take(&mut self.packets));
+        }
+    }
+
+    fn handle_timeouts(&mut self, now: Instant) {
+        let mut next_timeout = now + QUIC_IDLE_TIMEOUT;
+        for connection in self.connections.values_mut() {
+            if connection
+                .poll_timeout()
+                .map_or(false, |timeout| now >= timeout)
+            {
+                connection.handle_timeout(now);
+            }
+            connection.transmit(&self.socket, now);
+            if let Some(timeout) = connection.poll_timeout() {
+                next_timeout = cmp::min(next_timeout, timeout);
+            }
+        }
+        let initial_cids = &mut self.initial_cids;
+        let stats = &mut self.stats;
+        self.connections.retain(|_, connection| {
+            if connection.is_closed() {
+                initial_cids.remove(&(connection.remote_addr, connection.initial_dcid.clone()));
+                stats.closed_connections += 1;
+                false
+            } else {
+                true
+            }
+        });
+        self.next_timeout = next_timeout;
+    }
+}
+
+/// Spawns a thread that receives transactions over QUIC on `sock` and sends them to
+/// `packet_sender` the same way the UDP fetch stage does
+pub fn spawn_server(
+    sock: UdpSocket,
+    keypair: &Keypair,
+    packet_sender: PacketSender,
+    exit: Arc<AtomicBool>,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+) -> Result<JoinHandle<()>, QuicError> {
+    let config = new_server_config(keypair)?;
+    let thread = Builder::new()
+        .name("solana-quic-server".to_string())
+        .spawn(move || {
+            let now = Instant::now();
+            let mut server = QuicServer {
+                socket: sock,
+                config,
+                staked_nodes,
+                packet_sender,
+                connections: HashMap::new(),
+                initial_cids: HashMap::new(),
+                packets: Packets::default(),
+                next_timeout: now,
+                stats: QuicServerStats::default(),
+                last_report: now,
+            };
+            while !exit.load(Ordering::Relaxed) {
+                if server.run_once().is_err() {
+                    break;
+                }
+            }
+        })?;
+    Ok(thread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streamer::PacketReceiver;
+    use std::sync::mpsc::channel;
+
+    fn run_client(
+        socket: &UdpSocket,
+        connection: &mut Connection,
+        until: impl Fn(&Connection) -> bool,
+    ) {
+        let start = Instant::now();
+        let mut buf = [0u8; 2048];
+        socket
+            .set_read_timeout(Some(Duration::from_millis(1)))
+            .unwrap();
+        while !until(connection) && start.elapsed() < Duration::from_secs(10) {
+            let now = Instant::now();
+            if connection
+                .poll_timeout()
+                .map_or(false, |timeout| now >= timeout)
+            {
+                connection.handle_timeout(now);
+            }
+            connection.transmit(socket, now);
+            if let Ok((len, _)) = socket.recv_from(&mut buf) {
+                connection.handle_datagram(&mut buf[..len], Instant::now());
+            }
+        }
+    }
+
+    fn setup_server(
+        staked_nodes: StakedNodes,
+    ) -> (
+        Keypair,
+        SocketAddr,
+        PacketReceiver,
+        Arc<AtomicBool>,
+        JoinHandle<()>,
+    ) {
+        let keypair = Keypair::new();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = socket.local_addr().unwrap();
+        let (sender, receiver) = channel();
+        let exit = Arc::new(AtomicBool::new(false));
+        let thread = spawn_server(
+            socket,
+            &keypair,
+            sender,
+            exit.clone(),
+            Arc::new(RwLock::new(staked_nodes)),
+        )
+        .unwrap();
+        (keypair, server_addr, receiver, exit, thread)
+    }
+
+    #[test]
+    fn test_tls_certificate() {
+        let keypair = Keypair::new();
+        let (certificate, key) = new_self_signed_tls_certificate(&keypair);
+        assert_eq!(
+            get_pubkey_from_tls_certificate(&certificate),
+            Some(keypair.pubkey())
+        );
+        assert!(rustls::sign::any_eddsa_type(&key).is_ok());
+
+        // The public key must be the one in the SubjectPublicKeyInfo
+        let mut certificate = certificate;
+        let other = Keypair::new();
+        let position = certificate
+            .0
+            .windows(32)
+            .position(|window| window == keypair.pubkey().as_ref())
+            .unwrap();
+        certificate.0[position..position + 32].copy_from_slice(other.pubkey().as_ref());
+        assert_eq!(
+            get_pubkey_from_tls_certificate(&certificate),
+            Some(other.pubkey())
+        );
+        assert_eq!(
+            get_pubkey_from_tls_certificate(&Certificate(vec![0x30, 0x00])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in &[0, 63, 64, 16_383, 16_384, 1 << 30, (1 << 62) - 1] {
+            let mut buf = vec![];
+            write_varint(&mut buf, *value);
+            let mut reader = Reader::new(&buf);
+            assert_eq!(reader.varint(), Some(*value));
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_decode_packet_number() {
+        // RFC 9000 appendix A.3
+        assert_eq!(
+            decode_packet_number(Some(0xa82f30ea), 0x9b32, 2),
+            0xa82f9b32
+        );
+        assert_eq!(decode_packet_number(None, 0, 4), 0);
+        assert_eq!(decode_packet_number(Some(0xff), 0x00, 1), 0x100);
+    }
+
+    #[test]
+    fn test_transport_parameters() {
+        let parameters = TransportParameters {
+            original_destination_connection_id: Some(vec![1; 8]),
+            max_idle_timeout: 10_000,
+            initial_max_data: 1 << 20,
+            initial_max_stream_data_uni: PACKET_DATA_SIZE as u64,
+            initial_max_streams_uni: 128,
+            initial_source_connection_id: vec![2; 8],
+        };
+        assert_eq!(
+            TransportParameters::decode(&parameters.encode()),
+            Some(parameters)
+        );
+        assert_eq!(TransportParameters::decode(&[]), None);
+    }
+
+    #[test]
+    fn test_ack_ranges() {
+        let mut space = PacketSpace::default();
+        let now = Instant::now();
+        for packet_number in &[0, 1, 2, 5, 7, 6, 10] {
+            space.on_packet_received(*packet_number, true, now);
+        }
+        assert_eq!(
+            space.received.iter().copied().collect::<Vec<_>>(),
+            vec![(0, 2), (5, 7), (10, 10)]
+        );
+        assert!(space.is_duplicate(6));
+        assert!(!space.is_duplicate(8));
+        let mut buf = vec![];
+        space.encode_ack(&mut buf);
+        match Frame::decode(&mut Reader::new(&buf)) {
+            Some(Frame::Ack(ranges)) => assert_eq!(ranges, vec![(10, 10), (5, 7), (0, 2)]),
+            _ => panic!("expected an ACK frame"),
+        }
+    }
+
+    #[test]
+    fn test_compute_max_allowed_uni_streams() {
+        assert_eq!(
+            compute_max_allowed_uni_streams(0, 0),
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(0, 1_000),
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(1, 1_000_000),
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(500, 1_000),
+            QUIC_TOTAL_STAKED_CONCURRENT_STREAMS / 2
+        );
+    }
+
+    #[test]
+    fn test_quic_server_transactions() {
+        solana_logger::setup();
+        let (server_keypair, server_addr, receiver, exit, thread) =
+            setup_server(StakedNodes::default());
+        let client_keypair = Keypair::new();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = new_client_config(&client_keypair).unwrap();
+        let mut connection = Connection::connect(&config, server_addr, Instant::now());
+        run_client(&socket, &mut connection, Connection::is_established);
+        assert!(connection.is_established());
+        assert_eq!(connection.peer_pubkey(), Some(server_keypair.pubkey()));
+        assert_eq!(
+            connection.peer_max_streams,
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS as u64
+        );
+
+        // More transactions than the stream limit, so the server has to raise it
+        let num_transactions = 2 * QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS;
+        for i in 0..num_transactions {
+            connection
+                .send_stream(vec![i as u8; PACKET_DATA_SIZE - i % 7])
+                .unwrap();
+        }
+        assert!(connection
+            .send_stream(vec![0; PACKET_DATA_SIZE + 1])
+            .is_err());
+        let mut received = vec![];
+        let start = Instant::now();
+        while received.len() < num_transactions && start.elapsed() < Duration::from_secs(10) {
+            run_client(&socket, &mut connection, |_| true);
+            run_client(&socket, &mut connection, |connection| {
+                connection.spaces[SpaceId::Data.index()].sent.is_empty()
+                    && connection.queued_streams.is_empty()
+            });
+            while let Ok(packets) = receiver.try_recv() {
+                received.extend(packets.packets.iter().cloned());
+            }
+        }
+        assert_eq!(received.len(), num_transactions);
+        received.sort_by_key(|packet| packet.data[0]);
+        for (i, packet) in received.iter().enumerate() {
+            assert_eq!(packet.meta.size, PACKET_DATA_SIZE - i % 7);
+            assert!(packet.data[..packet.meta.size]
+                .iter()
+                .all(|byte| *byte == i as u8));
+            assert_eq!(packet.meta.addr(), socket.local_addr().unwrap());
+        }
+        assert!(connection.peer_max_streams >= num_transactions as u64);
+
+        connection.close();
+        connection.transmit(&socket, Instant::now());
+        assert!(connection.is_closed());
+        exit.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_quic_server_staked_stream_limit() {
+        solana_logger::setup();
+        let client_keypair = Keypair::new();
+        let mut stakes = HashMap::new();
+        stakes.insert(client_keypair.pubkey(), 1_000);
+        stakes.insert(Pubkey::new_unique(), 3_000);
+        let (_server_keypair, server_addr, _receiver, exit, thread) =
+            setup_server(StakedNodes::new(stakes));
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = new_client_config(&client_keypair).unwrap();
+        let mut connection = Connection::connect(&config, server_addr, Instant::now());
+        let max_streams = (QUIC_TOTAL_STAKED_CONCURRENT_STREAMS / 4) as u64;
+        run_client(&socket, &mut connection, |connection| {
+            connection.peer_max_streams == max_streams
+        });
+        assert_eq!(connection.peer_max_streams, max_streams);
+
+        // Opening streams beyond the limit closes the connection
+        connection.peer_max_streams = max_streams + 1;
+        connection.next_stream = max_streams;
+        connection.send_stream(vec![1]).unwrap();
+        run_client(&socket, &mut connection, Connection::is_closed);
+        assert_eq!(connection.error_code(), Some(STREAM_LIMIT_ERROR));
+
+        exit.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+}
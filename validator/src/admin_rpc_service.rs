@@ -0,0 +1,541 @@
+//! The `admin_rpc_service` module serves a JSON RPC interface over a Unix socket in the ledger
+//! directory, which lets the local operator inspect and control a running validator.
+use jsonrpc_core::{Error, MetaIoHandler, Metadata, Result};
+use jsonrpc_derive::rpc;
+use log::*;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_core::{
+    cluster_info::ClusterInfo,
+    consensus::Tower,
+    contact_info::ContactInfo,
+    tower_storage::TowerStorage,
+    validator::{ValidatorExit, ValidatorStartProgress},
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use std::{
+    fmt, fs,
+    io::{BufRead, BufReader, Write},
+    net::SocketAddr,
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread::{self, Builder},
+    time::{Duration, SystemTime},
+};
+
+/// State only available once the validator has finished starting up
+pub struct AdminRpcRequestMetadataPostInit {
+    pub cluster_info: Arc<ClusterInfo>,
+    pub validator_exit: Arc<RwLock<Option<ValidatorExit>>>,
+    pub tower_storage: Arc<dyn TowerStorage>,
+}
+
+#[derive(Clone)]
+pub struct AdminRpcRequestMetadata {
+    pub rpc_addr: Option<SocketAddr>,
+    pub start_time: SystemTime,
+    pub start_progress: Arc<RwLock<ValidatorStartProgress>>,
+    pub authorized_voter_keypairs: Arc<RwLock<Vec<Arc<Keypair>>>>,
+    pub post_init: Arc<RwLock<Option<AdminRpcRequestMetadataPostInit>>>,
+}
+impl Metadata for AdminRpcRequestMetadata {}
+
+impl AdminRpcRequestMetadata {
+    fn with_post_init<F, R>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce(&AdminRpcRequestMetadataPostInit) -> Result<R>,
+    {
+        match self.post_init.read().unwrap().as_ref() {
+            Some(post_init) => func(post_init),
+            None => Err(Error::invalid_params("validator is still starting up")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcContactInfo {
+    pub id: String,
+    pub gossip: SocketAddr,
+    pub tvu: SocketAddr,
+    pub tvu_forwards: SocketAddr,
+    pub repair: SocketAddr,
+    pub tpu: SocketAddr,
+    pub tpu_forwards: SocketAddr,
+    pub rpc: SocketAddr,
+    pub rpc_pubsub: SocketAddr,
+    pub serve_repair: SocketAddr,
+    pub last_updated_timestamp: u64,
+    pub shred_version: u16,
+}
+
+impl From<ContactInfo> for AdminRpcContactInfo {
+    fn from(contact_info: ContactInfo) -> Self {
+        Self {
+            id: contact_info.id.to_string(),
+            gossip: contact_info.gossip,
+            tvu: contact_info.tvu,
+            tvu_forwards: contact_info.tvu_forwards,
+            repair: contact_info.repair,
+            tpu: contact_info.tpu,
+            tpu_forwards: contact_info.tpu_forwards,
+            rpc: contact_info.rpc,
+            rpc_pubsub: contact_info.rpc_pubsub,
+            serve_repair: contact_info.serve_repair,
+            last_updated_timestamp: contact_info.wallclock,
+            shred_version: contact_info.shred_version,
+        }
+    }
+}
+
+impl fmt::Display for AdminRpcContactInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Identity: {}", self.id)?;
+        writeln!(f, "Gossip: {}", self.gossip)?;
+        writeln!(f, "TVU: {}", self.tvu)?;
+        writeln!(f, "TVU Forwards: {}", self.tvu_forwards)?;
+        writeln!(f, "Repair: {}", self.repair)?;
+        writeln!(f, "TPU: {}", self.tpu)?;
+        writeln!(f, "TPU Forwards: {}", self.tpu_forwards)?;
+        writeln!(f, "RPC: {}", self.rpc)?;
+        writeln!(f, "RPC Pubsub: {}", self.rpc_pubsub)?;
+        writeln!(f, "Serve Repair: {}", self.serve_repair)?;
+        writeln!(f, "Last Updated Timestamp: {}", self.last_updated_timestamp)?;
+        writeln!(f, "Shred Version: {}", self.shred_version)
+    }
+}
+
+#[rpc(server)]
+pub trait AdminRpc {
+    type Metadata;
+
+    #[rpc(meta, name = "exit")]
+    fn exit(&self, meta: Self::Metadata) -> Result<()>;
+
+    #[rpc(meta, name = "rpcAddr")]
+    fn rpc_addr(&self, meta: Self::Metadata) -> Result<Option<SocketAddr>>;
+
+    #[rpc(name = "setLogFilter")]
+    fn set_log_filter(&self, filter: String) -> Result<()>;
+
+    #[rpc(meta, name = "startTime")]
+    fn start_time(&self, meta: Self::Metadata) -> Result<SystemTime>;
+
+    #[rpc(meta, name = "startProgress")]
+    fn start_progress(&self, meta: Self::Metadata) -> Result<ValidatorStartProgress>;
+
+    #[rpc(meta, name = "addAuthorizedVoter")]
+    fn add_authorized_voter(&self, meta: Self::Metadata, keypair_file: String) -> Result<()>;
+
+    #[rpc(meta, name = "removeAuthorizedVoter")]
+    fn remove_authorized_voter(&self, meta: Self::Metadata, pubkey: String) -> Result<()>;
+
+    #[rpc(meta, name = "removeAllAuthorizedVoters")]
+    fn remove_all_authorized_voters(&self, meta: Self::Metadata) -> Result<()>;
+
+    #[rpc(meta, name = "setIdentity")]
+    fn set_identity(&self, meta: Self::Metadata, keypair_file: String) -> Result<()>;
+
+    #[rpc(meta, name = "contactInfo")]
+    fn contact_info(&self, meta: Self::Metadata) -> Result<AdminRpcContactInfo>;
+}
+
+pub struct AdminRpcImpl;
+impl AdminRpc for AdminRpcImpl {
+    type Metadata = AdminRpcRequestMetadata;
+
+    fn exit(&self, meta: Self::Metadata) -> Result<()> {
+        debug!("exit admin rpc request received");
+        // Delay the exit so the response to this request makes it back to the caller before the
+        // validator starts shutting down
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            warn!("validator exit requested");
+            let validator_exit = meta
+                .post_init
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(|post_init| post_init.validator_exit.clone());
+            match validator_exit {
+                Some(validator_exit) => {
+                    if let Some(x) = validator_exit.write().unwrap().take() {
+                        x.exit()
+                    }
+                }
+                None => {
+                    warn!("validator is still starting up, exiting the process");
+                    std::process::exit(0);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn rpc_addr(&self, meta: Self::Metadata) -> Result<Option<SocketAddr>> {
+        debug!("rpc_addr admin rpc request received");
+        Ok(meta.rpc_addr)
+    }
+
+    fn set_log_filter(&self, filter: String) -> Result<()> {
+        debug!("set_log_filter admin rpc request received");
+        solana_logger::setup_with(&filter);
+        Ok(())
+    }
+
+    fn start_time(&self, meta: Self::Metadata) -> Result<SystemTime> {
+        debug!("start_time admin rpc request received");
+        Ok(meta.start_time)
+    }
+
+    fn start_progress(&self, meta: Self::Metadata) -> Result<ValidatorStartProgress> {
+        debug!("start_progress admin rpc request received");
+        Ok(*meta.start_progress.read().unwrap())
+    }
+
+    fn add_authorized_voter(&self, meta: Self::Metadata, keypair_file: String) -> Result<()> {
+        debug!("add_authorized_voter admin rpc request received");
+        let authorized_voter = read_keypair_file(&keypair_file)
+            .map_err(|err| Error::invalid_params(format!("{}: {}", keypair_file, err)))?;
+
+        let mut authorized_voter_keypairs = meta.authorized_voter_keypairs.write().unwrap();
+        if authorized_voter_keypairs
+            .iter()
+            .any(|x| x.pubkey() == authorized_voter.pubkey())
+        {
+            return Err(Error::invalid_params(format!(
+                "Authorized voter {} already present",
+                authorized_voter.pubkey()
+            )));
+        }
+        warn!("authorized voter added: {}", authorized_voter.pubkey());
+        authorized_voter_keypairs.push(Arc::new(authorized_voter));
+        Ok(())
+    }
+
+    fn remove_authorized_voter(&self, meta: Self::Metadata, pubkey: String) -> Result<()> {
+        debug!("remove_authorized_voter admin rpc request received");
+        let pubkey = pubkey
+            .parse::<Pubkey>()
+            .map_err(|err| Error::invalid_params(format!("{}: {}", pubkey, err)))?;
+
+        let mut authorized_voter_keypairs = meta.authorized_voter_keypairs.write().unwrap();
+        let num_authorized_voters = authorized_voter_keypairs.len();
+        authorized_voter_keypairs.retain(|x| x.pubkey() != pubkey);
+        if authorized_voter_keypairs.len() == num_authorized_voters {
+            return Err(Error::invalid_params(format!(
+                "Authorized voter {} not present",
+                pubkey
+            )));
+        }
+        warn!("authorized voter removed: {}", pubkey);
+        Ok(())
+    }
+
+    fn remove_all_authorized_voters(&self, meta: Self::Metadata) -> Result<()> {
+        debug!("remove_all_authorized_voters admin rpc request received");
+        warn!("all authorized voters removed");
+        meta.authorized_voter_keypairs.write().unwrap().clear();
+        Ok(())
+    }
+
+    fn set_identity(&self, meta: Self::Metadata, keypair_file: String) -> Result<()> {
+        debug!("set_identity admin rpc request received");
+        let identity_keypair = read_keypair_file(&keypair_file)
+            .map_err(|err| Error::invalid_params(format!("{}: {}", keypair_file, err)))?;
+
+        meta.with_post_init(|post_init| {
+            // Refuse to switch unless the new identity already has a usable tower, so the
+            // validator never votes under the new identity without its lockouts
+            Tower::restore(post_init.tower_storage.as_ref(), &identity_keypair.pubkey()).map_err(
+                |err| {
+                    Error::invalid_params(format!(
+                        "Unable to load tower for identity {}: {}",
+                        identity_keypair.pubkey(),
+                        err
+                    ))
+                },
+            )?;
+
+            solana_metrics::set_host_id(identity_keypair.pubkey().to_string());
+            post_init
+                .cluster_info
+                .set_keypair(Arc::new(identity_keypair));
+            warn!("identity set to {}", post_init.cluster_info.id());
+            Ok(())
+        })
+    }
+
+    fn contact_info(&self, meta: Self::Metadata) -> Result<AdminRpcContactInfo> {
+        debug!("contact_info admin rpc request received");
+        meta.with_post_init(|post_init| Ok(post_init.cluster_info.my_contact_info().into()))
+    }
+}
+
+fn admin_rpc_path(ledger_path: &Path) -> PathBuf {
+    ledger_path.join("admin.rpc")
+}
+
+/// Start the admin RPC service, listening on a Unix socket in the ledger directory
+pub fn run(ledger_path: &Path, metadata: AdminRpcRequestMetadata) {
+    let admin_rpc_path = admin_rpc_path(ledger_path);
+    // A socket left behind by a previous run would make the bind fail
+    let _ = fs::remove_file(&admin_rpc_path);
+    let listener = match UnixListener::bind(&admin_rpc_path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!(
+                "Unable to start admin rpc service on {:?}: {}",
+                admin_rpc_path, err
+            );
+            return;
+        }
+    };
+
+    let mut io = MetaIoHandler::default();
+    io.extend_with(AdminRpcImpl.to_delegate());
+    let io = Arc::new(io);
+
+    Builder::new()
+        .name("solana-adminrpc".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let io = io.clone();
+                        let metadata = metadata.clone();
+                        Builder::new()
+                            .name("solana-adminrpc-conn".to_string())
+                            .spawn(move || handle_connection(stream, &io, metadata))
+                            .unwrap();
+                    }
+                    Err(err) => warn!("admin rpc connection failed: {}", err),
+                }
+            }
+        })
+        .unwrap();
+}
+
+// Requests and responses are exchanged as one JSON document per line
+fn handle_connection(
+    stream: UnixStream,
+    io: &MetaIoHandler<AdminRpcRequestMetadata>,
+    metadata: AdminRpcRequestMetadata,
+) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(err) => {
+            warn!("admin rpc connection failed: {}", err);
+            return;
+        }
+    };
+    for request in BufReader::new(stream).lines() {
+        let request = match request {
+            Ok(request) => request,
+            Err(_) => break,
+        };
+        if let Some(response) = io.handle_request_sync(&request, metadata.clone()) {
+            if writeln!(writer, "{}", response).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Send a request to the admin RPC service of the validator using `ledger_path`
+pub fn request<T: DeserializeOwned>(
+    ledger_path: &Path,
+    method: &str,
+    params: Value,
+) -> std::result::Result<T, String> {
+    let admin_rpc_path = admin_rpc_path(ledger_path);
+    let stream = UnixStream::connect(&admin_rpc_path)
+        .map_err(|err| format!("Unable to connect to {:?}: {}", admin_rpc_path, err))?;
+
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+    writeln!(&stream, "{}", request).map_err(|err| format!("Failed to send request: {}", err))?;
+
+    let mut response = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut response)
+        .map_err(|err| format!("Failed to read response: {}", err))?;
+    let mut response: Value = serde_json::from_str(&response)
+        .map_err(|err| format!("Invalid response {:?}: {}", response, err))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(error["message"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| error.to_string()));
+    }
+    serde_json::from_value(response["result"].take())
+        .map_err(|err| format!("Invalid result for {}: {}", method, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_core::tower_storage::FileTowerStorage;
+    use solana_ledger::{
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        get_tmp_ledger_path,
+    };
+    use solana_runtime::bank::Bank;
+    use solana_sdk::{pubkey::Pubkey, signature::write_keypair_file, timing::timestamp};
+
+    #[test]
+    fn test_admin_rpc_service() {
+        let ledger_path = get_tmp_ledger_path!();
+        fs::create_dir_all(&ledger_path).unwrap();
+        let authorized_voter_keypairs = Arc::new(RwLock::new(vec![]));
+        let start_progress = Arc::new(RwLock::new(ValidatorStartProgress::default()));
+        run(
+            &ledger_path,
+            AdminRpcRequestMetadata {
+                rpc_addr: None,
+                start_time: SystemTime::now(),
+                start_progress: start_progress.clone(),
+                authorized_voter_keypairs: authorized_voter_keypairs.clone(),
+                post_init: Arc::new(RwLock::new(None)),
+            },
+        );
+
+        assert_eq!(
+            request::<ValidatorStartProgress>(&ledger_path, "startProgress", json!([])),
+            Ok(ValidatorStartProgress::Initializing)
+        );
+        *start_progress.write().unwrap() = ValidatorStartProgress::LoadingLedger;
+        assert_eq!(
+            request::<ValidatorStartProgress>(&ledger_path, "startProgress", json!([])),
+            Ok(ValidatorStartProgress::LoadingLedger)
+        );
+        assert_eq!(
+            request::<Option<SocketAddr>>(&ledger_path, "rpcAddr", json!([])),
+            Ok(None)
+        );
+        assert!(request::<AdminRpcContactInfo>(&ledger_path, "contactInfo", json!([])).is_err());
+
+        let keypair = Keypair::new();
+        let keypair_file = ledger_path.join("authorized-voter.json");
+        let keypair_file = keypair_file.to_str().unwrap();
+        write_keypair_file(&keypair, keypair_file).unwrap();
+        assert_eq!(
+            request::<()>(&ledger_path, "addAuthorizedVoter", json!([keypair_file])),
+            Ok(())
+        );
+        assert_eq!(
+            authorized_voter_keypairs.read().unwrap()[0].pubkey(),
+            keypair.pubkey()
+        );
+        // The same voter can't be added twice
+        assert!(request::<()>(&ledger_path, "addAuthorizedVoter", json!([keypair_file])).is_err());
+        assert!(
+            request::<()>(&ledger_path, "addAuthorizedVoter", json!(["missing.json"])).is_err()
+        );
+        assert_eq!(authorized_voter_keypairs.read().unwrap().len(), 1);
+
+        let other_keypair = Keypair::new();
+        let other_keypair_file = ledger_path.join("other-authorized-voter.json");
+        let other_keypair_file = other_keypair_file.to_str().unwrap();
+        write_keypair_file(&other_keypair, other_keypair_file).unwrap();
+        assert_eq!(
+            request::<()>(
+                &ledger_path,
+                "addAuthorizedVoter",
+                json!([other_keypair_file])
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            request::<()>(
+                &ledger_path,
+                "removeAuthorizedVoter",
+                json!([keypair.pubkey().to_string()])
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            authorized_voter_keypairs
+                .read()
+                .unwrap()
+                .iter()
+                .map(|x| x.pubkey())
+                .collect::<Vec<_>>(),
+            vec![other_keypair.pubkey()]
+        );
+        // Only present voters and valid pubkeys can be removed
+        assert!(request::<()>(
+            &ledger_path,
+            "removeAuthorizedVoter",
+            json!([keypair.pubkey().to_string()])
+        )
+        .is_err());
+        assert!(request::<()>(&ledger_path, "removeAuthorizedVoter", json!(["bad"])).is_err());
+
+        assert_eq!(
+            request::<()>(&ledger_path, "removeAllAuthorizedVoters", json!([])),
+            Ok(())
+        );
+        assert!(authorized_voter_keypairs.read().unwrap().is_empty());
+
+        assert!(request::<()>(&ledger_path, "unknownMethod", json!([])).is_err());
+        fs::remove_dir_all(&ledger_path).unwrap();
+    }
+
+    #[test]
+    fn test_admin_rpc_set_identity() {
+        let ledger_path = get_tmp_ledger_path!();
+        fs::create_dir_all(&ledger_path).unwrap();
+        let post_init = Arc::new(RwLock::new(None));
+        run(
+            &ledger_path,
+            AdminRpcRequestMetadata {
+                rpc_addr: None,
+                start_time: SystemTime::now(),
+                start_progress: Arc::new(RwLock::new(ValidatorStartProgress::default())),
+                authorized_voter_keypairs: Arc::new(RwLock::new(vec![])),
+                post_init: post_init.clone(),
+            },
+        );
+
+        let new_identity = Arc::new(Keypair::new());
+        let new_identity_file = ledger_path.join("new-identity.json");
+        let new_identity_file = new_identity_file.to_str().unwrap();
+        write_keypair_file(&new_identity, new_identity_file).unwrap();
+
+        // Not available until the validator has started
+        assert!(request::<()>(&ledger_path, "setIdentity", json!([new_identity_file])).is_err());
+
+        let identity = Arc::new(Keypair::new());
+        let tower_storage = Arc::new(FileTowerStorage::new(ledger_path.clone()));
+        let cluster_info = Arc::new(ClusterInfo::new(
+            ContactInfo::new_localhost(&identity.pubkey(), timestamp()),
+            identity.clone(),
+        ));
+        *post_init.write().unwrap() = Some(AdminRpcRequestMetadataPostInit {
+            cluster_info: cluster_info.clone(),
+            validator_exit: Arc::new(RwLock::new(None)),
+            tower_storage: tower_storage.clone(),
+        });
+
+        // Refused while the new identity has no tower
+        assert!(request::<()>(&ledger_path, "setIdentity", json!([new_identity_file])).is_err());
+        assert_eq!(cluster_info.id(), identity.pubkey());
+
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let tower = Tower::new(&new_identity.pubkey(), &Pubkey::new_unique(), 0, &bank);
+        tower.save(tower_storage.as_ref(), &new_identity).unwrap();
+        assert_eq!(
+            request::<()>(&ledger_path, "setIdentity", json!([new_identity_file])),
+            Ok(())
+        );
+        assert_eq!(cluster_info.id(), new_identity.pubkey());
+        assert_eq!(cluster_info.keypair().pubkey(), new_identity.pubkey());
+        fs::remove_dir_all(&ledger_path).unwrap();
+    }
+}
@@ -1,17 +1,22 @@
 use clap::{
-    crate_description, crate_name, value_t, value_t_or_exit, values_t, values_t_or_exit, App, Arg,
-    ArgMatches,
+    crate_description, crate_name, value_t, value_t_or_exit, values_t, values_t_or_exit, App,
+    AppSettings, Arg, ArgMatches, SubCommand,
 };
 use log::*;
 use rand::{thread_rng, Rng};
+use serde_json::json;
 use solana_clap_utils::{
     input_parsers::{keypair_of, keypairs_of, pubkey_of},
     input_validators::{
-        is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot,
+        is_keypair, is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair,
+        is_slot, is_url, is_valid_percentage,
     },
     keypair::SKIP_SEED_PHRASE_VALIDATION_ARG,
 };
-use solana_client::rpc_client::RpcClient;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_response::{RpcLeaderSchedule, RpcVoteAccountStatus},
+};
 use solana_core::ledger_cleanup_service::{
     DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS,
 };
@@ -21,18 +26,20 @@ use solana_core::{
     gossip_service::GossipService,
     rpc::JsonRpcConfig,
     rpc_pubsub_service::PubSubConfig,
-    validator::{Validator, ValidatorConfig},
+    tower_storage::{FileTowerStorage, NetworkTowerStorage, TowerStorage},
+    validator::{Validator, ValidatorConfig, ValidatorStartProgress},
 };
 use solana_download_utils::{download_genesis_if_missing, download_snapshot};
-use solana_ledger::blockstore_db::BlockstoreRecoveryMode;
+use solana_ledger::blockstore_db::{BlockstoreRecoveryMode, LedgerRetention};
 use solana_perf::recycler::enable_recycler_warming;
 use solana_runtime::{
+    accounts_index::AccountsIndexConfig,
     bank_forks::{CompressionType, SnapshotConfig, SnapshotVersion},
     hardened_unpack::{unpack_genesis_archive, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_utils::get_highest_snapshot_archive_path,
 };
 use solana_sdk::{
-    clock::Slot,
+    clock::{Epoch, Slot},
     commitment_config::CommitmentConfig,
     genesis_config::GenesisConfig,
     hash::Hash,
@@ -41,7 +48,7 @@ use solana_sdk::{
 };
 use std::{
     collections::HashSet,
-    env,
+    env, error,
     fs::{self, File},
     net::{SocketAddr, TcpListener, UdpSocket},
     path::{Path, PathBuf},
@@ -49,12 +56,14 @@ use std::{
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, RwLock,
     },
     thread::{sleep, JoinHandle},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
+mod admin_rpc_service;
+
 fn port_validator(port: String) -> Result<(), String> {
     port.parse::<u16>()
         .map(|_| ())
@@ -557,6 +566,9 @@ fn verify_reachable_ports(
     }
     if ContactInfo::is_valid_address(&node.info.tpu) {
         udp_sockets.extend(node.sockets.tpu.iter());
+        if validator_config.enable_tpu_quic {
+            udp_sockets.extend(node.sockets.tpu_quic.iter());
+        }
     }
     if ContactInfo::is_valid_address(&node.info.tpu_forwards) {
         udp_sockets.extend(node.sockets.tpu_forwards.iter());
@@ -636,6 +648,7 @@ fn rpc_bootstrap(
     validator_config: &mut ValidatorConfig,
     bootstrap_config: RpcBootstrapConfig,
     no_port_check: bool,
+    start_progress: &Arc<RwLock<ValidatorStartProgress>>,
 ) {
     if !no_port_check {
         verify_reachable_ports(&node, cluster_entrypoint, &validator_config);
@@ -648,6 +661,7 @@ fn rpc_bootstrap(
     let mut blacklisted_rpc_nodes = HashSet::new();
     let mut gossip = None;
     loop {
+        *start_progress.write().unwrap() = ValidatorStartProgress::SearchingForRpcService;
         if gossip.is_none() {
             gossip = Some(start_gossip_node(
                 &identity_keypair,
@@ -726,6 +740,11 @@ fn rpc_bootstrap(
                         let (_cluster_info, gossip_exit_flag, gossip_service) =
                             gossip.take().unwrap();
                         gossip_exit_flag.store(true, Ordering::Relaxed);
+                        *start_progress.write().unwrap() =
+                            ValidatorStartProgress::DownloadingSnapshot {
+                                slot: snapshot_hash.0,
+                                rpc_addr: rpc_contact_info.rpc,
+                            };
                         let ret =
                             download_snapshot(&rpc_contact_info.rpc, &ledger_path, snapshot_hash);
                         gossip_service.join().unwrap();
@@ -787,11 +806,12 @@ fn create_validator(
     identity_keypair: &Arc<Keypair>,
     ledger_path: &Path,
     vote_account: &Pubkey,
-    authorized_voter_keypairs: Vec<Arc<Keypair>>,
+    authorized_voter_keypairs: Arc<RwLock<Vec<Arc<Keypair>>>>,
     cluster_entrypoint: Option<ContactInfo>,
     mut validator_config: ValidatorConfig,
     rpc_bootstrap_config: RpcBootstrapConfig,
     no_port_check: bool,
+    start_progress: &Arc<RwLock<ValidatorStartProgress>>,
 ) -> Validator {
     if validator_config.cuda {
         solana_perf::perf_libs::init_cuda();
@@ -805,11 +825,12 @@ fn create_validator(
             &identity_keypair,
             &ledger_path,
             &vote_account,
-            &authorized_voter_keypairs,
+            &authorized_voter_keypairs.read().unwrap(),
             cluster_entrypoint,
             &mut validator_config,
             rpc_bootstrap_config,
             no_port_check,
+            start_progress,
         );
     }
 
@@ -821,9 +842,227 @@ fn create_validator(
         authorized_voter_keypairs,
         cluster_entrypoint.as_ref(),
         &validator_config,
+        start_progress,
     )
 }
 
+// Report the startup progress of the validator using `ledger_path`, then its slot and
+// transaction count once it is running
+fn monitor_validator(ledger_path: &Path) {
+    let mut last_start_progress = None;
+    loop {
+        let start_progress = admin_rpc_service::request::<ValidatorStartProgress>(
+            ledger_path,
+            "startProgress",
+            json!([]),
+        );
+        match start_progress {
+            Err(err) => println!("Unable to connect to validator: {}", err),
+            Ok(ValidatorStartProgress::Running) => break,
+            Ok(start_progress) => {
+                if last_start_progress != Some(start_progress) {
+                    println!("Validator startup: {:?}", start_progress);
+                    last_start_progress = Some(start_progress);
+                }
+            }
+        }
+        sleep(Duration::from_secs(1));
+    }
+    println!("Validator startup: {:?}", ValidatorStartProgress::Running);
+
+    let rpc_addr =
+        admin_rpc_service::request::<Option<SocketAddr>>(ledger_path, "rpcAddr", json!([]))
+            .unwrap_or_else(|err| {
+                eprintln!("Unable to get validator RPC address: {}", err);
+                exit(1);
+            })
+            .unwrap_or_else(|| {
+                println!("RPC service is disabled");
+                exit(0);
+            });
+    let rpc_client = RpcClient::new_socket(rpc_addr);
+    match rpc_client.get_identity() {
+        Ok(identity) => println!("Identity: {}", identity),
+        Err(err) => println!("Unable to get validator identity: {}", err),
+    }
+    loop {
+        let info = rpc_client
+            .get_slot_with_commitment(CommitmentConfig::recent())
+            .and_then(|processed_slot| {
+                let root_slot = rpc_client.get_slot_with_commitment(CommitmentConfig::root())?;
+                let transaction_count =
+                    rpc_client.get_transaction_count_with_commitment(CommitmentConfig::recent())?;
+                Ok((processed_slot, root_slot, transaction_count))
+            });
+        match info {
+            Ok((processed_slot, root_slot, transaction_count)) => println!(
+                "Processed Slot: {} | Root Slot: {} | Transactions: {}",
+                processed_slot, root_slot, transaction_count
+            ),
+            Err(err) => println!("RPC request failed: {}", err),
+        }
+        sleep(Duration::from_secs(2));
+    }
+}
+
+// A validator further than this many slots behind the cluster is not considered caught up
+const MAX_RESTART_WINDOW_SLOT_DISTANCE: Slot = 150;
+
+// Absolute slots of `leader_schedule` (for the epoch starting at `first_slot_in_epoch`) that
+// are assigned to `identity`, in ascending order
+fn leader_slots_in_schedule(
+    leader_schedule: &RpcLeaderSchedule,
+    identity: &Pubkey,
+    first_slot_in_epoch: Slot,
+) -> Vec<Slot> {
+    let mut leader_slots: Vec<Slot> = leader_schedule
+        .get(&identity.to_string())
+        .map(|slot_indices| {
+            slot_indices
+                .iter()
+                .map(|slot_index| first_slot_in_epoch + *slot_index as Slot)
+                .collect()
+        })
+        .unwrap_or_default();
+    leader_slots.sort_unstable();
+    leader_slots
+}
+
+fn delinquent_stake_percentage(vote_accounts: &RpcVoteAccountStatus) -> f64 {
+    let current_stake: u64 = vote_accounts
+        .current
+        .iter()
+        .map(|vote_account| vote_account.activated_stake)
+        .sum();
+    let delinquent_stake: u64 = vote_accounts
+        .delinquent
+        .iter()
+        .map(|vote_account| vote_account.activated_stake)
+        .sum();
+    let total_stake = current_stake + delinquent_stake;
+    if total_stake == 0 {
+        0.
+    } else {
+        delinquent_stake as f64 * 100. / total_stake as f64
+    }
+}
+
+// Block until the validator using `ledger_path` has caught up with the cluster, has no leader
+// slots within the next `min_idle_slots` slots, and the cluster's delinquent stake is no more
+// than `max_delinquent_stake` percent
+fn wait_for_restart_window(
+    ledger_path: &Path,
+    cluster_rpc_url: Option<String>,
+    min_idle_slots: u64,
+    max_delinquent_stake: u8,
+) -> Result<(), Box<dyn error::Error>> {
+    let rpc_addr =
+        admin_rpc_service::request::<Option<SocketAddr>>(ledger_path, "rpcAddr", json!([]))
+            .map_err(|err| format!("Unable to get validator RPC address: {}", err))?
+            .ok_or("RPC service is disabled")?;
+    let rpc_client = RpcClient::new_socket(rpc_addr);
+    let identity = rpc_client.get_identity()?;
+
+    // Like `solana catchup`, compare the validator's slot against another node's view of the
+    // cluster, locating one through gossip if no RPC URL was given
+    let cluster_rpc_client = match cluster_rpc_url {
+        Some(cluster_rpc_url) => RpcClient::new(cluster_rpc_url),
+        None => {
+            let cluster_rpc_addr = rpc_client
+                .get_cluster_nodes()?
+                .into_iter()
+                .filter(|contact_info| contact_info.pubkey != identity.to_string())
+                .find_map(|contact_info| contact_info.rpc)
+                .ok_or("No RPC service found in the cluster, specify one with --url")?;
+            RpcClient::new_socket(cluster_rpc_addr)
+        }
+    };
+    println!("Identity: {}", identity);
+
+    let mut leader_slots: Option<(Epoch, Vec<Slot>)> = None;
+    loop {
+        let epoch_info = rpc_client.get_epoch_info_with_commitment(CommitmentConfig::recent())?;
+        let node_slot = epoch_info.absolute_slot;
+        let first_slot_in_epoch = node_slot - epoch_info.slot_index;
+
+        // The schedule of the next epoch is already fixed, so include it to see past the end of
+        // the current epoch
+        if leader_slots.as_ref().map(|(epoch, _)| *epoch) != Some(epoch_info.epoch) {
+            let mut epoch_leader_slots = vec![];
+            for first_slot_in_epoch in &[
+                first_slot_in_epoch,
+                first_slot_in_epoch + epoch_info.slots_in_epoch,
+            ] {
+                if let Some(leader_schedule) = rpc_client.get_leader_schedule_with_commitment(
+                    Some(*first_slot_in_epoch),
+                    CommitmentConfig::recent(),
+                )? {
+                    epoch_leader_slots.extend(leader_slots_in_schedule(
+                        &leader_schedule,
+                        &identity,
+                        *first_slot_in_epoch,
+                    ));
+                }
+            }
+            leader_slots = Some((epoch_info.epoch, epoch_leader_slots));
+        }
+        let next_leader_slot = leader_slots
+            .as_ref()
+            .and_then(|(_, leader_slots)| leader_slots.iter().find(|slot| **slot >= node_slot))
+            .copied();
+
+        let cluster_slot =
+            cluster_rpc_client.get_slot_with_commitment(CommitmentConfig::recent())?;
+        let slot_distance = cluster_slot.saturating_sub(node_slot);
+        let delinquent_stake = delinquent_stake_percentage(
+            &rpc_client.get_vote_accounts_with_commitment(CommitmentConfig::recent())?,
+        );
+
+        let wait_reason = if slot_distance > MAX_RESTART_WINDOW_SLOT_DISTANCE {
+            Some(format!(
+                "{} slots behind (us:{} them:{})",
+                slot_distance, node_slot, cluster_slot
+            ))
+        } else if delinquent_stake > f64::from(max_delinquent_stake) {
+            Some(format!(
+                "{:.2}% of the stake is delinquent, above {}%",
+                delinquent_stake, max_delinquent_stake
+            ))
+        } else {
+            next_leader_slot
+                .filter(|next_leader_slot| next_leader_slot - node_slot < min_idle_slots)
+                .map(|next_leader_slot| {
+                    format!(
+                        "Leader slot {} is {} slots away",
+                        next_leader_slot,
+                        next_leader_slot - node_slot
+                    )
+                })
+        };
+
+        match wait_reason {
+            Some(wait_reason) => println!("Slot {}: {}", node_slot, wait_reason),
+            None => {
+                match next_leader_slot {
+                    Some(next_leader_slot) => println!(
+                        "Slot {}: next leader slot {} is {} slots away",
+                        node_slot,
+                        next_leader_slot,
+                        next_leader_slot - node_slot
+                    ),
+                    None => println!("Slot {}: no upcoming leader slots", node_slot),
+                }
+                println!(
+                    "Restart window open, {:.2}% of the stake is delinquent",
+                    delinquent_stake
+                );
+                return Ok(());
+            }
+        }
+        sleep(Duration::from_secs(2));
+    }
+}
+
 pub fn main() {
     let default_dynamic_port_range =
         &format!("{}-{}", VALIDATOR_PORT_RANGE.0, VALIDATOR_PORT_RANGE.1);
@@ -838,6 +1077,7 @@ pub fn main() {
 
     let matches = App::new(crate_name!()).about(crate_description!())
         .version(solana_version::version!())
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
                 .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long)
@@ -990,6 +1230,15 @@ pub fn main() {
                        including the 'getConfirmedBlock' API.  \
                        This will cause an increase in disk usage and IOPS"),
         )
+        .arg(
+            Arg::with_name("enable_rpc_account_history")
+                .long("enable-rpc-account-history")
+                .takes_value(false)
+                .help("Archive the accounts modified in each root and serve them over JSON RPC \
+                       through the 'slot' parameter of 'getAccountInfo' and \
+                       'getMultipleAccounts'.  Account history starts at the first root \
+                       archived.  This will cause a large increase in disk usage"),
+        )
         .arg(
             Arg::with_name("enable_rpc_bigtable_ledger_storage")
                 .long("enable-rpc-bigtable-ledger-storage")
@@ -1005,6 +1254,15 @@ pub fn main() {
                 .takes_value(false)
                 .help("Upload new confirmed blocks into a BigTable instance"),
         )
+        .arg(
+            Arg::with_name("local_ledger_storage")
+                .long("local-ledger-storage")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Keep the ledger storage of --enable-rpc-bigtable-ledger-storage and \
+                       --enable-bigtable-ledger-upload in flat files under DIR \
+                       instead of a BigTable instance"),
+        )
         .arg(
             Arg::with_name("health_check_slot_distance")
                 .long("health-check-slot-distance")
@@ -1041,6 +1299,34 @@ pub fn main() {
                 .takes_value(true)
                 .help("Comma separated persistent accounts location"),
         )
+        .arg(
+            Arg::with_name("enable_accounts_disk_index")
+                .long("enable-accounts-disk-index")
+                .takes_value(false)
+                .help("Page the slot lists of the accounts index out to memory-mapped files \
+                       to bound the memory the index uses"),
+        )
+        .arg(
+            Arg::with_name("accounts_index_path")
+                .long("accounts-index-path")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .requires("enable_accounts_disk_index")
+                .help("Persistent accounts-index location. \
+                       May be specified multiple times. \
+                       [default: [ledger]/accounts_index]"),
+        )
+        .arg(
+            Arg::with_name("accounts_index_memory_limit_mb")
+                .long("accounts-index-memory-limit-mb")
+                .value_name("MEGABYTES")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .requires("enable_accounts_disk_index")
+                .help("How much memory the accounts index may use before slot lists are \
+                       paged out to disk [default: 1024]"),
+        )
         .arg(
             Arg::with_name("gossip_port")
                 .long("gossip-port")
@@ -1114,6 +1400,20 @@ pub fn main() {
                 /* .default_value() intentionally not used here! */
                 .help("Keep this amount of shreds in root slots."),
         )
+        .arg(
+            Arg::with_name("ledger_retention")
+                .long("ledger-retention")
+                .value_name("METHOD")
+                .takes_value(true)
+                .possible_values(&["purge", "compaction-filter"])
+                .requires("limit_ledger_size")
+                .help(
+                    "How --limit-ledger-size drops old slots. \
+                     purge: delete the slots, then compact them once a day. \
+                     compaction-filter: let regular compactions drop them, \
+                     which avoids bursts of writes [default: purge]",
+                ),
+        )
         .arg(
             Arg::with_name("skip_poh_verify")
                 .long("skip-poh-verify")
@@ -1132,6 +1432,31 @@ pub fn main() {
                 .takes_value(false)
                 .help("Refuse to start if saved tower state is not found"),
         )
+        .arg(
+            Arg::with_name("tower_storage")
+                .long("tower-storage")
+                .possible_values(&["file", "network"])
+                .default_value("file")
+                .value_name("STORAGE")
+                .takes_value(true)
+                .help("Where to store the tower"),
+        )
+        .arg(
+            Arg::with_name("tower")
+                .long("tower")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Use DIR as file tower storage location [default: --ledger value]"),
+        )
+        .arg(
+            Arg::with_name("tower_storage_server")
+                .long("tower-storage-server")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .required_if("tower_storage", "network")
+                .validator(solana_net_utils::is_host_port)
+                .help("Tower storage server used by --tower-storage network"),
+        )
         .arg(
             Arg::with_name("expected_genesis_hash")
                 .long("expected-genesis-hash")
@@ -1202,6 +1527,15 @@ pub fn main() {
                 .takes_value(true)
                 .help("Log when transactions are processed which reference a given key."),
         )
+        .arg(
+            Arg::with_name("accountsdb_plugin_config")
+                .long("accountsdb-plugin-config")
+                .value_name("FILE")
+                .takes_value(true)
+                .multiple(true)
+                .help("Load an AccountsDb plugin from the given Json config file. \
+                       The plugin is streamed every account update and slot status change"),
+        )
         .arg(
             Arg::with_name("no_untrusted_rpc")
                 .long("no-untrusted-rpc")
@@ -1235,6 +1569,20 @@ pub fn main() {
                 .takes_value(false)
                 .help("Disable manual compaction of the ledger database. May increase storage requirements.")
         )
+        .arg(
+            Arg::with_name("enable_tpu_quic")
+                .long("enable-tpu-quic")
+                .takes_value(false)
+                .help("Also receive transactions over QUIC at the TPU port plus 6, and send \
+                       RPC transactions to leaders over QUIC"),
+        )
+        .arg(
+            Arg::with_name("enable_banking_priority_scheduler")
+                .long("enable-banking-priority-scheduler")
+                .takes_value(false)
+                .help("Process and forward buffered transactions in order of the fee they pay \
+                       per requested compute unit"),
+        )
         .arg(
             Arg::with_name("bind_address")
                 .long("bind-address")
@@ -1342,13 +1690,217 @@ pub fn main() {
                     "Mode to recovery the ledger db write ahead log."
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("exit")
+                .about("Send an exit request to the validator")
+        )
+        .subcommand(
+            SubCommand::with_name("authorized-voter")
+                .about("Adjust the validator authorized voters")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .setting(AppSettings::InferSubcommands)
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add an authorized voter")
+                        .arg(
+                            Arg::with_name("authorized_voter_keypair")
+                                .index(1)
+                                .value_name("KEYPAIR")
+                                .required(true)
+                                .takes_value(true)
+                                .validator(is_keypair)
+                                .help("Keypair of the authorized voter to add"),
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Remove an authorized voter")
+                        .arg(
+                            Arg::with_name("authorized_voter_pubkey")
+                                .index(1)
+                                .value_name("PUBKEY")
+                                .required(true)
+                                .takes_value(true)
+                                .validator(is_pubkey_or_keypair)
+                                .help("Authorized voter to remove"),
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("remove-all")
+                        .about("Remove all authorized voters")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("set-log-filter")
+                .about("Adjust the validator log filter")
+                .arg(
+                    Arg::with_name("filter")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("New filter using the same format as the RUST_LOG environment variable")
+                )
+                .after_help("Note: the new filter only applies to the currently running validator instance")
+        )
+        .subcommand(
+            SubCommand::with_name("set-identity")
+                .about("Set the validator identity")
+                .arg(
+                    Arg::with_name("identity")
+                        .index(1)
+                        .value_name("KEYPAIR")
+                        .required(true)
+                        .takes_value(true)
+                        .validator(is_keypair)
+                        .help("Validator identity keypair")
+                )
+                .after_help("Note: the tower of the new identity must already exist in the \
+                             ledger directory, otherwise the request is refused")
+        )
+        .subcommand(
+            SubCommand::with_name("contact-info")
+                .about("Display the validator's contact info")
+        )
+        .subcommand(
+            SubCommand::with_name("monitor")
+                .about("Monitor the validator")
+        )
+        .subcommand(
+            SubCommand::with_name("wait-for-restart-window")
+                .about("Block until it is safe to restart the validator")
+                .arg(
+                    Arg::with_name("min_idle_slots")
+                        .long("min-idle-slots")
+                        .value_name("SLOTS")
+                        .takes_value(true)
+                        .validator(is_parsable::<u64>)
+                        .default_value("1500")
+                        .help("Minimum number of slots without a leader slot of this validator")
+                )
+                .arg(
+                    Arg::with_name("max_delinquent_stake")
+                        .long("max-delinquent-stake")
+                        .value_name("PERCENT")
+                        .takes_value(true)
+                        .validator(is_valid_percentage)
+                        .default_value("5")
+                        .help("Maximum percentage of the cluster stake that may be delinquent")
+                )
+                .arg(
+                    Arg::with_name("json_rpc_url")
+                        .short("u")
+                        .long("url")
+                        .value_name("URL")
+                        .takes_value(true)
+                        .validator(is_url)
+                        .help("RPC URL of another node used to check that the validator has \
+                               caught up [default: an RPC node found through gossip]")
+                )
+        )
         .get_matches();
 
+    if let (subcommand, Some(subcommand_matches)) = matches.subcommand() {
+        let ledger_path = matches
+            .value_of("ledger_path")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                eprintln!("--ledger is required to reach the running validator");
+                exit(1);
+            });
+        let admin_rpc_request = |method: &str, params| {
+            admin_rpc_service::request(&ledger_path, method, params).unwrap_or_else(|err| {
+                eprintln!("{} request failed: {}", method, err);
+                exit(1);
+            })
+        };
+        match subcommand {
+            "exit" => {
+                admin_rpc_request("exit", json!([]));
+                println!("Exit request sent");
+            }
+            "authorized-voter" => match subcommand_matches.subcommand() {
+                ("add", Some(subcommand_matches)) => {
+                    let authorized_voter_keypair =
+                        value_t_or_exit!(subcommand_matches, "authorized_voter_keypair", String);
+                    // The validator resolves the path relative to its own working directory
+                    let authorized_voter_keypair = fs::canonicalize(&authorized_voter_keypair)
+                        .unwrap_or_else(|err| {
+                            eprintln!("Unable to access {}: {}", authorized_voter_keypair, err);
+                            exit(1);
+                        });
+                    admin_rpc_request(
+                        "addAuthorizedVoter",
+                        json!([authorized_voter_keypair.display().to_string()]),
+                    );
+                    println!("New authorized voter added");
+                }
+                ("remove", Some(subcommand_matches)) => {
+                    let authorized_voter_pubkey =
+                        pubkey_of(subcommand_matches, "authorized_voter_pubkey").unwrap();
+                    admin_rpc_request(
+                        "removeAuthorizedVoter",
+                        json!([authorized_voter_pubkey.to_string()]),
+                    );
+                    println!("Authorized voter {} removed", authorized_voter_pubkey);
+                }
+                ("remove-all", _) => {
+                    admin_rpc_request("removeAllAuthorizedVoters", json!([]));
+                    println!("All authorized voters removed");
+                }
+                _ => unreachable!(),
+            },
+            "set-log-filter" => {
+                let filter = value_t_or_exit!(subcommand_matches, "filter", String);
+                admin_rpc_request("setLogFilter", json!([filter]));
+            }
+            "set-identity" => {
+                let identity_keypair = value_t_or_exit!(subcommand_matches, "identity", String);
+                // The validator resolves the path relative to its own working directory
+                let identity_keypair = fs::canonicalize(&identity_keypair).unwrap_or_else(|err| {
+                    eprintln!("Unable to access {}: {}", identity_keypair, err);
+                    exit(1);
+                });
+                admin_rpc_request(
+                    "setIdentity",
+                    json!([identity_keypair.display().to_string()]),
+                );
+                println!("Validator identity switched");
+            }
+            "contact-info" => {
+                let contact_info: admin_rpc_service::AdminRpcContactInfo =
+                    admin_rpc_request("contactInfo", json!([]));
+                print!("{}", contact_info);
+            }
+            "monitor" => monitor_validator(&ledger_path),
+            "wait-for-restart-window" => {
+                let min_idle_slots = value_t_or_exit!(subcommand_matches, "min_idle_slots", u64);
+                let max_delinquent_stake =
+                    value_t_or_exit!(subcommand_matches, "max_delinquent_stake", u8);
+                let cluster_rpc_url = subcommand_matches
+                    .value_of("json_rpc_url")
+                    .map(ToString::to_string);
+                wait_for_restart_window(
+                    &ledger_path,
+                    cluster_rpc_url,
+                    min_idle_slots,
+                    max_delinquent_stake,
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    exit(1);
+                });
+            }
+            _ => unreachable!(),
+        }
+        return;
+    }
+
     let identity_keypair = Arc::new(keypair_of(&matches, "identity").unwrap_or_else(Keypair::new));
 
     let authorized_voter_keypairs = keypairs_of(&matches, "authorized_voter_keypairs")
         .map(|keypairs| keypairs.into_iter().map(Arc::new).collect())
         .unwrap_or_else(|| vec![identity_keypair.clone()]);
+    let authorized_voter_keypairs = Arc::new(RwLock::new(authorized_voter_keypairs));
 
     let ledger_path = PathBuf::from(matches.value_of("ledger_path").unwrap());
     let init_complete_file = matches.value_of("init_complete_file");
@@ -1417,9 +1969,28 @@ pub fn main() {
         bind_address
     };
 
+    let tower_storage: Arc<dyn TowerStorage> =
+        match value_t_or_exit!(matches, "tower_storage", String).as_str() {
+            "file" => {
+                let tower_path = value_t!(matches, "tower", PathBuf)
+                    .ok()
+                    .unwrap_or_else(|| ledger_path.clone());
+                Arc::new(FileTowerStorage::new(tower_path))
+            }
+            "network" => {
+                let server_addr = solana_net_utils::parse_host_port(
+                    matches.value_of("tower_storage_server").unwrap(),
+                )
+                .expect("invalid tower_storage_server");
+                Arc::new(NetworkTowerStorage::new(server_addr))
+            }
+            tower_storage => panic!("Tower storage not recognized: {}", tower_storage),
+        };
+
     let restricted_repair_only_mode = matches.is_present("restricted_repair_only_mode");
     let mut validator_config = ValidatorConfig {
         require_tower: matches.is_present("require_tower"),
+        tower_storage: Some(tower_storage.clone()),
         dev_halt_at_slot: value_t!(matches, "dev_halt_at_slot", Slot).ok(),
         cuda: matches.is_present("cuda"),
         expected_genesis_hash: matches
@@ -1434,9 +2005,11 @@ pub fn main() {
             enable_validator_exit: matches.is_present("enable_rpc_exit"),
             enable_set_log_filter: matches.is_present("enable_rpc_set_log_filter"),
             enable_rpc_transaction_history: matches.is_present("enable_rpc_transaction_history"),
+            enable_rpc_account_history: matches.is_present("enable_rpc_account_history"),
             enable_bigtable_ledger_storage: matches
                 .is_present("enable_rpc_bigtable_ledger_storage"),
             enable_bigtable_ledger_upload: matches.is_present("enable_bigtable_ledger_upload"),
+            local_ledger_storage_path: matches.value_of("local_ledger_storage").map(PathBuf::from),
             identity_pubkey: identity_keypair.pubkey(),
             faucet_addr: matches.value_of("rpc_faucet_addr").map(|address| {
                 solana_net_utils::parse_host_port(address).expect("failed to parse faucet address")
@@ -1446,6 +2019,7 @@ pub fn main() {
                 "health_check_slot_distance",
                 u64
             ),
+            send_transactions_over_quic: matches.is_present("enable_tpu_quic"),
         },
         rpc_addrs: value_t!(matches, "rpc_port", u16).ok().map(|rpc_port| {
             (
@@ -1480,6 +2054,10 @@ pub fn main() {
         wal_recovery_mode,
         poh_verify: !matches.is_present("skip_poh_verify"),
         debug_keys,
+        accountsdb_plugin_config_files: values_t!(matches, "accountsdb_plugin_config", PathBuf)
+            .ok(),
+        enable_tpu_quic: matches.is_present("enable_tpu_quic"),
+        banking_priority_scheduler: matches.is_present("enable_banking_priority_scheduler"),
         ..ValidatorConfig::default()
     };
 
@@ -1518,6 +2096,15 @@ pub fn main() {
         })
         .collect();
 
+    if matches.is_present("enable_accounts_disk_index") {
+        let accounts_index_paths = values_t!(matches, "accounts_index_path", PathBuf)
+            .unwrap_or_else(|_| vec![ledger_path.join("accounts_index")]);
+        validator_config.accounts_index_config = Some(AccountsIndexConfig {
+            drives: Some(accounts_index_paths),
+            index_limit_mb: value_t!(matches, "accounts_index_memory_limit_mb", usize).ok(),
+        });
+    }
+
     let snapshot_interval_slots = value_t_or_exit!(matches, "snapshot_interval_slots", u64);
     let snapshot_path = ledger_path.join("snapshot");
     fs::create_dir_all(&snapshot_path).unwrap_or_else(|err| {
@@ -1590,6 +2177,10 @@ pub fn main() {
             exit(1);
         }
         validator_config.max_ledger_shreds = Some(limit_ledger_size);
+        validator_config.ledger_retention = match matches.value_of("ledger_retention") {
+            Some("compaction-filter") => LedgerRetention::CompactionFilter,
+            _ => LedgerRetention::Purge,
+        };
     }
 
     if matches.is_present("halt_on_trusted_validators_accounts_hash_mismatch") {
@@ -1629,6 +2220,19 @@ pub fn main() {
     };
     let _logger_thread = start_logger(logfile);
 
+    let start_progress = Arc::new(RwLock::new(ValidatorStartProgress::default()));
+    let admin_service_post_init = Arc::new(RwLock::new(None));
+    admin_rpc_service::run(
+        &ledger_path,
+        admin_rpc_service::AdminRpcRequestMetadata {
+            rpc_addr: validator_config.rpc_addrs.map(|(rpc_addr, _, _)| rpc_addr),
+            start_time: SystemTime::now(),
+            start_progress: start_progress.clone(),
+            authorized_voter_keypairs: authorized_voter_keypairs.clone(),
+            post_init: admin_service_post_init.clone(),
+        },
+    );
+
     // Default to RUST_BACKTRACE=1 for more informative validator logs
     if env::var_os("RUST_BACKTRACE").is_none() {
         env::set_var("RUST_BACKTRACE", "1")
@@ -1717,7 +2321,14 @@ pub fn main() {
         validator_config,
         rpc_bootstrap_config,
         no_port_check,
+        &start_progress,
     );
+    *admin_service_post_init.write().unwrap() =
+        Some(admin_rpc_service::AdminRpcRequestMetadataPostInit {
+            cluster_info: validator.cluster_info.clone(),
+            validator_exit: validator.validator_exit.clone(),
+            tower_storage,
+        });
 
     if let Some(filename) = init_complete_file {
         File::create(filename).unwrap_or_else(|_| {
@@ -1733,6 +2344,7 @@ pub fn main() {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use solana_client::rpc_response::RpcVoteAccountInfo;
 
     #[test]
     fn test_interval_check() {
@@ -1742,4 +2354,43 @@ pub mod tests {
         assert!(!is_snapshot_config_invalid(500, 100));
         assert!(!is_snapshot_config_invalid(5, 5));
     }
+
+    #[test]
+    fn test_leader_slots_in_schedule() {
+        let identity = Pubkey::new_unique();
+        let mut leader_schedule = RpcLeaderSchedule::new();
+        leader_schedule.insert(identity.to_string(), vec![12, 4, 5]);
+        leader_schedule.insert(Pubkey::new_unique().to_string(), vec![0, 1]);
+
+        assert_eq!(
+            leader_slots_in_schedule(&leader_schedule, &identity, 100),
+            vec![104, 105, 112]
+        );
+        assert!(leader_slots_in_schedule(&leader_schedule, &Pubkey::new_unique(), 100).is_empty());
+    }
+
+    #[test]
+    fn test_delinquent_stake_percentage() {
+        let vote_account = |activated_stake| RpcVoteAccountInfo {
+            vote_pubkey: Pubkey::new_unique().to_string(),
+            node_pubkey: Pubkey::new_unique().to_string(),
+            activated_stake,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits: vec![],
+            last_vote: 0,
+            root_slot: 0,
+        };
+        let mut vote_accounts = RpcVoteAccountStatus {
+            current: vec![],
+            delinquent: vec![],
+        };
+        assert!((delinquent_stake_percentage(&vote_accounts) - 0.).abs() < f64::EPSILON);
+
+        vote_accounts.current = vec![vote_account(60), vote_account(15)];
+        assert!((delinquent_stake_percentage(&vote_accounts) - 0.).abs() < f64::EPSILON);
+
+        vote_accounts.delinquent = vec![vote_account(25)];
+        assert!((delinquent_stake_percentage(&vote_accounts) - 25.).abs() < f64::EPSILON);
+    }
 }